@@ -0,0 +1,44 @@
+Feature: data
+
+Background:
+  Given a dummy api-tokens file
+  And I have a dummy cli.toml file
+  And the Delivery API server:
+    """
+      get('/api/v0/e/dummy/orgs') do
+        status 200
+        { "orgs" => ["zelda"] }
+      end
+      get('/api/v0/e/dummy/orgs/zelda/projects/hyrule/pipelines/master/data/version') do
+        status 200
+        { "version" => "1.2.3" }
+      end
+      get('/api/v0/e/dummy/orgs/zelda/projects/hyrule/changes/a-change/data/version') do
+        status 404
+        {}
+      end
+      put('/api/v0/e/dummy/orgs/zelda/projects/hyrule/changes/a-change/data/version') do
+        status 204
+      end
+    """
+
+Scenario: getting data scoped to a pipeline
+  When I successfully run `delivery data get version -p hyrule`
+  Then the output should contain:
+    """
+      "version": "1.2.3"
+    """
+
+Scenario: getting data that does not exist
+  When I run `delivery data get version -p hyrule --change a-change`
+  Then the exit status should be 1
+  And the output should contain "No data found for 'version'"
+
+Scenario: setting data scoped to a change
+  When I successfully run `delivery data set version '{"version":"1.2.4"}' -p hyrule --change a-change`
+  Then the output should contain "Data 'version' saved"
+
+Scenario: setting data that is not JSON
+  When I run `delivery data set version not-json -p hyrule --change a-change`
+  Then the exit status should be 1
+  And the output should contain "Data must be valid JSON"
@@ -3,7 +3,8 @@ Feature: local
   The `delivery local` command runs Workflow phases and stages on your
   local workstation, it requires your project to have the `project.toml`
   file inside the `.delivery/` directory where a user can configure the
-  command(s) to run.
+  command(s) to run. Cookbook, Rust, Node and Python projects get default
+  lint, syntax and unit commands for the phases they do not configure.
 
 Background:
   When I clean up the ruby env so I can run other ruby bins like ChefDK
@@ -81,19 +82,28 @@ Scenario: When the project has a partial `.delivery/project.toml`
 
 Scenario: When the project has a partial `.delivery/project.toml`
 	  it should fail to execute the NOT configured phase
+	  that has no default for the project type
   When I have a partially config project.toml file
-  And I invoke a pseudo tty with command "delivery local unit"
+  And I invoke a pseudo tty with command "delivery local smoke"
   And I want to debug the pseudo tty command
   And I cd inside my ptty to "local"
   And I run my ptty command
   Then the ptty exit status should be 1
   And the ptty output should contain "Unable to execute an empty phase"
 
-Scenario: When `.delivery/project.toml` file is missing fail and
-          show a helpful message about how to recover, additionally
-          run the command to prove it will actually fix it
+Scenario: When `.delivery/project.toml` file is missing in a cookbook
+          run the default cookbook phases
   When I successfully run `rm -rf .delivery/project.toml`
   And I run `delivery local lint`
+  Then the exit status should be 0
+  And the output should match /Running.*Lint.*Phase/
+  And the output should contain "no offenses detected"
+
+Scenario: When `.delivery/project.toml` file is missing and the project
+          type is unknown fail and show a helpful message about how to
+          recover, additionally run the command to prove it will actually fix it
+  When I successfully run `rm -rf .delivery/project.toml metadata.rb`
+  And I run `delivery local lint`
   Then the exit status should be 1
   And the output should contain:
     """
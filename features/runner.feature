@@ -0,0 +1,44 @@
+Feature: runner
+
+Background:
+  Given a dummy api-tokens file
+  And I have a dummy cli.toml file
+  And the Delivery API server:
+    """
+      get('/api/v0/e/dummy/orgs') do
+        status 200
+        { "orgs" => ["zelda"] }
+      end
+      get('/api/v0/e/dummy/runners') do
+        status 200
+        [{ "hostname" => "runner-1.example.com", "os" => "linux",
+           "platform" => "ubuntu", "platform_version" => "16.04" }]
+      end
+      post('/api/v0/e/dummy/runners') do
+        status 201
+        { "hostname" => "runner-2.example.com",
+          "openssh_public_key" => "ssh-rsa AAAAB3Nza delivery" }
+      end
+      delete('/api/v0/e/dummy/runners/runner-1.example.com') do
+        status 204
+      end
+    """
+
+Scenario: listing the runners
+  When I successfully run `delivery runner list`
+  Then the output should contain "runner-1.example.com"
+  And the output should contain "linux ubuntu 16.04"
+
+Scenario: registering a runner
+  When I successfully run `delivery runner register runner-2.example.com --os linux`
+  Then the output should contain "Runner runner-2.example.com registered"
+  And the output should contain "echo 'ssh-rsa AAAAB3Nza delivery' | sudo tee -a ~job_runner/.ssh/authorized_keys"
+
+Scenario: deleting a runner
+  When I successfully run `delivery runner delete runner-1.example.com`
+  Then the output should contain "Runner runner-1.example.com deleted"
+
+Scenario: deleting a runner without a hostname
+  When I run `delivery runner delete`
+  Then the exit status should be 1
+  And the output should contain "A hostname is required to delete a runner"
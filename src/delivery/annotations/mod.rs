@@ -0,0 +1,185 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Markers a change leaves behind
+//!
+//! `delivery review` looks for TODO and FIXME markers in the lines a
+//! change adds and lists them as a checklist at the end of the change
+//! description, so reviewers see the temporary hacks the change brings
+//! in. The markers come from `todo_markers` in the cli.toml; a project
+//! turns the checklist off with `todo_checklist = false`.
+
+use regex::{self, Regex};
+use config::Config;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+
+/// The markers looked for without a `todo_markers`
+pub const DEFAULT_MARKERS: [&'static str; 2] = ["TODO", "FIXME"];
+
+/// What starts the section of the change description listing the markers
+pub const CHECKLIST_LABEL: &'static str = "Added TODOs:";
+
+const CHECKLIST_ITEM: &'static str = "- [ ] ";
+
+/// A marker on a line the change adds
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub file: String,
+    pub line: usize,
+    /// The line from the marker on, i.e. `TODO: drop once on 2.x`
+    pub text: String,
+}
+
+/// The markers of `config`, or the default ones
+pub fn markers(config: &Config) -> Vec<String> {
+    match config.todo_markers {
+        Some(ref markers) => markers.clone(),
+        None => DEFAULT_MARKERS.iter().map(|m| m.to_string()).collect()
+    }
+}
+
+/// The `markers` on the lines added by `diff`, the output of a
+/// `git diff --unified=0`
+pub fn find(markers: &[String], diff: &str) -> DeliveryResult<Vec<Annotation>> {
+    if markers.is_empty() {
+        return Ok(Vec::new())
+    }
+    let alternatives: Vec<String> = markers.iter().map(|m| regex::quote(m)).collect();
+    let pattern = format!(r"\b(?:{})\b", alternatives.join("|"));
+    let re = try!(Regex::new(&pattern).map_err(|e| {
+        let msg = format!("Invalid todo_markers {:?}: {}", markers, e);
+        DeliveryError::throw(Kind::ConfigValidation, Some(msg))
+    }));
+    let hunk_re = Regex::new(r"^@@ -\d+(?:,\d+)? \+(\d+)(?:,\d+)? @@").unwrap();
+    let mut found = Vec::new();
+    let mut file: Option<String> = None;
+    let mut line = 0;
+    for l in diff.lines() {
+        if l.starts_with("+++ ") {
+            file = match l[4..].trim() {
+                "/dev/null" => None,
+                path => Some(path.trim_left_matches("b/").to_string())
+            };
+        } else if let Some(caps) = hunk_re.captures(l) {
+            line = caps.at(1).and_then(|n| n.parse().ok()).unwrap_or(0);
+        } else if l.starts_with('+') {
+            if let (Some(ref f), Some((start, _))) = (file.as_ref(), re.find(&l[1..])) {
+                found.push(Annotation {
+                    file: f.to_string(),
+                    line: line,
+                    text: l[1 + start..].trim().to_string(),
+                });
+            }
+            line += 1;
+        } else if l.starts_with(' ') {
+            line += 1;
+        }
+    }
+    Ok(found)
+}
+
+/// `description` with a checklist of `annotations` in place of the one
+/// it had, if any
+pub fn describe(description: &str, annotations: &[Annotation]) -> String {
+    let mut kept: Vec<&str> = Vec::new();
+    let mut in_checklist = false;
+    for l in description.lines() {
+        if l.trim_left().starts_with(CHECKLIST_LABEL) {
+            in_checklist = true;
+            continue
+        }
+        if in_checklist && l.trim_left().starts_with(CHECKLIST_ITEM) {
+            continue
+        }
+        // The blank line that closed the checklist goes with it
+        let closes_checklist = in_checklist && l.trim().is_empty();
+        in_checklist = false;
+        if closes_checklist {
+            continue
+        }
+        kept.push(l);
+    }
+    let kept = kept.join("\n");
+    let kept = kept.trim_right();
+    if annotations.is_empty() {
+        return kept.to_string()
+    }
+    let mut checklist = CHECKLIST_LABEL.to_string();
+    for a in annotations.iter() {
+        checklist.push_str(&format!("\n{}{}:{} {}", CHECKLIST_ITEM, a.file, a.line, a.text));
+    }
+    if kept.is_empty() {
+        checklist
+    } else {
+        format!("{}\n\n{}", kept, checklist)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::Config;
+
+    const DIFF: &'static str = "\
+diff --git a/src/login.rs b/src/login.rs
+index 3b18e51..a9c2f4e 100644
+--- a/src/login.rs
++++ b/src/login.rs
+@@ -10,0 +11,2 @@ fn login() {
++    // TODO: drop the retry once the server is fixed
++    retry(3);
+@@ -40 +42 @@ fn logout() {
+-    // TODO: old one, removed
++    session.clear(); // FIXME leaks the cookie
+diff --git a/README.md b/README.md
+--- a/README.md
++++ b/README.md
+@@ -1,0 +2 @@
++Nothing TODOne here, no TODOS either
+";
+
+    #[test]
+    fn test_find() {
+        let found = find(&markers(&Config::default()), DIFF).unwrap();
+        assert_eq!(vec![
+            Annotation { file: "src/login.rs".to_string(), line: 11,
+                         text: "TODO: drop the retry once the server is fixed".to_string() },
+            Annotation { file: "src/login.rs".to_string(), line: 42,
+                         text: "FIXME leaks the cookie".to_string() },
+        ], found);
+        let only_fixme = vec!["FIXME".to_string()];
+        assert_eq!(1, find(&only_fixme, DIFF).unwrap().len());
+        assert!(find(&[], DIFF).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_describe() {
+        let annotations = vec![
+            Annotation { file: "src/login.rs".to_string(), line: 11, text: "TODO: retry".to_string() },
+        ];
+        let checklist = "Added TODOs:\n- [ ] src/login.rs:11 TODO: retry";
+        assert_eq!(checklist, describe("", &annotations));
+        assert_eq!(format!("Fixes the login.\n\n{}", checklist),
+                   describe("Fixes the login.\n", &annotations));
+        let old = "Fixes the login.\n\nAdded TODOs:\n- [ ] src/a.rs:1 TODO\n\nTickets: PROJ-12";
+        assert_eq!(format!("Fixes the login.\n\nTickets: PROJ-12\n\n{}", checklist),
+                   describe(old, &annotations));
+        assert_eq!("Fixes the login.",
+                   describe("Fixes the login.\n\nAdded TODOs:\n- [ ] src/a.rs:1 TODO", &[]));
+    }
+}
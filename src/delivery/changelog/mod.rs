@@ -0,0 +1,201 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Release changelogs
+//!
+//! Every change the server merges leaves a merge commit on the pipeline
+//! branch whose message starts with `Merged change <id>` and names the
+//! pipeline it went into. `delivery changelog` collects those commits
+//! between two git refs, fetches the title and description of each
+//! change from the server and renders them as Markdown, grouped by the
+//! type tag the title starts with (`feat:`, `[fix]`, ...).
+
+use regex::Regex;
+use http::change::Description;
+
+/// A change merged into a pipeline, as recorded by its merge commit
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedChange {
+    pub id: String,
+    pub pipeline: String,
+    pub sha: String,
+}
+
+/// The arguments of the `git log` listing the merge commits between
+/// `from` and `to`, one `<sha>\n<message>` record per commit, NUL
+/// separated.
+pub fn git_log_args(from: &str, to: &str) -> Vec<String> {
+    vec!["log".to_string(), "--merges".to_string(), "--format=%H%n%B%x00".to_string(),
+         format!("{}..{}", from, to)]
+}
+
+/// The changes merged into `pipeline` according to the output of the
+/// `git log` built by `git_log_args`, newest first
+pub fn parse_merges(log: &str, pipeline: &str) -> Vec<MergedChange> {
+    let merged = Regex::new(r"Merged change ([a-f0-9-]{36})").unwrap();
+    let into = Regex::new(r"From review branch \S+ into (\S+)").unwrap();
+    log.split('\0').filter_map(|record| {
+        let record = record.trim();
+        let mut lines = record.splitn(2, '\n');
+        let sha = lines.next().unwrap_or("").trim();
+        let message = lines.next().unwrap_or("");
+        let id = match merged.captures(message).and_then(|c| c.at(1)) {
+            Some(id) => id,
+            None => return None
+        };
+        let target = into.captures(message).and_then(|c| c.at(1)).unwrap_or(pipeline);
+        if target != pipeline {
+            return None
+        }
+        Some(MergedChange { id: id.to_string(), pipeline: target.to_string(),
+                            sha: sha.to_string() })
+    }).collect()
+}
+
+/// The kind of a change, from the tag its title starts with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeType {
+    Breaking,
+    Feature,
+    Fix,
+    Performance,
+    Documentation,
+    Other,
+}
+
+impl ChangeType {
+    fn from_tag(tag: &str) -> Option<ChangeType> {
+        match tag.to_lowercase().as_ref() {
+            "breaking" | "break" => Some(ChangeType::Breaking),
+            "feat" | "feature" | "enhancement" => Some(ChangeType::Feature),
+            "fix" | "bug" | "bugfix" => Some(ChangeType::Fix),
+            "perf" | "performance" => Some(ChangeType::Performance),
+            "doc" | "docs" => Some(ChangeType::Documentation),
+            "chore" | "refactor" | "test" | "tests" | "ci" => Some(ChangeType::Other),
+            _ => None
+        }
+    }
+
+    /// The heading of the section grouping the changes of this type
+    pub fn heading(&self) -> &'static str {
+        match *self {
+            ChangeType::Breaking => "Breaking Changes",
+            ChangeType::Feature => "Features",
+            ChangeType::Fix => "Bug Fixes",
+            ChangeType::Performance => "Performance",
+            ChangeType::Documentation => "Documentation",
+            ChangeType::Other => "Other Changes",
+        }
+    }
+}
+
+/// Split the type tag off a change title: `feat: x`, `fix(api): x` and
+/// `[docs] x` are tagged, any other title is an `Other` change. A `!`
+/// before the colon, as in `feat!: x` or `feat(api)!: x`, marks a
+/// breaking change.
+pub fn classify(title: &str) -> (ChangeType, String) {
+    let tagged = Regex::new(r"^(?:\[(\w+)\]\s*|(\w+)(?:\([^)]*\))?(!)?:\s*)(.*)$").unwrap();
+    if let Some(caps) = tagged.captures(title.trim()) {
+        let tag = caps.at(1).or(caps.at(2)).unwrap_or("");
+        if let Some(t) = ChangeType::from_tag(tag) {
+            let rest = caps.at(4).unwrap_or("").trim().to_string();
+            let breaking = caps.at(3).is_some();
+            return (if breaking { ChangeType::Breaking } else { t }, rest)
+        }
+    }
+    (ChangeType::Other, title.trim().to_string())
+}
+
+/// Render the Markdown changelog of `changes`, each merged change
+/// along with its description, grouped by change type.
+pub fn render(from: &str, to: &str, changes: &[(MergedChange, Description)]) -> String {
+    let mut out = format!("# Changes from {} to {}\n", from, to);
+    if changes.is_empty() {
+        out.push_str("\nNo changes were merged.\n");
+        return out
+    }
+    let mut classified: Vec<(ChangeType, String, &MergedChange, &Description)> =
+        changes.iter().map(|&(ref m, ref d)| {
+            let (t, title) = classify(&d.title);
+            (t, title, m, d)
+        }).collect();
+    // A stable sort keeps the changes of each group in log order
+    classified.sort_by_key(|c| c.0);
+    let mut current = None;
+    for (t, title, merged, desc) in classified {
+        if current != Some(t) {
+            out.push_str(&format!("\n## {}\n\n", t.heading()));
+            current = Some(t);
+        }
+        out.push_str(&format!("- {} ({})\n", title, &merged.id[..8]));
+        for line in desc.description.lines().filter(|l| !l.trim().is_empty()) {
+            out.push_str(&format!("  {}\n", line.trim_end()));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::change::Description;
+
+    const LOG: &'static str = "aaa111\nMerged change 6a2b3c4d-0000-4000-8000-000000000001\n\n\
+                               From review branch add-widgets into master\n\0\n\
+                               bbb222\nMerge branch 'local-thing'\n\0\n\
+                               ccc333\nMerged change 6a2b3c4d-0000-4000-8000-000000000002\n\n\
+                               From review branch hotfix into release-1\n\0\n";
+
+    #[test]
+    fn test_parse_merges() {
+        assert_eq!(vec![MergedChange { id: "6a2b3c4d-0000-4000-8000-000000000001".to_string(),
+                                       pipeline: "master".to_string(),
+                                       sha: "aaa111".to_string() }],
+                   parse_merges(LOG, "master"));
+        assert_eq!("ccc333", parse_merges(LOG, "release-1")[0].sha);
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!((ChangeType::Feature, "Add widgets".to_string()), classify("feat: Add widgets"));
+        assert_eq!((ChangeType::Fix, "Fix crash".to_string()), classify("fix(api): Fix crash"));
+        assert_eq!((ChangeType::Documentation, "Typo".to_string()), classify("[docs] Typo"));
+        assert_eq!((ChangeType::Breaking, "Drop v1".to_string()), classify("feat!: Drop v1"));
+        assert_eq!((ChangeType::Breaking, "Drop v1".to_string()),
+                   classify("feat(api)!: Drop v1"));
+        assert_eq!((ChangeType::Fix, "Keep v1".to_string()), classify("fix(api!): Keep v1"));
+        assert_eq!((ChangeType::Other, "Note: whatever".to_string()), classify("Note: whatever"));
+    }
+
+    #[test]
+    fn test_render() {
+        let change = |n: &str, title: &str, desc: &str| {
+            (MergedChange { id: format!("{}-0000-4000-8000-000000000000", n),
+                            pipeline: "master".to_string(), sha: String::new() },
+             Description { title: title.to_string(), description: desc.to_string() })
+        };
+        let changes = vec![change("11111111", "Bump deps", ""),
+                           change("22222222", "fix: Handle empty config", "It used to panic.\n"),
+                           change("33333333", "feat: Add changelog", "")];
+        assert_eq!("# Changes from v1.2.0 to HEAD\n\
+                    \n## Features\n\n- Add changelog (33333333)\n\
+                    \n## Bug Fixes\n\n- Handle empty config (22222222)\n  It used to panic.\n\
+                    \n## Other Changes\n\n- Bump deps (11111111)\n",
+                   render("v1.2.0", "HEAD", &changes));
+        assert!(render("a", "b", &[]).contains("No changes were merged."));
+    }
+}
@@ -27,7 +27,10 @@ pub struct ApiClapOptions<'n> {
     pub method: &'n str,
     pub path: &'n str,
     pub data: &'n str,
+    pub filter: &'n str,
+    pub no_paginate: bool,
     pub server: &'n str,
+    pub server_url: &'n str,
     pub api_port: &'n str,
     pub ent: &'n str,
     pub user: &'n str,
@@ -39,7 +42,10 @@ impl<'n> Default for ApiClapOptions<'n> {
             method: "",
             path: "",
             data: "",
+            filter: "",
+            no_paginate: false,
             server: "",
+            server_url: "",
             api_port: "",
             ent: "",
             user: ""
@@ -52,7 +58,10 @@ impl<'n> ApiClapOptions<'n> {
             method: value_of(&matches, "method"),
             path: value_of(&matches, "path"),
             data: value_of(&matches, "data"),
+            filter: value_of(&matches, "filter"),
+            no_paginate: matches.is_present("no-paginate"),
             server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
             api_port: value_of(&matches, "api-port"),
             ent: value_of(&matches, "ent"),
             user: value_of(&matches, "user")
@@ -64,6 +73,7 @@ impl<'n> Options for ApiClapOptions<'n> {
     fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
         let new_config = config.set_user(&self.user)
             .set_server(&self.server)
+            .set_server_url(&self.server_url)
             .set_api_port(&self.api_port)
             .set_enterprise(&self.ent);
         Ok(new_config)
@@ -88,5 +98,10 @@ pub fn clap_subcommand<'c>() -> App<'c, 'c> {
              .multiple(false)
              .number_of_values(1)
              .use_delimiter(false))
+        .args_from_usage(
+            "--filter=[expression] 'Print only what the path picks out of the response, \
+             i.e. .changes[0].id or [*].title'
+            --no-paginate 'Print only the first page of a GET with a limit, instead of \
+             following the next offsets and merging the pages'")
         .args(&u_e_s_o_args())
 }
@@ -41,12 +41,71 @@ macro_rules! fn_arg {
     )
 }
 
+// Options structs
+//
+// `clap_options!` writes the `<Command>ClapOptions` struct of a
+// subcommand, its `Default` and its `new(&ArgMatches)` from a single
+// list of fields, so they can not drift apart:
+//
+//     clap_options!(DiffClapOptions {
+//         change: value("change"),
+//         pipeline: value("pipeline", "master"),
+//         local: flag("local"),
+//     });
+//
+// A `value` is the `&str` of an option or positional argument, empty
+// unless a default is given, and a `flag` is whether a switch was
+// passed. Values set in the environment come through the `DELIVERY_<KEY>`
+// layer of the config, see `Config::env_layers`.
+macro_rules! clap_options {
+    ( $name:ident { $( $field:ident : $kind:ident ( $key:expr $(, $default:expr)* ) ),*
+                    $(,)* } ) => (
+        #[derive(Debug)]
+        pub struct $name<'n> {
+            $( pub $field: clap_option_type!($kind, 'n), )*
+        }
+
+        impl<'n> Default for $name<'n> {
+            fn default() -> Self {
+                $name {
+                    $( $field: clap_option_default!($kind $(, $default)*), )*
+                }
+            }
+        }
+
+        impl<'n> $name<'n> {
+            pub fn new(matches: &'n ::clap::ArgMatches<'n>) -> Self {
+                $name {
+                    $( $field: clap_option_value!($kind, matches, $key), )*
+                }
+            }
+        }
+    )
+}
+
+macro_rules! clap_option_type {
+    ( value, $lt:lifetime ) => ( &$lt str );
+    ( flag, $lt:lifetime ) => ( bool );
+}
+
+macro_rules! clap_option_default {
+    ( value ) => ( "" );
+    ( value, $default:expr ) => ( $default );
+    ( flag ) => ( false );
+}
+
+macro_rules! clap_option_value {
+    ( value, $matches:ident, $key:expr ) => ( ::cli::arguments::value_of(&$matches, $key) );
+    ( flag, $matches:ident, $key:expr ) => ( $matches.is_present($key) );
+}
+
 pub fn u_e_s_o_args<'a>() -> Vec<Arg<'a, 'a>> {
     make_arg_vec![
         "-u --user=[user] 'User name for Delivery authentication'",
-        "-e --ent=[ent] 'The enterprise in which the project lives'",
+        ent_arg_str(),
         "-o --org=[org] 'The organization in which the project lives'",
-        server_arg_str()]
+        server_arg_str(),
+        server_url_arg_str()]
 }
 
 // Defines all the options shared between commands that
@@ -64,7 +123,9 @@ pub fn scp_args<'a>() -> Vec<Arg<'a, 'a>> {
         "--bitbucket=[project-key] 'Use a Bitbucket repository for Code Review with the provided Project Key'",
         "--github=[org-name] 'Use a Github repository for Code Review with the provided Organization'",
         "-r --repo-name=[repo-name] 'Source code provider repository name'",
-        "--no-verify-ssl 'Do not use SSL verification. [Github]'"]
+        "--no-verify-ssl 'Do not use SSL verification. [Github]'",
+        "--upstream-remote=[remote] 'Name of the git remote of the source code provider \
+         repository (default: detected from the remote URLs)'"]
 }
 
 pub fn pipeline_arg<'a>() -> Vec<Arg<'a, 'a>> {
@@ -73,21 +134,49 @@ pub fn pipeline_arg<'a>() -> Vec<Arg<'a, 'a>> {
         ).visible_alias("for")]
 }
 
+// Not a `fn_arg!`, the usage string syntax has no room for the quotes
+// of the example.
+pub fn format_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("format")
+        .long("format")
+        .takes_value(true)
+        .value_name("template=TEMPLATE")
+        .help("Print each item through a template instead, \
+               i.e. template='{{.id}} {{.title}}'")
+}
+
+pub fn ent_arg_str<'a>() -> &'a str {
+    "-e --ent=[ent] 'The enterprise in which the project lives'"
+}
+
 pub fn server_arg_str<'a>() -> &'a str {
     "-s --server=[server] 'The Automate server address'"
 }
 
+pub fn server_url_arg_str<'a>() -> &'a str {
+    "--server-url=[url] 'The full Automate server URL, including any port and \
+     path prefix (i.e. https://automate.example.com:8443/delivery)'"
+}
+
 pub fn api_port_arg_str<'a>() -> &'a str {
     "--api-port=[api-port] 'Port for Automate server'"
 }
 
+fn_arg!(ent_arg, ent_arg_str());
+
 fn_arg!(server_arg, server_arg_str());
 
+fn_arg!(server_url_arg, server_url_arg_str());
+
 fn_arg!(api_port_arg, api_port_arg_str());
 
 fn_arg!(config_project_arg,
        "-c --config-json=[config-json] 'Path of a custom config.json file'");
 
+fn_arg!(config_dir_arg,
+       "--config-dir=[config-dir] 'Path of a directory whose files are synced \
+        into .delivery/ (config.json, dependencies/, phase data files...)'");
+
 fn_arg!(patchset_arg,
        "-P --patchset=[patchset] 'A patchset number (default: latest)'");
 
@@ -98,8 +187,22 @@ fn_arg!(config_path_arg,
         "--config-path=[dir] 'Directory to read/write your config file \
          (cli.toml) from'");
 
+fn_arg!(concurrency_arg,
+        "--concurrency=[N] 'Number of API requests to run in parallel (default: 4)'");
+
 fn_arg!(local_arg, "-l --local 'Operate without a Delivery server'");
 
+fn_arg!(unshallow_arg,
+        "--unshallow 'Fetch the full history first when the repository is a shallow clone'");
+
+fn_arg!(fix_remote_arg,
+        "--fix-remote 'Update the delivery git remote when it does not match the \
+         configured server, enterprise, organization or project'");
+
+fn_arg!(i_know_what_im_doing_arg,
+        "--i-know-what-im-doing 'Run even from inside a workspace or cache managed \
+         by the delivery CLI'");
+
 fn_arg!(no_open_arg, "-n --no-open 'Do not open the change in a browser'");
 
 fn_arg!(auto_bump, "-a --auto-bump 'Automatic cookbook version bump'");
@@ -110,11 +213,58 @@ fn_arg!(no_color_arg, "--no-color 'Disable colors in terminal'");
 
 fn_arg!(non_interactive_arg, "--non-interactive 'Disable command line interactions'");
 
+fn_arg!(json_arg, "--json 'Print a JSON document describing what the command did \
+                    instead of the usual output'");
+
+fn_arg!(verbose_arg, "-v --verbose... 'Log more of what the CLI does to stderr, \
+                       repeat for more details'");
+
+fn_arg!(log_commands_arg, "--log-commands 'Append every git command and HTTP request \
+                            to the CLI log file'");
+
+fn_arg!(record_fixtures_arg, "--record-fixtures=[dir] 'Record every API request and \
+                             response to dir, credentials redacted, to attach to a bug report'");
+
+fn_arg!(replay_fixtures_arg, "--replay-fixtures=[dir] 'Answer API requests with the \
+                             responses recorded in dir instead of asking the server'");
+
+fn_arg!(ca_cert_arg, "--ca-cert=[path] 'PEM bundle of the certificate authorities to \
+                       trust for the Automate server and generator clones over https'");
+
+fn_arg!(strict_arg, "--strict 'Fail on warnings instead of printing them, each with an \
+                      exit code of its own'");
+
+fn_arg!(debug_arg, "--debug 'Show the details the server sent along with its errors'");
+
 #[cfg(test)]
 mod tests {
     use cli;
     use super::value_of;
 
+    clap_options!(TestClapOptions {
+        name: value("name"),
+        pipeline: value("pipeline", "master"),
+        local: flag("local"),
+    });
+
+    #[test]
+    fn test_clap_options_macro() {
+        let defaults = TestClapOptions::default();
+        assert_eq!("", defaults.name);
+        assert_eq!("master", defaults.pipeline);
+        assert_eq!(false, defaults.local);
+
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let matches = cli::make_app(&build_version).get_matches_from(
+            vec!["delivery", "diff", "branch", "-l", "--pipeline", "slytherin"]
+        );
+        let cmd_matches = matches.subcommand_matches(cli::diff::SUBCOMMAND_NAME).unwrap();
+        let options = TestClapOptions::new(&cmd_matches);
+        assert_eq!("", options.name);
+        assert_eq!("slytherin", options.pipeline);
+        assert_eq!(true, options.local);
+    }
+
     #[test]
     fn test_value_of_trait() {
         let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
@@ -0,0 +1,62 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::value_of;
+use clap::{Arg, App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+
+pub const SUBCOMMAND_NAME: &'static str = "cache";
+
+#[derive(Debug)]
+pub struct CacheClapOptions<'n> {
+    pub action: &'n str,
+    pub all: bool,
+}
+
+impl<'n> Default for CacheClapOptions<'n> {
+    fn default() -> Self {
+        CacheClapOptions {
+            action: "",
+            all: false,
+        }
+    }
+}
+
+impl<'n> CacheClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        CacheClapOptions {
+            action: value_of(&matches, "action"),
+            all: matches.is_present("all"),
+        }
+    }
+}
+
+impl<'n> Options for CacheClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        Ok(config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Maintain the cache of custom build cookbook generators")
+        .arg(Arg::from_usage("<action> 'clean: remove the generators whose source has \
+                              moved on, is gone or is unknown'")
+             .possible_values(&["clean"]))
+        .args_from_usage("--all 'Remove every cached generator'")
+}
@@ -0,0 +1,79 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use project;
+use cli::arguments::{api_port_arg, pipeline_arg, project_arg, u_e_s_o_args};
+use clap::{Arg, App, SubCommand};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+
+pub const SUBCOMMAND_NAME: &'static str = "change";
+
+clap_options!(ChangeClapOptions {
+    action: value("action"),
+    state: value("state"),
+    older_than: value("older-than"),
+    topic: value("match"),
+    pipeline: value("pipeline"),
+    project: value("project"),
+    yes: flag("yes"),
+    non_interactive: flag("non-interactive"),
+    user: value("user"),
+    server: value("server"),
+    server_url: value("server-url"),
+    api_port: value("api-port"),
+    ent: value("ent"),
+    org: value("org"),
+});
+
+impl<'n> Options for ChangeClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let mut new_config = config.set_user(&self.user)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_api_port(&self.api_port)
+            .set_enterprise(&self.ent)
+            .set_organization(&self.org)
+            .set_pipeline(&self.pipeline)
+            .set_project(&self.project);
+
+        if new_config.project.is_none() {
+            new_config.project = project::project_from_cwd().ok();
+        }
+        Ok(new_config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Abandon, retry or approve every change of a pipeline matching a selector")
+        .arg(Arg::from_usage("<action> 'abandon: abandon the changes; retry: run their \
+                              current stage again; approve: merge them'")
+             .possible_values(&["abandon", "retry", "approve"]))
+        .arg(Arg::from_usage("--state=[state] 'Only the changes in this state'")
+             .possible_values(&["open", "approved", "delivered", "superseded", "abandoned"])
+             .default_value("open"))
+        .args_from_usage(
+            "--older-than=[age] 'Only the changes submitted longer ago than this, \
+             i.e. 36h, 90d or 12w'
+            --match=[glob] 'Only the changes whose feature branch matches this glob, \
+             i.e. feature/*'
+            -y --yes 'Do not ask for a confirmation after the preview'")
+        .args(&pipeline_arg())
+        .args(&vec![project_arg(), api_port_arg()])
+        .args(&u_e_s_o_args())
+}
@@ -16,49 +16,27 @@
 //
 use project;
 use fips;
-use cli::arguments::{pipeline_arg, patchset_arg, value_of, project_specific_args};
-use clap::{App, SubCommand, ArgMatches};
+use cli::arguments::{pipeline_arg, patchset_arg, i_know_what_im_doing_arg, fix_remote_arg,
+                     project_specific_args, format_arg};
+use clap::{App, SubCommand};
 use cli::Options;
 use config::Config;
 use types::DeliveryResult;
 
 pub const SUBCOMMAND_NAME: &'static str = "checkout";
 
-#[derive(Debug)]
-pub struct CheckoutClapOptions<'n> {
-    pub pipeline: &'n str,
-    pub change: &'n str,
-    pub patchset: &'n str,
-    pub fips: bool,
-    pub fips_git_port: &'n str,
-    pub fips_custom_cert_filename: &'n str,
-}
-
-impl<'n> Default for CheckoutClapOptions<'n> {
-    fn default() -> Self {
-        CheckoutClapOptions {
-            pipeline: "master",
-            change: "",
-            patchset: "",
-            fips: false,
-            fips_git_port: "",
-            fips_custom_cert_filename: "",
-        }
-    }
-}
-
-impl<'n> CheckoutClapOptions<'n> {
-    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
-        CheckoutClapOptions {
-            pipeline: value_of(&matches, "pipeline"),
-            change: value_of(&matches, "change"),
-            patchset: value_of(&matches, "patchset"),
-            fips: matches.is_present("fips"),
-            fips_git_port: value_of(&matches, "fips-git-port"),
-            fips_custom_cert_filename: value_of(&matches, "fips-custom-cert-filename"),
-        }
-    }
-}
+clap_options!(CheckoutClapOptions {
+    pipeline: value("pipeline", "master"),
+    change: value("change"),
+    patchset: value("patchset"),
+    list: flag("list"),
+    format: value("format"),
+    i_know_what_im_doing: flag("i-know-what-im-doing"),
+    fix_remote: flag("fix-remote"),
+    fips: flag("fips"),
+    fips_git_port: value("fips-git-port"),
+    fips_custom_cert_filename: value("fips-custom-cert-filename"),
+});
 
 impl<'n> Options for CheckoutClapOptions<'n> {
     fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
@@ -76,8 +54,11 @@ impl<'n> Options for CheckoutClapOptions<'n> {
 pub fn clap_subcommand<'c>() -> App<'c, 'c> {
     SubCommand::with_name(SUBCOMMAND_NAME)
         .about("Create a local branch tracking an in-progress change")
-        .args(&vec![patchset_arg()])
+        .args(&vec![patchset_arg(), i_know_what_im_doing_arg(), fix_remote_arg(), format_arg()])
         .args(&pipeline_arg())
-        .args_from_usage("<change> 'Name of the feature branch to checkout'")
+        .args_from_usage("[change] 'Name of the feature branch to checkout; without it, pick \
+                          one of the open changes of the pipeline'
+                          --list 'Print the open changes of the pipeline, one per line, \
+                          instead of checking one out'")
         .args(&project_specific_args())
 }
@@ -16,69 +16,36 @@
 //
 use project;
 use fips;
-use cli::arguments::{u_e_s_o_args, value_of, project_specific_args};
-use clap::{App, SubCommand, ArgMatches};
+use cli::arguments::{u_e_s_o_args, project_specific_args};
+use clap::{App, SubCommand};
 use cli::Options;
 use types::DeliveryResult;
 use config::Config;
 
 pub const SUBCOMMAND_NAME: &'static str = "clone";
 
-#[derive(Debug)]
-pub struct CloneClapOptions<'n> {
-    pub project: &'n str,
-    pub user: &'n str,
-    pub server: &'n str,
-    pub ent: &'n str,
-    pub org: &'n str,
-    pub git_url: &'n str,
-    pub fips: bool,
-    pub fips_git_port: &'n str,
-    pub fips_custom_cert_filename: &'n str,
-}
-impl<'n> Default for CloneClapOptions<'n> {
-    fn default() -> Self {
-        CloneClapOptions {
-            project: "",
-            user: "",
-            server: "",
-            ent: "",
-            org: "",
-            git_url: "",
-            fips: false,
-            fips_git_port: "",
-            fips_custom_cert_filename: "",
-        }
-    }
-}
-
-impl<'n> CloneClapOptions<'n> {
-    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
-        CloneClapOptions {
-            project: value_of(&matches, "project"),
-            user: value_of(&matches, "user"),
-            server: value_of(&matches, "server"),
-            ent: value_of(&matches, "ent"),
-            org: value_of(&matches, "org"),
-            git_url: value_of(&matches, "git-url"),
-            fips: matches.is_present("fips"),
-            fips_git_port: value_of(&matches, "fips-git-port"),
-            fips_custom_cert_filename: value_of(&matches, "fips-custom-cert-filename"),
-        }
-    }
-}
+clap_options!(CloneClapOptions {
+    project: value("project"),
+    user: value("user"),
+    server: value("server"),
+    server_url: value("server-url"),
+    ent: value("ent"),
+    org: value("org"),
+    git_url: value("git-url"),
+    fips: flag("fips"),
+    fips_git_port: value("fips-git-port"),
+    fips_custom_cert_filename: value("fips-custom-cert-filename"),
+});
 
 impl<'n> Options for CloneClapOptions<'n> {
     fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
-        let mut new_config = config.set_user(&self.user)
+        let project = try!(project::ProjectRef::resolve(&self.project, &self.ent, &self.org));
+        let new_config = config.set_user(&self.user)
             .set_server(&self.server)
-            .set_enterprise(&self.ent)
-            .set_organization(&self.org)
-            .set_project(&self.project);
-
-        if new_config.project.is_none() {
-            new_config.project = project::project_from_cwd().ok();
-        }
+            .set_server_url(&self.server_url)
+            .set_enterprise(&project.ent)
+            .set_organization(&project.org)
+            .set_project(&project.name);
 
         fips::merge_fips_options_and_config(self.fips, self.fips_git_port,
                                             self.fips_custom_cert_filename, new_config)
@@ -89,7 +56,8 @@ pub fn clap_subcommand<'c>() -> App<'c, 'c> {
     SubCommand::with_name(SUBCOMMAND_NAME)
         .about("Clone a project repository")
         .args_from_usage(
-            "<project> 'Name of project to clone'
+            "<project> 'Name of project to clone, or ORG/PROJECT or ENT/ORG/PROJECT \
+             to clone it from another organization or enterprise'
             -g --git-url=[url] \
             'Git URL (-u -s -e -o ignored if used)'")
         .args(&u_e_s_o_args())
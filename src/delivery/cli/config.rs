@@ -0,0 +1,53 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{api_port_arg, u_e_s_o_args};
+use clap::{App, SubCommand};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+
+pub const SUBCOMMAND_NAME: &'static str = "config";
+
+clap_options!(ConfigClapOptions {
+    show_origin: flag("show-origin"),
+    user: value("user"),
+    server: value("server"),
+    server_url: value("server-url"),
+    api_port: value("api-port"),
+    ent: value("ent"),
+    org: value("org"),
+});
+
+impl<'n> Options for ConfigClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        Ok(config.set_user(&self.user)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_api_port(&self.api_port)
+            .set_enterprise(&self.ent)
+            .set_organization(&self.org))
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Show the configuration in effect, from the system, user and \
+                project cli.toml, the DELIVERY_* environment variables and the flags")
+        .args_from_usage("--show-origin 'Show where each value comes from'")
+        .args(&vec![api_port_arg()])
+        .args(&u_e_s_o_args())
+}
@@ -0,0 +1,104 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::arguments::{api_port_arg, project_arg, u_e_s_o_args, value_of};
+use clap::{App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+use project;
+
+pub const SUBCOMMAND_NAME: &'static str = "deliver";
+
+#[derive(Debug)]
+pub struct DeliverClapOptions<'n> {
+    pub change: &'n str,
+    pub at: &'n str,
+    pub window: &'n str,
+    pub project: &'n str,
+    pub user: &'n str,
+    pub server: &'n str,
+    pub server_url: &'n str,
+    pub api_port: &'n str,
+    pub ent: &'n str,
+    pub org: &'n str,
+}
+
+impl<'n> Default for DeliverClapOptions<'n> {
+    fn default() -> Self {
+        DeliverClapOptions {
+            change: "",
+            at: "",
+            window: "",
+            project: "",
+            user: "",
+            server: "",
+            server_url: "",
+            api_port: "",
+            ent: "",
+            org: "",
+        }
+    }
+}
+
+impl<'n> DeliverClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        DeliverClapOptions {
+            change: value_of(&matches, "change"),
+            at: value_of(&matches, "at"),
+            window: value_of(&matches, "window"),
+            project: value_of(&matches, "project"),
+            user: value_of(&matches, "user"),
+            server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
+            api_port: value_of(&matches, "api-port"),
+            ent: value_of(&matches, "ent"),
+            org: value_of(&matches, "org"),
+        }
+    }
+}
+
+impl<'n> Options for DeliverClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let project = try!(project::ProjectRef::resolve(&self.project, &self.ent, &self.org));
+
+        let mut new_config = config.set_user(&self.user)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_api_port(&self.api_port)
+            .set_enterprise(&project.ent)
+            .set_organization(&project.org)
+            .set_project(&project.name);
+
+        if new_config.project.is_none() {
+            new_config.project = project::project_from_cwd().ok();
+        }
+        Ok(new_config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Deliver an approved change, now or later")
+        .args_from_usage(
+            "<change> 'The change to deliver'
+            --at=[time] 'Deliver at this time, in UTC, i.e. 2024-06-01T22:00Z'
+            --window=[window] 'Deliver once this window is open: weeknights, weekends \
+                               or one of the [delivery_windows] of your cli.toml'")
+        .args(&vec![project_arg(), api_port_arg()])
+        .args(&u_e_s_o_args())
+}
@@ -0,0 +1,105 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{api_port_arg, concurrency_arg, pipeline_arg, project_arg,
+                     u_e_s_o_args, value_of};
+use clap::{App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+use project;
+
+pub const SUBCOMMAND_NAME: &'static str = "deliver-order";
+
+#[derive(Debug)]
+pub struct DeliverOrderClapOptions<'n> {
+    pub changes: &'n str,
+    pub execute: bool,
+    pub concurrency: &'n str,
+    pub pipeline: &'n str,
+    pub project: &'n str,
+    pub user: &'n str,
+    pub server: &'n str,
+    pub server_url: &'n str,
+    pub api_port: &'n str,
+    pub ent: &'n str,
+    pub org: &'n str,
+}
+
+impl<'n> Default for DeliverOrderClapOptions<'n> {
+    fn default() -> Self {
+        DeliverOrderClapOptions {
+            changes: "",
+            execute: false,
+            concurrency: "",
+            pipeline: "master",
+            project: "",
+            user: "",
+            server: "",
+            server_url: "",
+            api_port: "",
+            ent: "",
+            org: "",
+        }
+    }
+}
+
+impl<'n> DeliverOrderClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        DeliverOrderClapOptions {
+            changes: value_of(&matches, "changes"),
+            execute: matches.is_present("execute"),
+            concurrency: value_of(&matches, "concurrency"),
+            pipeline: value_of(&matches, "pipeline"),
+            project: value_of(&matches, "project"),
+            user: value_of(&matches, "user"),
+            server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
+            api_port: value_of(&matches, "api-port"),
+            ent: value_of(&matches, "ent"),
+            org: value_of(&matches, "org"),
+        }
+    }
+}
+
+impl<'n> Options for DeliverOrderClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let project = try!(project::ProjectRef::resolve(&self.project, &self.ent, &self.org));
+
+        let new_config = config.set_user(&self.user)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_api_port(&self.api_port)
+            .set_enterprise(&project.ent)
+            .set_organization(&project.org)
+            .set_pipeline(&self.pipeline)
+            .set_project(&project.name);
+        Ok(new_config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Print the order in which changes of dependent pipelines \
+                can be delivered safely")
+        .args_from_usage(
+            "--changes=<changes> 'Comma separated list of changes, as <change-id> \
+             for the current project or <project>:<change-id>'
+            --execute 'Deliver the changes one after the other, in that order'")
+        .args(&vec![project_arg(), api_port_arg(), concurrency_arg()])
+        .args(&pipeline_arg())
+        .args(&u_e_s_o_args())
+}
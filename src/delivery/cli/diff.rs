@@ -16,50 +16,55 @@
 //
 use project;
 use fips;
-use cli::arguments::{pipeline_arg, patchset_arg,
-                     value_of, project_specific_args};
-use clap::{App, SubCommand, ArgMatches};
+use git::DiffMode;
+use cli::arguments::{pipeline_arg, patchset_arg, fix_remote_arg, project_specific_args};
+use clap::{Arg, App, SubCommand};
 use cli::Options;
 use types::DeliveryResult;
 use config::Config;
 
 pub const SUBCOMMAND_NAME: &'static str = "diff";
 
-#[derive(Debug)]
-pub struct DiffClapOptions<'n> {
-    pub change: &'n str,
-    pub patchset: &'n str,
-    pub pipeline: &'n str,
-    pub local: bool,
-    pub fips: bool,
-    pub fips_git_port: &'n str,
-    pub fips_custom_cert_filename: &'n str,
-}
+clap_options!(DiffClapOptions {
+    change: value("change"),
+    patchset: value("patchset"),
+    interdiff: value("interdiff"),
+    from_patchset: value("from-patchset"),
+    to_patchset: value("to-patchset"),
+    pipeline: value("pipeline", "master"),
+    local: flag("local"),
+    stat: flag("stat"),
+    name_only: flag("name-only"),
+    word_diff: flag("word-diff"),
+    json: flag("json"),
+    fix_remote: flag("fix-remote"),
+    fips: flag("fips"),
+    fips_git_port: value("fips-git-port"),
+    fips_custom_cert_filename: value("fips-custom-cert-filename"),
+});
 
-impl<'n> Default for DiffClapOptions<'n> {
-    fn default() -> Self {
-        DiffClapOptions {
-            change: "",
-            patchset: "",
-            pipeline: "master",
-            local: false,
-            fips: false,
-            fips_git_port: "",
-            fips_custom_cert_filename: "",
-        }
+impl<'n> DiffClapOptions<'n> {
+    /// The patchset to show, --to-patchset standing for --patchset
+    pub fn target_patchset(&self) -> &'n str {
+        if self.to_patchset.is_empty() { self.patchset } else { self.to_patchset }
     }
-}
 
-impl<'n> DiffClapOptions<'n> {
-    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
-        DiffClapOptions {
-            change: value_of(&matches, "change"),
-            patchset: value_of(&matches, "patchset"),
-            pipeline: value_of(&matches, "pipeline"),
-            local: matches.is_present("local"),
-            fips: matches.is_present("fips"),
-            fips_git_port: value_of(&matches, "fips-git-port"),
-            fips_custom_cert_filename: value_of(&matches, "fips-custom-cert-filename"),
+    /// The patchset to diff against, --from-patchset standing for
+    /// --interdiff. Empty when diffing against the pipeline or HEAD.
+    pub fn base_patchset(&self) -> &'n str {
+        if self.from_patchset.is_empty() { self.interdiff } else { self.from_patchset }
+    }
+
+    /// The output mode picked with --stat, --name-only or --word-diff
+    pub fn mode(&self) -> DiffMode {
+        if self.stat {
+            DiffMode::Stat
+        } else if self.name_only {
+            DiffMode::NameOnly
+        } else if self.word_diff {
+            DiffMode::WordDiff
+        } else {
+            DiffMode::Full
         }
     }
 }
@@ -80,11 +85,25 @@ impl<'n> Options for DiffClapOptions<'n> {
 pub fn clap_subcommand<'c>() -> App<'c, 'c> {
     SubCommand::with_name(SUBCOMMAND_NAME)
         .about("Display diff for a change")
-        .args(&vec![patchset_arg()])
+        .args(&vec![patchset_arg(), fix_remote_arg()])
         .args(&pipeline_arg())
         .args_from_usage(
             "<change> 'Name of the feature branch to compare'
             -l --local \
-            'Diff against the local branch HEAD'")
+            'Diff against the local branch HEAD'
+            --interdiff=[patchset] \
+            'Diff against another patchset of the change'")
+        .arg(Arg::from_usage("--from-patchset=[patchset] \
+                              'Diff from this patchset of the change'")
+             .conflicts_with_all(&["interdiff", "local"]))
+        .arg(Arg::from_usage("--to-patchset=[patchset] \
+                              'Diff up to this patchset of the change (default: latest)'")
+             .conflicts_with("patchset")
+             .requires("from-patchset"))
+        .arg(Arg::from_usage("--stat 'Show a summary of the changed files'")
+             .conflicts_with_all(&["name-only", "word-diff"]))
+        .arg(Arg::from_usage("--name-only 'Only show the names of the changed files'")
+             .conflicts_with("word-diff"))
+        .arg(Arg::from_usage("--word-diff 'Show the changed words instead of lines'"))
         .args(&project_specific_args())
 }
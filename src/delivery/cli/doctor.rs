@@ -0,0 +1,76 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{api_port_arg, u_e_s_o_args, value_of};
+use clap::{App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+
+pub const SUBCOMMAND_NAME: &'static str = "doctor";
+
+#[derive(Debug)]
+pub struct DoctorClapOptions<'n> {
+    pub user: &'n str,
+    pub server: &'n str,
+    pub server_url: &'n str,
+    pub api_port: &'n str,
+    pub ent: &'n str,
+}
+
+impl<'n> Default for DoctorClapOptions<'n> {
+    fn default() -> Self {
+        DoctorClapOptions {
+            user: "",
+            server: "",
+            server_url: "",
+            api_port: "",
+            ent: "",
+        }
+    }
+}
+
+impl<'n> DoctorClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        DoctorClapOptions {
+            user: value_of(&matches, "user"),
+            server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
+            api_port: value_of(&matches, "api-port"),
+            ent: value_of(&matches, "ent"),
+        }
+    }
+}
+
+impl<'n> Options for DoctorClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let new_config = config.set_user(&self.user)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_api_port(&self.api_port)
+            .set_enterprise(&self.ent);
+        Ok(new_config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Diagnose problems with the local environment and the Automate server: \
+                tools on the PATH, cli.toml, the delivery remote, clock skew, token \
+                validity and SSH access")
+        .arg(api_port_arg())
+        .args(&u_e_s_o_args())
+}
@@ -0,0 +1,105 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{pipeline_arg, project_arg, u_e_s_o_args, value_of};
+use clap::{Arg, App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+use project;
+
+pub const SUBCOMMAND_NAME: &'static str = "env";
+
+const STAGES: &'static [&'static str] = &["acceptance", "union", "rehearsal", "delivered"];
+
+#[derive(Debug)]
+pub struct EnvClapOptions<'n> {
+    pub action: &'n str,
+    pub from: &'n str,
+    pub to: &'n str,
+    pub pipeline: &'n str,
+    pub project: &'n str,
+    pub user: &'n str,
+    pub server: &'n str,
+    pub server_url: &'n str,
+    pub ent: &'n str,
+    pub org: &'n str,
+}
+
+impl<'n> Default for EnvClapOptions<'n> {
+    fn default() -> Self {
+        EnvClapOptions {
+            action: "",
+            from: "acceptance",
+            to: "union",
+            pipeline: "master",
+            project: "",
+            user: "",
+            server: "",
+            server_url: "",
+            ent: "",
+            org: "",
+        }
+    }
+}
+
+impl<'n> EnvClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        let default = EnvClapOptions::default();
+        EnvClapOptions {
+            action: value_of(&matches, "action"),
+            from: matches.value_of("from").unwrap_or(default.from),
+            to: matches.value_of("to").unwrap_or(default.to),
+            pipeline: value_of(&matches, "pipeline"),
+            project: value_of(&matches, "project"),
+            user: value_of(&matches, "user"),
+            server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
+            ent: value_of(&matches, "ent"),
+            org: value_of(&matches, "org"),
+        }
+    }
+}
+
+impl<'n> Options for EnvClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let project = try!(project::ProjectRef::resolve(&self.project, &self.ent, &self.org));
+
+        let new_config = config.set_user(&self.user)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_enterprise(&project.ent)
+            .set_organization(&project.org)
+            .set_pipeline(&self.pipeline)
+            .set_project(&project.name);
+        Ok(new_config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Compare the Chef environments of two stages of a pipeline")
+        .arg(Arg::from_usage("<action> 'diff: show the cookbook versions and \
+                              attributes that differ between the stages'")
+             .possible_values(&["diff"]))
+        .arg(Arg::from_usage("[from] 'Stage to compare from (default: acceptance)'")
+             .possible_values(STAGES))
+        .arg(Arg::from_usage("[to] 'Stage to compare to (default: union)'")
+             .possible_values(STAGES))
+        .args(&vec![project_arg()])
+        .args(&pipeline_arg())
+        .args(&u_e_s_o_args())
+}
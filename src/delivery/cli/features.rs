@@ -0,0 +1,87 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{api_port_arg, format_arg, u_e_s_o_args, value_of};
+use clap::{App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+
+pub const SUBCOMMAND_NAME: &'static str = "features";
+
+#[derive(Debug)]
+pub struct FeaturesClapOptions<'n> {
+    pub refresh: bool,
+    // The global --json, which features took before it was global
+    pub json: bool,
+    pub format: &'n str,
+    pub user: &'n str,
+    pub server: &'n str,
+    pub server_url: &'n str,
+    pub api_port: &'n str,
+    pub ent: &'n str,
+}
+
+impl<'n> Default for FeaturesClapOptions<'n> {
+    fn default() -> Self {
+        FeaturesClapOptions {
+            refresh: false,
+            json: false,
+            format: "",
+            user: "",
+            server: "",
+            server_url: "",
+            api_port: "",
+            ent: "",
+        }
+    }
+}
+
+impl<'n> FeaturesClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        FeaturesClapOptions {
+            refresh: matches.is_present("refresh"),
+            json: matches.is_present("json"),
+            format: value_of(&matches, "format"),
+            user: value_of(&matches, "user"),
+            server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
+            api_port: value_of(&matches, "api-port"),
+            ent: value_of(&matches, "ent"),
+        }
+    }
+}
+
+impl<'n> Options for FeaturesClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let new_config = config.set_user(&self.user)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_api_port(&self.api_port)
+            .set_enterprise(&self.ent);
+        Ok(new_config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("List the feature flags the Automate server enables for the enterprise")
+        .args_from_usage(
+            "--refresh 'Ask the server again instead of using the cached flags'")
+        .arg(api_port_arg())
+        .arg(format_arg())
+        .args(&u_e_s_o_args())
+}
@@ -0,0 +1,50 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use clap::{Arg, App, SubCommand};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+
+pub const SUBCOMMAND_NAME: &'static str = "fixture";
+
+clap_options!(FixtureClapOptions {
+    kind: value("kind"),
+    action: value("action"),
+    name: value("name"),
+    dir: value("dir"),
+    branch: value("branch", "master"),
+});
+
+impl<'n> Options for FixtureClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        Ok(config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Create throwaway fixtures for functional tests")
+        .arg(Arg::from_usage("<kind> 'repo: a bare git repository and a clone of it'")
+             .possible_values(&["repo"]))
+        .arg(Arg::from_usage("<action> 'create'")
+             .possible_values(&["create"]))
+        .args_from_usage(
+            "<name> 'Name of the fixture, <name>.git for the bare repository'
+            --dir=[dir] 'Where to create the fixture (default: the current directory)'")
+        .arg(Arg::from_usage("-b --branch=[branch] 'Branch of the initial commit'")
+             .default_value("master"))
+}
@@ -0,0 +1,59 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::value_of;
+use clap::{Arg, App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+
+pub const SUBCOMMAND_NAME: &'static str = "git-credential";
+
+#[derive(Debug)]
+pub struct GitCredentialClapOptions<'n> {
+    pub operation: &'n str,
+}
+
+impl<'n> Default for GitCredentialClapOptions<'n> {
+    fn default() -> Self {
+        GitCredentialClapOptions {
+            operation: "",
+        }
+    }
+}
+
+impl<'n> GitCredentialClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        GitCredentialClapOptions {
+            operation: value_of(&matches, "operation"),
+        }
+    }
+}
+
+impl<'n> Options for GitCredentialClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        Ok(config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Git credential helper that answers with your stored API tokens. \
+                Enable it with: git config --global \
+                credential.https://<server>.helper '!delivery git-credential'")
+        .arg(Arg::from_usage("<operation> 'The operation git requests'")
+             .possible_values(&["get", "store", "erase"]))
+}
@@ -15,13 +15,16 @@
 // limitations under the License.
 //
 use cli::arguments::{pipeline_arg, config_path_arg, no_open_arg, project_arg,
-          local_arg, config_project_arg, u_e_s_o_args, scp_args,
-          value_of, project_specific_args};
+          local_arg, config_project_arg, config_dir_arg, unshallow_arg, u_e_s_o_args,
+          scp_args, value_of, project_specific_args, i_know_what_im_doing_arg};
 use clap::{App, SubCommand, ArgMatches};
 use cli::Options;
 use types::DeliveryResult;
 use config::Config;
+use errors::{DeliveryError, Kind};
+use git;
 use project;
+use utils;
 use fips;
 
 pub const SUBCOMMAND_NAME: &'static str = "init";
@@ -30,19 +33,35 @@ pub const SUBCOMMAND_NAME: &'static str = "init";
 pub struct InitClapOptions<'n> {
     pub user: &'n str,
     pub server: &'n str,
+    pub server_url: &'n str,
     pub ent: &'n str,
     pub org: &'n str,
     pub project: &'n str,
     pub pipeline: &'n str,
     pub config_json: &'n str,
+    pub config_dir: &'n str,
     pub generator: &'n str,
+    pub generator_refresh: bool,
+    pub pipeline_template: &'n str,
     pub github_org_name: &'n str,
     pub bitbucket_project_key: &'n str,
     pub repo_name: &'n str,
+    pub upstream_remote: &'n str,
     pub no_v_ssl: bool,
     pub no_open: bool,
     pub skip_build_cookbook: bool,
+    pub readme_badge: bool,
     pub local: bool,
+    pub no_local_repo: bool,
+    pub unshallow: bool,
+    pub no_rollback: bool,
+    pub force_push: bool,
+    pub adopt: bool,
+    pub replace_history: bool,
+    pub dry_run: bool,
+    pub interactive: bool,
+    pub no_detect_scp: bool,
+    pub i_know_what_im_doing: bool,
     pub fips: bool,
     pub fips_git_port: &'n str,
     pub fips_custom_cert_filename: &'n str,
@@ -53,19 +72,35 @@ impl<'n> Default for InitClapOptions<'n> {
         InitClapOptions {
             user: "",
             server: "",
+            server_url: "",
             ent: "",
             org: "",
             project: "",
             pipeline: "master",
             config_json: "",
+            config_dir: "",
             generator: "",
+            generator_refresh: false,
+            pipeline_template: "",
             github_org_name: "",
             bitbucket_project_key: "",
             repo_name: "",
+            upstream_remote: "",
             no_v_ssl: false,
             no_open: false,
             skip_build_cookbook: false,
+            readme_badge: false,
             local: false,
+            no_local_repo: false,
+            unshallow: false,
+            no_rollback: false,
+            force_push: false,
+            adopt: false,
+            replace_history: false,
+            dry_run: false,
+            interactive: false,
+            no_detect_scp: false,
+            i_know_what_im_doing: false,
             fips: false,
             fips_git_port: "",
             fips_custom_cert_filename: "",
@@ -78,38 +113,114 @@ impl<'n> InitClapOptions<'n> {
         InitClapOptions {
             user: value_of(&matches, "user"),
             server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
             ent: value_of(&matches, "ent"),
             org: value_of(&matches, "org"),
             project: value_of(&matches, "project"),
             pipeline: value_of(&matches, "pipeline"),
             config_json: value_of(&matches, "config-json"),
+            config_dir: value_of(&matches, "config-dir"),
             generator: value_of(&matches, "generator"),
+            generator_refresh: matches.is_present("generator-refresh"),
+            pipeline_template: value_of(&matches, "pipeline-template"),
             github_org_name: value_of(&matches, "github"),
             bitbucket_project_key: value_of(&matches, "bitbucket"),
             repo_name: value_of(&matches, "repo-name"),
+            upstream_remote: value_of(&matches, "upstream-remote"),
             no_v_ssl: matches.is_present("no-verify-ssl"),
             no_open: matches.is_present("no-open"),
             skip_build_cookbook: matches.is_present("skip-build-cookbook"),
+            readme_badge: matches.is_present("readme-badge"),
             local: matches.is_present("local"),
+            no_local_repo: matches.is_present("no-local-repo"),
+            unshallow: matches.is_present("unshallow"),
+            no_rollback: matches.is_present("no-rollback"),
+            force_push: matches.is_present("force-push"),
+            adopt: matches.is_present("adopt"),
+            replace_history: matches.is_present("replace-history"),
+            dry_run: matches.is_present("dry-run"),
+            interactive: matches.is_present("interactive"),
+            no_detect_scp: matches.is_present("no-detect-scp"),
+            i_know_what_im_doing: matches.is_present("i-know-what-im-doing"),
             fips: matches.is_present("fips"),
             fips_git_port: value_of(&matches, "fips-git-port"),
             fips_custom_cert_filename: value_of(&matches, "fips-custom-cert-filename"),
         }
     }
+
+    // In a bare repository the project is named after it; outside of
+    // any repository it has to be given with --project, or answered
+    // with --interactive.
+    fn project_name(&self) -> DeliveryResult<String> {
+        if !self.project.is_empty() {
+            return Ok(self.project.to_string())
+        }
+        let cwd = utils::cwd();
+        if git::is_bare_repository(&cwd) {
+            if let Some(proj) = project::project_from_bare_repo(&cwd) {
+                return Ok(proj)
+            }
+        }
+        match project::project_from_cwd() {
+            Err(_) if self.no_local_repo && self.interactive => Ok(String::new()),
+            Err(_) if self.no_local_repo => {
+                let msg = "--project is required with --no-local-repo outside of \
+                           a git repository".to_string();
+                Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+            },
+            result => result
+        }
+    }
+
+    // A pipeline template brings its own config and build cookbook.
+    fn check_pipeline_template(&self) -> DeliveryResult<()> {
+        if !self.pipeline_template.is_empty()
+            && !(self.generator.is_empty() && self.config_json.is_empty()
+                 && self.config_dir.is_empty()) {
+            let msg = "--pipeline-template can not be used with --generator, \
+                       --config-json or --config-dir".to_string();
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        Ok(())
+    }
+
+    // What to do with a project of the same name on the server whose
+    // history has nothing in common with the local one
+    pub fn takeover(&self) -> DeliveryResult<project::Takeover> {
+        match (self.adopt, self.replace_history) {
+            (true, true) => {
+                let msg = "--adopt and --replace-history can not be used together".to_string();
+                Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+            },
+            (true, false) => Ok(project::Takeover::Adopt),
+            (false, true) => Ok(project::Takeover::ReplaceHistory),
+            (false, false) => Ok(project::Takeover::Refuse)
+        }
+    }
 }
 
 impl<'n> Options for InitClapOptions<'n> {
     fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
-        let project = try!(project::project_or_from_cwd(&self.project));
+        let project = try!(self.project_name());
+        try!(self.check_pipeline_template());
+        try!(self.takeover());
 
-        let new_config = config.set_user(&self.user)
+        let mut new_config = config.set_user(&self.user)
             .set_server(&self.server)
+            .set_server_url(&self.server_url)
             .set_enterprise(&self.ent)
             .set_organization(&self.org)
             .set_project(&project)
             .set_pipeline(&self.pipeline)
             .set_generator(&self.generator)
-            .set_config_json(&self.config_json);
+            .set_config_json(&self.config_json)
+            .set_config_dir(&self.config_dir)
+            .set_pipeline_template(&self.pipeline_template)
+            .set_upstream_remote(&self.upstream_remote);
+        // Nothing of a local init goes over the network
+        if self.local {
+            new_config.offline = Some(true);
+        }
 
         fips::merge_fips_options_and_config(self.fips, self.fips_git_port,
                                             self.fips_custom_cert_filename, new_config)
@@ -122,11 +233,36 @@ pub fn clap_subcommand<'c>() -> App<'c, 'c> {
         .about("Initialize a Delivery project \
                 (and lots more!)")
         .args(&vec![config_path_arg(), no_open_arg(), project_arg(),
-                local_arg(), config_project_arg()])
+                local_arg(), config_project_arg(), config_dir_arg(), unshallow_arg(),
+                i_know_what_im_doing_arg()])
         .args_from_usage(
-            "--generator=[generator] 'Local path or Git repo URL to a \
-             custom ChefDK build_cookbook generator (default:github)'
-            --skip-build-cookbook 'Do not create a build cookbook'")
+            "--generator=[generator] 'Local path, Git repo URL or \
+             supermarket:NAME[@VERSION] of a custom ChefDK build_cookbook \
+             generator (default:github)'
+            --generator-refresh 'Fetch the custom generator again when the cached \
+             copy is stale, e.g. its git upstream has moved'
+            --pipeline-template=[template] 'Local path or Git repo URL of a pipeline \
+             template: a config.json, build_cookbook and project.toml rendered with the \
+             project variables and committed to .delivery'
+            --skip-build-cookbook 'Do not create a build cookbook'
+            --readme-badge 'Add a pipeline status badge to the project README'
+            --no-local-repo 'Only create the project and pipeline on the server, \
+             for automation that runs without a worktree'
+            --no-rollback 'Leave the repository as it is when init fails, instead of \
+             undoing the branches, commits, files and remotes it changed'
+            --force-push 'Push the local pipeline branch to the server even when it is \
+             behind or has diverged from its copy on another remote, i.e. origin'
+            --adopt 'When the project exists on the server with a history unrelated to \
+             the local one, merge its history into the local pipeline branch'
+            --replace-history 'When the project exists on the server with a history \
+             unrelated to the local one, force push the local pipeline branch over it'
+            --dry-run 'Only print what init would do, without changing the \
+             repository or the server'
+            --interactive 'Prompt for the server, enterprise, organization, project, \
+             pipeline and source code provider, with defaults from the config and the \
+             git remotes, and write the answers to .delivery/cli.toml'
+            --no-detect-scp 'Do not take the repository name from the upstream \
+             remote when --github or --bitbucket is given without --repo-name'")
         .args(&u_e_s_o_args())
         .args(&scp_args())
         .args(&pipeline_arg())
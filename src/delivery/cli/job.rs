@@ -36,6 +36,7 @@ pub struct JobClapOptions<'n> {
     pub project: &'n str,
     pub user: &'n str,
     pub server: &'n str,
+    pub server_url: &'n str,
     pub ent: &'n str,
     pub org: &'n str,
     pub patchset: &'n str,
@@ -44,8 +45,11 @@ pub struct JobClapOptions<'n> {
     pub shasum: &'n str,
     pub branch: &'n str,
     pub skip_default: bool,
+    pub isolate: bool,
+    pub keep_workspace: bool,
     pub local: bool,
     pub docker_image: &'n str,
+    pub docker_dns: &'n str,
     pub fips: bool,
     pub fips_git_port: &'n str,
     pub fips_custom_cert_filename: &'n str,
@@ -62,6 +66,7 @@ impl<'n> Default for JobClapOptions<'n> {
             project: "",
             user: "",
             server: "",
+            server_url: "",
             ent: "",
             org: "",
             patchset: "",
@@ -70,8 +75,11 @@ impl<'n> Default for JobClapOptions<'n> {
             shasum: "",
             branch: "",
             skip_default: false,
+            isolate: false,
+            keep_workspace: false,
             local: false,
             docker_image: "",
+            docker_dns: "",
             fips: false,
             fips_git_port: "",
             fips_custom_cert_filename: "",
@@ -90,6 +98,7 @@ impl<'n> JobClapOptions<'n> {
             project: value_of(&matches, "project"),
             user: value_of(&matches, "user"),
             server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
             ent: value_of(&matches, "ent"),
             org: value_of(&matches, "org"),
             patchset: value_of(&matches, "patchset"),
@@ -98,8 +107,11 @@ impl<'n> JobClapOptions<'n> {
             shasum: value_of(&matches, "shasum"),
             branch: value_of(&matches, "branch"),
             skip_default: matches.is_present("skip-default"),
+            isolate: matches.is_present("isolate"),
+            keep_workspace: matches.is_present("keep-workspace"),
             local: matches.is_present("local"),
             docker_image: value_of(&matches, "docker"),
+            docker_dns: value_of(&matches, "docker-dns"),
             fips: matches.is_present("fips"),
             fips_git_port: value_of(&matches, "fips-git-port"),
             fips_custom_cert_filename: value_of(&matches, "fips-custom-cert-filename"),
@@ -109,14 +121,15 @@ impl<'n> JobClapOptions<'n> {
 
 impl<'n> Options for JobClapOptions<'n> {
     fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
-        let project = try!(project::project_or_from_cwd(&self.project));
+        let project = try!(project::ProjectRef::resolve(&self.project, &self.ent, &self.org));
 
         let new_config = config.set_pipeline(&self.pipeline)
             .set_user(with_default(&self.user, "you", &&self.local))
             .set_server(with_default(&self.server, "localhost", &&self.local))
-            .set_enterprise(with_default(&self.ent, "local", &&self.local))
-            .set_organization(with_default(&self.org, "workstation", &&self.local))
-            .set_project(&project);
+            .set_server_url(&self.server_url)
+            .set_enterprise(with_default(&project.ent, "local", &&self.local))
+            .set_organization(with_default(&project.org, "workstation", &&self.local))
+            .set_project(&project.name);
 
         fips::merge_fips_options_and_config(self.fips, self.fips_git_port,
                                             self.fips_custom_cert_filename, new_config)
@@ -143,7 +156,10 @@ pub fn clap_subcommand<'c>() -> App<'c, 'c> {
             "-S --shasum=[gitsha] 'Git SHA of change'",
             "--change-id=[id] 'The change ID'",
             "--skip-default 'skip default'",
-            "--docker=[image] 'Docker image'"])
+            "--isolate 'Run in a workspace of its own, removed when the job ends'",
+            "--keep-workspace 'Keep the workspace of an --isolate run'",
+            "--docker=[image] 'Docker image'",
+            "--docker-dns=[server] 'DNS server of the --docker container'"])
         .args_from_usage("<stage> 'Stage for the run'
                           <phases> 'One or more phases'")
         .args(&u_e_s_o_args())
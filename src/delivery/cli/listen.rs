@@ -0,0 +1,68 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use project;
+use cli::arguments::{api_port_arg, pipeline_arg, project_arg, u_e_s_o_args};
+use clap::{App, SubCommand};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+
+pub const SUBCOMMAND_NAME: &'static str = "listen";
+
+clap_options!(ListenClapOptions {
+    events: value("events"),
+    exec: value("exec"),
+    user: value("user"),
+    server: value("server"),
+    server_url: value("server-url"),
+    api_port: value("api-port"),
+    ent: value("ent"),
+    org: value("org"),
+    project: value("project"),
+    pipeline: value("pipeline"),
+});
+
+impl<'n> Options for ListenClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let project = try!(project::ProjectRef::resolve(&self.project, &self.ent, &self.org));
+
+        let new_config = config.set_user(&self.user)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_api_port(&self.api_port)
+            .set_enterprise(&project.ent)
+            .set_organization(&project.org)
+            .set_pipeline(&self.pipeline)
+            .set_project(&project.name);
+        Ok(new_config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Watch the changes of a pipeline and run a local command on each \
+                event, the event JSON on its stdin")
+        .args_from_usage(
+            "--events=[events] 'Comma separated events to run the command on: \
+             change.created, change.approved, change.delivered, phase.passed, \
+             phase.failed (default: all)'
+            -x --exec=<command> 'Shell command to run on each event, with \
+             DELIVERY_EVENT and DELIVERY_CHANGE_ID set'")
+        .args(&vec![api_port_arg(), project_arg()])
+        .args(&pipeline_arg())
+        .args(&u_e_s_o_args())
+}
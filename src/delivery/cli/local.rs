@@ -16,7 +16,10 @@
 //
 use clap::{App, SubCommand, ArgMatches, Arg};
 use delivery_config::project::{Stage, Phase};
-use cli::arguments::value_of;
+use cli::arguments::{pipeline_arg, value_of};
+use cli::Options;
+use config::Config;
+use types::DeliveryResult;
 
 pub const SUBCOMMAND_NAME: &'static str = "local";
 
@@ -24,7 +27,13 @@ pub const SUBCOMMAND_NAME: &'static str = "local";
 pub struct LocalClapOptions<'n> {
     pub phase: Option<Phase>,
     pub stage: Option<Stage>,
-    pub remote_toml: Option<&'n str>
+    pub remote_toml: Option<&'n str>,
+    pub remote_config: bool,
+    pub pipeline: &'n str,
+    pub non_interactive: bool,
+    pub keep_going: bool,
+    pub stage_phase: &'n str,
+    pub matrix: &'n str,
 }
 
 impl<'n> Default for LocalClapOptions<'n> {
@@ -32,7 +41,13 @@ impl<'n> Default for LocalClapOptions<'n> {
         LocalClapOptions {
             phase: None,
             stage: None,
-            remote_toml: None
+            remote_toml: None,
+            remote_config: false,
+            pipeline: "",
+            non_interactive: false,
+            keep_going: false,
+            stage_phase: "",
+            matrix: "",
         }
     }
 }
@@ -64,11 +79,23 @@ impl<'n> LocalClapOptions<'n> {
         LocalClapOptions {
             phase: phase,
             stage: stage,
-            remote_toml: url
+            remote_toml: url,
+            remote_config: matches.is_present("remote-config"),
+            pipeline: value_of(&matches, "pipeline"),
+            non_interactive: matches.is_present("non-interactive"),
+            keep_going: matches.is_present("keep-going"),
+            stage_phase: value_of(matches, "stage_phase"),
+            matrix: value_of(matches, "matrix"),
         }
     }
 }
 
+impl<'n> Options for LocalClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        Ok(config.set_pipeline(self.pipeline))
+    }
+}
+
 pub fn clap_subcommand<'c>() -> App<'c, 'c> {
     SubCommand::with_name(SUBCOMMAND_NAME)
         .about("Run Delivery phases on your local workstation.")
@@ -85,5 +112,13 @@ pub fn clap_subcommand<'c>() -> App<'c, 'c> {
                   lint, syntax]\nacceptance: [provision, deploy, smoke, functional, \
                   cleanup]\nall: [unit, lint, syntax, provision, deploy, smoke, functional, \
                   cleanup]\n\n"))
-        .args_from_usage("-r --remote-project-toml=[remote-url] 'URL for remote project.toml'")
+        .args_from_usage("-r --remote-project-toml=[remote-url] 'URL for remote project.toml'
+                          --remote-config 'Run the phases with the project.toml of the \
+                          pipeline on the server, reporting how it differs from the local one'
+                          -k --keep-going 'Run the remaining phases of the stage after one \
+                          fails'
+                          --matrix=[dimension=values] 'Run once per value in a docker \
+                          container, e.g. os=ubuntu,centos; each value needs an image \
+                          with delivery installed in the matrix_images of the project.toml'")
+        .args(&pipeline_arg())
 }
@@ -0,0 +1,53 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::value_of;
+use clap::{App, SubCommand, ArgMatches};
+
+pub const SUBCOMMAND_NAME: &'static str = "logs-self";
+
+#[derive(Debug)]
+pub struct LogsClapOptions {
+    pub tail: usize,
+    pub follow: bool,
+}
+
+impl Default for LogsClapOptions {
+    fn default() -> Self {
+        LogsClapOptions {
+            tail: 100,
+            follow: false,
+        }
+    }
+}
+
+impl LogsClapOptions {
+    pub fn new(matches: &ArgMatches) -> Self {
+        let tail = value_of(&matches, "tail").parse::<usize>()
+            .unwrap_or(LogsClapOptions::default().tail);
+        LogsClapOptions {
+            tail: tail,
+            follow: matches.is_present("follow"),
+        }
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Show the most recent entries of the delivery cli log files")
+        .args_from_usage("--tail=[lines] 'Number of lines to show. default:100'
+                          -f --follow 'Keep printing new log entries as they are written'")
+}
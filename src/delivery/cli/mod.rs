@@ -18,16 +18,24 @@
 use std;
 use std::env;
 use std::process;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::path::PathBuf;
 use utils;
 use utils::say::{self, sayln, print_error};
-use errors::DeliveryError;
+use utils::json_output;
+use utils::strict;
+use errors::{DeliveryError, Kind};
 use types::{DeliveryResult, ExitCode};
 use config::Config;
 use clap::{App, ArgMatches, AppSettings};
 use delivery_config::project::ProjectToml;
+use utils::privilege::Escalator;
 use utils::cwd;
+use logs as log_file;
+use http::fixtures;
+use http::tls;
+use telemetry as usage;
+use project::wizard;
 
 // Clap Arguments
 //
@@ -35,7 +43,9 @@ use utils::cwd;
 // the ClapAlias trait for arguments that we might deprecate in the future
 #[macro_use]
 pub mod arguments;
-use cli::arguments::{non_interactive_arg, no_spinner_arg, no_color_arg};
+use cli::arguments::{non_interactive_arg, no_spinner_arg, no_color_arg, debug_arg, json_arg,
+                     strict_arg, verbose_arg, log_commands_arg, record_fixtures_arg,
+                     replay_fixtures_arg, ca_cert_arg};
 
 // Modules for setting up clap subcommand including their options and defaults,
 // as well as advanced subcommand match parsing (see local for an example).
@@ -51,6 +61,38 @@ pub mod setup;
 pub mod local;
 pub mod status;
 pub mod pull;
+pub mod data;
+pub mod pin;
+pub mod logs;
+pub mod support_bundle;
+pub mod runner;
+pub mod deliver_order;
+pub mod report;
+pub mod git_credential;
+pub mod prompt;
+pub mod reconcile;
+pub mod queue;
+pub mod changelog;
+pub mod doctor;
+pub mod comment;
+pub mod review_verdict;
+pub mod review_action;
+pub mod features;
+pub mod update_build_deps;
+pub mod environment;
+pub mod refs;
+pub mod cache;
+pub mod telemetry;
+pub mod projects;
+pub mod config;
+pub mod conformance;
+pub mod fixture;
+pub mod listen;
+pub mod change;
+pub mod protect;
+pub mod deliver;
+pub mod schedule;
+pub mod pipeline;
 mod spin;
 
 // Implemented sub-commands. Should handle everything after args have
@@ -68,6 +110,38 @@ use command::setup::SetupCommand;
 use command::token::TokenCommand;
 use command::status::StatusCommand;
 use command::pull::PullCommand;
+use command::data::DataCommand;
+use command::pin::PinCommand;
+use command::logs::LogsCommand;
+use command::support_bundle::SupportBundleCommand;
+use command::runner::RunnerCommand;
+use command::deliver_order::DeliverOrderCommand;
+use command::report::ReportCommand;
+use command::git_credential::GitCredentialCommand;
+use command::prompt::PromptCommand;
+use command::reconcile::ReconcileCommand;
+use command::queue::QueueCommand;
+use command::changelog::ChangelogCommand;
+use command::doctor::DoctorCommand;
+use command::comment::CommentCommand;
+use command::review_verdict::ReviewVerdictCommand;
+use command::review_action::ReviewActionCommand;
+use command::features::FeaturesCommand;
+use command::update_build_deps::UpdateBuildDepsCommand;
+use command::environment::EnvCommand;
+use command::refs::RefsCommand;
+use command::cache::CacheCommand;
+use command::telemetry::TelemetryCommand;
+use command::projects::ProjectCommand;
+use command::config::ConfigCommand;
+use command::conformance::ConformanceCommand;
+use command::fixture::FixtureCommand;
+use command::listen::ListenCommand;
+use command::change::ChangeCommand;
+use command::protect::ProtectCommand;
+use command::deliver::DeliverCommand;
+use command::schedule::ScheduleCommand;
+use command::pipeline::PipelineCommand;
 
 pub trait Options {
     fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config>;
@@ -78,13 +152,39 @@ pub fn run() {
 
     let app = make_app(&build_version);
     let app_matches = app.get_matches();
+    log_file::logger::init(verbosity(&app_matches));
+    let args: Vec<String> = env::args().collect();
+    // The prompt runs on every shell prompt, logging it would only
+    // push the useful entries out of the log files.
+    let logged = app_matches.subcommand_name() != Some(prompt::SUBCOMMAND_NAME);
+    if logged {
+        log_file::record(&format!("run: {}", args.join(" ")));
+    }
 
-    match match_command_and_start(&app_matches, &build_version) {
+    let started = Instant::now();
+    let result = match_command_and_start(&app_matches, &build_version);
+    // Telemetry does not count itself, nor the prompt for the same
+    // reason it is not logged.
+    if logged && app_matches.subcommand_name() != Some(telemetry::SUBCOMMAND_NAME) {
+        usage::record(&usage::Event::new(command_name(&app_matches), started.elapsed(),
+                                         &result, &version()));
+    }
+    match result {
         // You can exit with any integer, can also be used to bypass default
         // error handling if you handled an error and returned non-zero.
-        Ok(exit_status) => process::exit(exit_status),
-        // Handles DeliveryError and exits 1.
-        Err(e) => exit_with(e, 1)
+        Ok(exit_status) => {
+            if logged {
+                log_file::record(&format!("exit: {}", exit_status));
+            }
+            json_output::finish(command_name(&app_matches), exit_status, None);
+            process::exit(exit_status)
+        },
+        // Handles DeliveryError and exits 1, or with the code of the
+        // warning that failed a strict run.
+        Err(e) => {
+            let exit_code = e.exit_code();
+            exit_with(e, exit_code, command_name(&app_matches))
+        }
     }
 }
 
@@ -149,9 +249,18 @@ fn match_command_and_start(app_matches: &ArgMatches, build_version: &str) -> Del
             execute_command(&matches, command)
         },
         (init::SUBCOMMAND_NAME, Some(matches)) => {
-            let options = init::InitClapOptions::new(&matches);
+            let mut options = init::InitClapOptions::new(&matches);
             let config = try!(load_config_and_merge_with_options(&options));
-            let command = InitCommand{options: &options, config: &config};
+            let (config, scp) = if options.interactive {
+                let ask_scp = options.github_org_name.is_empty()
+                    && options.bitbucket_project_key.is_empty();
+                // The answer, Delivery included, is not second guessed
+                options.no_detect_scp = options.no_detect_scp || ask_scp;
+                try!(wizard::run(config, ask_scp, options.no_v_ssl))
+            } else {
+                (config, None)
+            };
+            let command = InitCommand{options: &options, config: &config, scp: scp};
             execute_command(&matches, command)
         },
         (job::SUBCOMMAND_NAME, Some(matches)) => {
@@ -166,10 +275,29 @@ fn match_command_and_start(app_matches: &ArgMatches, build_version: &str) -> Del
         },
         (local::SUBCOMMAND_NAME, Some(matches)) => {
             let options = local::LocalClapOptions::new(&matches);
-            let config = try!(ProjectToml::load_toml(options.remote_toml));
-            let command = LocalCommand{options: &options, config: &config};
+            // The pipeline, the sudo command and interactivity come from the cli.toml
+            let cli_config = try!(load_config_and_merge_with_options(&options));
+            let pipeline = try!(cli_config.pipeline());
+            let config = if options.remote_config {
+                try!(ProjectToml::load_toml_from_pipeline(&pipeline))
+            } else {
+                try!(ProjectToml::load_toml(options.remote_toml))
+            };
+            let interactive = !(options.non_interactive ||
+                                cli_config.non_interactive.unwrap_or(false));
+            let escalator = Escalator::new(cli_config.sudo_command, interactive);
+            let command = LocalCommand{options: &options, config: &config,
+                                       pipeline: &pipeline, escalator: &escalator};
             execute_command(&matches, command)
         },
+        (review::SUBCOMMAND_NAME, Some(matches)) if matches.subcommand_name().is_some() => {
+            let (action, action_matches) = matches.subcommand();
+            let action_matches = action_matches.unwrap();
+            let options = review_action::ReviewActionClapOptions::new(action, action_matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = ReviewActionCommand{options: &options, config: &config};
+            execute_command(action_matches, command)
+        },
         (review::SUBCOMMAND_NAME, Some(matches)) => {
             let options = review::ReviewClapOptions::new(&matches);
             let config = try!(load_config_and_merge_with_options(&options));
@@ -209,6 +337,195 @@ fn match_command_and_start(app_matches: &ArgMatches, build_version: &str) -> Del
             let command = PullCommand{options: &options, config: &config};
             execute_command(&matches, command)
         },
+        (data::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = data::DataClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = DataCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (pin::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = pin::PinClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = PinCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (logs::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = logs::LogsClapOptions::new(&matches);
+            let command = LogsCommand{options: &options};
+            execute_command(&matches, command)
+        },
+        (support_bundle::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = support_bundle::SupportBundleClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = SupportBundleCommand{
+                options: &options,
+                config: &config,
+                build_version: build_version,
+            };
+            execute_command(&matches, command)
+        },
+        (runner::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = runner::RunnerClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = RunnerCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (deliver_order::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = deliver_order::DeliverOrderClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = DeliverOrderCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (report::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = report::ReportClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = ReportCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (git_credential::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = git_credential::GitCredentialClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = GitCredentialCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (prompt::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = prompt::PromptClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = PromptCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (reconcile::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = reconcile::ReconcileClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = ReconcileCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (queue::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = queue::QueueClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = QueueCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (changelog::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = changelog::ChangelogClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = ChangelogCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (doctor::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = doctor::DoctorClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = DoctorCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (comment::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = comment::CommentClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = CommentCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (review_verdict::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = review_verdict::ReviewVerdictClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = ReviewVerdictCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (features::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = features::FeaturesClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = FeaturesCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (update_build_deps::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = update_build_deps::UpdateBuildDepsClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = UpdateBuildDepsCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (environment::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = environment::EnvClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = EnvCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (refs::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = refs::RefsClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = RefsCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (cache::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = cache::CacheClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = CacheCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (telemetry::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = telemetry::TelemetryClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = TelemetryCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (projects::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = projects::ProjectClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = ProjectCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (config::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = config::ConfigClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = ConfigCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (conformance::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = conformance::ConformanceClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = ConformanceCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (fixture::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = fixture::FixtureClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = FixtureCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (listen::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = listen::ListenClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = ListenCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (change::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = change::ChangeClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = ChangeCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (protect::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = protect::ProtectClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = ProtectCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (deliver::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = deliver::DeliverClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = DeliverCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (schedule::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = schedule::ScheduleClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = ScheduleCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
+        (pipeline::SUBCOMMAND_NAME, Some(matches)) => {
+            let options = pipeline::PipelineClapOptions::new(&matches);
+            let config = try!(load_config_and_merge_with_options(&options));
+            let command = PipelineCommand{options: &options, config: &config};
+            execute_command(&matches, command)
+        },
         (spin::SUBCOMMAND_NAME, Some(matches)) => {
             handle_global_flags(&matches);
             let spin_opts = spin::SpinClapOptions::new(&matches);
@@ -237,6 +554,14 @@ fn make_app<'a>(version: &'a str) -> App<'a, 'a> {
         .arg(no_spinner_arg().global(true))
         .arg(no_color_arg().global(true))
         .arg(non_interactive_arg().global(true))
+        .arg(debug_arg().global(true))
+        .arg(json_arg().global(true))
+        .arg(strict_arg().global(true))
+        .arg(verbose_arg().global(true))
+        .arg(log_commands_arg().global(true))
+        .arg(record_fixtures_arg().global(true).conflicts_with("replay-fixtures"))
+        .arg(replay_fixtures_arg().global(true))
+        .arg(ca_cert_arg().global(true))
         .subcommand(review::clap_subcommand())
         .subcommand(clone::clap_subcommand())
         .subcommand(checkout::clap_subcommand())
@@ -250,6 +575,37 @@ fn make_app<'a>(version: &'a str) -> App<'a, 'a> {
         .subcommand(local::clap_subcommand())
         .subcommand(status::clap_subcommand())
         .subcommand(pull::clap_subcommand())
+        .subcommand(data::clap_subcommand())
+        .subcommand(pin::clap_subcommand())
+        .subcommand(logs::clap_subcommand())
+        .subcommand(support_bundle::clap_subcommand())
+        .subcommand(runner::clap_subcommand())
+        .subcommand(deliver_order::clap_subcommand())
+        .subcommand(report::clap_subcommand())
+        .subcommand(git_credential::clap_subcommand())
+        .subcommand(prompt::clap_subcommand())
+        .subcommand(reconcile::clap_subcommand())
+        .subcommand(queue::clap_subcommand())
+        .subcommand(changelog::clap_subcommand())
+        .subcommand(doctor::clap_subcommand())
+        .subcommand(comment::clap_subcommand())
+        .subcommand(review_verdict::clap_subcommand())
+        .subcommand(features::clap_subcommand())
+        .subcommand(update_build_deps::clap_subcommand())
+        .subcommand(environment::clap_subcommand())
+        .subcommand(refs::clap_subcommand())
+        .subcommand(cache::clap_subcommand())
+        .subcommand(telemetry::clap_subcommand())
+        .subcommand(projects::clap_subcommand())
+        .subcommand(config::clap_subcommand())
+        .subcommand(conformance::clap_subcommand())
+        .subcommand(fixture::clap_subcommand())
+        .subcommand(listen::clap_subcommand())
+        .subcommand(change::clap_subcommand())
+        .subcommand(protect::clap_subcommand())
+        .subcommand(deliver::clap_subcommand())
+        .subcommand(schedule::clap_subcommand())
+        .subcommand(pipeline::clap_subcommand())
 }
 
 fn handle_global_flags(matches: &ArgMatches) {
@@ -260,13 +616,70 @@ fn handle_global_flags(matches: &ArgMatches) {
     if matches.is_present("no-color") {
         say::turn_off_color()
     }
+
+    if matches.is_present("debug") {
+        say::turn_on_debug()
+    }
+
+    if matches.is_present("json") {
+        json_output::turn_on();
+        say::turn_off_spinner()
+    }
+
+    if matches.is_present("strict") {
+        strict::turn_on()
+    }
+
+    if matches.is_present("log-commands") {
+        log_file::logger::turn_on_command_log()
+    }
+
+    if let Some(dir) = matches.value_of("record-fixtures") {
+        fixtures::turn_on_recording(dir)
+    }
+
+    if let Some(dir) = matches.value_of("replay-fixtures") {
+        fixtures::turn_on_replay(dir)
+    }
+
+    if let Some(path) = matches.value_of("ca-cert") {
+        tls::set_ca_cert(path)
+    }
+}
+
+// The `-v` flags count wherever they are given; `--debug` is worth two.
+fn verbosity(app_matches: &ArgMatches) -> u64 {
+    let matches = app_matches.subcommand_matches(command_name(app_matches))
+        .unwrap_or(app_matches);
+    let debug = if matches.is_present("debug") { 2 } else { 0 };
+    matches.occurrences_of("verbose").max(app_matches.occurrences_of("verbose")).max(debug)
 }
 
-fn exit_with(e: DeliveryError, i: ExitCode) {
+fn command_name<'a>(matches: &'a ArgMatches) -> &'a str {
+    matches.subcommand_name().unwrap_or_default()
+}
+
+fn exit_with(e: DeliveryError, i: ExitCode, command: &str) {
+    log_file::record(&format!("error: {} {}", e, e.detail().unwrap_or_default()));
+    if json_output::enabled() {
+        json_output::finish(command, i, Some(&e));
+        process::exit(i)
+    }
     sayln("red", &format!("{}", e));
     if let Some(dtail) = e.detail() {
         sayln("red", &dtail);
     }
+    match e.kind {
+        Kind::ApiError(_, Ok(ref body)) if !body.is_empty() => {
+            if say::debug_enabled() {
+                sayln("white", "Server response:");
+                sayln("white", body);
+            } else {
+                sayln("white", "Run the command again with --debug to see the server response.");
+            }
+        },
+        _ => {}
+    }
     process::exit(i)
 }
 
@@ -275,6 +688,12 @@ fn load_config_and_merge_with_options<T: Options>(opts: &T) -> DeliveryResult<Co
     debug!("Initial config: {:?}", config);
     config = try!(opts.merge_options_and_config(config));
     debug!("Merged config: {:?}", config);
+    if config.strict.unwrap_or(false) {
+        strict::turn_on()
+    }
+    if config.log_commands.unwrap_or(false) {
+        log_file::logger::turn_on_command_log()
+    }
     Ok(config)
 }
 
@@ -291,7 +710,144 @@ fn build_git_sha() -> String {
 #[cfg(test)]
 mod tests {
     use cli;
-    use cli::{api, review, clone, checkout, diff, init, job, spin, token, setup};
+    use cli::Options;
+    use config::Config;
+    use project;
+    use git::DiffMode;
+    use cli::{api, review, clone, checkout, diff, init, job, spin, token, setup, data, pin,
+              logs, support_bundle, runner, deliver_order, report, status, local,
+              git_credential, prompt, reconcile, queue, changelog, doctor, comment,
+              review_verdict, features, update_build_deps, environment, refs, cache, telemetry,
+              projects, config, conformance, fixture, listen, review_action,
+              change, protect, deliver, schedule, pipeline};
+
+    #[test]
+    fn test_clap_listen_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "listen", "--events",
+                                                "change.approved,phase.failed",
+                                                "-x", "./notify.sh", "-p", "frijol"]);
+        let listen_matches = matches.subcommand_matches(listen::SUBCOMMAND_NAME).unwrap();
+        let listen_opts = listen::ListenClapOptions::new(&listen_matches);
+        assert_eq!(listen_opts.events, "change.approved,phase.failed");
+        assert_eq!(listen_opts.exec, "./notify.sh");
+        assert_eq!(listen_opts.project, "frijol");
+        assert!(cli::make_app(&build_version)
+                .get_matches_from_safe(vec!["delivery", "listen"]).is_err());
+    }
+
+    #[test]
+    fn test_clap_json_alias() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        // --json given to the command or before it
+        for args in vec![vec!["delivery", "status", "--json"],
+                         vec!["delivery", "--json", "status"]] {
+            let matches = cli::make_app(&build_version).get_matches_from(args);
+            let status_matches = matches.subcommand_matches(status::SUBCOMMAND_NAME).unwrap();
+            assert!(status::StatusClapOptions::new(&status_matches).json);
+        }
+        for args in vec![vec!["delivery", "features", "--json"],
+                         vec!["delivery", "--json", "features"]] {
+            let matches = cli::make_app(&build_version).get_matches_from(args);
+            let features_matches = matches.subcommand_matches(features::SUBCOMMAND_NAME)
+                .unwrap();
+            assert!(features::FeaturesClapOptions::new(&features_matches).json);
+        }
+        let matches = cli::make_app(&build_version)
+            .get_matches_from(vec!["delivery", "status"]);
+        let status_matches = matches.subcommand_matches(status::SUBCOMMAND_NAME).unwrap();
+        assert!(!status::StatusClapOptions::new(&status_matches).json);
+    }
+
+    #[test]
+    fn test_clap_change_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "change", "abandon",
+                                                "--older-than", "90d", "--pipeline",
+                                                "legacy", "--match", "rob/*", "--yes"]);
+        let change_matches = matches.subcommand_matches(change::SUBCOMMAND_NAME).unwrap();
+        let change_opts = change::ChangeClapOptions::new(&change_matches);
+        assert_eq!(change_opts.action, "abandon");
+        assert_eq!(change_opts.state, "open");
+        assert_eq!(change_opts.older_than, "90d");
+        assert_eq!(change_opts.pipeline, "legacy");
+        assert_eq!(change_opts.topic, "rob/*");
+        assert_eq!(change_opts.yes, true);
+        assert!(cli::make_app(&build_version)
+                .get_matches_from_safe(vec!["delivery", "change", "rebase"]).is_err());
+    }
+
+    #[test]
+    fn test_clap_fixture_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "fixture", "repo", "create",
+                                                "remote", "--dir", "/tmp/scratch"]);
+        let fixture_matches = matches.subcommand_matches(fixture::SUBCOMMAND_NAME).unwrap();
+        let fixture_opts = fixture::FixtureClapOptions::new(&fixture_matches);
+        assert_eq!(fixture_opts.kind, "repo");
+        assert_eq!(fixture_opts.action, "create");
+        assert_eq!(fixture_opts.name, "remote");
+        assert_eq!(fixture_opts.dir, "/tmp/scratch");
+        assert_eq!(fixture_opts.branch, "master");
+    }
+
+    #[test]
+    fn test_clap_conformance_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "conformance", "-e", "foundry",
+                                                "-o", "forge", "-p", "anvil"]);
+        assert_eq!(Some(conformance::SUBCOMMAND_NAME), matches.subcommand_name());
+        let conformance_matches = matches.subcommand_matches(conformance::SUBCOMMAND_NAME).unwrap();
+        let conformance_opts = conformance::ConformanceClapOptions::new(&conformance_matches);
+        assert_eq!(conformance_opts.ent, "foundry");
+        assert_eq!(conformance_opts.org, "forge");
+        assert_eq!(conformance_opts.project, "anvil");
+        assert_eq!(conformance_opts.server, "");
+    }
+
+    #[test]
+    fn test_clap_config_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "config", "--show-origin",
+                                                "-s", "flag.example.com"]);
+        assert_eq!(Some(config::SUBCOMMAND_NAME), matches.subcommand_name());
+        let config_matches = matches.subcommand_matches(config::SUBCOMMAND_NAME).unwrap();
+        let config_opts = config::ConfigClapOptions::new(&config_matches);
+        assert!(config_opts.show_origin);
+        assert_eq!(config_opts.server, "flag.example.com");
+        assert_eq!(config_opts.user, "");
+    }
+
+    #[test]
+    fn test_clap_project_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "project", "delete", "-p", "ring",
+                                                "--force", "--remove-remote", "-o", "shire"]);
+        let project_matches = matches.subcommand_matches(projects::SUBCOMMAND_NAME).unwrap();
+        let project_opts = projects::ProjectClapOptions::new(&project_matches);
+        assert_eq!(project_opts.action, "delete");
+        assert_eq!(project_opts.project, "ring");
+        assert_eq!(project_opts.org, "shire");
+        assert_eq!(project_opts.force, true);
+        assert_eq!(project_opts.remove_remote, true);
+        assert_eq!(project_opts.non_interactive, false);
+    }
+
+    #[test]
+    fn test_clap_telemetry_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "telemetry", "enable"]);
+        let telemetry_matches = matches.subcommand_matches(telemetry::SUBCOMMAND_NAME).unwrap();
+        let telemetry_opts = telemetry::TelemetryClapOptions::new(&telemetry_matches);
+        assert_eq!(telemetry_opts.action, "enable");
+    }
 
     #[test]
     fn test_clap_api_options() {
@@ -300,7 +856,8 @@ mod tests {
         let matches = app.get_matches_from(vec!["delivery", "api", "get", "endpoint",
                                            "--data", "\"name\":\"n\",\"value\":\"d\"",
                                            "-e", "starwars", "-u", "vader", "-s",
-                                           "death-star", "--api-port", "9999"]);
+                                           "death-star", "--api-port", "9999",
+                                           "--filter", "[*].name", "--no-paginate"]);
         assert_eq!(Some("api"), matches.subcommand_name());
         let api_matches = matches.subcommand_matches(api::SUBCOMMAND_NAME).unwrap();
         let api_opts = api::ApiClapOptions::new(&api_matches);
@@ -311,6 +868,8 @@ mod tests {
         assert_eq!(api_opts.api_port, "9999");
         assert_eq!(api_opts.ent, "starwars");
         assert_eq!(api_opts.user, "vader");
+        assert_eq!(api_opts.filter, "[*].name");
+        assert_eq!(api_opts.no_paginate, true);
     }
 
     #[test]
@@ -318,7 +877,9 @@ mod tests {
         let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
         let app = cli::make_app(&build_version);
         let matches = app.get_matches_from(vec!["delivery", "review", "--auto-bump",
-                                           "--no-open", "--edit", "-f", "custom-pipe"]);
+                                           "--no-open", "--edit", "--reuse", "-f", "custom-pipe",
+                                           "--skip-secret-scan", "--unshallow",
+                                           "--i-know-what-im-doing", "--for-group", "release"]);
         assert_eq!(Some("review"), matches.subcommand_name());
         let review_matches = matches.subcommand_matches(review::SUBCOMMAND_NAME).unwrap();
         let review_opts = review::ReviewClapOptions::new(&review_matches);
@@ -326,6 +887,11 @@ mod tests {
         assert_eq!(review_opts.no_open, true);
         assert_eq!(review_opts.auto_bump, true);
         assert_eq!(review_opts.edit, true);
+        assert_eq!(review_opts.reuse, true);
+        assert_eq!(review_opts.skip_secret_scan, true);
+        assert_eq!(review_opts.unshallow, true);
+        assert_eq!(review_opts.i_know_what_im_doing, true);
+        assert_eq!(review_opts.for_group, "release");
     }
 
     #[test]
@@ -340,6 +906,17 @@ mod tests {
         assert_eq!(checkout_opts.pipeline, "custom-pipe");
         assert_eq!(checkout_opts.change, "change_the_force");
         assert_eq!(checkout_opts.patchset, "p4tchs3t");
+        assert_eq!(checkout_opts.i_know_what_im_doing, false);
+        assert_eq!(checkout_opts.list, false);
+
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "checkout", "--list",
+                                                "--format", "{{.topic}}"]);
+        let checkout_matches = matches.subcommand_matches(checkout::SUBCOMMAND_NAME).unwrap();
+        let checkout_opts = checkout::CheckoutClapOptions::new(&checkout_matches);
+        assert_eq!(checkout_opts.change, "");
+        assert_eq!(checkout_opts.list, true);
+        assert_eq!(checkout_opts.format, "{{.topic}}");
     }
 
     #[test]
@@ -366,7 +943,8 @@ mod tests {
         let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
         let app = cli::make_app(&build_version);
         let matches = app.get_matches_from(vec!["delivery", "diff", "change-me", "-l",
-                                           "-P", "p4tchs3t", "-f", "coolest"]);
+                                           "-P", "p4tchs3t", "-f", "coolest",
+                                           "--interdiff", "2", "--fix-remote"]);
         assert_eq!(Some("diff"), matches.subcommand_name());
         let diff_matches = matches.subcommand_matches(diff::SUBCOMMAND_NAME).unwrap();
         let diff_opts = diff::DiffClapOptions::new(&diff_matches);
@@ -374,6 +952,35 @@ mod tests {
         assert_eq!(diff_opts.patchset, "p4tchs3t");
         assert_eq!(diff_opts.pipeline, "coolest");
         assert_eq!(diff_opts.local, true);
+        assert_eq!(diff_opts.interdiff, "2");
+        assert_eq!(diff_opts.fix_remote, true);
+        assert_eq!(diff_opts.mode(), DiffMode::Full);
+        assert_eq!(diff_opts.base_patchset(), "2");
+        assert_eq!(diff_opts.target_patchset(), "p4tchs3t");
+
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "diff", "change-me", "--name-only"]);
+        let diff_matches = matches.subcommand_matches(diff::SUBCOMMAND_NAME).unwrap();
+        let diff_opts = diff::DiffClapOptions::new(&diff_matches);
+        assert_eq!(diff_opts.mode(), DiffMode::NameOnly);
+
+        let app = cli::make_app(&build_version);
+        let result = app.get_matches_from_safe(vec!["delivery", "diff", "change-me",
+                                                    "--stat", "--word-diff"]);
+        assert!(result.is_err());
+
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "diff", "change-me",
+                                                "--from-patchset", "2", "--to-patchset", "4"]);
+        let diff_matches = matches.subcommand_matches(diff::SUBCOMMAND_NAME).unwrap();
+        let diff_opts = diff::DiffClapOptions::new(&diff_matches);
+        assert_eq!(diff_opts.base_patchset(), "2");
+        assert_eq!(diff_opts.target_patchset(), "4");
+
+        let app = cli::make_app(&build_version);
+        let result = app.get_matches_from_safe(vec!["delivery", "diff", "change-me",
+                                                    "--to-patchset", "4"]);
+        assert!(result.is_err());
     }
 
     #[test]
@@ -382,9 +989,13 @@ mod tests {
         let app = cli::make_app(&build_version);
         let init_cmd = vec!["delivery", "init", "-l", "-p", "frijol", "-u", "concha",
                         "-s", "cocina.central.com", "-e", "mexicana", "-o", "oaxaca",
-                        "-f", "postres", "-c", "receta.json", "--generator", "/original",
+                        "-f", "postres", "-c", "receta.json", "--config-dir", "/recetario", "--generator", "/original",
+                        "--generator-refresh", "--pipeline-template", "/plantilla",
                         "--github", "git-mx", "--bitbucket", "bit-mx", "-r", "antojitos",
-                        "--no-verify-ssl", "--skip-build-cookbook", "-n"];
+                        "--upstream-remote", "upstream",
+                        "--no-verify-ssl", "--skip-build-cookbook", "--readme-badge", "-n",
+                        "--no-local-repo", "--unshallow", "--no-rollback", "--dry-run",
+                        "--force-push", "--adopt", "--interactive", "--no-detect-scp"];
         let matches = app.get_matches_from(init_cmd);
         assert_eq!(Some("init"), matches.subcommand_name());
         let init_matches = matches.subcommand_matches(init::SUBCOMMAND_NAME).unwrap();
@@ -396,14 +1007,29 @@ mod tests {
         assert_eq!(init_opts.org, "oaxaca");
         assert_eq!(init_opts.project, "frijol");
         assert_eq!(init_opts.config_json, "receta.json");
+        assert_eq!(init_opts.config_dir, "/recetario");
         assert_eq!(init_opts.generator, "/original");
+        assert_eq!(init_opts.generator_refresh, true);
+        assert_eq!(init_opts.pipeline_template, "/plantilla");
         assert_eq!(init_opts.github_org_name, "git-mx");
         assert_eq!(init_opts.bitbucket_project_key, "bit-mx");
         assert_eq!(init_opts.repo_name, "antojitos");
+        assert_eq!(init_opts.upstream_remote, "upstream");
         assert_eq!(init_opts.no_v_ssl, true);
         assert_eq!(init_opts.no_open, true);
         assert_eq!(init_opts.skip_build_cookbook, true);
+        assert_eq!(init_opts.readme_badge, true);
         assert_eq!(init_opts.local, true);
+        assert_eq!(init_opts.no_local_repo, true);
+        assert_eq!(init_opts.unshallow, true);
+        assert_eq!(init_opts.no_rollback, true);
+        assert_eq!(init_opts.dry_run, true);
+        assert_eq!(init_opts.interactive, true);
+        assert_eq!(init_opts.no_detect_scp, true);
+        assert_eq!(init_opts.force_push, true);
+        assert_eq!(init_opts.adopt, true);
+        assert_eq!(init_opts.replace_history, false);
+        assert_eq!(init_opts.takeover().unwrap(), project::Takeover::Adopt);
     }
 
     #[test]
@@ -414,7 +1040,8 @@ mod tests {
                         "-u", "naruto", "-s", "manga.com", "-e", "shippuden", "-o",
                         "akatsuki", "-f", "sharingan", "-j", "/path", "-p", "uchiha",
                         "-P", "latest", "--change-id", "super-cool-id", "-g", "powerful-url",
-                        "-S", "SHA", "-b", "evil", "--skip-default", "-l", "--docker", "uzumaki"];
+                        "-S", "SHA", "-b", "evil", "--skip-default", "-l", "--docker", "uzumaki",
+                        "--isolate"];
         let matches = app.get_matches_from(job_cmd);
         assert_eq!(Some("job"), matches.subcommand_name());
         let job_matches = matches.subcommand_matches(job::SUBCOMMAND_NAME).unwrap();
@@ -437,6 +1064,8 @@ mod tests {
         assert_eq!(job_opts.docker_image, "uzumaki");
         assert_eq!(job_opts.local, true);
         assert_eq!(job_opts.skip_default, true);
+        assert_eq!(job_opts.isolate, true);
+        assert_eq!(job_opts.keep_workspace, false);
     }
 
     #[test]
@@ -465,9 +1094,36 @@ mod tests {
         assert_eq!(token_opts.ent, "fellowship");
         assert_eq!(token_opts.user, "gandalf");
         assert_eq!(token_opts.verify, true);
+        assert_eq!(token_opts.renew, false);
         assert_eq!(token_opts.saml, Some(true));
     }
 
+    #[test]
+    fn test_clap_token_renew_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "token", "--renew"]);
+        let token_matches = matches.subcommand_matches(token::SUBCOMMAND_NAME).unwrap();
+        let token_opts = token::TokenClapOptions::new(&token_matches);
+        assert!(token_opts.renew);
+        assert!(!token_opts.issue());
+    }
+
+    #[test]
+    fn test_clap_token_issue_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "token", "issue",
+                                           "--user", "builder1, builder2", "--ttl", "30d",
+                                           "--out-dir", "/srv/tokens"]);
+        let token_matches = matches.subcommand_matches(token::SUBCOMMAND_NAME).unwrap();
+        let token_opts = token::TokenClapOptions::new(&token_matches);
+        assert!(token_opts.issue());
+        assert_eq!(vec!["builder1", "builder2"], token_opts.issue_users());
+        assert_eq!(token_opts.ttl, "30d");
+        assert_eq!(token_opts.out_dir, "/srv/tokens");
+    }
+
     #[test]
     fn test_clap_setup_options() {
         let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
@@ -485,4 +1141,530 @@ mod tests {
         assert_eq!(setup_opts.pipeline, "p");
         assert_eq!(setup_opts.path, "/my/config/cli.toml");
     }
+
+    #[test]
+    fn test_clap_data_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "data", "set", "version",
+                                           "{\"version\":\"1.0.0\"}", "--change",
+                                           "super-cool-id", "-p", "starship", "-f",
+                                           "warp"]);
+        assert_eq!(Some("data"), matches.subcommand_name());
+        let data_matches = matches.subcommand_matches(data::SUBCOMMAND_NAME).unwrap();
+        let data_opts = data::DataClapOptions::new(&data_matches);
+        assert_eq!(data_opts.action, "set");
+        assert_eq!(data_opts.key, "version");
+        assert_eq!(data_opts.value, "{\"version\":\"1.0.0\"}");
+        assert_eq!(data_opts.change, "super-cool-id");
+        assert_eq!(data_opts.project, "starship");
+        assert_eq!(data_opts.pipeline, "warp");
+    }
+
+    #[test]
+    fn test_clap_pin_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "pin", "fetch",
+                                           "--server-url", "https://a2.example.com:8443/delivery",
+                                           "--config-path", "/my/config"]);
+        assert_eq!(Some("pin"), matches.subcommand_name());
+        let pin_matches = matches.subcommand_matches(pin::SUBCOMMAND_NAME).unwrap();
+        let pin_opts = pin::PinClapOptions::new(&pin_matches);
+        assert_eq!(pin_opts.action, "fetch");
+        assert_eq!(pin_opts.server_url, "https://a2.example.com:8443/delivery");
+        assert_eq!(pin_opts.path, "/my/config");
+    }
+
+    #[test]
+    fn test_clap_logs_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "logs-self", "--tail", "20", "-f"]);
+        assert_eq!(Some("logs-self"), matches.subcommand_name());
+        let logs_matches = matches.subcommand_matches(logs::SUBCOMMAND_NAME).unwrap();
+        let logs_opts = logs::LogsClapOptions::new(&logs_matches);
+        assert_eq!(logs_opts.tail, 20);
+        assert_eq!(logs_opts.follow, true);
+    }
+
+    #[test]
+    fn test_clap_support_bundle_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "support-bundle",
+                                           "-o", "bundle.tar.gz"]);
+        assert_eq!(Some("support-bundle"), matches.subcommand_name());
+        let bundle_matches = matches.subcommand_matches(support_bundle::SUBCOMMAND_NAME).unwrap();
+        let bundle_opts = support_bundle::SupportBundleClapOptions::new(&bundle_matches);
+        assert_eq!(bundle_opts.output, "bundle.tar.gz");
+    }
+
+    #[test]
+    fn test_clap_runner_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "runner", "register",
+                                           "runner-1.example.com", "--os", "linux",
+                                           "--platform", "ubuntu", "--platform-version",
+                                           "16.04", "-e", "ent"]);
+        assert_eq!(Some("runner"), matches.subcommand_name());
+        let runner_matches = matches.subcommand_matches(runner::SUBCOMMAND_NAME).unwrap();
+        let runner_opts = runner::RunnerClapOptions::new(&runner_matches);
+        assert_eq!(runner_opts.action, "register");
+        assert_eq!(runner_opts.hostname, "runner-1.example.com");
+        assert_eq!(runner_opts.os, "linux");
+        assert_eq!(runner_opts.platform, "ubuntu");
+        assert_eq!(runner_opts.platform_version, "16.04");
+        assert_eq!(runner_opts.ent, "ent");
+    }
+
+    #[test]
+    fn test_clap_deliver_order_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "deliver-order",
+                                           "--changes", "abc,api:def", "--execute",
+                                           "-p", "web", "--concurrency", "2"]);
+        assert_eq!(Some("deliver-order"), matches.subcommand_name());
+        let order_matches = matches.subcommand_matches(deliver_order::SUBCOMMAND_NAME).unwrap();
+        let order_opts = deliver_order::DeliverOrderClapOptions::new(&order_matches);
+        assert_eq!(order_opts.changes, "abc,api:def");
+        assert_eq!(order_opts.execute, true);
+        assert_eq!(order_opts.project, "web");
+        assert_eq!(order_opts.concurrency, "2");
+    }
+
+    #[test]
+    fn test_clap_report_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "report", "junit", "results.xml",
+                                           "--change", "abc-123", "--phase", "syntax"]);
+        assert_eq!(Some("report"), matches.subcommand_name());
+        let report_matches = matches.subcommand_matches(report::SUBCOMMAND_NAME).unwrap();
+        let report_opts = report::ReportClapOptions::new(&report_matches);
+        assert_eq!(report_opts.format, "junit");
+        assert_eq!(report_opts.file, "results.xml");
+        assert_eq!(report_opts.change, "abc-123");
+        assert_eq!(report_opts.phase, "syntax");
+    }
+
+    #[test]
+    fn test_clap_status_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "status", "--for", "acceptance",
+                                           "--merge-check"]);
+        assert_eq!(Some("status"), matches.subcommand_name());
+        let status_matches = matches.subcommand_matches(status::SUBCOMMAND_NAME).unwrap();
+        let status_opts = status::StatusClapOptions::new(&status_matches);
+        assert_eq!(status_opts.pipeline, "acceptance");
+        assert_eq!(status_opts.merge_check, true);
+        assert_eq!(status_opts.json, false);
+        assert_eq!(status_opts.watch, "");
+
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "status", "--watch", "my-feature",
+                                           "--until", "verify"]);
+        let status_matches = matches.subcommand_matches(status::SUBCOMMAND_NAME).unwrap();
+        let status_opts = status::StatusClapOptions::new(&status_matches);
+        assert_eq!(status_opts.watch, "my-feature");
+        assert_eq!(status_opts.until, "verify");
+        assert_eq!(status_opts.merge_check, false);
+    }
+
+    #[test]
+    fn test_clap_status_project_ref() {
+        use cli::Options;
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "status", "-p", "other/beans/frijol"]);
+        let status_matches = matches.subcommand_matches(status::SUBCOMMAND_NAME).unwrap();
+        let status_opts = status::StatusClapOptions::new(&status_matches);
+        let merged = status_opts.merge_options_and_config(::config::Config::default()).unwrap();
+        assert_eq!(Some("other".to_string()), merged.enterprise);
+        assert_eq!(Some("beans".to_string()), merged.organization);
+        assert_eq!(Some("frijol".to_string()), merged.project);
+    }
+
+    #[test]
+    fn test_clap_local_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "local", "unit", "--remote-config"]);
+        assert_eq!(Some("local"), matches.subcommand_name());
+        let local_matches = matches.subcommand_matches(local::SUBCOMMAND_NAME).unwrap();
+        let local_opts = local::LocalClapOptions::new(&local_matches);
+        assert_eq!(local_opts.remote_config, true);
+        assert_eq!(local_opts.pipeline, "");
+        assert_eq!(local_opts.remote_toml, None);
+        assert_eq!(local_opts.keep_going, false);
+    }
+
+    #[test]
+    fn test_clap_local_keep_going_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "local", "verify", "-k"]);
+        let local_matches = matches.subcommand_matches(local::SUBCOMMAND_NAME).unwrap();
+        let local_opts = local::LocalClapOptions::new(&local_matches);
+        assert!(local_opts.keep_going);
+        assert!(local_opts.phase.is_none());
+        assert_eq!(local_opts.stage_phase, "verify");
+        assert_eq!(local_opts.matrix, "");
+    }
+
+    #[test]
+    fn test_clap_local_matrix_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "local", "unit",
+                                                "--matrix", "os=ubuntu,centos"]);
+        let local_matches = matches.subcommand_matches(local::SUBCOMMAND_NAME).unwrap();
+        let local_opts = local::LocalClapOptions::new(&local_matches);
+        assert_eq!(local_opts.matrix, "os=ubuntu,centos");
+        assert_eq!(local_opts.stage_phase, "unit");
+    }
+
+    #[test]
+    fn test_clap_local_pipeline_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "local", "unit", "--for", "release"]);
+        let local_matches = matches.subcommand_matches(local::SUBCOMMAND_NAME).unwrap();
+        let local_opts = local::LocalClapOptions::new(&local_matches);
+        assert_eq!(local_opts.pipeline, "release");
+        let config = local_opts.merge_options_and_config(Config::default()).unwrap();
+        assert_eq!("release", config.pipeline().unwrap());
+        let from_toml = local::LocalClapOptions::default()
+            .merge_options_and_config(Config::default().set_pipeline("stable")).unwrap();
+        assert_eq!("stable", from_toml.pipeline().unwrap());
+    }
+
+    #[test]
+    fn test_clap_git_credential_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "git-credential", "get"]);
+        assert_eq!(Some("git-credential"), matches.subcommand_name());
+        let credential_matches = matches.subcommand_matches(git_credential::SUBCOMMAND_NAME)
+            .unwrap();
+        let credential_opts = git_credential::GitCredentialClapOptions::new(&credential_matches);
+        assert_eq!(credential_opts.operation, "get");
+    }
+
+    #[test]
+    fn test_clap_prompt_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "prompt", "--max-age", "300"]);
+        assert_eq!(Some("prompt"), matches.subcommand_name());
+        let prompt_matches = matches.subcommand_matches(prompt::SUBCOMMAND_NAME).unwrap();
+        let prompt_opts = prompt::PromptClapOptions::new(&prompt_matches);
+        assert_eq!(prompt_opts.max_age, 300);
+        assert_eq!(prompt_opts.refresh, false);
+    }
+
+    #[test]
+    fn test_clap_reconcile_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "reconcile", "--dry-run", "--prune",
+                                           "--file", "pipelines.toml", "-p", "web"]);
+        assert_eq!(Some("reconcile"), matches.subcommand_name());
+        let reconcile_matches = matches.subcommand_matches(reconcile::SUBCOMMAND_NAME).unwrap();
+        let reconcile_opts = reconcile::ReconcileClapOptions::new(&reconcile_matches);
+        assert_eq!(reconcile_opts.dry_run, true);
+        assert_eq!(reconcile_opts.prune, true);
+        assert_eq!(reconcile_opts.file, "pipelines.toml");
+        assert_eq!(reconcile_opts.project, "web");
+    }
+
+    #[test]
+    fn test_clap_queue_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "queue", "--pipeline", "release",
+                                           "--projects", "web,api",
+                                           "--format", "template={{.change.id}}"]);
+        assert_eq!(Some("queue"), matches.subcommand_name());
+        let queue_matches = matches.subcommand_matches(queue::SUBCOMMAND_NAME).unwrap();
+        let queue_opts = queue::QueueClapOptions::new(&queue_matches);
+        assert_eq!(queue_opts.pipeline, "release");
+        assert_eq!(queue_opts.projects, "web,api");
+        assert_eq!(queue_opts.format, "template={{.change.id}}");
+    }
+
+    #[test]
+    fn test_clap_changelog_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "changelog", "--pipeline", "master",
+                                           "--from", "v1.2.0"]);
+        assert_eq!(Some("changelog"), matches.subcommand_name());
+        let changelog_matches = matches.subcommand_matches(changelog::SUBCOMMAND_NAME).unwrap();
+        let changelog_opts = changelog::ChangelogClapOptions::new(&changelog_matches);
+        assert_eq!(changelog_opts.pipeline, "master");
+        assert_eq!(changelog_opts.from, "v1.2.0");
+        assert_eq!(changelog_opts.to, "HEAD");
+    }
+
+    #[test]
+    fn test_clap_doctor_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "doctor", "-u", "frodo",
+                                           "-e", "shire", "-s", "bag.end"]);
+        assert_eq!(Some("doctor"), matches.subcommand_name());
+        let doctor_matches = matches.subcommand_matches(doctor::SUBCOMMAND_NAME).unwrap();
+        let doctor_opts = doctor::DoctorClapOptions::new(&doctor_matches);
+        assert_eq!(doctor_opts.user, "frodo");
+        assert_eq!(doctor_opts.ent, "shire");
+        assert_eq!(doctor_opts.server, "bag.end");
+    }
+
+    #[test]
+    fn test_clap_comment_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "comment", "resolve", "1234",
+                                           "42", "--project", "ring"]);
+        assert_eq!(Some("comment"), matches.subcommand_name());
+        let comment_matches = matches.subcommand_matches(comment::SUBCOMMAND_NAME).unwrap();
+        let comment_opts = comment::CommentClapOptions::new(&comment_matches);
+        assert_eq!(comment_opts.action, "resolve");
+        assert_eq!(comment_opts.change, "1234");
+        assert_eq!(comment_opts.comment_id, "42");
+        assert_eq!(comment_opts.message, "");
+        assert_eq!(comment_opts.project, "ring");
+    }
+
+    #[test]
+    fn test_clap_review_verdict_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "review-verdict", "1234",
+                                           "--request-changes", "-m", "Needs tests"]);
+        assert_eq!(Some("review-verdict"), matches.subcommand_name());
+        let verdict_matches = matches.subcommand_matches(review_verdict::SUBCOMMAND_NAME)
+            .unwrap();
+        let verdict_opts = review_verdict::ReviewVerdictClapOptions::new(&verdict_matches);
+        assert_eq!(verdict_opts.change, "1234");
+        assert_eq!(verdict_opts.approve, false);
+        assert_eq!(verdict_opts.request_changes, true);
+        assert_eq!(verdict_opts.message, "Needs tests");
+
+        let app = cli::make_app(&build_version);
+        assert!(app.get_matches_from_safe(vec!["delivery", "review-verdict", "1234",
+                                               "--approve", "--request-changes"]).is_err());
+    }
+
+    #[test]
+    fn test_clap_protect_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "protege", "sync", "--for", "release",
+                                           "--status-check", "ci/delivery", "--dry-run"]);
+        assert_eq!(Some("protect"), matches.subcommand_name());
+        let protect_matches = matches.subcommand_matches(protect::SUBCOMMAND_NAME).unwrap();
+        let protect_opts = protect::ProtectClapOptions::new(&protect_matches);
+        assert_eq!(protect_opts.action, "sync");
+        assert_eq!(protect_opts.pipeline, "release");
+        assert_eq!(protect_opts.status_check, "ci/delivery");
+        assert_eq!(protect_opts.api_url, "");
+        assert!(protect_opts.dry_run);
+    }
+
+    #[test]
+    fn test_clap_deliver_and_schedule_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "deliver", "c1", "--at",
+                                           "2024-06-01T22:00Z", "--window", "weeknights"]);
+        let deliver_matches = matches.subcommand_matches(deliver::SUBCOMMAND_NAME).unwrap();
+        let deliver_opts = deliver::DeliverClapOptions::new(&deliver_matches);
+        assert_eq!(deliver_opts.change, "c1");
+        assert_eq!(deliver_opts.at, "2024-06-01T22:00Z");
+        assert_eq!(deliver_opts.window, "weeknights");
+
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "schedule", "run", "--once"]);
+        let schedule_matches = matches.subcommand_matches(schedule::SUBCOMMAND_NAME).unwrap();
+        let schedule_opts = schedule::ScheduleClapOptions::new(&schedule_matches);
+        assert_eq!(schedule_opts.action, "run");
+        assert_eq!(schedule_opts.change, "");
+        assert!(schedule_opts.once);
+
+        let app = cli::make_app(&build_version);
+        assert!(app.get_matches_from_safe(vec!["delivery", "schedule", "pause"]).is_err());
+    }
+
+    #[test]
+    fn test_clap_pipeline_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "pipeline", "rename", "master",
+                                           "main", "--force", "-p", "ring"]);
+        let pipeline_matches = matches.subcommand_matches(pipeline::SUBCOMMAND_NAME).unwrap();
+        let pipeline_opts = pipeline::PipelineClapOptions::new(&pipeline_matches);
+        assert_eq!(pipeline_opts.action, "rename");
+        assert_eq!(pipeline_opts.name, "master");
+        assert_eq!(pipeline_opts.new_name, "main");
+        assert_eq!(pipeline_opts.project, "ring");
+        assert!(pipeline_opts.force);
+
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "pipeline", "list"]);
+        let pipeline_matches = matches.subcommand_matches(pipeline::SUBCOMMAND_NAME).unwrap();
+        let pipeline_opts = pipeline::PipelineClapOptions::new(&pipeline_matches);
+        assert_eq!(pipeline_opts.name, "");
+        assert!(!pipeline_opts.force);
+    }
+
+    #[test]
+    fn test_clap_review_action_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "review", "comment", "1234",
+                                           "-m", "Looks good", "--project", "ring"]);
+        let review_matches = matches.subcommand_matches(review::SUBCOMMAND_NAME).unwrap();
+        let (action, action_matches) = review_matches.subcommand();
+        let opts = review_action::ReviewActionClapOptions::new(action, action_matches.unwrap());
+        assert_eq!(opts.action, "comment");
+        assert_eq!(opts.change, "1234");
+        assert_eq!(opts.message, "Looks good");
+        assert_eq!(opts.project, "ring");
+
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "review", "approve", "1234"]);
+        let review_matches = matches.subcommand_matches(review::SUBCOMMAND_NAME).unwrap();
+        let (action, action_matches) = review_matches.subcommand();
+        let opts = review_action::ReviewActionClapOptions::new(action, action_matches.unwrap());
+        assert_eq!(opts.action, "approve");
+        assert_eq!(opts.message, "");
+
+        let app = cli::make_app(&build_version);
+        assert!(app.get_matches_from_safe(vec!["delivery", "review", "comment", "1234"])
+                .is_err());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "review", "--no-open"]);
+        let review_matches = matches.subcommand_matches(review::SUBCOMMAND_NAME).unwrap();
+        assert_eq!(None, review_matches.subcommand_name());
+    }
+
+    #[test]
+    fn test_clap_features_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "features", "--refresh",
+                                           "-e", "shire", "-s", "bag.end"]);
+        assert_eq!(Some("features"), matches.subcommand_name());
+        let features_matches = matches.subcommand_matches(features::SUBCOMMAND_NAME).unwrap();
+        let features_opts = features::FeaturesClapOptions::new(&features_matches);
+        assert_eq!(features_opts.refresh, true);
+        assert_eq!(features_opts.json, false);
+        assert_eq!(features_opts.ent, "shire");
+        assert_eq!(features_opts.server, "bag.end");
+    }
+
+    #[test]
+    fn test_clap_update_build_deps_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "update-build-deps", "--check",
+                                           "--no-open", "--local", "-f", "cocina"]);
+        assert_eq!(Some("update-build-deps"), matches.subcommand_name());
+        let deps_matches = matches.subcommand_matches(update_build_deps::SUBCOMMAND_NAME).unwrap();
+        let deps_opts = update_build_deps::UpdateBuildDepsClapOptions::new(&deps_matches);
+        assert_eq!(deps_opts.pipeline, "cocina");
+        assert_eq!(deps_opts.check, true);
+        assert_eq!(deps_opts.no_open, true);
+        assert_eq!(deps_opts.local, true);
+    }
+
+    #[test]
+    fn test_clap_env_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "env", "diff",
+                                           "-f", "cocina", "-o", "frijol"]);
+        assert_eq!(Some("env"), matches.subcommand_name());
+        let env_matches = matches.subcommand_matches(environment::SUBCOMMAND_NAME).unwrap();
+        let env_opts = environment::EnvClapOptions::new(&env_matches);
+        assert_eq!(env_opts.action, "diff");
+        assert_eq!(env_opts.from, "acceptance");
+        assert_eq!(env_opts.to, "union");
+        assert_eq!(env_opts.pipeline, "cocina");
+        assert_eq!(env_opts.org, "frijol");
+
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "env", "diff",
+                                           "union", "rehearsal"]);
+        let env_matches = matches.subcommand_matches(environment::SUBCOMMAND_NAME).unwrap();
+        let env_opts = environment::EnvClapOptions::new(&env_matches);
+        assert_eq!(env_opts.from, "union");
+        assert_eq!(env_opts.to, "rehearsal");
+
+        let app = cli::make_app(&build_version);
+        assert!(app.get_matches_from_safe(vec!["delivery", "env", "diff", "verify"]).is_err());
+    }
+
+    #[test]
+    fn test_clap_refs_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "refs", "prune", "--all"]);
+        assert_eq!(Some("refs"), matches.subcommand_name());
+        let refs_matches = matches.subcommand_matches(refs::SUBCOMMAND_NAME).unwrap();
+        let refs_opts = refs::RefsClapOptions::new(&refs_matches);
+        assert_eq!(refs_opts.action, "prune");
+        assert_eq!(refs_opts.all, true);
+    }
+
+    #[test]
+    fn test_clap_cache_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "cache", "clean", "--all"]);
+        assert_eq!(Some("cache"), matches.subcommand_name());
+        let cache_matches = matches.subcommand_matches(cache::SUBCOMMAND_NAME).unwrap();
+        let cache_opts = cache::CacheClapOptions::new(&cache_matches);
+        assert_eq!(cache_opts.action, "clean");
+        assert_eq!(cache_opts.all, true);
+    }
+
+    #[test]
+    fn test_fixtures_flags() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let matches = cli::make_app(&build_version)
+            .get_matches_from(vec!["delivery", "review", "--record-fixtures", "/tmp/bug-42"]);
+        let review_matches = matches.subcommand_matches(review::SUBCOMMAND_NAME).unwrap();
+        assert_eq!(Some("/tmp/bug-42"), review_matches.value_of("record-fixtures"));
+        assert!(cli::make_app(&build_version)
+                .get_matches_from_safe(vec!["delivery", "review", "--record-fixtures", "a",
+                                            "--replay-fixtures", "b"]).is_err());
+    }
+
+    #[test]
+    fn test_ca_cert_flag() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let matches = cli::make_app(&build_version)
+            .get_matches_from(vec!["delivery", "init", "--ca-cert", "/etc/ssl/internal-ca.pem"]);
+        let init_matches = matches.subcommand_matches(init::SUBCOMMAND_NAME).unwrap();
+        assert_eq!(Some("/etc/ssl/internal-ca.pem"), init_matches.value_of("ca-cert"));
+    }
+
+    #[test]
+    fn test_verbosity() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let verbosity = |args: Vec<&str>| {
+            cli::verbosity(&cli::make_app(&build_version).get_matches_from(args))
+        };
+        assert_eq!(0, verbosity(vec!["delivery", "review"]));
+        assert_eq!(1, verbosity(vec!["delivery", "review", "-v"]));
+        assert_eq!(2, verbosity(vec!["delivery", "review", "-vv"]));
+        assert_eq!(2, verbosity(vec!["delivery", "review", "--debug", "-v"]));
+        assert_eq!(3, verbosity(vec!["delivery", "review", "--verbose", "-vv"]));
+    }
 }
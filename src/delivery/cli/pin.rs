@@ -0,0 +1,74 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{api_port_arg, config_path_arg, server_arg, server_url_arg, value_of};
+use clap::{Arg, App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+
+pub const SUBCOMMAND_NAME: &'static str = "pin";
+
+#[derive(Debug)]
+pub struct PinClapOptions<'n> {
+    pub action: &'n str,
+    pub server: &'n str,
+    pub server_url: &'n str,
+    pub api_port: &'n str,
+    pub path: &'n str,
+}
+
+impl<'n> Default for PinClapOptions<'n> {
+    fn default() -> Self {
+        PinClapOptions {
+            action: "",
+            server: "",
+            server_url: "",
+            api_port: "",
+            path: "",
+        }
+    }
+}
+
+impl<'n> PinClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        PinClapOptions {
+            action: value_of(&matches, "action"),
+            server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
+            api_port: value_of(&matches, "api-port"),
+            path: value_of(&matches, "config-path"),
+        }
+    }
+}
+
+impl<'n> Options for PinClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let new_config = config.set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_api_port(&self.api_port);
+        Ok(new_config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Manage the certificate pins of the Automate server")
+        .arg(Arg::from_usage("<action> 'fetch: record the pin of the certificate \
+                              the server currently presents'")
+             .possible_values(&["fetch"]))
+        .args(&vec![server_arg(), server_url_arg(), api_port_arg(), config_path_arg()])
+}
@@ -0,0 +1,71 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use project;
+use cli::arguments::{api_port_arg, project_arg, u_e_s_o_args};
+use clap::{Arg, App, SubCommand};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+
+pub const SUBCOMMAND_NAME: &'static str = "pipeline";
+
+clap_options!(PipelineClapOptions {
+    action: value("action"),
+    name: value("name"),
+    new_name: value("new-name"),
+    project: value("project"),
+    force: flag("force"),
+    non_interactive: flag("non-interactive"),
+    user: value("user"),
+    server: value("server"),
+    server_url: value("server-url"),
+    api_port: value("api-port"),
+    ent: value("ent"),
+    org: value("org"),
+});
+
+impl<'n> Options for PipelineClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let mut new_config = config.set_user(&self.user)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_api_port(&self.api_port)
+            .set_enterprise(&self.ent)
+            .set_organization(&self.org)
+            .set_project(&self.project);
+
+        if new_config.project.is_none() {
+            new_config.project = project::project_from_cwd().ok();
+        }
+        Ok(new_config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Manage the pipelines of the project on the server")
+        .arg(Arg::from_usage("<action> 'list: the pipelines of the project; delete: remove \
+                              <name> and its changes; rename: rename <name> to <new-name>'")
+             .possible_values(&["list", "delete", "rename"]))
+        .args_from_usage(
+            "[name] 'The pipeline to delete or rename'
+            [new-name] 'The new name of the pipeline'
+            --force 'Do not ask for a confirmation'")
+        .args(&vec![project_arg(), api_port_arg()])
+        .args(&u_e_s_o_args())
+}
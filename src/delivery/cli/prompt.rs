@@ -0,0 +1,66 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::value_of;
+use clap::{Arg, App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+use prompt::DEFAULT_MAX_AGE;
+
+pub const SUBCOMMAND_NAME: &'static str = "prompt";
+
+#[derive(Debug)]
+pub struct PromptClapOptions {
+    pub max_age: i64,
+    pub refresh: bool,
+}
+
+impl Default for PromptClapOptions {
+    fn default() -> Self {
+        PromptClapOptions {
+            max_age: DEFAULT_MAX_AGE,
+            refresh: false,
+        }
+    }
+}
+
+impl PromptClapOptions {
+    pub fn new(matches: &ArgMatches) -> Self {
+        let max_age = value_of(&matches, "max-age").parse::<i64>()
+            .unwrap_or(DEFAULT_MAX_AGE);
+        PromptClapOptions {
+            max_age: max_age,
+            refresh: matches.is_present("refresh"),
+        }
+    }
+}
+
+impl Options for PromptClapOptions {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        Ok(config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Print a short summary of the change of the current branch \
+                for shell prompts (i.e. 'verify✔ ●2'), from a local cache")
+        .args_from_usage("--max-age=[seconds] 'Refresh the cache in the background when \
+                          it is older than this. default:60'")
+        .arg(Arg::from_usage("--refresh 'Fetch the state of the change and update the cache'")
+             .hidden(true))
+}
@@ -0,0 +1,112 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{api_port_arg, project_arg, u_e_s_o_args, value_of};
+use clap::{Arg, App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+use project;
+
+pub const SUBCOMMAND_NAME: &'static str = "protect";
+
+#[derive(Debug)]
+pub struct ProtectClapOptions<'n> {
+    pub action: &'n str,
+    pub pipeline: &'n str,
+    pub status_check: &'n str,
+    pub api_url: &'n str,
+    pub dry_run: bool,
+    pub project: &'n str,
+    pub user: &'n str,
+    pub server: &'n str,
+    pub server_url: &'n str,
+    pub api_port: &'n str,
+    pub ent: &'n str,
+    pub org: &'n str,
+}
+
+impl<'n> Default for ProtectClapOptions<'n> {
+    fn default() -> Self {
+        ProtectClapOptions {
+            action: "",
+            pipeline: "",
+            status_check: "",
+            api_url: "",
+            dry_run: false,
+            project: "",
+            user: "",
+            server: "",
+            server_url: "",
+            api_port: "",
+            ent: "",
+            org: "",
+        }
+    }
+}
+
+impl<'n> ProtectClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        ProtectClapOptions {
+            action: value_of(&matches, "action"),
+            pipeline: value_of(&matches, "for"),
+            status_check: value_of(&matches, "status-check"),
+            api_url: value_of(&matches, "api-url"),
+            dry_run: matches.is_present("dry-run"),
+            project: value_of(&matches, "project"),
+            user: value_of(&matches, "user"),
+            server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
+            api_port: value_of(&matches, "api-port"),
+            ent: value_of(&matches, "ent"),
+            org: value_of(&matches, "org"),
+        }
+    }
+}
+
+impl<'n> Options for ProtectClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let project = try!(project::ProjectRef::resolve(&self.project, &self.ent, &self.org));
+
+        let new_config = config.set_user(&self.user)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_api_port(&self.api_port)
+            .set_enterprise(&project.ent)
+            .set_organization(&project.org)
+            .set_project(&project.name);
+        Ok(new_config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .alias("protege")
+        .about("Protect the pipeline branches on GitHub or Bitbucket, so they only \
+                move through Delivery")
+        .arg(Arg::from_usage("<action> 'sync: require the Delivery status check and \
+                              forbid force pushes on the pipeline branches'")
+             .possible_values(&["sync"]))
+        .args_from_usage(
+            "--for=[pipeline] 'Only protect this pipeline (default: every pipeline of \
+                               the project)'
+            --status-check=[check] 'The status check to require (default: delivery/verify)'
+            --api-url=[url] 'The API of the source code provider (default: from the \
+                             server, else https://api.github.com)'
+            --dry-run 'Only list what protecting the branches would change'")
+        .args(&vec![project_arg(), api_port_arg()])
+        .args(&u_e_s_o_args())
+}
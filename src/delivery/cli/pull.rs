@@ -17,46 +17,23 @@
 
 use project;
 use fips;
-use cli::arguments::{value_of, project_specific_args};
-use clap::{App, SubCommand, ArgMatches};
+use cli::arguments::{i_know_what_im_doing_arg, fix_remote_arg, project_specific_args};
+use clap::{App, SubCommand};
 use cli::Options;
 use config::Config;
 use types::DeliveryResult;
 
 pub const SUBCOMMAND_NAME: &'static str = "pull";
 
-#[derive(Debug)]
-pub struct PullClapOptions<'n> {
-    pub pipeline: &'n str,
-    pub fips: bool,
-    pub fips_custom_cert_filename: &'n str,
-    pub fips_git_port: &'n str,
-    pub rebase: bool,
-}
-
-impl<'n> Default for PullClapOptions<'n> {
-    fn default() -> Self {
-        PullClapOptions {
-            pipeline: "",
-            fips: false,
-            fips_custom_cert_filename: "",
-            fips_git_port: "",
-            rebase: false,
-        }
-    }
-}
-
-impl<'n> PullClapOptions<'n> {
-    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
-        PullClapOptions {
-            pipeline: value_of(&matches, "pipeline"),
-            fips: matches.is_present("fips"),
-            fips_custom_cert_filename: value_of(&matches, "fips-custom-cert-filename"),
-            fips_git_port: value_of(&matches, "fips-git-port"),
-            rebase: matches.is_present("rebase"),
-        }
-    }
-}
+clap_options!(PullClapOptions {
+    pipeline: value("pipeline"),
+    fips: flag("fips"),
+    fips_custom_cert_filename: value("fips-custom-cert-filename"),
+    fips_git_port: value("fips-git-port"),
+    rebase: flag("rebase"),
+    i_know_what_im_doing: flag("i-know-what-im-doing"),
+    fix_remote: flag("fix-remote"),
+});
 
 impl<'n> Options for PullClapOptions<'n> {
     fn merge_options_and_config(&self, mut config: Config) -> DeliveryResult<Config> {
@@ -76,5 +53,7 @@ pub fn clap_subcommand<'c>() -> App<'c, 'c> {
             "<pipeline> 'Name of the remote pipeline on the Automate server to retrieve (can also be any git ref such as a branch)'
             --rebase 'Performs a rebase on the pipeline retrieved from Automate server instead of a merge'"
         )
+        .arg(i_know_what_im_doing_arg())
+        .arg(fix_remote_arg())
         .args(&project_specific_args())
 }
@@ -0,0 +1,62 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::value_of;
+use clap::{Arg, App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+
+pub const SUBCOMMAND_NAME: &'static str = "refs";
+
+#[derive(Debug)]
+pub struct RefsClapOptions<'n> {
+    pub action: &'n str,
+    pub all: bool,
+}
+
+impl<'n> Default for RefsClapOptions<'n> {
+    fn default() -> Self {
+        RefsClapOptions {
+            action: "",
+            all: false,
+        }
+    }
+}
+
+impl<'n> RefsClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        RefsClapOptions {
+            action: value_of(&matches, "action"),
+            all: matches.is_present("all"),
+        }
+    }
+}
+
+impl<'n> Options for RefsClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        Ok(config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Maintain the patchset refs fetched by diff and checkout")
+        .arg(Arg::from_usage("<action> 'prune: delete the refs of the changes that are \
+                              no longer open on the server'")
+             .possible_values(&["prune"]))
+        .args_from_usage("--all 'Delete every patchset ref, even those of open changes'")
+}
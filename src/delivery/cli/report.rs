@@ -0,0 +1,104 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{api_port_arg, project_arg, u_e_s_o_args, value_of};
+use clap::{Arg, App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+use project;
+
+pub const SUBCOMMAND_NAME: &'static str = "report";
+
+#[derive(Debug)]
+pub struct ReportClapOptions<'n> {
+    pub format: &'n str,
+    pub file: &'n str,
+    pub change: &'n str,
+    pub phase: &'n str,
+    pub project: &'n str,
+    pub user: &'n str,
+    pub server: &'n str,
+    pub server_url: &'n str,
+    pub api_port: &'n str,
+    pub ent: &'n str,
+    pub org: &'n str,
+}
+
+impl<'n> Default for ReportClapOptions<'n> {
+    fn default() -> Self {
+        ReportClapOptions {
+            format: "junit",
+            file: "",
+            change: "",
+            phase: "unit",
+            project: "",
+            user: "",
+            server: "",
+            server_url: "",
+            api_port: "",
+            ent: "",
+            org: "",
+        }
+    }
+}
+
+impl<'n> ReportClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        let phase = value_of(&matches, "phase");
+        ReportClapOptions {
+            format: value_of(&matches, "format"),
+            file: value_of(&matches, "file"),
+            change: value_of(&matches, "change"),
+            phase: if phase.is_empty() { "unit" } else { phase },
+            project: value_of(&matches, "project"),
+            user: value_of(&matches, "user"),
+            server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
+            api_port: value_of(&matches, "api-port"),
+            ent: value_of(&matches, "ent"),
+            org: value_of(&matches, "org"),
+        }
+    }
+}
+
+impl<'n> Options for ReportClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let project = try!(project::ProjectRef::resolve(&self.project, &self.ent, &self.org));
+
+        let new_config = config.set_user(&self.user)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_api_port(&self.api_port)
+            .set_enterprise(&project.ent)
+            .set_organization(&project.org)
+            .set_project(&project.name);
+        Ok(new_config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Post a summary of test results on a change")
+        .arg(Arg::from_usage("<format> 'Format of the test results'")
+             .possible_values(&["junit"]))
+        .args_from_usage(
+            "<file> 'Path to the test results'
+            --change=<change-id> 'The change to post the summary on'
+            --phase=[phase] 'The phase that produced the results (default: unit)'")
+        .args(&vec![project_arg(), api_port_arg()])
+        .args(&u_e_s_o_args())
+}
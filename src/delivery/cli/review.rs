@@ -17,8 +17,9 @@
 
 use project;
 use fips;
-use cli::arguments::{pipeline_arg, no_open_arg,
-                     value_of, auto_bump, project_specific_args};
+use cli::review_action;
+use cli::arguments::{pipeline_arg, no_open_arg, unshallow_arg, i_know_what_im_doing_arg,
+                     fix_remote_arg, value_of, auto_bump, project_specific_args};
 use clap::{App, SubCommand, ArgMatches};
 use config::Config;
 use types::DeliveryResult;
@@ -29,25 +30,39 @@ pub const SUBCOMMAND_NAME: &'static str = "review";
 #[derive(Debug)]
 pub struct ReviewClapOptions<'n> {
     pub pipeline: &'n str,
+    pub for_group: &'n str,
     pub no_open: bool,
     pub auto_bump: bool,
     pub edit: bool,
+    pub reuse: bool,
+    pub skip_secret_scan: bool,
+    pub unshallow: bool,
+    pub i_know_what_im_doing: bool,
+    pub fix_remote: bool,
     pub fips: bool,
     pub fips_git_port: &'n str,
     pub fips_custom_cert_filename: &'n str,
     pub user: &'n str,
+    pub ent: &'n str,
 }
 impl<'n> Default for ReviewClapOptions<'n> {
     fn default() -> Self {
         ReviewClapOptions {
             pipeline: "master",
+            for_group: "",
             no_open: false,
             auto_bump: false,
             edit: false,
+            reuse: false,
+            skip_secret_scan: false,
+            unshallow: false,
+            i_know_what_im_doing: false,
+            fix_remote: false,
             fips: false,
             fips_git_port: "",
             fips_custom_cert_filename: "",
             user: "",
+            ent: "",
         }
     }
 }
@@ -56,13 +71,20 @@ impl<'n> ReviewClapOptions<'n> {
     pub fn new(matches: &'n ArgMatches<'n>) -> Self {
         ReviewClapOptions {
             pipeline: value_of(&matches, "pipeline"),
+            for_group: value_of(&matches, "for-group"),
             no_open: matches.is_present("no-open"),
             auto_bump: matches.is_present("auto-bump"),
             edit: matches.is_present("edit"),
+            reuse: matches.is_present("reuse"),
+            skip_secret_scan: matches.is_present("skip-secret-scan"),
+            unshallow: matches.is_present("unshallow"),
+            i_know_what_im_doing: matches.is_present("i-know-what-im-doing"),
+            fix_remote: matches.is_present("fix-remote"),
             fips: matches.is_present("fips"),
             fips_git_port: value_of(&matches, "fips-git-port"),
             fips_custom_cert_filename: value_of(&matches, "fips-custom-cert-filename"),
             user: value_of(&matches, "user"),
+            ent: value_of(&matches, "ent"),
         }
     }
 }
@@ -70,7 +92,8 @@ impl<'n> ReviewClapOptions<'n> {
 impl<'n> Options for ReviewClapOptions<'n> {
     fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
         let mut new_config = config.set_pipeline(&self.pipeline)
-            .set_user(&self.user);
+            .set_user(&self.user)
+            .set_enterprise(&self.ent);
 
         if new_config.auto_bump.is_none() {
             new_config.auto_bump = Some(self.auto_bump);
@@ -88,9 +111,19 @@ impl<'n> Options for ReviewClapOptions<'n> {
 pub fn clap_subcommand<'c>() -> App<'c, 'c> {
     SubCommand::with_name(SUBCOMMAND_NAME)
         .about("Submit current branch for review")
-        .args(&vec![no_open_arg(), auto_bump()])
-        .args_from_usage("-e --edit 'Edit change title and description'")
+        .args(&vec![no_open_arg(), auto_bump(), unshallow_arg(), i_know_what_im_doing_arg(),
+                    fix_remote_arg()])
+        .args_from_usage("-e --edit 'Edit change title and description'
+                          --reuse 'Push a new patchset to an open change that already \
+                          has the same head instead of creating a new change'
+                          --skip-secret-scan 'Do not scan the change for credentials, even \
+                          if the project has a .delivery/secretscan.toml'
+                          --for-group=[group] 'Also submit the change, cherry-picked, to \
+                          every pipeline of a group from the [pipeline_groups] of your cli.toml'")
         .args(&pipeline_arg())
         .args(&project_specific_args())
-        .args_from_usage("-u --user=[user] 'Automate user name for authentication'")
+        .args_from_usage("-u --user=[user] 'Automate user name for authentication'
+                          --ent=[ent] 'The enterprise in which the project lives'")
+        .subcommand(review_action::comment_subcommand())
+        .subcommand(review_action::approve_subcommand())
 }
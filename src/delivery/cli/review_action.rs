@@ -0,0 +1,113 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{api_port_arg, project_arg, u_e_s_o_args, value_of};
+use clap::{App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+use project;
+
+// `delivery review comment` and `delivery review approve`
+pub const COMMENT_SUBCOMMAND_NAME: &'static str = "comment";
+pub const APPROVE_SUBCOMMAND_NAME: &'static str = "approve";
+
+#[derive(Debug)]
+pub struct ReviewActionClapOptions<'n> {
+    pub action: &'n str,
+    pub change: &'n str,
+    pub message: &'n str,
+    pub project: &'n str,
+    pub user: &'n str,
+    pub server: &'n str,
+    pub server_url: &'n str,
+    pub api_port: &'n str,
+    pub ent: &'n str,
+    pub org: &'n str,
+}
+
+impl<'n> Default for ReviewActionClapOptions<'n> {
+    fn default() -> Self {
+        ReviewActionClapOptions {
+            action: "",
+            change: "",
+            message: "",
+            project: "",
+            user: "",
+            server: "",
+            server_url: "",
+            api_port: "",
+            ent: "",
+            org: "",
+        }
+    }
+}
+
+impl<'n> ReviewActionClapOptions<'n> {
+    pub fn new(action: &'n str, matches: &'n ArgMatches<'n>) -> Self {
+        ReviewActionClapOptions {
+            action: action,
+            change: value_of(&matches, "change"),
+            message: value_of(&matches, "message"),
+            project: value_of(&matches, "project"),
+            user: value_of(&matches, "user"),
+            server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
+            api_port: value_of(&matches, "api-port"),
+            ent: value_of(&matches, "ent"),
+            org: value_of(&matches, "org"),
+        }
+    }
+}
+
+impl<'n> Options for ReviewActionClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let project = try!(project::ProjectRef::resolve(&self.project, &self.ent, &self.org));
+
+        let mut new_config = config.set_user(&self.user)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_api_port(&self.api_port)
+            .set_enterprise(&project.ent)
+            .set_organization(&project.org)
+            .set_project(&project.name);
+
+        if new_config.project.is_none() {
+            new_config.project = project::project_from_cwd().ok();
+        }
+        Ok(new_config)
+    }
+}
+
+pub fn comment_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(COMMENT_SUBCOMMAND_NAME)
+        .about("Comment on a change")
+        .args_from_usage(
+            "<change> 'The change to comment on'
+            -m --message=<message> 'The comment to post'")
+        .args(&vec![project_arg(), api_port_arg()])
+        .args(&u_e_s_o_args())
+}
+
+pub fn approve_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(APPROVE_SUBCOMMAND_NAME)
+        .about("Approve a change, merging it into its pipeline")
+        .args_from_usage(
+            "<change> 'The change to approve'
+            -m --message=[message] 'A comment to post on the change before approving it'")
+        .args(&vec![project_arg(), api_port_arg()])
+        .args(&u_e_s_o_args())
+}
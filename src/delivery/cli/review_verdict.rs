@@ -0,0 +1,105 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{api_port_arg, project_arg, u_e_s_o_args, value_of};
+use clap::{App, ArgGroup, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+use project;
+
+pub const SUBCOMMAND_NAME: &'static str = "review-verdict";
+
+#[derive(Debug)]
+pub struct ReviewVerdictClapOptions<'n> {
+    pub change: &'n str,
+    pub approve: bool,
+    pub request_changes: bool,
+    pub message: &'n str,
+    pub project: &'n str,
+    pub user: &'n str,
+    pub server: &'n str,
+    pub server_url: &'n str,
+    pub api_port: &'n str,
+    pub ent: &'n str,
+    pub org: &'n str,
+}
+
+impl<'n> Default for ReviewVerdictClapOptions<'n> {
+    fn default() -> Self {
+        ReviewVerdictClapOptions {
+            change: "",
+            approve: false,
+            request_changes: false,
+            message: "",
+            project: "",
+            user: "",
+            server: "",
+            server_url: "",
+            api_port: "",
+            ent: "",
+            org: "",
+        }
+    }
+}
+
+impl<'n> ReviewVerdictClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        ReviewVerdictClapOptions {
+            change: value_of(&matches, "change"),
+            approve: matches.is_present("approve"),
+            request_changes: matches.is_present("request-changes"),
+            message: value_of(&matches, "message"),
+            project: value_of(&matches, "project"),
+            user: value_of(&matches, "user"),
+            server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
+            api_port: value_of(&matches, "api-port"),
+            ent: value_of(&matches, "ent"),
+            org: value_of(&matches, "org"),
+        }
+    }
+}
+
+impl<'n> Options for ReviewVerdictClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let project = try!(project::ProjectRef::resolve(&self.project, &self.ent, &self.org));
+
+        let new_config = config.set_user(&self.user)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_api_port(&self.api_port)
+            .set_enterprise(&project.ent)
+            .set_organization(&project.org)
+            .set_project(&project.name);
+        Ok(new_config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Approve a change or request changes on it")
+        .args_from_usage(
+            "<change> 'The change to review'
+            --approve 'Approve the change'
+            --request-changes 'Ask for changes before the change can be approved'
+            -m --message=[message] 'Explain the verdict'")
+        .group(ArgGroup::with_name("verdict")
+               .args(&["approve", "request-changes"])
+               .required(true))
+        .args(&vec![project_arg(), api_port_arg()])
+        .args(&u_e_s_o_args())
+}
@@ -0,0 +1,107 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{api_port_arg, format_arg, u_e_s_o_args, value_of};
+use clap::{Arg, App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+
+pub const SUBCOMMAND_NAME: &'static str = "runner";
+
+#[derive(Debug)]
+pub struct RunnerClapOptions<'n> {
+    pub action: &'n str,
+    pub hostname: &'n str,
+    pub os: &'n str,
+    pub platform: &'n str,
+    pub platform_family: &'n str,
+    pub platform_version: &'n str,
+    pub format: &'n str,
+    pub user: &'n str,
+    pub server: &'n str,
+    pub server_url: &'n str,
+    pub api_port: &'n str,
+    pub ent: &'n str,
+    pub org: &'n str,
+}
+
+impl<'n> Default for RunnerClapOptions<'n> {
+    fn default() -> Self {
+        RunnerClapOptions {
+            action: "",
+            hostname: "",
+            os: "",
+            platform: "",
+            platform_family: "",
+            platform_version: "",
+            format: "",
+            user: "",
+            server: "",
+            server_url: "",
+            api_port: "",
+            ent: "",
+            org: "",
+        }
+    }
+}
+
+impl<'n> RunnerClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        RunnerClapOptions {
+            action: value_of(&matches, "action"),
+            hostname: value_of(&matches, "hostname"),
+            os: value_of(&matches, "os"),
+            platform: value_of(&matches, "platform"),
+            platform_family: value_of(&matches, "platform-family"),
+            platform_version: value_of(&matches, "platform-version"),
+            format: value_of(&matches, "format"),
+            user: value_of(&matches, "user"),
+            server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
+            api_port: value_of(&matches, "api-port"),
+            ent: value_of(&matches, "ent"),
+            org: value_of(&matches, "org"),
+        }
+    }
+}
+
+impl<'n> Options for RunnerClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let new_config = config.set_user(&self.user)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_api_port(&self.api_port)
+            .set_enterprise(&self.ent)
+            .set_organization(&self.org);
+        Ok(new_config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("List, register or delete the job runners of the enterprise")
+        .arg(Arg::from_usage("<action> 'Action to perform on the runners'")
+             .possible_values(&["list", "register", "delete"]))
+        .args_from_usage(
+            "[hostname] 'Hostname of the runner (register and delete only)'
+            --os=[os] 'Operating system of the runner, i.e. linux'
+            --platform=[platform] 'Platform of the runner, i.e. ubuntu'
+            --platform-family=[family] 'Platform family of the runner, i.e. debian'
+            --platform-version=[version] 'Platform version of the runner, i.e. 16.04'")
+        .args(&vec![api_port_arg(), format_arg()])
+        .args(&u_e_s_o_args())
+}
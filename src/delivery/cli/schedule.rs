@@ -0,0 +1,114 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::arguments::{api_port_arg, project_arg, u_e_s_o_args, value_of};
+use clap::{Arg, App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+use project;
+
+pub const SUBCOMMAND_NAME: &'static str = "schedule";
+
+#[derive(Debug)]
+pub struct ScheduleClapOptions<'n> {
+    pub action: &'n str,
+    pub change: &'n str,
+    pub once: bool,
+    pub project: &'n str,
+    pub user: &'n str,
+    pub server: &'n str,
+    pub server_url: &'n str,
+    pub api_port: &'n str,
+    pub ent: &'n str,
+    pub org: &'n str,
+}
+
+impl<'n> Default for ScheduleClapOptions<'n> {
+    fn default() -> Self {
+        ScheduleClapOptions {
+            action: "",
+            change: "",
+            once: false,
+            project: "",
+            user: "",
+            server: "",
+            server_url: "",
+            api_port: "",
+            ent: "",
+            org: "",
+        }
+    }
+}
+
+impl<'n> ScheduleClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        ScheduleClapOptions {
+            action: value_of(&matches, "action"),
+            change: value_of(&matches, "change"),
+            once: matches.is_present("once"),
+            project: value_of(&matches, "project"),
+            user: value_of(&matches, "user"),
+            server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
+            api_port: value_of(&matches, "api-port"),
+            ent: value_of(&matches, "ent"),
+            org: value_of(&matches, "org"),
+        }
+    }
+}
+
+impl<'n> Options for ScheduleClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let config = config.set_user(&self.user)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_api_port(&self.api_port);
+
+        // The local schedule is listed and run from anywhere, not only
+        // from inside a project
+        let mut new_config = if self.project.is_empty() {
+            config.set_enterprise(&self.ent).set_organization(&self.org)
+        } else {
+            let project = try!(project::ProjectRef::resolve(&self.project, &self.ent,
+                                                            &self.org));
+            config.set_enterprise(&project.ent)
+                .set_organization(&project.org)
+                .set_project(&project.name)
+        };
+
+        if new_config.project.is_none() {
+            new_config.project = project::project_from_cwd().ok();
+        }
+        Ok(new_config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("List, cancel and make the deliveries scheduled with `delivery deliver --at` \
+                or `--window`")
+        .arg(Arg::from_usage("<action> 'list: the scheduled deliveries; cancel: the \
+                              delivery of <change>; run: deliver the local schedule as it \
+                              comes due'")
+             .possible_values(&["list", "cancel", "run"]))
+        .args_from_usage(
+            "[change] 'The change whose delivery to cancel'
+            --once 'With run, deliver what is due and exit, i.e. from cron'")
+        .args(&vec![project_arg(), api_port_arg()])
+        .args(&u_e_s_o_args())
+}
@@ -27,6 +27,7 @@ pub const SUBCOMMAND_NAME: &'static str = "setup";
 pub struct SetupClapOptions<'n> {
     pub user: &'n str,
     pub server: &'n str,
+    pub server_url: &'n str,
     pub ent: &'n str,
     pub org: &'n str,
     pub path: &'n str,
@@ -39,6 +40,7 @@ impl<'n> Default for SetupClapOptions<'n> {
         SetupClapOptions {
             user: "",
             server: "",
+            server_url: "",
             ent: "",
             org: "",
             path: "",
@@ -53,6 +55,7 @@ impl<'n> SetupClapOptions<'n> {
         SetupClapOptions {
             user: value_of(&matches, "user"),
             server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
             ent: value_of(&matches, "ent"),
             org: value_of(&matches, "org"),
             path: value_of(&matches, "config-path"),
@@ -65,6 +68,7 @@ impl<'n> SetupClapOptions<'n> {
 impl<'n> Options for SetupClapOptions<'n> {
     fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
         let new_config = config.set_server(&self.server)
+            .set_server_url(&self.server_url)
             .set_user(&self.user)
             .set_enterprise(&self.ent)
             .set_organization(&self.org)
@@ -15,27 +15,48 @@
 // limitations under the License.
 //
 
-use cli::arguments::{api_port_arg, server_arg, value_of};
-use clap::{App, SubCommand, ArgMatches};
+use cli::arguments::{api_port_arg, ent_arg, format_arg, pipeline_arg, project_arg, server_arg,
+                     server_url_arg, value_of};
+use clap::{App, Arg, SubCommand, ArgMatches};
 use cli::Options;
 use types::DeliveryResult;
 use config::Config;
+use project;
 
 pub const SUBCOMMAND_NAME: &'static str = "status";
 
 #[derive(Debug)]
 pub struct StatusClapOptions<'n> {
     pub api_port: &'n str,
+    pub ent: &'n str,
+    // The global --json, which status took before it was global
     pub json: bool,
+    pub format: &'n str,
+    pub merge_check: bool,
+    pub pipeline: &'n str,
+    pub project: &'n str,
     pub server: &'n str,
+    pub server_url: &'n str,
+    pub watch: &'n str,
+    pub until: &'n str,
+    pub timeout: &'n str,
 }
 
 impl<'n> Default for StatusClapOptions<'n> {
     fn default() -> Self {
         StatusClapOptions {
             api_port: "",
+            ent: "",
             json: false,
+            format: "",
+            merge_check: false,
+            pipeline: "",
+            project: "",
             server: "",
+            server_url: "",
+            watch: "",
+            until: "",
+            timeout: "",
         }
     }
 }
@@ -44,8 +65,17 @@ impl<'n> StatusClapOptions<'n> {
     pub fn new(matches: &'n ArgMatches<'n>) -> Self {
         StatusClapOptions {
             api_port: value_of(&matches, "api-port"),
+            ent: value_of(&matches, "ent"),
             json: matches.is_present("json"),
+            format: value_of(&matches, "format"),
+            merge_check: matches.is_present("merge-check"),
+            pipeline: value_of(&matches, "pipeline"),
+            project: value_of(&matches, "project"),
             server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
+            watch: value_of(&matches, "watch"),
+            until: value_of(&matches, "until"),
+            timeout: value_of(&matches, "timeout"),
         }
     }
 }
@@ -53,8 +83,18 @@ impl<'n> StatusClapOptions<'n> {
 impl<'n> Options for StatusClapOptions<'n> {
     fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
         let new_config = config.set_api_port(&self.api_port)
-            .set_server(&self.server);
-        Ok(new_config)
+            .set_server(&self.server)
+            .set_server_url(&self.server_url)
+            .set_enterprise(&self.ent)
+            .set_pipeline(&self.pipeline);
+        if self.project.is_empty() {
+            return Ok(new_config)
+        }
+        // The merge check looks for the change in another project
+        let project = try!(project::ProjectRef::resolve(&self.project, &self.ent, ""));
+        Ok(new_config.set_enterprise(&project.ent)
+           .set_organization(&project.org)
+           .set_project(&project.name))
     }
 }
 
@@ -62,6 +102,33 @@ pub fn clap_subcommand<'c>() -> App<'c, 'c> {
     SubCommand::with_name(SUBCOMMAND_NAME)
         .about("Get status information about the Automate Server's _status endpoint")
         .arg(api_port_arg())
-        .args_from_usage("--json 'Output the raw JSON from the _status endpoint'")
+        .arg(ent_arg())
+        .arg(format_arg())
+        .args_from_usage("--merge-check 'Also check whether the current branch merges \
+                          cleanly with its pipeline, asking the server or simulating the merge'")
+        .args(&pipeline_arg())
+        .arg(project_arg())
         .arg(server_arg())
+        .arg(server_url_arg())
+        .arg(Arg::with_name("watch")
+             .long("watch")
+             .value_name("CHANGE")
+             .takes_value(true)
+             .help("Follow a change, by id or feature branch, through its stages. \
+                    Exits 0 when it passes, 1 when a stage fails, 2 on --timeout, \
+                    3 when the change is abandoned or superseded and 4 when it \
+                    waits for approval"))
+        .arg(Arg::with_name("until")
+             .long("until")
+             .value_name("STAGE")
+             .takes_value(true)
+             .requires("watch")
+             .possible_values(&["verify", "build", "acceptance"])
+             .help("The last stage to watch [default: acceptance]"))
+        .arg(Arg::with_name("timeout")
+             .long("timeout")
+             .value_name("DURATION")
+             .takes_value(true)
+             .requires("watch")
+             .help("Stop watching after this long, i.e. 90s, 30m or 2h"))
 }
@@ -0,0 +1,58 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::value_of;
+use clap::{App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+
+pub const SUBCOMMAND_NAME: &'static str = "support-bundle";
+
+#[derive(Debug)]
+pub struct SupportBundleClapOptions<'n> {
+    pub output: &'n str,
+}
+
+impl<'n> Default for SupportBundleClapOptions<'n> {
+    fn default() -> Self {
+        SupportBundleClapOptions {
+            output: "",
+        }
+    }
+}
+
+impl<'n> SupportBundleClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        SupportBundleClapOptions {
+            output: value_of(&matches, "output"),
+        }
+    }
+}
+
+impl<'n> Options for SupportBundleClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        Ok(config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Create a tarball with the recent cli logs and configuration, \
+                with credentials redacted, to attach to a support request")
+        .args_from_usage("-o --output=[file] 'Where to write the bundle. \
+                          default:delivery-support-<timestamp>.tar.gz'")
+}
@@ -0,0 +1,41 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use clap::{Arg, App, SubCommand};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+
+pub const SUBCOMMAND_NAME: &'static str = "telemetry";
+
+clap_options!(TelemetryClapOptions {
+    action: value("action"),
+});
+
+impl<'n> Options for TelemetryClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        Ok(config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Opt in or out of the anonymous usage analytics: the name, duration \
+                and result of each command and the version of the cli")
+        .arg(Arg::from_usage("<action> 'enable, disable, status or flush: send the \
+                              spooled events to the telemetry_endpoint now'")
+             .possible_values(&["enable", "disable", "status", "flush"]))
+}
@@ -24,25 +24,35 @@ pub const SUBCOMMAND_NAME: &'static str = "token";
 
 #[derive(Debug)]
 pub struct TokenClapOptions<'n> {
+    pub action: &'n str,
     pub server: &'n str,
+    pub server_url: &'n str,
     pub port: &'n str,
     pub ent: &'n str,
     pub user: &'n str,
     pub verify: bool,
+    pub renew: bool,
     pub raw: bool,
     // if None, use what the server tells us on its /e/<ent>/saml/enabled endpoint
     pub saml: Option<bool>,
+    pub ttl: &'n str,
+    pub out_dir: &'n str,
 }
 impl<'n> Default for TokenClapOptions<'n> {
     fn default() -> Self {
         TokenClapOptions {
+            action: "",
             server: "",
+            server_url: "",
             port: "",
             ent: "",
             user: "",
             verify: false,
+            renew: false,
             raw: false,
             saml: None,
+            ttl: "",
+            out_dir: "",
         }
     }
 }
@@ -50,27 +60,50 @@ impl<'n> Default for TokenClapOptions<'n> {
 impl<'n> TokenClapOptions<'n> {
     pub fn new(matches: &'n ArgMatches<'n>) -> Self {
         TokenClapOptions {
+            action: value_of(&matches, "action"),
             server: value_of(&matches, "server"),
+            server_url: value_of(&matches, "server-url"),
             port: value_of(&matches, "api-port"),
             ent: value_of(&matches, "ent"),
             user: value_of(&matches, "user"),
             verify: matches.is_present("verify"),
+            renew: matches.is_present("renew"),
             raw: matches.is_present("raw"),
             saml: match value_of(&matches, "saml") {
               "true" => Some(true),
               "false" => Some(false),
               _ => None,
             },
+            ttl: value_of(&matches, "ttl"),
+            out_dir: value_of(&matches, "out-dir"),
         }
     }
 }
 
+impl<'n> TokenClapOptions<'n> {
+    /// Whether we are issuing tokens for other users
+    pub fn issue(&self) -> bool {
+        self.action == "issue"
+    }
+
+    /// The users to issue tokens for, from a comma separated `--user`
+    pub fn issue_users(&self) -> Vec<&'n str> {
+        self.user.split(',').map(|u| u.trim()).filter(|u| !u.is_empty()).collect()
+    }
+}
+
 impl<'n> Options for TokenClapOptions<'n> {
     fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
         let mut new_config = config.set_server(&self.server)
+            .set_server_url(&self.server_url)
             .set_api_port(&self.port)
-            .set_enterprise(&self.ent)
-            .set_user(&self.user);
+            .set_enterprise(&self.ent);
+
+        // When issuing tokens `--user` names the users we issue for, the
+        // admin authenticates as the user of the cli.toml.
+        if !self.issue() {
+            new_config = new_config.set_user(&self.user);
+        }
 
         if self.saml.is_some() {
             new_config.saml = self.saml;
@@ -83,10 +116,16 @@ impl<'n> Options for TokenClapOptions<'n> {
 pub fn clap_subcommand<'c>() -> App<'c, 'c> {
     SubCommand::with_name(SUBCOMMAND_NAME)
         .about("Create a local API token")
+        .arg(Arg::from_usage("[action] 'issue: mint tokens for the users given with \
+                              --user (comma separated); admins only'")
+             .possible_values(&["issue"]))
         .arg(api_port_arg())
         .args(&u_e_s_o_args())
         .args(&make_arg_vec![
             "--raw 'Output only the raw token string'",
             "--verify 'Verify the Token has expired'",
-            "--saml=[true/false] 'Use SAML authentication (overrides Delivery server)'"])
+            "--renew 'Replace the stored token, without a prompt when AUTOMATE_PASSWORD is set'",
+            "--saml=[true/false] 'Use SAML authentication (overrides Delivery server)'",
+            "--ttl=[duration] 'Lifetime of the issued tokens, e.g. 12h or 30d'",
+            "--out-dir=[dir] 'Write each issued token to <dir>/<user>/api-tokens'"])
 }
@@ -0,0 +1,78 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{pipeline_arg, no_open_arg, local_arg, i_know_what_im_doing_arg,
+                     value_of};
+use clap::{App, SubCommand, ArgMatches};
+use cli::Options;
+use types::DeliveryResult;
+use config::Config;
+use project;
+
+pub const SUBCOMMAND_NAME: &'static str = "update-build-deps";
+
+#[derive(Debug)]
+pub struct UpdateBuildDepsClapOptions<'n> {
+    pub pipeline: &'n str,
+    pub check: bool,
+    pub no_open: bool,
+    pub local: bool,
+    pub i_know_what_im_doing: bool,
+}
+
+impl<'n> Default for UpdateBuildDepsClapOptions<'n> {
+    fn default() -> Self {
+        UpdateBuildDepsClapOptions {
+            pipeline: "master",
+            check: false,
+            no_open: false,
+            local: false,
+            i_know_what_im_doing: false,
+        }
+    }
+}
+
+impl<'n> UpdateBuildDepsClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        UpdateBuildDepsClapOptions {
+            pipeline: value_of(&matches, "pipeline"),
+            check: matches.is_present("check"),
+            no_open: matches.is_present("no-open"),
+            local: matches.is_present("local"),
+            i_know_what_im_doing: matches.is_present("i-know-what-im-doing"),
+        }
+    }
+}
+
+impl<'n> Options for UpdateBuildDepsClapOptions<'n> {
+    fn merge_options_and_config(&self, config: Config) -> DeliveryResult<Config> {
+        let mut new_config = config.set_pipeline(&self.pipeline);
+        if new_config.project.is_none() {
+            new_config.project = project::project_from_cwd().ok();
+        }
+        Ok(new_config)
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Refresh the Berksfile.lock of the build cookbook and submit it \
+                in a review of its own")
+        .args_from_usage(
+            "--check 'Only verify that the Berksfile.lock is present and committed'")
+        .args(&vec![no_open_arg(), local_arg(), i_know_what_im_doing_arg()])
+        .args(&pipeline_arg())
+}
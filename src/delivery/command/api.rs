@@ -20,8 +20,11 @@ use types::{DeliveryResult, ExitCode};
 use errors::DeliveryError;
 use errors::Kind::UnsupportedHttpMethod;
 use hyper::status::StatusCode::Conflict;
-use utils::say::sayln;
+use serde_json::{self, Value};
+use utils::say::{sayln, outln};
+use utils::filter::Filter;
 use http::APIClient;
+use http::paginate;
 use command::Command;
 use config::Config;
 
@@ -32,19 +35,32 @@ pub struct ApiCommand<'n> {
 
 impl<'n> Command for ApiCommand<'n> {
     fn run(&self) -> DeliveryResult<ExitCode> {
+        let filter = if self.options.filter.is_empty() {
+            None
+        } else {
+            Some(try!(Filter::parse(self.options.filter)))
+        };
         let client = try!(APIClient::from_config(&self.config));
-        let response = match self.options.method {
-            "get"    => try!(client.get(self.options.path)),
-            "post"   => try!(client.post(self.options.path, self.options.data)),
-            "put"    => try!(client.put(self.options.path, self.options.data)),
-            "delete" => try!(client.delete(self.options.path)),
+        let path = self.options.path;
+        let parsed = match self.options.method {
+            "get" if !self.options.no_paginate => try!(paginate::get_all(&client, path)),
+            "get"    => try!(APIClient::parse_response(try!(client.get(path)))),
+            "post"   => try!(APIClient::parse_response(try!(client.post(path, self.options.data)))),
+            "put"    => try!(APIClient::parse_response(try!(client.put(path, self.options.data)))),
+            "delete" => try!(APIClient::parse_response(try!(client.delete(path)))),
             _ => return Err(DeliveryError::throw(UnsupportedHttpMethod, None))
         };
 
-        match try!(APIClient::parse_response(response)) {
+        match parsed {
             // if the response returned some content, printed out
             (_code, Some(content)) => {
-                sayln("white", &format!("{}", content));
+                match filter {
+                    Some(filter) => {
+                        let value: Value = try!(serde_json::from_str(&content));
+                        outln("white", &filtered(&filter.apply(&value)));
+                    },
+                    None => outln("white", &format!("{}", content))
+                }
             },
             // but if there was a conflict, show it and exit with non_zero code
             (Conflict, None) => {
@@ -57,3 +73,11 @@ impl<'n> Command for ApiCommand<'n> {
         Ok(0)
     }
 }
+
+// Strings are printed raw for scripts, everything else as JSON
+fn filtered(value: &Value) -> String {
+    match *value {
+        Value::String(ref s) => s.clone(),
+        ref other => serde_json::to_string_pretty(other).unwrap_or_default()
+    }
+}
@@ -0,0 +1,57 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::cache::CacheClapOptions;
+use types::{DeliveryResult, ExitCode};
+use project;
+use project::generator_cache;
+use project::pipeline_template;
+use utils::say::sayln;
+use utils::json_output;
+use command::Command;
+use config::Config;
+
+pub struct CacheCommand<'n> {
+    pub options: &'n CacheClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for CacheCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let cache_paths = vec![try!(project::generator_cache_path()),
+                               try!(pipeline_template::cache_path())];
+        let mut removed = vec![];
+        for cache_path in cache_paths.iter() {
+            for entry in try!(generator_cache::entries(cache_path)) {
+                if self.options.all || generator_cache::is_stale(&entry) {
+                    try!(generator_cache::remove(&entry));
+                    removed.push(entry.file_name().unwrap_or_default()
+                                 .to_string_lossy().into_owned());
+                }
+            }
+        }
+        json_output::set("removed", &removed);
+
+        if removed.is_empty() {
+            sayln("white", "No cached generator to remove");
+        }
+        for name in removed.iter() {
+            sayln("white", &format!("  Removed {}", name));
+        }
+        Ok(0)
+    }
+}
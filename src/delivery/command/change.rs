@@ -0,0 +1,119 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use time;
+use cli::change::ChangeClapOptions;
+use types::{ChangeId, DeliveryResult, ExitCode};
+use errors::{DeliveryError, Kind};
+use http::APIClient;
+use http::change::{self, ChangeSummary};
+use project::bulk::{self, Selector};
+use utils::say::{say, sayln, outln};
+use utils::prompt::Prompt;
+use utils::json_output;
+use command::Command;
+use config::Config;
+
+pub struct ChangeCommand<'n> {
+    pub options: &'n ChangeClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for ChangeCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project());
+        let selector = try!(self.selector());
+        let client = try!(APIClient::from_config(&self.config));
+
+        let now = time::get_time().sec;
+        let changes: Vec<ChangeSummary> = try!(change::all_changes_in_state(
+                &client, &org, &proj, &selector.pipeline, &selector.state))
+            .into_iter()
+            .filter(|c| selector.matches(c, now))
+            .collect();
+        json_output::set("selected", &changes);
+
+        // The preview is always shown, --yes only skips the question
+        sayln("white", &format!("{} {}/{}: {} {}", self.options.action, org, proj,
+                                changes.len(), selector.describe()));
+        for c in changes.iter() {
+            outln("white", &format!("{}  {}  {}  {}", c.id, c.topic, c.submitted_at, c.title));
+        }
+        if changes.is_empty() {
+            return Ok(0)
+        }
+        if !self.options.yes && !try!(self.confirmed(changes.len())) {
+            sayln("yellow", "No change was touched");
+            return Ok(1)
+        }
+
+        let mut failed = Vec::new();
+        for c in changes.iter() {
+            say("white", &format!("{} {}... ", self.options.action, c.id));
+            let result = match self.options.action {
+                "abandon" => change::abandon(&client, &org, &proj, &c.id),
+                "retry" => change::retry(&client, &org, &proj, c),
+                _ => ChangeId::new(&c.id)
+                    .and_then(|id| client.approve_change(&org, &proj, &id)),
+            };
+            match result {
+                Ok(()) => sayln("green", "done"),
+                Err(e) => {
+                    sayln("red", &format!("failed: {}", e.detail().unwrap_or(e.to_string())));
+                    failed.push(c.id.clone());
+                }
+            }
+        }
+        json_output::set("failed", &failed);
+        if failed.is_empty() {
+            sayln("green", &format!("{} changes done", changes.len()));
+            Ok(0)
+        } else {
+            sayln("red", &format!("{} of {} changes failed", failed.len(), changes.len()));
+            Ok(1)
+        }
+    }
+}
+
+impl<'n> ChangeCommand<'n> {
+    fn selector(&self) -> DeliveryResult<Selector> {
+        let older_than = match self.options.older_than {
+            "" => None,
+            age => Some(try!(bulk::parse_age(age))),
+        };
+        Ok(Selector {
+            state: match self.options.state { "" => "open", s => s }.to_string(),
+            pipeline: try!(self.config.pipeline()),
+            older_than: older_than,
+            topic: match self.options.topic { "" => None, t => Some(t.to_string()) },
+        })
+    }
+
+    fn non_interactive(&self) -> bool {
+        self.options.non_interactive || self.config.non_interactive.unwrap_or(false)
+    }
+
+    fn confirmed(&self, count: usize) -> DeliveryResult<bool> {
+        if self.non_interactive() {
+            let msg = format!("Refusing to {} {} changes without --yes when running \
+                               non-interactively", self.options.action, count);
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        let question = format!("Go ahead and {} these {} changes?", self.options.action, count);
+        Prompt::new(&question).default("n").interactive(true).confirm()
+    }
+}
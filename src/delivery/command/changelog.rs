@@ -0,0 +1,73 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::fs::File;
+use std::io::{self, Write};
+use cli::changelog::ChangelogClapOptions;
+use types::{DeliveryResult, ExitCode};
+use changelog;
+use http::{self, APIClient};
+use git;
+use project;
+use utils::pool;
+use utils::say::{self, say, sayln};
+use command::Command;
+use config::Config;
+
+pub struct ChangelogCommand<'n> {
+    pub options: &'n ChangelogClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for ChangelogCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        // The changelog is meant to be piped into release tooling, keep
+        // stdout clean unless it goes to a file.
+        let to_stdout = self.options.output.is_empty();
+        if to_stdout {
+            say::turn_off_spinner();
+        }
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project());
+        let pipe = try!(self.config.pipeline());
+        let concurrency = try!(pool::parse_concurrency(self.options.concurrency));
+
+        let args = changelog::git_log_args(self.options.from, self.options.to);
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        let log = try!(git::git_command(&args, &try!(project::project_path()))).stdout;
+        let merged = changelog::parse_merges(&log, &pipe);
+
+        let client = try!(APIClient::from_config(&self.config));
+        let descriptions = pool::parallel_map(merged.clone(), concurrency, move |m| {
+            http::change::description(&client, &org, &proj, &m.id)
+        });
+        let mut changes = Vec::new();
+        for (m, description) in merged.into_iter().zip(descriptions.into_iter()) {
+            changes.push((m, try!(description)));
+        }
+
+        let markdown = changelog::render(self.options.from, self.options.to, &changes);
+        if to_stdout {
+            try!(io::stdout().write_all(markdown.as_bytes()));
+        } else {
+            try!(try!(File::create(self.options.output)).write_all(markdown.as_bytes()));
+            say("white", &format!("Wrote {} changes to ", changes.len()));
+            sayln("yellow", self.options.output);
+        }
+        Ok(0)
+    }
+}
@@ -19,11 +19,20 @@ use std;
 use fips;
 use git;
 use cli::checkout::CheckoutClapOptions;
+use errors::{DeliveryError, Kind};
+use http::{self, APIClient};
+use http::change::ChangeSummary;
 use types::{DeliveryResult, ExitCode};
 use utils::say::{sayln, say};
+use utils::prompt::Prompt;
+use utils::json_output;
+use utils::template::{self, Template};
 use config::Config;
 use command::Command;
 
+// What `--list` prints for each change without a `--format`
+const LIST_TEMPLATE: &'static str = "{{.topic}}\t{{.id}}\t{{.stage}}\t{{.stage_status}}\t{{.title}}";
+
 pub struct CheckoutCommand<'n> {
     pub options: &'n CheckoutClapOptions<'n>,
     pub config: &'n Config,
@@ -31,19 +40,31 @@ pub struct CheckoutCommand<'n> {
 
 impl<'n> Command for CheckoutCommand<'n> {
     fn setup(&self, child_processes: &mut Vec<std::process::Child>) -> DeliveryResult<()> {
+        try!(super::verify_and_repair_git_remote(&self.config, self.options.fix_remote));
         if self.config.fips.unwrap_or(false) {
-            try!(super::verify_and_repair_git_remote(&self.config));
             try!(fips::setup_and_start_stunnel(&self.config, child_processes));
         }
         Ok(())
     }
 
     fn run(&self) -> DeliveryResult<ExitCode> {
+        if self.options.list {
+            return self.list()
+        }
         sayln("green", "Chef Delivery");
+        try!(super::refuse_in_managed_workspace(self.options.i_know_what_im_doing));
         let config_ref = self.config;
         let target = validate!(config_ref, pipeline);
+        let change = if self.options.change.is_empty() {
+            match try!(self.pick_change(&target)) {
+                Some(change) => change,
+                None => return Ok(1)
+            }
+        } else {
+            self.options.change.to_string()
+        };
         say("white", "Checking out ");
-        say("yellow", self.options.change);
+        say("yellow", &change);
         say("white", " targeted for pipeline ");
         say("magenta", &target);
 
@@ -58,7 +79,68 @@ impl<'n> Command for CheckoutCommand<'n> {
                 p
             }
         };
-        try!(git::checkout_review(self.options.change, pset, &target));
+        try!(git::checkout_review(&change, pset, &target));
         Ok(0)
     }
 }
+
+impl<'n> CheckoutCommand<'n> {
+    fn open_changes(&self, pipeline: &str) -> DeliveryResult<Vec<ChangeSummary>> {
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project());
+        let client = try!(APIClient::from_config(&self.config));
+        http::change::open_changes(&client, &org, &proj, pipeline)
+    }
+
+    // Print the open changes for scripts, through the --format template
+    fn list(&self) -> DeliveryResult<ExitCode> {
+        let target = try!(self.config.pipeline());
+        let template = match try!(template::from_format_option(self.options.format)) {
+            Some(template) => template,
+            None => try!(Template::parse(LIST_TEMPLATE))
+        };
+        let changes = try!(self.open_changes(&target));
+        json_output::set("changes", &changes);
+        if json_output::enabled() {
+            return Ok(0)
+        }
+        template.print_each(&changes).map(|_| 0)
+    }
+
+    // Ask which of the open changes of `pipeline` to check out; `None`
+    // when there is none.
+    fn pick_change(&self, pipeline: &str) -> DeliveryResult<Option<String>> {
+        if self.config.non_interactive.unwrap_or(false) {
+            let msg = "Name the change to check out, or see the open ones with \
+                       --list".to_string();
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        let changes = try!(self.open_changes(pipeline));
+        if changes.is_empty() {
+            say("white", "There are no open changes in the ");
+            say("magenta", pipeline);
+            sayln("white", " pipeline");
+            return Ok(None)
+        }
+        say("white", "Open changes in the ");
+        say("magenta", pipeline);
+        sayln("white", " pipeline:");
+        let choices: Vec<String> = changes.iter().map(choice).collect();
+        let choices: Vec<&str> = choices.iter().map(|c| c.as_str()).collect();
+        let picked = try!(Prompt::new("Change to check out").select(&choices));
+        Ok(Some(changes[picked].topic.clone()))
+    }
+}
+
+// How a change is offered, i.e. `foo (verify passed) Fix the login`
+fn choice(change: &ChangeSummary) -> String {
+    let state = format!("{} {}", change.stage, change.stage_status);
+    let mut choice = change.topic.clone();
+    if !state.trim().is_empty() {
+        choice.push_str(&format!(" ({})", state.trim()));
+    }
+    if !change.title.is_empty() {
+        choice.push_str(&format!(" {}", change.title));
+    }
+    choice
+}
@@ -46,7 +46,8 @@ impl<'n> Command for CloneCommand<'n> {
         sayln("green", "Chef Delivery");
         say("white", "Cloning ");
         let delivery_url = try!(self.config.delivery_git_ssh_url());
-        let project_root = cwd().join(self.options.project);
+        let project = try!(self.config.project());
+        let project_root = cwd().join(&project);
         let clone_url = if self.options.git_url.is_empty() {
             delivery_url.clone()
         } else {
@@ -55,7 +56,7 @@ impl<'n> Command for CloneCommand<'n> {
 
         say("yellow", &clone_url);
         say("white", " to ");
-        sayln("magenta", &format!("{}", self.options.project));
+        sayln("magenta", &project);
 
         // Verify if the destination path already exist.
         if path_ext::is_dir(&project_root) {
@@ -64,9 +65,9 @@ impl<'n> Command for CloneCommand<'n> {
             return Err(DeliveryError::throw(CloneFailed, Some(msg)))
         }
 
-        if let Some(e) = git::clone(self.options.project, &clone_url).err() {
+        if let Some(e) = git::clone(&project, &clone_url).err() {
             debug!("Raw Clone Error: {:?}", e);
-            sayln("red", &format!("Unable to clone project '{}'", self.options.project));
+            sayln("red", &format!("Unable to clone project '{}'", project));
             sayln("yellow", "Analyzing error:");
 
             // Verify that the user is well configured
@@ -81,7 +82,7 @@ impl<'n> Command for CloneCommand<'n> {
 
             // Does the project exist?
             let o = self.config.organization()?;
-            let p = self.options.project;
+            let p = &project;
             if !APIClient::from_config(&self.config)?.project_exists(&o, p) {
                 let msg = format!("You can find the list of available projects \
                                   at:\n\t{}", self.config.projects_url()?);
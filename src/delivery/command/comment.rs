@@ -0,0 +1,64 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::comment::CommentClapOptions;
+use types::{DeliveryResult, ExitCode, ChangeId};
+use errors::{DeliveryError, Kind};
+use http::APIClient;
+use http::comment::NewComment;
+use utils::say::sayln;
+use command::Command;
+use config::Config;
+
+pub struct CommentCommand<'n> {
+    pub options: &'n CommentClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for CommentCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project());
+        let change = try!(ChangeId::new(self.options.change));
+        let client = try!(APIClient::from_config(&self.config));
+
+        match self.options.action {
+            "post" => {
+                if self.options.message.is_empty() {
+                    let msg = "Use --message to say what to comment".to_string();
+                    return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+                }
+                try!(client.post_comment(&org, &proj, &change,
+                                         &NewComment::new(self.options.message)));
+                sayln("green", &format!("Comment posted on change {}", change));
+            },
+            action => {
+                let resolved = action == "resolve";
+                let comment_id = self.options.comment_id;
+                if comment_id.is_empty() {
+                    let msg = format!("Give the id of the comment that starts the thread \
+                                       to {}", action);
+                    return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+                }
+                try!(client.set_thread_resolved(&org, &proj, &change, comment_id, resolved));
+                sayln("green", &format!("Thread {} {} on change {}", comment_id,
+                                        if resolved { "resolved" } else { "reopened" }, change));
+            }
+        }
+        Ok(0)
+    }
+}
@@ -0,0 +1,48 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::Options;
+use cli::config::ConfigClapOptions;
+use command::Command;
+use config::{Config, ConfigOrigin};
+use types::{DeliveryResult, ExitCode};
+use utils::cwd;
+use utils::say::{out, outln};
+
+pub struct ConfigCommand<'n> {
+    pub options: &'n ConfigClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for ConfigCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let mut layers = try!(Config::layers(&cwd()));
+        let flags = try!(self.options.merge_options_and_config(Config::unset()));
+        layers.push((ConfigOrigin::CommandLine, flags));
+        let origins = Config::origins(&layers);
+        let width = origins.keys().map(|k| k.len()).max().unwrap_or(0);
+        for (key, &(ref value, ref origin)) in origins.iter() {
+            out("white", &format!("{:<width$} = ", key, width = width));
+            if self.options.show_origin {
+                out("yellow", value);
+                outln("magenta", &format!("  ({})", origin));
+            } else {
+                outln("yellow", value);
+            }
+        }
+        Ok(0)
+    }
+}
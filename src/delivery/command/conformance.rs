@@ -0,0 +1,61 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::conformance::ConformanceClapOptions;
+use types::{DeliveryResult, ExitCode};
+use features;
+use features::conformance;
+use http::{APIAuth, APIClient};
+use utils::say::{sayln, out, outln};
+use utils::json_output;
+use command::Command;
+use config::Config;
+
+pub struct ConformanceCommand<'n> {
+    pub options: &'n ConformanceClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for ConformanceCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project());
+        let mut client = try!(APIClient::from_config_no_auth(&self.config));
+        if let Ok(auth) = APIAuth::from_stored_token(&self.config) {
+            client.set_auth(auth);
+        }
+        let advertised = try!(client.server_features());
+        let checks = conformance::run(&client, &org, &proj, &advertised);
+        if let Err(e) = features::record_detected(&self.config, conformance::flags(&checks)) {
+            debug!("Unable to record the detected features: {:?}", e);
+        }
+        json_output::set("checks", &checks);
+
+        sayln("white", &format!("Compatibility of {} (enterprise {}, project {}/{}):",
+                                try!(self.config.api_host_and_port()),
+                                try!(self.config.enterprise()), org, proj));
+        for check in checks.iter() {
+            out("white", &format!("  {:<14}", check.feature));
+            match check.supported {
+                Some(true) => out("green", &format!("{:<13}", "supported")),
+                Some(false) => out("yellow", &format!("{:<13}", "unsupported")),
+                None => out("red", &format!("{:<13}", "unknown")),
+            }
+            outln("white", &check.detail);
+        }
+        Ok(0)
+    }
+}
@@ -0,0 +1,87 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::data::DataClapOptions;
+use types::{DeliveryResult, ExitCode};
+use errors::{DeliveryError, Kind};
+use http::APIClient;
+use http::data_bag::DataBagScope;
+use utils::say::{sayln, outln};
+use utils::read_file;
+use std::path::PathBuf;
+use command::Command;
+use config::Config;
+
+pub struct DataCommand<'n> {
+    pub options: &'n DataClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for DataCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project());
+        let pipeline = try!(self.config.pipeline());
+        let scope = if self.options.change.is_empty() {
+            DataBagScope::Pipeline(&pipeline)
+        } else {
+            DataBagScope::Change(self.options.change)
+        };
+        let key = self.options.key;
+        let client = try!(APIClient::from_config(&self.config));
+
+        match self.options.action {
+            "get" => {
+                match try!(client.get_data_bag(&org, &proj, &scope, key)) {
+                    Some(content) => outln("white", &content),
+                    None => {
+                        sayln("red", &format!("No data found for '{}'", key));
+                        return Ok(1)
+                    }
+                }
+            },
+            "set" => {
+                let content = try!(self.content_to_set());
+                try!(client.set_data_bag(&org, &proj, &scope, key, &content));
+                sayln("green", &format!("Data '{}' saved", key));
+            },
+            "delete" => {
+                try!(client.delete_data_bag(&org, &proj, &scope, key));
+                sayln("green", &format!("Data '{}' deleted", key));
+            },
+            action => {
+                let msg = format!("Unknown data action '{}', use get, set or delete", action);
+                return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+            }
+        }
+        Ok(0)
+    }
+}
+
+impl<'n> DataCommand<'n> {
+    fn content_to_set(&self) -> DeliveryResult<String> {
+        match (self.options.value.is_empty(), self.options.file.is_empty()) {
+            (false, true) => Ok(self.options.value.to_string()),
+            (true, false) => read_file(&PathBuf::from(self.options.file)),
+            _ => {
+                let msg = "Provide the data to store either as an argument \
+                           or with --file, but not both".to_string();
+                Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+            }
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::deliver::DeliverClapOptions;
+use types::{DeliveryResult, ExitCode, ChangeId};
+use errors::{DeliveryError, Kind};
+use features::{self, SCHEDULED_DELIVERY};
+use http::APIClient;
+use http::change::{self, ScheduledDelivery};
+use schedule::{self, Entry, Schedule, Window};
+use utils::say::sayln;
+use utils::json_output;
+use command::Command;
+use config::Config;
+use time;
+
+pub struct DeliverCommand<'n> {
+    pub options: &'n DeliverClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for DeliverCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let org = try!(self.config.org_name());
+        let proj = try!(self.config.project_name());
+        let change = try!(ChangeId::new(self.options.change));
+        if self.options.at.is_empty() && self.options.window.is_empty() {
+            let client = try!(APIClient::from_config(&self.config));
+            try!(change::deliver(&client, &org, &proj, &change));
+            json_output::set("delivered", &change.as_str());
+            sayln("green", &format!("Change {} delivered", change));
+            return Ok(0)
+        }
+
+        let now = time::get_time().sec;
+        let earliest = if self.options.at.is_empty() {
+            now
+        } else {
+            try!(schedule::parse_at(self.options.at))
+        };
+        if earliest < now {
+            let msg = format!("{} is in the past, drop --at to deliver now",
+                              schedule::format_time(earliest));
+            return Err(DeliveryError::throw(Kind::InvalidSchedule, Some(msg)))
+        }
+        // The spec rather than the name is kept, so `schedule run` needs
+        // no cli.toml
+        let spec = if self.options.window.is_empty() {
+            None
+        } else {
+            Some(try!(schedule::window_spec(self.options.window, &self.config)))
+        };
+        let window = match spec {
+            Some(ref spec) => Some(try!(Window::parse(spec))),
+            None => None
+        };
+        let (at, until) = schedule::plan(earliest, window.as_ref());
+        let entry = Entry {
+            change: change.to_string(),
+            server_url: try!(self.config.server_base_url()),
+            user: try!(self.config.user()),
+            ent: try!(self.config.enterprise()),
+            org: org.to_string(),
+            project: proj.to_string(),
+            at: at,
+            window: spec,
+            until: until,
+            last_error: None,
+            attempts: 0,
+            retry_at: None,
+        };
+        json_output::set("scheduled_at", &schedule::format_time(at));
+
+        if features::is_enabled(&self.config, SCHEDULED_DELIVERY) {
+            let client = try!(APIClient::from_config(&self.config));
+            let delivery = ScheduledDelivery {
+                change: entry.change.clone(),
+                at: schedule::format_time(at),
+                until: until.map(schedule::format_time),
+            };
+            try!(change::schedule_delivery(&client, &org, &proj, &delivery));
+            sayln("green", &format!("The server will deliver change {} at {}",
+                                    change, entry.when()));
+        } else {
+            let path = try!(Schedule::path());
+            let mut local = try!(Schedule::load(&path));
+            local.add(entry.clone());
+            try!(local.save(&path));
+            sayln("green", &format!("Change {} is scheduled for delivery at {}",
+                                    change, entry.when()));
+            sayln("white", "  The server does not schedule deliveries, keep \
+                            `delivery schedule run` running to make it, or run \
+                            `delivery schedule run --once` from cron.");
+        }
+        Ok(0)
+    }
+}
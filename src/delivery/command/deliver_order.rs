@@ -0,0 +1,152 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use std::collections::HashMap;
+use cli::deliver_order::DeliverOrderClapOptions;
+use types::{DeliveryResult, ExitCode};
+use http::{self, budget, APIClient};
+use project::deliver_order::{self, ChangeRef};
+use utils::pool;
+use utils::say::{say, sayln, out, outln};
+use command::Command;
+use config::Config;
+
+pub struct DeliverOrderCommand<'n> {
+    pub options: &'n DeliverOrderClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for DeliverOrderCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project());
+        let pipe = try!(self.config.pipeline());
+        let changes = try!(deliver_order::parse_changes(self.options.changes, &proj));
+        let client = try!(APIClient::from_config(&self.config));
+
+        let concurrency = try!(pool::parse_concurrency(self.options.concurrency));
+
+        let projects: Vec<String> = changes.iter().map(|c| c.project.clone()).collect();
+        let deps = try!(fetch_dependencies(&client, &org, &pipe, projects, concurrency));
+        let ordered = try!(deliver_order::order(&changes, &deps));
+
+        sayln("white", "Safe delivery order:");
+        for (i, change) in ordered.iter().enumerate() {
+            out("white", &format!("  {}. ", i + 1));
+            outln("yellow", &format!("{}:{}", change.project, change.id));
+        }
+
+        if self.options.execute {
+            for change in ordered.iter() {
+                try!(deliver(&client, &org, change));
+            }
+            sayln("green", "All changes delivered");
+        }
+        sayln("white", &budget::report().summary());
+        Ok(0)
+    }
+}
+
+/// Fetch the dependencies of `projects` (of `org`) and of every project
+/// they reach, so that a project without a change to deliver still links
+/// the changes upstream and downstream of it. A dependency written as
+/// `org/project` is looked up in its own organization.
+pub fn fetch_dependencies(client: &APIClient, org: &str, pipe: &str, projects: Vec<String>,
+                          concurrency: usize) -> DeliveryResult<HashMap<String, Vec<String>>> {
+    let mut deps: HashMap<String, Vec<String>> = HashMap::new();
+    let mut pending: Vec<(String, String)> = projects.into_iter()
+        .map(|p| (org.to_string(), p))
+        .collect();
+    while !pending.is_empty() {
+        pending.sort();
+        pending.dedup();
+        let fetch_client = client.clone();
+        let fetch_pipe = pipe.to_string();
+        let fetched = pool::parallel_map(pending.clone(), concurrency, move |(o, p)| {
+            fetch_client.pipeline_dependencies(&o, &p, &fetch_pipe)
+        });
+        let mut reached = Vec::new();
+        for ((project_org, project), d) in pending.into_iter().zip(fetched.into_iter()) {
+            let d = try!(d);
+            debug!("{}/{} depends on {:?}", project_org, project, d);
+            reached.extend(d.iter().map(|d| {
+                let (o, p) = deliver_order::dependency_project(d, &project_org);
+                (o.to_string(), p.to_string())
+            }));
+            deps.insert(project, d);
+        }
+        pending = reached.into_iter().filter(|&(_, ref p)| !deps.contains_key(p)).collect();
+    }
+    Ok(deps)
+}
+
+fn deliver(client: &APIClient, org: &str, change: &ChangeRef) -> DeliveryResult<()> {
+    say("white", "Delivering ");
+    sayln("yellow", &format!("{}:{}", change.project, change.id));
+    http::change::deliver(client, org, &change.project, &change.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, SERVER_ADDRESS};
+    use http::APIClient;
+    use project::deliver_order::{self, ChangeRef};
+
+    fn change(project: &str, id: &str) -> ChangeRef {
+        ChangeRef { project: project.to_string(), id: id.to_string() }
+    }
+
+    #[test]
+    fn test_fetch_dependencies_in_their_own_organization() {
+        // app -> shared/lib -> util, util being in the shared organization
+        let path = "/api/v0/e/deliver-order-orgs/orgs";
+        for &(org, project, deps) in [("acme", "app", "[\"shared/lib\"]"),
+                                      ("shared", "lib", "[\"util\"]"),
+                                      ("shared", "util", "[]")].iter() {
+            mock("GET", &format!("{}/{}/projects/{}/pipelines/master/dependencies",
+                                 path, org, project))
+                .with_status(200)
+                .with_body(&format!("{{\"dependencies\": {}}}", deps))
+                .create();
+        }
+        let client = APIClient::new_http(SERVER_ADDRESS, "deliver-order-orgs");
+        let deps = fetch_dependencies(&client, "acme", "master",
+                                      vec!["app".to_string()], 2).unwrap();
+        assert_eq!(Some(&vec!["util".to_string()]), deps.get("lib"));
+        assert_eq!(Some(&Vec::<String>::new()), deps.get("util"));
+    }
+
+    #[test]
+    fn test_fetch_dependencies_through_projects_without_changes() {
+        // web -> api -> db, with changes on web and db only
+        let path = "/api/v0/e/deliver-order/orgs/acme/projects";
+        for &(project, deps) in [("web", "[\"api\"]"), ("api", "[\"acme/db\"]"),
+                                 ("db", "[]")].iter() {
+            mock("GET", &format!("{}/{}/pipelines/master/dependencies", path, project))
+                .with_status(200)
+                .with_body(&format!("{{\"dependencies\": {}}}", deps))
+                .create();
+        }
+        let client = APIClient::new_http(SERVER_ADDRESS, "deliver-order");
+        let changes = vec![change("web", "1"), change("db", "2")];
+        let projects = changes.iter().map(|c| c.project.clone()).collect();
+        let deps = fetch_dependencies(&client, "acme", "master", projects, 2).unwrap();
+        assert_eq!(Some(&vec!["acme/db".to_string()]), deps.get("api"));
+        assert_eq!(vec![change("db", "2"), change("web", "1")],
+                   deliver_order::order(&changes, &deps).unwrap());
+    }
+}
@@ -21,6 +21,7 @@ use git;
 use cli::diff::DiffClapOptions;
 use types::{DeliveryResult, ExitCode};
 use utils::say::{say, sayln};
+use utils::json_output;
 use command::Command;
 use config::Config;
 
@@ -32,8 +33,8 @@ pub struct DiffCommand<'n> {
 impl<'n> Command for DiffCommand<'n> {
     fn setup(&self, child_processes: &mut Vec<std::process::Child>) -> DeliveryResult<()> {
         if !self.options.local {
+            try!(super::verify_and_repair_git_remote(&self.config, self.options.fix_remote));
             if self.config.fips.unwrap_or(false) {
-                try!(super::verify_and_repair_git_remote(&self.config));
                 try!(fips::setup_and_start_stunnel(&self.config, child_processes));
             }
         }
@@ -50,13 +51,30 @@ impl<'n> Command for DiffCommand<'n> {
         say("white", " targeted for pipeline ");
         say("magenta", &target);
 
-        if self.options.patchset == "latest" {
-            sayln("white", " latest patchset");
+        let patchset = match self.options.target_patchset() {
+            "" | "latest" => {
+                say("white", " latest patchset");
+                "latest"
+            },
+            p @ _ => {
+                say("white", " at patchset ");
+                say("yellow", p);
+                p
+            }
+        };
+        let base = self.options.base_patchset();
+        if base.is_empty() {
+            sayln("white", "");
         } else {
-            say("white", " at patchset ");
-            sayln("yellow", self.options.patchset);
+            say("white", " against patchset ");
+            sayln("yellow", base);
+            json_output::set("from_patchset", &base);
         }
-        try!(git::diff(self.options.change, self.options.patchset, &target, &self.options.local));
+        json_output::set("change", &self.options.change);
+        json_output::set("patchset", &patchset);
+        json_output::set("pipeline", &target);
+        try!(git::diff(self.options.change, patchset, &target, &self.options.local,
+                       base, self.options.mode(), self.options.json));
         Ok(0)
     }
 }
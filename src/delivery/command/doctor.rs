@@ -0,0 +1,256 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::process::Stdio;
+use hyper::status::StatusCode;
+use cli::doctor::DoctorClapOptions;
+use types::{DeliveryResult, ExitCode};
+use http::{APIClient, APIAuth};
+use http::diagnostics::{self, Finding};
+use utils::{self, read_file};
+use utils::say::{say, sayln};
+use command::Command;
+use config::Config;
+use git;
+use project;
+
+pub struct DoctorCommand<'n> {
+    pub options: &'n DoctorClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for DoctorCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        sayln("green", "Chef Delivery");
+        sayln("white", "Checking the local environment");
+        let mut problems = report(&environment_findings());
+
+        match (self.config.api_host_and_port(), self.config.enterprise(), self.config.user()) {
+            (Ok(server), Ok(ent), Ok(user)) => {
+                say("white", "Checking authentication of ");
+                say("yellow", &format!("{}@{}", user, ent));
+                say("white", " against ");
+                sayln("yellow", &server);
+                problems |= report(&try!(self.auth_findings()));
+
+                sayln("white", "Checking SSH access to the git server");
+                problems |= report(&[self.ssh_finding()]);
+            },
+            _ => {
+                problems |= report(&[Finding {
+                    problem: true,
+                    message: "The server, enterprise and user are not all configured; pass \
+                              them or run `delivery setup`".to_string()
+                }]);
+            }
+        }
+
+        if problems {
+            Ok(1)
+        } else {
+            Ok(0)
+        }
+    }
+}
+
+impl<'n> DoctorCommand<'n> {
+    fn auth_findings(&self) -> DeliveryResult<Vec<Finding>> {
+        let mut findings = Vec::new();
+        let mut client = try!(APIClient::from_config_no_auth(&self.config));
+        // Never prompt here, a missing token is one of the findings
+        let has_token = match APIAuth::from_stored_token(&self.config) {
+            Ok(auth) => {
+                client.set_auth(auth);
+                true
+            },
+            Err(_) => {
+                findings.push(Finding {
+                    problem: true,
+                    message: "No token is stored for this user; run `delivery token` \
+                              to get one".to_string()
+                });
+                false
+            }
+        };
+
+        let response = match client.get("orgs") {
+            Ok(response) => response,
+            Err(e) => {
+                findings.push(Finding {
+                    problem: true,
+                    message: format!("Unable to reach the server: {}", e)
+                });
+                return Ok(findings)
+            }
+        };
+        findings.extend(diagnostics::clock_finding(diagnostics::clock_skew(&response.headers)));
+        if has_token {
+            findings.push(diagnostics::token_finding(diagnostics::token_age()).unwrap_or(Finding {
+                problem: false,
+                message: "The age of your token is unknown, it was saved by an older \
+                          version of delivery".to_string()
+            }));
+            findings.push(match response.status {
+                StatusCode::Ok => Finding {
+                    problem: false,
+                    message: "The server accepts your token".to_string()
+                },
+                StatusCode::Unauthorized => Finding {
+                    problem: true,
+                    message: "The server rejects your token; run `delivery token` \
+                              to get a new one".to_string()
+                },
+                status => Finding {
+                    problem: true,
+                    message: format!("The server answered an unexpected {}", status)
+                },
+            });
+        }
+        Ok(findings)
+    }
+
+    // ssh exits with 255 when it could not connect or authenticate, with
+    // whatever the git server answered otherwise.
+    fn ssh_finding(&self) -> Finding {
+        let (host, port, user, ent) = match (self.config.server_host(), self.config.git_port(),
+                                             self.config.user(), self.config.enterprise()) {
+            (Ok(h), Ok(p), Ok(u), Ok(e)) => (h, p, u, e),
+            _ => return Finding {
+                problem: true,
+                message: "The git port is not configured".to_string()
+            }
+        };
+        let login = format!("{}@{}@{}", user, ent, host);
+        let mut command = utils::make_command("ssh");
+        command.args(&["-T", "-o", "BatchMode=yes", "-o", "ConnectTimeout=10", "-p", &port, &login])
+            .stdin(Stdio::null());
+        match command.output() {
+            Err(_) => Finding {
+                problem: true,
+                message: "ssh is not on your PATH; it is needed to push to the \
+                          git server".to_string()
+            },
+            Ok(ref output) if output.status.code() == Some(255) => Finding {
+                problem: true,
+                message: format!("Unable to connect with SSH to {} on port {}: {}; make sure \
+                                  your public key is in your Automate profile", login, port,
+                                 String::from_utf8_lossy(&output.stderr).trim())
+            },
+            Ok(_) => Finding {
+                problem: false,
+                message: format!("SSH access to {} on port {} works", login, port)
+            }
+        }
+    }
+}
+
+// The tools, config and git remote the other commands rely on
+fn environment_findings() -> Vec<Finding> {
+    let mut findings = vec![tool_finding("git", "by every command working with a project"),
+                            tool_finding("chef", "to generate build cookbooks and run \
+                                                  phases locally"),
+                            cli_toml_finding()];
+    findings.extend(delivery_remote_finding());
+    findings
+}
+
+fn tool_finding(tool: &str, needed_for: &str) -> Finding {
+    match utils::find_command(tool) {
+        Some(path) => Finding {
+            problem: false,
+            message: format!("{} found at {}", tool, path.display())
+        },
+        None => Finding {
+            problem: true,
+            message: format!("{} is not on your PATH; it is needed {}", tool, needed_for)
+        }
+    }
+}
+
+// Loading the config falls back to the defaults when cli.toml does not
+// parse, parse it again here to tell.
+fn cli_toml_finding() -> Finding {
+    match Config::dot_delivery_cli_path(&utils::cwd()) {
+        None => Finding {
+            problem: true,
+            message: "No .delivery/cli.toml in this directory or its parents; run \
+                      `delivery setup`".to_string()
+        },
+        Some(path) => match read_file(&path).and_then(|toml| Config::parse_config(&toml)) {
+            Ok(_) => Finding {
+                problem: false,
+                message: format!("{} parses", path.display())
+            },
+            Err(e) => Finding {
+                problem: true,
+                message: format!("{} does not parse: {}", path.display(),
+                                 e.detail().unwrap_or(e.to_string()))
+            }
+        }
+    }
+}
+
+// Only checked inside a repository, outside of one there is no remote
+// to look for.
+fn delivery_remote_finding() -> Option<Finding> {
+    let project_path = match project::project_path() {
+        Ok(path) => path,
+        Err(_) => return None
+    };
+    let url = git::delivery_remote_from_repo(&project_path).unwrap_or_default();
+    if url.is_empty() {
+        return Some(Finding {
+            problem: true,
+            message: "This repository has no 'delivery' git remote; run `delivery init` \
+                      or `delivery setup`".to_string()
+        })
+    }
+    let mut command = utils::make_command("git");
+    command.args(&["ls-remote", "--heads", "delivery"])
+        .env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes -o ConnectTimeout=10")
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .current_dir(&project_path)
+        .stdin(Stdio::null());
+    Some(match command.output() {
+        Ok(ref output) if output.status.success() => Finding {
+            problem: false,
+            message: format!("The 'delivery' git remote ({}) is reachable", url)
+        },
+        Ok(output) => Finding {
+            problem: true,
+            message: format!("The 'delivery' git remote ({}) is not reachable: {}", url,
+                             String::from_utf8_lossy(&output.stderr).trim())
+        },
+        Err(e) => Finding {
+            problem: true,
+            message: format!("Unable to run git: {}", e)
+        }
+    })
+}
+
+// Print the findings, returns whether any of them is a problem
+fn report(findings: &[Finding]) -> bool {
+    for f in findings.iter() {
+        if f.problem {
+            say("red", "  ✘ ");
+        } else {
+            say("green", "  ✔ ");
+        }
+        sayln("white", &f.message);
+    }
+    findings.iter().any(|f| f.problem)
+}
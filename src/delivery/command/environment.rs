@@ -0,0 +1,77 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::environment::EnvClapOptions;
+use types::{DeliveryResult, ExitCode};
+use project::environment::{self, Difference};
+use utils::say::{sayln, outln};
+use utils::json_output;
+use serde_json::{self, Value};
+use command::Command;
+use config::Config;
+
+pub struct EnvCommand<'n> {
+    pub options: &'n EnvClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for EnvCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let ent = try!(self.config.enterprise());
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project());
+        let pipeline = try!(self.config.pipeline());
+        let from = environment::environment_name(self.options.from, &ent, &org,
+                                                 &proj, &pipeline);
+        let to = environment::environment_name(self.options.to, &ent, &org,
+                                               &proj, &pipeline);
+
+        let differences = environment::diff(&try!(environment::fetch(&from)),
+                                            &try!(environment::fetch(&to)));
+        if json_output::enabled() {
+            json_output::set("from", &from);
+            json_output::set("to", &to);
+            json_output::set("differences", &differences);
+            return Ok(0)
+        }
+
+        if differences.is_empty() {
+            sayln("green", &format!("The environments {} and {} deploy the same \
+                                     cookbook versions and attributes", from, to));
+            return Ok(0)
+        }
+        outln("white", &format!("--- {}\n+++ {}", from, to));
+        for difference in differences.iter() {
+            print_difference(difference);
+        }
+        Ok(0)
+    }
+}
+
+fn print_difference(difference: &Difference) {
+    outln("white", &difference.path);
+    if let Some(ref value) = difference.from {
+        outln("red", &format!("  - {}", to_string(value)));
+    }
+    if let Some(ref value) = difference.to {
+        outln("green", &format!("  + {}", to_string(value)));
+    }
+}
+
+fn to_string(value: &Value) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
@@ -0,0 +1,64 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::features::FeaturesClapOptions;
+use types::{DeliveryResult, ExitCode};
+use features;
+use utils::say::{sayln, out, outln};
+use utils::json_output;
+use utils::template;
+use command::Command;
+use config::Config;
+
+pub struct FeaturesCommand<'n> {
+    pub options: &'n FeaturesClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for FeaturesCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let template = try!(template::from_format_option(self.options.format));
+        let features = try!(features::load(&self.config, self.options.refresh));
+        if let Some(template) = template {
+            let items: Vec<_> = features.flags.iter().map(|(name, enabled)| json!({
+                "name": name,
+                "enabled": enabled
+            })).collect();
+            return template.print_each(&items).map(|_| 0)
+        }
+        if self.options.json {
+            json_output::set("features", &features.flags);
+            return Ok(0)
+        }
+
+        sayln("white", &format!("Feature flags of enterprise {} on {}:",
+                                try!(self.config.enterprise()),
+                                try!(self.config.api_host_and_port())));
+        if features.flags.is_empty() {
+            sayln("white", "  The server does not advertise any feature flag");
+        }
+        for (name, enabled) in features.flags.iter() {
+            out("white", &format!("  {}: ", name));
+            if *enabled {
+                outln("green", "enabled");
+            } else {
+                outln("yellow", "disabled");
+            }
+        }
+        Ok(0)
+    }
+}
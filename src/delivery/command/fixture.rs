@@ -0,0 +1,58 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use std::path::PathBuf;
+use cli::fixture::FixtureClapOptions;
+use types::{DeliveryResult, ExitCode};
+use git::{self, fixture};
+use utils;
+use utils::say::outln;
+use utils::json_output;
+use command::Command;
+use config::Config;
+
+// Who authors the initial commit when the cli.toml does not say, as
+// build nodes seldom have a git identity
+const FIXTURE_USER_NAME: &'static str = "Delivery Fixture";
+const FIXTURE_EMAIL: &'static str = "fixture@delivery.invalid";
+
+pub struct FixtureCommand<'n> {
+    pub options: &'n FixtureClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for FixtureCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let dir = if self.options.dir.is_empty() {
+            utils::cwd()
+        } else {
+            PathBuf::from(self.options.dir)
+        };
+        let mut identity = git::CommitIdentity::from_config(&self.config);
+        identity.name = identity.name.or(Some(FIXTURE_USER_NAME.to_string()));
+        identity.email = identity.email.or(Some(FIXTURE_EMAIL.to_string()));
+
+        let repo = try!(fixture::create(&dir, self.options.name, self.options.branch,
+                                        &identity));
+        json_output::set("bare", &repo.bare.to_string_lossy());
+        json_output::set("clone", &repo.clone.to_string_lossy());
+        json_output::set("branch", &repo.branch);
+        outln("green", &format!("Created the bare repository {}", repo.bare.display()));
+        outln("green", &format!("Cloned it to {}, branch {}", repo.clone.display(),
+                                repo.branch));
+        Ok(0)
+    }
+}
@@ -0,0 +1,53 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use cli::git_credential::GitCredentialClapOptions;
+use types::{DeliveryResult, ExitCode};
+use git::credential::Credential;
+use token::TokenStore;
+use command::Command;
+use config::Config;
+
+pub struct GitCredentialCommand<'n> {
+    pub options: &'n GitCredentialClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for GitCredentialCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let mut input = String::new();
+        try!(io::stdin().read_to_string(&mut input));
+        // Tokens are only ever stored by `delivery token`, so there is
+        // nothing to do when git asks us to store or erase a credential.
+        if self.options.operation != "get" {
+            return Ok(0)
+        }
+        let request = Credential::parse(&input);
+        let tstore = match self.config.token_file {
+            Some(ref f) => try!(TokenStore::from_file(&PathBuf::from(f))),
+            None => try!(TokenStore::from_home())
+        };
+        // Git reads our stdout, so this must never go through `say`.
+        if let Some(credential) = request.fill_from(&tstore.stored_tokens()) {
+            debug!("Answering git with the token of {:?}", credential.username);
+            try!(io::stdout().write_all(credential.to_output().as_bytes()));
+        }
+        Ok(0)
+    }
+}
@@ -24,23 +24,68 @@ use std;
 use fips;
 use cli::init::InitClapOptions;
 use delivery_config::{BuildCookbookLocation, DeliveryConfig};
+use delivery_config::sync::{self, FileSync};
 use config::Config;
 use std::path::{Path, PathBuf};
 use std::fmt::Debug;
 use project;
+use project::pipeline_template;
+use project::rollback::Journal;
 use git;
 use utils;
-use utils::say::{say, sayln};
-use std::io;
+use utils::say::sayln;
+use utils::prompt::Prompt;
+use utils::json_output;
+use utils::strict::{self, Warning};
+use utils::pool::DEFAULT_CONCURRENCY;
+use std::io::prelude::*;
+use std::fs::File;
+use serde_json;
+use tempdir::TempDir;
 use http::APIClient;
+use http::tls::Tls;
 use errors::{Kind, DeliveryError};
-use types::{DeliveryResult, ExitCode};
+use types::{DeliveryResult, ExitCode, OrgName, ProjectName, PipelineName};
 use hyper::status::StatusCode;
-use command::Command;
+use command::{Command, check_shallow_clone, check_line_endings};
+
+/// What `delivery init` did, written to `.delivery/init-summary.json`
+/// for provisioning automation to read instead of scraping stdout. The
+/// file is kept out of git through `.git/info/exclude`.
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct InitSummary {
+    pub project: String,
+    pub project_created: bool,
+    pub pipeline: String,
+    pub pipeline_created: bool,
+    pub remote_url: Option<String>,
+    pub generator: Option<String>,
+    pub generator_version: Option<String>,
+    pub custom_config: bool,
+    pub pipeline_template: Option<String>,
+    pub branch: Option<String>,
+    pub review_url: Option<String>,
+}
+
+impl InitSummary {
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join(".delivery").join("init-summary.json")
+    }
+
+    pub fn write(&self, dir: &Path) -> DeliveryResult<PathBuf> {
+        let path = InitSummary::path(dir);
+        try!(utils::mkdir_recursive(&dir.join(".delivery")));
+        let mut f = try!(File::create(&path));
+        try!(f.write_all(try!(serde_json::to_string_pretty(&self)).as_bytes()));
+        Ok(path)
+    }
+}
 
 pub struct InitCommand<'n> {
     pub options: &'n InitClapOptions<'n>,
     pub config: &'n Config,
+    /// The source code provider answered with --interactive
+    pub scp: Option<project::SourceCodeProvider>,
 }
 
 impl<'n> Command for InitCommand<'n> {
@@ -55,6 +100,7 @@ impl<'n> Command for InitCommand<'n> {
 
     fn run(&self) -> DeliveryResult<ExitCode> {
         sayln("green", "Chef Delivery");
+        try!(super::refuse_in_managed_workspace(self.options.i_know_what_im_doing));
 
         let branch = try!(self.config.pipeline());
 
@@ -65,45 +111,119 @@ impl<'n> Command for InitCommand<'n> {
                 return Ok(1)
             }
 
-        let scp = if !self.options.github_org_name.is_empty() {
-            Some(
-                try!(project::SourceCodeProvider::new("github", &self.options.repo_name,
-                                                      &self.options.github_org_name, &branch,
-                                                      self.options.no_v_ssl))
-            )
-        } else if !self.options.bitbucket_project_key.is_empty() {
-            Some(
-                try!(project::SourceCodeProvider::new("bitbucket", &self.options.repo_name,
-                                                      &self.options.bitbucket_project_key,
-                                                      &branch, true))
-            )
+        let scp = try!(self.scp(&branch));
+
+        if self.options.dry_run {
+            sayln("yellow", "Dry run: nothing is changed in the repository or on the server.");
+        }
+
+        let summary = InitSummary {
+            project: try!(self.config.project()),
+            pipeline: branch.clone(),
+            ..InitSummary::default()
+        };
+
+        // Without a worktree only the server side can be set up.
+        let result = if self.options.no_local_repo || git::is_bare_repository(&utils::cwd()) {
+            self.init_on_server_only(scp, summary)
         } else {
-            None
+            // Initalize the repo.
+            let project_path = try!(project::project_path());
+            let mut journal = Journal::begin(&project_path);
+            let result = self.init_repo(&project_path, scp, summary, &mut journal);
+            match result {
+                Ok(0) => sayln("green", "\nYour new Delivery project is ready!"),
+                _ if self.options.dry_run => {},
+                _ => self.roll_back(&journal)
+            }
+            result
         };
+        if self.options.dry_run && result.is_ok() {
+            sayln("yellow", "\nRun the command again without --dry-run to initialize the project.");
+        }
+        result
+    }
+}
 
-        // Initalize the repo.
-        let project_path = try!(project::project_path());
-        project::create_dot_delivery();
+impl<'n> InitCommand<'n> {
+    // Everything init does in the local repository, recording in the
+    // journal what it changes so a failure can be rolled back. With
+    // --dry-run each step only says what it would do.
+    fn init_repo(&self, project_path: &PathBuf, scp: Option<project::SourceCodeProvider>,
+                 mut summary: InitSummary, journal: &mut Journal) -> DeliveryResult<ExitCode> {
+        let dry_run = self.options.dry_run;
+        journal.remember_dir(Path::new(".delivery"));
+        if !dry_run {
+            try!(project::create_dot_delivery());
+        } else if !project_path.join(".delivery").exists() {
+            would("dot-delivery", "create the .delivery directory");
+        }
 
+        if dry_run && self.options.unshallow && git::is_shallow_repository(project_path) {
+            would("unshallow", "fetch the full history of this shallow clone");
+        } else {
+            try!(check_shallow_clone(project_path, self.options.unshallow));
+        }
+        try!(check_line_endings(project_path));
         if !self.options.local {
-            try!(create_on_server(&self.config, scp.clone()))
+            journal.remember_remote("delivery");
+            let takeover = try!(self.options.takeover());
+            if takeover == project::Takeover::Adopt {
+                journal.remember_branch(&try!(self.config.pipeline()));
+            }
+            try!(create_on_server(&self.config, scp.clone(), &mut summary,
+                                  self.options.force_push, takeover, dry_run))
         }
 
-        // Generate build cookbook, either custom or default.
-        let custom_build_cookbook_generated = if !self.options.skip_build_cookbook {
-            try!(generate_build_cookbook(&self.config))
-        } else {
+        // Generate build cookbook, either custom or default, unless a
+        // pipeline template brings its own.
+        let template = self.config.pipeline_template().ok();
+        let custom_build_cookbook_generated = if self.options.skip_build_cookbook {
             false
+        } else if template.is_some() {
+            skipping("build-cookbook", "The pipeline template provides the build cookbook.");
+            false
+        } else {
+            journal.remember_dir(Path::new(".delivery/build_cookbook"));
+            try!(generate_build_cookbook(&self.config, self.options.generator_refresh, dry_run))
         };
 
         // Generate delivery config if passed
-        let custom_config_passed = try!(
-            generate_delivery_config(self.config.config_json().ok())
+        journal.remember_file(&DeliveryConfig::config_file_path(project_path));
+        if let Ok(dir) = self.config.config_dir() {
+            for file in try!(sync::files_under(Path::new(&dir))) {
+                journal.remember_file(&Path::new(".delivery").join(file));
+            }
+        }
+        let custom_json_passed = try!(
+            generate_delivery_config(self.config.config_json().ok(), dry_run)
         );
+        let mut synced_files = try!(sync_delivery_config_dir(self.config.config_dir().ok(),
+                                                             dry_run));
+        if let Some(ref template) = template {
+            synced_files.extend(try!(apply_pipeline_template(&self.config, template,
+                                                             self.options.generator_refresh,
+                                                             journal, dry_run)));
+            summary.pipeline_template = Some(template.clone());
+        }
+        // A dry run does not fetch the template, assume it brings some config.
+        let custom_config_passed = custom_json_passed || !synced_files.is_empty()
+            || (dry_run && template.is_some());
+        summary.custom_config = custom_config_passed;
+        if custom_build_cookbook_generated {
+            summary.generator = self.config.generator().ok();
+            summary.generator_version = summary.generator.as_ref()
+                .and_then(|g| generator_version(g));
+        }
 
-        // Verify that the project has a config file
-        let config_path = DeliveryConfig::config_file_path(&project_path);
-        if !config_path.exists() {
+        // Verify that the project has a config file. A dry run generated
+        // nothing, so it assumes the generators and the custom config
+        // bring one.
+        let config_path = DeliveryConfig::config_file_path(project_path);
+        let config_planned = dry_run && (custom_build_cookbook_generated || custom_config_passed
+            || (!self.options.skip_build_cookbook
+                && !project_path.join(".delivery/build_cookbook").exists()));
+        if !config_path.exists() && !config_planned {
             // Custom error handling for missing config file.
             if custom_build_cookbook_generated && !custom_config_passed {
                 sayln("red", "\nYou used a custom build cookbook generator, but \
@@ -112,13 +232,7 @@ impl<'n> Command for InitCommand<'n> {
                               .delivery/config.json or pass in a custom config.");
                 return Ok(1)
             } else {
-                let msg = "Missing .delivery/config.json file.\nPlease use a \
-                           custom build cookbook generator that creates this \
-                           file or pass in a custom config.".to_string();
-                return Err(DeliveryError{
-                    kind: Kind::MissingConfigFile,
-                    detail: Some(msg)
-                });
+                return Err(missing_config_file())
             }
         }
 
@@ -126,72 +240,238 @@ impl<'n> Command for InitCommand<'n> {
         // will put the commits in the initialize-delivery-pipeline branch, otherwise,
         // if a custom build cookbook was generated or a custom config was passed,
         // commits will land in the add-delivery-config branch.
+        let identity = git::CommitIdentity::from_config(&self.config);
         let branch_name;
         let mut review_needed = false;
         if custom_build_cookbook_generated || custom_config_passed {
             branch_name = "add-delivery-config";
             review_needed = true;
             sayln("cyan", "Committing unmerged Delivery content and submitting for review...");
-            if !try!(project::create_feature_branch_if_missing(&project_path, branch_name)) {
-                sayln("white", &format!("  Skipping: A branch named '{}' already exists, \
-                                         switching to it.", branch_name))
-            } else {
-                sayln("green", &format!("  Feature branch named '{}' created.", branch_name))
-            }
+            journal.remember_branch(branch_name);
+            try!(self.feature_branch(project_path, branch_name));
 
             if custom_build_cookbook_generated {
-                if try!(project::add_commit_build_cookbook(&custom_config_passed)) {
+                if dry_run {
+                    would("commit-build-cookbook", "commit the custom build cookbook");
+                } else if try!(project::add_commit_build_cookbook(&custom_config_passed,
+                                                                   &identity)) {
                     sayln("green", "  Custom build cookbook committed to feature branch.")
                 } else {
-                    sayln("white", "  Skipping: Build cookbook was not modified, no need to commit.");
+                    skipping("commit-build-cookbook",
+                             "Build cookbook was not modified, no need to commit.");
                 }
             }
 
             // project::add_commit_build_cookbook will commit the custom config for us,
             // so if a custom build cookbook was passed, the delivery config was already committed.
             if custom_config_passed && !custom_build_cookbook_generated {
-                if try!(DeliveryConfig::git_add_commit_config(&project_path)) {
+                if dry_run {
+                    would("commit-delivery-config", "commit the custom delivery config");
+                } else if try!(DeliveryConfig::git_add_commit_config(project_path, &synced_files,
+                                                                      &identity)) {
                     sayln("green", "  Custom delivery config committed to feature branch.")
                 } else {
-                    sayln("white", "  Skipping: Delivery config was not modified, no need to commit.");
+                    skipping("commit-delivery-config",
+                             "Delivery config was not modified, no need to commit.");
                 }
             }
         } else {
             branch_name = "initialize-delivery-pipeline";
             // Create a commit to send to review.
             sayln("cyan", "Creating and committing DELIVERY.md readme...");
-            if !try!(project::create_feature_branch_if_missing(&project_path, branch_name)) {
-                sayln("white", &format!("  Skipping: A branch named '{}' already exists, \
-                                         switching to it.", branch_name))
-            } else {
-                sayln("green", &format!("  Feature branch named '{}' created.", branch_name))
-            }
+            journal.remember_branch(branch_name);
+            try!(self.feature_branch(project_path, branch_name));
 
             // Create and commit DELIVERY.md readme if it doesn't exist.
-            if try!(project::create_delivery_readme()) {
+            journal.remember_file(Path::new("DELIVERY.md"));
+            if dry_run && !project_path.join("DELIVERY.md").exists() {
+                review_needed = true;
+                would("delivery-readme", "create and commit DELIVERY.md");
+            } else if !dry_run && try!(project::create_delivery_readme()) {
                 review_needed = true;
                 sayln("green", "  DELIVERY.md created.");
-                try!(project::commit_delivery_readme());
+                try!(project::commit_delivery_readme(&identity));
                 sayln("green", &format!("  DELIVERY.md committed in branch '{}'.", branch_name))
             } else {
-                sayln("white", "  Skipping: DELIVERY.md already exists, no need to create or commit.");
+                skipping("delivery-readme",
+                         "DELIVERY.md already exists, no need to create or commit.");
             }
 
         }
 
+        if self.options.readme_badge {
+            sayln("cyan", "Adding pipeline status badge to the README...");
+            if let Some(readme) = project::readme_path(project_path) {
+                journal.remember_file(&readme);
+            }
+            if dry_run {
+                match try!(project::readme_missing_badge(self.config, project_path)) {
+                    Some(readme) => {
+                        would("readme-badge", &format!("add the badge to {} and commit it",
+                                                       readme.display()));
+                        review_needed = true;
+                    },
+                    None => skipping("readme-badge", "The README already has the badge \
+                                                      or there is no README.")
+                }
+            } else {
+                match try!(project::inject_readme_badge(&self.config, project_path)) {
+                    Some(readme) => {
+                        if try!(project::commit_readme_badge(&readme, &identity)) {
+                            review_needed = true;
+                        }
+                        sayln("green", &format!("  Badge committed in branch '{}'.", branch_name))
+                    },
+                    None => skipping("readme-badge", "The README already has the badge \
+                                                      or there is no README.")
+                }
+            }
+        }
+
         // Trigger review if there were any custom commits to review.
         if !self.options.local {
-            if review_needed {
+            if review_needed && dry_run {
+                would("review", &format!("submit the feature branch '{}' for review",
+                                         branch_name));
+            } else if review_needed {
                 sayln("cyan", &format!("Submitting feature branch '{}' for review...", branch_name));
-                try!(trigger_review(self.config, scp, &self.options.no_open));
+                summary.review_url = try!(trigger_review(self.config, scp,
+                                                         &self.options.no_open));
             } else {
-                sayln("white", "  Skipping: All changes have already be submitted for review, skipping.");
+                skipping("review", "All changes have already be submitted for review, skipping.");
             }
         } else {
-            sayln("white", " Skipping:  You passed --local, skipping review submission.");
+            skipping("review", "You passed --local, skipping review submission.");
         }
 
-        sayln("green", "\nYour new Delivery project is ready!");
+        summary.branch = Some(branch_name.to_string());
+        journal.remember_file(&InitSummary::path(project_path));
+        try!(write_summary(&summary, project_path, dry_run));
+        if !dry_run {
+            try!(git::exclude(project_path, "/.delivery/init-summary.json"));
+        }
+        Ok(0)
+    }
+
+    // Create the feature branch, or switch to it when it already exists.
+    fn feature_branch(&self, project_path: &PathBuf, branch_name: &str) -> DeliveryResult<()> {
+        if self.options.dry_run {
+            if git::branch_exists(branch_name, project_path) {
+                would("feature-branch", &format!("switch to the existing branch '{}'",
+                                                 branch_name));
+            } else {
+                would("feature-branch", &format!("create the feature branch '{}'", branch_name));
+            }
+        } else if !try!(project::create_feature_branch_if_missing(project_path, branch_name)) {
+            skipping("feature-branch", &format!("A branch named '{}' already exists, \
+                                                 switching to it.", branch_name))
+        } else {
+            sayln("green", &format!("  Feature branch named '{}' created.", branch_name))
+        }
+        Ok(())
+    }
+
+    // The source code provider answered with --interactive, else the one
+    // of --github or --bitbucket, else Delivery. The upstream remote never
+    // picks the provider; when it is on the one the flags name, it only
+    // fills in the repository --repo-name leaves out.
+    fn scp(&self, branch: &str) -> DeliveryResult<Option<project::SourceCodeProvider>> {
+        if self.scp.is_some() {
+            return Ok(self.scp.clone())
+        }
+        let detected = if self.options.no_detect_scp {
+            None
+        } else {
+            // Without a worktree there is no remote to look at
+            project::detect_scp(&self.config).unwrap_or(None)
+        };
+        let (kind, org) = if !self.options.github_org_name.is_empty() {
+            ("github", self.options.github_org_name.to_string())
+        } else if !self.options.bitbucket_project_key.is_empty() {
+            ("bitbucket", self.options.bitbucket_project_key.to_string())
+        } else {
+            if let Some(d) = detected.as_ref().filter(|d| d.provider != "gitlab") {
+                sayln("white", &format!("The upstream remote is on {}; pass --{} {} \
+                                         to use it as source code provider",
+                                        d.provider, d.provider, d.organization));
+            }
+            return Ok(None)
+        };
+        let repo = match detected {
+            _ if !self.options.repo_name.is_empty() => self.options.repo_name.to_string(),
+            Some(ref d) if d.provider == kind && d.organization.eq_ignore_ascii_case(&org) => {
+                sayln("white", &format!("Using the repository {} of the upstream remote \
+                                         (--no-detect-scp to leave it out)", d.repo_name));
+                d.repo_name.clone()
+            },
+            _ => String::new()
+        };
+        let no_ssl = kind != "github" || self.options.no_v_ssl;
+        let scp = try!(project::SourceCodeProvider::new(kind, &repo, &org, branch, no_ssl));
+        Ok(Some(scp))
+    }
+
+    fn roll_back(&self, journal: &Journal) {
+        if self.options.no_rollback {
+            sayln("yellow", "\nLeaving the repository as it is, you passed --no-rollback.");
+            return
+        }
+        sayln("cyan", "\nRolling back the changes made to the repository...");
+        for step in journal.rollback() {
+            match step {
+                Ok(done) => sayln("white", &format!("  {}.", done)),
+                Err(e) => sayln("red", &format!("  Unable to roll back: {} {}", e,
+                                                e.detail().unwrap_or_default())),
+            }
+        }
+        sayln("white", "  Projects and pipelines created on the server, and the \
+                        history pushed to it, are kept.");
+    }
+
+    // Create the project, and its pipeline when Delivery is the SCP, then
+    // print the git remote to push to: the repository on GitHub or
+    // Bitbucket when the project is backed by one, else 'delivery'. This is what bare repositories and
+    // automation that provisions projects before any code exists get.
+    fn init_on_server_only(&self, scp: Option<project::SourceCodeProvider>,
+                           mut summary: InitSummary) -> DeliveryResult<ExitCode> {
+        if self.options.local {
+            return Err(local_without_repo())
+        }
+        let dry_run = self.options.dry_run;
+        sayln("white", "No local worktree, only setting up the project on the server.");
+        let client = try!(APIClient::from_config(self.config));
+        let org = try!(self.config.org_name());
+        let proj = try!(self.config.project_name());
+        let pipe = try!(self.config.pipeline_name());
+
+        let remote_url = match scp {
+            Some(scp_config) => {
+                let api_url = try!(scp_config.verify_server_config(&client));
+                summary.project_created = try!(create_scp_project(&client, &org, &proj,
+                                                                  &scp_config, dry_run));
+                // The pipeline is created along with the project
+                summary.pipeline_created = summary.project_created;
+                let fancy_kind = try!(scp_config.kind_to_fancy_str());
+                sayln("white", &format!("\nPush the project content to the {} repository \
+                                         {}/{}:", fancy_kind, scp_config.organization,
+                                        scp_config.repo_name));
+                scp_config.remote_url(api_url.as_ref().map(|u| u.as_str()))
+            },
+            None => {
+                client.prefetch_exists(&org, &proj, &pipe);
+                summary.project_created = try!(create_delivery_project(&client, &org, &proj,
+                                                                       dry_run));
+                summary.pipeline_created = try!(create_delivery_pipeline(&client, &org,
+                                                                         &proj, &pipe, dry_run));
+                sayln("white", "\nPush the project content to the 'delivery' git remote:");
+                Some(try!(self.config.delivery_git_ssh_url()))
+            }
+        };
+        if let Some(ref url) = remote_url {
+            sayln("yellow", &format!("  {}", url));
+        }
+        summary.remote_url = remote_url;
+        try!(write_summary(&summary, &utils::cwd(), dry_run));
         Ok(0)
     }
 }
@@ -201,68 +481,124 @@ impl<'n> Command for InitCommand<'n> {
 // This method will create a Delivery Project depending on the SCP that we specify,
 // either a Github, Bitbucket or Delivery (default). It also creates a pipeline,
 // adds the `delivery` remote and push the content of the local repo to the Server.
-fn create_on_server(config: &Config,
-                    scp: Option<project::SourceCodeProvider>) -> DeliveryResult<()> {
+fn create_on_server(config: &Config, scp: Option<project::SourceCodeProvider>,
+                    summary: &mut InitSummary, force_push: bool,
+                    takeover: project::Takeover, dry_run: bool) -> DeliveryResult<()> {
     let client = try!(APIClient::from_config(config));
-    let org = try!(config.organization());
-    let proj = try!(config.project());
-    let pipe = try!(config.pipeline());
+    let org = try!(config.org_name());
+    let proj = try!(config.project_name());
+    let pipe = try!(config.pipeline_name());
+    let identity = git::CommitIdentity::from_config(config);
 
     match scp {
         // If the user requested a custom scp
         Some(scp_config) => {
             // TODO: actually handle this error
             try!(scp_config.verify_server_config(&client));
-            try!(compare_directory_name(&scp_config.repo_name));
+            try!(compare_directory_name(&scp_config.repo_name,
+                                        !config.non_interactive.unwrap_or(false)));
             let fancy_kind = try!(scp_config.kind_to_fancy_str());
-            let response: StatusCode;
-
-            sayln("cyan", &format!("Creating {} backed Delivery project...", fancy_kind));
-            match scp_config.kind {
-                project::Type::Bitbucket => {
-                    response = try!(client.create_bitbucket_project(
-                        &org, &proj, &scp_config.repo_name,
-                        &scp_config.organization, &scp_config.branch));
+            match project::upstream_remote(config, Some(&scp_config)) {
+                Ok(Some(remote)) => {
+                    sayln("white", &format!("  Using git remote '{}' ({}) for the {} \
+                                             repository.", remote.name, remote.url, fancy_kind))
                 },
-                project::Type::Github => {
-                    response = try!(client.create_github_project(&org, &proj, &scp_config.repo_name,
-                                                                 &scp_config.organization, &scp_config.branch,
-                                                                 scp_config.verify_ssl));
-                }
-            }
-
-            match response {
-                StatusCode::Conflict => {
-                    sayln("white", &format!("  Skipping: {} backed Delivery project named {} \
-                                             already exists.", fancy_kind, proj));
-
+                Ok(None) => {
+                    try!(strict::warn(Warning::MissingUpstreamRemote, &format!(
+                        "No git remote points at the {} repository {}/{}. Use \
+                         --upstream-remote if it is configured with a different URL.",
+                        fancy_kind, scp_config.organization, scp_config.repo_name)))
                 },
-                _ => {
-                    sayln("green", &format!("  {} backed Delivery project named {} \
-                                             created.", fancy_kind, proj));
-                }
+                Err(e) => debug!("Unable to list the git remotes: {:?}", e)
             }
-            try!(create_or_update_git_remote(config));
-            try!(push_project_content_to_delivery(&pipe));
+            summary.project_created = try!(create_scp_project(&client, &org, &proj,
+                                                              &scp_config, dry_run));
+            // The pipeline is created along with the project
+            summary.pipeline_created = summary.project_created;
+            let remote_ready = try!(create_or_update_git_remote(config, dry_run));
+            try!(check_server_history(&pipe, takeover, &identity, dry_run));
+            try!(push_project_content_to_delivery(&pipe, force_push, remote_ready, dry_run));
         },
         // If the user isn't using an scp, just delivery itself.
         None => {
-            // Create delivery project on server unless it already exists.
-            sayln("cyan", "Creating Delivery project...");
-            if try!(project::create_delivery_project(&client, &org, &proj)) {
-                sayln("green", &format!("  Delivery project named {} was created.", proj));
-            } else {
-                sayln("white",
-                      &format!("  Skipping: Delivery project named {} already exists.", proj));
-            }
-            try!(create_or_update_git_remote(config));
-            try!(push_project_content_to_delivery(&pipe));
-            try!(create_delivery_pipeline(&client, &org, &proj, &pipe));
+            client.prefetch_exists(&org, &proj, &pipe);
+            summary.project_created = try!(create_delivery_project(&client, &org, &proj,
+                                                                   dry_run));
+            let remote_ready = try!(create_or_update_git_remote(config, dry_run));
+            try!(check_server_history(&pipe, takeover, &identity, dry_run));
+            try!(push_project_content_to_delivery(&pipe, force_push, remote_ready, dry_run));
+            summary.pipeline_created = try!(create_delivery_pipeline(&client, &org,
+                                                                     &proj, &pipe, dry_run));
         }
     }
+    summary.remote_url = config.delivery_git_ssh_url().ok();
     Ok(())
 }
 
+// Create Delivery project on server unless it already exists.
+fn create_delivery_project(client: &APIClient, org: &OrgName,
+                           proj: &ProjectName, dry_run: bool) -> DeliveryResult<bool> {
+    sayln("cyan", "Creating Delivery project...");
+    if dry_run && !client.project_exists(org, proj) {
+        would("project", &format!("create the Delivery project {}", proj));
+        Ok(false)
+    } else if !dry_run && try!(project::create_delivery_project(client, org, proj)) {
+        sayln("green", &format!("  Delivery project named {} was created.", proj));
+        json_output::created("project", proj);
+        Ok(true)
+    } else {
+        skipping("project", &format!("Delivery project named {} already exists.", proj));
+        Ok(false)
+    }
+}
+
+// Create a Github or Bitbucket backed Delivery project unless it already exists.
+fn create_scp_project(client: &APIClient, org: &OrgName, proj: &ProjectName,
+                      scp_config: &project::SourceCodeProvider,
+                      dry_run: bool) -> DeliveryResult<bool> {
+    let fancy_kind = try!(scp_config.kind_to_fancy_str());
+    let pipe = try!(PipelineName::new(&scp_config.branch));
+    let response: StatusCode;
+
+    sayln("cyan", &format!("Creating {} backed Delivery project...", fancy_kind));
+    if dry_run {
+        if client.project_exists(org, proj) {
+            skipping("project", &format!("{} backed Delivery project named {} \
+                                          already exists.", fancy_kind, proj));
+        } else {
+            would("project", &format!("create the {} backed Delivery project {}",
+                                      fancy_kind, proj));
+        }
+        return Ok(false)
+    }
+    match scp_config.kind {
+        project::Type::Bitbucket => {
+            response = try!(client.create_bitbucket_project(
+                org, proj, &scp_config.repo_name,
+                &scp_config.organization, &pipe));
+        },
+        project::Type::Github => {
+            response = try!(client.create_github_project(org, proj, &scp_config.repo_name,
+                                                         &scp_config.organization, &pipe,
+                                                         scp_config.verify_ssl));
+        }
+    }
+
+    match response {
+        StatusCode::Conflict => {
+            skipping("project", &format!("{} backed Delivery project named {} \
+                                          already exists.", fancy_kind, proj));
+            Ok(false)
+        },
+        _ => {
+            sayln("green", &format!("  {} backed Delivery project named {} \
+                                     created.", fancy_kind, proj));
+            json_output::created("project", proj);
+            Ok(true)
+        }
+    }
+}
+
 // Verify if the config file already exists, if it does, parse the config and see where the
 // build_cookbook is being source from. We will only generate the build_cookbook if it is
 // coming from a local path. Otherwise we won't need to generate it.
@@ -291,28 +627,49 @@ fn verify_config_get_build_cookbook_path<P>(p_path: P) -> DeliveryResult<Option<
 //
 // This function first verify that the remote is up-to-date, and if it is not
 // then it will automatically create or update the remote.
-pub fn create_or_update_git_remote(config: &Config) -> DeliveryResult<()> {
+//
+// Returns false when a dry run left the remote pointing elsewhere.
+pub fn create_or_update_git_remote(config: &Config, dry_run: bool) -> DeliveryResult<bool> {
     sayln("cyan", "Setting up the 'delivery' git remote...");
     let project_path = project::project_path()?;
     if project::git_remote_up_to_date(config)? {
         let git_remote = git::delivery_remote_from_repo(&project_path)?;
-        sayln("white", &format!("  Skipping: The delivery git remote is up-to-date. \
-                                 ({}).", &git_remote));
+        skipping("git-remote", &format!("The delivery git remote is up-to-date. \
+                                         ({}).", &git_remote));
+    } else if dry_run {
+        let git_ssh_url = config.delivery_git_ssh_url()?;
+        would("git-remote", &format!("configure the delivery git remote to '{}'",
+                                     git_ssh_url));
+        return Ok(false)
     } else {
         let git_ssh_url = config.delivery_git_ssh_url()?;
         try!(git::update_delivery_remote(&git_ssh_url, &project_path));
         sayln("green", &format!("  The delivery git remote has been configured \
                                  to '{}'.", &git_ssh_url));
     }
-    Ok(())
+    Ok(true)
+}
+
+// Check the history of the pipeline on the server is related to the
+// local one. It fetches the pipeline, so a dry run leaves it out.
+fn check_server_history(pipeline: &str, takeover: project::Takeover,
+                        identity: &git::CommitIdentity, dry_run: bool) -> DeliveryResult<()> {
+    if dry_run {
+        return Ok(())
+    }
+    project::check_server_history(pipeline, takeover, identity, &try!(project::project_path()))
 }
 
 // Push content to Delivery if no upstream commits.
-fn push_project_content_to_delivery(pipeline: &str) -> DeliveryResult<()> {
+fn push_project_content_to_delivery(pipeline: &str, force: bool, remote_ready: bool,
+                                    dry_run: bool) -> DeliveryResult<()> {
     sayln("cyan", "Pushing initial git history...");
-    if !try!(project::push_project_content_to_delivery(&pipeline)) {
-        sayln("white", &format!("  Skipping: Found commits on remote for pipeline {}, \
-                                 not pushing local commits.", pipeline))
+    if dry_run {
+        return plan_push(pipeline, force, remote_ready)
+    }
+    if !try!(project::push_project_content_to_delivery(&pipeline, force)) {
+        skipping("push", &format!("Found commits on remote for pipeline {}, \
+                                   not pushing local commits.", pipeline))
     } else {
         sayln("green", &format!("  No git history found for pipeline {}, \
                                  pushing local commits from branch {}.", pipeline, pipeline))
@@ -320,20 +677,117 @@ fn push_project_content_to_delivery(pipeline: &str) -> DeliveryResult<()> {
     Ok(())
 }
 
+// Say whether a push would happen, and warn when it would be refused.
+// Without the remote there is no telling what the server has.
+fn plan_push(pipeline: &str, force: bool, remote_ready: bool) -> DeliveryResult<()> {
+    let pushing = if !remote_ready {
+        would("push", &format!("push the local commits of branch {} if pipeline {} \
+                                has no git history yet", pipeline, pipeline));
+        true
+    } else if try!(git::server_content(pipeline)) {
+        skipping("push", &format!("Found commits on remote for pipeline {}, \
+                                   not pushing local commits.", pipeline));
+        false
+    } else {
+        would("push", &format!("push the local commits of branch {}", pipeline));
+        true
+    };
+    if pushing && !force {
+        let diverged = try!(project::diverged_remote_branches(pipeline,
+                                                              &project::project_path()?));
+        for d in diverged.iter() {
+            sayln("red", &format!("  The push would be refused: branch {} is {} \
+                                   commits behind {}, pass --force-push to push it \
+                                   anyway.", pipeline, d.behind, d.remote_ref));
+        }
+    }
+    Ok(())
+}
+
 // Create Delivery pipeline unless it already exists.
-fn create_delivery_pipeline(client: &APIClient, org: &str,
-                            proj: &str, pipe: &str) -> DeliveryResult<()> {
+fn create_delivery_pipeline(client: &APIClient, org: &OrgName, proj: &ProjectName,
+                            pipe: &PipelineName, dry_run: bool) -> DeliveryResult<bool> {
     sayln("cyan", "Creating pipeline on Delivery server...");
-    if try!(project::create_delivery_pipeline(client, org, proj, pipe)) {
+    if dry_run && !client.pipeline_exists(org, proj, pipe) {
+        would("pipeline", &format!("create the pipeline {} of project {}", pipe, proj));
+        Ok(false)
+    } else if !dry_run && try!(project::create_delivery_pipeline(client, org, proj, pipe)) {
         sayln("green", &format!("  Created Delivery pipeline {} for project {}.",
-                                pipe, proj))
+                                pipe, proj));
+        json_output::created("pipeline", pipe);
+        Ok(true)
     } else {
-        sayln("white", &format!("  Skipping: Delivery pipeline \
-                                 named {} already exists for project {}.", pipe, proj))
+        skipping("pipeline", &format!("Delivery pipeline named {} already exists \
+                                       for project {}.", pipe, proj));
+        Ok(false)
+    }
+}
+
+// Write the summary of what init did and say where it is.
+fn write_summary(summary: &InitSummary, dir: &Path, dry_run: bool) -> DeliveryResult<()> {
+    if dry_run {
+        would("summary", &format!("write the summary to {}",
+                                  InitSummary::path(dir).display()));
+        return Ok(())
     }
+    let path = try!(summary.write(dir));
+    sayln("white", &format!("  Summary written to {}.", path.display()));
+    json_output::set("init", summary);
     Ok(())
 }
 
+// Say a step was skipped, and why.
+fn skipping(step: &str, reason: &str) {
+    sayln("white", &format!("  Skipping: {}", reason));
+    json_output::skipped(step, reason);
+}
+
+// Say what a step would do, for --dry-run.
+fn would(step: &str, action: &str) {
+    sayln("yellow", &format!("  Would {}.", action));
+    json_output::skipped(step, &format!("--dry-run: would {}.", action));
+}
+
+fn local_without_repo() -> DeliveryError {
+    let msg = "--local needs a local repository, there is nothing to \
+               initialize without one".to_string();
+    DeliveryError::throw(Kind::OptionConstraint, Some(msg))
+}
+
+fn missing_config_file() -> DeliveryError {
+    let msg = "Missing .delivery/config.json file.\nPlease use a \
+               custom build cookbook generator that creates this \
+               file or pass in a custom config.".to_string();
+    DeliveryError{
+        kind: Kind::MissingConfigFile,
+        detail: Some(msg)
+    }
+}
+
+// The commit of a custom generator when it was cloned from git, or the
+// cookbook version when it was downloaded from a Supermarket.
+fn generator_version(generator: &str) -> Option<String> {
+    let cache_path = match project::generator_cache_path() {
+        Ok(path) => path,
+        Err(_) => return None
+    };
+    let generator_path = match project::generator_cache_dir(generator, &cache_path) {
+        Ok(path) => path,
+        Err(_) => return None
+    };
+    if let Ok(Some(_)) = project::SupermarketGenerator::parse(generator) {
+        let mut metadata = String::new();
+        return File::open(generator_path.join("metadata.json")).ok()
+            .and_then(|mut f| f.read_to_string(&mut metadata).ok())
+            .and_then(|_| serde_json::from_str::<serde_json::Value>(&metadata).ok())
+            .and_then(|json| json.get("version")
+                      .and_then(|v| v.as_str())
+                      .map(String::from))
+    }
+    git::git_command(&["rev-parse", "HEAD"], &generator_path).ok()
+        .map(|result| result.stdout.trim().to_string())
+}
+
 // Handles the build_cookbook generation
 //
 // Use the provided custom generator, if it is not provided generate a build cookbook
@@ -343,7 +797,9 @@ fn create_delivery_pipeline(client: &APIClient, org: &str,
 // generate it. If there is no need to generate the build cookbook, skip and inform the user.
 //
 // Returns true if a CUSTOM build cookbook was generated, else it returns false.
-fn generate_build_cookbook(config: &Config) -> DeliveryResult<bool> {
+// A dry run says what it would generate and returns what the real run would.
+fn generate_build_cookbook(config: &Config, refresh: bool,
+                           dry_run: bool) -> DeliveryResult<bool> {
     sayln("cyan", "Generating build cookbook...");
     if let Some(bk_path) = verify_config_get_build_cookbook_path(project::project_path()?)? {
         let cache_path = try!(project::generator_cache_path());
@@ -351,53 +807,82 @@ fn generate_build_cookbook(config: &Config) -> DeliveryResult<bool> {
         match config.generator().ok() {
             // Using a custom build cookbook generator
             Some(generator_str) => {
+                if dry_run {
+                    would("build-cookbook", &format!("generate a custom build cookbook \
+                                                      with the generator {}", generator_str));
+                    return Ok(true)
+                }
                 sayln("green", &format!("  Using custom generator {}.", generator_str));
-                generate_custom_build_cookbook(generator_str, cache_path, project_path)?;
+                let supermarket = config.supermarket()
+                    .unwrap_or(project::DEFAULT_SUPERMARKET.to_string());
+                let tls = Tls::from_config(config)?;
+                generate_custom_build_cookbook(generator_str, cache_path, project_path,
+                                               &supermarket, refresh,
+                                               config.offline.unwrap_or(false), &tls)?;
                 Ok(true)
             },
             // Generate build cookbook
             None => {
                 if bk_path.exists() {
-                    sayln("white", &format!(
-                        "  Skipping: build cookbook already exists at {}.", bk_path.display()
+                    skipping("build-cookbook", &format!(
+                        "build cookbook already exists at {}.", bk_path.display()
                     ));
                 } else {
                     let pipeline = try!(config.pipeline());
                     // Verify if the build_cookbook path is not the default, then `Err()`
                     try!(verify_default_build_cookbook_path(&bk_path));
+                    if dry_run {
+                        would("build-cookbook", &format!("generate a build cookbook at {}, \
+                                                          commit it and push it to pipeline {}",
+                                                         bk_path.display(), pipeline));
+                        return Ok(false)
+                    }
                     try!(project::create_build_cookbook(&pipeline, &bk_path));
                     sayln("green", &format!(
                         "  Build cookbook generated at {}.", bk_path.display()
                     ));
-                    try!(git::git_push(&pipeline));
-                    sayln("green", &format!(
-                        "  Build cookbook committed to git and pushed to pipeline named {}.", pipeline
-                    ));
+                    if config.offline.unwrap_or(false) {
+                        skipping("push", &format!("Offline, not pushing pipeline {}.", pipeline));
+                    } else {
+                        try!(git::git_push(&pipeline));
+                        sayln("green", &format!(
+                            "  Build cookbook committed to git and pushed to pipeline named {}.", pipeline
+                        ));
+                    }
                 }
                 Ok(false)
             }
         }
     } else {
-        sayln("white", "  Skipping: build cookbook doesn't need to be generated locally.");
+        skipping("build-cookbook", "build cookbook doesn't need to be generated locally.");
         Ok(false)
     }
 }
 
 fn generate_custom_build_cookbook(generator_str: String,
                                   cache_path: PathBuf,
-                                  project_path: PathBuf) -> DeliveryResult<()> {
+                                  project_path: PathBuf,
+                                  supermarket: &str,
+                                  refresh: bool,
+                                  offline: bool,
+                                  tls: &Tls) -> DeliveryResult<()> {
     let gen_path = Path::new(&generator_str);
-    let mut generator_path = cache_path.clone();
-    generator_path.push(gen_path.file_stem().unwrap());
-    match try!(project::download_or_mv_custom_build_cookbook_generator(&gen_path, &cache_path)) {
+    let generator_path = try!(project::generator_cache_dir(&generator_str, &cache_path));
+    match try!(project::download_or_mv_custom_build_cookbook_generator(&gen_path, &cache_path,
+                                                                       supermarket, refresh,
+                                                                       offline, tls)) {
         project::CustomCookbookSource::Disk => {
             sayln("green", "  Copying custom build cookbook generator to the cache.")
         },
         project::CustomCookbookSource::Cached => {
-            sayln("white", "  Skipping: Using cached copy of custom build cookbook generator.")
+            skipping("download-generator", "Using cached copy of custom build cookbook generator.")
         },
         project::CustomCookbookSource::Git => {
             sayln("green", &format!("  Downloading build_cookbook generator from {}.", generator_str))
+        },
+        project::CustomCookbookSource::Supermarket => {
+            sayln("green", &format!("  Downloading build_cookbook generator {} from {}.",
+                                    generator_str, supermarket))
         }
     }
 
@@ -406,22 +891,33 @@ fn generate_custom_build_cookbook(generator_str: String,
     Ok(())
 }
 
-fn generate_delivery_config(config_json: Option<String>) -> DeliveryResult<bool> {
+fn generate_delivery_config(config_json: Option<String>, dry_run: bool) -> DeliveryResult<bool> {
     if let Some(json) = config_json {
         sayln("cyan", "Copying custom Delivery config...");
         let proj_path = try!(project::project_path());
         let json_path = PathBuf::from(&json);
+        let config_path = DeliveryConfig::config_file_path(&proj_path);
 
         // Create config
-        match try!(DeliveryConfig::copy_config_file(&json_path, &proj_path)) {
+        let copied = if dry_run {
+            if try!(utils::file_needs_updated(&json_path, &config_path)) {
+                would("delivery-config", &format!("copy {} to .delivery/config.json", json));
+                return Ok(true)
+            }
+            None
+        } else {
+            try!(DeliveryConfig::copy_config_file(&json_path, &proj_path))
+        };
+        match copied {
             Some(_) => {
                 sayln("green", &format!("  Custom Delivery config copied \
                                          from {} to .delivery/config.json.", &json));
                 Ok(true)
             },
             None => {
-                sayln("white", &format!("  Skipped: Content of custom config passed from {} exactly \
-                                         matches existing .delivery/config.json.", &json));
+                skipping("delivery-config", &format!("Content of custom config passed from {} \
+                                                      exactly matches existing \
+                                                      .delivery/config.json.", &json));
                 Ok(false)
             }
         }
@@ -430,44 +926,117 @@ fn generate_delivery_config(config_json: Option<String>) -> DeliveryResult<bool>
     }
 }
 
+// Sync the files of a custom config directory into .delivery/ and
+// return the ones that were added or updated.
+fn sync_delivery_config_dir(config_dir: Option<String>,
+                            dry_run: bool) -> DeliveryResult<Vec<PathBuf>> {
+    if let Some(dir) = config_dir {
+        sayln("cyan", &format!("Syncing custom Delivery config directory {}...", &dir));
+        let proj_path = try!(project::project_path());
+        if dry_run {
+            let plan = try!(sync::plan_dir(Path::new(&dir), &proj_path.join(".delivery"),
+                                           DEFAULT_CONCURRENCY));
+            for &(ref file, sync) in plan.files.iter() {
+                match sync {
+                    FileSync::Added => would("sync", &format!("add .delivery/{}",
+                                                              file.display())),
+                    FileSync::Updated => would("sync", &format!("update .delivery/{}",
+                                                                file.display())),
+                    FileSync::Unchanged => {}
+                }
+            }
+            return Ok(plan.changed())
+        }
+        let summary = try!(DeliveryConfig::sync_config_dir(&PathBuf::from(&dir), &proj_path));
+        for &(ref file, sync) in summary.files.iter() {
+            if sync != FileSync::Unchanged {
+                sayln("green", &format!("  {} .delivery/{}", sync, file.display()));
+            }
+        }
+        sayln("white", &format!("  {}.", summary.summary()));
+        Ok(summary.changed())
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+// Fetch the pipeline template into its cache, render it with the
+// project variables and sync it into .delivery/, returning the files
+// that were added or updated.
+fn apply_pipeline_template(config: &Config, template: &str, refresh: bool,
+                           journal: &mut Journal, dry_run: bool) -> DeliveryResult<Vec<PathBuf>> {
+    sayln("cyan", &format!("Applying pipeline template {}...", template));
+    if dry_run {
+        would("pipeline-template", &format!("fetch the pipeline template {}, render it \
+                                             and sync it into .delivery", template));
+        return Ok(Vec::new())
+    }
+    let cache_path = try!(pipeline_template::cache_path());
+    let supermarket = config.supermarket()
+        .unwrap_or(project::DEFAULT_SUPERMARKET.to_string());
+    let tls = try!(Tls::from_config(config));
+    match try!(project::download_or_mv_custom_build_cookbook_generator(Path::new(template),
+                                                                       &cache_path,
+                                                                       &supermarket,
+                                                                       refresh,
+                                                                       config.offline.unwrap_or(false),
+                                                                       &tls)) {
+        project::CustomCookbookSource::Cached => {
+            skipping("download-template", "Using cached copy of pipeline template.")
+        },
+        _ => sayln("green", &format!("  Fetched pipeline template {}.", template))
+    }
+
+    let template_path = try!(project::generator_cache_dir(template, &cache_path));
+    let rendered = try!(TempDir::new("pipeline-template"));
+    let files = try!(pipeline_template::render_dir(&template_path, rendered.path(),
+                                                   &pipeline_template::variables(config)));
+    for file in files.iter() {
+        journal.remember_file(&Path::new(".delivery").join(file));
+    }
+    let proj_path = try!(project::project_path());
+    let summary = try!(DeliveryConfig::sync_config_dir(&rendered.path().to_path_buf(),
+                                                       &proj_path));
+    for &(ref file, sync) in summary.files.iter() {
+        if sync != FileSync::Unchanged {
+            sayln("green", &format!("  {} .delivery/{}", sync, file.display()));
+        }
+    }
+    sayln("white", &format!("  {}.", summary.summary()));
+    Ok(summary.changed())
+}
+
 // Triggers an delivery review.
 fn trigger_review(config: &Config, scp: Option<project::SourceCodeProvider>,
-                  no_open: &bool) -> DeliveryResult<()> {
+                  no_open: &bool) -> DeliveryResult<Option<String>> {
     let pipeline = try!(config.pipeline());
     let head = try!(git::get_head());
 
     // We now trigger a review for every single project type
     let review = try!(project::review(&pipeline, &head));
-    match project::handle_review_result(&review, no_open) {
-        Ok(_) => (),
-        Err(_) => {
-            sayln("yellow", "  We could not open the review in the browser for you.");
-            sayln("yellow", "  Make sure there is a program that can open HTML files in your path \
-                             or pass --no-open to bypass attempting to open this review in a browser.");
-        }
-    }
+    try!(project::handle_review_result(&review, no_open, config));
     match scp {
         Some(s) => sayln("green", &format!("  Review submitted to Delivery with {} \
                                             integration enabled.", try!(s.kind_to_fancy_str()))),
         None => sayln("green", "  Review submitted to Delivery.")
     }
-    Ok(())
+    Ok(review.url)
 }
 
 // Compare that the directory name is the same as the repo-name
 // provided by the user, if not show a WARN message
-fn compare_directory_name(repo_name: &str) -> DeliveryResult<()> {
+fn compare_directory_name(repo_name: &str, interactive: bool) -> DeliveryResult<()> {
     let c_dir = utils::cwd();
     if !c_dir.ends_with(repo_name) {
-        let mut answer = String::new();
         let project_name = try!(project::project_from_cwd());
         sayln("yellow", &format!(
             "WARN: This project will be named '{}', but the repository name is '{}'.",
             project_name, repo_name));
-        say("yellow", "Are you sure this is what you want? y/n: ");
-        try!(io::stdin().read_line(&mut answer));
-        debug!("You answered '{}'", answer.trim());
-        if answer.trim() != "y" {
+        let sure = try!(Prompt::new("Are you sure this is what you want?")
+                        .default("n")
+                        .interactive(interactive)
+                        .confirm());
+        if !sure {
             let msg = "\nTo match the project and the repository name you can:\n  1) \
                        Create a directory with the same name as the repository.\n  2) \
                        Clone or download the content of the repository inside.\n  3) \
@@ -508,6 +1077,7 @@ fn verify_default_build_cookbook_path<P>(path: P) -> DeliveryResult<()>
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempdir::TempDir;
 
     mod verify_config_get_build_cookbook_path {
         use super::*;
@@ -533,4 +1103,24 @@ mod tests {
             // Because we don't need to generate the build cookbook
         }
     }
+
+    #[test]
+    fn test_write_init_summary() {
+        let tmp = TempDir::new("init-summary").unwrap();
+        let summary = InitSummary {
+            project: "frijol".to_string(),
+            project_created: true,
+            pipeline: "master".to_string(),
+            remote_url: Some("ssh://user@ent@server:8989/ent/org/frijol".to_string()),
+            ..InitSummary::default()
+        };
+        let path = summary.write(tmp.path()).unwrap();
+        assert_eq!(tmp.path().join(".delivery").join("init-summary.json"), path);
+        let json: serde_json::Value = serde_json::from_str(&utils::read_file(&path).unwrap())
+            .unwrap();
+        assert_eq!(json["project"], "frijol");
+        assert_eq!(json["project_created"].as_bool(), Some(true));
+        assert_eq!(json["pipeline_created"].as_bool(), Some(false));
+        assert_eq!(json["review_url"], serde_json::Value::Null);
+    }
 }
@@ -16,20 +16,25 @@
 //
 
 use git;
+use time;
 use std;
 use fips;
 use std::env;
 use std::process;
-use std::io::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use libc;
 use cli::job::JobClapOptions;
 use job::workspace::{Workspace, Privilege};
+use delivery_config::project::ProjectToml;
 use job::change::Change;
+use job::env::{PhaseEnv, fetch_prior_stage_metadata};
 use types::{DeliveryResult, ExitCode};
 use errors::{DeliveryError, Kind};
 use utils::say::{say, sayln};
 use utils::path_join_many::PathJoinMany;
 use utils::{self, cwd, privileged_process};
+use utils::preflight;
+use utils::limits::Limits;
 use command::Command;
 use config::Config;
 use project;
@@ -48,7 +53,7 @@ impl<'n> Command for JobCommand<'n> {
             // if that is the case, it should not initialize any project specific
             // command, like the remote.
             if project::project_path().is_ok() && !self.options.local {
-                try!(super::verify_and_repair_git_remote(&self.config));
+                try!(super::verify_and_repair_git_remote(&self.config, false));
             }
 
             if !self.options.local {
@@ -63,7 +68,7 @@ impl<'n> Command for JobCommand<'n> {
         sayln("green", "Chef Delivery");
 
         let p = try!(self.config.project());
-        let s = try!(self.config.server());
+        let s = try!(self.config.server_host());
         let e = try!(self.config.enterprise());
         let o = try!(self.config.organization());
         let pi = try!(self.config.pipeline());
@@ -93,8 +98,18 @@ impl<'n> Command for JobCommand<'n> {
         } else {
             PathBuf::from(self.options.job_root)
         };
-        let ws = Workspace::new(&job_root_path);
-        sayln("white", &format!("Creating workspace in {}", job_root_path.to_string_lossy()));
+        let ws = if self.options.isolate {
+            Workspace::for_run(&job_root_path, &run_id())
+        } else {
+            Workspace::new(&job_root_path)
+        };
+        let _cleanup = if self.options.isolate {
+            Some(RunCleanup { ws: &ws, keep: self.options.keep_workspace })
+        } else {
+            None
+        };
+        sayln("white", &format!("Creating workspace in {}", ws.root.to_string_lossy()));
+        try!(preflight::check(&job_root_path, preflight::WORKSPACE_SPACE, "the job workspace"));
         try!(ws.build());
         say("white", "Cloning repository, and merging");
         let mut local_change = false;
@@ -146,6 +161,13 @@ impl<'n> Command for JobCommand<'n> {
             change_id: self.options.change_id.to_string(),
             patchset_number: patch.to_string()
         };
+        let mut phase_env = try!(PhaseEnv::for_job(&change, &ws));
+        if !self.options.local && !self.options.change_id.is_empty() {
+            match fetch_prior_stage_metadata(&self.config, self.options.change_id) {
+                Ok(metadata) => phase_env.set_prior_stage_metadata(&metadata),
+                Err(e) => debug!("Unable to fetch prior stage metadata: {:?}", e)
+            }
+        }
         try!(ws.setup_chef_for_job(&self.config, change, &ws_path));
         sayln("white", "Running the job");
 
@@ -157,7 +179,8 @@ impl<'n> Command for JobCommand<'n> {
 
         if privileged_process() && !&self.options.skip_default {
             sayln("yellow", "Setting up the builder");
-            try!(ws.run_job("default", &Privilege::NoDrop, &local_change));
+            try!(ws.run_job("default", &Privilege::NoDrop, &local_change, &phase_env,
+                            &Limits::default()));
         }
 
         let phase_msg = if phases.len() > 1 {
@@ -166,101 +189,184 @@ impl<'n> Command for JobCommand<'n> {
             "phase"
         };
         sayln("magenta", &format!("Running {} {}", phase_msg, phases.join(", ")));
-        try!(ws.run_job(self.options.phases, &privilege_drop, &local_change));
+        let limits = try!(phase_limits(&ws, &phases));
+        try!(ws.run_job(self.options.phases, &privilege_drop, &local_change, &phase_env,
+                        &limits));
         Ok(0)
     }
 }
 
+/// Run the job in a container of the `--docker` image. The project and
+/// the job root are bind-mounted at the same paths inside, so the phase
+/// sees the same workspace layout and leaves it on the host, and the
+/// output of the container streams straight to ours.
 pub fn run_docker_job(opts: &JobClapOptions) -> DeliveryResult<ExitCode> {
     let cwd_path = cwd();
-    let cwd_str = cwd_path.to_str().unwrap();
-    let volume = &[cwd_str, cwd_str].join(":");
-    // We might want to wrap this in `bash -c $BLAH 2>&1` so that
-    // we get stderr with our streaming output. OTOH, what's here
-    // seems to work in terms of expected output and has a better
-    // chance of working on Windows.
+    let job_root = if opts.job_root.is_empty() {
+        let phase_dir = opts.phases.split(" ").collect::<Vec<&str>>().join("-");
+        try!(utils::home_dir(&[".delivery", "docker-jobs", opts.stage, &phase_dir]))
+    } else {
+        cwd_path.join(opts.job_root)
+    };
+    try!(utils::mkdir_recursive(&job_root));
+    let args = docker_run_args(opts, &cwd_path, &job_root, stdin_is_tty());
     let mut docker = utils::make_command("docker");
+    docker.args(&args)
+        .stdin(process::Stdio::inherit())
+        .stdout(process::Stdio::inherit())
+        .stderr(process::Stdio::inherit());
+    debug!("command: {:?}", docker);
+    let status = match docker.status() {
+        Ok(status) => status,
+        Err(e) => {
+            let msg = format!("failed to execute docker: {}", e);
+            return Err(DeliveryError::throw(Kind::FailedToExecute, Some(msg)))
+        }
+    };
+    Ok(status.code().unwrap_or(1))
+}
 
-    docker.arg("run")
-        .arg("-t")
-        .arg("-i")
-        .arg("-v").arg(volume)
-        .arg("-w").arg(cwd_str)
-    // TODO: get this via config
-        .arg("--dns").arg("8.8.8.8")
-        .arg(opts.docker_image)
-        .arg("delivery").arg("job").arg(opts.stage).arg(opts.phases);
+// The `docker run` arguments of a job, the job inside the container
+// running with the same options but in `job_root`
+fn docker_run_args(opts: &JobClapOptions, project: &Path, job_root: &Path,
+                   tty: bool) -> Vec<String> {
+    let project = project.to_string_lossy().into_owned();
+    let job_root = job_root.to_string_lossy().into_owned();
+    let mut args: Vec<String> = vec!["run".to_string(), "--rm".to_string()];
+    if tty {
+        args.push("-t".to_string());
+        args.push("-i".to_string());
+    }
+    for arg in &["-v", &format!("{}:{}", project, project),
+                 "-v", &format!("{}:{}", job_root, job_root),
+                 "-w", &project] {
+        args.push(arg.to_string());
+    }
+    if !opts.docker_dns.is_empty() {
+        args.push("--dns".to_string());
+        args.push(opts.docker_dns.to_string());
+    }
+    for arg in &[opts.docker_image, "delivery", "job", opts.stage, opts.phases,
+                 "--job-root", &job_root] {
+        args.push(arg.to_string());
+    }
 
     let flags_with_values = vec![("--change", opts.change),
                                  ("--for", opts.pipeline),
-                                 ("--job-root", opts.job_root),
                                  ("--project", opts.project),
                                  ("--user", opts.user),
                                  ("--server", opts.server),
+                                 ("--server-url", opts.server_url),
                                  ("--ent", opts.ent),
                                  ("--org", opts.org),
                                  ("--patchset", opts.patchset),
-                                 ("--change_id", opts.change_id),
+                                 ("--change-id", opts.change_id),
                                  ("--git-url", opts.git_url),
                                  ("--shasum", opts.shasum),
                                  ("--branch", opts.branch)];
-
     for (flag, value) in flags_with_values {
-        maybe_add_flag_value(&mut docker, flag, value);
+        if !value.is_empty() {
+            args.push(flag.to_string());
+            args.push(value.to_string());
+        }
     }
 
-    let flags = vec![("--skip-default", &opts.skip_default),
-                     ("--local", &opts.local)];
-
+    let flags = vec![("--skip-default", opts.skip_default),
+                     ("--isolate", opts.isolate),
+                     ("--keep-workspace", opts.keep_workspace),
+                     ("--local", opts.local)];
     for (flag, value) in flags {
-        maybe_add_flag(&mut docker, flag, value);
+        if value {
+            args.push(flag.to_string());
+        }
     }
+    args
+}
 
-    docker.stdout(process::Stdio::piped());
-    docker.stderr(process::Stdio::piped());
+// `-t -i` need a terminal on our side, CI builds have none
+#[cfg(unix)]
+pub fn stdin_is_tty() -> bool {
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 1 }
+}
 
-    debug!("command: {:?}", docker);
-    let mut child = try!(docker.spawn());
-    let mut c_stdout = match child.stdout {
-        Some(ref mut s) => s,
-    None => {
-    let msg = "failed to execute docker".to_string();
-    let docker_err = DeliveryError { kind: Kind::FailedToExecute,
-                                     detail: Some(msg) };
-    return Err(docker_err);
+#[cfg(not(unix))]
+pub fn stdin_is_tty() -> bool {
+    true
 }
-};
-let mut line = String::with_capacity(256);
-loop {
-    let mut buf = [0u8; 1]; // Our byte buffer
-    let len = try!(c_stdout.read(&mut buf));
-    match len {
-        0 => { // 0 == EOF, so stop writing and finish progress
-        break;
-    },
-    _ => { // Write the buffer to the BufWriter on the Heap
-    let buf_vec = buf[0 .. len].to_vec();
-    let buf_string = String::from_utf8(buf_vec).unwrap();
-    line.push_str(&buf_string);
-    if line.contains("\n") {
-        print!("{}", line);
-        line = String::with_capacity(256);
-    }
+
+// Takes the workspace of an `--isolate` run away when the job ends,
+// whether it passed, failed or errored out.
+struct RunCleanup<'a> {
+    ws: &'a Workspace,
+    keep: bool,
 }
+
+impl<'a> Drop for RunCleanup<'a> {
+    fn drop(&mut self) {
+        if self.keep {
+            sayln("white", &format!("Keeping the workspace of the run in {}",
+                                    self.ws.root.display()));
+        } else if let Err(e) = self.ws.remove() {
+            sayln("yellow", &format!("Unable to remove the workspace {}: {}",
+                                     self.ws.root.display(), e));
         }
     }
-    return Ok(0);
 }
 
-fn maybe_add_flag_value(cmd: &mut process::Command, flag: &str, value: &str) {
-    if !value.is_empty() {
-        cmd.arg(flag).arg(value);
-    }
+// Unique enough among the runs of one job root
+fn run_id() -> String {
+    format!("{}-{}", time::now_utc().to_timespec().sec, process::id())
 }
 
-fn maybe_add_flag(cmd: &mut process::Command, flag: &str, value: &bool) {
-    if *value {
-        cmd.arg(flag);
+// The limits of `phases` from the project.toml of the change, chef-client
+// runs them all in one go
+fn phase_limits(ws: &Workspace, phases: &[&str]) -> DeliveryResult<Limits> {
+    let project_toml = match try!(ProjectToml::load_from_repo(&ws.repo)) {
+        Some(project_toml) => project_toml,
+        None => return Ok(Limits::default())
+    };
+    let mut limits: Option<Limits> = None;
+    for phase in phases {
+        let phase_limits = try!(project_toml.limits_for(phase));
+        limits = Some(match limits {
+            Some(l) => l.then(&phase_limits),
+            None => phase_limits
+        });
     }
+    let limits = limits.unwrap_or_default();
+    if !limits.is_empty() {
+        sayln("white", &format!("Phase limits: {}", limits));
+    }
+    Ok(limits)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::docker_run_args;
+    use cli::job::JobClapOptions;
+    use std::path::Path;
+
+    #[test]
+    fn test_docker_run_args() {
+        let opts = JobClapOptions {
+            stage: "verify",
+            phases: "unit lint",
+            docker_image: "chef/delivery:1.0",
+            change_id: "abc",
+            skip_default: true,
+            ..JobClapOptions::default()
+        };
+        let args = docker_run_args(&opts, Path::new("/src/proj"), Path::new("/jobs/v"), false);
+        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+        assert_eq!(&["run", "--rm", "-v", "/src/proj:/src/proj", "-v", "/jobs/v:/jobs/v",
+                     "-w", "/src/proj", "chef/delivery:1.0",
+                     "delivery", "job", "verify", "unit lint", "--job-root", "/jobs/v",
+                     "--for", "master", "--change-id", "abc", "--skip-default"],
+                   &args[..]);
+        let with_tty = docker_run_args(&opts, Path::new("/p"), Path::new("/j"), true);
+        assert_eq!(&["run", "--rm", "-t", "-i"], &with_tty[..4]);
+        let with_dns = JobClapOptions { docker_dns: "10.0.0.2", ..opts };
+        let args = docker_run_args(&with_dns, Path::new("/p"), Path::new("/j"), false);
+        assert_eq!(&["-w", "/p", "--dns", "10.0.0.2", "chef/delivery:1.0"], &args[6..11]);
+    }
+}
@@ -0,0 +1,74 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::listen::ListenClapOptions;
+use types::{DeliveryResult, ExitCode};
+use events::{self, Event};
+use http::APIClient;
+use utils::poll::{self, Cancel, Poll};
+use utils::say::sayln;
+use command::Command;
+use config::Config;
+
+pub struct ListenCommand<'n> {
+    pub options: &'n ListenClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for ListenCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let wanted = try!(events::parse(self.options.events));
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project());
+        let pipeline = try!(self.config.pipeline());
+        let client = try!(APIClient::from_config(&self.config));
+
+        let mut known = try!(events::snapshot(&client, &org, &proj, &pipeline));
+        sayln("white", &format!("Listening for {} on {}/{} {} ({} changes known)",
+                                wanted.join(", "), org, proj, pipeline, known.len()));
+        let mut backoff = self.config.poll_backoff();
+        let listened: Option<()> = try!(poll::poll(&mut backoff, &Cancel::new(), || {
+            let current = match events::snapshot(&client, &org, &proj, &pipeline) {
+                Ok(current) => current,
+                Err(e) => {
+                    // The server going away for a while is no reason to stop
+                    debug!("Unable to poll the changes: {:?}", e);
+                    return Ok(Poll::Unchanged)
+                }
+            };
+            let found = events::diff(&known, &current);
+            known = current;
+            if found.is_empty() {
+                return Ok(Poll::Unchanged)
+            }
+            for (name, change) in found.into_iter().filter(|e| wanted.contains(&e.0)) {
+                sayln("cyan", &format!("{} {} ({})", name, change.id, change.title));
+                let event = Event {
+                    event: name,
+                    org: org.clone(),
+                    project: proj.clone(),
+                    pipeline: pipeline.clone(),
+                    change: change,
+                };
+                if let Err(e) = events::dispatch(self.options.exec, &event) {
+                    sayln("yellow", &format!("{}", e.detail().unwrap_or_default()));
+                }
+            }
+            Ok(Poll::Changed)
+        }));
+        Ok(if listened.is_some() { 0 } else { 1 })
+    }
+}
@@ -18,50 +18,213 @@
 use cli::local::LocalClapOptions;
 use types::{DeliveryResult, ExitCode};
 use utils::say::{sayln, say};
+use utils::strict::{self, Warning};
 use std::process::{Stdio};
-use delivery_config::project::{Phase, ProjectToml};
+use delivery_config::project::{Matrix, Phase, ProjectToml, Stage};
 use errors::{DeliveryError, Kind};
+use job::env::PhaseEnv;
+use utils::privilege::Escalator;
+use utils::limits::{self, Limits};
 use project;
 use utils;
+use git;
 use command::Command;
+use command::job::stdin_is_tty;
 
 pub struct LocalCommand<'n> {
     pub options: &'n LocalClapOptions<'n>,
     pub config: &'n ProjectToml,
+    /// The --for pipeline, else the one of the cli.toml
+    pub pipeline: &'n str,
+    pub escalator: &'n Escalator,
 }
 
 impl<'n> Command for LocalCommand<'n> {
     fn run(&self) -> DeliveryResult<ExitCode> {
         sayln("green", "Chef Delivery");
+        if self.options.remote_config {
+            try!(self.say_config_drift());
+        }
+
+        if !self.options.matrix.is_empty() {
+            let matrix = try!(Matrix::parse(self.options.matrix));
+            return self.run_matrix(&matrix)
+        }
 
         // If a Stage was provided, trigger their phases in order
         if let Some(stage) = self.options.stage.clone() {
             say("white", "Running ");
             say("yellow", &format!("{}", stage));
             sayln("white", " Stage");
+            let mut results = vec![];
             for phase in stage.phases().into_iter() {
-                match try!(exec_phase(&self.config.clone(), Some(phase))) {
-                    0 => continue,
-                    exit_code => return Err(DeliveryError {
-                        kind: Kind::PhaseFailed(exit_code),
-                        detail: None
-                    }),
+                let exit_code = try!(exec_phase(self.config, Some(phase.clone()),
+                                                self.escalator));
+                results.push((phase, exit_code));
+                if exit_code != 0 && !self.options.keep_going {
+                    break
                 }
             }
-            Ok(0)
+            say_stage_summary(&stage, &results);
+            match results.iter().find(|&&(_, exit_code)| exit_code != 0) {
+                Some(&(_, exit_code)) => Err(DeliveryError {
+                    kind: Kind::PhaseFailed(exit_code),
+                    detail: None
+                }),
+                None => Ok(0)
+            }
         } else {
-            exec_phase(self.config, self.options.phase.clone())
+            exec_phase(self.config, self.options.phase.clone(), self.escalator)
         }
     }
 }
 
-fn exec_phase(project_toml: &ProjectToml, phase: Option<Phase>) -> DeliveryResult<ExitCode> {
+impl<'n> LocalCommand<'n> {
+    // Run the phase, or stage, once per value of the matrix, each in a
+    // docker container of the image of its value. Every value runs even
+    // when one fails, the way build nodes do on the server.
+    fn run_matrix(&self, matrix: &Matrix) -> DeliveryResult<ExitCode> {
+        let cwd = try!(utils::path_to_string(utils::cwd()));
+        // Stock images have no `delivery` to run, so each value needs
+        // an image of the project.toml
+        let mut images = vec![];
+        for value in matrix.values.iter() {
+            match self.config.matrix_image(&matrix.dimension, value) {
+                Some(image) => images.push(image),
+                None => {
+                    let msg = format!("No image to run {}={} in, add one with \
+                                       delivery installed to [matrix_images.{}] \
+                                       of the project.toml",
+                                      matrix.dimension, value, matrix.dimension);
+                    return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+                }
+            }
+        }
+        let tty = stdin_is_tty();
+        let mut results = vec![];
+        for (value, image) in matrix.values.iter().zip(images.into_iter()) {
+            say("white", "Running ");
+            say("yellow", self.options.stage_phase);
+            say("white", " for ");
+            say("magenta", &format!("{}={}", matrix.dimension, value));
+            say("white", " in ");
+            sayln("yellow", &image);
+            let mut docker = utils::make_command("docker");
+            docker.arg("run").arg("--rm");
+            if tty {
+                docker.arg("-t").arg("-i");
+            }
+            docker.arg("-v").arg(&format!("{}:{}", cwd, cwd))
+                .arg("-w").arg(&cwd)
+                .arg("-e").arg(&format!("DELIVERY_MATRIX_{}={}",
+                                        matrix.dimension.to_uppercase(), value))
+                .arg(&image)
+                .arg("delivery").arg("local").arg(self.options.stage_phase)
+                .arg("--for").arg(self.pipeline);
+            if let Some(url) = self.options.remote_toml {
+                docker.arg("--remote-project-toml").arg(url);
+            }
+            if self.options.remote_config {
+                docker.arg("--remote-config");
+            }
+            if self.options.keep_going {
+                docker.arg("--keep-going");
+            }
+            debug!("command: {:?}", docker);
+            let status = try!(docker.stdout(Stdio::inherit())
+                                    .stderr(Stdio::inherit())
+                                    .status());
+            results.push((value.clone(), image, status.code().unwrap_or(1)));
+        }
+        say_matrix_summary(self.options.stage_phase, &matrix.dimension, &results);
+        match results.iter().find(|&&(_, _, exit_code)| exit_code != 0) {
+            Some(&(_, _, exit_code)) => Err(DeliveryError {
+                kind: Kind::PhaseFailed(exit_code),
+                detail: None
+            }),
+            None => Ok(0)
+        }
+    }
+
+    // Let the user know when the local project.toml would not run the
+    // phases the way the server does.
+    fn say_config_drift(&self) -> DeliveryResult<()> {
+        say("white", "Using the project.toml of the ");
+        say("magenta", self.pipeline);
+        sayln("white", " pipeline");
+        let local = match ProjectToml::load_toml(None) {
+            Ok(local) => local,
+            Err(e) => {
+                debug!("Unable to load the local project.toml: {:?}", e);
+                return Ok(())
+            }
+        };
+        let drift = local.drift(self.config);
+        if drift.is_empty() {
+            return Ok(())
+        }
+        sayln("yellow", "Your local project.toml differs from the pipeline's:");
+        let none = "(not configured)".to_string();
+        for d in drift.iter() {
+            sayln("yellow", &format!("  {}:", d.phase));
+            sayln("red", &format!("    local:  {}", d.local.as_ref().unwrap_or(&none)));
+            sayln("green", &format!("    remote: {}", d.remote.as_ref().unwrap_or(&none)));
+        }
+        let phases: Vec<String> = drift.iter().map(|d| d.phase.to_string()).collect();
+        strict::check(Warning::ConfigDrift, &format!("The local project.toml differs from \
+                                                      the pipeline's for {}", phases.join(", ")))
+    }
+}
+
+// How each phase of the stage went, the exit code of the stage is the
+// one of its first failed phase
+fn say_stage_summary(stage: &Stage, results: &[(Phase, ExitCode)]) {
+    sayln("white", &format!("{} Stage summary:", stage));
+    for phase in stage.phases() {
+        say("white", &format!("  {}: ", phase));
+        match results.iter().find(|&&(ref p, _)| *p == phase) {
+            Some(&(_, 0)) => sayln("green", "passed"),
+            Some(&(_, exit_code)) => sayln("red", &format!("failed (exit code {})", exit_code)),
+            None => sayln("yellow", "not run")
+        }
+    }
+}
+
+// How the run went for each value of the matrix
+fn say_matrix_summary(stage_phase: &str, dimension: &str,
+                      results: &[(String, String, ExitCode)]) {
+    sayln("white", &format!("Matrix summary for {}:", stage_phase));
+    let width = results.iter().map(|&(ref value, _, _)| value.len()).max().unwrap_or(0);
+    for &(ref value, ref image, exit_code) in results {
+        say("white", &format!("  {}={:<width$}  {:<24} ", dimension, value, image,
+                              width = width));
+        match exit_code {
+            0 => sayln("green", "passed"),
+            _ => sayln("red", &format!("failed (exit code {})", exit_code))
+        }
+    }
+}
+
+fn exec_phase(project_toml: &ProjectToml, phase: Option<Phase>,
+              escalator: &Escalator) -> DeliveryResult<ExitCode> {
     if let Some(phase_cmd) = try!(project_toml.local_phase(phase.clone())) {
+        let p = phase.unwrap();
+        let privileged = project_toml.is_privileged(&p);
         say("white", "Running ");
-        say("magenta", &format!("{:?}", phase.unwrap()));
-        sayln("white", " Phase");
+        say("magenta", &format!("{:?}", p));
+        if privileged {
+            sayln("white", " Phase with elevated privileges");
+        } else {
+            sayln("white", " Phase");
+        }
         debug!("Executing command: {}", phase_cmd);
-        exec_command(&phase_cmd)
+        let phase_env = try!(local_phase_env(&p));
+        let limits = try!(project_toml.limits_for(&p.to_string()));
+        if !limits.is_empty() {
+            sayln("white", &format!("Phase limits: {}", limits));
+        }
+        let escalate = if privileged { Some((&p, escalator)) } else { None };
+        exec_command(&phase_cmd, &phase_env, escalate, &p, &limits)
     } else {
         let p = phase.unwrap();
         sayln("red", &format!("Unable to execute an empty phase.\nPlease verify that \
@@ -70,7 +233,23 @@ fn exec_phase(project_toml: &ProjectToml, phase: Option<Phase>) -> DeliveryResul
         Ok(1)
     }
 }
-fn exec_command(cmd: &str) -> DeliveryResult<ExitCode> {
+
+// Build the same `DELIVERY_*` environment that a `delivery job` run
+// would export, with the project repository acting as the workspace.
+fn local_phase_env(phase: &Phase) -> DeliveryResult<PhaseEnv> {
+    let repo = try!(project::project_path());
+    let project = try!(project::project_from_cwd());
+    let sha = git::git_current_sha().unwrap_or(String::new());
+    let stage = match *phase {
+        Phase::Lint | Phase::Syntax | Phase::Unit => "verify",
+        _ => "acceptance"
+    };
+    PhaseEnv::for_local(&project, stage, &phase.to_string(), sha.trim(), &repo)
+}
+
+fn exec_command(cmd: &str, phase_env: &PhaseEnv,
+                escalate: Option<(&Phase, &Escalator)>,
+                phase: &Phase, limits: &Limits) -> DeliveryResult<ExitCode> {
     // TODO: I just copy paste the old code and modified a little bit
     // so it works but we have to work on UW-75 to make it right!
     // We should maybe create a tempfile to stick the command coming from
@@ -78,13 +257,22 @@ fn exec_command(cmd: &str) -> DeliveryResult<ExitCode> {
     let mut split_cmd = cmd.split_whitespace();
     let c = split_cmd.next().unwrap();
     let args_vec = split_cmd.collect::<Vec<&str>>();
-    let output  = utils::make_command(c)
-        .args(&args_vec)
+    let argv = match escalate {
+        Some((phase, escalator)) => try!(escalator.escalate(&phase.to_string(), c, &args_vec,
+                                                             &phase_env.vars())),
+        None => vec![c.to_string()]
+                    .into_iter()
+                    .chain(args_vec.iter().map(|a| a.to_string()))
+                    .collect()
+    };
+    let mut command = utils::make_command(&argv[0]);
+    phase_env.apply(&mut command);
+    command
+        .args(&argv[1..])
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
-        .current_dir(try!(project::project_path()))
-        .output()
-        .unwrap_or_else(|e| { panic!("Unexpected error: Failed to execute process: {}", e) });
+        .current_dir(try!(project::project_path()));
+    let output = try!(limits::run(&mut command, &phase.to_string(), limits));
 
     let return_code = match output.status.code() {
         Some(code) => code,
@@ -0,0 +1,42 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::logs::LogsClapOptions;
+use types::{DeliveryResult, ExitCode};
+use utils::say::{sayln, outln};
+use command::Command;
+use logs;
+
+pub struct LogsCommand<'n> {
+    pub options: &'n LogsClapOptions,
+}
+
+impl<'n> Command for LogsCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let dir = try!(logs::logs_dir());
+        if logs::log_files(&dir).is_empty() && !self.options.follow {
+            sayln("yellow", &format!("No logs found in {}", dir.display()));
+            return Ok(0)
+        }
+        for line in try!(logs::tail(&dir, self.options.tail)) {
+            outln("white", &line);
+        }
+        if self.options.follow {
+            try!(logs::follow(&dir, |line| outln("white", line)));
+        }
+        Ok(0)
+    }
+}
@@ -21,8 +21,13 @@ use project;
 use utils;
 use utils::say::sayln;
 use utils::cwd;
+use utils::json_output;
+use utils::strict::{self, Warning};
+use errors::{DeliveryError, Kind};
+use job::workspace;
 use types::{DeliveryResult, ExitCode};
 use config::Config;
+use std::path::Path;
 
 pub mod init;
 pub mod review;
@@ -36,6 +41,38 @@ pub mod api;
 pub mod job;
 pub mod status;
 pub mod pull;
+pub mod data;
+pub mod pin;
+pub mod logs;
+pub mod support_bundle;
+pub mod runner;
+pub mod deliver_order;
+pub mod report;
+pub mod git_credential;
+pub mod prompt;
+pub mod reconcile;
+pub mod queue;
+pub mod changelog;
+pub mod doctor;
+pub mod comment;
+pub mod review_verdict;
+pub mod review_action;
+pub mod features;
+pub mod update_build_deps;
+pub mod environment;
+pub mod refs;
+pub mod cache;
+pub mod telemetry;
+pub mod projects;
+pub mod config;
+pub mod conformance;
+pub mod fixture;
+pub mod listen;
+pub mod change;
+pub mod protect;
+pub mod deliver;
+pub mod schedule;
+pub mod pipeline;
 
 pub trait Command: Sized {
     fn setup(&self, child_processes: &mut Vec<std::process::Child>) -> DeliveryResult<()> {
@@ -59,17 +96,100 @@ pub trait Command: Sized {
 //
 // Once you have added the method, you can just call it with `super::foo()` from
 // within any command. (don't forget to make it public)
-pub fn verify_and_repair_git_remote(config: &Config) -> DeliveryResult<()> {
-    if !project::git_remote_up_to_date(config)? {
-        let p_path = project::project_path()?;
-        let c_path = Config::dot_delivery_cli_path(&cwd()).expect("Unable to find cli.toml");
-        let git_ssh_url = config.delivery_git_ssh_url()?;
-        let msg = &format!("Updating 'delivery' remote with the default configuration \
-                  loaded from {:?}.\n\tcurrent: {}\n\tupdate:  {}", c_path,
-                  &git::delivery_remote_from_repo(&p_path)?,
-                  &git_ssh_url);
-        sayln("yellow", msg);
+//
+// The delivery remote has to point at the server, enterprise, organization
+// and project of the config, through the local stunnel port in FIPS mode.
+// A missing remote is added. A remote that points elsewhere, after a server
+// migration or a project rename, is only updated with `fix` (--fix-remote),
+// since it may as well be the config that is wrong; otherwise what differs
+// is reported as a warning. In FIPS mode the remote is always fixed, the
+// git traffic has to go through the stunnel the cli starts.
+pub fn verify_and_repair_git_remote(config: &Config, fix: bool) -> DeliveryResult<()> {
+    let fips = config.fips.unwrap_or(false);
+    let git_ssh_url = match config.delivery_git_ssh_url() {
+        Ok(url) => url,
+        // Without a complete config there is nothing to compare to.
+        Err(_) if !fips => return Ok(()),
+        Err(e) => return Err(e)
+    };
+    let p_path = project::project_path()?;
+    let current = git::delivery_remote_from_repo(&p_path)?;
+    if current == git_ssh_url {
+        return Ok(())
+    }
+    if current.is_empty() {
+        sayln("yellow", &format!("Adding the 'delivery' remote {}", git_ssh_url));
+        return git::update_delivery_remote(&git_ssh_url, &p_path)
+    }
+
+    let differences = project::git_remote_differences(&git_ssh_url, &current);
+    json_output::set("remote_differences", &differences);
+    if fix || fips {
+        let c_path = Config::dot_delivery_cli_path(&cwd())
+            .map(|p| p.display().to_string())
+            .unwrap_or("the command line".to_string());
+        sayln("yellow", &format!("Updating the 'delivery' remote with the configuration \
+                                  from {}:\n\t{}", c_path, differences.join("\n\t")));
         try!(git::update_delivery_remote(&git_ssh_url, &p_path));
+        json_output::set("remote_fixed", &true);
+    } else {
+        try!(strict::warn(Warning::DeliveryRemoteMismatch,
+                          &format!("The 'delivery' remote does not match your configuration:\n\t\
+                                    {}\n  Run the command again with --fix-remote to update \
+                                    it, or fix your cli.toml.", differences.join("\n\t"))));
     }
     Ok(())
 }
+
+// The delivery server rejects pushes of commits whose parents it does not
+// have, which is what a shallow clone sends when its history was cut
+// before the pipeline. Fetch the full history when asked to, otherwise
+// warn about it and explain how to get out of it.
+pub fn check_shallow_clone(path: &Path, unshallow: bool) -> DeliveryResult<()> {
+    if !git::is_shallow_repository(path) {
+        return Ok(())
+    }
+    if unshallow {
+        sayln("cyan", "Fetching the full history of this shallow clone...");
+        try!(git::unshallow(path));
+    } else {
+        try!(strict::warn(Warning::ShallowClone,
+                          "This repository is a shallow clone, the push to the delivery \
+                           remote fails if the server misses some of its history.\n  \
+                           Run the command again with --unshallow, or run \
+                           `git fetch --unshallow` yourself, to fetch the full history."));
+        json_output::skipped("unshallow", "The repository is a shallow clone, \
+                                           --unshallow was not given.");
+    }
+    Ok(())
+}
+
+// On Windows, a repository that does not convert line endings commits
+// the CRLF of the editors, and the build cookbook fails on the builders.
+pub fn check_line_endings(path: &Path) -> DeliveryResult<()> {
+    let value = git::autocrlf(path);
+    match utils::autocrlf_advice(&value, path.join(".gitattributes").exists()) {
+        Some(advice) => strict::warn(Warning::LineEndings, &advice),
+        None => Ok(())
+    }
+}
+
+// The CLI resets and cleans the repositories of its job workspaces, and
+// prunes its caches, as it sees fit. Reviewing, checking out or pulling
+// from inside one of them leaves the builder with a state it does not
+// expect, so refuse to unless the user insists.
+pub fn refuse_in_managed_workspace(i_know_what_im_doing: bool) -> DeliveryResult<()> {
+    let root = match workspace::managed_root(&cwd()) {
+        Some(root) => root,
+        None => return Ok(())
+    };
+    if i_know_what_im_doing {
+        sayln("yellow", &format!("WARN: Running inside {}, which is managed by \
+                                  the delivery CLI.", root.display()));
+        return Ok(())
+    }
+    let msg = format!("{} is managed by the delivery CLI, which resets it as it \
+                       sees fit.\nRun the command from your own clone of the project, \
+                       or pass --i-know-what-im-doing to run it anyway.", root.display());
+    Err(DeliveryError::throw(Kind::InsideManagedWorkspace, Some(msg)))
+}
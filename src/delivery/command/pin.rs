@@ -0,0 +1,73 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::pin::PinClapOptions;
+use types::{DeliveryResult, ExitCode};
+use errors::{DeliveryError, Kind};
+use http::pin;
+use utils::say::{say, sayln, outln};
+use utils::cwd;
+use std::path::PathBuf;
+use command::Command;
+use config::Config;
+
+pub struct PinCommand<'n> {
+    pub options: &'n PinClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for PinCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let server_url = try!(self.config.server_url_parts());
+        if server_url.protocol != "https" {
+            let msg = format!("Certificate pins only apply to https servers, \
+                              but the server is configured as {}", server_url.base_url());
+            return Err(DeliveryError::throw(Kind::UnsupportedProtocol, Some(msg)))
+        }
+        let port = server_url.port.clone().unwrap_or("443".to_string());
+        say("white", "Fetching certificate pin for ");
+        sayln("yellow", &server_url.host_and_port());
+        let new_pin = try!(pin::fetch_pin(&server_url.host, &port));
+        outln("magenta", &new_pin);
+
+        // Only touch the pins of the cli.toml on disk, we do not want to
+        // persist the rest of the options we were called with.
+        let config_path = self.config_dir();
+        let config = try!(Config::load_project_config(&config_path)).add_server_pin(&new_pin);
+        try!(config.write_file(&config_path));
+        say("white", "Pin recorded in ");
+        sayln("yellow", &format!("{}", config_path.join(".delivery")
+                                                     .join("cli.toml").display()));
+        Ok(0)
+    }
+}
+
+impl<'n> PinCommand<'n> {
+    // The directory that holds the `.delivery/cli.toml` to update; either
+    // the one provided with --config-path, the one the config was loaded
+    // from, or the current directory.
+    fn config_dir(&self) -> PathBuf {
+        if !self.options.path.is_empty() {
+            return PathBuf::from(self.options.path)
+        }
+        match Config::dot_delivery_cli_path(cwd()) {
+            Some(toml) => toml.parent().and_then(|d| d.parent())
+                              .map(|d| d.to_path_buf()).unwrap_or(cwd()),
+            None => cwd()
+        }
+    }
+}
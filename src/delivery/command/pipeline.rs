@@ -0,0 +1,138 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::pipeline::PipelineClapOptions;
+use types::{DeliveryResult, ExitCode, PipelineName};
+use errors::{DeliveryError, Kind};
+use http::APIClient;
+use utils::say::{sayln, outln};
+use utils::prompt::Prompt;
+use utils::json_output;
+use command::Command;
+use config::Config;
+
+pub struct PipelineCommand<'n> {
+    pub options: &'n PipelineClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for PipelineCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project());
+        let client = try!(APIClient::from_config(&self.config));
+        match self.options.action {
+            "list" => {
+                let pipelines = try!(client.list_pipelines(&org, &proj));
+                for pipeline in pipelines.iter() {
+                    outln("white", pipeline);
+                }
+                json_output::set("pipelines", &pipelines);
+                Ok(0)
+            },
+            "delete" => self.delete(&client, &org, &proj),
+            _ => self.rename(&client, &org, &proj),
+        }
+    }
+}
+
+impl<'n> PipelineCommand<'n> {
+    fn delete(&self, client: &APIClient, org: &str, proj: &str) -> DeliveryResult<ExitCode> {
+        let pipe = try!(self.name("delete"));
+        let pipelines = try!(client.list_pipelines(org, proj));
+        if !pipelines.iter().any(|p| p == pipe) {
+            return Err(no_such_pipeline(org, proj, pipe))
+        }
+        if !self.options.force && !try!(self.delete_confirmed(org, proj, pipe)) {
+            sayln("yellow", "Nothing was deleted");
+            return Ok(1)
+        }
+        try!(client.delete_pipeline(org, proj, pipe));
+        json_output::set("deleted", &pipe);
+        sayln("green", &format!("Pipeline {} of {}/{} deleted from the server", pipe, org, proj));
+        Ok(0)
+    }
+
+    fn rename(&self, client: &APIClient, org: &str, proj: &str) -> DeliveryResult<ExitCode> {
+        let pipe = try!(self.name("rename"));
+        if self.options.new_name.is_empty() {
+            let msg = format!("Give the new name of the pipeline {}", pipe);
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        let new_name = try!(PipelineName::new(self.options.new_name));
+        let pipelines = try!(client.list_pipelines(org, proj));
+        if !pipelines.iter().any(|p| p == pipe) {
+            return Err(no_such_pipeline(org, proj, pipe))
+        }
+        if pipelines.iter().any(|p| p == new_name.as_str()) {
+            let msg = format!("The project {}/{} already has a pipeline {}", org, proj, new_name);
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        if !self.options.force {
+            sayln("yellow", &format!("Changes for {} have to be reviewed for {} once it is \
+                                      renamed.", pipe, new_name));
+            let question = format!("Rename the pipeline {} of {}/{} to {}?", pipe, org, proj,
+                                   new_name);
+            if !try!(Prompt::new(&question).default("n")
+                     .interactive(!self.non_interactive()).confirm()) {
+                sayln("yellow", "Nothing was renamed");
+                return Ok(1)
+            }
+        }
+        try!(client.rename_pipeline(org, proj, pipe, &new_name));
+        json_output::set("renamed", &pipe);
+        json_output::set("to", &new_name.as_str());
+        sayln("green", &format!("Pipeline {} of {}/{} renamed to {}", pipe, org, proj, new_name));
+        if self.config.pipeline.as_ref().map_or(false, |p| p == pipe) {
+            sayln("white", &format!("  Your configuration still reviews for {}; set \
+                                     `pipeline = \"{}\"` in your cli.toml.", pipe, new_name));
+        }
+        Ok(0)
+    }
+
+    fn name(&self, action: &str) -> DeliveryResult<&'n str> {
+        if self.options.name.is_empty() {
+            let msg = format!("Give the name of the pipeline to {}", action);
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        Ok(self.options.name)
+    }
+
+    fn non_interactive(&self) -> bool {
+        self.options.non_interactive || self.config.non_interactive.unwrap_or(false)
+    }
+
+    // Like the project, deleting a pipeline takes its changes with it,
+    // so the user has to type its name rather than just say yes.
+    fn delete_confirmed(&self, org: &str, proj: &str, pipe: &str) -> DeliveryResult<bool> {
+        if self.non_interactive() {
+            let msg = format!("Refusing to delete the pipeline {} of {}/{} without --force \
+                               when running non-interactively", pipe, org, proj);
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        sayln("yellow", &format!("This deletes the pipeline {} of {}/{} from the server, \
+                                  with all its changes.", pipe, org, proj));
+        let answer = try!(Prompt::new("Type the name of the pipeline to confirm").input());
+        Ok(answer == pipe)
+    }
+}
+
+fn no_such_pipeline(org: &str, proj: &str, pipe: &str) -> DeliveryError {
+    let msg = format!("The project {}/{} has no pipeline {}; `delivery pipeline list` \
+                       lists them", org, proj, pipe);
+    DeliveryError::throw(Kind::OptionConstraint, Some(msg))
+}
@@ -0,0 +1,79 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::projects::ProjectClapOptions;
+use types::{DeliveryResult, ExitCode};
+use errors::{DeliveryError, Kind};
+use http::APIClient;
+use git;
+use utils;
+use utils::say::{sayln, outln};
+use utils::prompt::Prompt;
+use utils::json_output;
+use command::Command;
+use config::Config;
+
+pub struct ProjectCommand<'n> {
+    pub options: &'n ProjectClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for ProjectCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project());
+        if !self.options.force && !try!(self.confirmed(&org, &proj)) {
+            sayln("yellow", "Nothing was deleted");
+            return Ok(1)
+        }
+
+        let client = try!(APIClient::from_config(&self.config));
+        try!(client.delete_project(&org, &proj));
+        json_output::set("deleted", &format!("{}/{}", org, proj));
+        outln("green", &format!("Project {}/{} deleted from the server", org, proj));
+
+        if self.options.remove_remote {
+            let cwd = utils::cwd();
+            let has_remote = git::git_remotes(&cwd).map(|remotes| {
+                remotes.iter().any(|r| r.name == "delivery")
+            }).unwrap_or(false);
+            if has_remote {
+                try!(git::git_command(&["remote", "remove", "delivery"], &cwd));
+                outln("green", "Removed the delivery git remote");
+            } else {
+                outln("white", "There is no delivery git remote to remove");
+            }
+            json_output::set("remote_removed", &has_remote);
+        }
+        Ok(0)
+    }
+}
+
+impl<'n> ProjectCommand<'n> {
+    // Deleting a project can not be undone, so the user has to type
+    // its name rather than just say yes.
+    fn confirmed(&self, org: &str, proj: &str) -> DeliveryResult<bool> {
+        if self.options.non_interactive {
+            let msg = format!("Refusing to delete {}/{} without --force when running \
+                               non-interactively", org, proj);
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        sayln("yellow", &format!("This deletes the project {}/{} from the server, with all \
+                                  its pipelines and changes.", org, proj));
+        let answer = try!(Prompt::new("Type the name of the project to confirm").input());
+        Ok(answer == proj)
+    }
+}
@@ -0,0 +1,94 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::prompt::PromptClapOptions;
+use types::{DeliveryResult, ExitCode};
+use http::{self, APIAuth, APIClient};
+use prompt::{self, PromptCache};
+use project;
+use command::Command;
+use utils::say::outln;
+use config::Config;
+
+pub struct PromptCommand<'n> {
+    pub options: &'n PromptClapOptions,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for PromptCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        // Outside of a repository, on a detached HEAD or without a
+        // configured server there is nothing to show, and a prompt must
+        // never print an error.
+        let path = match project::project_path() {
+            Ok(path) => path,
+            Err(_) => return Ok(0)
+        };
+        let branch = match prompt::current_branch(&path) {
+            Some(branch) => branch,
+            None => return Ok(0)
+        };
+        let (pipeline_key, cache_path) = match (self.pipeline_key(), PromptCache::path()) {
+            (Ok(key), Ok(cache_path)) => (key, cache_path),
+            _ => return Ok(0)
+        };
+        let mut cache = PromptCache::load(&cache_path);
+
+        if self.options.refresh {
+            match self.fetch_open_changes() {
+                Ok(changes) => cache.update_pipeline(&pipeline_key, &changes, prompt::now()),
+                // Keep what we had, and back off until it is stale again
+                // instead of retrying on every prompt.
+                Err(e) => {
+                    debug!("Unable to fetch the changes of {}: {:?}", pipeline_key, e);
+                    let change = cache.get(&pipeline_key, &branch).and_then(|b| b.change.clone());
+                    cache.update(&pipeline_key, &branch, change, prompt::now());
+                }
+            }
+            try!(cache.save(&cache_path));
+            return Ok(0)
+        }
+
+        let summary = cache.summary(&pipeline_key, &branch);
+        if !summary.is_empty() {
+            outln("white", &summary);
+        }
+        if cache.is_stale(&pipeline_key, &branch, self.options.max_age, prompt::now()) {
+            prompt::spawn_refresh(&path);
+        }
+        Ok(0)
+    }
+}
+
+impl<'n> PromptCommand<'n> {
+    fn pipeline_key(&self) -> DeliveryResult<String> {
+        Ok(PromptCache::pipeline_key(&try!(self.config.enterprise()),
+                                     &try!(self.config.organization()),
+                                     &try!(project::project_from_cwd()),
+                                     &try!(self.config.pipeline())))
+    }
+
+    // The open changes of the pipeline, using only a stored token
+    fn fetch_open_changes(&self) -> DeliveryResult<Vec<http::change::ChangeSummary>> {
+        let org = try!(self.config.organization());
+        let proj = try!(project::project_from_cwd());
+        let pipe = try!(self.config.pipeline());
+        let mut client = try!(APIClient::from_config_no_auth(&self.config));
+        client.set_auth(try!(APIAuth::from_stored_token(&self.config)));
+        http::change::open_changes(&client, &org, &proj, &pipe)
+    }
+}
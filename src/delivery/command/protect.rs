@@ -0,0 +1,122 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::protect::ProtectClapOptions;
+use errors::{DeliveryError, Kind};
+use http::APIClient;
+use http::scp::{self, ScpClient};
+use project::{self, RemoteScp};
+use serde_json::Value;
+use types::{DeliveryResult, ExitCode};
+use utils::json_output;
+use utils::say::{say, sayln};
+use command::Command;
+use config::Config;
+
+pub struct ProtectCommand<'n> {
+    pub options: &'n ProtectClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for ProtectCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let remote = try!(self.remote());
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project());
+        let client = try!(APIClient::from_config(&self.config));
+        let pipelines = if self.options.pipeline.is_empty() {
+            try!(client.list_pipelines(&org, &proj))
+        } else {
+            vec![self.options.pipeline.to_string()]
+        };
+        let check = if self.options.status_check.is_empty() {
+            scp::DEFAULT_STATUS_CHECK
+        } else {
+            self.options.status_check
+        };
+        json_output::set("pipelines", &pipelines);
+
+        say("white", &format!("Protecting the pipeline branches of {}/{} on {} ",
+                              org, proj, remote.provider));
+        sayln("yellow", &format!("{}/{}", remote.organization, remote.repo_name));
+
+        // A dry run reads the current protection too, to tell what would
+        // change; the settings that are there already are kept either way.
+        let api_url = try!(self.api_url(&client, remote.provider));
+        let scp_client = try!(ScpClient::from_config(&self.config, remote.provider, &api_url));
+        let dry_run = self.options.dry_run;
+        let mut failed = 0;
+        for pipe in pipelines.iter() {
+            match scp_client.protect_branch(&remote.organization, &remote.repo_name, pipe,
+                                            check, dry_run) {
+                Ok(ref changes) if changes.is_empty() => {
+                    sayln("green", &format!("  {}: requires {}, no force pushes, unchanged",
+                                            pipe, check))
+                },
+                Ok(changes) => {
+                    let verb = if dry_run { "would" } else { "updated to" };
+                    sayln("green", &format!("  {}: {} {}, keeping the rest of its protection",
+                                            pipe, verb, changes.join(", ")))
+                },
+                Err(e) => {
+                    failed += 1;
+                    sayln("red", &format!("  {}: {}", pipe, e.detail().unwrap_or(e.to_string())));
+                }
+            }
+        }
+        Ok(if failed == 0 { 0 } else { 1 })
+    }
+}
+
+impl<'n> ProtectCommand<'n> {
+    // The GitHub or Bitbucket repository of the upstream remote
+    fn remote(&self) -> DeliveryResult<RemoteScp> {
+        match try!(project::detect_scp(&self.config)) {
+            Some(ref remote) if remote.provider != "gitlab" => Ok(remote.clone()),
+            _ => {
+                let msg = "Only projects with a GitHub or Bitbucket upstream remote have \
+                           branches to protect".to_string();
+                Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+            }
+        }
+    }
+
+    // `--api-url`, else the API the server itself uses for the provider
+    fn api_url(&self, client: &APIClient, provider: &str) -> DeliveryResult<String> {
+        if !self.options.api_url.is_empty() {
+            return Ok(self.options.api_url.to_string())
+        }
+        let configs = if provider == "bitbucket" {
+            client.get_bitbucket_server_config()
+        } else {
+            client.get_github_server_config()
+        };
+        let from_server = configs.ok().and_then(|configs| {
+            configs.iter()
+                .filter_map(|c| c.get("root_api_url").and_then(Value::as_str))
+                .next()
+                .map(|url| url.to_string())
+        });
+        match from_server {
+            Some(url) => Ok(url),
+            None if provider == "github" => Ok(scp::GITHUB_API_URL.to_string()),
+            None => {
+                let msg = "Pass the url of the Bitbucket server with --api-url".to_string();
+                Err(DeliveryError::throw(Kind::ConfigValidation, Some(msg)))
+            }
+        }
+    }
+}
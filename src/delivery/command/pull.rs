@@ -32,8 +32,8 @@ pub struct PullCommand<'n> {
 
 impl<'n> Command for PullCommand<'n> {
     fn setup(&self, child_processes: &mut Vec<std::process::Child>) -> DeliveryResult<()> {
+        try!(super::verify_and_repair_git_remote(&self.config, self.options.fix_remote));
         if self.config.fips.unwrap_or(false) {
-            try!(super::verify_and_repair_git_remote(&self.config));
             try!(fips::setup_and_start_stunnel(&self.config, child_processes));
         }
         Ok(())
@@ -41,6 +41,7 @@ impl<'n> Command for PullCommand<'n> {
 
     fn run(&self) -> DeliveryResult<ExitCode> {
         sayln("green", "Chef Delivery");
+        try!(super::refuse_in_managed_workspace(self.options.i_know_what_im_doing));
         let verb = if self.options.rebase { "Rebasing" } else { "Merging" };
         sayln("white", &format!("{} local HEAD on remote version of {}",
                                 verb, self.options.pipeline)
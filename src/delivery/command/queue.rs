@@ -0,0 +1,101 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::queue::QueueClapOptions;
+use types::{DeliveryResult, ExitCode};
+use http::{self, APIClient};
+use project::deliver_order::{self, QueuedChange};
+use utils::pool;
+use utils::template;
+use utils::say::{say, sayln, out, outln};
+use command::Command;
+use command::deliver_order::fetch_dependencies;
+use config::Config;
+
+pub struct QueueCommand<'n> {
+    pub options: &'n QueueClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for QueueCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let org = try!(self.config.organization());
+        let pipe = try!(self.config.pipeline());
+        let concurrency = try!(pool::parse_concurrency(self.options.concurrency));
+        let template = try!(template::from_format_option(self.options.format));
+        let projects = if self.options.projects.is_empty() {
+            vec![try!(self.config.project())]
+        } else {
+            self.options.projects.split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect()
+        };
+        let client = try!(APIClient::from_config(&self.config));
+
+        let (fetch_client, fetch_org, fetch_pipe) = (client.clone(), org.clone(), pipe.clone());
+        let fetched = pool::parallel_map(projects.clone(), concurrency, move |p| {
+            http::change::changes_in_state(&fetch_client, &fetch_org, &p, &fetch_pipe,
+                                           "approved")
+        });
+        let mut queued = Vec::new();
+        for (project, approved) in projects.iter().zip(fetched.into_iter()) {
+            queued.extend(try!(approved).into_iter().map(|c| {
+                QueuedChange { project: project.clone(), change: c }
+            }));
+        }
+        // Walks on through projects with nothing approved, so a change
+        // still waits for the ones it depends on indirectly
+        let deps = try!(fetch_dependencies(&client, &org, &pipe, projects, concurrency));
+
+        let ordered = try!(deliver_order::merge_queue(queued, &deps));
+        if let Some(ref template) = template {
+            let items: Vec<_> = ordered.iter().enumerate().map(|(i, q)| json!({
+                "position": i + 1,
+                "project": q.project,
+                "change": q.change,
+                "depends_on": deps.get(&q.project).cloned().unwrap_or_default()
+            })).collect();
+            return template.print_each(&items).map(|_| 0)
+        }
+
+        if ordered.is_empty() {
+            say("white", "No approved changes waiting in the ");
+            say("magenta", &pipe);
+            sayln("white", " pipeline");
+            return Ok(0)
+        }
+        say("white", "Merge queue of the ");
+        say("magenta", &pipe);
+        sayln("white", " pipeline:");
+        for (i, q) in ordered.iter().enumerate() {
+            out("white", &format!("  {}. ", i + 1));
+            out("yellow", &format!("{}:{}", q.project, q.change.id));
+            outln("white", &format!(" {}", q.change.title));
+            if !q.change.approved_by.is_empty() {
+                outln("white", &format!("       approved by {} at {}",
+                                        q.change.approved_by, q.change.approved_at));
+            }
+            match deps.get(&q.project) {
+                Some(d) if !d.is_empty() =>
+                    outln("cyan", &format!("       depends on {}", d.join(", "))),
+                _ => {}
+            }
+        }
+        Ok(0)
+    }
+}
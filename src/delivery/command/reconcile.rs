@@ -0,0 +1,94 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::path::PathBuf;
+use cli::reconcile::ReconcileClapOptions;
+use types::{DeliveryResult, ExitCode, OrgName, ProjectName, PipelineName};
+use delivery_config::pipelines::{Action, PipelinesToml};
+use http::APIClient;
+use project;
+use utils::say::{say, sayln};
+use command::Command;
+use config::Config;
+
+pub struct ReconcileCommand<'n> {
+    pub options: &'n ReconcileClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for ReconcileCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let org = try!(self.config.org_name());
+        let proj = try!(self.config.project_name());
+        let path = if self.options.file.is_empty() {
+            PipelinesToml::file_path(&try!(project::project_path()))
+        } else {
+            PathBuf::from(self.options.file)
+        };
+        let desired = try!(PipelinesToml::load_file(&path));
+        let client = try!(APIClient::from_config(&self.config));
+        let current = try!(client.project_state(&org, &proj));
+        let actions = desired.plan(&current, self.options.prune);
+
+        if actions.is_empty() {
+            say("green", "The server matches ");
+            sayln("yellow", &format!("{}", path.display()));
+            return Ok(0)
+        }
+
+        let verb = if self.options.dry_run { "Drift" } else { "Reconciling" };
+        say("white", &format!("{} of project ", verb));
+        say("yellow", &proj);
+        sayln("white", ":");
+        for action in actions.iter() {
+            let color = match *action {
+                Action::CreatePipeline { .. } => "green",
+                Action::DeletePipeline { .. } => "red",
+                _ => "yellow"
+            };
+            sayln(color, &format!("  {}", action));
+            if !self.options.dry_run {
+                try!(apply(&client, &org, &proj, action));
+            }
+        }
+        if self.options.dry_run {
+            sayln("white", "Run without --dry-run to apply these changes.");
+        } else {
+            sayln("green", "The server now matches the pipelines file");
+        }
+        Ok(0)
+    }
+}
+
+fn apply(client: &APIClient, org: &OrgName, proj: &ProjectName,
+         action: &Action) -> DeliveryResult<()> {
+    match *action {
+        Action::CreatePipeline { ref name, ref base } => {
+            let pipe = try!(PipelineName::new(name));
+            try!(client.create_pipeline(org, proj, &pipe, Some(base)));
+            Ok(())
+        },
+        Action::UpdateBase { ref name, ref to, .. } =>
+            client.update_pipeline_base(org, proj, name, to),
+        Action::SetDependencies { ref name, ref to, .. } =>
+            client.set_pipeline_dependencies(org, proj, name, to),
+        Action::SetNotifications { ref to, .. } =>
+            client.set_project_notifications(org, proj, to),
+        Action::DeletePipeline { ref name } =>
+            client.delete_pipeline(org, proj, name),
+    }
+}
@@ -0,0 +1,53 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::refs::RefsClapOptions;
+use types::{DeliveryResult, ExitCode};
+use git::refs;
+use project;
+use utils::say::{sayln, outln};
+use utils::json_output;
+use command::Command;
+use config::Config;
+
+pub struct RefsCommand<'n> {
+    pub options: &'n RefsClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for RefsCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let project_path = try!(project::project_path());
+        let local = try!(refs::local_patchset_refs(&project_path));
+        let pruned = if self.options.all {
+            local
+        } else {
+            let review_branches = try!(refs::remote_review_branches(&project_path));
+            refs::stale_refs(&local, &review_branches)
+        };
+        try!(refs::delete(&pruned, &project_path));
+        json_output::set("pruned", &pruned);
+
+        if pruned.is_empty() {
+            sayln("white", "No patchset ref to prune");
+        }
+        for r in pruned.iter() {
+            outln("white", &format!("  Deleted {}", r));
+        }
+        Ok(0)
+    }
+}
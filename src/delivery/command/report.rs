@@ -0,0 +1,48 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use std::path::PathBuf;
+use cli::report::ReportClapOptions;
+use types::{DeliveryResult, ExitCode, ChangeId};
+use http::APIClient;
+use http::comment::NewComment;
+use report::junit::JUnitReport;
+use utils::say::{sayln, outln};
+use utils::read_file;
+use command::Command;
+use config::Config;
+
+pub struct ReportCommand<'n> {
+    pub options: &'n ReportClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for ReportCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project());
+        let change = try!(ChangeId::new(self.options.change));
+        let xml = try!(read_file(&PathBuf::from(self.options.file)));
+        let report = try!(JUnitReport::parse(&xml));
+        let summary = report.to_markdown(self.options.phase);
+        outln(if report.is_success() { "green" } else { "red" }, &summary);
+
+        let client = try!(APIClient::from_config(&self.config));
+        try!(client.post_comment(&org, &proj, &change, &NewComment::new(&summary)));
+        sayln("white", &format!("Summary posted on change {}", change));
+        Ok(0)
+    }
+}
@@ -20,14 +20,24 @@ use fips;
 use cli::review::ReviewClapOptions;
 use config::Config;
 use utils;
-use utils::say::{sayln, say};
-use errors::DeliveryError;
+use utils::say::{sayln, say, outln};
+use utils::prompt::Prompt;
+use std::path::Path;
+use utils::json_output;
+use utils::strict::{self, Warning};
+use errors::{DeliveryError, Kind};
 use types::{DeliveryResult, ExitCode};
 use cookbook;
 use git::{self, ReviewResult};
-use http;
+use http::{self, APIClient, APIAuth};
+use http::change::Duplicate;
 use delivery_config::DeliveryConfig;
-use command::Command;
+use delivery_config::build_deps;
+use secretscan::{self, SecretScanConfig, Scanner};
+use tickets::{self, TicketLink};
+use annotations;
+use prompt;
+use command::{Command, check_shallow_clone, check_line_endings};
 use project;
 
 pub struct ReviewCommand<'n> {
@@ -37,8 +47,8 @@ pub struct ReviewCommand<'n> {
 
 impl<'n> Command for ReviewCommand<'n> {
     fn setup(&self, child_processes: &mut Vec<std::process::Child>) -> DeliveryResult<()> {
+        try!(super::verify_and_repair_git_remote(&self.config, self.options.fix_remote));
         if self.config.fips.unwrap_or(false) {
-            try!(super::verify_and_repair_git_remote(&self.config));
             try!(fips::setup_and_start_stunnel(&self.config, child_processes));
         }
         Ok(())
@@ -46,6 +56,7 @@ impl<'n> Command for ReviewCommand<'n> {
 
     fn run(&self) -> DeliveryResult<ExitCode> {
         sayln("green", "Chef Delivery");
+        try!(super::refuse_in_managed_workspace(self.options.i_know_what_im_doing));
 
         let config_ref = self.config;
         let target = validate!(config_ref, pipeline);
@@ -54,40 +65,346 @@ impl<'n> Command for ReviewCommand<'n> {
                 let project =  validate!(config_ref, project);
                 let project_root = try!(project::root_dir(&utils::cwd()));
                 try!(DeliveryConfig::validate_config_file(&project_root));
-                try!(cookbook::bump_version(&project_root, &target, &project))
+                let identity = git::CommitIdentity::from_config(&self.config);
+                try!(cookbook::bump_version(&project_root, &target, &project, &identity))
             }
         }
+        if !self.config.auto_bump.unwrap_or(false) {
+            try!(warn_unbumped_version(&target));
+        }
 
         let head = try!(git::get_head());
         say("white", "Review for change ");
         say("yellow", &head);
         say("white", " targeted for pipeline ");
         sayln("magenta", &target);
-        let review = try!(project::review(&target, &head));
+        if !self.options.skip_secret_scan {
+            try!(scan_for_secrets(&target));
+        }
+        let project_root = try!(project::project_path());
+        try!(check_shallow_clone(&project_root, self.options.unshallow));
+        try!(check_line_endings(&project_root));
+        try!(warn_unpinned_build_deps());
+        if !self.options.for_group.is_empty() {
+            return self.review_for_group(&target, &head)
+        }
+        let topic = try!(self.topic_for_review(&target, &head));
+        let review = try!(project::review_as(&target, &head, &topic));
+        json_output::set("pipeline", &target);
+        json_output::set("topic", &topic);
+        json_output::set("change_id", &review.change_id);
+        json_output::set("url", &review.url);
+        try!(self.after_review(&target, &head, &review));
+        Ok(0)
+    }
+}
+
+/// The outcome of submitting a change to one pipeline of a group
+#[derive(Serialize, Debug)]
+struct GroupReview {
+    pipeline: String,
+    change_id: Option<String>,
+    url: Option<String>,
+    error: Option<String>,
+}
 
+impl<'n> ReviewCommand<'n> {
+    // What follows submitting the commits of `target..head` as `review`:
+    // editing the change, linking its tickets, listing its TODOs and
+    // opening it in the browser.
+    fn after_review(&self, target: &str, head: &str,
+                    review: &ReviewResult) -> DeliveryResult<()> {
         if self.options.edit {
-            try!(edit_change(&self.config, &review));
+            try!(edit_change(&self.config, review));
+        }
+        // The change is submitted by now, so tickets never fail the review
+        if let Err(e) = link_tickets(&self.config, target, head, review) {
+            let error = e.detail.clone().unwrap_or(e.to_string());
+            sayln("yellow", &format!("Unable to link the tickets of the change: {}", error));
+        }
+        if let Err(e) = list_todos(&self.config, target, head, review) {
+            let error = e.detail.clone().unwrap_or(e.to_string());
+            sayln("yellow", &format!("Unable to list the TODOs of the change: {}", error));
+        }
+        // Have the shell prompt show the new change without waiting
+        if review.change_id.is_some() {
+            prompt::spawn_refresh(&try!(project::project_path()));
         }
 
         for line in review.messages.iter() {
             sayln("white", line);
         }
 
-        match project::handle_review_result(&review, &self.options.no_open) {
-            Ok(result) => {
-                match result {
-                    Some(url) => {sayln("magenta", &url)},
-                    None => {}
+        try!(project::handle_review_result(review, &self.options.no_open, &self.config));
+        Ok(())
+    }
+
+    // Submit the change to every pipeline of the group. The commits of
+    // `head` that are not on `base` are cherry-picked onto the other
+    // pipelines, each in a `<head>-for-<pipeline>` branch, and pushed
+    // with `head` as feature branch. A pipeline that fails does not
+    // stop the others.
+    fn review_for_group(&self, base: &str, head: &str) -> DeliveryResult<ExitCode> {
+        let group = self.options.for_group;
+        let pipelines = try!(self.config.pipeline_group(group));
+        let project_path = try!(project::project_path());
+        let commits = try!(git::commits_between(base, head, &project_path));
+        if commits.is_empty() {
+            let msg = format!("{} has no commits that are not on {}", head, base);
+            return Err(DeliveryError::throw(Kind::PipelineGroupReviewFailed, Some(msg)))
+        }
+
+        let mut reviews = vec![];
+        for pipeline in pipelines.iter() {
+            say("white", "Review for change ");
+            say("yellow", head);
+            say("white", " targeted for pipeline ");
+            sayln("magenta", pipeline);
+            let submitted = review_in_pipeline(base, head, pipeline, &commits, &project_path)
+                .and_then(|(target, branch, review)| {
+                    self.after_review(&target, &branch, &review).map(|_| review)
+                });
+            let review = match submitted {
+                Ok(review) => {
+                    GroupReview {
+                        pipeline: pipeline.clone(),
+                        change_id: review.change_id,
+                        url: review.url,
+                        error: None,
+                    }
+                },
+                Err(e) => {
+                    let error = e.detail.clone().unwrap_or(e.to_string());
+                    sayln("red", &format!("  {}", error));
+                    GroupReview {
+                        pipeline: pipeline.clone(),
+                        change_id: None,
+                        url: None,
+                        error: Some(error),
+                    }
                 }
-            },
-            Err(_) => {
-                sayln("yellow", "We could not open the review in the browser for you.");
-                sayln("yellow", "Make sure there is a program that can open HTML files in your path \
-                                 or pass --no-open to bypass attempting to open this review in a browser.");
+            };
+            reviews.push(review);
+        }
+
+        sayln("cyan", &format!("Pipeline group {}:", group));
+        for review in reviews.iter() {
+            match (&review.url, &review.error) {
+                (_, &Some(_)) => outln("red", &format!("  {}: failed", review.pipeline)),
+                (&Some(ref url), _) => outln("white", &format!("  {}: {}", review.pipeline, url)),
+                _ => outln("white", &format!("  {}: submitted", review.pipeline)),
             }
         }
+        json_output::set("group", &group);
+        json_output::set("topic", &head);
+        json_output::set("reviews", &reviews);
+
+        let failed = reviews.iter().filter(|r| r.error.is_some()).count();
+        if failed > 0 {
+            let msg = format!("{} of {} pipelines of group {} failed", failed,
+                              reviews.len(), group);
+            return Err(DeliveryError::throw(Kind::PipelineGroupReviewFailed, Some(msg)))
+        }
         Ok(0)
     }
+
+    // Check the open changes of the pipeline before pushing so that the
+    // same commit is not submitted twice under different feature branches.
+    // Returns the feature branch (topic) the review should be pushed as.
+    fn topic_for_review(&self, target: &str, head: &str) -> DeliveryResult<String> {
+        let changes = match open_changes(&self.config, target) {
+            Ok(changes) => changes,
+            Err(e) => {
+                debug!("Skipping duplicate change check: {:?}", e);
+                return Ok(head.to_string())
+            }
+        };
+        let sha = try!(git::git_current_sha());
+        match http::change::find_duplicate(&changes, head, sha.trim()) {
+            Some(Duplicate::SameTopic(change)) => {
+                say("white", "Adding a new patchset to the open change ");
+                sayln("yellow", &change.id);
+                Ok(head.to_string())
+            },
+            Some(Duplicate::SameSha(change)) => {
+                say("yellow", "The open change ");
+                say("magenta", &change.id);
+                say("yellow", " (");
+                say("magenta", &change.topic);
+                sayln("yellow", ") already has this commit as its head.");
+                if self.options.reuse {
+                    say("white", "Pushing a new patchset to ");
+                    sayln("magenta", &change.topic);
+                    return Ok(change.topic.clone())
+                }
+                if self.config.non_interactive.unwrap_or(false) {
+                    let msg = format!("Change {} already exists for this commit. Use --reuse \
+                                      to push a new patchset to it instead.", change.id);
+                    return Err(DeliveryError::throw(Kind::DuplicateChange, Some(msg)))
+                }
+                let anyway = try!(Prompt::new("Create a new change anyway? (use --reuse to \
+                                               update the existing one)")
+                                  .default("n")
+                                  .confirm());
+                if anyway {
+                    Ok(head.to_string())
+                } else {
+                    Err(DeliveryError::throw(Kind::DuplicateChange, None))
+                }
+            },
+            None => Ok(head.to_string())
+        }
+    }
+}
+
+// Submit `commits` to `pipeline`, as is when it is the pipeline the
+// change was made against, cherry-picked onto it otherwise. Returns the
+// review along with the ref it starts from and the branch it was
+// pushed from.
+fn review_in_pipeline(base: &str, head: &str, pipeline: &str, commits: &[String],
+                      project_path: &Path) -> DeliveryResult<(String, String, ReviewResult)> {
+    if pipeline == base {
+        let review = try!(project::review_as(pipeline, head, head));
+        return Ok((base.to_string(), head.to_string(), review))
+    }
+    let onto = try!(git::fetch_pipeline(pipeline, project_path));
+    let branch = format!("{}-for-{}", head, pipeline.replace("/", "-"));
+    try!(git::cherry_pick_to_branch(&branch, &onto, commits, project_path));
+    let review = try!(git::git_push_review_to_topic(&branch, pipeline, head));
+    Ok((onto, branch, review))
+}
+
+// Verify runs of a change are only reproducible when the dependencies of
+// the build cookbook are pinned; say so, but do not hold the review back
+// unless in strict mode.
+fn warn_unpinned_build_deps() -> DeliveryResult<()> {
+    let status = project::project_path().and_then(|p| build_deps::lockfile_status(&p));
+    if let Ok(status) = status {
+        if status.is_problem() {
+            let msg = format!("{}.\n  Run `delivery update-build-deps` to pin them in \
+                               a review.", status);
+            try!(strict::warn(Warning::UnpinnedBuildDeps, &msg));
+        }
+    }
+    Ok(())
+}
+
+// A cookbook change that keeps the version of the pipeline cannot be
+// told apart from it once delivered.
+fn warn_unbumped_version(target: &str) -> DeliveryResult<()> {
+    let project_root = match project::root_dir(&utils::cwd()) {
+        Ok(root) => root,
+        Err(_) => return Ok(())
+    };
+    match cookbook::unbumped_version(&project_root, target) {
+        Ok(Some(version)) => {
+            let msg = format!("The cookbook version {} is the same as on {}; bump it, \
+                               or use --auto-bump.", version, target);
+            strict::warn(Warning::UnbumpedVersion, &msg)
+        },
+        Ok(None) => Ok(()),
+        Err(e) => {
+            debug!("Skipping the cookbook version check: {:?}", e);
+            Ok(())
+        }
+    }
+}
+
+// Refuse to push a change that adds lines that look like credentials,
+// for projects that opted in with a .delivery/secretscan.toml.
+fn scan_for_secrets(target: &str) -> DeliveryResult<()> {
+    let project_root = try!(project::root_dir(&utils::cwd()));
+    let config = match try!(SecretScanConfig::load(&project_root)) {
+        Some(config) => config,
+        None => return Ok(())
+    };
+    let scanner = try!(Scanner::new(&config));
+    let range = format!("{}...HEAD", target);
+    let diff = try!(git::git_command(&["diff", "--unified=0", "--no-color", &range],
+                                     &project_root));
+    let findings = scanner.scan_diff(&diff.stdout);
+    if findings.is_empty() {
+        sayln("white", "No credentials found in the change");
+        Ok(())
+    } else {
+        Err(secretscan::findings_error(&findings))
+    }
+}
+
+// Fetch the open changes of the project that target `pipeline`. Only a
+// token that is already stored is used, a review never prompts for one.
+fn open_changes(config: &Config,
+                pipeline: &str) -> DeliveryResult<Vec<http::change::ChangeSummary>> {
+    let org = try!(config.organization());
+    let proj = try!(config.project());
+    let mut client = try!(APIClient::from_config_no_auth(config));
+    client.set_auth(try!(APIAuth::from_stored_token(config)));
+    http::change::open_changes(&client, &org, &proj, pipeline)
+}
+
+// List the tickets named by the feature branch and its commits in the
+// description of the change and, with a ticket_link_url, have the
+// tracker link back to the change.
+fn link_tickets(config: &Config, target: &str, head: &str,
+                review: &ReviewResult) -> DeliveryResult<()> {
+    let (pattern, change_id) = match (&config.ticket_pattern, &review.change_id) {
+        (&Some(ref pattern), &Some(ref change_id)) => (pattern, change_id),
+        _ => return Ok(())
+    };
+    let range = format!("{}..{}", target, head);
+    let log = try!(git::git_command(&["log", "--format=%B", &range],
+                                    &try!(project::project_path())));
+    let found = try!(tickets::find(pattern, &[head, &log.stdout]));
+    if found.is_empty() {
+        return Ok(())
+    }
+    say("white", "Tickets: ");
+    sayln("yellow", &found.join(", "));
+    json_output::set("tickets", &found);
+
+    let mut change = try!(http::change::get(config, change_id));
+    let description = tickets::describe(&change.description, &found);
+    if description != change.description {
+        change.description = description;
+        try!(http::change::set(config, change_id, &change));
+    }
+    if let Ok(url) = config.ticket_link_url() {
+        let ticket_link = TicketLink {
+            change_id: change_id.clone(),
+            change_url: review.url.clone().unwrap_or_default(),
+            tickets: found,
+        };
+        try!(tickets::link(config, &url, &ticket_link));
+        sayln("white", "Linked the change from its tickets");
+    }
+    Ok(())
+}
+
+// Add a checklist of the TODO markers the change adds to its
+// description, unless the project turned it off with todo_checklist.
+fn list_todos(config: &Config, target: &str, head: &str,
+              review: &ReviewResult) -> DeliveryResult<()> {
+    let change_id = match review.change_id {
+        Some(ref change_id) if config.todo_checklist.unwrap_or(true) => change_id,
+        _ => return Ok(())
+    };
+    let range = format!("{}...{}", target, head);
+    let diff = try!(git::git_command(&["diff", "--unified=0", "--no-color", &range],
+                                     &try!(project::project_path())));
+    let found = try!(annotations::find(&annotations::markers(config), &diff.stdout));
+    if !found.is_empty() {
+        sayln("white", &format!("The change adds {} TODO(s), listed in its description",
+                                found.len()));
+        json_output::set("todos", &found);
+    }
+    // Even without any, a checklist from an earlier revision has to go
+    let mut change = try!(http::change::get(config, change_id));
+    let description = annotations::describe(&change.description, &found);
+    if description != change.description {
+        change.description = description;
+        try!(http::change::set(config, change_id, &change));
+    }
+    Ok(())
 }
 
 fn edit_change(config: &Config,
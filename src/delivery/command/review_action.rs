@@ -0,0 +1,51 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::review_action::{ReviewActionClapOptions, APPROVE_SUBCOMMAND_NAME};
+use types::{DeliveryResult, ExitCode, ChangeId};
+use http::APIClient;
+use http::comment::NewComment;
+use utils::say::sayln;
+use utils::json_output;
+use command::Command;
+use config::Config;
+
+pub struct ReviewActionCommand<'n> {
+    pub options: &'n ReviewActionClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for ReviewActionCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project());
+        let change = try!(ChangeId::new(self.options.change));
+        let client = try!(APIClient::from_config(&self.config));
+
+        // clap requires the message of a comment, an approval takes one
+        if !self.options.message.is_empty() {
+            try!(client.post_comment(&org, &proj, &change,
+                                     &NewComment::new(self.options.message)));
+            sayln("green", &format!("Comment posted on change {}", change));
+        }
+        if self.options.action == APPROVE_SUBCOMMAND_NAME {
+            try!(client.approve_change(&org, &proj, &change));
+            json_output::set("approved", &change.as_str());
+            sayln("green", &format!("Change {} approved and merging into its pipeline", change));
+        }
+        Ok(0)
+    }
+}
@@ -0,0 +1,49 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::review_verdict::ReviewVerdictClapOptions;
+use types::{DeliveryResult, ExitCode, ChangeId};
+use http::APIClient;
+use http::comment::{ReviewVerdict, Verdict};
+use utils::say::sayln;
+use command::Command;
+use config::Config;
+
+pub struct ReviewVerdictCommand<'n> {
+    pub options: &'n ReviewVerdictClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for ReviewVerdictCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project());
+        let change = try!(ChangeId::new(self.options.change));
+        // clap makes sure exactly one of --approve and --request-changes is given
+        let verdict = if self.options.approve { Verdict::Approve } else { Verdict::RequestChanges };
+
+        let client = try!(APIClient::from_config(&self.config));
+        try!(client.post_review_verdict(&org, &proj, &change,
+                                        &ReviewVerdict::new(verdict, self.options.message)));
+        match verdict {
+            Verdict::Approve => sayln("green", &format!("Change {} approved", change)),
+            Verdict::RequestChanges => sayln("yellow", &format!("Changes requested on change {}",
+                                                                change)),
+        }
+        Ok(0)
+    }
+}
@@ -0,0 +1,92 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::runner::RunnerClapOptions;
+use types::{DeliveryResult, ExitCode};
+use errors::{DeliveryError, Kind};
+use http::APIClient;
+use http::runner::Runner;
+use utils::say::{sayln, out, outln};
+use utils::template;
+use command::Command;
+use config::Config;
+
+pub struct RunnerCommand<'n> {
+    pub options: &'n RunnerClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for RunnerCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let client = try!(APIClient::from_config(&self.config));
+        match self.options.action {
+            "list" => {
+                let runners = try!(client.list_runners());
+                if let Some(template) = try!(template::from_format_option(self.options.format)) {
+                    return template.print_each(&runners).map(|_| 0)
+                }
+                if runners.is_empty() {
+                    sayln("yellow", "No runners registered");
+                }
+                for runner in runners.iter() {
+                    out("white", &format!("{:<40}", runner.hostname));
+                    outln("magenta", &format!("{} {} {}", runner.os, runner.platform,
+                                              runner.platform_version).trim());
+                }
+            },
+            "register" => {
+                let runner = try!(client.register_runner(&try!(self.runner())));
+                sayln("green", &format!("Runner {} registered", runner.hostname));
+                if let Some(cmd) = runner.install_command() {
+                    sayln("white", "Run the following on the runner host so the server \
+                                    is able to log into it:\n");
+                    sayln("yellow", &cmd);
+                }
+            },
+            "delete" => {
+                let hostname = try!(self.hostname());
+                try!(client.delete_runner(hostname));
+                sayln("green", &format!("Runner {} deleted", hostname));
+            },
+            action => {
+                let msg = format!("Unknown runner action '{}', use list, register or delete",
+                                  action);
+                return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+            }
+        }
+        Ok(0)
+    }
+}
+
+impl<'n> RunnerCommand<'n> {
+    fn hostname(&self) -> DeliveryResult<&'n str> {
+        if self.options.hostname.is_empty() {
+            let msg = format!("A hostname is required to {} a runner", self.options.action);
+            Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        } else {
+            Ok(self.options.hostname)
+        }
+    }
+
+    fn runner(&self) -> DeliveryResult<Runner> {
+        let mut runner = Runner::new(try!(self.hostname()));
+        runner.os = self.options.os.to_string();
+        runner.platform = self.options.platform.to_string();
+        runner.platform_family = self.options.platform_family.to_string();
+        runner.platform_version = self.options.platform_version.to_string();
+        Ok(runner)
+    }
+}
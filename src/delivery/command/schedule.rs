@@ -0,0 +1,229 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::path::Path;
+use std::time::Duration;
+use cli::schedule::ScheduleClapOptions;
+use types::{DeliveryResult, ExitCode, ChangeId};
+use errors::{DeliveryError, Kind};
+use features::{self, SCHEDULED_DELIVERY};
+use http::{self, APIAuth, APIClient};
+use hyper::status::StatusCode;
+use schedule::{self, Due, Entry, Schedule};
+use utils::poll::{self, Backoff, Cancel, Poll};
+use utils::say::{sayln, outln};
+use command::Command;
+use config::Config;
+use time;
+
+// How often `schedule run` looks for due deliveries
+const RUN_INTERVAL: u64 = 30;
+
+pub struct ScheduleCommand<'n> {
+    pub options: &'n ScheduleClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for ScheduleCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        match self.options.action {
+            "list" => self.list(),
+            "cancel" => self.cancel(),
+            _ => self.run_schedule(),
+        }
+    }
+}
+
+impl<'n> ScheduleCommand<'n> {
+    fn list(&self) -> DeliveryResult<ExitCode> {
+        let local = try!(Schedule::load(&try!(Schedule::path())));
+        for entry in local.entries.iter() {
+            outln("white", &format!("{}  {}/{}  {}  local", entry.change, entry.org,
+                                    entry.project, entry.when()));
+            if let Some(ref error) = entry.last_error {
+                if entry.gave_up() {
+                    outln("red", &format!("  gave up after {} attempts: {}", entry.attempts,
+                                          error));
+                } else {
+                    outln("red", &format!("  last attempt failed: {}", error));
+                }
+            }
+        }
+        let mut listed = local.entries.len();
+        if let Some((client, org, proj)) = self.server_schedule() {
+            match http::change::scheduled_deliveries(&client, &org, &proj) {
+                Ok(deliveries) => {
+                    for delivery in deliveries.iter() {
+                        let when = match delivery.until {
+                            Some(ref until) => format!("{}-{}", delivery.at, until),
+                            None => delivery.at.clone()
+                        };
+                        outln("white", &format!("{}  {}/{}  {}  server", delivery.change,
+                                                org, proj, when));
+                    }
+                    listed += deliveries.len();
+                },
+                Err(e) => sayln("yellow", &format!("WARN: Unable to list the deliveries \
+                                                    the server scheduled: {}", e))
+            }
+        }
+        if listed == 0 {
+            sayln("white", "No deliveries are scheduled");
+        }
+        Ok(0)
+    }
+
+    fn cancel(&self) -> DeliveryResult<ExitCode> {
+        if self.options.change.is_empty() {
+            let msg = "Give the change whose delivery to cancel".to_string();
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        let change = try!(ChangeId::new(self.options.change));
+        let path = try!(Schedule::path());
+        let mut local = try!(Schedule::load(&path));
+        if local.remove(&change).is_some() {
+            try!(local.save(&path));
+            sayln("green", &format!("Delivery of change {} cancelled", change));
+            return Ok(0)
+        }
+        if let Some((client, org, proj)) = self.server_schedule() {
+            try!(http::change::cancel_scheduled_delivery(&client, &org, &proj, &change));
+            sayln("green", &format!("Delivery of change {} cancelled on the server", change));
+            return Ok(0)
+        }
+        let msg = format!("No delivery of change {} is scheduled", change);
+        Err(DeliveryError::throw(Kind::NoScheduledDelivery, Some(msg)))
+    }
+
+    // Deliver the local schedule as it comes due, until interrupted, or
+    // once with --once
+    fn run_schedule(&self) -> DeliveryResult<ExitCode> {
+        let path = try!(Schedule::path());
+        if self.options.once {
+            try!(run_due(self.config, &path));
+            return Ok(0)
+        }
+        sayln("white", &format!("Delivering the schedule of {} as it comes due",
+                                path.display()));
+        let interval = Duration::from_secs(RUN_INTERVAL);
+        let mut backoff = Backoff::new(interval, interval);
+        let done: Option<()> = try!(poll::poll(&mut backoff, &Cancel::new(), || {
+            try!(run_due(self.config, &path));
+            Ok(Poll::Unchanged)
+        }));
+        Ok(if done.is_some() { 0 } else { 1 })
+    }
+
+    // The client, org and project to ask about the deliveries the server
+    // schedules, when it does
+    fn server_schedule(&self) -> Option<(APIClient, String, String)> {
+        if !features::is_enabled(&self.config, SCHEDULED_DELIVERY) {
+            return None
+        }
+        let org = self.config.organization().ok();
+        let proj = self.config.project().ok();
+        match (APIClient::from_config(&self.config).ok(), org, proj) {
+            (Some(client), Some(org), Some(proj)) => Some((client, org, proj)),
+            _ => None
+        }
+    }
+}
+
+// Deliver the entries of the schedule at `path` that are due. A failed
+// delivery is tried again later, within its window, unless the server
+// refused it for good or it failed too many times already.
+fn run_due(config: &Config, path: &Path) -> DeliveryResult<()> {
+    let now = time::get_time().sec;
+    let due = try!(Schedule::load(path)).entries;
+    for entry in due.iter() {
+        let outcome = match entry.due(now) {
+            Due::NotYet | Due::GaveUp => continue,
+            Due::Missed((at, until)) => {
+                sayln("yellow", &format!("The window of change {} closed before it was \
+                                          delivered, trying again at {}", entry.change,
+                                         schedule::format_time(at)));
+                Outcome::Reschedule(at, until)
+            },
+            Due::Now => match deliver(config, entry) {
+                Ok(()) => {
+                    sayln("green", &format!("Change {} of {}/{} delivered", entry.change,
+                                            entry.org, entry.project));
+                    Outcome::Delivered
+                },
+                Err(e) => {
+                    let error = format!("{} {}", e, e.detail().unwrap_or_default());
+                    let permanent = is_permanent(&e);
+                    if permanent || entry.attempts + 1 >= schedule::MAX_ATTEMPTS {
+                        sayln("red", &format!("Giving up on the delivery of change {}: {}\n\
+                                               Schedule it again with `delivery deliver`.",
+                                              entry.change, error));
+                    } else {
+                        sayln("red", &format!("Unable to deliver change {}, trying again \
+                                               later: {}", entry.change, error));
+                    }
+                    Outcome::Failed(error, permanent)
+                }
+            }
+        };
+        // `delivery deliver` may have changed the schedule meanwhile
+        let mut current = try!(Schedule::load(path));
+        match outcome {
+            Outcome::Delivered => { current.remove(&entry.change); },
+            Outcome::Reschedule(at, until) => if let Some(e) = current.get_mut(&entry.change) {
+                e.at = at;
+                e.until = Some(until);
+            },
+            Outcome::Failed(error, permanent) => if let Some(e) = current.get_mut(&entry.change) {
+                e.failed(error, permanent, now);
+            }
+        }
+        try!(current.save(path));
+    }
+    Ok(())
+}
+
+enum Outcome {
+    Delivered,
+    Reschedule(i64, i64),
+    // The error, and whether trying again can not help
+    Failed(String, bool),
+}
+
+// Nobody is there to answer a prompt, so only a stored token is used
+fn deliver(config: &Config, entry: &Entry) -> DeliveryResult<()> {
+    let config = config.clone()
+        .set_server_url(&entry.server_url)
+        .set_user(&entry.user)
+        .set_enterprise(&entry.ent);
+    let mut client = try!(APIClient::from_config_no_auth(&config));
+    client.set_auth(try!(APIAuth::from_stored_token(&config)));
+    http::change::deliver(&client, &entry.org, &entry.project, &entry.change)
+}
+
+// Whether a failed delivery would fail the same way if tried again: the
+// server refused the request itself, or there are no credentials
+fn is_permanent(e: &DeliveryError) -> bool {
+    match e.kind {
+        Kind::ApiError(status, _) => {
+            status.is_client_error() && status != StatusCode::RequestTimeout
+                && status != StatusCode::TooManyRequests
+        },
+        Kind::NoToken | Kind::TokenExpired | Kind::AuthenticationFailed
+            | Kind::ForbiddenRequest | Kind::EndpointNotFound => true,
+        _ => false
+    }
+}
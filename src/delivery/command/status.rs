@@ -18,11 +18,20 @@
 use cli::status::StatusClapOptions;
 use command::Command;
 use config::Config;
-use http;
+use events::watch::{self, Outcome};
+use git::{self, MergeStatus};
+use http::{self, APIAuth, APIClient};
+use project;
+use prompt::{self, PromptCache};
 use serde_json;
 use types::{DeliveryResult, ExitCode};
-use utils::say::{say, sayln};
-use std::time::Instant;
+use utils::say::{out, outln};
+use utils::json_output;
+use utils::limits;
+use utils::poll::{self, Cancel, Poll};
+use utils::template;
+use std::path::Path;
+use std::time::{Duration, Instant};
 use json::server_status::*;
 
 pub struct StatusCommand<'n> {
@@ -32,6 +41,10 @@ pub struct StatusCommand<'n> {
 
 impl<'n> Command for StatusCommand<'n> {
     fn run(&self) -> DeliveryResult<ExitCode> {
+        if !self.options.watch.is_empty() {
+            return self.watch()
+        }
+        let template = try!(template::from_format_option(self.options.format));
         let client = try!(http::APIClient::from_config_with_basic_routing(&self.config));
 
         let start = Instant::now();
@@ -44,64 +57,71 @@ impl<'n> Command for StatusCommand<'n> {
         // key with a space in it.
         json_string = json_string.replace("configuration mode", "configuration_mode");
 
+        if let Some(template) = template {
+            let status: serde_json::Value = try!(serde_json::from_str(&json_string));
+            return template.print_each(&[status]).map(|_| 0)
+        }
+
         if self.options.json {
-            sayln("white", json_string.as_ref());
+            let status: serde_json::Value = try!(serde_json::from_str(&json_string));
+            json_output::set("status", &status);
+            json_output::set("response_time_ms", &elapsed_milli);
             return Ok(0)
         }
 
         // Closures to display status with the right color
-        let say_status   = |s: &str| if s == "up" { say("success", s) } else { say("error", s) };
-        let sayln_status = |s: &str| if s == "up" { sayln("success", s) } else { sayln("error", s) };
+        let out_status   = |s: &str| if s == "up" { out("success", s) } else { out("error", s) };
+        let outln_status = |s: &str| if s == "up" { outln("success", s) } else { outln("error", s) };
 
         // Replace "pong" with "up" because it is more human friendly word.
         json_string = json_string.replace("pong", "up");
 
         let s: ServerStatus = serde_json::from_str(&json_string)?;
 
-        sayln("white", &format!(
+        outln("white", &format!(
                 "Status information for Automate server {}...\n",
                 self.config.api_host_and_port()?
         ));
 
-        say("white", "Status: ");
-        say_status(&s.status);
+        out("white", "Status: ");
+        out_status(&s.status);
 
         if s.status == "up" {
-            sayln("success", &format!(" (request took {} ms)", &elapsed_milli.to_string()));
+            outln("success", &format!(" (request took {} ms)", &elapsed_milli.to_string()));
         } else {
-            sayln("error", &format!(" (request took {} ms)", &elapsed_milli.to_string()));
+            outln("error", &format!(" (request took {} ms)", &elapsed_milli.to_string()));
 	}
 
-        sayln("white", &format!("Configuration Mode: {}", s.configuration_mode));
+        outln("white", &format!("Configuration Mode: {}", s.configuration_mode));
 
         // Backward compat: fips_mode was added later so it is an optional field.
         if let Some(fips) = s.fips_mode {
             let fips_output = if fips { "enabled" } else { "disabled" };
-            sayln("white", &format!("FIPS Mode: {}", fips_output));
+            outln("white", &format!("FIPS Mode: {}", fips_output));
         }
 
         let ref u = s.upstreams[0];
-        sayln("white", "Upstreams:");
-        sayln("white", "  Lsyncd:");
-        say("white", "    status: ");
-        sayln_status(&u.lsyncd.status);
-        sayln("white", "  PostgreSQL:");
-        say("white",   "    status: ");
-        sayln_status(&u.postgres.status);
-        sayln("white", "  RabbitMQ:");
-        say("white",   "    status: ");
-        sayln_status(&u.rabbitmq.status);
+        outln("white", "Upstreams:");
+        outln("white", "  Lsyncd:");
+        out("white", "    status: ");
+        outln_status(&u.lsyncd.status);
+        outln("white", "  PostgreSQL:");
+        out("white",   "    status: ");
+        outln_status(&u.postgres.status);
+        outln("white", "  RabbitMQ:");
+        out("white",   "    status: ");
+        outln_status(&u.rabbitmq.status);
 
         if let Some(ref node_health) = u.rabbitmq.node_health {
-            sayln("white", "    node_health:");
-            say("white",   "      status: ");
-            sayln_status(&node_health.status);
+            outln("white", "    node_health:");
+            out("white",   "      status: ");
+            outln_status(&node_health.status);
         }
 
         if let Some(ref vhost_aliveness) = u.rabbitmq.vhost_aliveness {
-            sayln("white", "    vhost_aliveness:");
-            say("white",   "      status: ");
-            sayln_status(&vhost_aliveness.status);
+            outln("white", "    vhost_aliveness:");
+            out("white",   "      status: ");
+            outln_status(&vhost_aliveness.status);
         }
 
         if let Some(fips) = s.fips_mode {
@@ -110,9 +130,219 @@ impl<'n> Command for StatusCommand<'n> {
                     Please add the following to your cli.toml to enable Automate FIPS \
                     mode on your machine:\n\nfips = true\nfips_git_port = \"OPEN_PORT\"\n\n\
                     Replace OPEN_PORT with any port that is free on your machine.";
-                sayln("white", msg);
+                outln("white", msg);
             }
         }
+
+        if self.options.merge_check {
+            self.say_merge_status();
+        }
         Ok(0)
     }
 }
+
+impl<'n> StatusCommand<'n> {
+    // Follow the change of `--watch` through its stages, printing every
+    // phase whose status moves, until one fails, the `--until` stage
+    // passes, the change can not go further or `--timeout` passes. Only
+    // the first poll failing is an error; after that the server going
+    // away for a while just means waiting longer.
+    fn watch(&self) -> DeliveryResult<ExitCode> {
+        let until = try!(watch::parse_until(self.options.until));
+        let cancel = if self.options.timeout.is_empty() {
+            Cancel::new()
+        } else {
+            let timeout = try!(limits::parse_duration(self.options.timeout));
+            Cancel::after(Duration::from_secs(timeout))
+        };
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project().or_else(|_| project::project_from_cwd()));
+        let client = try!(APIClient::from_config(&self.config));
+        let change_id = self.watched_change_id(&client, &org, &proj);
+        outln("white", &format!("Watching change {} of {}/{} until {} passes",
+                                change_id, org, proj, until));
+
+        let mut known = watch::Progress::new();
+        let mut first = true;
+        let mut backoff = self.config.poll_backoff();
+        let watched = try!(poll::poll(&mut backoff, &cancel, || {
+            let change = match http::change::progress(&client, &org, &proj, &change_id) {
+                Ok(change) => change,
+                Err(e) => {
+                    if first {
+                        return Err(e)
+                    }
+                    debug!("Unable to poll the change: {:?}", e);
+                    return Ok(Poll::Unchanged)
+                }
+            };
+            first = false;
+            let current = watch::progress(&change.stages, &until);
+            let moved = watch::diff(&known, &current);
+            known = current;
+            for &(ref key, ref status) in moved.iter() {
+                say_progress(key, status);
+            }
+            Ok(match watch::outcome(&known, &change, &until) {
+                Some(outcome) => Poll::Ready(outcome),
+                None if moved.is_empty() => Poll::Unchanged,
+                None => Poll::Changed
+            })
+        }));
+        let outcome = watched.unwrap_or(Outcome::TimedOut);
+        match outcome {
+            Outcome::Passed => {
+                outln("success", &format!("Change {} passed {}", change_id, until))
+            },
+            Outcome::Failed => outln("error", &format!("Change {} failed", change_id)),
+            Outcome::TimedOut => {
+                outln("error", &format!("Timed out after {} watching change {}",
+                                        self.options.timeout, change_id))
+            },
+            Outcome::Closed(ref state) => {
+                outln("yellow", &format!("Change {} is {}, it will not run further",
+                                         change_id, state))
+            },
+            Outcome::AwaitingApproval => {
+                outln("yellow", &format!("Change {} passed verify and is waiting for \
+                                          approval", change_id))
+            }
+        }
+        Ok(outcome.exit_code())
+    }
+
+    // The change `--watch` names: the open change of that feature
+    // branch if there is one, else the id as given.
+    fn watched_change_id(&self, client: &APIClient, org: &str, proj: &str) -> String {
+        let watched = self.options.watch;
+        let changes = self.config.pipeline()
+            .and_then(|pipe| http::change::open_changes(client, org, proj, &pipe));
+        match changes {
+            Ok(changes) => changes.into_iter()
+                .find(|c| c.topic == watched)
+                .map(|c| c.id)
+                .unwrap_or(watched.to_string()),
+            Err(e) => {
+                debug!("Unable to look up the open changes: {:?}", e);
+                watched.to_string()
+            }
+        }
+    }
+
+    // With --merge-check, from a feature branch, report whether it merges
+    // cleanly with the head of its pipeline. This is only informative, anything
+    // that prevents the check is logged and the check skipped.
+    fn say_merge_status(&self) {
+        let path = match project::project_path() {
+            Ok(path) => path,
+            Err(_) => return
+        };
+        let (head, pipe) = match (git::get_head(), self.config.pipeline()) {
+            (Ok(head), Ok(pipe)) => (head, pipe),
+            _ => return
+        };
+        if head == pipe {
+            return
+        }
+
+        let (status, source) = match self.server_merge_status(&head, &pipe) {
+            Ok(Some(status)) => (Ok(status), "server"),
+            result => {
+                debug!("No merge status from the server: {:?}", result);
+                (local_merge_status(&path, &pipe), "local merge simulation")
+            }
+        };
+        outln("white", &format!("\nMerge check of {} into {} ({}):", head, pipe, source));
+        out("white", "  status: ");
+        match status {
+            Ok(MergeStatus::Clean) => outln("success", "merges cleanly"),
+            Ok(MergeStatus::Conflicts(files)) => {
+                outln("error", "conflicts");
+                for file in files.iter() {
+                    outln("error", &format!("    {}", file));
+                }
+                outln("white", &format!("  Rebase on {} and resolve the conflicts before \
+                                        the change can be approved.", pipe));
+            },
+            Err(e) => {
+                debug!("Unable to simulate the merge: {:?}", e);
+                outln("yellow", "unknown");
+            }
+        }
+    }
+
+    // The merge status the server computed for the open change of the
+    // feature branch, if any. Only a stored token is used. The change is
+    // looked up in the prompt cache first, and the open changes fetched
+    // otherwise go to the cache.
+    fn server_merge_status(&self, head: &str,
+                           pipe: &str) -> DeliveryResult<Option<MergeStatus>> {
+        let ent = try!(self.config.enterprise());
+        let org = try!(self.config.organization());
+        let proj = try!(self.config.project().or_else(|_| project::project_from_cwd()));
+        let mut client = try!(APIClient::from_config_no_auth(&self.config));
+        client.set_auth(try!(APIAuth::from_stored_token(&self.config)));
+        let pipeline_key = PromptCache::pipeline_key(&ent, &org, &proj, pipe);
+        let change_id = match cached_change_id(&pipeline_key, head) {
+            Some(change_id) => change_id,
+            None => {
+                let changes = try!(http::change::open_changes(&client, &org, &proj, pipe));
+                cache_open_changes(&pipeline_key, &changes);
+                changes.iter().find(|c| c.topic == head).map(|c| c.id.clone())
+            }
+        };
+        match change_id {
+            Some(change_id) => http::change::merge_status(&client, &org, &proj, &change_id),
+            None => Ok(None)
+        }
+    }
+}
+
+fn say_progress(key: &str, status: &str) {
+    let color = match status {
+        "passed" => "success",
+        "failed" => "error",
+        "running" => "cyan",
+        _ => "white"
+    };
+    out("white", &format!("  {:<28} ", key));
+    outln(color, status);
+}
+
+// The id of the open change of `head` as fetched in the last minute;
+// `Some(None)` when it had none.
+fn cached_change_id(pipeline_key: &str, head: &str) -> Option<Option<String>> {
+    let cache = match PromptCache::path() {
+        Ok(path) => PromptCache::load(&path),
+        Err(_) => return None
+    };
+    match cache.fresh(pipeline_key, head, prompt::DEFAULT_MAX_AGE, prompt::now()) {
+        Some(Some(ref change)) if change.id.is_empty() => None,
+        Some(change) => Some(change.map(|c| c.id)),
+        None => None
+    }
+}
+
+fn cache_open_changes(pipeline_key: &str, changes: &[http::change::ChangeSummary]) {
+    let result = PromptCache::path().and_then(|path| {
+        let mut cache = PromptCache::load(&path);
+        cache.update_pipeline(pipeline_key, changes, prompt::now());
+        cache.save(&path)
+    });
+    if let Err(e) = result {
+        debug!("Unable to update the prompt cache: {:?}", e);
+    }
+}
+
+// Simulate the merge of HEAD into the pipeline, using the latest
+// pipeline head from the server when it can be fetched.
+fn local_merge_status(path: &Path, pipe: &str) -> DeliveryResult<MergeStatus> {
+    let target = match git::git_command(&["fetch", "delivery", pipe], path) {
+        Ok(_) => "FETCH_HEAD",
+        Err(e) => {
+            debug!("Unable to fetch {} from delivery: {:?}", pipe, e);
+            pipe
+        }
+    };
+    git::simulate_merge(path, target, "HEAD")
+}
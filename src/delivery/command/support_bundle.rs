@@ -0,0 +1,99 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use std::env::consts;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::PathBuf;
+use cli::support_bundle::SupportBundleClapOptions;
+use types::{DeliveryResult, ExitCode};
+use errors::{DeliveryError, Kind};
+use utils::say::{say, sayln, outln};
+use utils::{self, cwd};
+use command::Command;
+use config::Config;
+use tempdir::TempDir;
+use toml;
+use time;
+use logs;
+
+pub struct SupportBundleCommand<'n> {
+    pub options: &'n SupportBundleClapOptions<'n>,
+    pub config: &'n Config,
+    pub build_version: &'n str,
+}
+
+impl<'n> Command for SupportBundleCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let bundle = try!(TempDir::new("delivery-support"));
+        let bundle_dir = bundle.path();
+
+        // Logs and config are redacted as they get copied, the
+        // api-tokens file is never part of the bundle.
+        let logs_dir = try!(logs::logs_dir());
+        let bundle_logs = bundle_dir.join("logs");
+        try!(utils::mkdir_recursive(&bundle_logs));
+        for log in logs::log_files(&logs_dir) {
+            let mut content = String::new();
+            try!(try!(File::open(&log)).read_to_string(&mut content));
+            if let Some(name) = log.file_name() {
+                try!(write(&bundle_logs.join(name), &logs::redact(&content)));
+            }
+        }
+        let config = try!(toml::to_string(self.config));
+        try!(write(&bundle_dir.join("cli.toml"), &logs::redact(&config)));
+        try!(write(&bundle_dir.join("info.txt"), &self.info()));
+
+        let output = self.output_path();
+        let result = try!(utils::make_command("tar")
+            .arg("-czf").arg(&output)
+            .arg("-C").arg(bundle_dir)
+            .arg(".")
+            .output());
+        if !result.status.success() {
+            let msg = format!("Unable to create {}: {}", output.display(),
+                              String::from_utf8_lossy(&result.stderr));
+            return Err(DeliveryError::throw(Kind::FailedToExecute, Some(msg)))
+        }
+        say("white", "Support bundle written to ");
+        outln("yellow", &format!("{}", output.display()));
+        sayln("white", "Please review its content before attaching it to a support request.");
+        Ok(0)
+    }
+}
+
+impl<'n> SupportBundleCommand<'n> {
+    fn output_path(&self) -> PathBuf {
+        if self.options.output.is_empty() {
+            let stamp = time::strftime("%Y%m%d%H%M%S", &time::now()).unwrap_or_default();
+            cwd().join(format!("delivery-support-{}.tar.gz", stamp))
+        } else {
+            cwd().join(self.options.output)
+        }
+    }
+
+    fn info(&self) -> String {
+        format!("delivery {}\nos: {} {}\ncreated: {}\ncwd: {}\n",
+                self.build_version, consts::OS, consts::ARCH,
+                time::now().rfc3339(), cwd().display())
+    }
+}
+
+fn write(path: &PathBuf, content: &str) -> DeliveryResult<()> {
+    let mut file = try!(File::create(path));
+    try!(file.write_all(content.as_bytes()));
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::telemetry::TelemetryClapOptions;
+use types::{DeliveryResult, ExitCode};
+use telemetry;
+use utils::say::sayln;
+use utils::json_output;
+use command::Command;
+use config::Config;
+
+pub struct TelemetryCommand<'n> {
+    pub options: &'n TelemetryClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+impl<'n> Command for TelemetryCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        let dir = try!(telemetry::telemetry_dir());
+        match self.options.action {
+            "enable" => {
+                if telemetry::air_gapped(&self.config) {
+                    sayln("red", "air_gapped is set in your cli.toml, usage analytics \
+                                  can not be enabled");
+                    return Ok(1)
+                }
+                try!(telemetry::enable(&dir));
+                sayln("green", "Usage analytics enabled, thank you!");
+                sayln("white", "Recorded for each command: its name, duration and result \
+                                and the version of the cli.");
+                if self.config.telemetry_endpoint.is_none() {
+                    sayln("yellow", "No telemetry_endpoint is set in your cli.toml, events \
+                                     are only spooled until one is.");
+                }
+            },
+            "disable" => {
+                try!(telemetry::disable(&dir));
+                sayln("green", "Usage analytics disabled, the spooled events were removed.");
+            },
+            "flush" => {
+                let endpoint = try!(self.config.telemetry_endpoint());
+                let sent = try!(telemetry::flush(&dir, &self.config, &endpoint));
+                json_output::set("sent", &sent);
+                sayln("white", &format!("Sent {} events to {}", sent, endpoint));
+            },
+            _ => {
+                let enabled = telemetry::is_enabled(&dir);
+                let spooled = try!(telemetry::spooled(&dir)).len();
+                let air_gapped = telemetry::air_gapped(&self.config);
+                json_output::set("enabled", &enabled);
+                json_output::set("spooled", &spooled);
+                json_output::set("endpoint", &self.config.telemetry_endpoint);
+                json_output::set("air_gapped", &air_gapped);
+                let state = if air_gapped {
+                    "disabled by air_gapped"
+                } else if enabled {
+                    "enabled"
+                } else {
+                    "disabled"
+                };
+                sayln("white", &format!("Usage analytics: {}", state));
+                sayln("white", &format!("Spooled events: {}", spooled));
+                sayln("white", &format!("Endpoint: {}", self.config.telemetry_endpoint.clone()
+                                        .unwrap_or("none".to_string())));
+            }
+        }
+        Ok(0)
+    }
+}
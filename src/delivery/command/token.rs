@@ -15,10 +15,13 @@
 // limitations under the License.
 //
 
+use std::path::Path;
 use cli::token::TokenClapOptions;
 use types::{DeliveryResult, ExitCode};
-use utils::say::{turn_on_output, turn_off_output, sayln};
-use token::TokenStore;
+use errors::{DeliveryError, Kind};
+use utils::say::{turn_off_output, say, sayln, out, outln};
+use token::{self, TokenStore};
+use http::{self, APIClient};
 use config::Config;
 use command::Command;
 
@@ -38,17 +41,52 @@ impl<'n> Command for TokenCommand<'n> {
 
         sayln("green", "Chef Delivery");
 
+        if self.options.issue() {
+            return self.issue()
+        }
+
         let token: String = if self.options.verify {
             try!(TokenStore::verify_token(&self.config))
+        } else if self.options.renew {
+            try!(TokenStore::renew_token(&self.config))
         } else {
             try!(TokenStore::request_token(&self.config))
         };
 
         if self.options.raw {
-            turn_on_output();
-            sayln("white", &format!("{}", &token));
+            outln("white", &format!("{}", &token));
         }
 
         Ok(0)
     }
 }
+
+impl<'n> TokenCommand<'n> {
+    // Mint a token for each of the users given with `--user`
+    fn issue(&self) -> DeliveryResult<ExitCode> {
+        let users = self.options.issue_users();
+        if users.is_empty() {
+            let msg = "Name the users to issue tokens for with --user".to_string();
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        let ttl = try!(token::parse_ttl(self.options.ttl));
+        let client = try!(APIClient::from_config(&self.config));
+        let server = try!(self.config.api_host_and_port());
+        let ent = try!(self.config.enterprise());
+        for user in users {
+            let issued = try!(http::token::issue(&client, user, ttl));
+            if self.options.raw {
+                outln("white", &issued);
+            } else if self.options.out_dir.is_empty() {
+                out("white", &format!("{}: ", user));
+                outln("magenta", &issued);
+            } else {
+                let path = try!(TokenStore::write_issued_token(Path::new(self.options.out_dir),
+                                                               &server, &ent, user, &issued));
+                say("white", &format!("{}: ", user));
+                sayln("green", &format!("token written to {}", path.display()));
+            }
+        }
+        Ok(0)
+    }
+}
@@ -0,0 +1,90 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::update_build_deps::UpdateBuildDepsClapOptions;
+use delivery_config::build_deps::{self, LockfileStatus};
+use types::{DeliveryResult, ExitCode};
+use git::{self, CommitIdentity};
+use project;
+use utils::json_output;
+use utils::say::sayln;
+use command::Command;
+use config::Config;
+
+pub struct UpdateBuildDepsCommand<'n> {
+    pub options: &'n UpdateBuildDepsClapOptions<'n>,
+    pub config: &'n Config,
+}
+
+// Build cookbook dependency updates get a branch, and a review, of their own
+const BRANCH_NAME: &'static str = "update-build-deps";
+
+impl<'n> Command for UpdateBuildDepsCommand<'n> {
+    fn run(&self) -> DeliveryResult<ExitCode> {
+        sayln("green", "Chef Delivery");
+        let project_path = try!(project::project_path());
+        let status = try!(build_deps::lockfile_status(&project_path));
+        json_output::set("lockfile", &status.summary());
+
+        if self.options.check {
+            if status.is_problem() {
+                sayln("red", &format!("{}.", status));
+                sayln("white", "Run `delivery update-build-deps` to pin them in a review.");
+                return Ok(1)
+            }
+            sayln("green", &format!("{}.", status));
+            return Ok(0)
+        }
+        try!(super::refuse_in_managed_workspace(self.options.i_know_what_im_doing));
+
+        let cookbook = match try!(build_deps::local_build_cookbook(&project_path)) {
+            Some(ref cookbook) if status != LockfileStatus::NotApplicable => cookbook.clone(),
+            _ => {
+                sayln("white", &format!("  Skipping: {}.", status));
+                json_output::skipped("update-build-deps", &status.to_string());
+                return Ok(0)
+            }
+        };
+
+        sayln("cyan", &format!("Creating feature branch '{}'...", BRANCH_NAME));
+        if !try!(project::create_feature_branch_if_missing(&project_path, BRANCH_NAME)) {
+            sayln("white", &format!("  Skipping: A branch named '{}' already exists, \
+                                     switching to it.", BRANCH_NAME))
+        }
+
+        sayln("cyan", "Resolving the build cookbook dependencies with berks...");
+        try!(build_deps::update_lockfile(&cookbook));
+        let identity = CommitIdentity::from_config(&self.config);
+        if !try!(build_deps::commit_lockfile(&project_path, &cookbook, &identity)) {
+            sayln("white", "  Skipping: The Berksfile.lock did not change, nothing to review.");
+            json_output::skipped("review", "The Berksfile.lock did not change.");
+            return Ok(0)
+        }
+        sayln("green", &format!("  Berksfile.lock committed to feature branch '{}'.",
+                                BRANCH_NAME));
+
+        if self.options.local {
+            sayln("white", "  Skipping: You passed --local, skipping review submission.");
+            return Ok(0)
+        }
+        let pipeline = try!(self.config.pipeline());
+        sayln("cyan", &format!("Submitting feature branch '{}' for review...", BRANCH_NAME));
+        let review = try!(project::review(&pipeline, &try!(git::get_head())));
+        json_output::set("url", &review.url);
+        try!(project::handle_review_result(&review, &self.options.no_open, &self.config));
+        Ok(0)
+    }
+}
@@ -17,7 +17,7 @@
 
 pub use errors;
 use errors::{DeliveryError, Kind};
-use types::DeliveryResult;
+use types::{DeliveryResult, OrgName, ProjectName, PipelineName};
 use std::fs::File;
 use std::default::Default;
 use std::path::{Path, PathBuf};
@@ -27,10 +27,18 @@ use std::io::prelude::*;
 use utils::path_join_many::PathJoinMany;
 use utils::path_ext::{is_dir, is_file};
 use std::clone::Clone;
+use std::time::Duration;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use serde_json;
+use utils;
+use utils::poll::{Backoff, DEFAULT_POLL_INTERVAL, DEFAULT_MAX_POLL_INTERVAL};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Config {
     pub server: Option<String>,
+    pub server_url: Option<String>,
     pub api_port: Option<String>,
     pub api_protocol: Option<String>,
     pub user: Option<String>,
@@ -44,18 +52,91 @@ pub struct Config {
     pub non_interactive: Option<bool>,
     pub auto_bump: Option<bool>,
     pub config_json: Option<String>,
+    pub config_dir: Option<String>,
     pub saml: Option<bool>,
     pub fips: Option<bool>,
     pub fips_git_port: Option<String>,
     pub fips_custom_cert_filename: Option<String>,
+    pub server_pins: Option<Vec<String>>,
+    pub upstream_remote: Option<String>,
+    pub commit_user_name: Option<String>,
+    pub commit_email: Option<String>,
+    pub sudo_command: Option<String>,
+    pub supermarket: Option<String>,
+    pub poll_interval: Option<u64>,
+    pub max_poll_interval: Option<u64>,
+    pub signing_key_id: Option<String>,
+    pub signing_key_file: Option<String>,
+    pub strict: Option<bool>,
+    pub log_commands: Option<bool>,
+    pub proxy_pac_url: Option<String>,
+    pub browser: Option<String>,
+    pub pipeline_template: Option<String>,
+    pub telemetry_endpoint: Option<String>,
+    pub air_gapped: Option<bool>,
+    pub pipeline_groups: Option<BTreeMap<String, Vec<String>>>,
+    pub ticket_pattern: Option<String>,
+    pub ticket_link_url: Option<String>,
+    pub offline: Option<bool>,
+    pub proxy_host: Option<String>,
+    pub proxy_port: Option<String>,
+    pub proxy_user: Option<String>,
+    pub proxy_bypass: Option<Vec<String>>,
+    pub ca_cert: Option<String>,
+    pub client_cert: Option<String>,
+    pub client_key: Option<String>,
+    pub no_open: Option<bool>,
+    pub todo_checklist: Option<bool>,
+    pub todo_markers: Option<Vec<String>>,
+    pub delivery_windows: Option<BTreeMap<String, String>>,
 }
 
 pub mod url_format;
+pub mod server_url;
+
+/// The cli.toml that applies to every user of the machine
+#[cfg(not(windows))]
+pub const SYSTEM_CONFIG_PATH: &'static str = "/etc/delivery/cli.toml";
+#[cfg(windows)]
+pub const SYSTEM_CONFIG_PATH: &'static str = "C:\\ProgramData\\delivery\\cli.toml";
+
+/// Shorter names of the `DELIVERY_<KEY>` variables, the same ones the
+/// flags of the cli take
+pub const ENV_ALIASES: &'static [(&'static str, &'static str)] = &[
+    ("DELIVERY_ENT", "enterprise"),
+    ("DELIVERY_ORG", "organization"),
+];
+
+/// Where a layer of the configuration comes from. The layers apply in
+/// this order, each one overriding the values of the ones before it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigOrigin {
+    Default,
+    System(PathBuf),
+    User(PathBuf),
+    Project(PathBuf),
+    Env(String),
+    CommandLine,
+}
+
+impl fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigOrigin::Default => write!(f, "default"),
+            ConfigOrigin::System(ref p) => write!(f, "system {}", p.display()),
+            ConfigOrigin::User(ref p) => write!(f, "user {}", p.display()),
+            ConfigOrigin::Project(ref p) => write!(f, "project {}", p.display()),
+            ConfigOrigin::Env(ref var) => write!(f, "env {}", var),
+            ConfigOrigin::CommandLine => write!(f, "command line"),
+        }
+    }
+}
 
 impl Default for Config {
     fn default() -> Config {
         Config{
             server: None,
+            server_url: None,
             api_port: None,
             api_protocol: Some(String::from("https")),
             enterprise: None,
@@ -69,10 +150,43 @@ impl Default for Config {
             non_interactive: None,
             auto_bump: None,
             config_json: None,
+            config_dir: None,
             saml: None,
             fips: None,
             fips_git_port: None,
             fips_custom_cert_filename: None,
+            server_pins: None,
+            upstream_remote: None,
+            commit_user_name: None,
+            commit_email: None,
+            sudo_command: None,
+            supermarket: None,
+            poll_interval: None,
+            max_poll_interval: None,
+            signing_key_id: None,
+            signing_key_file: None,
+            strict: None,
+            log_commands: None,
+            proxy_pac_url: None,
+            browser: None,
+            pipeline_template: None,
+            telemetry_endpoint: None,
+            air_gapped: None,
+            pipeline_groups: None,
+            ticket_pattern: None,
+            ticket_link_url: None,
+            offline: None,
+            proxy_host: None,
+            proxy_port: None,
+            proxy_user: None,
+            proxy_bypass: None,
+            ca_cert: None,
+            client_cert: None,
+            client_key: None,
+            no_open: None,
+            todo_checklist: None,
+            todo_markers: None,
+            delivery_windows: None,
         }
     }
 }
@@ -100,7 +214,8 @@ macro_rules! config_accessor_for {
     )
 }
 
-config_accessor_for!(server, set_server, "Server not set; try --server or set it in your .toml config file");
+config_accessor_for!(server, set_server, "Server not set; try --server or --server-url or set it in your .toml config file");
+config_accessor_for!(server_url, set_server_url, "Server URL not set; try --server-url or set it in your .toml config file");
 config_accessor_for!(api_port, set_api_port, "API port not set; try --api-port or set it in your .toml config file");
 config_accessor_for!(api_protocol, set_api_protocol, "api_protocol not set; set it in your cli.toml");
 config_accessor_for!(user, set_user, "User not set; try --user or set it in your .toml config file");
@@ -112,20 +227,33 @@ config_accessor_for!(pipeline, set_pipeline, "Pipeline not set; try --for or set
 config_accessor_for!(token_file, set_token_file, "token_file not set; set it in your cli.toml");
 config_accessor_for!(generator, set_generator, "build_cookbook generator not set; set it in your cli.toml");
 config_accessor_for!(config_json, set_config_json, "config_json not set; set it in your cli.toml");
+config_accessor_for!(config_dir, set_config_dir, "config_dir not set; set it in your cli.toml");
+config_accessor_for!(upstream_remote, set_upstream_remote, "upstream_remote not set; try --upstream-remote or set it in your cli.toml");
+config_accessor_for!(commit_user_name, set_commit_user_name, "commit_user_name not set; set it in your cli.toml");
+config_accessor_for!(commit_email, set_commit_email, "commit_email not set; set it in your cli.toml");
+config_accessor_for!(sudo_command, set_sudo_command, "sudo_command not set; set it in your cli.toml");
+config_accessor_for!(supermarket, set_supermarket, "supermarket not set; set it in your cli.toml");
+config_accessor_for!(signing_key_id, set_signing_key_id, "signing_key_id not set; set it in your cli.toml");
+config_accessor_for!(proxy_pac_url, set_proxy_pac_url, "proxy_pac_url not set; set it in your cli.toml");
+config_accessor_for!(browser, set_browser, "browser not set; set it in your cli.toml");
+config_accessor_for!(pipeline_template, set_pipeline_template, "pipeline_template not set; set it in your cli.toml");
+config_accessor_for!(telemetry_endpoint, set_telemetry_endpoint, "telemetry_endpoint not set; set it in your cli.toml");
+config_accessor_for!(ticket_pattern, set_ticket_pattern, "ticket_pattern not set; set it in your cli.toml");
+config_accessor_for!(proxy_host, set_proxy_host, "proxy_host not set; set it in your cli.toml");
+config_accessor_for!(ca_cert, set_ca_cert, "ca_cert not set; set it in your cli.toml or pass --ca-cert");
+config_accessor_for!(client_cert, set_client_cert, "client_cert not set; set it in your cli.toml");
+config_accessor_for!(ticket_link_url, set_ticket_link_url, "ticket_link_url not set; set it in your cli.toml along with ticket_pattern");
+config_accessor_for!(signing_key_file, set_signing_key_file, "signing_key_file not set; set it in your cli.toml along with signing_key_id");
 config_accessor_for!(fips_git_port, set_fips_git_port, "You did not set the fips_git_port. Set this value in your cli.toml or pass --fips-git-port.\nIt should be set to any port that is free and open on localhost (i.e. `fips_git_port = \"36534\"` in your cli.toml).");
 
 impl Config {
     /// Return the host and port at which we can access the Delivery
     /// API. By default, we assume the use of HTTPS on the standard
-    /// port `443`. Unless a port is specified in the configuration,
-    /// we'll just return the server name; otherwise we append the
-    /// port.
+    /// port `443`. Unless a port is specified in the configuration
+    /// (either `api_port` or as part of `server_url`), we'll just
+    /// return the server name; otherwise we append the port.
     pub fn api_host_and_port(&self) -> DeliveryResult<String> {
-        let s = try!(self.server());
-        return Ok(match self.api_port {
-            Some(ref p) => format!("{}:{}", s, p),
-            None    => s
-        });
+        self.server_url_parts().map(|s| s.host_and_port())
     }
 
     /// Returns the SSH URL to talk to Delivery's Git
@@ -138,7 +266,7 @@ impl Config {
     }
 
     fn delivery_git_ssh_standard_url(&self) -> DeliveryResult<String> {
-        let s = try!(self.server());
+        let s = try!(self.server_host());
         let host_and_port = match self.git_port {
             Some(ref p) => format!("{}:{}", s, p),
             None    => s // TODO: Currently we *always* have a git port
@@ -159,7 +287,198 @@ impl Config {
         Ok(format!("ssh://{}@{}@{}/{}/{}/{}", u, e, host_and_port, e, o, p))
     }
 
+    /// The certificate pins configured for the server. An empty
+    /// list means pinning is disabled.
+    pub fn server_pins(&self) -> Vec<String> {
+        self.server_pins.clone().unwrap_or(Vec::new())
+    }
+
+    /// Add a pin to the list, ignoring it if it is already there
+    pub fn add_server_pin(mut self, pin: &str) -> Config {
+        let mut pins = self.server_pins();
+        if !pins.iter().any(|p| p == pin) {
+            pins.push(pin.to_string());
+        }
+        self.server_pins = Some(pins);
+        self
+    }
+
+    /// The organization, checked to be a valid name
+    pub fn org_name(&self) -> DeliveryResult<OrgName> {
+        OrgName::new(&try!(self.organization()))
+    }
+
+    /// The project, checked to be a valid name
+    pub fn project_name(&self) -> DeliveryResult<ProjectName> {
+        ProjectName::new(&try!(self.project()))
+    }
+
+    /// The pipeline, checked to be a valid git branch name
+    pub fn pipeline_name(&self) -> DeliveryResult<PipelineName> {
+        PipelineName::new(&try!(self.pipeline()))
+    }
+
+    /// The pipelines of the group `name`, from the `[pipeline_groups]`
+    /// table of the cli.toml
+    pub fn pipeline_group(&self, name: &str) -> DeliveryResult<Vec<String>> {
+        match self.pipeline_groups.as_ref().and_then(|groups| groups.get(name)) {
+            Some(pipelines) if !pipelines.is_empty() => Ok(pipelines.clone()),
+            _ => Err(DeliveryError{
+                kind: Kind::MissingConfig,
+                detail: Some(format!("Pipeline group {} not set; add it to the \
+                                      [pipeline_groups] of your cli.toml", name))
+            })
+        }
+    }
+
+    /// The spec of the delivery window `name` from the `[delivery_windows]`
+    /// table of the cli.toml, i.e. `mon-thu 22:00-05:00`
+    pub fn delivery_window(&self, name: &str) -> Option<String> {
+        self.delivery_windows.as_ref().and_then(|windows| windows.get(name)).cloned()
+    }
+
+    /// Fail instead of `doing` anything over the network in offline
+    /// mode, set by `offline = true` in the cli.toml or `init --local`.
+    pub fn require_network(&self, doing: &str) -> DeliveryResult<()> {
+        if self.offline.unwrap_or(false) {
+            let msg = format!("Not {} in offline mode", doing);
+            return Err(DeliveryError::throw(Kind::OfflineMode, Some(msg)))
+        }
+        Ok(())
+    }
+
+    /// The backoff of watch and status loops: `poll_interval` seconds
+    /// between polls at first, up to `max_poll_interval` seconds.
+    pub fn poll_backoff(&self) -> Backoff {
+        let initial = self.poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL).max(1);
+        let max = self.max_poll_interval.unwrap_or(DEFAULT_MAX_POLL_INTERVAL);
+        Backoff::new(Duration::from_secs(initial), Duration::from_secs(max))
+    }
+
+    /// The configuration that applies in `cwd`: the defaults, then the
+    /// system, user and project cli.toml, then the `DELIVERY_*`
+    /// environment variables. The flags given to a command override
+    /// all of them when its options are merged in.
     pub fn load_config(cwd: &PathBuf) -> DeliveryResult<Self> {
+        let mut config = Config::default();
+        for (_, layer) in try!(Config::layers(cwd)) {
+            config.override_with(layer);
+        }
+        Ok(config)
+    }
+
+    /// Every layer of the configuration that applies in `cwd`, lowest
+    /// precedence first.
+    pub fn layers(cwd: &PathBuf) -> DeliveryResult<Vec<(ConfigOrigin, Config)>> {
+        let user = utils::home_dir(&[".delivery", "cli.toml"]).ok();
+        Config::layers_from(Path::new(SYSTEM_CONFIG_PATH), user, cwd, env::vars())
+    }
+
+    fn layers_from<I>(system: &Path, user: Option<PathBuf>, cwd: &PathBuf,
+                      vars: I) -> DeliveryResult<Vec<(ConfigOrigin, Config)>>
+            where I: Iterator<Item=(String, String)> {
+        let mut layers = vec![(ConfigOrigin::Default, Config::default())];
+        if let Some(c) = try!(Config::load_layer(system)) {
+            layers.push((ConfigOrigin::System(system.to_path_buf()), c));
+        }
+        if let Some(ref user) = user {
+            if let Some(c) = try!(Config::load_layer(user)) {
+                layers.push((ConfigOrigin::User(user.clone()), c));
+            }
+        }
+        // Walking up from a directory of the home finds the user cli.toml
+        if let Some(project) = Config::dot_delivery_cli_path(cwd) {
+            if Some(&project) != user.as_ref() {
+                if let Some(c) = try!(Config::load_layer(&project)) {
+                    layers.push((ConfigOrigin::Project(project), c));
+                }
+            }
+        }
+        layers.extend(Config::env_layers(vars));
+        Ok(layers)
+    }
+
+    // The settings of a cli.toml, without the defaults. A cli.toml that
+    // does not parse is ignored, as it always was.
+    fn load_layer(path: &Path) -> DeliveryResult<Option<Config>> {
+        if !is_file(path) {
+            return Ok(None)
+        }
+        let toml = try!(read_file(&path.to_path_buf()));
+        match toml::from_str::<Config>(&toml) {
+            Ok(c) => Ok(Some(c)),
+            Err(e) => {
+                debug!("Ignoring {}: {}", path.display(), e);
+                Ok(None)
+            }
+        }
+    }
+
+    // A layer for each `DELIVERY_<KEY>` variable naming a key of the
+    // cli.toml, e.g. `DELIVERY_SERVER`, or one of the `ENV_ALIASES`. The
+    // value is taken as a string unless the key wants something else,
+    // like `DELIVERY_FIPS=true`. The full name wins over an alias.
+    fn env_layers<I>(vars: I) -> Vec<(ConfigOrigin, Config)>
+            where I: Iterator<Item=(String, String)> {
+        let keys = Config::keys();
+        let mut vars: Vec<(String, String)> = vars.filter(|&(ref var, _)| {
+            var.starts_with("DELIVERY_")
+        }).collect();
+        vars.sort_by_key(|&(ref var, _)| !ENV_ALIASES.iter().any(|&(alias, _)| alias == var));
+        let mut layers = Vec::new();
+        for (var, value) in vars {
+            let key = match ENV_ALIASES.iter().find(|&&(alias, _)| alias == var) {
+                Some(&(_, key)) => key.to_string(),
+                None => var["DELIVERY_".len()..].to_lowercase()
+            };
+            if !keys.contains(&key) {
+                continue
+            }
+            let mut table = serde_json::Map::new();
+            table.insert(key.clone(), serde_json::Value::String(value.clone()));
+            let layer = serde_json::from_value::<Config>(serde_json::Value::Object(table))
+                .ok()
+                .or_else(|| toml::from_str::<Config>(&format!("{} = {}", key, value)).ok());
+            match layer {
+                Some(c) => layers.push((ConfigOrigin::Env(var), c)),
+                None => debug!("Ignoring {}, {} is not a valid {}", var, value, key)
+            }
+        }
+        layers
+    }
+
+    /// A configuration with nothing set, not even the defaults
+    pub fn unset() -> Config {
+        toml::from_str::<Config>("").unwrap_or_default()
+    }
+
+    /// The keys a cli.toml may set
+    pub fn keys() -> Vec<String> {
+        match serde_json::to_value(&Config::default()) {
+            Ok(serde_json::Value::Object(map)) => map.keys().cloned().collect(),
+            _ => Vec::new()
+        }
+    }
+
+    /// The effective value of every key set by one of the `layers`,
+    /// rendered as JSON, with the layer it comes from.
+    pub fn origins(layers: &[(ConfigOrigin, Config)]) -> BTreeMap<String, (String, ConfigOrigin)> {
+        let mut origins = BTreeMap::new();
+        for &(ref origin, ref layer) in layers {
+            if let Ok(serde_json::Value::Object(map)) = serde_json::to_value(layer) {
+                for (key, value) in map {
+                    if !value.is_null() {
+                        origins.insert(key, (value.to_string(), origin.clone()));
+                    }
+                }
+            }
+        }
+        origins
+    }
+
+    /// Only the cli.toml of the project, the one `delivery setup` and
+    /// `delivery pin` write to.
+    pub fn load_project_config(cwd: &PathBuf) -> DeliveryResult<Self> {
         let have_config = Config::dot_delivery_cli_path(cwd);
         match have_config.as_ref() {
             Some(path) => {
@@ -205,6 +524,7 @@ impl Config {
         //
         // If the `config` has some new config, override `self`
         if config.server.is_some() { self.server = config.server }
+        if config.server_url.is_some() { self.server_url = config.server_url }
         if config.api_port.is_some() { self.api_port = config.api_port }
         if config.pipeline.is_some() { self.pipeline = config.pipeline }
         if config.project.is_some() { self.project = config.project }
@@ -217,11 +537,44 @@ impl Config {
         if config.non_interactive.is_some() { self.non_interactive = config.non_interactive }
         if config.auto_bump.is_some() { self.auto_bump = config.auto_bump }
         if config.config_json.is_some() { self.config_json = config.config_json }
+        if config.config_dir.is_some() { self.config_dir = config.config_dir }
         if config.saml.is_some() { self.saml = config.saml }
         if config.fips.is_some() { self.fips = config.fips }
         if config.fips_git_port.is_some() { self.fips_git_port = config.fips_git_port }
         if config.fips_custom_cert_filename.is_some() { self.fips_custom_cert_filename = config.fips_custom_cert_filename }
         if config.api_protocol.is_some() { self.api_protocol = config.api_protocol }
+        if config.server_pins.is_some() { self.server_pins = config.server_pins }
+        if config.upstream_remote.is_some() { self.upstream_remote = config.upstream_remote }
+        if config.commit_user_name.is_some() { self.commit_user_name = config.commit_user_name }
+        if config.commit_email.is_some() { self.commit_email = config.commit_email }
+        if config.sudo_command.is_some() { self.sudo_command = config.sudo_command }
+        if config.supermarket.is_some() { self.supermarket = config.supermarket }
+        if config.poll_interval.is_some() { self.poll_interval = config.poll_interval }
+        if config.max_poll_interval.is_some() { self.max_poll_interval = config.max_poll_interval }
+        if config.signing_key_id.is_some() { self.signing_key_id = config.signing_key_id }
+        if config.signing_key_file.is_some() { self.signing_key_file = config.signing_key_file }
+        if config.strict.is_some() { self.strict = config.strict }
+        if config.log_commands.is_some() { self.log_commands = config.log_commands }
+        if config.proxy_pac_url.is_some() { self.proxy_pac_url = config.proxy_pac_url }
+        if config.browser.is_some() { self.browser = config.browser }
+        if config.pipeline_template.is_some() { self.pipeline_template = config.pipeline_template }
+        if config.telemetry_endpoint.is_some() { self.telemetry_endpoint = config.telemetry_endpoint }
+        if config.air_gapped.is_some() { self.air_gapped = config.air_gapped }
+        if config.pipeline_groups.is_some() { self.pipeline_groups = config.pipeline_groups }
+        if config.ticket_pattern.is_some() { self.ticket_pattern = config.ticket_pattern }
+        if config.ticket_link_url.is_some() { self.ticket_link_url = config.ticket_link_url }
+        if config.offline.is_some() { self.offline = config.offline }
+        if config.proxy_host.is_some() { self.proxy_host = config.proxy_host }
+        if config.proxy_port.is_some() { self.proxy_port = config.proxy_port }
+        if config.proxy_user.is_some() { self.proxy_user = config.proxy_user }
+        if config.proxy_bypass.is_some() { self.proxy_bypass = config.proxy_bypass }
+        if config.ca_cert.is_some() { self.ca_cert = config.ca_cert }
+        if config.client_cert.is_some() { self.client_cert = config.client_cert }
+        if config.client_key.is_some() { self.client_key = config.client_key }
+        if config.no_open.is_some() { self.no_open = config.no_open }
+        if config.todo_checklist.is_some() { self.todo_checklist = config.todo_checklist }
+        if config.todo_markers.is_some() { self.todo_markers = config.todo_markers }
+        if config.delivery_windows.is_some() { self.delivery_windows = config.delivery_windows }
     }
 
     fn check_dot_delivery_cli(path: PathBuf) -> Option<PathBuf> {
@@ -266,6 +619,7 @@ mod tests {
         match config_result {
             Ok(config) => {
                 assert_eq!(Some(String::from("127.0.0.1")), config.server);
+                assert_eq!(None, config.server_url);
                 assert_eq!(None, config.api_port);
                 assert_eq!(Some("https".to_string()), config.api_protocol);
                 assert_eq!(Some("8989".to_string()), config.git_port);
@@ -276,9 +630,19 @@ mod tests {
                 assert_eq!(None, config.non_interactive);
                 assert_eq!(None, config.auto_bump);
                 assert_eq!(None, config.config_json);
+                assert_eq!(None, config.config_dir);
                 assert_eq!(None, config.saml);
                 assert_eq!(None, config.fips);
                 assert_eq!(None, config.fips_git_port);
+                assert_eq!(None, config.server_pins);
+                assert_eq!(None, config.upstream_remote);
+                assert_eq!(None, config.commit_user_name);
+                assert_eq!(None, config.commit_email);
+                assert_eq!(None, config.sudo_command);
+                assert_eq!(None, config.poll_interval);
+                assert_eq!(None, config.max_poll_interval);
+                assert_eq!(None, config.signing_key_id);
+                assert_eq!(None, config.signing_key_file);
             },
             Err(e) => {
                 panic!("Failed to parse: {:?}", e.detail)
@@ -299,9 +663,46 @@ mod tests {
             non_interactive = true
             auto_bump = true
             config_json = "/path/to/my/custom/config.json"
+            config_dir = "/path/to/my/delivery"
             saml = true
             fips = true
             fips_git_port = "55555"
+            server_pins = ["sha256/abc=", "sha256/def="]
+            upstream_remote = "github"
+            commit_user_name = "Delivery Bot"
+            commit_email = "delivery-bot@example.com"
+            sudo_command = "doas"
+            supermarket = "https://supermarket.example.com"
+            poll_interval = 10
+            max_poll_interval = 300
+            signing_key_id = "automation-1"
+            signing_key_file = "/etc/delivery/signing.key"
+            strict = true
+            log_commands = true
+            proxy_pac_url = "http://wpad.example.com/wpad.dat"
+            browser = "firefox --new-tab"
+            pipeline_template = "https://github.com/chef/pipeline-template.git"
+            telemetry_endpoint = "https://telemetry.example.com/delivery-cli"
+            air_gapped = true
+            ticket_pattern = "[A-Z]+-[0-9]+"
+            ticket_link_url = "https://tickets.example.com/link"
+            offline = true
+            proxy_host = "proxy.example.com"
+            proxy_port = "3128"
+            proxy_user = "builder"
+            proxy_bypass = ["localhost", ".internal.example.com"]
+            ca_cert = "/etc/delivery/internal-ca.pem"
+            client_cert = "/etc/delivery/client.pem"
+            client_key = "/etc/delivery/client.key"
+            no_open = false
+            todo_checklist = false
+            todo_markers = ["TODO", "HACK"]
+
+            [pipeline_groups]
+            release = ["release/1.x", "release/2.x"]
+
+            [delivery_windows]
+            weeknights = "mon-thu 21:00-05:00"
 "#;
         let config_result = Config::parse_config(toml);
         match config_result {
@@ -316,9 +717,56 @@ mod tests {
                 assert_eq!(Some(true), config.auto_bump);
                 assert_eq!(Some("/path/to/my/custom/config.json".to_string()),
                           config.config_json);
+                assert_eq!(Some("/path/to/my/delivery".to_string()), config.config_dir);
                 assert_eq!(Some(true), config.saml);
                 assert_eq!(Some(true), config.fips);
                 assert_eq!(Some("55555".to_string()), config.fips_git_port);
+                assert_eq!(vec!["sha256/abc=".to_string(), "sha256/def=".to_string()],
+                           config.server_pins());
+                assert_eq!(Some("github".to_string()), config.upstream_remote);
+                assert_eq!(Some("Delivery Bot".to_string()), config.commit_user_name);
+                assert_eq!(Some("delivery-bot@example.com".to_string()), config.commit_email);
+                assert_eq!(Some("doas".to_string()), config.sudo_command);
+                assert_eq!(Some("https://supermarket.example.com".to_string()),
+                           config.supermarket);
+                assert_eq!(Some(10), config.poll_interval);
+                assert_eq!(Some(300), config.max_poll_interval);
+                assert_eq!(Some("automation-1".to_string()), config.signing_key_id);
+                assert_eq!(Some("/etc/delivery/signing.key".to_string()),
+                           config.signing_key_file);
+                assert_eq!(Some(true), config.strict);
+                assert_eq!(Some(true), config.log_commands);
+                assert_eq!(Some("http://wpad.example.com/wpad.dat".to_string()),
+                           config.proxy_pac_url);
+                assert_eq!(Some("firefox --new-tab".to_string()), config.browser);
+                assert_eq!(Some("https://github.com/chef/pipeline-template.git".to_string()),
+                           config.pipeline_template);
+                assert_eq!(Some("https://telemetry.example.com/delivery-cli".to_string()),
+                           config.telemetry_endpoint);
+                assert_eq!(Some(true), config.air_gapped);
+                assert_eq!(Some("[A-Z]+-[0-9]+".to_string()), config.ticket_pattern);
+                assert_eq!(Some("https://tickets.example.com/link".to_string()),
+                           config.ticket_link_url);
+                assert!(config.require_network("testing").is_err());
+                assert_eq!(Some("proxy.example.com".to_string()), config.proxy_host);
+                assert_eq!(Some("3128".to_string()), config.proxy_port);
+                assert_eq!(Some("builder".to_string()), config.proxy_user);
+                assert_eq!(Some(vec!["localhost".to_string(),
+                                     ".internal.example.com".to_string()]),
+                           config.proxy_bypass);
+                assert_eq!(Some("/etc/delivery/internal-ca.pem".to_string()), config.ca_cert);
+                assert_eq!(Some("/etc/delivery/client.pem".to_string()), config.client_cert);
+                assert_eq!(Some("/etc/delivery/client.key".to_string()), config.client_key);
+                assert_eq!(Some(false), config.no_open);
+                assert_eq!(Some(false), config.todo_checklist);
+                assert_eq!(Some(vec!["TODO".to_string(), "HACK".to_string()]),
+                           config.todo_markers);
+                assert_eq!(vec!["release/1.x".to_string(), "release/2.x".to_string()],
+                           config.pipeline_group("release").unwrap());
+                assert!(config.pipeline_group("hotfix").is_err());
+                assert_eq!(Some("mon-thu 21:00-05:00".to_string()),
+                           config.delivery_window("weeknights"));
+                assert_eq!(None, config.delivery_window("weekends"));
             },
             Err(e) => {
                 panic!("Failed to parse: {:?}", e.detail)
@@ -351,6 +799,16 @@ mod tests {
         assert!(conf.api_host_and_port().is_err());
     }
 
+    #[test]
+    fn test_names() {
+        let conf = Config::default().set_organization("org")
+                                    .set_project("my project");
+        assert_eq!("org", &*conf.org_name().unwrap());
+        assert!(conf.project_name().is_err());
+        assert_eq!("master", &*conf.pipeline_name().unwrap());
+        assert!(conf.set_pipeline("feature..x").pipeline_name().is_err());
+    }
+
     #[test]
     fn test_git_url_with_default_port() {
         let mut conf      = Config::default();
@@ -376,10 +834,88 @@ mod tests {
                    conf.delivery_git_ssh_url().unwrap());
     }
 
+    #[test]
+    fn test_api_url_with_server_url() {
+        let mut conf    = Config::default();
+        conf.server     = Some("127.0.0.1".to_string());
+        conf.server_url = Some("https://automate.example.com:8443/delivery".to_string());
+        assert_eq!("automate.example.com:8443".to_string(),
+                   conf.api_host_and_port().unwrap());
+    }
+
+    #[test]
+    fn test_git_url_with_server_url() {
+        let mut conf      = Config::default();
+        conf.server_url   = Some("https://automate.example.com:8443/delivery".to_string());
+        conf.user         = Some("user".to_string());
+        conf.enterprise   = Some("ent".to_string());
+        conf.organization = Some("org".to_string());
+        conf.project      = Some("proj".to_string());
+        assert_eq!("ssh://user@ent@automate.example.com:8989/ent/org/proj".to_string(),
+                   conf.delivery_git_ssh_url().unwrap());
+    }
+
     #[test]
     fn test_git_url_without_server() {
         let conf = Config::default();
         assert!(conf.server.is_none());
         assert!(conf.delivery_git_ssh_url().is_err());
     }
+
+    #[test]
+    fn test_layers() {
+        use super::ConfigOrigin;
+        use std::fs::{self, File};
+        use std::io::prelude::*;
+        use tempdir::TempDir;
+
+        let tmp = TempDir::new("config-layers").unwrap();
+        let write = |dir: &str, toml: &str| {
+            let path = tmp.path().join(dir).join(".delivery").join("cli.toml");
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            File::create(&path).unwrap().write_all(toml.as_bytes()).unwrap();
+            path
+        };
+        let system = write("etc", "server = \"system\"\nuser = \"system\"\nfips = false\n");
+        let user = write("home", "user = \"me\"\nenterprise = \"ent\"\n");
+        let project = write("home/proj", "enterprise = \"proj-ent\"\n");
+        let cwd = tmp.path().join("home").join("proj").join("src");
+        let vars = vec![("DELIVERY_FIPS".to_string(), "true".to_string()),
+                        ("DELIVERY_ORGANIZATION".to_string(), "org".to_string()),
+                        ("DELIVERY_ORG".to_string(), "alias-org".to_string()),
+                        ("DELIVERY_ENT".to_string(), "alias-ent".to_string()),
+                        ("DELIVERY_CHANGE_ID".to_string(), "not-a-key".to_string())];
+
+        let layers = Config::layers_from(&system, Some(user.clone()), &cwd,
+                                         vars.into_iter()).unwrap();
+        let origins: Vec<ConfigOrigin> = layers.iter().map(|&(ref o, _)| o.clone()).collect();
+        assert_eq!(vec![ConfigOrigin::Default,
+                        ConfigOrigin::System(system.clone()),
+                        ConfigOrigin::User(user.clone()),
+                        ConfigOrigin::Project(project),
+                        ConfigOrigin::Env("DELIVERY_ORG".to_string()),
+                        ConfigOrigin::Env("DELIVERY_ENT".to_string()),
+                        ConfigOrigin::Env("DELIVERY_FIPS".to_string()),
+                        ConfigOrigin::Env("DELIVERY_ORGANIZATION".to_string())], origins);
+
+        let values = Config::origins(&layers);
+        assert_eq!(("\"system\"".to_string(), ConfigOrigin::System(system)),
+                   values["server"]);
+        assert_eq!(("\"me\"".to_string(), ConfigOrigin::User(user.clone())), values["user"]);
+        assert_eq!(("\"alias-ent\"".to_string(), ConfigOrigin::Env("DELIVERY_ENT".to_string())),
+                   values["enterprise"]);
+        assert_eq!(("\"org\"".to_string(),
+                    ConfigOrigin::Env("DELIVERY_ORGANIZATION".to_string())),
+                   values["organization"]);
+        assert_eq!(("true".to_string(), ConfigOrigin::Env("DELIVERY_FIPS".to_string())),
+                   values["fips"]);
+        assert_eq!(("\"8989\"".to_string(), ConfigOrigin::Default), values["git_port"]);
+
+        // From the home, the user cli.toml is not a project one too
+        let layers = Config::layers_from(&tmp.path().join("none"), Some(user.clone()),
+                                         &tmp.path().join("home"),
+                                         Vec::new().into_iter()).unwrap();
+        assert_eq!(vec![ConfigOrigin::Default, ConfigOrigin::User(user)],
+                   layers.into_iter().map(|(o, _)| o).collect::<Vec<_>>());
+    }
 }
@@ -0,0 +1,154 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+// This module parses the `server_url` config value, a full base URL
+// like `https://automate.example.com:8443/delivery`, into the pieces
+// the rest of the CLI needs to talk to the server.
+
+use regex::Regex;
+use config::Config;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerUrl {
+    pub protocol: String,
+    pub host: String,
+    pub port: Option<String>,
+    pub path_prefix: String,
+}
+
+impl ServerUrl {
+    /// Parse a base URL of the form `scheme://host[:port][/path/prefix]`.
+    /// The scheme must be `http` or `https`; a trailing slash on the
+    /// path prefix is dropped so it can be joined with API routes.
+    pub fn parse(url: &str) -> DeliveryResult<ServerUrl> {
+        let r = Regex::new(r"^(?i)(https?)://([^/:\s]+)(?::(\d+))?(/\S*)?$").unwrap();
+        let caps = match r.captures(url.trim()) {
+            Some(caps) => caps,
+            None => {
+                let msg = format!("'{}' is not a valid server URL. Expected something \
+                                  like https://automate.example.com:8443/delivery", url);
+                return Err(DeliveryError::throw(Kind::InvalidServerUrl, Some(msg)))
+            }
+        };
+        let path_prefix = caps.at(4).unwrap_or("").trim_right_matches('/');
+        Ok(ServerUrl {
+            protocol: caps.at(1).unwrap().to_lowercase(),
+            host: caps.at(2).unwrap().to_string(),
+            port: caps.at(3).map(|p| p.to_string()),
+            path_prefix: path_prefix.to_string(),
+        })
+    }
+
+    /// The `host[:port]` portion of the URL.
+    pub fn host_and_port(&self) -> String {
+        match self.port {
+            Some(ref p) => format!("{}:{}", self.host, p),
+            None => self.host.clone()
+        }
+    }
+
+    /// The URL every API and web route hangs off of, without a
+    /// trailing slash. (i.e. `https://automate.example.com:8443/delivery`)
+    pub fn base_url(&self) -> String {
+        format!("{}://{}{}", self.protocol, self.host_and_port(), self.path_prefix)
+    }
+}
+
+impl Config {
+    /// Returns the parsed location of the Automate server. When
+    /// `server_url` is set it takes precedence over `server`,
+    /// `api_port` and `api_protocol`; otherwise those are combined
+    /// into an equivalent `ServerUrl` without a path prefix.
+    pub fn server_url_parts(&self) -> DeliveryResult<ServerUrl> {
+        match self.server_url {
+            Some(ref url) => ServerUrl::parse(url),
+            None => {
+                Ok(ServerUrl {
+                    protocol: try!(self.api_protocol()),
+                    host: try!(self.server()),
+                    port: self.api_port.clone(),
+                    path_prefix: String::new(),
+                })
+            }
+        }
+    }
+
+    /// The hostname of the Automate server, without port or path.
+    /// Use this rather than `server()` anywhere we need the bare host,
+    /// like when building the git ssh url.
+    pub fn server_host(&self) -> DeliveryResult<String> {
+        self.server_url_parts().map(|s| s.host)
+    }
+
+    /// The base URL of the Automate server.
+    pub fn server_base_url(&self) -> DeliveryResult<String> {
+        self.server_url_parts().map(|s| s.base_url())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_url() {
+        let url = ServerUrl::parse("https://automate.example.com:8443/delivery/").unwrap();
+        assert_eq!("https", url.protocol);
+        assert_eq!("automate.example.com", url.host);
+        assert_eq!(Some("8443".to_string()), url.port);
+        assert_eq!("/delivery", url.path_prefix);
+        assert_eq!("automate.example.com:8443", url.host_and_port());
+        assert_eq!("https://automate.example.com:8443/delivery", url.base_url());
+    }
+
+    #[test]
+    fn test_parse_url_without_port_or_prefix() {
+        let url = ServerUrl::parse("HTTP://automate.example.com").unwrap();
+        assert_eq!("http", url.protocol);
+        assert_eq!(None, url.port);
+        assert_eq!("", url.path_prefix);
+        assert_eq!("http://automate.example.com", url.base_url());
+    }
+
+    #[test]
+    fn test_parse_invalid_url() {
+        assert!(ServerUrl::parse("automate.example.com").is_err());
+        assert!(ServerUrl::parse("ftp://automate.example.com").is_err());
+        assert!(ServerUrl::parse("https://automate.example.com:port").is_err());
+    }
+
+    #[test]
+    fn test_server_url_takes_precedence() {
+        let conf = Config::default()
+            .set_server("old.example.com")
+            .set_api_port("443")
+            .set_server_url("http://automate.example.com:8080/delivery");
+        assert_eq!("automate.example.com", conf.server_host().unwrap());
+        assert_eq!("http://automate.example.com:8080/delivery",
+                   conf.server_base_url().unwrap());
+    }
+
+    #[test]
+    fn test_server_url_parts_from_server_and_port() {
+        let conf = Config::default()
+            .set_server("automate.example.com")
+            .set_api_port("8443");
+        assert_eq!("https://automate.example.com:8443", conf.server_base_url().unwrap());
+    }
+}
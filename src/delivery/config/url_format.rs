@@ -29,31 +29,49 @@ impl Config {
     //
     // The url we use to manage users. (ssh-pub-key, permissions, etc.)
     pub fn users_url(&self) -> DeliveryResult<String> {
-        let p = self.api_protocol()?;
-        let s = self.server()?;
+        let b = self.server_base_url()?;
         let e = self.enterprise()?;
-        Ok(format!("{}://{}/e/{}/#/users", p, s, e))
+        Ok(format!("{}/e/{}/#/users", b, e))
     }
 
     // Organizations url
     //
     // List of organizations within an enterprise.
     pub fn organizations_url(&self) -> DeliveryResult<String> {
-        let p = self.api_protocol()?;
-        let s = self.server()?;
+        let b = self.server_base_url()?;
         let e = self.enterprise()?;
-        Ok(format!("{}://{}/e/{}/#/organizations", p, s, e))
+        Ok(format!("{}/e/{}/#/organizations", b, e))
     }
 
     // Projects url
     //
     // List of projects within an organization.
     pub fn projects_url(&self) -> DeliveryResult<String> {
-        let p = self.api_protocol()?;
-        let s = self.server()?;
+        let b = self.server_base_url()?;
         let e = self.enterprise()?;
         let o = self.organization()?;
-        Ok(format!("{}://{}/e/{}/#/organizations/{}", p, s, e, o))
+        Ok(format!("{}/e/{}/#/organizations/{}", b, e, o))
+    }
+
+    // Project url
+    //
+    // The page of the project, with its pipelines and changes.
+    pub fn project_url(&self) -> DeliveryResult<String> {
+        let p = self.project()?;
+        Ok(format!("{}/projects/{}", self.projects_url()?, p))
+    }
+
+    // Pipeline badge url
+    //
+    // An image showing the status of the last change on the pipeline.
+    pub fn pipeline_badge_url(&self) -> DeliveryResult<String> {
+        let b = self.server_base_url()?;
+        let e = self.enterprise()?;
+        let o = self.organization()?;
+        let p = self.project()?;
+        let pipe = self.pipeline()?;
+        Ok(format!("{}/api/v0/e/{}/orgs/{}/projects/{}/pipelines/{}/badge",
+                   b, e, o, p, pipe))
     }
 }
 
@@ -88,4 +106,27 @@ mod tests {
         assert_eq!("https://server/e/test/#/organizations/org".to_string(),
                    conf.projects_url().unwrap());
     }
+
+    #[test]
+    fn test_projects_url_with_server_url() {
+        let mut conf      = Config::default();
+        conf.server_url   = Some("https://server:8443/delivery".to_string());
+        conf.enterprise   = Some("test".to_string());
+        conf.organization = Some("org".to_string());
+        assert_eq!("https://server:8443/delivery/e/test/#/organizations/org".to_string(),
+                   conf.projects_url().unwrap());
+    }
+
+    #[test]
+    fn test_pipeline_badge_url() {
+        let mut conf      = Config::default();
+        conf.server       = Some("server".to_string());
+        conf.enterprise   = Some("test".to_string());
+        conf.organization = Some("org".to_string());
+        conf.project      = Some("proj".to_string());
+        assert_eq!("https://server/e/test/#/organizations/org/projects/proj".to_string(),
+                   conf.project_url().unwrap());
+        assert_eq!("https://server/api/v0/e/test/orgs/org/projects/proj/pipelines/master/badge"
+                   .to_string(), conf.pipeline_badge_url().unwrap());
+    }
 }
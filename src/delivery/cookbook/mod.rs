@@ -22,7 +22,7 @@ use std::io::prelude::*;
 use utils::{self, read_file};
 use utils::say::{say, sayln};
 use utils::path_ext::is_file;
-use git;
+use git::{self, CommitIdentity};
 use regex::Regex;
 use regex::Captures;
 
@@ -58,8 +58,8 @@ impl MetadataVersion {
 // @param p_root [&PathBuf] The project root path
 // @param pipeline [&str] Pipeline the change is targeting to
 // @return () if success
-pub fn bump_version(p_root: &PathBuf, pipeline: &str,
-                    project: &str) -> Result<(), DeliveryError> {
+pub fn bump_version(p_root: &PathBuf, pipeline: &str, project: &str,
+                    identity: &CommitIdentity) -> Result<(), DeliveryError> {
     if is_cookbook(&p_root) {
         say("white", "Project ");
         say("yellow", &project);
@@ -84,7 +84,7 @@ pub fn bump_version(p_root: &PathBuf, pipeline: &str,
             let new_version = new_meta_version.to_string();
             say("white", "Bumping version to: ");
             sayln("green", &new_version);
-            try!(save_version(&meta_f_p, new_version));
+            try!(save_version(&meta_f_p, new_version, identity));
         } else {
             say("white", "Version already updated (");
             say("magenta", &pipeline_v);
@@ -96,6 +96,26 @@ pub fn bump_version(p_root: &PathBuf, pipeline: &str,
     Ok(())
 }
 
+/// The version of the cookbook at `p_root` when it is the same as the
+/// one on `pipeline`; `None` when it was bumped or the project is not a
+/// cookbook.
+pub fn unbumped_version(p_root: &PathBuf,
+                        pipeline: &str) -> Result<Option<String>, DeliveryError> {
+    if !is_cookbook(&p_root) {
+        return Ok(None)
+    }
+    let meta_f_c = try!(read_file(&PathBuf::from(metadata_file(&p_root))));
+    let current_v = try!(metadata_version_from(&meta_f_c)).to_string();
+    let t_file = format!("{}:metadata.rb", pipeline);
+    let pipeline_meta = try!(git::git_command(&["show", &t_file], &p_root));
+    let pipeline_v = try!(metadata_version_from(&pipeline_meta.stdout)).to_string();
+    if current_v == pipeline_v {
+        Ok(Some(current_v))
+    } else {
+        Ok(None)
+    }
+}
+
 // @Private
 
 // Return the path to the metadata.rb file
@@ -157,7 +177,8 @@ fn bump_patchset(mut version: MetadataVersion) -> Result<MetadataVersion, Delive
 }
 
 // Saves the new version to the metadata and commit the changes
-fn save_version(metadata: &PathBuf, version: String) -> Result<(), DeliveryError> {
+fn save_version(metadata: &PathBuf, version: String,
+                identity: &CommitIdentity) -> Result<(), DeliveryError> {
     let current_meta = try!(read_file(metadata));
     let current_meta_version = try!(metadata_version_from(&current_meta));
     let current_version = current_meta_version.to_string();
@@ -171,7 +192,7 @@ fn save_version(metadata: &PathBuf, version: String) -> Result<(), DeliveryError
     let mut commit_msg = String::from("Bump version to ");
     commit_msg.push_str(&version);
     try!(git::git_command(&["add", metadata.to_str().unwrap()], &utils::cwd()));
-    try!(git::git_command(&["commit", "-m", &commit_msg], &utils::cwd()));
+    try!(git::git_commit_in(&commit_msg, identity, &utils::cwd()));
     Ok(())
 }
 
@@ -0,0 +1,213 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Pinning the dependencies of the build cookbook
+//!
+//! A local build cookbook with a `Berksfile` resolves its dependencies
+//! again on every run unless a committed `Berksfile.lock` pins them, so
+//! two verify runs of the same change can use different cookbooks. The
+//! lockfile is checked before reviews, and refreshed on purpose, in a
+//! review of its own, with `delivery update-build-deps`.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use delivery_config::{DeliveryConfig, BuildCookbookLocation};
+use errors::{DeliveryError, Kind};
+use git::{self, CommitIdentity};
+use types::DeliveryResult;
+use utils::{self, cmd_success_or_err};
+
+pub const BERKSFILE: &'static str = "Berksfile";
+pub const LOCKFILE: &'static str = "Berksfile.lock";
+
+/// Where the lockfile of the build cookbook stands
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LockfileStatus {
+    /// The build cookbook is not in the project or has no Berksfile
+    NotApplicable,
+    Missing,
+    Untracked,
+    Modified,
+    Committed,
+}
+
+/// The lockfile status as `--json` reports it
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LockfileSummary {
+    pub status: &'static str,
+    pub problem: bool,
+    pub message: String,
+}
+
+impl LockfileStatus {
+    /// Whether the dependencies of the build cookbook are not pinned
+    /// by what is committed
+    pub fn is_problem(&self) -> bool {
+        match *self {
+            LockfileStatus::Missing
+                | LockfileStatus::Untracked
+                | LockfileStatus::Modified => true,
+            _ => false
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            LockfileStatus::NotApplicable => "not_applicable",
+            LockfileStatus::Missing => "missing",
+            LockfileStatus::Untracked => "untracked",
+            LockfileStatus::Modified => "modified",
+            LockfileStatus::Committed => "committed",
+        }
+    }
+
+    pub fn summary(&self) -> LockfileSummary {
+        LockfileSummary {
+            status: self.name(),
+            problem: self.is_problem(),
+            message: self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for LockfileStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            LockfileStatus::NotApplicable => "The build cookbook has no dependencies to pin",
+            LockfileStatus::Missing => "The build cookbook has no Berksfile.lock, its \
+                                        dependencies are resolved again on every run",
+            LockfileStatus::Untracked => "The Berksfile.lock of the build cookbook is \
+                                          not committed",
+            LockfileStatus::Modified => "The Berksfile.lock of the build cookbook has \
+                                         uncommitted changes",
+            LockfileStatus::Committed => "The dependencies of the build cookbook are pinned",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+/// The directory of the build cookbook when the project carries it
+pub fn local_build_cookbook(proj_path: &Path) -> DeliveryResult<Option<PathBuf>> {
+    let config = try!(DeliveryConfig::load_config(proj_path));
+    match try!(config.build_cookbook_location()) {
+        BuildCookbookLocation::Local => {
+            Ok(Some(proj_path.join(try!(config.build_cookbook_get("path")))))
+        },
+        _ => Ok(None)
+    }
+}
+
+pub fn lockfile_status(proj_path: &Path) -> DeliveryResult<LockfileStatus> {
+    let cookbook = match try!(local_build_cookbook(proj_path)) {
+        Some(cookbook) => cookbook,
+        None => return Ok(LockfileStatus::NotApplicable)
+    };
+    if !cookbook.join(BERKSFILE).exists() {
+        return Ok(LockfileStatus::NotApplicable)
+    }
+    let lockfile = cookbook.join(LOCKFILE);
+    if !lockfile.exists() {
+        return Ok(LockfileStatus::Missing)
+    }
+    let lockfile_str = lockfile.to_string_lossy();
+    if git::git_command(&["ls-files", "--error-unmatch", &lockfile_str], &proj_path).is_err() {
+        return Ok(LockfileStatus::Untracked)
+    }
+    let changes = try!(git::git_command(&["status", "--porcelain", "--", &lockfile_str],
+                                        &proj_path));
+    if changes.stdout.trim().is_empty() {
+        Ok(LockfileStatus::Committed)
+    } else {
+        Ok(LockfileStatus::Modified)
+    }
+}
+
+/// Resolve the dependencies of the build cookbook again, to their latest
+/// versions allowed by the Berksfile
+pub fn update_lockfile(cookbook: &Path) -> DeliveryResult<()> {
+    let subcommand = if cookbook.join(LOCKFILE).exists() { "update" } else { "install" };
+    let mut command = utils::make_command("berks");
+    command.arg(subcommand).current_dir(cookbook);
+    let output = try!(command.output());
+    cmd_success_or_err(&output, Kind::BerksFailed)
+}
+
+/// Commit the lockfile, and nothing else that may be staged, returns
+/// false when it did not change
+pub fn commit_lockfile(proj_path: &Path, cookbook: &Path,
+                       identity: &CommitIdentity) -> DeliveryResult<bool> {
+    let lockfile = cookbook.join(LOCKFILE);
+    let lockfile_str = lockfile.to_string_lossy();
+    try!(git::git_command(&["add", &lockfile_str], &proj_path));
+    let staged = try!(git::git_command(&["diff", "--cached", "--name-only", "--", &lockfile_str],
+                                       &proj_path));
+    if staged.stdout.trim().is_empty() {
+        return Ok(false)
+    }
+    match git::git_commit_paths_in("Update build cookbook dependencies", identity,
+                                   &[&lockfile_str], proj_path) {
+        Ok(_) => Ok(true),
+        Err(DeliveryError{ kind: Kind::EmptyGitCommit, .. }) => Ok(false),
+        Err(e) => Err(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use tempdir::TempDir;
+    use utils::test_paths::fixture_file;
+
+    #[test]
+    fn test_lockfile_status() {
+        let tempdir = TempDir::new("build-deps").unwrap();
+        let path = tempdir.path();
+        let cookbook = path.join("cookbooks/delivery_test");
+        fs::create_dir_all(&cookbook).unwrap();
+        fs::create_dir_all(path.join(".delivery")).unwrap();
+        fs::copy(fixture_file("path_config.json"), path.join(".delivery/config.json")).unwrap();
+        let write = |file: &Path, content: &str| {
+            File::create(file).unwrap().write_all(content.as_bytes()).unwrap();
+        };
+        git::git_command(&["init"], &path).unwrap();
+
+        assert_eq!(LockfileStatus::NotApplicable, lockfile_status(path).unwrap());
+        write(&cookbook.join(BERKSFILE), "metadata\n");
+        assert_eq!(LockfileStatus::Missing, lockfile_status(path).unwrap());
+        write(&cookbook.join(LOCKFILE), "DEPENDENCIES\n  delivery-truck\n");
+        assert_eq!(LockfileStatus::Untracked, lockfile_status(path).unwrap());
+
+        let identity = CommitIdentity { name: Some("test".to_string()),
+                                        email: Some("test@example.com".to_string()) };
+        // Something else staged stays out of the lockfile commit
+        write(&path.join("README.md"), "staged\n");
+        git::git_command(&["add", "README.md"], &path).unwrap();
+        assert!(commit_lockfile(path, &cookbook, &identity).unwrap());
+        assert_eq!(LockfileStatus::Committed, lockfile_status(path).unwrap());
+        let staged = git::git_command(&["diff", "--cached", "--name-only"], &path).unwrap();
+        assert_eq!("README.md", staged.stdout.trim());
+        assert!(!commit_lockfile(path, &cookbook, &identity).unwrap());
+
+        write(&cookbook.join(LOCKFILE), "DEPENDENCIES\n  delivery-sugar\n");
+        assert_eq!(LockfileStatus::Modified, lockfile_status(path).unwrap());
+        assert!(LockfileStatus::Modified.is_problem());
+        assert!(!LockfileStatus::NotApplicable.is_problem());
+        assert_eq!("modified", LockfileStatus::Modified.summary().status);
+    }
+}
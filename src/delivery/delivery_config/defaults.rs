@@ -0,0 +1,131 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+/// Default local phases
+///
+/// When the .delivery/project.toml does not configure a phase we try to
+/// detect what kind of project we are in and fall back to the commands
+/// that project type usually runs, so `delivery local lint` works on
+/// common repositories with zero configuration.
+
+use std::fmt::{Display, Formatter, Error};
+use std::path::Path;
+use delivery_config::project::{LocalPhases, Phase};
+use utils::path_ext::is_file;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProjectType {
+    Cookbook,
+    Rust,
+    Node,
+    Python,
+}
+
+impl Display for ProjectType {
+    fn fmt(&self, f:&mut Formatter) -> Result<(), Error> {
+        match *self {
+            ProjectType::Cookbook => write!(f, "cookbook"),
+            ProjectType::Rust => write!(f, "Rust"),
+            ProjectType::Node => write!(f, "Node"),
+            ProjectType::Python => write!(f, "Python"),
+        }
+    }
+}
+
+impl ProjectType {
+    /// Detect the project type by looking for the files each
+    /// ecosystem keeps at the root of the repository.
+    pub fn detect(project_path: &Path) -> Option<ProjectType> {
+        let has = |f: &str| is_file(&project_path.join(f));
+        if has("metadata.rb") || has("metadata.json") {
+            Some(ProjectType::Cookbook)
+        } else if has("Cargo.toml") {
+            Some(ProjectType::Rust)
+        } else if has("package.json") {
+            Some(ProjectType::Node)
+        } else if has("setup.py") || has("pyproject.toml") || has("requirements.txt") {
+            Some(ProjectType::Python)
+        } else {
+            None
+        }
+    }
+
+    /// The default command for a phase, if this project type has one
+    pub fn default_phase(&self, phase: &Phase) -> Option<&'static str> {
+        match (self, phase) {
+            (&ProjectType::Cookbook, &Phase::Lint) => Some("cookstyle"),
+            (&ProjectType::Cookbook, &Phase::Syntax) => Some("foodcritic ."),
+            (&ProjectType::Cookbook, &Phase::Unit) => Some("chef exec rspec"),
+            (&ProjectType::Rust, &Phase::Lint) => Some("cargo clippy"),
+            (&ProjectType::Rust, &Phase::Syntax) => Some("cargo check"),
+            (&ProjectType::Rust, &Phase::Unit) => Some("cargo test"),
+            (&ProjectType::Node, &Phase::Lint) => Some("npx eslint ."),
+            (&ProjectType::Node, &Phase::Unit) => Some("npx jest"),
+            (&ProjectType::Python, &Phase::Lint) => Some("flake8"),
+            (&ProjectType::Python, &Phase::Unit) => Some("pytest"),
+            _ => None
+        }
+    }
+
+    /// Fill every phase that is not configured with its default
+    pub fn fill_defaults(&self, phases: &mut LocalPhases) {
+        fill(&mut phases.lint, self.default_phase(&Phase::Lint));
+        fill(&mut phases.syntax, self.default_phase(&Phase::Syntax));
+        fill(&mut phases.unit, self.default_phase(&Phase::Unit));
+    }
+}
+
+fn fill(phase: &mut Option<String>, default: Option<&'static str>) {
+    if phase.is_none() {
+        *phase = default.map(|d| d.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use tempdir::TempDir;
+    use delivery_config::project::ProjectToml;
+
+    #[test]
+    fn test_detect_project_type() {
+        let tmp = TempDir::new("project-type").unwrap();
+        assert_eq!(None, ProjectType::detect(tmp.path()));
+        File::create(tmp.path().join("package.json")).unwrap();
+        assert_eq!(Some(ProjectType::Node), ProjectType::detect(tmp.path()));
+        File::create(tmp.path().join("metadata.rb")).unwrap();
+        assert_eq!(Some(ProjectType::Cookbook), ProjectType::detect(tmp.path()));
+    }
+
+    #[test]
+    fn test_fill_defaults_keeps_configured_phases() {
+        let mut phases = ProjectToml::default().local_phases.unwrap();
+        phases.unit = Some("make test".to_string());
+        ProjectType::Rust.fill_defaults(&mut phases);
+        assert_eq!(Some("make test".to_string()), phases.unit);
+        assert_eq!(Some("cargo clippy".to_string()), phases.lint);
+        assert_eq!(Some("cargo check".to_string()), phases.syntax);
+        assert_eq!(None, phases.provision);
+    }
+
+    #[test]
+    fn test_default_phase_without_command() {
+        assert_eq!(None, ProjectType::Python.default_phase(&Phase::Syntax));
+        assert_eq!(None, ProjectType::Cookbook.default_phase(&Phase::Deploy));
+    }
+}
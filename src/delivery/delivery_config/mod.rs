@@ -27,9 +27,15 @@ use utils::{walk_tree_for_path, read_file, copy_recursive, file_needs_updated};
 use utils::path_join_many::PathJoinMany;
 use serde_json;
 use serde_json::Value as SerdeJson;
-use git;
+use git::{self, CommitIdentity};
+use utils::pool::DEFAULT_CONCURRENCY;
+use self::sync::{FileSync, SyncSummary};
 
 pub mod project;
+pub mod defaults;
+pub mod pipelines;
+pub mod sync;
+pub mod build_deps;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct DeliveryConfig {
@@ -203,15 +209,38 @@ impl DeliveryConfig {
         Ok(Some(read_file(&write_path)?))
     }
 
-    pub fn git_add_commit_config<P>(proj_path: P) -> DeliveryResult<bool>
+    /// Sync the files of a provided config directory into `.delivery/`
+    /// of the project root path, copying only the new or modified ones.
+    /// If `config.json` is among them, verify that it is still valid.
+    pub fn sync_config_dir<P>(config_dir: P, proj_path: P) -> DeliveryResult<SyncSummary>
+            where P: AsRef<Path> + Debug {
+        let dot_delivery = proj_path.as_ref().join(".delivery");
+        let summary = try!(sync::sync_dir(config_dir.as_ref(), &dot_delivery,
+                                          DEFAULT_CONCURRENCY));
+        let config_changed = summary.files.iter().any(|&(ref f, s)| {
+            f == Path::new("config.json") && s != FileSync::Unchanged
+        });
+        if config_changed {
+            try!(DeliveryConfig::validate_config_file(&proj_path));
+        }
+        Ok(summary)
+    }
+
+    /// Add and commit `config.json` along with any other file of
+    /// `.delivery/` that was synced from a config directory.
+    pub fn git_add_commit_config<P>(proj_path: P, synced: &[PathBuf],
+                                    identity: &CommitIdentity) -> DeliveryResult<bool>
             where P: AsRef<Path> {
         let config_path = DeliveryConfig::config_file_path(&proj_path);
-        let config_path_str = &config_path.to_str().unwrap();
-        try!(git::git_command(&["add", &config_path_str], &proj_path));
+        let mut paths = vec![config_path];
+        paths.extend(synced.iter().map(|f| proj_path.as_ref().join(".delivery").join(f)));
+        for path in paths.iter().filter(|p| p.exists()) {
+            try!(git::git_command(&["add", &path.to_str().unwrap()], &proj_path));
+        }
 
         // Commit the changes made in .delivery but detect if nothing has changed,
         // if that is the case, we are Ok() to continue
-        match git::git_commit("Adds custom Delivery config") {
+        match git::git_commit("Adds custom Delivery config", identity) {
           Ok(_) => Ok(true),
           Err(DeliveryError{ kind: Kind::EmptyGitCommit, .. }) => Ok(false),
           Err(e) => Err(e)
@@ -0,0 +1,276 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+/// Pipelines as code
+///
+/// The `.delivery/pipelines.toml` file declares the pipelines a project
+/// should have on the server, the branch each one merges into, the
+/// projects it depends on and the project notification settings:
+///
+/// ```toml
+/// [pipelines.master]
+/// dependencies = ["api"]
+///
+/// [pipelines.release]
+/// base = "release-1.x"
+///
+/// [notifications]
+/// slack_url = "https://hooks.slack.com/services/T000/B000/XXXX"
+/// ```
+///
+/// `delivery reconcile` compares it to what the server has and plans
+/// the `Action`s that converge the server to the file.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Error};
+use std::path::{Path, PathBuf};
+use toml;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+use utils;
+use utils::path_join_many::PathJoinMany;
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct PipelinesToml {
+    #[serde(default)]
+    pub pipelines: BTreeMap<String, PipelineSpec>,
+    pub notifications: Option<Notifications>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct PipelineSpec {
+    /// The branch the pipeline merges into, defaults to its name
+    pub base: Option<String>,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Notifications {
+    pub slack_url: String,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// What the server currently has for a pipeline. The `base` is `None`
+/// when the server does not report it.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct PipelineState {
+    pub base: Option<String>,
+    pub dependencies: Vec<String>,
+}
+
+/// What the server currently has for a project
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct ServerState {
+    pub pipelines: BTreeMap<String, PipelineState>,
+    pub notifications: Option<Notifications>,
+}
+
+/// A change to make on the server
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    CreatePipeline { name: String, base: String },
+    UpdateBase { name: String, from: String, to: String },
+    SetDependencies { name: String, from: Vec<String>, to: Vec<String> },
+    SetNotifications { from: Option<Notifications>, to: Notifications },
+    DeletePipeline { name: String },
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            Action::CreatePipeline { ref name, ref base } =>
+                write!(f, "+ create pipeline {} (base: {})", name, base),
+            Action::UpdateBase { ref name, ref from, ref to } =>
+                write!(f, "~ pipeline {}: base {} -> {}", name, from, to),
+            Action::SetDependencies { ref name, ref from, ref to } =>
+                write!(f, "~ pipeline {}: dependencies [{}] -> [{}]",
+                       name, from.join(", "), to.join(", ")),
+            Action::SetNotifications { ref from, ref to } => {
+                let state = |n: &Notifications| format!("{} ({})", n.slack_url,
+                    if n.enabled { "enabled" } else { "disabled" });
+                write!(f, "~ notifications: {} -> {}",
+                       from.as_ref().map(|n| state(n)).unwrap_or("none".to_string()),
+                       state(to))
+            },
+            Action::DeletePipeline { ref name } =>
+                write!(f, "- delete pipeline {}", name),
+        }
+    }
+}
+
+impl PipelinesToml {
+    /// The path of the pipelines.toml of the project at `proj_path`
+    pub fn file_path(proj_path: &Path) -> PathBuf {
+        proj_path.join_many(&[".delivery", "pipelines.toml"])
+    }
+
+    pub fn load_file(path: &Path) -> DeliveryResult<PipelinesToml> {
+        if !path.exists() {
+            return Err(DeliveryError{
+                kind: Kind::MissingConfigFile,
+                detail: Some(format!("{} was not found", path.display()))
+            })
+        }
+        let content = try!(utils::read_file(&path.to_path_buf()));
+        PipelinesToml::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> DeliveryResult<PipelinesToml> {
+        let pipelines = try!(toml::from_str::<PipelinesToml>(content));
+        if pipelines.pipelines.is_empty() {
+            return Err(DeliveryError{
+                kind: Kind::MissingConfig,
+                detail: Some("The pipelines.toml does not declare any pipeline; a \
+                             project needs at least one.".to_string())
+            })
+        }
+        Ok(pipelines)
+    }
+
+    /// The actions that make the server match this file. Pipelines the
+    /// file does not declare are only deleted when `prune` is set.
+    pub fn plan(&self, current: &ServerState, prune: bool) -> Vec<Action> {
+        let mut creates = Vec::new();
+        let mut updates = Vec::new();
+        for (name, spec) in self.pipelines.iter() {
+            let base = spec.base.clone().unwrap_or(name.clone());
+            let mut dependencies = spec.dependencies.clone();
+            dependencies.sort();
+            match current.pipelines.get(name) {
+                None => {
+                    creates.push(Action::CreatePipeline { name: name.clone(), base: base });
+                    if !dependencies.is_empty() {
+                        updates.push(Action::SetDependencies {
+                            name: name.clone(), from: Vec::new(), to: dependencies
+                        });
+                    }
+                },
+                Some(state) => {
+                    if let Some(ref current_base) = state.base {
+                        if current_base != &base {
+                            updates.push(Action::UpdateBase {
+                                name: name.clone(), from: current_base.clone(), to: base
+                            });
+                        }
+                    }
+                    let mut current_deps = state.dependencies.clone();
+                    current_deps.sort();
+                    if current_deps != dependencies {
+                        updates.push(Action::SetDependencies {
+                            name: name.clone(), from: current_deps, to: dependencies
+                        });
+                    }
+                }
+            }
+        }
+        if let Some(ref notifications) = self.notifications {
+            if current.notifications.as_ref() != Some(notifications) {
+                updates.push(Action::SetNotifications {
+                    from: current.notifications.clone(), to: notifications.clone()
+                });
+            }
+        }
+        let mut actions = creates;
+        actions.extend(updates);
+        if prune {
+            for name in current.pipelines.keys() {
+                if !self.pipelines.contains_key(name) {
+                    actions.push(Action::DeletePipeline { name: name.clone() });
+                }
+            }
+        }
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toml() -> PipelinesToml {
+        PipelinesToml::parse(r#"
+[pipelines.master]
+dependencies = ["web", "api"]
+
+[pipelines.release]
+base = "release-1.x"
+
+[notifications]
+slack_url = "https://hooks.slack.com/services/T/B/X"
+"#).unwrap()
+    }
+
+    fn state(base: Option<&str>, deps: &[&str]) -> PipelineState {
+        PipelineState {
+            base: base.map(|b| b.to_string()),
+            dependencies: deps.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let pipelines = toml();
+        assert_eq!(2, pipelines.pipelines.len());
+        assert_eq!(None, pipelines.pipelines["master"].base);
+        assert_eq!(true, pipelines.notifications.unwrap().enabled);
+        assert!(PipelinesToml::parse("").is_err());
+    }
+
+    #[test]
+    fn test_plan_from_scratch() {
+        let actions = toml().plan(&ServerState::default(), false);
+        assert_eq!(vec![
+            Action::CreatePipeline { name: "master".to_string(), base: "master".to_string() },
+            Action::CreatePipeline { name: "release".to_string(),
+                                     base: "release-1.x".to_string() },
+            Action::SetDependencies { name: "master".to_string(), from: vec![],
+                                      to: vec!["api".to_string(), "web".to_string()] },
+            Action::SetNotifications {
+                from: None,
+                to: Notifications { slack_url: "https://hooks.slack.com/services/T/B/X"
+                                                   .to_string(),
+                                    enabled: true }
+            },
+        ], actions);
+    }
+
+    #[test]
+    fn test_plan_drift_and_prune() {
+        let mut current = ServerState::default();
+        current.pipelines.insert("master".to_string(), state(None, &["api", "web"]));
+        current.pipelines.insert("release".to_string(), state(Some("release"), &[]));
+        current.pipelines.insert("legacy".to_string(), state(Some("legacy"), &[]));
+        current.notifications = toml().notifications;
+
+        let actions = toml().plan(&current, false);
+        assert_eq!(vec![Action::UpdateBase { name: "release".to_string(),
+                                             from: "release".to_string(),
+                                             to: "release-1.x".to_string() }],
+                   actions);
+
+        let pruned = toml().plan(&current, true);
+        assert_eq!(Some(&Action::DeletePipeline { name: "legacy".to_string() }),
+                   pruned.last());
+        assert_eq!("- delete pipeline legacy", format!("{}", pruned[1]));
+    }
+}
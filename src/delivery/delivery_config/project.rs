@@ -20,25 +20,61 @@
 /// This module is responsible for handling the .delivery/project.toml file
 /// that is currently a prototype for local phases execution. This file can
 /// be configurable and it doesn't conflict with the existing config.json
+///
+/// Projects without a project.toml may instead list their phases in the
+/// `local_phases` object of the config.json, same keys as the toml table.
 
 use errors::{DeliveryError, Kind};
+use delivery_config::DeliveryConfig;
+use delivery_config::defaults::ProjectType;
+use git;
 use hyper::Client as HyperClient;
+use serde_json;
 use project;
+use std::collections::BTreeMap;
 use std::default::Default;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fmt::{Display, Formatter, Error};
 use toml;
 use types::DeliveryResult;
 use utils;
+use utils::limits::Limits;
+use utils::say::sayln;
 use utils::path_join_many::PathJoinMany;
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct ProjectToml {
     pub remote_file: Option<String>,
-    pub local_phases: Option<LocalPhases>
+    pub local_phases: Option<LocalPhases>,
+    // Phases that need elevated privileges, e.g. functional tests
+    // binding low ports. Nothing runs elevated unless listed here.
+    pub privileged_phases: Option<Vec<String>>,
+    pub phase_limits: Option<BTreeMap<String, PhaseLimits>>,
+    // The docker image each value of a `delivery local --matrix`
+    // dimension runs in, e.g. `[matrix_images.os]` with
+    // `ubuntu = "ubuntu:16.04"`. The images need `delivery` installed.
+    pub matrix_images: Option<BTreeMap<String, BTreeMap<String, String>>>,
+}
+
+/// What a phase may use, e.g.
+///
+/// ```toml
+/// [phase_limits.functional]
+/// timeout = "45m"
+/// memory = "4G"
+/// cpu = "30m"
+/// ```
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct PhaseLimits {
+    pub timeout: Option<String>,
+    pub memory: Option<String>,
+    pub cpu: Option<String>,
 }
 
+// The phases `delivery job` runs on top of the ones we run locally
+const JOB_ONLY_PHASES: [&'static str; 3] = ["quality", "security", "publish"];
+
 #[derive(Deserialize, Clone, Debug)]
 pub struct LocalPhases {
     pub unit: Option<String>,
@@ -51,6 +87,15 @@ pub struct LocalPhases {
     pub cleanup: Option<String>,
 }
 
+/// A phase whose command differs between the project.toml of the
+/// repository and the one the server runs the pipeline with
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhaseDrift {
+    pub phase: Phase,
+    pub local: Option<String>,
+    pub remote: Option<String>,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Phase {
     Unit,
@@ -63,6 +108,36 @@ pub enum Phase {
     Cleanup,
 }
 
+/// The values of a dimension to run a local phase for, once per value,
+/// the way build node filters fan a phase out on the server
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix {
+    pub dimension: String,
+    pub values: Vec<String>,
+}
+
+impl Matrix {
+    /// Parse a `dimension=value,value` matrix, e.g. `os=ubuntu,centos`
+    pub fn parse(matrix: &str) -> DeliveryResult<Matrix> {
+        let invalid = || DeliveryError::throw(
+            Kind::OptionConstraint,
+            Some(format!("Invalid matrix '{}', expected dimension=value,value \
+                          e.g. os=ubuntu,centos", matrix))
+        );
+        let mut parts = matrix.splitn(2, '=');
+        let dimension = parts.next().unwrap_or("").trim();
+        let values: Vec<String> = match parts.next() {
+            Some(v) => v.split(',').map(|v| v.trim().to_string())
+                        .filter(|v| !v.is_empty()).collect(),
+            None => return Err(invalid())
+        };
+        if dimension.is_empty() || values.is_empty() {
+            return Err(invalid())
+        }
+        Ok(Matrix { dimension: dimension.to_string(), values: values })
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Stage {
     Verify,
@@ -139,7 +214,10 @@ impl Default for ProjectToml {
                 smoke: None,
                 functional: None,
                 cleanup: None
-            })
+            }),
+            privileged_phases: None,
+            phase_limits: None,
+            matrix_images: None,
         }
     }
 }
@@ -151,12 +229,88 @@ impl ProjectToml {
             return ProjectToml::load_toml_remote(url)
         }
 
-        let path = ProjectToml::toml_file_path(project::project_path()?);
-        let project_toml = ProjectToml::load_toml_file(path)?;
+        let project_path = project::project_path()?;
+        let path = ProjectToml::toml_file_path(project_path.clone());
+        let project_type = ProjectType::detect(&project_path);
+
+        // Without a project.toml we can still run the phases of the
+        // config.json, or of a project type we know about.
+        let project_toml = if path.exists() {
+            ProjectToml::load_toml_file(path)?
+        } else if let Some(phases) = ProjectToml::config_json_phases(&project_path)? {
+            debug!("Using the local phases of the config.json");
+            ProjectToml { local_phases: Some(phases), ..ProjectToml::default() }
+        } else if project_type.is_some() {
+            ProjectToml::default()
+        } else {
+            ProjectToml::load_toml_file(path)?
+        };
+
+        match project_toml.remote_file {
+            Some(url) => ProjectToml::load_toml_remote(&url),
+            None => Ok(project_toml.with_defaults_for(project_type))
+        }
+    }
+
+    /// Fill the phases the project.toml does not configure with the
+    /// defaults of the detected project type.
+    pub fn with_defaults_for(mut self, project_type: Option<ProjectType>) -> ProjectToml {
+        if let Some(t) = project_type {
+            debug!("Using default local phases for a {} project", t);
+            let mut phases = self.local_phases.unwrap_or(
+                ProjectToml::default().local_phases.unwrap()
+            );
+            t.fill_defaults(&mut phases);
+            self.local_phases = Some(phases);
+        }
+        self
+    }
 
+    /// Load the project.toml committed on the `pipeline` branch of the
+    /// delivery remote, the canonical one the server runs phases with,
+    /// regardless of what was modified locally.
+    pub fn load_toml_from_pipeline(pipeline: &str) -> DeliveryResult<ProjectToml> {
+        let project_path = project::project_path()?;
+        git::git_command(&["fetch", "delivery", pipeline], &project_path)?;
+        let toml = match git::git_command(&["show", "FETCH_HEAD:.delivery/project.toml"],
+                                          &project_path) {
+            Ok(result) => result.stdout,
+            Err(_) => return Err(DeliveryError{
+                kind: Kind::MissingConfigFile,
+                detail: Some(format!("The {} pipeline on the server has no \
+                                     .delivery/project.toml", pipeline))
+            })
+        };
+        let project_toml = ProjectToml::parse_config(&toml)?
+            .without_privileged_phases(&format!("the {} pipeline", pipeline));
         match project_toml.remote_file {
             Some(url) => ProjectToml::load_toml_remote(&url),
-            None => Ok(project_toml)
+            None => Ok(project_toml.with_defaults_for(ProjectType::detect(&project_path)))
+        }
+    }
+
+    /// The phases configured differently in `self` and `remote`
+    pub fn drift(&self, remote: &ProjectToml) -> Vec<PhaseDrift> {
+        Stage::All.phases().into_iter().filter_map(|phase| {
+            let local = self.local_phase(Some(phase.clone())).unwrap_or(None);
+            let remote = remote.local_phase(Some(phase.clone())).unwrap_or(None);
+            if local == remote {
+                None
+            } else {
+                Some(PhaseDrift { phase: phase, local: local, remote: remote })
+            }
+        }).collect()
+    }
+
+    // The `local_phases` of the config.json of the project, if any
+    fn config_json_phases(project_path: &PathBuf) -> DeliveryResult<Option<LocalPhases>> {
+        if !DeliveryConfig::config_file_path(project_path).exists() {
+            return Ok(None)
+        }
+        let config = DeliveryConfig::load_raw_config(project_path)?;
+        match config.get("local_phases") {
+            Some(phases) => Ok(Some(serde_json::from_value::<LocalPhases>(phases.clone())?)),
+            None => Ok(None)
         }
     }
 
@@ -176,6 +330,7 @@ impl ProjectToml {
                 resp.read_to_string(&mut toml)?;
                 debug!("Content Remote project.toml: {:?}", toml);
                 ProjectToml::parse_config(&toml)
+                    .map(|p| p.without_privileged_phases(toml_url))
             },
             Err(e) => {
                 Err(DeliveryError{
@@ -208,13 +363,93 @@ impl ProjectToml {
        }
     }
 
+    /// Whether the project.toml opted `phase` in to run with elevated
+    /// privileges through `privileged_phases`
+    pub fn is_privileged(&self, phase: &Phase) -> bool {
+        match self.privileged_phases {
+            Some(ref phases) => phases.iter().any(|p| *p == phase.to_string()),
+            None => false
+        }
+    }
+
+    /// The same project.toml without its `privileged_phases`. Only the
+    /// project.toml committed in the local repository may run phases as
+    /// root, not one loaded from `source`, which anyone could point us to.
+    pub fn without_privileged_phases(mut self, source: &str) -> ProjectToml {
+        if let Some(phases) = self.privileged_phases.take() {
+            sayln("yellow", &format!("Ignoring privileged_phases ({}) of the project.toml \
+                                      of {}; only the local project.toml can run phases \
+                                      with elevated privileges", phases.join(", "), source));
+        }
+        self
+    }
+
+    /// The limits the project.toml declares for the phase named `phase`
+    pub fn limits_for(&self, phase: &str) -> DeliveryResult<Limits> {
+        match self.phase_limits.as_ref().and_then(|limits| limits.get(phase)) {
+            Some(l) => Limits::parse(l.timeout.as_ref().map(|t| t.as_ref()),
+                                     l.memory.as_ref().map(|m| m.as_ref()),
+                                     l.cpu.as_ref().map(|c| c.as_ref())),
+            None => Ok(Limits::default())
+        }
+    }
+
+    /// The docker image to run `value` of the matrix `dimension` in
+    pub fn matrix_image(&self, dimension: &str, value: &str) -> Option<String> {
+        self.matrix_images.as_ref()
+            .and_then(|images| images.get(dimension))
+            .and_then(|images| images.get(value))
+            .cloned()
+    }
+
+    /// The project.toml of the repository checked out at `repo`, if it
+    /// has one, as a job runs it.
+    pub fn load_from_repo(repo: &Path) -> DeliveryResult<Option<ProjectToml>> {
+        let path = ProjectToml::toml_file_path(repo.to_path_buf());
+        if !path.exists() {
+            return Ok(None)
+        }
+        let project_toml = ProjectToml::load_toml_file(path)?;
+        match project_toml.remote_file {
+            Some(url) => ProjectToml::load_toml_remote(&url).map(Some),
+            None => Ok(Some(project_toml))
+        }
+    }
+
     fn toml_file_path(proj_path: PathBuf) -> PathBuf {
         proj_path.join_many(&[".delivery", "project.toml"])
     }
 
     fn parse_config(toml: &str) -> DeliveryResult<ProjectToml> {
         debug!("Parsing toml: {}", toml);
-        Ok(toml::from_str::<ProjectToml>(toml)?)
+        let project_toml = toml::from_str::<ProjectToml>(toml)?;
+        if let Some(ref privileged) = project_toml.privileged_phases {
+            let known: Vec<String> = Stage::All.phases().iter().map(|p| p.to_string()).collect();
+            if let Some(unknown) = privileged.iter().find(|p| !known.contains(p)) {
+                return Err(DeliveryError{
+                    kind: Kind::PhaseNotFound,
+                    detail: Some(format!("privileged_phases lists '{}', which is not a \
+                                         phase. Valid phases are: {}",
+                                         unknown, known.join(", ")))
+                })
+            }
+        }
+        if let Some(ref limits) = project_toml.phase_limits {
+            let mut known: Vec<String> = Stage::All.phases().iter()
+                .map(|p| p.to_string()).collect();
+            known.extend(JOB_ONLY_PHASES.iter().map(|p| p.to_string()));
+            for phase in limits.keys() {
+                if !known.contains(phase) {
+                    return Err(DeliveryError{
+                        kind: Kind::PhaseNotFound,
+                        detail: Some(format!("phase_limits has limits for '{}', which is not                                              a phase. Valid phases are: {}",
+                                             phase, known.join(", ")))
+                    })
+                }
+                project_toml.limits_for(phase)?;
+            }
+        }
+        Ok(project_toml)
     }
 
     fn validate_file(toml_path: &PathBuf) -> DeliveryResult<()> {
@@ -226,7 +461,9 @@ impl ProjectToml {
                 detail: Some(
                     format!("The .delivery/project.toml file was not found.\n\n\
                             You can generate this file using the command:\n\
-                            \tchef generate build-cookbook [NAME]")
+                            \tchef generate build-cookbook [NAME]\n\n\
+                            or list the phases in a \"local_phases\" object of \
+                            your .delivery/config.json")
                 )
             })
         }
@@ -236,7 +473,7 @@ impl ProjectToml {
 
 #[cfg(test)]
 mod tests {
-    pub use super::{ProjectToml, Phase, Stage};
+    pub use super::{ProjectToml, Phase, PhaseDrift, Stage, Matrix};
 
     #[test]
     fn test_project_toml_with_defaults_plus_overrides() {
@@ -270,6 +507,119 @@ mod tests {
                    p_toml.local_phases.unwrap().unit);
     }
 
+    #[test]
+    fn test_with_defaults_for_project_type() {
+        use delivery_config::defaults::ProjectType;
+        let toml = r#"
+[local_phases]
+lint = "my-linter"
+"#;
+        let p_toml = ProjectToml::parse_config(toml).unwrap()
+            .with_defaults_for(Some(ProjectType::Python));
+        assert_eq!(Some("my-linter".to_string()),
+                   p_toml.local_phase(Some(Phase::Lint)).unwrap());
+        assert_eq!(Some("pytest".to_string()),
+                   p_toml.local_phase(Some(Phase::Unit)).unwrap());
+        assert_eq!(None, p_toml.local_phase(Some(Phase::Syntax)).unwrap());
+    }
+
+    #[test]
+    fn test_drift_between_local_and_remote() {
+        let local = ProjectToml::parse_config(r#"
+[local_phases]
+unit = "rspec --fail-fast"
+lint = "cookstyle"
+"#).unwrap();
+        let remote = ProjectToml::parse_config(r#"
+[local_phases]
+unit = "rspec"
+lint = "cookstyle"
+syntax = "foodcritic ."
+"#).unwrap();
+        assert_eq!(vec![
+            PhaseDrift { phase: Phase::Syntax, local: None,
+                         remote: Some("foodcritic .".to_string()) },
+            PhaseDrift { phase: Phase::Unit, local: Some("rspec --fail-fast".to_string()),
+                         remote: Some("rspec".to_string()) },
+        ], local.drift(&remote));
+        assert!(remote.drift(&remote).is_empty());
+    }
+
+    #[test]
+    fn test_privileged_phases() {
+        let p_toml = ProjectToml::parse_config(r#"
+privileged_phases = ["functional"]
+
+[local_phases]
+functional = "rspec spec/functional"
+"#).unwrap();
+        assert!(p_toml.is_privileged(&Phase::Functional));
+        assert!(!p_toml.is_privileged(&Phase::Unit));
+        assert!(!ProjectToml::default().is_privileged(&Phase::Functional));
+        assert!(ProjectToml::parse_config("privileged_phases = [\"functionnal\"]").is_err());
+        let remote = p_toml.without_privileged_phases("https://example.com/project.toml");
+        assert!(!remote.is_privileged(&Phase::Functional));
+        assert!(remote.local_phase(Some(Phase::Functional)).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_config_json_phases() {
+        use tempdir::TempDir;
+        use std::fs::{self, File};
+        use std::io::Write;
+        let tempdir = TempDir::new("config-json-phases").unwrap();
+        let path = tempdir.path().to_path_buf();
+        assert!(ProjectToml::config_json_phases(&path).unwrap().is_none());
+        fs::create_dir_all(path.join(".delivery")).unwrap();
+        let mut config = File::create(path.join(".delivery").join("config.json")).unwrap();
+        config.write_all(br#"{
+            "version": "2",
+            "build_cookbook": { "name": "build_cookbook", "path": ".delivery/build_cookbook" },
+            "local_phases": { "lint": "cookstyle", "unit": "rspec" }
+        }"#).unwrap();
+        let phases = ProjectToml::config_json_phases(&path).unwrap().unwrap();
+        assert_eq!(Some("cookstyle".to_string()), phases.lint);
+        assert_eq!(Some("rspec".to_string()), phases.unit);
+        assert_eq!(None, phases.syntax);
+    }
+
+    #[test]
+    fn test_phase_limits() {
+        use utils::limits::Limits;
+        let p_toml = ProjectToml::parse_config(r#"
+[phase_limits.functional]
+timeout = "45m"
+memory = "4G"
+
+[phase_limits.security]
+cpu = "10m"
+"#).unwrap();
+        assert_eq!(Limits { timeout: Some(2700), memory: Some(4 << 30), cpu: None },
+                   p_toml.limits_for("functional").unwrap());
+        assert_eq!(Some(600), p_toml.limits_for("security").unwrap().cpu);
+        assert!(p_toml.limits_for("unit").unwrap().is_empty());
+        assert!(ProjectToml::parse_config("[phase_limits.unti]\ntimeout = \"1m\"").is_err());
+        assert!(ProjectToml::parse_config("[phase_limits.unit]\nmemory = \"lots\"").is_err());
+    }
+
+    #[test]
+    fn test_matrix() {
+        let matrix = Matrix::parse("os=ubuntu, centos").unwrap();
+        assert_eq!("os", matrix.dimension);
+        assert_eq!(vec!["ubuntu".to_string(), "centos".to_string()], matrix.values);
+        assert!(Matrix::parse("ubuntu,centos").is_err());
+        assert!(Matrix::parse("os=").is_err());
+        assert!(Matrix::parse("=ubuntu").is_err());
+
+        let p_toml = ProjectToml::parse_config(r#"
+[matrix_images.os]
+ubuntu = "ubuntu:16.04"
+"#).unwrap();
+        assert_eq!(Some("ubuntu:16.04".to_string()), p_toml.matrix_image("os", "ubuntu"));
+        assert_eq!(None, p_toml.matrix_image("os", "centos"));
+        assert_eq!(None, p_toml.matrix_image("arch", "ubuntu"));
+    }
+
     #[test]
     fn test_stages_phases() {
         let verify = Stage::Verify;
@@ -0,0 +1,176 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Syncing a config directory into `.delivery/`
+//!
+//! Besides `config.json`, some organizations ship a whole directory of
+//! Delivery files (`dependencies/`, data files for the phases...). The
+//! files of that directory are compared with their copy in `.delivery/`
+//! by checksum, a few at a time, and only the new or modified ones are
+//! copied over.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use types::DeliveryResult;
+use utils::{self, file_needs_updated};
+use utils::pool;
+
+/// What syncing did to a file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileSync {
+    Added,
+    Updated,
+    Unchanged,
+}
+
+impl fmt::Display for FileSync {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FileSync::Added => write!(f, "added"),
+            FileSync::Updated => write!(f, "updated"),
+            FileSync::Unchanged => write!(f, "unchanged"),
+        }
+    }
+}
+
+/// The files of a synced directory, relative to it, in path order
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncSummary {
+    pub files: Vec<(PathBuf, FileSync)>,
+}
+
+impl SyncSummary {
+    /// The files that were added or updated
+    pub fn changed(&self) -> Vec<PathBuf> {
+        self.files.iter()
+            .filter(|&&(_, s)| s != FileSync::Unchanged)
+            .map(|&(ref p, _)| p.clone())
+            .collect()
+    }
+
+    pub fn count(&self, sync: FileSync) -> usize {
+        self.files.iter().filter(|&&(_, s)| s == sync).count()
+    }
+
+    /// i.e. `2 added, 1 updated, 3 unchanged`
+    pub fn summary(&self) -> String {
+        format!("{} added, {} updated, {} unchanged", self.count(FileSync::Added),
+                self.count(FileSync::Updated), self.count(FileSync::Unchanged))
+    }
+}
+
+/// What copying `source` over `dest` would do
+pub fn compare(source: &Path, dest: &Path) -> DeliveryResult<FileSync> {
+    if !dest.exists() {
+        Ok(FileSync::Added)
+    } else if try!(file_needs_updated(source, dest)) {
+        Ok(FileSync::Updated)
+    } else {
+        Ok(FileSync::Unchanged)
+    }
+}
+
+/// Every file under `dir`, relative to it, in path order
+pub fn files_under(dir: &Path) -> DeliveryResult<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in try!(fs::read_dir(&current)) {
+            let path = try!(entry).path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if let Ok(relative) = path.strip_prefix(dir) {
+                files.push(relative.to_path_buf());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// What syncing `source_dir` to `dest_dir` would do, without copying
+/// anything, comparing up to `concurrency` files at a time.
+pub fn plan_dir(source_dir: &Path, dest_dir: &Path,
+                concurrency: usize) -> DeliveryResult<SyncSummary> {
+    let files = try!(files_under(source_dir));
+    let (source, dest) = (source_dir.to_path_buf(), dest_dir.to_path_buf());
+    let compared = pool::parallel_map(files.clone(), concurrency, move |f: PathBuf| {
+        compare(&source.join(&f), &dest.join(&f))
+    });
+    let mut summary = SyncSummary::default();
+    for (file, sync) in files.into_iter().zip(compared.into_iter()) {
+        summary.files.push((file, try!(sync)));
+    }
+    Ok(summary)
+}
+
+/// Copy the new and modified files of `source_dir` to `dest_dir`,
+/// comparing up to `concurrency` files at a time.
+pub fn sync_dir(source_dir: &Path, dest_dir: &Path,
+                concurrency: usize) -> DeliveryResult<SyncSummary> {
+    let summary = try!(plan_dir(source_dir, dest_dir, concurrency));
+    for file in summary.changed() {
+        let target = dest_dir.join(&file);
+        if let Some(parent) = target.parent() {
+            try!(utils::mkdir_recursive(parent));
+        }
+        try!(fs::copy(source_dir.join(&file), &target));
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use tempdir::TempDir;
+
+    fn write(path: &Path, content: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path).unwrap().write_all(content).unwrap();
+    }
+
+    #[test]
+    fn test_sync_dir() {
+        let tmp = TempDir::new("config-dir").unwrap();
+        let (source, dest) = (tmp.path().join("source"), tmp.path().join("dest"));
+        write(&source.join("config.json"), b"{\"version\": \"2\"}");
+        write(&source.join("dependencies").join("web.json"), b"[\"api\"]");
+        write(&source.join("data.bin"), &[0xff, 0xfe, 0x00]);
+        write(&dest.join("config.json"), b"{\"version\": \"1\"}");
+        write(&dest.join("data.bin"), &[0xff, 0xfe, 0x00]);
+
+        let plan = plan_dir(&source, &dest, 2).unwrap();
+        assert!(!dest.join("dependencies").exists());
+
+        let summary = sync_dir(&source, &dest, 2).unwrap();
+        assert_eq!(plan, summary);
+        assert_eq!(vec![(PathBuf::from("config.json"), FileSync::Updated),
+                        (PathBuf::from("data.bin"), FileSync::Unchanged),
+                        (PathBuf::from("dependencies/web.json"), FileSync::Added)],
+                   summary.files);
+        assert_eq!("1 added, 1 updated, 1 unchanged", summary.summary());
+        assert_eq!(2, summary.changed().len());
+        assert!(dest.join("dependencies").join("web.json").exists());
+
+        let again = sync_dir(&source, &dest, 2).unwrap();
+        assert!(again.changed().is_empty());
+    }
+}
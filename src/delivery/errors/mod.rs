@@ -24,6 +24,7 @@ use std::string;
 use hyper;
 use toml;
 use types::ExitCode;
+use utils::strict::Warning;
 use hyper::error::Error as HttpError;
 
 #[derive(Debug)]
@@ -88,6 +89,36 @@ pub enum Kind {
     UnsupportedHttpMethod,
     HttpError(HttpError),
     UnsupportedProtocol,
+    InvalidServerUrl,
+    InvalidDataBag,
+    CertificatePinMismatch,
+    CertificatePinFetchFailed,
+    DuplicateChange,
+    DependencyCycle,
+    InvalidReport,
+    PrivilegeEscalationRefused,
+    SecretsDetected,
+    EnvironmentFetchFailed,
+    InsideManagedWorkspace,
+    InsufficientDiskSpace,
+    PathNotWritable,
+    PipelineGroupReviewFailed,
+    StrictWarning(Warning),
+    ProxyAutoConfigFailed,
+    PhaseLimitExceeded,
+    TelemetryFailed,
+    BranchDiverged,
+    NonUnicodePath,
+    CommandPanicked,
+    PromptUnanswered,
+    UnrelatedHistory,
+    TicketLinkFailed,
+    OfflineMode,
+    TlsConfig,
+    IncompatibleGenerator,
+    InvalidName,
+    InvalidSchedule,
+    NoScheduledDelivery,
     ApiError(hyper::status::StatusCode, Result<String, io::Error>),
     JsonParseError,
     TomlDecodeError,
@@ -131,6 +162,14 @@ impl DeliveryError {
     pub fn detail(&self) -> Option<String> {
         self.detail.clone()
     }
+
+    /// The exit code of a command that failed with this error
+    pub fn exit_code(&self) -> ExitCode {
+        match self.kind {
+            Kind::StrictWarning(ref warning) => warning.exit_code(),
+            _ => 1
+        }
+    }
 }
 
 impl error::Error for DeliveryError {
@@ -191,8 +230,38 @@ impl error::Error for DeliveryError {
             Kind::ChmodFailed => "Cannot set permissions",
             Kind::UnsupportedHttpMethod => "Unsupported HTTP method",
             Kind::UnsupportedProtocol => "Unsupported protocol",
+            Kind::InvalidServerUrl => "The server URL could not be parsed",
+            Kind::InvalidDataBag => "Invalid data bag",
+            Kind::CertificatePinMismatch => "The server certificate does not match the pinned certificate",
+            Kind::CertificatePinFetchFailed => "Unable to fetch the server certificate",
+            Kind::DuplicateChange => "An open change already exists for this commit",
+            Kind::DependencyCycle => "Circular dependency between pipelines",
+            Kind::InvalidReport => "Unable to parse the test report",
+            Kind::PrivilegeEscalationRefused => "Unable to run the phase with elevated privileges",
+            Kind::SecretsDetected => "The change looks like it contains credentials",
+            Kind::EnvironmentFetchFailed => "Failed to fetch an environment from the Chef Server",
+            Kind::InsideManagedWorkspace => "Refusing to run inside a workspace managed by the delivery CLI",
+            Kind::InsufficientDiskSpace => "Not enough free disk space",
+            Kind::PathNotWritable => "Cannot write to the path",
+            Kind::PipelineGroupReviewFailed => "The change could not be submitted to every pipeline of the group",
+            Kind::StrictWarning(_) => "A warning failed the command in strict mode",
+            Kind::ProxyAutoConfigFailed => "Unable to evaluate the proxy auto-config file",
+            Kind::PhaseLimitExceeded => "A phase exceeded one of its limits",
+            Kind::TelemetryFailed => "Unable to send the usage analytics",
+            Kind::BranchDiverged => "The local branch is behind another remote",
+            Kind::NonUnicodePath => "A path contains non-unicode sequences",
+            Kind::CommandPanicked => "The command stopped on an unexpected error",
+            Kind::PromptUnanswered => "A question was left without an answer",
+            Kind::UnrelatedHistory => "The project on the server has an unrelated git history",
+            Kind::TicketLinkFailed => "Unable to link the change from its tickets",
+            Kind::OfflineMode => "The network is off limits in offline mode",
+            Kind::TlsConfig => "The custom CA bundle or client certificate can not be used",
+            Kind::IncompatibleGenerator => "The generator does not support the installed ChefDK",
+            Kind::InvalidName => "The name is not valid for the Delivery server",
+            Kind::InvalidSchedule => "The time or window of the delivery is not valid",
+            Kind::NoScheduledDelivery => "No delivery of the change is scheduled",
             Kind::HttpError(_) => "An HTTP Error occurred",
-            Kind::ApiError(_, _) => "An API Error occurred",
+            Kind::ApiError(_, _) => "The Delivery server returned an error",
             Kind::JsonParseError => "Attempted to parse invalid JSON",
             Kind::TomlDecodeError => "Attempted to decode invalid TOML",
             Kind::IntParseError => "Attempted to parse invalid Int",
@@ -0,0 +1,228 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+//! Change events of a pipeline, for local automation
+//!
+//! `delivery listen` polls the changes of a pipeline and compares each
+//! answer with the previous one: a change showing up is
+//! `change.created`, moving to the approved or delivered list is
+//! `change.approved` or `change.delivered`, and its stage ending is
+//! `phase.passed` or `phase.failed`. Each event is handed to a local
+//! command, its JSON on stdin. Nothing fires for what the first poll
+//! finds, only for what changes while listening.
+
+use std::collections::BTreeMap;
+use std::io::prelude::*;
+use std::process::{Command, Stdio};
+use serde_json;
+use errors::{DeliveryError, Kind};
+use http::APIClient;
+use http::change::{self, ChangeSummary};
+use types::DeliveryResult;
+
+pub mod watch;
+
+pub const EVENTS: &'static [&'static str] = &["change.created", "change.approved",
+                                              "change.delivered", "phase.passed",
+                                              "phase.failed"];
+
+// The change lists polled, in the order a change goes through them
+const STATES: &'static [&'static str] = &["open", "approved", "delivered"];
+
+/// What the command of `delivery listen` gets on stdin
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Event {
+    pub event: String,
+    pub org: String,
+    pub project: String,
+    pub pipeline: String,
+    pub change: ChangeSummary,
+}
+
+/// The changes of a pipeline by id, along with the list they are in
+pub type Snapshot = BTreeMap<String, (String, ChangeSummary)>;
+
+/// The event names of a comma separated `list`, every one of them
+/// when it is empty
+pub fn parse(list: &str) -> DeliveryResult<Vec<String>> {
+    if list.trim().is_empty() {
+        return Ok(EVENTS.iter().map(|e| e.to_string()).collect())
+    }
+    let mut events = Vec::new();
+    for event in list.split(',').map(|e| e.trim()).filter(|e| !e.is_empty()) {
+        if !EVENTS.contains(&event) {
+            let msg = format!("Unknown event {}, use one of {}", event, EVENTS.join(", "));
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        events.push(event.to_string());
+    }
+    Ok(events)
+}
+
+/// The changes of `pipeline` in every list, a change in several taking
+/// the last one
+pub fn snapshot(client: &APIClient, org: &str, proj: &str,
+                pipeline: &str) -> DeliveryResult<Snapshot> {
+    let mut snapshot = Snapshot::new();
+    for state in STATES.iter() {
+        for summary in try!(change::changes_in_state(client, org, proj, pipeline, state)) {
+            snapshot.insert(summary.id.clone(), (state.to_string(), summary));
+        }
+    }
+    Ok(snapshot)
+}
+
+/// The events between two snapshots, as `(event, change)` pairs
+pub fn diff(before: &Snapshot, after: &Snapshot) -> Vec<(String, ChangeSummary)> {
+    let mut events = Vec::new();
+    for (id, &(ref state, ref summary)) in after.iter() {
+        let previous = before.get(id);
+        let was = |s: &str| previous.map(|&(ref st, _)| st == s).unwrap_or(false);
+        if previous.is_none() && state == "open" {
+            events.push(("change.created".to_string(), summary.clone()));
+        }
+        if state != "open" && !was(state) {
+            events.push((format!("change.{}", state), summary.clone()));
+        }
+        let stage_ended = match previous {
+            Some(&(_, ref p)) => p.stage != summary.stage || p.stage_status != summary.stage_status,
+            None => true
+        };
+        if stage_ended && (summary.stage_status == "passed" || summary.stage_status == "failed") {
+            events.push((format!("phase.{}", summary.stage_status), summary.clone()));
+        }
+    }
+    events
+}
+
+/// Run `command` through the shell with the JSON of `event` on stdin,
+/// and its name and change in `DELIVERY_EVENT` and `DELIVERY_CHANGE_ID`
+pub fn dispatch(command: &str, event: &Event) -> DeliveryResult<()> {
+    let json = try!(serde_json::to_string(event));
+    let mut child = try!(shell(command)
+                         .env("DELIVERY_EVENT", &event.event)
+                         .env("DELIVERY_CHANGE_ID", &event.change.id)
+                         .stdin(Stdio::piped())
+                         .spawn());
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that ignores its input may exit before reading it
+        if let Err(e) = stdin.write_all(json.as_bytes()) {
+            debug!("Unable to write the event to {}: {:?}", command, e);
+        }
+    }
+    let status = try!(child.wait());
+    if !status.success() {
+        let msg = format!("{} exited with {} on {} of change {}",
+                          command, status, event.event, event.change.id);
+        return Err(DeliveryError::throw(Kind::FailedToExecute, Some(msg)))
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(target_os = "windows")]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd.exe");
+    shell.arg("/c").arg(command);
+    shell
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use tempdir::TempDir;
+    use errors::Kind;
+    use http::change::ChangeSummary;
+
+    fn summary(id: &str, stage: &str, stage_status: &str) -> ChangeSummary {
+        ChangeSummary {
+            id: id.to_string(),
+            topic: format!("topic-{}", id),
+            target: "master".to_string(),
+            title: String::new(),
+            sha: String::new(),
+            stage: stage.to_string(),
+            stage_status: stage_status.to_string(),
+            patchset_number: 1,
+            approved_by: String::new(),
+            approved_at: String::new(),
+            submitted_at: String::new(),
+        }
+    }
+
+    fn snapshot(changes: &[(&str, &str, &str, &str)]) -> Snapshot {
+        changes.iter().map(|&(id, state, stage, status)| {
+            (id.to_string(), (state.to_string(), summary(id, stage, status)))
+        }).collect()
+    }
+
+    fn names(events: Vec<(String, ChangeSummary)>) -> Vec<String> {
+        events.into_iter().map(|(event, change)| format!("{} {}", event, change.id)).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(vec!["change.approved", "phase.failed"],
+                   parse("change.approved, phase.failed").unwrap());
+        assert_eq!(EVENTS.len(), parse("").unwrap().len());
+        let e = parse("change.approved,change.exploded").unwrap_err();
+        assert!(assert_enum!(e.kind, Kind::OptionConstraint));
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = snapshot(&[("a", "open", "verify", "running"),
+                                ("b", "open", "verify", "passed"),
+                                ("c", "approved", "build", "running")]);
+        let after = snapshot(&[("a", "open", "verify", "failed"),
+                               ("b", "approved", "build", "running"),
+                               ("c", "delivered", "delivered", "passed"),
+                               ("d", "open", "verify", "running")]);
+        assert_eq!(vec!["phase.failed a", "change.approved b", "change.delivered c",
+                        "phase.passed c", "change.created d"],
+                   names(diff(&before, &after)));
+        assert!(diff(&after, &after).is_empty());
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_dispatch() {
+        let tempdir = TempDir::new("events").unwrap();
+        let out = tempdir.path().join("event.json");
+        let command = format!("cat > {} && test \"$DELIVERY_EVENT\" = change.approved",
+                              out.display());
+        let event = Event {
+            event: "change.approved".to_string(),
+            org: "beans".to_string(),
+            project: "frijol".to_string(),
+            pipeline: "master".to_string(),
+            change: summary("a", "build", "running"),
+        };
+        dispatch(&command, &event).unwrap();
+        let mut json = String::new();
+        File::open(&out).unwrap().read_to_string(&mut json).unwrap();
+        assert!(json.contains("\"event\":\"change.approved\""));
+        assert!(json.contains("\"project\":\"frijol\""));
+        assert!(dispatch("exit 3", &event).is_err());
+    }
+}
@@ -0,0 +1,186 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! The progress of one change through its stages
+//!
+//! `delivery status --watch` polls a change and prints each phase whose
+//! status moved since the previous answer. Watching ends when a stage
+//! or phase fails, when the stage it watches up to passes, when the
+//! change is closed or waits for an approval to go on, or when it times
+//! out. Each ending has an exit code of its own, for scripts.
+
+use std::collections::BTreeMap;
+use errors::{DeliveryError, Kind};
+use http::change::{ChangeProgress, StageRun};
+use types::{DeliveryResult, ExitCode};
+
+/// The stages a change is watched through, in order
+pub const STAGES: &'static [&'static str] = &["verify", "build", "acceptance"];
+
+/// The status of every stage and phase, keyed by `stage` and
+/// `stage/phase`
+pub type Progress = BTreeMap<String, String>;
+
+/// How watching a change ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Passed,
+    Failed,
+    TimedOut,
+    /// The change was abandoned or superseded, in that state
+    Closed(String),
+    /// Verify passed and the next stages wait for the change to be
+    /// approved
+    AwaitingApproval,
+}
+
+impl Outcome {
+    pub fn exit_code(&self) -> ExitCode {
+        match *self {
+            Outcome::Passed => 0,
+            Outcome::Failed => 1,
+            Outcome::TimedOut => 2,
+            Outcome::Closed(_) => 3,
+            Outcome::AwaitingApproval => 4,
+        }
+    }
+}
+
+/// Check `until` names one of the watched stages
+pub fn parse_until(until: &str) -> DeliveryResult<String> {
+    let until = if until.is_empty() { "acceptance" } else { until };
+    let until = until.to_lowercase();
+    if STAGES.contains(&until.as_ref()) {
+        Ok(until)
+    } else {
+        Err(DeliveryError::throw(Kind::OptionConstraint, Some(format!(
+            "Unable to watch until '{}'; the stages are {}.", until, STAGES.join(", ")
+        ))))
+    }
+}
+
+/// The progress `stages` report, for the stages up to `until`
+pub fn progress(stages: &[StageRun], until: &str) -> Progress {
+    let mut progress = Progress::new();
+    for stage in stages.iter().filter(|s| watched(&s.stage, until)) {
+        progress.insert(stage.stage.clone(), stage.status.clone());
+        for phase in stage.phases.iter() {
+            progress.insert(format!("{}/{}", stage.stage, phase.name), phase.status.clone());
+        }
+    }
+    progress
+}
+
+/// What moved from `before` to `after`, as `(stage/phase, status)` pairs
+pub fn diff(before: &Progress, after: &Progress) -> Vec<(String, String)> {
+    after.iter()
+        .filter(|&(key, status)| before.get(key) != Some(status))
+        .map(|(key, status)| (key.clone(), status.clone()))
+        .collect()
+}
+
+/// How watching is over given the `progress` of the change and its
+/// `change` details, `None` while there is still something to wait for
+pub fn outcome(progress: &Progress, change: &ChangeProgress, until: &str) -> Option<Outcome> {
+    if progress.values().any(|s| s == "failed") {
+        return Some(Outcome::Failed)
+    }
+    if progress.get(until).map(|s| s == "passed").unwrap_or(false) {
+        return Some(Outcome::Passed)
+    }
+    match change.state.as_ref() {
+        "" | "open" | "merged" => (),
+        state => return Some(Outcome::Closed(state.to_string()))
+    }
+    let verified = progress.get("verify").map(|s| s == "passed").unwrap_or(false);
+    if verified && change.state == "open" && change.approved_by.is_empty() {
+        return Some(Outcome::AwaitingApproval)
+    }
+    None
+}
+
+// Whether `stage` comes no later than `until`
+fn watched(stage: &str, until: &str) -> bool {
+    let position = |s: &str| STAGES.iter().position(|w| *w == s);
+    match (position(stage), position(until)) {
+        (Some(s), Some(u)) => s <= u,
+        _ => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use errors::Kind;
+    use http::change::{PhaseRun, StageRun};
+
+    fn stage(name: &str, status: &str, phases: &[(&str, &str)]) -> StageRun {
+        StageRun {
+            stage: name.to_string(),
+            status: status.to_string(),
+            phases: phases.iter().map(|&(n, s)| {
+                PhaseRun { name: n.to_string(), status: s.to_string() }
+            }).collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_until() {
+        assert_eq!("acceptance", parse_until("").unwrap());
+        assert_eq!("build", parse_until("Build").unwrap());
+        let e = parse_until("union").unwrap_err();
+        assert!(assert_enum!(e.kind, Kind::OptionConstraint));
+    }
+
+    #[test]
+    fn test_progress_and_diff() {
+        let before = progress(&[stage("verify", "running", &[("unit", "running")])], "build");
+        let after = progress(&[stage("verify", "passed", &[("unit", "passed")]),
+                               stage("build", "running", &[("publish", "idle")]),
+                               stage("acceptance", "idle", &[])], "build");
+        assert_eq!(None, after.get("acceptance"));
+        assert_eq!(vec![("build".to_string(), "running".to_string()),
+                        ("build/publish".to_string(), "idle".to_string()),
+                        ("verify".to_string(), "passed".to_string()),
+                        ("verify/unit".to_string(), "passed".to_string())],
+                   diff(&before, &after));
+        assert!(diff(&after, &after).is_empty());
+    }
+
+    fn change(state: &str, approved_by: &str) -> ChangeProgress {
+        ChangeProgress { state: state.to_string(), approved_by: approved_by.to_string(),
+                         stages: Vec::new() }
+    }
+
+    #[test]
+    fn test_outcome() {
+        let open = change("open", "");
+        let verified = progress(&[stage("verify", "passed", &[("unit", "passed")])], "acceptance");
+        assert_eq!(Some(Outcome::Passed), outcome(&verified, &open, "verify"));
+        assert_eq!(Some(Outcome::AwaitingApproval), outcome(&verified, &open, "acceptance"));
+        assert_eq!(None, outcome(&verified, &change("open", "alice"), "acceptance"));
+        assert_eq!(None, outcome(&verified, &change("merged", "alice"), "acceptance"));
+        let failed = progress(&[stage("verify", "running", &[("lint", "failed")])], "acceptance");
+        assert_eq!(Some(Outcome::Failed), outcome(&failed, &open, "acceptance"));
+        let running = progress(&[stage("verify", "running", &[])], "acceptance");
+        assert_eq!(None, outcome(&running, &open, "acceptance"));
+        assert_eq!(Some(Outcome::Closed("abandoned".to_string())),
+                   outcome(&running, &change("abandoned", ""), "acceptance"));
+        assert_eq!(3, Outcome::Closed("superseded".to_string()).exit_code());
+        assert_eq!(2, Outcome::TimedOut.exit_code());
+    }
+}
@@ -0,0 +1,274 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! A read-only battery of requests telling which features of the cli
+//! the server supports, for servers that predate feature flags or do
+//! not advertise all of them.
+
+use std::collections::BTreeMap;
+use std::io::prelude::*;
+use hyper::status::StatusCode;
+use http::APIClient;
+use http::change::ChangeSummary;
+
+pub const PAGINATION: &'static str = "pagination";
+pub const COMMENTS_API: &'static str = "comments_api";
+pub const RUNNERS_API: &'static str = "runners_api";
+pub const CONFIG_V2: &'static str = "config_v2";
+
+/// The outcome of probing one feature. `supported` is `None` when the
+/// probe could not tell.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Check {
+    pub feature: String,
+    pub endpoint: String,
+    pub supported: Option<bool>,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(feature: &str, endpoint: &str, supported: Option<bool>, detail: &str) -> Check {
+        Check {
+            feature: feature.to_string(),
+            endpoint: endpoint.to_string(),
+            supported: supported,
+            detail: detail.to_string(),
+        }
+    }
+}
+
+/// Whether a status tells the endpoint exists. Servers answer 404,
+/// 405 or 501 for what they do not implement; anything else but a
+/// success says nothing about the feature.
+pub fn classify(status: StatusCode) -> Option<bool> {
+    match status {
+        s if s.is_success() => Some(true),
+        StatusCode::NotFound | StatusCode::MethodNotAllowed |
+        StatusCode::NotImplemented => Some(false),
+        _ => None
+    }
+}
+
+fn describe(status: StatusCode) -> String {
+    match classify(status) {
+        Some(true) => "available".to_string(),
+        Some(false) => format!("not implemented ({})", status),
+        None => format!("unexpected answer ({})", status),
+    }
+}
+
+// GET `path`, returning the status and the body. Failing to reach the
+// server is reported in place of the status.
+fn probe(client: &APIClient, path: &str) -> Result<(StatusCode, String), String> {
+    let mut response = try!(client.get(path).map_err(|e| format!("request failed: {}", e)));
+    let mut body = String::new();
+    if let Err(e) = response.read_to_string(&mut body) {
+        debug!("Unable to read the body of {}: {:?}", path, e);
+    }
+    Ok((response.status, body))
+}
+
+// The changes of the page at `path`, or the check telling
+// why there are none
+fn page(client: &APIClient, path: &str) -> Result<Vec<ChangeSummary>, Check> {
+    match probe(client, path) {
+        Ok((status, ref body)) if status.is_success() => {
+            ChangeSummary::parse_json_list(body).map_err(|e| {
+                Check::new(PAGINATION, path, None, &format!("unreadable list of changes: {}", e))
+            })
+        },
+        Ok((status, _)) => Err(Check::new(PAGINATION, path, classify(status), &describe(status))),
+        Err(e) => Err(Check::new(PAGINATION, path, None, &e))
+    }
+}
+
+// A server paginating changes honors `limit` and `offset`. Only a
+// project with two changes or more can show it, with fewer every
+// server returns what it has. Also returns a change of the project,
+// for the checks that need one.
+fn check_pagination(client: &APIClient, org: &str,
+                    proj: &str) -> (Check, Option<String>) {
+    let first = format!("orgs/{}/projects/{}/changes?limit=1&offset=0", org, proj);
+    let list = match page(client, &first) {
+        Ok(list) => list,
+        Err(check) => return (check, None)
+    };
+    let change_id = list.first().map(|c| c.id.clone());
+    let check = match list.len() {
+        0 => Check::new(PAGINATION, &first, None, "the project has no change to page through"),
+        1 => {
+            let second = format!("orgs/{}/projects/{}/changes?limit=1&offset=1", org, proj);
+            match page(client, &second) {
+                Ok(ref next) if next.is_empty() => {
+                    Check::new(PAGINATION, &second, None,
+                               "the project has a single change to page through")
+                },
+                Ok(ref next) if next.len() == 1 && next[0].id != list[0].id => {
+                    Check::new(PAGINATION, &second, Some(true), "limit and offset are honored")
+                },
+                Ok(ref next) if next.len() == 1 => {
+                    Check::new(PAGINATION, &second, Some(false), "offset is ignored")
+                },
+                Ok(next) => Check::new(PAGINATION, &second, Some(false),
+                                       &format!("limit=1 returned {} changes", next.len())),
+                Err(check) => check
+            }
+        },
+        n => Check::new(PAGINATION, &first, Some(false),
+                        &format!("limit=1 returned {} changes", n))
+    };
+    (check, change_id)
+}
+
+/// Run every check against the project `org/proj`. `advertised` are
+/// the feature flags of the server, for what can not be probed
+/// without changing anything.
+pub fn run(client: &APIClient, org: &str, proj: &str,
+           advertised: &BTreeMap<String, bool>) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let (pagination, change_id) = check_pagination(client, org, proj);
+    checks.push(pagination);
+
+    checks.push(match change_id {
+        Some(id) => {
+            let comments = format!("orgs/{}/projects/{}/changes/{}/comments", org, proj, id);
+            match probe(client, &comments) {
+                Ok((status, _)) => Check::new(COMMENTS_API, &comments,
+                                              classify(status), &describe(status)),
+                Err(e) => Check::new(COMMENTS_API, &comments, None, &e)
+            }
+        },
+        None => Check::new(COMMENTS_API, "", None, "the project has no change to read comments of")
+    });
+
+    // Listing runners takes admin rights, which says the API is there. A
+    // token the server did not take says nothing about the API.
+    checks.push(match probe(client, "runners") {
+        Ok((StatusCode::Forbidden, _)) => {
+            Check::new(RUNNERS_API, "runners", Some(true), "available, needs admin rights")
+        },
+        Ok((status, _)) => Check::new(RUNNERS_API, "runners", classify(status), &describe(status)),
+        Err(e) => Check::new(RUNNERS_API, "runners", None, &e)
+    });
+
+    checks.push(match advertised.get(CONFIG_V2) {
+        Some(&enabled) => Check::new(CONFIG_V2, "features", Some(enabled), "advertised by the server"),
+        None => Check::new(CONFIG_V2, "features", None,
+                           "not advertised, and can not be probed without running a job")
+    });
+    checks
+}
+
+/// The features the checks could tell about, for the feature flags
+pub fn flags(checks: &[Check]) -> BTreeMap<String, bool> {
+    checks.iter()
+        .filter_map(|c| c.supported.map(|s| (c.feature.clone(), s)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use hyper::status::StatusCode;
+    use http::APIClient;
+    use mockito::{SERVER_ADDRESS, mock};
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(Some(true), classify(StatusCode::Ok));
+        assert_eq!(Some(true), classify(StatusCode::NoContent));
+        assert_eq!(Some(false), classify(StatusCode::NotFound));
+        assert_eq!(Some(false), classify(StatusCode::NotImplemented));
+        assert_eq!(None, classify(StatusCode::InternalServerError));
+        assert_eq!(None, classify(StatusCode::Unauthorized));
+    }
+
+    #[test]
+    fn test_run() {
+        mock("GET", "/api/v0/e/conformer/orgs/acme/projects/rocket/changes?limit=1&offset=0")
+            .with_status(200)
+            .with_body("[{\"id\": \"abc\", \"topic\": \"boost\", \"target\": \"master\"}, \
+                        {\"id\": \"def\", \"topic\": \"wings\", \"target\": \"master\"}]")
+            .create();
+        mock("GET", "/api/v0/e/conformer/orgs/acme/projects/rocket/changes/abc/comments")
+            .with_status(200)
+            .with_body("[]")
+            .create();
+        mock("GET", "/api/v0/e/conformer/runners")
+            .with_status(403)
+            .create();
+        let client = APIClient::new_http(SERVER_ADDRESS, "conformer");
+        let checks = run(&client, "acme", "rocket", &BTreeMap::new());
+
+        let supported: Vec<_> = checks.iter()
+            .map(|c| (c.feature.as_str(), c.supported))
+            .collect();
+        assert_eq!(vec![(PAGINATION, Some(false)),
+                        (COMMENTS_API, Some(true)),
+                        (RUNNERS_API, Some(true)),
+                        (CONFIG_V2, None)], supported);
+        assert_eq!("limit=1 returned 2 changes", checks[0].detail);
+
+        let detected = flags(&checks);
+        assert_eq!(3, detected.len());
+        assert_eq!(Some(&false), detected.get(PAGINATION));
+    }
+
+    #[test]
+    fn test_check_pagination() {
+        let changes = "/api/v0/e/pager/orgs/acme/projects";
+        mock("GET", &format!("{}/paged/changes?limit=1&offset=0", changes))
+            .with_status(200)
+            .with_body("[{\"id\": \"abc\", \"topic\": \"boost\", \"target\": \"master\"}]")
+            .create();
+        mock("GET", &format!("{}/paged/changes?limit=1&offset=1", changes))
+            .with_status(200)
+            .with_body("[{\"id\": \"def\", \"topic\": \"wings\", \"target\": \"master\"}]")
+            .create();
+        mock("GET", &format!("{}/single/changes?limit=1&offset=0", changes))
+            .with_status(200)
+            .with_body("[{\"id\": \"abc\", \"topic\": \"boost\", \"target\": \"master\"}]")
+            .create();
+        mock("GET", &format!("{}/single/changes?limit=1&offset=1", changes))
+            .with_status(200)
+            .with_body("[]")
+            .create();
+        mock("GET", &format!("{}/empty/changes?limit=1&offset=0", changes))
+            .with_status(200)
+            .with_body("[]")
+            .create();
+        mock("GET", "/api/v0/e/pager/runners")
+            .with_status(401)
+            .create();
+        let client = APIClient::new_http(SERVER_ADDRESS, "pager");
+
+        let (check, change_id) = check_pagination(&client, "acme", "paged");
+        assert_eq!(Some(true), check.supported);
+        assert_eq!(Some("abc".to_string()), change_id);
+        let (check, change_id) = check_pagination(&client, "acme", "single");
+        assert_eq!(None, check.supported);
+        assert_eq!(Some("abc".to_string()), change_id);
+        let (check, change_id) = check_pagination(&client, "acme", "empty");
+        assert_eq!(None, check.supported);
+        assert_eq!(None, change_id);
+
+        let runners = run(&client, "acme", "empty", &BTreeMap::new());
+        assert_eq!(None, runners[2].supported);
+    }
+}
@@ -0,0 +1,190 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Server feature flags
+//!
+//! Servers advertise per-enterprise toggles (i.e. `stacked_changes`,
+//! `comments_api_v2`) so one version of the cli can talk to servers of
+//! different versions and pick the behavior each one supports. The
+//! flags are cached in `~/.delivery/cache/features/` for `CACHE_TTL`
+//! seconds to avoid asking the server on every command.
+//!
+//! Servers that do not advertise a flag may still have the feature;
+//! what `delivery conformance` detects is kept in the same cache and
+//! stands in for the flags the server does not advertise.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use serde_json;
+use time;
+use config::Config;
+use http::{APIAuth, APIClient};
+use types::DeliveryResult;
+use utils::{self, read_file};
+
+pub mod conformance;
+
+pub const CACHE_TTL: i64 = 60 * 60;
+
+pub const STACKED_CHANGES: &'static str = "stacked_changes";
+pub const COMMENTS_API_V2: &'static str = "comments_api_v2";
+pub const SCHEDULED_DELIVERY: &'static str = "scheduled_delivery";
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Features {
+    pub fetched_at: i64,
+    pub flags: BTreeMap<String, bool>,
+    // What `delivery conformance` found out, kept across fetches
+    #[serde(default)]
+    pub detected: BTreeMap<String, bool>,
+}
+
+impl Features {
+    pub fn new(flags: BTreeMap<String, bool>) -> Features {
+        Features { fetched_at: time::get_time().sec, flags: flags, detected: BTreeMap::new() }
+    }
+
+    /// Flags the server neither advertises nor was detected to support
+    /// are disabled
+    pub fn enabled(&self, name: &str) -> bool {
+        self.flags.get(name).or_else(|| self.detected.get(name)).cloned().unwrap_or(false)
+    }
+
+    pub fn is_fresh(&self, now: i64) -> bool {
+        now - self.fetched_at < CACHE_TTL
+    }
+
+    pub fn load(path: &Path) -> Option<Features> {
+        read_file(&path.to_path_buf()).ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+    }
+
+    pub fn save(&self, path: &Path) -> DeliveryResult<()> {
+        if let Some(dir) = path.parent() {
+            try!(utils::mkdir_recursive(dir));
+        }
+        let mut f = try!(File::create(path));
+        try!(f.write_all(try!(serde_json::to_string_pretty(&self)).as_bytes()));
+        Ok(())
+    }
+}
+
+/// Where the flags of an enterprise are cached
+pub fn cache_path(server: &str, ent: &str) -> DeliveryResult<PathBuf> {
+    let name = format!("{}_{}.json", server.replace(":", "_"), ent);
+    utils::home_dir(&[".delivery", "cache", "features", &name])
+}
+
+/// The flags of the configured enterprise, from the cache while it is
+/// fresh, unless `refresh` is set. When the server can not be reached
+/// a stale cache is better than nothing.
+pub fn load(config: &Config, refresh: bool) -> DeliveryResult<Features> {
+    let path = try!(cache_path(&try!(config.api_host_and_port()),
+                               &try!(config.enterprise())));
+    let cached = Features::load(&path);
+    if let Some(ref features) = cached {
+        if !refresh && features.is_fresh(time::get_time().sec) {
+            return Ok(features.clone())
+        }
+    }
+    match fetch(config) {
+        Ok(mut features) => {
+            if let Some(ref cached) = cached {
+                features.detected = cached.detected.clone();
+            }
+            if let Err(e) = features.save(&path) {
+                debug!("Unable to cache the feature flags: {:?}", e);
+            }
+            Ok(features)
+        },
+        Err(e) => match cached {
+            Some(features) => {
+                debug!("Using stale feature flags, fetching failed: {:?}", e);
+                Ok(features)
+            },
+            None => Err(e)
+        }
+    }
+}
+
+/// Keep the features `delivery conformance` detected with the flags of
+/// the configured enterprise
+pub fn record_detected(config: &Config, detected: BTreeMap<String, bool>) -> DeliveryResult<()> {
+    let path = try!(cache_path(&try!(config.api_host_and_port()),
+                               &try!(config.enterprise())));
+    let mut features = load(config, false).unwrap_or_else(|e| {
+        debug!("Unable to load the feature flags: {:?}", e);
+        Features::new(BTreeMap::new())
+    });
+    features.detected = detected;
+    features.save(&path)
+}
+
+/// Whether the server enables a feature. Meant for commands deciding
+/// how to behave, so it never prompts and any failure means disabled.
+pub fn is_enabled(config: &Config, name: &str) -> bool {
+    match load(config, false) {
+        Ok(features) => features.enabled(name),
+        Err(e) => {
+            debug!("Unable to load the feature flags: {:?}", e);
+            false
+        }
+    }
+}
+
+fn fetch(config: &Config) -> DeliveryResult<Features> {
+    let mut client = try!(APIClient::from_config_no_auth(config));
+    if let Ok(auth) = APIAuth::from_stored_token(config) {
+        client.set_auth(auth);
+    }
+    Ok(Features::new(try!(client.server_features())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_features_cache() {
+        let tmp = TempDir::new("features").unwrap();
+        let path = tmp.path().join("cache").join("automate_chef.json");
+        assert_eq!(None, Features::load(&path));
+
+        let mut flags = BTreeMap::new();
+        flags.insert(STACKED_CHANGES.to_string(), true);
+        flags.insert(COMMENTS_API_V2.to_string(), false);
+        let mut detected = BTreeMap::new();
+        detected.insert(COMMENTS_API_V2.to_string(), true);
+        detected.insert("runners_api".to_string(), true);
+        let features = Features { fetched_at: 1000, flags: flags, detected: detected };
+        features.save(&path).unwrap();
+
+        let loaded = Features::load(&path).unwrap();
+        assert_eq!(features, loaded);
+        assert!(loaded.enabled(STACKED_CHANGES));
+        // The flags of the server win over what was detected
+        assert!(!loaded.enabled(COMMENTS_API_V2));
+        assert!(loaded.enabled("runners_api"));
+        assert!(!loaded.enabled("unknown"));
+        assert!(loaded.is_fresh(1000 + CACHE_TTL - 1));
+        assert!(!loaded.is_fresh(1000 + CACHE_TTL));
+    }
+}
@@ -0,0 +1,159 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+// Git credential helper
+//
+// Git talks to credential helpers with `key=value` lines on stdin and
+// expects the same format back on stdout, see gitcredentials(7). We
+// answer `get` requests for the Automate servers we hold an API token
+// for, so pushes over https never prompt for a password:
+//
+// ```ini
+// [credential "https://automate.example.com"]
+//     helper = "!delivery git-credential"
+// ```
+//
+// Requests for any other host get an empty answer, which makes git move
+// on to the next configured helper.
+
+use std::collections::BTreeMap;
+use token::StoredToken;
+
+/// The attributes git describes a credential with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Credential {
+    pub protocol: Option<String>,
+    pub host: Option<String>,
+    pub path: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl Credential {
+    /// Parse the attributes git sends, up to the first blank line
+    pub fn parse(input: &str) -> Credential {
+        let mut attrs: BTreeMap<&str, String> = BTreeMap::new();
+        for line in input.lines().take_while(|l| !l.trim().is_empty()) {
+            let mut kv = line.splitn(2, '=');
+            if let (Some(k), Some(v)) = (kv.next(), kv.next()) {
+                attrs.insert(k.trim(), v.trim_end().to_string());
+            }
+        }
+        Credential {
+            protocol: attrs.remove("protocol"),
+            host: attrs.remove("host"),
+            path: attrs.remove("path"),
+            username: attrs.remove("username"),
+            password: attrs.remove("password"),
+        }
+    }
+
+    /// The answer to send back to git
+    pub fn to_output(&self) -> String {
+        let mut out = String::new();
+        let attrs = [("protocol", &self.protocol), ("host", &self.host),
+                     ("path", &self.path), ("username", &self.username),
+                     ("password", &self.password)];
+        for &(key, value) in attrs.iter() {
+            if let Some(ref v) = *value {
+                out.push_str(&format!("{}={}\n", key, v));
+            }
+        }
+        out
+    }
+
+    /// Fill in the username and password from the stored API token of
+    /// the requested host. When the path starts with an enterprise (as
+    /// in `/<ent>/<org>/<project>`) or a username is requested, only
+    /// the tokens for that enterprise and user are considered. Tokens
+    /// only go over https, a request without a protocol gets none.
+    pub fn fill_from(&self, tokens: &[StoredToken]) -> Option<Credential> {
+        if self.protocol.as_ref().map(|p| p != "https").unwrap_or(true) {
+            return None
+        }
+        let host = match self.host {
+            Some(ref h) => h,
+            None => return None
+        };
+        let ent = self.path.as_ref()
+            .and_then(|p| p.trim_start_matches('/').split('/').next().map(|e| e.to_string()))
+            .filter(|e| !e.is_empty());
+        let mut candidates = tokens.iter()
+            .filter(|t| &t.server == host)
+            .filter(|t| self.username.as_ref().map(|u| u == &t.user).unwrap_or(true));
+        let found = match ent {
+            Some(ref ent) => candidates.find(|t| &t.ent == ent),
+            None => candidates.next()
+        };
+        found.map(|t| Credential {
+            username: Some(t.user.clone()),
+            password: Some(t.token.clone()),
+            .. self.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use token::StoredToken;
+
+    fn token(server: &str, ent: &str, user: &str, token: &str) -> StoredToken {
+        StoredToken {
+            server: server.to_string(),
+            ent: ent.to_string(),
+            user: user.to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_and_output() {
+        let input = "protocol=https\nhost=automate.example.com\n\nignored=1\n";
+        let credential = Credential::parse(input);
+        assert_eq!(Some("https".to_string()), credential.protocol);
+        assert_eq!(Some("automate.example.com".to_string()), credential.host);
+        assert_eq!(None, credential.username);
+        assert_eq!("protocol=https\nhost=automate.example.com\n", credential.to_output());
+    }
+
+    #[test]
+    fn test_fill_from_stored_tokens() {
+        let tokens = vec![token("automate.example.com", "acme", "alice", "aaa"),
+                          token("automate.example.com", "chef", "bob", "bbb"),
+                          token("other.example.com", "acme", "alice", "ccc")];
+        let request = Credential::parse("protocol=https\nhost=automate.example.com\n");
+        let filled = request.fill_from(&tokens).unwrap();
+        assert_eq!(Some("alice".to_string()), filled.username);
+        assert_eq!(Some("aaa".to_string()), filled.password);
+
+        let by_ent = Credential::parse("protocol=https\nhost=automate.example.com\n\
+                                        path=chef/org/project\n");
+        assert_eq!(Some("bbb".to_string()), by_ent.fill_from(&tokens).unwrap().password);
+
+        let by_user = Credential::parse("protocol=https\nhost=automate.example.com\n\
+                                         username=bob\n");
+        assert_eq!(Some("bbb".to_string()), by_user.fill_from(&tokens).unwrap().password);
+
+        let unknown = Credential::parse("protocol=https\nhost=github.com\n");
+        assert_eq!(None, unknown.fill_from(&tokens));
+        let http = Credential::parse("protocol=http\nhost=automate.example.com\n");
+        assert_eq!(None, http.fill_from(&tokens));
+        let no_protocol = Credential::parse("host=automate.example.com\n");
+        assert_eq!(None, no_protocol.fill_from(&tokens));
+    }
+}
@@ -0,0 +1,109 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// Throwaway git hosting
+//
+// Functional phases of build cookbooks, and the tests of this crate,
+// often need a remote to clone from and push to. A fixture repo is a
+// bare repository in a directory of the workspace along with a clone of
+// it, the same way `delivery fixture repo create` sets them up, so
+// nobody has to script it again.
+
+use std::path::{Path, PathBuf};
+use git::{git_command, git_commit_in, CommitIdentity};
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+use utils::{mkdir_recursive, path_to_string};
+
+/// A bare repository and a clone of it that tracks its `branch`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixtureRepo {
+    pub bare: PathBuf,
+    pub clone: PathBuf,
+    pub branch: String,
+}
+
+/// Create `<dir>/<name>.git`, bare, and its clone `<dir>/<name>` with
+/// an empty initial commit on `branch` already pushed, authored by
+/// `identity`.
+pub fn create(dir: &Path, name: &str, branch: &str,
+              identity: &CommitIdentity) -> DeliveryResult<FixtureRepo> {
+    let repo = FixtureRepo {
+        bare: dir.join(format!("{}.git", name)),
+        clone: dir.join(name),
+        branch: branch.to_string(),
+    };
+    for path in &[&repo.bare, &repo.clone] {
+        if path.exists() {
+            let msg = format!("{} already exists", path.display());
+            return Err(DeliveryError::throw(Kind::GitSetupFailed, Some(msg)))
+        }
+    }
+    try!(mkdir_recursive(&repo.bare));
+    let bare = try!(path_to_string(&repo.bare));
+    let clone = try!(path_to_string(&repo.clone));
+    let head = format!("refs/heads/{}", branch);
+    try!(git_command(&["init", "--bare", "--quiet", &bare], &repo.bare));
+    try!(git_command(&["symbolic-ref", "HEAD", &head], &repo.bare));
+    try!(git_command(&["clone", "--quiet", &bare, &clone], dir));
+    try!(git_command(&["checkout", "--quiet", "-b", branch], &repo.clone));
+    let identity_args = identity.git_args();
+    let mut args: Vec<&str> = identity_args.iter().map(|a| a.as_str()).collect();
+    args.extend_from_slice(&["commit", "--quiet", "--allow-empty", "-m", "Initial commit"]);
+    try!(git_command(&args, &repo.clone));
+    try!(git_command(&["push", "--quiet", "--set-upstream", "origin", branch], &repo.clone));
+    Ok(repo)
+}
+
+/// Commit everything in the clone of `repo` and push it
+pub fn commit_and_push(repo: &FixtureRepo, message: &str,
+                       identity: &CommitIdentity) -> DeliveryResult<()> {
+    try!(git_command(&["add", "--all"], &repo.clone));
+    try!(git_commit_in(message, identity, &repo.clone));
+    try!(git_command(&["push", "--quiet", "origin", &repo.branch], &repo.clone));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempdir::TempDir;
+    use git::{git_command, CommitIdentity};
+
+    fn identity() -> CommitIdentity {
+        CommitIdentity {
+            name: Some("Fixture".to_string()),
+            email: Some("fixture@example.com".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_create_and_push() {
+        let tmp = TempDir::new("fixture").unwrap();
+        let repo = create(tmp.path(), "scratch", "main", &identity()).unwrap();
+        assert_eq!(tmp.path().join("scratch.git"), repo.bare);
+        assert_eq!(tmp.path().join("scratch"), repo.clone);
+
+        File::create(repo.clone.join("README.md")).unwrap().write_all(b"scratch").unwrap();
+        commit_and_push(&repo, "Add a README", &identity()).unwrap();
+        let log = git_command(&["log", "--format=%s", "main"], &repo.bare).unwrap();
+        assert_eq!("Add a README\nInitial commit", log.stdout.trim());
+
+        assert!(create(tmp.path(), "scratch", "main", &identity()).is_err());
+    }
+}
@@ -17,18 +17,28 @@
 
 pub use errors;
 
+pub mod credential;
+pub mod refs;
+pub mod fixture;
+
 use std::process::Command;
-use utils::say::{say, sayln, Spinner};
+use utils::say::{say, sayln, out, Spinner};
+use utils::json_output;
 use utils::path_ext::{is_dir};
-use utils::{cmd_success_or_err, find_command};
+use utils::{cmd_success_or_err, find_command, path_to_string, plain_path, read_file,
+            GIT_SETTINGS};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
 use errors::{DeliveryError, Kind};
 use std::env;
 use std::path::{Path, PathBuf};
 use std::convert::AsRef;
 use std::error;
 use regex::Regex;
+use tempdir::TempDir;
 use project::project_path;
 use types::DeliveryResult;
+use config::Config;
 
 fn cwd() -> PathBuf {
     env::current_dir().unwrap()
@@ -89,22 +99,33 @@ pub struct GitResult {
 // Where P: AsRef<Path> == Any type that implements the AsRef<Path> trait
 pub fn git_command<P>(args: &[&str], c: &P) -> Result<GitResult, DeliveryError>
         where P: AsRef<Path> + ?Sized {
-    let cwd = c.as_ref();
     let spinner = Spinner::start();
+    let result = git_command_without_spinner(args, c);
+    spinner.stop();
+    result
+}
+
+/// Same as `git_command`, for callers running several git commands at
+/// the same time under a spinner of their own
+pub fn git_command_without_spinner<P>(args: &[&str], c: &P) -> Result<GitResult, DeliveryError>
+        where P: AsRef<Path> + ?Sized {
+    let cwd = c.as_ref();
     let command_path = match find_command("git") {
         Some(path) => path,
         None => return Err(DeliveryError{ kind: Kind::FailedToExecute, detail: Some("git executable not found".to_owned())}),
     };
     let mut command = Command::new(command_path);
+    for setting in GIT_SETTINGS {
+        command.arg("-c").arg(setting);
+    }
     command.args(args);
-    command.current_dir(cwd);
+    command.current_dir(plain_path(cwd));
     debug!("Git command: {:?}", command);
     let output = match command.output() {
         Ok(o) => o,
-        Err(e) => { spinner.stop(); return Err(DeliveryError{ kind: Kind::FailedToExecute, detail: Some(format!("failed to execute git: {}", error::Error::description(&e)))}) },
+        Err(e) => return Err(DeliveryError{ kind: Kind::FailedToExecute, detail: Some(format!("failed to execute git: {}", error::Error::description(&e)))}),
     };
     debug!("Git exited: {}", output.status);
-    spinner.stop();
     cmd_success_or_err(&output, Kind::GitFailed)?;
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     debug!("Git stdout: {}", stdout);
@@ -115,11 +136,18 @@ pub fn git_command<P>(args: &[&str], c: &P) -> Result<GitResult, DeliveryError>
 
 pub fn git_push_review(branch: &str,
                        target: &str) -> Result<ReviewResult, DeliveryError> {
+    git_push_review_to_topic(branch, target, branch)
+}
+
+/// Push `branch` as a new patchset of the change whose feature
+/// branch is `topic`, which may differ from the local branch name.
+pub fn git_push_review_to_topic(branch: &str, target: &str,
+                                topic: &str) -> Result<ReviewResult, DeliveryError> {
     let gitr = try!(git_command(&["push",
                                   "--porcelain", "--progress",
                                   "--verbose", "delivery",
                                   &format!("{}:_for/{}/{}",
-                                           branch, target, branch)],
+                                           branch, target, topic)],
                                 &cwd()));
     parse_git_push_output(&gitr.stdout, &gitr.stderr)
 }
@@ -249,6 +277,137 @@ fn parse_line_from_remote(line: &str, review_result: &mut ReviewResult) -> () {
     }
 }
 
+/// Whether `path` is in a bare repository, one without a worktree
+pub fn is_bare_repository<P>(path: P) -> bool
+        where P: AsRef<Path> {
+    match git_command(&["rev-parse", "--is-bare-repository"], path.as_ref()) {
+        Ok(result) => result.stdout.trim() == "true",
+        Err(_) => false
+    }
+}
+
+/// Whether `path` is in a shallow clone, one missing part of its history
+///
+/// Git records the commits where the history was cut in `shallow`
+/// inside the git dir, which is what older gits check too.
+pub fn is_shallow_repository<P>(path: P) -> bool
+        where P: AsRef<Path> {
+    match git_command(&["rev-parse", "--git-dir"], path.as_ref()) {
+        Ok(result) => path.as_ref().join(result.stdout.trim()).join("shallow").exists(),
+        Err(_) => false
+    }
+}
+
+/// Keep `pattern` out of `git status` in the repository at `path`, through
+/// its `info/exclude` rather than a `.gitignore` that would need committing
+pub fn exclude<P>(path: P, pattern: &str) -> DeliveryResult<()>
+        where P: AsRef<Path> {
+    let git_dir = try!(git_command(&["rev-parse", "--git-dir"], path.as_ref()));
+    let info = path.as_ref().join(git_dir.stdout.trim()).join("info");
+    try!(fs::create_dir_all(&info));
+    let exclude = info.join("exclude");
+    let current = if exclude.exists() { try!(read_file(&exclude)) } else { String::new() };
+    if current.lines().any(|l| l.trim() == pattern) {
+        return Ok(())
+    }
+    let mut f = try!(OpenOptions::new().create(true).append(true).open(&exclude));
+    if !current.is_empty() && !current.ends_with('\n') {
+        try!(f.write_all(b"\n"));
+    }
+    try!(f.write_all(format!("{}\n", pattern).as_bytes()));
+    Ok(())
+}
+
+/// The `core.autocrlf` setting of the repository at `path`, empty when unset
+pub fn autocrlf<P>(path: P) -> String
+        where P: AsRef<Path> {
+    git_command(&["config", "--get", "core.autocrlf"], path.as_ref())
+        .map(|g| g.stdout.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Whether the repository at `path` has a local branch named `branch`
+pub fn branch_exists<P>(branch: &str, path: P) -> bool
+        where P: AsRef<Path> {
+    let branch_ref = format!("refs/heads/{}", branch);
+    git_command(&["rev-parse", "--verify", "--quiet", &branch_ref], path.as_ref()).is_ok()
+}
+
+/// The commits of `head` that are not on `base`, oldest first
+pub fn commits_between<P>(base: &str, head: &str, path: P) -> DeliveryResult<Vec<String>>
+        where P: AsRef<Path> {
+    let range = format!("{}..{}", base, head);
+    let result = try!(git_command(&["rev-list", "--reverse", "--no-merges", &range],
+                                  path.as_ref()));
+    Ok(result.stdout.lines().map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect())
+}
+
+/// How many commits `local` has that `other` has not, and the other
+/// way round
+pub fn ahead_behind<P>(local: &str, other: &str, path: P) -> DeliveryResult<(usize, usize)>
+        where P: AsRef<Path> {
+    let range = format!("{}...{}", local, other);
+    let result = try!(git_command(&["rev-list", "--left-right", "--count", &range],
+                                  path.as_ref()));
+    let counts: Vec<usize> = result.stdout.split_whitespace()
+        .filter_map(|c| c.parse().ok())
+        .collect();
+    if counts.len() != 2 {
+        let msg = format!("Unexpected rev-list output: {}", result.stdout);
+        return Err(DeliveryError::throw(Kind::GitFailed, Some(msg)))
+    }
+    Ok((counts[0], counts[1]))
+}
+
+/// The commits of `rev` without parents, the roots of its history
+pub fn root_commits<P>(rev: &str, path: P) -> DeliveryResult<Vec<String>>
+        where P: AsRef<Path> {
+    let result = try!(git_command(&["rev-list", "--max-parents=0", rev], path.as_ref()));
+    Ok(result.stdout.lines().map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect())
+}
+
+/// Fetch `pipeline` from the delivery remote to `refs/remotes/delivery/<pipeline>`
+pub fn fetch_pipeline<P>(pipeline: &str, path: P) -> DeliveryResult<String>
+        where P: AsRef<Path> {
+    let remote_ref = format!("refs/remotes/delivery/{}", pipeline);
+    let refspec = format!("+refs/heads/{}:{}", pipeline, remote_ref);
+    try!(git_command(&["fetch", "delivery", &refspec], path.as_ref()));
+    Ok(remote_ref)
+}
+
+/// Point `branch` at `onto` with `commits` cherry-picked on top of it.
+/// The picking happens in a scratch worktree, so the checkout at `path`
+/// is left alone; on a conflict the cherry-pick is aborted and the
+/// branch deleted.
+pub fn cherry_pick_to_branch<P>(branch: &str, onto: &str, commits: &[String],
+                                path: P) -> DeliveryResult<()>
+        where P: AsRef<Path> {
+    let scratch = try!(TempDir::new("delivery-cherry-pick"));
+    let worktree = try!(path_to_string(scratch.path().join("worktree")));
+    try!(git_command(&["worktree", "add", "-B", branch, &worktree, onto], path.as_ref()));
+    let mut args = vec!["cherry-pick"];
+    args.extend(commits.iter().map(|c| c.as_str()));
+    let picked = git_command(&args, &worktree);
+    if picked.is_err() {
+        if let Err(e) = git_command(&["cherry-pick", "--abort"], &worktree) {
+            debug!("Unable to abort the cherry-pick onto {}: {:?}", branch, e);
+        }
+    }
+    try!(git_command(&["worktree", "remove", "--force", &worktree], path.as_ref()));
+    if picked.is_err() {
+        try!(git_command(&["branch", "-D", branch], path.as_ref()));
+    }
+    picked.map(|_| ())
+}
+
+/// Fetch the history a shallow clone is missing, from the remote it
+/// was cloned from
+pub fn unshallow<P>(path: P) -> DeliveryResult<()>
+        where P: AsRef<Path> {
+    try!(git_command(&["fetch", "--unshallow"], path.as_ref()));
+    Ok(())
+}
+
 pub fn check_repo_init(path: &PathBuf) -> Result<(), DeliveryError> {
     say("white", "Is ");
     say("magenta", &format!("{} ", path.display()));
@@ -291,6 +450,133 @@ pub fn delivery_remote_from_repo<P>(path: P) -> DeliveryResult<String>
         .or(Ok(String::from("")))
 }
 
+/// A git remote and the URL it fetches from
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitRemote {
+    pub name: String,
+    pub url: String,
+}
+
+/// The location a remote URL points at: the host, the owner (GitHub
+/// organization or Bitbucket project key) and the repository name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteLocation {
+    pub host: String,
+    pub owner: String,
+    pub repo: String,
+}
+
+// List the remotes configured in the repository at `path`
+pub fn git_remotes<P>(path: P) -> DeliveryResult<Vec<GitRemote>>
+        where P: AsRef<Path> {
+    let gitr = try!(git_command(&["remote", "-v"], path.as_ref()));
+    Ok(parse_git_remotes(&gitr.stdout))
+}
+
+// Parse the output of `git remote -v`, keeping the fetch URLs
+pub fn parse_git_remotes(output: &str) -> Vec<GitRemote> {
+    output.lines().filter_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() == 3 && fields[2] == "(fetch)" {
+            Some(GitRemote { name: fields[0].to_string(), url: fields[1].to_string() })
+        } else {
+            None
+        }
+    }).collect()
+}
+
+// Figure out where a remote URL points to. Understands the URL forms
+// GitHub and Bitbucket hand out:
+//
+//   git@github.com:org/repo.git
+//   https://github.com/org/repo
+//   ssh://git@bitbucket.example.com:7999/PROJ/repo.git
+//   https://bitbucket.example.com/scm/PROJ/repo.git
+pub fn parse_remote_url(url: &str) -> Option<RemoteLocation> {
+    let url_re = Regex::new(r"^[A-Za-z+]+://(?:[^@/]+@)?([^/:]+)(?::\d+)?/(.+)$").unwrap();
+    let scp_re = Regex::new(r"^(?:[^@/]+@)?([^:/]+):(.+)$").unwrap();
+    let caps = match url_re.captures(url).or_else(|| scp_re.captures(url)) {
+        Some(c) => c,
+        None => return None
+    };
+    let host = caps.at(1).unwrap_or("");
+    let path = caps.at(2).unwrap_or("").trim_right_matches('/');
+    let path = path.trim_right_matches(".git");
+    let mut segments = path.rsplit('/');
+    match (segments.next(), segments.next()) {
+        (Some(repo), Some(owner)) if !repo.is_empty() && !owner.is_empty() => {
+            Some(RemoteLocation { host: host.to_string(), owner: owner.to_string(),
+                                  repo: repo.to_string() })
+        },
+        _ => None
+    }
+}
+
+/// The parts of a delivery remote URL,
+/// `ssh://user@ent@host:port/ent/org/project`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeliveryRemote {
+    pub user: String,
+    pub server: String,
+    pub enterprise: String,
+    pub organization: String,
+    pub project: String,
+}
+
+pub fn parse_delivery_remote(url: &str) -> Option<DeliveryRemote> {
+    let re = Regex::new(r"^ssh://([^@/]+)@[^@/]+@([^/]+)/([^/]+)/([^/]+)/([^/]+?)/?$").unwrap();
+    re.captures(url).map(|caps| DeliveryRemote {
+        user: caps.at(1).unwrap_or("").to_string(),
+        server: caps.at(2).unwrap_or("").to_string(),
+        enterprise: caps.at(3).unwrap_or("").to_string(),
+        organization: caps.at(4).unwrap_or("").to_string(),
+        project: caps.at(5).unwrap_or("").to_string(),
+    })
+}
+
+/// Whether a change merges cleanly into its pipeline
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeStatus {
+    Clean,
+    Conflicts(Vec<String>),
+}
+
+// Simulate merging `head` into `target` in the repository at `path`,
+// without touching the working tree or the index. Needs git >= 2.38
+// for `merge-tree --write-tree`; older versions return an error.
+pub fn simulate_merge<P>(path: P, target: &str, head: &str) -> DeliveryResult<MergeStatus>
+        where P: AsRef<Path> {
+    let command_path = match find_command("git") {
+        Some(path) => path,
+        None => return Err(DeliveryError{ kind: Kind::FailedToExecute, detail: Some("git executable not found".to_owned())}),
+    };
+    let output = try!(Command::new(command_path)
+        .args(&["merge-tree", "--write-tree", "--name-only", "--no-messages", target, head])
+        .current_dir(path.as_ref())
+        .output());
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    debug!("git merge-tree exited {}: {}", output.status, stdout);
+    // merge-tree exits with 1 when the merge has conflicts, anything
+    // else means it could not tell.
+    match output.status.code() {
+        Some(0) => Ok(MergeStatus::Clean),
+        Some(1) => Ok(parse_merge_tree(&stdout)),
+        _ => Err(DeliveryError{ kind: Kind::GitFailed,
+                                detail: Some(String::from_utf8_lossy(&output.stderr).to_string()) })
+    }
+}
+
+// Parse the output of a conflicting `git merge-tree --name-only`: the
+// tree it wrote followed by the conflicted files.
+pub fn parse_merge_tree(output: &str) -> MergeStatus {
+    let files = output.lines().skip(1)
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| l.to_string())
+        .collect();
+    MergeStatus::Conflicts(files)
+}
+
 // Update the (Git) delivery remote
 //
 // Try to add the delivery remote and if it fails adding it, try to remove
@@ -322,25 +608,88 @@ pub fn checkout_branch_name(change: &str, patchset: &str) -> String {
     }
 }
 
-pub fn diff(change: &str, patchset: &str, pipeline: &str, local: &bool) -> Result<(), DeliveryError> {
-    try!(git_command(&["fetch", "delivery"], &cwd()));
-    let mut first_branch = format!("delivery/{}", pipeline);
-    if *local {
-        first_branch = String::from("HEAD");
+/// How `diff` presents the changes, passed through to `git diff`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffMode {
+    Full,
+    Stat,
+    NameOnly,
+    WordDiff,
+}
+
+impl DiffMode {
+    pub fn git_arg(&self) -> Option<&'static str> {
+        match *self {
+            DiffMode::Full => None,
+            DiffMode::Stat => Some("--stat"),
+            DiffMode::NameOnly => Some("--name-only"),
+            DiffMode::WordDiff => Some("--word-diff"),
+        }
     }
-    let diff = try!(git_command(&["diff", "--color=always", &first_branch, &format!("delivery/_reviews/{}/{}/{}", pipeline, change, patchset)], &cwd()));
-    say("white", "\n");
-    sayln("white", &diff.stdout);
+}
+
+/// Diff a patchset against the pipeline, the local HEAD or, with
+/// `interdiff`, another patchset of the change. With `json` the diff
+/// goes into the JSON document, uncolored, instead of the terminal.
+pub fn diff(change: &str, patchset: &str, pipeline: &str, local: &bool,
+            interdiff: &str, mode: DiffMode, json: bool) -> Result<(), DeliveryError> {
+    let mut refspecs = vec![refs::patchset_refspec(pipeline, change, patchset)];
+    let first_branch = if !interdiff.is_empty() {
+        refspecs.push(refs::patchset_refspec(pipeline, change, interdiff));
+        refs::patchset_ref(pipeline, change, interdiff)
+    } else if *local {
+        String::from("HEAD")
+    } else {
+        refspecs.push(refs::pipeline_refspec(pipeline));
+        format!("delivery/{}", pipeline)
+    };
+    try!(refs::fetch(refspecs, &cwd()));
+    let color = if json { "--color=never" } else { "--color=always" };
+    let last_branch = refs::patchset_ref(pipeline, change, patchset);
+    let mut args = vec!["diff", color];
+    args.extend(mode.git_arg());
+    args.push(&first_branch);
+    args.push(&last_branch);
+    let diff = try!(git_command(&args, &cwd()));
+    if json {
+        json_output::set("diff", &diff.stdout);
+        return Ok(())
+    }
+    if mode != DiffMode::NameOnly {
+        out("white", "\n");
+    }
+    out("white", &diff.stdout);
     Ok(())
 }
 
 pub fn clone(project: &str, git_url: &str) -> Result<(), DeliveryError> {
-    try!(git_command(&["clone", git_url, project], &cwd()));
+    clone_with_config(project, git_url, &[])
+}
+
+/// Clone with `key=value` settings given to git with `-c`,
+/// i.e. `http.sslCAInfo=/etc/ssl/internal-ca.pem`
+pub fn clone_with_config(project: &str, git_url: &str,
+                         settings: &[String]) -> Result<(), DeliveryError> {
+    let mut args = vec![];
+    for setting in settings {
+        args.push("-c");
+        args.push(setting);
+    }
+    args.push("clone");
+    // Keep the platform settings in the clone for the git commands
+    // users run in it themselves
+    for setting in GIT_SETTINGS {
+        args.push("--config");
+        args.push(setting);
+    }
+    args.extend_from_slice(&[git_url, project]);
+    try!(git_command(&args, &cwd()));
     Ok(())
 }
 
 pub fn checkout_review(change: &str, patchset: &str, pipeline: &str) -> Result<(), DeliveryError> {
-    try!(git_command(&["fetch", "delivery"], &cwd()));
+    try!(refs::fetch(vec![refs::patchset_refspec(pipeline, change, patchset),
+                          refs::tracking_refspec(pipeline, change, patchset)], &cwd()));
     let branchname = checkout_branch_name(change, patchset);
     let result = git_command(&["branch", "--track", &branchname, &format!("delivery/_reviews/{}/{}/{}", pipeline, change, patchset)], &cwd());
     match result {
@@ -451,13 +800,67 @@ pub fn git_push(pipeline: &str) -> Result<(), DeliveryError> {
     }
 }
 
+/// The identity to author the commits the cli makes with, from the
+/// `commit_user_name` and `commit_email` of the cli.toml. Whatever is
+/// not set falls back to the identity configured for the repository.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommitIdentity {
+    pub name: Option<String>,
+    pub email: Option<String>,
+}
+
+impl CommitIdentity {
+    pub fn from_config(config: &Config) -> CommitIdentity {
+        CommitIdentity {
+            name: config.commit_user_name().ok(),
+            email: config.commit_email().ok(),
+        }
+    }
+
+    // The `-c` options that set this identity for a single git command
+    fn git_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(ref name) = self.name {
+            args.push("-c".to_string());
+            args.push(format!("user.name={}", name));
+        }
+        if let Some(ref email) = self.email {
+            args.push("-c".to_string());
+            args.push(format!("user.email={}", email));
+        }
+        args
+    }
+}
+
 // Commit content to local repo
 //
 // This fun will commit the changes you have loaded in the current repo,
 // it will also detect if the commit failed and transform the error to a
 // more specific one. (Ex. If we try to commit when nothing has changed)
-pub fn git_commit(message: &str) -> Result<(), DeliveryError> {
-    match git_command(&["commit", "-m", message], &try!(project_path())) {
+pub fn git_commit(message: &str, identity: &CommitIdentity) -> Result<(), DeliveryError> {
+    git_commit_in(message, identity, &try!(project_path()))
+}
+
+// Commit content to the repo at `path` as `identity`
+pub fn git_commit_in<P>(message: &str, identity: &CommitIdentity,
+                        path: P) -> Result<(), DeliveryError>
+        where P: AsRef<Path> {
+    git_commit_paths_in(message, identity, &[], path)
+}
+
+// Commit only `paths` to the repo at `path` as `identity`, whatever
+// else is staged; every staged change when `paths` is empty
+pub fn git_commit_paths_in<P>(message: &str, identity: &CommitIdentity, paths: &[&str],
+                              path: P) -> Result<(), DeliveryError>
+        where P: AsRef<Path> {
+    let identity_args = identity.git_args();
+    let mut args: Vec<&str> = identity_args.iter().map(|a| a.as_str()).collect();
+    args.extend_from_slice(&["commit", "-m", message]);
+    if !paths.is_empty() {
+        args.push("--");
+        args.extend_from_slice(paths);
+    }
+    match git_command(&args, path.as_ref()) {
         Err(DeliveryError{ kind, detail: Some(output) }) => {
             if output.contains("nothing to commit") {
               return Err(DeliveryError{ kind: Kind::EmptyGitCommit, detail: None });
@@ -470,12 +873,31 @@ pub fn git_commit(message: &str) -> Result<(), DeliveryError> {
     }
 }
 
+// Merge `rev`, which shares no history with the checked out branch,
+// into it as `identity`. A merge that fails is aborted, leaving the
+// branch as it was.
+pub fn git_merge_unrelated_in<P>(rev: &str, identity: &CommitIdentity,
+                                 path: P) -> DeliveryResult<()>
+        where P: AsRef<Path> {
+    let identity_args = identity.git_args();
+    let mut args: Vec<&str> = identity_args.iter().map(|a| a.as_str()).collect();
+    args.extend_from_slice(&["merge", "--allow-unrelated-histories", "--no-edit", rev]);
+    if let Err(e) = git_command(&args, path.as_ref()) {
+        if let Err(abort) = git_command(&["merge", "--abort"], path.as_ref()) {
+            debug!("Unable to abort the merge of {}: {:?}", rev, abort);
+        }
+        return Err(e)
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempdir::TempDir;
     use std::path::PathBuf;
-    use std::fs::DirBuilder;
+    use std::fs::{DirBuilder, File};
+    use std::io::Write;
 
     #[test]
     fn test_check_repo_init_with_invalid_path() {
@@ -525,6 +947,95 @@ mod tests {
         assert_eq!(String::from(""), remote_url.unwrap());
     }
 
+    #[test]
+    fn test_exclude() {
+        let tempdir = TempDir::new("repo").ok().expect("Temp repo dir failed");
+        let path = tempdir.path();
+        assert!(git_command(&["init"], path).is_ok());
+        assert!(exclude(path, "/.delivery/init-summary.json").is_ok());
+        assert!(exclude(path, "/.delivery/init-summary.json").is_ok());
+        DirBuilder::new().create(path.join(".delivery")).unwrap();
+        File::create(path.join(".delivery/init-summary.json")).unwrap();
+        let status = git_command(&["status", "--porcelain", "--untracked-files=all"], path)
+            .unwrap();
+        assert_eq!("", status.stdout.trim());
+        let excluded = read_file(&path.join(".git/info/exclude")).unwrap();
+        assert_eq!(1, excluded.lines().filter(|l| *l == "/.delivery/init-summary.json").count());
+    }
+
+    #[test]
+    fn test_is_shallow_repository() {
+        let tempdir = TempDir::new("repo").ok().expect("Temp repo dir failed");
+        let path = tempdir.path();
+        assert!(git_command(&["init"], path).is_ok());
+        assert!(!is_shallow_repository(path));
+        File::create(path.join(".git/shallow")).unwrap()
+            .write_all(b"3e1f0a4b0c1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f\n").unwrap();
+        assert!(is_shallow_repository(path));
+    }
+
+    #[test]
+    fn test_parse_merge_tree() {
+        let output = "3e1f0a4b0c1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f\nREADME.md\nsrc/lib.rs\n";
+        assert_eq!(MergeStatus::Conflicts(vec!["README.md".to_string(),
+                                               "src/lib.rs".to_string()]),
+                   parse_merge_tree(output));
+    }
+
+    #[test]
+    fn test_simulate_merge() {
+        let tempdir = TempDir::new("repo").ok().expect("Temp repo dir failed");
+        let path = tempdir.path();
+        let commit = |file: &str, content: &str| {
+            File::create(path.join(file)).unwrap().write_all(content.as_bytes()).unwrap();
+            git_command(&["add", file], path).unwrap();
+            git_command(&["-c", "user.name=test", "-c", "user.email=test@example.com",
+                          "commit", "-m", file], path).unwrap();
+        };
+        git_command(&["init"], path).unwrap();
+        git_command(&["checkout", "-b", "master"], path).unwrap();
+        commit("a.txt", "one\n");
+        git_command(&["checkout", "-b", "clean"], path).unwrap();
+        commit("b.txt", "two\n");
+        git_command(&["checkout", "-b", "conflict", "master"], path).unwrap();
+        commit("a.txt", "three\n");
+        git_command(&["checkout", "master"], path).unwrap();
+        commit("a.txt", "four\n");
+        // Skip on git versions without `merge-tree --write-tree`
+        if let Ok(status) = simulate_merge(path, "master", "clean") {
+            assert_eq!(MergeStatus::Clean, status);
+            assert_eq!(MergeStatus::Conflicts(vec!["a.txt".to_string()]),
+                       simulate_merge(path, "master", "conflict").unwrap());
+        }
+    }
+
+    #[test]
+    fn test_commit_identity_git_args() {
+        assert!(CommitIdentity::default().git_args().is_empty());
+        let identity = CommitIdentity {
+            name: Some("Delivery Bot".to_string()),
+            email: Some("bot@example.com".to_string()),
+        };
+        assert_eq!(vec!["-c", "user.name=Delivery Bot", "-c", "user.email=bot@example.com"],
+                   identity.git_args());
+    }
+
+    #[test]
+    fn test_git_commit_in_as_identity() {
+        let tempdir = TempDir::new("repo").ok().expect("Temp repo dir failed");
+        let path = tempdir.path();
+        git_command(&["init"], path).unwrap();
+        File::create(path.join("a.txt")).unwrap();
+        git_command(&["add", "a.txt"], path).unwrap();
+        let identity = CommitIdentity {
+            name: Some("Delivery Bot".to_string()),
+            email: Some("bot@example.com".to_string()),
+        };
+        git_commit_in("Add a.txt", &identity, path).unwrap();
+        let author = git_command(&["log", "-1", "--format=%an <%ae>"], path).unwrap();
+        assert_eq!("Delivery Bot <bot@example.com>", author.stdout.trim());
+    }
+
     #[test]
     fn test_parse_line_from_remote() {
         test_parse_line_from_remote_with_eol("");
@@ -653,4 +1164,80 @@ mod tests {
             Ok(result) => assert_eq!(expected, result)
         }
     }
+
+    #[test]
+    fn test_parse_git_remotes() {
+        let output = "delivery\tssh://u@e@server:8989/e/o/p (fetch)\n\
+                      delivery\tssh://u@e@server:8989/e/o/p (push)\n\
+                      github\tgit@github.com:chef/delivery-cli.git (fetch)\n\
+                      github\tgit@github.com:chef/delivery-cli.git (push)\n";
+        let remotes = parse_git_remotes(output);
+        assert_eq!(2, remotes.len());
+        assert_eq!("github", remotes[1].name);
+        assert_eq!("git@github.com:chef/delivery-cli.git", remotes[1].url);
+    }
+
+    #[test]
+    fn test_parse_remote_url() {
+        let expect = Some(RemoteLocation { host: "github.com".to_string(),
+                                           owner: "chef".to_string(),
+                                           repo: "delivery-cli".to_string() });
+        assert_eq!(expect, parse_remote_url("git@github.com:chef/delivery-cli.git"));
+        assert_eq!(expect, parse_remote_url("https://github.com/chef/delivery-cli"));
+        assert_eq!(expect, parse_remote_url("https://user@github.com/chef/delivery-cli.git/"));
+        let bitbucket = parse_remote_url("https://bitbucket.example.com/scm/PROJ/repo.git").unwrap();
+        assert_eq!(("PROJ", "repo"), (bitbucket.owner.as_str(), bitbucket.repo.as_str()));
+        let bitbucket = parse_remote_url("ssh://git@bitbucket.example.com:7999/PROJ/repo.git").unwrap();
+        assert_eq!("bitbucket.example.com", bitbucket.host);
+        assert_eq!(None, parse_remote_url("/some/local/path"));
+    }
+
+    #[test]
+    fn test_parse_delivery_remote() {
+        let remote = parse_delivery_remote("ssh://link@hyrule@castle.com:8989/hyrule/heroes/sword")
+            .unwrap();
+        assert_eq!(DeliveryRemote { user: "link".to_string(),
+                                    server: "castle.com:8989".to_string(),
+                                    enterprise: "hyrule".to_string(),
+                                    organization: "heroes".to_string(),
+                                    project: "sword".to_string() }, remote);
+        assert_eq!(None, parse_delivery_remote("git@github.com:chef/delivery-cli.git"));
+    }
+
+    #[test]
+    fn test_cherry_pick_to_branch() {
+        let tempdir = TempDir::new("repo").ok().expect("Temp repo dir failed");
+        let path = tempdir.path();
+        let commit = |file: &str, content: &str| {
+            File::create(path.join(file)).unwrap().write_all(content.as_bytes()).unwrap();
+            git_command(&["add", file], path).unwrap();
+            git_command(&["commit", "-m", file], path).unwrap();
+        };
+        git_command(&["init"], path).unwrap();
+        git_command(&["config", "user.name", "test"], path).unwrap();
+        git_command(&["config", "user.email", "test@example.com"], path).unwrap();
+        git_command(&["checkout", "-b", "master"], path).unwrap();
+        commit("a.txt", "one\n");
+        git_command(&["branch", "release"], path).unwrap();
+        commit("a.txt", "two\n");
+        git_command(&["checkout", "-b", "fix"], path).unwrap();
+        commit("b.txt", "fix\n");
+
+        let commits = commits_between("master", "fix", path).unwrap();
+        assert_eq!(1, commits.len());
+        cherry_pick_to_branch("fix-for-release", "release", &commits, path).unwrap();
+        assert_eq!(1, commits_between("release", "fix-for-release", path).unwrap().len());
+        // The checkout stays on the change
+        let head = git_command(&["rev-parse", "--abbrev-ref", "HEAD"], path).unwrap();
+        assert_eq!("fix", head.stdout.trim());
+
+        commit("a.txt", "three\n");
+        let commits = commits_between("master", "fix", path).unwrap();
+        assert!(cherry_pick_to_branch("conflict", "release", &commits, path).is_err());
+        // Nothing of the failed pick is left behind
+        assert!(git_command(&["rev-parse", "--verify", "--quiet", "refs/heads/conflict"],
+                            path).is_err());
+        let worktrees = git_command(&["worktree", "list", "--porcelain"], path).unwrap();
+        assert_eq!(1, worktrees.stdout.lines().filter(|l| l.starts_with("worktree ")).count());
+    }
 }
@@ -0,0 +1,152 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// Patchset refs
+//
+// Diffing or checking out a patchset only needs a couple of refs from
+// the delivery remote, not the review branches of every change. Those
+// refs are fetched together and kept in an area of their own,
+// `refs/delivery/changes/<pipeline>/<change>/<patchset>`, that `delivery
+// refs prune` cleans up once the changes are gone from the server. It is
+// laid out like the review branches, `_reviews/<pipeline>/<change>/
+// <patchset>`, so the two compare as they are even when pipeline names
+// contain a `/`.
+
+use std::collections::HashSet;
+use std::path::Path;
+use git::git_command;
+use types::DeliveryResult;
+
+pub const CHANGES_REFS: &'static str = "refs/delivery/changes";
+
+const REVIEW_BRANCHES: &'static str = "refs/heads/_reviews";
+
+/// The local ref a patchset of a change is kept under
+pub fn patchset_ref(pipeline: &str, change: &str, patchset: &str) -> String {
+    format!("{}/{}/{}/{}", CHANGES_REFS, pipeline, change, patchset)
+}
+
+/// The branch of a patchset on the delivery remote
+pub fn review_branch(pipeline: &str, change: &str, patchset: &str) -> String {
+    format!("{}/{}/{}/{}", REVIEW_BRANCHES, pipeline, change, patchset)
+}
+
+/// Fetches a patchset into its local ref
+pub fn patchset_refspec(pipeline: &str, change: &str, patchset: &str) -> String {
+    format!("+{}:{}", review_branch(pipeline, change, patchset),
+            patchset_ref(pipeline, change, patchset))
+}
+
+/// Fetches a patchset into the remote-tracking branch that the branches
+/// of `delivery checkout` track
+pub fn tracking_refspec(pipeline: &str, change: &str, patchset: &str) -> String {
+    format!("+{}:refs/remotes/delivery/_reviews/{}/{}/{}",
+            review_branch(pipeline, change, patchset), pipeline, change, patchset)
+}
+
+/// Fetches a pipeline into its remote-tracking branch
+pub fn pipeline_refspec(pipeline: &str) -> String {
+    format!("+refs/heads/{}:refs/remotes/delivery/{}", pipeline, pipeline)
+}
+
+/// Fetch the refspecs from the delivery remote, all in one fetch so that
+/// git takes the locks of the repository once
+pub fn fetch<P: AsRef<Path>>(refspecs: Vec<String>, path: P) -> DeliveryResult<()> {
+    let mut args = vec!["fetch", "--quiet", "delivery"];
+    args.extend(refspecs.iter().map(|r| r.as_str()));
+    try!(git_command(&args, path.as_ref()));
+    Ok(())
+}
+
+/// The patchset refs kept in the repository
+pub fn local_patchset_refs<P: AsRef<Path>>(path: P) -> DeliveryResult<Vec<String>> {
+    let result = try!(git_command(&["for-each-ref", "--format=%(refname)", CHANGES_REFS],
+                                  path.as_ref()));
+    Ok(result.stdout.lines()
+       .map(|line| line.trim().to_string())
+       .filter(|line| !line.is_empty())
+       .collect())
+}
+
+/// The review branches on the delivery remote
+pub fn remote_review_branches<P: AsRef<Path>>(path: P) -> DeliveryResult<Vec<String>> {
+    let pattern = format!("{}/*", REVIEW_BRANCHES);
+    let result = try!(git_command(&["ls-remote", "delivery", &pattern], path.as_ref()));
+    Ok(result.stdout.lines()
+       .filter_map(|line| line.split_whitespace().nth(1))
+       .map(String::from)
+       .collect())
+}
+
+/// The patchset refs whose review branch is gone from the server,
+/// because the change was merged or abandoned
+pub fn stale_refs(local: &[String], review_branches: &[String]) -> Vec<String> {
+    // `_reviews/<pipeline>/<change>/<patchset>` on the server is kept
+    // as `changes/<pipeline>/<change>/<patchset>` locally
+    let prefix = format!("{}/", REVIEW_BRANCHES);
+    let live: HashSet<&str> = review_branches.iter()
+        .filter(|branch| branch.starts_with(&prefix))
+        .map(|branch| &branch[prefix.len()..])
+        .collect();
+    let prefix = format!("{}/", CHANGES_REFS);
+    local.iter()
+        .filter(|r| r.starts_with(&prefix) && !live.contains(&r[prefix.len()..]))
+        .cloned()
+        .collect()
+}
+
+/// Delete refs from the repository
+pub fn delete<P: AsRef<Path>>(refs: &[String], path: P) -> DeliveryResult<()> {
+    for r in refs {
+        try!(git_command(&["update-ref", "-d", r], path.as_ref()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refspecs() {
+        assert_eq!("refs/delivery/changes/master/foo/2", patchset_ref("master", "foo", "2"));
+        assert_eq!("+refs/heads/_reviews/master/foo/2:refs/delivery/changes/master/foo/2",
+                   patchset_refspec("master", "foo", "2"));
+        assert_eq!("+refs/heads/_reviews/master/foo/latest:\
+                    refs/remotes/delivery/_reviews/master/foo/latest",
+                   tracking_refspec("master", "foo", "latest"));
+        assert_eq!("+refs/heads/master:refs/remotes/delivery/master",
+                   pipeline_refspec("master"));
+    }
+
+    #[test]
+    fn test_stale_refs() {
+        let local = vec![patchset_ref("master", "foo", "1"),
+                         patchset_ref("master", "foo", "latest"),
+                         patchset_ref("release/1.x", "bar", "3"),
+                         patchset_ref("release", "1.x", "3"),
+                         patchset_ref("master", "gone", "1"),
+                         format!("{}/foo/1", CHANGES_REFS)];
+        let remote = vec![review_branch("master", "foo", "1"),
+                          review_branch("master", "foo", "latest"),
+                          review_branch("release/1.x", "bar", "3")];
+        assert_eq!(vec![patchset_ref("release", "1.x", "3"),
+                        patchset_ref("master", "gone", "1"),
+                        format!("{}/foo/1", CHANGES_REFS)],
+                   stale_refs(&local, &remote));
+        assert_eq!(local, stale_refs(&local, &[]));
+    }
+}
@@ -0,0 +1,133 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// API budget
+//
+// Every request the `APIClient` sends is counted, and the rate-limit
+// headers of the responses are remembered. Commands that hit the API
+// many times print `report().summary()` when they are done so users can
+// tune their `--concurrency` without tripping the server limits.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use hyper::header::Headers;
+
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+static RATE_LIMIT: Mutex<Option<RateLimit>> = Mutex::new(None);
+
+/// The rate limit as last reported by the server
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RateLimit {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    pub reset: Option<String>,
+    pub retry_after: Option<String>,
+}
+
+impl RateLimit {
+    /// Read the rate-limit headers, `header` returns the value of the
+    /// header with the provided name. Returns `None` when the server did
+    /// not send any of them.
+    pub fn from_headers<F>(header: F) -> Option<RateLimit>
+            where F: Fn(&str) -> Option<String> {
+        let limit = RateLimit {
+            limit: header("X-RateLimit-Limit").and_then(|v| v.trim().parse().ok()),
+            remaining: header("X-RateLimit-Remaining").and_then(|v| v.trim().parse().ok()),
+            reset: header("X-RateLimit-Reset"),
+            retry_after: header("Retry-After"),
+        };
+        if limit == RateLimit::default() { None } else { Some(limit) }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiBudget {
+    pub calls: usize,
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl ApiBudget {
+    pub fn summary(&self) -> String {
+        let mut summary = format!("API calls made: {}", self.calls);
+        if let Some(ref rl) = self.rate_limit {
+            match (rl.remaining, rl.limit) {
+                (Some(r), Some(l)) => summary.push_str(&format!(", rate limit: {}/{} remaining", r, l)),
+                (Some(r), None) => summary.push_str(&format!(", rate limit: {} remaining", r)),
+                _ => {}
+            }
+            if let Some(ref reset) = rl.reset {
+                summary.push_str(&format!(", resets at {}", reset));
+            }
+            if let Some(ref retry) = rl.retry_after {
+                summary.push_str(&format!(", throttled (retry after {})", retry));
+            }
+        }
+        summary
+    }
+}
+
+/// Account for a response received from the server
+pub fn record(headers: &Headers) {
+    CALLS.fetch_add(1, Ordering::SeqCst);
+    let header = |name: &str| {
+        headers.get_raw(name)
+            .and_then(|v| v.first())
+            .map(|v| String::from_utf8_lossy(v).into_owned())
+    };
+    if let Some(limit) = RateLimit::from_headers(header) {
+        if let Ok(mut last) = RATE_LIMIT.lock() {
+            *last = Some(limit);
+        }
+    }
+}
+
+/// What this run has used of the API so far
+pub fn report() -> ApiBudget {
+    ApiBudget {
+        calls: CALLS.load(Ordering::SeqCst),
+        rate_limit: RATE_LIMIT.lock().ok().and_then(|l| l.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_from_headers() {
+        let limit = RateLimit::from_headers(|name| match name {
+            "X-RateLimit-Limit" => Some("5000".to_string()),
+            "X-RateLimit-Remaining" => Some(" 4990".to_string()),
+            _ => None
+        }).unwrap();
+        assert_eq!(Some(5000), limit.limit);
+        assert_eq!(Some(4990), limit.remaining);
+        assert_eq!(None, RateLimit::from_headers(|_| None));
+    }
+
+    #[test]
+    fn test_budget_summary() {
+        let budget = ApiBudget { calls: 12, rate_limit: None };
+        assert_eq!("API calls made: 12", budget.summary());
+        let budget = ApiBudget {
+            calls: 12,
+            rate_limit: Some(RateLimit { limit: Some(100), remaining: Some(0),
+                                         reset: None, retry_after: Some("30".to_string()) })
+        };
+        assert_eq!("API calls made: 12, rate limit: 0/100 remaining, \
+                    throttled (retry after 30)", budget.summary());
+    }
+}
@@ -21,6 +21,7 @@ use hyper::status::StatusCode;
 use serde_json;
 
 use config::Config;
+use git::MergeStatus;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, PartialOrd)]
 pub struct Description {
@@ -84,16 +85,21 @@ pub fn get(config: &Config,
             Err(DeliveryError{ kind: Kind::AuthenticationFailed,
                                detail: Some(msg)})
         },
-        error_code @ _ => {
-            let msg = format!("API request returned {}",
-                              error_code);
-            let mut detail = String::new();
-            let e = match result.read_to_string(&mut detail) {
-                Ok(_) => Ok(detail),
-                Err(e) => Err(e)
-            };
-            Err(DeliveryError{ kind: Kind::ApiError(error_code, e),
-                               detail: Some(msg)})
+        _ => Err(APIClient::api_error(&mut result))
+    }
+}
+
+/// Fetch the description of a change of `proj` with an existing client
+pub fn description(client: &APIClient, org: &str, proj: &str,
+                   change: &str) -> Result<Description, DeliveryError> {
+    let path = format!("orgs/{}/projects/{}/changes/{}/description", org, proj, change);
+    debug!("description path: {}", path);
+    match try!(APIClient::parse_response(try!(client.get(&path)))) {
+        (_, Some(body)) => Description::parse_json(&body),
+        (code, None) => {
+            let msg = format!("The server returned no description for change {} ({})",
+                              change, code);
+            Err(DeliveryError::throw(Kind::ChangeNotFound, Some(msg)))
         }
     }
 }
@@ -116,15 +122,269 @@ pub fn set(config: &Config,
             Err(DeliveryError{ kind: Kind::AuthenticationFailed,
                                detail: Some(msg)})
         },
-        error_code @ _ => {
-            let msg = format!("API request returned {}", error_code);
-            let mut detail = String::new();
-            let e = result.read_to_string(&mut detail).and(Ok(detail));
-            Err(DeliveryError::throw(Kind::ApiError(error_code, e),Some(msg)))
-        }
+        _ => Err(APIClient::api_error(&mut result))
+    }
+}
+
+/// A change as listed by the project changes endpoint
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChangeSummary {
+    pub id: String,
+    pub topic: String,
+    pub target: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub sha: String,
+    #[serde(default)]
+    pub stage: String,
+    #[serde(default)]
+    pub stage_status: String,
+    #[serde(default)]
+    pub patchset_number: u64,
+    #[serde(default)]
+    pub approved_by: String,
+    #[serde(default)]
+    pub approved_at: String,
+    #[serde(default)]
+    pub submitted_at: String,
+}
+
+/// An open change that a new review would duplicate
+#[derive(Debug, PartialEq)]
+pub enum Duplicate<'a> {
+    /// A change already exists for this feature branch; pushing
+    /// again just adds a new patchset to it.
+    SameTopic(&'a ChangeSummary),
+    /// A change on a different feature branch already points
+    /// at the same head SHA.
+    SameSha(&'a ChangeSummary),
+}
+
+impl ChangeSummary {
+    pub fn parse_json_list(response: &str) -> Result<Vec<ChangeSummary>, DeliveryError> {
+        Ok(serde_json::from_str::<Vec<ChangeSummary>>(response)?)
+    }
+}
+
+/// Look for an open change matching the feature branch `topic` or the
+/// head `sha` we are about to submit. A match on the topic wins.
+pub fn find_duplicate<'a>(changes: &'a [ChangeSummary],
+                          topic: &str, sha: &str) -> Option<Duplicate<'a>> {
+    if let Some(c) = changes.iter().find(|c| c.topic == topic) {
+        return Some(Duplicate::SameTopic(c))
+    }
+    changes.iter()
+        .find(|c| !sha.is_empty() && c.sha == sha)
+        .map(|c| Duplicate::SameSha(c))
+}
+
+/// List the open changes of a project targeting `pipeline`
+pub fn open_changes(client: &APIClient, org: &str, proj: &str,
+                    pipeline: &str) -> Result<Vec<ChangeSummary>, DeliveryError> {
+    changes_in_state(client, org, proj, pipeline, "open")
+}
+
+/// List the changes of a project targeting `pipeline` that are in `state`
+/// (`open`, `approved`, `delivered`...)
+pub fn changes_in_state(client: &APIClient, org: &str, proj: &str, pipeline: &str,
+                        state: &str) -> Result<Vec<ChangeSummary>, DeliveryError> {
+    let path = format!("orgs/{}/projects/{}/changes?pipeline={}&state={}&limit=100",
+                       org, proj, pipeline, state);
+    debug!("{} changes path: {}", state, path);
+    let response = try!(client.get(&path));
+    match try!(APIClient::parse_response(response)) {
+        (_, Some(body)) => ChangeSummary::parse_json_list(&body),
+        (_, None) => Ok(Vec::new())
     }
 }
 
+/// List every change of a project targeting `pipeline` in `state`,
+/// following the server's pages rather than stopping at the first hundred
+pub fn all_changes_in_state(client: &APIClient, org: &str, proj: &str, pipeline: &str,
+                            state: &str) -> Result<Vec<ChangeSummary>, DeliveryError> {
+    let path = format!("orgs/{}/projects/{}/changes?pipeline={}&state={}&limit=100",
+                       org, proj, pipeline, state);
+    debug!("all {} changes path: {}", state, path);
+    match try!(paginate::get_all(client, &path)) {
+        (_, Some(body)) => ChangeSummary::parse_json_list(&body),
+        (_, None) => Ok(Vec::new())
+    }
+}
+
+/// Abandon a change; its feature branch stays on the server
+pub fn abandon(client: &APIClient, org: &str, proj: &str,
+               change: &str) -> Result<(), DeliveryError> {
+    let path = format!("orgs/{}/projects/{}/changes/{}", org, proj, change);
+    debug!("abandon path: {}", path);
+    let response = try!(client.delete(&path));
+    try!(APIClient::parse_response(response));
+    Ok(())
+}
+
+/// Run the current stage of a change again, or verify if it has none
+pub fn retry(client: &APIClient, org: &str, proj: &str,
+             change: &ChangeSummary) -> Result<(), DeliveryError> {
+    let stage = if change.stage.is_empty() { "verify" } else { &change.stage };
+    let path = format!("orgs/{}/projects/{}/changes/{}/trigger/{}",
+                       org, proj, change.id, stage);
+    debug!("retry path: {}", path);
+    let response = try!(client.post(&path, ""));
+    try!(APIClient::parse_response(response));
+    Ok(())
+}
+
+// What the server reports about merging a change into its pipeline
+#[derive(Deserialize, Debug)]
+struct Mergeability {
+    mergeable: bool,
+    #[serde(default)]
+    conflicts: Vec<String>,
+}
+
+/// Parse the body of the change `merge_status` endpoint
+pub fn parse_merge_status(response: &str) -> Result<MergeStatus, DeliveryError> {
+    let m = try!(serde_json::from_str::<Mergeability>(response));
+    if m.mergeable {
+        Ok(MergeStatus::Clean)
+    } else {
+        Ok(MergeStatus::Conflicts(m.conflicts))
+    }
+}
+
+/// Ask the server whether a change merges cleanly with the head of its
+/// pipeline. Returns `None` when the server does not compute it.
+pub fn merge_status(client: &APIClient, org: &str, proj: &str,
+                    change: &str) -> Result<Option<MergeStatus>, DeliveryError> {
+    let path = format!("orgs/{}/projects/{}/changes/{}/merge_status", org, proj, change);
+    debug!("merge status path: {}", path);
+    let response = try!(client.get(&path));
+    if let StatusCode::NotFound = response.status {
+        return Ok(None)
+    }
+    match try!(APIClient::parse_response(response)) {
+        (_, Some(body)) => parse_merge_status(&body).map(Some),
+        (_, None) => Ok(None)
+    }
+}
+
+/// A stage a change went or is going through, with its phases
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StageRun {
+    pub stage: String,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub phases: Vec<PhaseRun>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PhaseRun {
+    pub name: String,
+    #[serde(default)]
+    pub status: String,
+}
+
+/// The part of a change the change endpoint answers that watching it
+/// looks at: where it stands and the stages it ran so far
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ChangeProgress {
+    /// `open`, `merged`, `abandoned`, `superseded`...
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub approved_by: String,
+    #[serde(default)]
+    pub stages: Vec<StageRun>,
+}
+
+/// Parse the body of the change endpoint
+pub fn parse_progress(response: &str) -> Result<ChangeProgress, DeliveryError> {
+    Ok(try!(serde_json::from_str::<ChangeProgress>(response)))
+}
+
+/// Parse the stages out of the body of the change endpoint
+pub fn parse_stages(response: &str) -> Result<Vec<StageRun>, DeliveryError> {
+    Ok(try!(parse_progress(response)).stages)
+}
+
+/// The state of a change, the stages it ran so far and the status of
+/// their phases
+pub fn progress(client: &APIClient, org: &str, proj: &str,
+                change: &str) -> Result<ChangeProgress, DeliveryError> {
+    let path = format!("orgs/{}/projects/{}/changes/{}", org, proj, change);
+    debug!("change progress path: {}", path);
+    let response = try!(client.get(&path));
+    match try!(APIClient::parse_response(response)) {
+        (_, Some(body)) => parse_progress(&body),
+        (_, None) => Ok(ChangeProgress::default())
+    }
+}
+
+/// Deliver an approved change
+pub fn deliver(client: &APIClient, org: &str, proj: &str,
+               change: &str) -> Result<(), DeliveryError> {
+    let path = format!("orgs/{}/projects/{}/changes/{}/deliver", org, proj, change);
+    debug!("deliver path: {}", path);
+    let response = try!(client.post(&path, ""));
+    try!(APIClient::parse_response(response));
+    Ok(())
+}
+
+/// A delivery the server will make, within `at` and `until` when given
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScheduledDelivery {
+    pub change: String,
+    pub at: String,
+    #[serde(default)]
+    pub until: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ScheduledDeliveries {
+    #[serde(default)]
+    scheduled_deliveries: Vec<ScheduledDelivery>,
+}
+
+pub fn parse_scheduled_deliveries(response: &str) -> Result<Vec<ScheduledDelivery>, DeliveryError> {
+    Ok(try!(serde_json::from_str::<ScheduledDeliveries>(response)).scheduled_deliveries)
+}
+
+fn scheduled_delivery_path(org: &str, proj: &str, change: &str) -> String {
+    format!("orgs/{}/projects/{}/changes/{}/scheduled-delivery", org, proj, change)
+}
+
+/// Have the server deliver an approved change later, for servers that
+/// advertise `scheduled_delivery`
+pub fn schedule_delivery(client: &APIClient, org: &str, proj: &str,
+                         delivery: &ScheduledDelivery) -> Result<(), DeliveryError> {
+    let path = scheduled_delivery_path(org, proj, &delivery.change);
+    debug!("schedule delivery path: {}", path);
+    let payload = try!(serde_json::to_string(delivery));
+    let response = try!(client.post(&path, &payload));
+    try!(APIClient::parse_response(response));
+    Ok(())
+}
+
+/// The deliveries the server has scheduled for the project
+pub fn scheduled_deliveries(client: &APIClient, org: &str,
+                            proj: &str) -> Result<Vec<ScheduledDelivery>, DeliveryError> {
+    let path = format!("orgs/{}/projects/{}/scheduled-deliveries", org, proj);
+    let response = try!(client.get(&path));
+    match try!(APIClient::parse_response(response)) {
+        (_, Some(body)) => parse_scheduled_deliveries(&body),
+        (_, None) => Ok(Vec::new())
+    }
+}
+
+/// Cancel the delivery the server has scheduled for a change
+pub fn cancel_scheduled_delivery(client: &APIClient, org: &str, proj: &str,
+                                 change: &str) -> Result<(), DeliveryError> {
+    let response = try!(client.delete(&scheduled_delivery_path(org, proj, change)));
+    try!(APIClient::parse_response(response));
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -190,4 +450,74 @@ mod tests {
         assert_eq!(expect, desc);
     }
 
+    fn summary(id: &str, topic: &str, sha: &str) -> ChangeSummary {
+        ChangeSummary { id: id.to_string(), topic: topic.to_string(),
+                        target: "master".to_string(), title: String::new(),
+                        sha: sha.to_string(), stage: String::new(),
+                        stage_status: String::new(), patchset_number: 1,
+                        approved_by: String::new(), approved_at: String::new(),
+                        submitted_at: String::new() }
+    }
+
+    #[test]
+    fn change_summary_parse_json_list_test() {
+        let response = "[{\"id\":\"abc\",\"topic\":\"foo\",\"target\":\"master\",\
+                        \"title\":\"Foo\",\"state\":\"open\",\"stage\":\"verify\",\
+                        \"stage_status\":\"passed\",\"patchset_number\":2}]";
+        let changes = ChangeSummary::parse_json_list(response).unwrap();
+        assert_eq!(1, changes.len());
+        assert_eq!("foo", changes[0].topic);
+        assert_eq!("", changes[0].sha);
+        assert_eq!("verify", changes[0].stage);
+        assert_eq!("passed", changes[0].stage_status);
+        assert_eq!(2, changes[0].patchset_number);
+    }
+
+    #[test]
+    fn find_duplicate_test() {
+        let changes = vec![summary("1", "foo", "aaa"), summary("2", "bar", "bbb")];
+        assert_eq!(Some(Duplicate::SameTopic(&changes[1])),
+                   find_duplicate(&changes, "bar", "aaa"));
+        assert_eq!(Some(Duplicate::SameSha(&changes[0])),
+                   find_duplicate(&changes, "baz", "aaa"));
+        assert_eq!(None, find_duplicate(&changes, "baz", "ccc"));
+        assert_eq!(None, find_duplicate(&[summary("1", "foo", "")], "baz", ""));
+    }
+
+    #[test]
+    fn parse_merge_status_test() {
+        assert_eq!(MergeStatus::Clean, parse_merge_status("{\"mergeable\":true}").unwrap());
+        let conflicts = "{\"mergeable\":false,\"conflicts\":[\"README.md\"]}";
+        assert_eq!(MergeStatus::Conflicts(vec!["README.md".to_string()]),
+                   parse_merge_status(conflicts).unwrap());
+        assert!(parse_merge_status("{}").is_err());
+    }
+
+    #[test]
+    fn parse_stages_test() {
+        let response = r#"{"id":"abc","stages":[{"stage":"verify","status":"running",
+                           "phases":[{"name":"unit","status":"passed","run_details":[]},
+                                     {"name":"lint","status":"running"}]}]}"#;
+        let stages = parse_stages(response).unwrap();
+        assert_eq!(1, stages.len());
+        assert_eq!("running", stages[0].status);
+        assert_eq!(PhaseRun { name: "lint".to_string(), status: "running".to_string() },
+                   stages[0].phases[1]);
+        assert!(parse_stages("{\"id\":\"abc\"}").unwrap().is_empty());
+        let progress = parse_progress(r#"{"id":"abc","state":"open","approved_by":"alice"}"#);
+        assert_eq!("alice", progress.unwrap().approved_by);
+    }
+
+    #[test]
+    fn parse_scheduled_deliveries_test() {
+        let response = r#"{"scheduled_deliveries":[
+                           {"change":"c1","at":"2024-06-03T22:00Z","until":"2024-06-04T06:00Z"},
+                           {"change":"c2","at":"2024-06-01T22:00Z"}]}"#;
+        let deliveries = parse_scheduled_deliveries(response).unwrap();
+        assert_eq!(2, deliveries.len());
+        assert_eq!(Some("2024-06-04T06:00Z".to_string()), deliveries[0].until);
+        assert_eq!(None, deliveries[1].until);
+        assert!(parse_scheduled_deliveries("{}").unwrap().is_empty());
+    }
+
 }
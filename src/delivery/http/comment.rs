@@ -0,0 +1,153 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use http::APIClient;
+use serde_json;
+use types::{DeliveryResult, ChangeId};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NewComment {
+    pub content: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+impl NewComment {
+    pub fn new(content: &str) -> NewComment {
+        NewComment { content: content.to_string(), kind: "comment".to_string() }
+    }
+
+    pub fn to_json(&self) -> DeliveryResult<String> {
+        Ok(try!(serde_json::to_string(&self)))
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ThreadResolution {
+    pub resolved: bool,
+}
+
+/// The formal outcome of a review
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+    Approve,
+    RequestChanges,
+}
+
+impl Verdict {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Verdict::Approve => "approve",
+            Verdict::RequestChanges => "request_changes",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ReviewVerdict {
+    pub verdict: String,
+    pub message: String,
+}
+
+impl ReviewVerdict {
+    pub fn new(verdict: Verdict, message: &str) -> ReviewVerdict {
+        ReviewVerdict { verdict: verdict.as_str().to_string(), message: message.to_string() }
+    }
+
+    pub fn to_json(&self) -> DeliveryResult<String> {
+        Ok(try!(serde_json::to_string(&self)))
+    }
+}
+
+impl APIClient {
+    /// Post a top level comment on the latest patchset of a change
+    pub fn post_comment(&self, org: &str, proj: &str, change: &ChangeId,
+                        comment: &NewComment) -> DeliveryResult<()> {
+        let path = format!("orgs/{}/projects/{}/changes/{}/comments", org, proj, change);
+        debug!("comment path: {}", path);
+        let payload = try!(comment.to_json());
+        let response = try!(self.post(&path, &payload));
+        try!(APIClient::parse_response(response));
+        Ok(())
+    }
+
+    /// Mark the thread started by `comment_id` as resolved, or open it again
+    pub fn set_thread_resolved(&self, org: &str, proj: &str, change: &ChangeId,
+                               comment_id: &str, resolved: bool) -> DeliveryResult<()> {
+        let path = format!("orgs/{}/projects/{}/changes/{}/comments/{}",
+                           org, proj, change, comment_id);
+        let payload = try!(serde_json::to_string(&ThreadResolution { resolved: resolved }));
+        let response = try!(self.put(&path, &payload));
+        try!(APIClient::parse_response(response));
+        Ok(())
+    }
+
+    /// Cast a review verdict on the latest patchset of a change
+    pub fn post_review_verdict(&self, org: &str, proj: &str, change: &ChangeId,
+                               verdict: &ReviewVerdict) -> DeliveryResult<()> {
+        let path = format!("orgs/{}/projects/{}/changes/{}/reviews", org, proj, change);
+        let payload = try!(verdict.to_json());
+        let response = try!(self.post(&path, &payload));
+        try!(APIClient::parse_response(response));
+        Ok(())
+    }
+
+    /// Approve a change, which merges its latest patchset into the
+    /// pipeline and starts its build stage
+    pub fn approve_change(&self, org: &str, proj: &str, change: &ChangeId) -> DeliveryResult<()> {
+        let path = format!("orgs/{}/projects/{}/changes/{}/merge", org, proj, change);
+        debug!("approve path: {}", path);
+        let response = try!(self.post(&path, ""));
+        try!(APIClient::parse_response(response));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, SERVER_ADDRESS};
+    use errors::Kind;
+
+    #[test]
+    fn test_approve_change() {
+        let path = "/api/v0/e/approver/orgs/acme/projects/rocket/changes";
+        mock("POST", &format!("{}/c1/merge", path)).with_status(204).create();
+        mock("POST", &format!("{}/c2/merge", path)).with_status(412)
+            .with_body("{\"message\": \"change not verified\"}").create();
+        let client = APIClient::new_http(SERVER_ADDRESS, "approver");
+        let c1 = ChangeId::new("c1").unwrap();
+        assert!(client.approve_change("acme", "rocket", &c1).is_ok());
+        let c2 = ChangeId::new("c2").unwrap();
+        let e = client.approve_change("acme", "rocket", &c2).unwrap_err();
+        assert!(assert_enum!(e.kind, Kind::ApiError(_, _)));
+        assert!(e.detail.unwrap().contains("change not verified"));
+    }
+
+    #[test]
+    fn test_new_comment_to_json() {
+        let comment = NewComment::new("**unit** tests passed");
+        assert_eq!("{\"content\":\"**unit** tests passed\",\"type\":\"comment\"}",
+                   comment.to_json().unwrap());
+    }
+
+    #[test]
+    fn test_review_verdict_to_json() {
+        let verdict = ReviewVerdict::new(Verdict::RequestChanges, "Needs tests");
+        assert_eq!("{\"verdict\":\"request_changes\",\"message\":\"Needs tests\"}",
+                   verdict.to_json().unwrap());
+    }
+}
@@ -0,0 +1,154 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+// Data bags are small JSON blobs stored on the server and scoped to
+// either a change or a pipeline. Phases (and humans) use them to pass
+// structured state between stages, like a computed version number.
+
+use http::APIClient;
+use hyper::status::StatusCode;
+use regex::Regex;
+use serde_json;
+use serde_json::Value as SerdeJson;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+
+/// The biggest blob we are willing to upload; data bags are meant
+/// for metadata, not artifacts.
+pub const MAX_DATA_BAG_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataBagScope<'a> {
+    Change(&'a str),
+    Pipeline(&'a str),
+}
+
+impl<'a> DataBagScope<'a> {
+    fn route(&self) -> String {
+        match *self {
+            DataBagScope::Change(id) => format!("changes/{}", id),
+            DataBagScope::Pipeline(name) => format!("pipelines/{}", name),
+        }
+    }
+}
+
+/// Returns the route of a data bag item relative to the enterprise
+pub fn data_bag_path(org: &str, proj: &str,
+                     scope: &DataBagScope, key: &str) -> String {
+    format!("orgs/{}/projects/{}/{}/data/{}", org, proj, scope.route(), key)
+}
+
+/// Verify that a key is safe to use as part of the URL
+pub fn validate_key(key: &str) -> DeliveryResult<()> {
+    let r = Regex::new(r"^[A-Za-z0-9_-][A-Za-z0-9_.-]*$").unwrap();
+    if r.is_match(key) {
+        Ok(())
+    } else {
+        let msg = format!("Invalid key '{}'. Keys may only contain letters, \
+                          numbers, '.', '_' and '-', and may not start with '.'", key);
+        Err(DeliveryError::throw(Kind::InvalidDataBag, Some(msg)))
+    }
+}
+
+/// Verify that the content is valid JSON and small enough to upload
+pub fn validate_content(content: &str) -> DeliveryResult<()> {
+    if content.len() > MAX_DATA_BAG_SIZE {
+        let msg = format!("Data is {} bytes; the maximum allowed is {} bytes",
+                          content.len(), MAX_DATA_BAG_SIZE);
+        return Err(DeliveryError::throw(Kind::InvalidDataBag, Some(msg)))
+    }
+    match serde_json::from_str::<SerdeJson>(content) {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            let msg = format!("Data must be valid JSON: {}", e);
+            Err(DeliveryError::throw(Kind::InvalidDataBag, Some(msg)))
+        }
+    }
+}
+
+impl APIClient {
+    /// Fetch a data bag item. Returns `None` if it does not exist.
+    pub fn get_data_bag(&self, org: &str, proj: &str, scope: &DataBagScope,
+                        key: &str) -> DeliveryResult<Option<String>> {
+        try!(validate_key(key));
+        let path = data_bag_path(org, proj, scope, key);
+        debug!("data bag path: {}", path);
+        let response = try!(self.get(&path));
+        if let StatusCode::NotFound = response.status {
+            return Ok(None)
+        }
+        let (_, content) = try!(APIClient::parse_response(response));
+        Ok(content)
+    }
+
+    /// Create or replace a data bag item with the provided JSON content
+    pub fn set_data_bag(&self, org: &str, proj: &str, scope: &DataBagScope,
+                        key: &str, content: &str) -> DeliveryResult<()> {
+        try!(validate_key(key));
+        try!(validate_content(content));
+        let path = data_bag_path(org, proj, scope, key);
+        debug!("data bag path: {}", path);
+        let response = try!(self.put(&path, content));
+        try!(APIClient::parse_response(response));
+        Ok(())
+    }
+
+    /// Delete a data bag item. Deleting an item that does not exist
+    /// is not considered an error.
+    pub fn delete_data_bag(&self, org: &str, proj: &str, scope: &DataBagScope,
+                           key: &str) -> DeliveryResult<()> {
+        try!(validate_key(key));
+        let path = data_bag_path(org, proj, scope, key);
+        debug!("data bag path: {}", path);
+        let response = try!(self.delete(&path));
+        if let StatusCode::NotFound = response.status {
+            return Ok(())
+        }
+        try!(APIClient::parse_response(response));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_bag_path() {
+        assert_eq!("orgs/o/projects/p/changes/abc/data/version",
+                   data_bag_path("o", "p", &DataBagScope::Change("abc"), "version"));
+        assert_eq!("orgs/o/projects/p/pipelines/master/data/deploy",
+                   data_bag_path("o", "p", &DataBagScope::Pipeline("master"), "deploy"));
+    }
+
+    #[test]
+    fn test_validate_key() {
+        assert!(validate_key("build-version_1.2").is_ok());
+        assert!(validate_key("../orgs").is_err());
+        assert!(validate_key("..").is_err());
+        assert!(validate_key("").is_err());
+    }
+
+    #[test]
+    fn test_validate_content() {
+        assert!(validate_content("{\"version\":\"1.2.3\"}").is_ok());
+        assert!(validate_content("not json").is_err());
+        let a: String = ::std::iter::repeat("a").take(MAX_DATA_BAG_SIZE).collect();
+        let big = format!("\"{}\"", a);
+        assert!(validate_content(&big).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+// Authentication diagnostics
+//
+// A laptop whose clock drifted and a token issued long ago both end in
+// the same unhelpful 401. When authentication fails we compare the
+// `Date` header of the server with the local clock and look at how old
+// the token we sent is, so the error can point at the likely cause.
+
+use std::cell::Cell;
+use hyper::header::{Date, Headers};
+use time;
+
+/// How far apart the clocks can be before we call it out
+pub const MAX_CLOCK_SKEW: i64 = 5 * 60;
+/// The default lifetime of the tokens the server issues
+pub const TOKEN_LIFETIME: i64 = 7 * 24 * 60 * 60;
+
+// Per thread, like the requests: the token is loaded on the thread
+// that builds the client and most commands send their requests there.
+thread_local!(static TOKEN_AGE: Cell<Option<i64>> = Cell::new(None));
+
+/// Remember the age of the token the requests are authenticated with
+pub fn note_token_age(age: Option<i64>) {
+    TOKEN_AGE.with(|current| current.set(age));
+}
+
+/// The age of the token last loaded from the token store, if known
+pub fn token_age() -> Option<i64> {
+    TOKEN_AGE.with(|current| current.get())
+}
+
+/// How many seconds the server clock is ahead of ours (negative when
+/// it is behind), from the `Date` header of a response
+pub fn clock_skew(headers: &Headers) -> Option<i64> {
+    headers.get::<Date>().map(|date| {
+        (date.0).0.to_timespec().sec - time::get_time().sec
+    })
+}
+
+/// A short human form of a number of seconds, i.e. `3d 4h` or `12m`
+pub fn format_duration(seconds: i64) -> String {
+    let s = seconds.abs();
+    let (days, hours, minutes) = (s / 86400, (s % 86400) / 3600, (s % 3600) / 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, s % 60)
+    } else {
+        format!("{}s", s)
+    }
+}
+
+/// Something the diagnostics found; `problem` tells whether it likely
+/// explains an authentication failure
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub problem: bool,
+    pub message: String,
+}
+
+/// What the clock skew with the server tells
+pub fn clock_finding(skew: Option<i64>) -> Option<Finding> {
+    skew.map(|s| {
+        let direction = if s > 0 { "behind" } else { "ahead of" };
+        if s.abs() > MAX_CLOCK_SKEW {
+            Finding {
+                problem: true,
+                message: format!("Your clock is {} {} the server's; the server rejects \
+                                  tokens when clocks disagree, sync the clock of this \
+                                  machine (e.g. enable NTP)", format_duration(s), direction)
+            }
+        } else {
+            Finding {
+                problem: false,
+                message: format!("Your clock agrees with the server's (off by {})",
+                                 format_duration(s))
+            }
+        }
+    })
+}
+
+/// What the age of the token tells
+pub fn token_finding(token_age: Option<i64>) -> Option<Finding> {
+    token_age.map(|age| {
+        if age > TOKEN_LIFETIME {
+            Finding {
+                problem: true,
+                message: format!("Your token was issued {} ago and has likely expired; \
+                                  run `delivery token` to get a new one", format_duration(age))
+            }
+        } else {
+            Finding {
+                problem: false,
+                message: format!("Your token was issued {} ago", format_duration(age))
+            }
+        }
+    })
+}
+
+/// The lines added to the detail of an authentication failure: the
+/// clock only when it is off, the age of the token whenever known.
+pub fn explain_auth_failure(skew: Option<i64>, token_age: Option<i64>) -> Vec<String> {
+    clock_finding(skew).into_iter().filter(|f| f.problem)
+        .chain(token_finding(token_age).into_iter())
+        .map(|f| f.message)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::{Date, Headers, HttpDate};
+    use time;
+
+    #[test]
+    fn test_clock_skew() {
+        let mut headers = Headers::new();
+        assert_eq!(None, clock_skew(&headers));
+        let ahead = time::at_utc(time::Timespec::new(time::get_time().sec + 600, 0));
+        headers.set(Date(HttpDate(ahead)));
+        let skew = clock_skew(&headers).unwrap();
+        assert!(skew > 590 && skew <= 600);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!("45s", format_duration(45));
+        assert_eq!("12m 0s", format_duration(-720));
+        assert_eq!("3d 4h", format_duration(3 * 86400 + 4 * 3600 + 59));
+    }
+
+    #[test]
+    fn test_findings() {
+        assert_eq!(None, clock_finding(None));
+        let clock = clock_finding(Some(-3600)).unwrap();
+        assert!(clock.problem);
+        assert!(clock.message.starts_with("Your clock is 1h 0m ahead of the server's"));
+        assert_eq!(Finding { problem: false,
+                             message: "Your clock agrees with the server's (off by 2s)".to_string() },
+                   clock_finding(Some(2)).unwrap());
+        assert!(token_finding(Some(TOKEN_LIFETIME + 1)).unwrap().problem);
+    }
+
+    #[test]
+    fn test_explain_auth_failure() {
+        assert!(explain_auth_failure(None, None).is_empty());
+        assert!(explain_auth_failure(Some(2), None).is_empty());
+        assert_eq!(vec!["Your token was issued 1h 0m ago"],
+                   explain_auth_failure(Some(2), Some(3600)));
+        assert_eq!(2, explain_auth_failure(Some(900), Some(3600)).len());
+    }
+}
@@ -0,0 +1,43 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::collections::BTreeMap;
+use http::APIClient;
+use hyper::status::StatusCode;
+use serde_json;
+use types::DeliveryResult;
+
+#[derive(Deserialize, Debug, Default)]
+struct FeatureList {
+    #[serde(default)]
+    features: BTreeMap<String, bool>,
+}
+
+impl APIClient {
+    /// The feature flags the server advertises for the enterprise.
+    /// Servers that predate feature flags advertise none.
+    pub fn server_features(&self) -> DeliveryResult<BTreeMap<String, bool>> {
+        let response = try!(self.get("features"));
+        if let StatusCode::NotFound = response.status {
+            return Ok(BTreeMap::new())
+        }
+        match try!(APIClient::parse_response(response)) {
+            (_, Some(body)) => Ok(try!(serde_json::from_str::<FeatureList>(&body)).features),
+            (_, None) => Ok(BTreeMap::new())
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Recorded HTTP fixtures
+//!
+//! With `--record-fixtures DIR` every request of the API client is
+//! written to DIR along with the response of the server, one JSON file
+//! per exchange. Credentials are redacted and the authentication
+//! headers are never written, so the directory can be attached to a
+//! bug report. `--replay-fixtures DIR` answers the requests from such a
+//! directory instead of the server: each recorded exchange answers the
+//! first request with the same method and path, in the recorded order.
+
+use std::fs::{self, File};
+use std::io::{self, Cursor};
+use std::io::prelude::*;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use hyper::Url;
+use hyper::client::response::Response as HyperResponse;
+use hyper::error::Error as HttpError;
+use hyper::net::NetworkStream;
+use hyper::status::StatusCode;
+use serde_json;
+use logs::redact;
+use types::DeliveryResult;
+use utils;
+use utils::say::sayln;
+
+/// One request to the API and the response of the server
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Fixture {
+    pub method: String,
+    pub path: String,
+    pub request: String,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub response: String,
+}
+
+enum Mode {
+    Record { dir: PathBuf, count: usize },
+    Replay { dir: PathBuf, fixtures: Option<Vec<(Fixture, bool)>> },
+}
+
+static MODE: Mutex<Option<Mode>> = Mutex::new(None);
+
+pub fn turn_on_recording(dir: &str) {
+    let dir = PathBuf::from(dir);
+    let count = list(&dir).len();
+    *MODE.lock().unwrap() = Some(Mode::Record { dir: dir, count: count });
+}
+
+pub fn turn_on_replay(dir: &str) {
+    *MODE.lock().unwrap() = Some(Mode::Replay { dir: PathBuf::from(dir), fixtures: None });
+}
+
+pub fn replaying() -> bool {
+    match *MODE.lock().unwrap() {
+        Some(Mode::Replay { .. }) => true,
+        _ => false
+    }
+}
+
+/// The recorded response to the request, `None` unless replaying
+pub fn replay(method: &str, path: &str, url: &str) -> Result<Option<HyperResponse>, HttpError> {
+    let mut mode = MODE.lock().unwrap();
+    let (dir, fixtures) = match *mode {
+        Some(Mode::Replay { ref dir, ref mut fixtures }) => (dir, fixtures),
+        _ => return Ok(None)
+    };
+    if fixtures.is_none() {
+        let loaded = try!(load(dir).map_err(|e| fixture_error(&e.to_string())));
+        *fixtures = Some(loaded.into_iter().map(|f| (f, false)).collect());
+    }
+    let unused = fixtures.as_mut().unwrap().iter_mut()
+        .find(|entry| !entry.1 && entry.0.method == method && entry.0.path == path);
+    match unused {
+        Some(entry) => {
+            debug!("Replaying the recorded response to {} {}", method, path);
+            entry.1 = true;
+            to_response(url, &entry.0).map(Some)
+        },
+        None => {
+            let msg = format!("No recorded response to {} {} in {}", method, path, dir.display());
+            debug!("{}", msg);
+            Err(fixture_error(&msg))
+        }
+    }
+}
+
+/// Write the exchange to the fixtures directory when recording. The
+/// response has to be read to be recorded, what is returned is a copy.
+pub fn record(method: &str, path: &str, payload: &str, url: &str,
+              response: HyperResponse) -> Result<HyperResponse, HttpError> {
+    let mut mode = MODE.lock().unwrap();
+    let (dir, count) = match *mode {
+        Some(Mode::Record { ref dir, ref mut count }) => (dir, count),
+        _ => return Ok(response)
+    };
+    let fixture = try!(from_response(method, path, payload, response));
+    *count += 1;
+    if let Err(e) = write(dir, *count, &fixture) {
+        sayln("yellow", &format!("WARN: Unable to record {} {} in {}: {}",
+                                 method, path, dir.display(), e));
+    }
+    to_response(url, &fixture)
+}
+
+/// The fixture of an exchange, with the credentials it carries redacted
+pub fn from_response(method: &str, path: &str, payload: &str,
+                     mut response: HyperResponse) -> Result<Fixture, HttpError> {
+    let mut body = vec![];
+    try!(response.read_to_end(&mut body));
+    let headers = response.headers.iter()
+        .filter(|h| !h.name().eq_ignore_ascii_case("set-cookie"))
+        .map(|h| (h.name().to_string(), h.value_string()))
+        .collect();
+    Ok(Fixture {
+        method: method.to_string(),
+        path: path.to_string(),
+        request: redact(payload),
+        status: response.status.to_u16(),
+        headers: headers,
+        response: redact(&String::from_utf8_lossy(&body)),
+    })
+}
+
+/// A response read from the recorded `fixture`, as if `url` answered it
+pub fn to_response(url: &str, fixture: &Fixture) -> Result<HyperResponse, HttpError> {
+    let status = StatusCode::from_u16(fixture.status);
+    let mut raw = format!("HTTP/1.1 {} {}\r\n", fixture.status,
+                          status.canonical_reason().unwrap_or("Unknown"));
+    for &(ref name, ref value) in fixture.headers.iter() {
+        if !name.eq_ignore_ascii_case("content-length")
+            && !name.eq_ignore_ascii_case("transfer-encoding") {
+            raw.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+    raw.push_str(&format!("Content-Length: {}\r\n\r\n", fixture.response.len()));
+    raw.push_str(&fixture.response);
+    let url = try!(Url::parse(url));
+    HyperResponse::new(url, Box::new(RecordedStream { read: Cursor::new(raw.into_bytes()) }))
+}
+
+// The fixture files of `dir`, in the order they were recorded
+fn list(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path())
+            .filter(|p| p.extension().map(|e| e == "json").unwrap_or(false))
+            .collect(),
+        Err(_) => vec![]
+    };
+    files.sort();
+    files
+}
+
+fn load(dir: &Path) -> DeliveryResult<Vec<Fixture>> {
+    let mut fixtures = vec![];
+    for file in list(dir) {
+        fixtures.push(try!(serde_json::from_str::<Fixture>(&try!(utils::read_file(&file)))));
+    }
+    Ok(fixtures)
+}
+
+fn write(dir: &Path, index: usize, fixture: &Fixture) -> DeliveryResult<PathBuf> {
+    try!(utils::mkdir_recursive(dir));
+    let name: String = fixture.path.trim_matches('/').chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+        .take(80)
+        .collect();
+    let path = dir.join(format!("{:04}-{}-{}.json", index, fixture.method, name));
+    let mut file = try!(File::create(&path));
+    try!(file.write_all(try!(serde_json::to_string_pretty(fixture)).as_bytes()));
+    Ok(path)
+}
+
+fn fixture_error(msg: &str) -> HttpError {
+    HttpError::Io(io::Error::new(io::ErrorKind::NotFound, msg.to_string()))
+}
+
+// The recorded bytes of a response, in place of a connection
+struct RecordedStream {
+    read: Cursor<Vec<u8>>,
+}
+
+impl Read for RecordedStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read.read(buf)
+    }
+}
+
+impl Write for RecordedStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl NetworkStream for RecordedStream {
+    fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+        Ok(SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0)))
+    }
+
+    fn set_read_timeout(&self, _dur: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_write_timeout(&self, _dur: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::{load, write};
+    use tempdir::TempDir;
+
+    fn fixture() -> Fixture {
+        Fixture {
+            method: "POST".to_string(),
+            path: "/api/v0/e/acme/users/alice/get-token".to_string(),
+            request: "{\"username\":\"alice\",\"password\":\"[REDACTED]\"}".to_string(),
+            status: 200,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string()),
+                          ("Content-Length".to_string(), "999".to_string())],
+            response: "{\"token\":\"[REDACTED]\"}".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_fixture_round_trip() {
+        let url = "https://automate.example.com/api/v0/e/acme/users/alice/get-token";
+        let response = to_response(url, &fixture()).unwrap();
+        assert_eq!(StatusCode::Ok, response.status);
+        let recorded = from_response("POST", &fixture().path,
+                                     "{\"username\":\"alice\",\"password\":\"hunter2\"}",
+                                     response).unwrap();
+        assert_eq!("{\"username\":\"alice\",\"password\":\"[REDACTED]\"}", recorded.request);
+        assert_eq!(fixture().response, recorded.response);
+        let mut body = String::new();
+        to_response(url, &recorded).unwrap().read_to_string(&mut body).unwrap();
+        assert_eq!(fixture().response, body);
+    }
+
+    #[test]
+    fn test_write_and_load() {
+        let tempdir = TempDir::new("fixtures").unwrap();
+        let path = write(tempdir.path(), 1, &fixture()).unwrap();
+        assert_eq!("0001-POST-api_v0_e_acme_users_alice_get-token.json",
+                   path.file_name().unwrap().to_str().unwrap());
+        assert_eq!(vec![fixture()], load(tempdir.path()).unwrap());
+    }
+}
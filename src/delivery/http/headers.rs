@@ -17,3 +17,7 @@
 
 header!{ (ChefDeliveryToken, "chef-delivery-token") => [String] }
 header!{ (ChefDeliveryUser,  "chef-delivery-user") => [String] }
+header!{ (DeliveryKeyId, "x-delivery-key-id") => [String] }
+header!{ (DeliveryTimestamp, "x-delivery-timestamp") => [String] }
+header!{ (DeliverySignature, "x-delivery-signature") => [String] }
+header!{ (ProxyAuthorization, "proxy-authorization") => [String] }
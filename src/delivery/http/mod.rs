@@ -17,13 +17,15 @@
 
 use std::fmt;
 use std::env;
-use std::path::PathBuf;
+use std::io;
 use hyper;
 use hyper::status::StatusCode;
 use hyper::client::response::Response as HyperResponse;
 use hyper::error::Error as HttpError;
 use http;
 use http::token::TokenResponse;
+use http::server_error::ServerError;
+use http::signing::RequestSigner;
 use mime;
 use serde_json;
 use serde_json::Value as SerdeJson;
@@ -32,17 +34,37 @@ use errors::{DeliveryError, Kind};
 use errors::Kind::{ApiError, EndpointNotFound, AuthenticationFailed,
                    ForbiddenRequest, TokenExpired};
 use token::TokenStore;
-use utils::say::sayln;
+use utils::say::{sayln, Spinner};
+use utils::pool;
+use utils::env_variable;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
 use config::Config;
-use types::DeliveryResult;
+use types::{DeliveryResult, OrgName, ProjectName, PipelineName};
 
 mod headers;
 pub mod token;
 pub mod change;
 pub mod saml;
 pub mod user;
-
-#[derive(Debug)]
+pub mod data_bag;
+pub mod pin;
+pub mod runner;
+pub mod pipeline;
+pub mod comment;
+pub mod budget;
+pub mod server_error;
+pub mod diagnostics;
+pub mod features;
+pub mod signing;
+pub mod pac;
+pub mod proxy;
+pub mod fixtures;
+pub mod tls;
+pub mod paginate;
+pub mod scp;
+
+#[derive(Debug, Clone)]
 enum HProto {
     HTTP,
     HTTPS
@@ -81,13 +103,18 @@ enum HTTPMethod {
     DELETE
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct APIClient {
     enterprise: Option<String>,
     api_version: Option<String>,
     proto: HProto,
     host: String,
-    auth: Option<APIAuth>
+    path_prefix: String,
+    auth: Option<APIAuth>,
+    signer: Option<RequestSigner>,
+    proxy: Option<pac::Proxy>,
+    tls: tls::Tls,
+    renew_with: Option<Config>
 }
 
 impl APIClient {
@@ -100,6 +127,7 @@ impl APIClient {
         APIClient::from_config_no_auth(config).and_then(|mut c| {
             let auth = try!(APIAuth::from_config(&config));
             c.set_auth(auth);
+            c.set_token_renewal(config);
             Ok(c)
         })
     }
@@ -126,10 +154,24 @@ impl APIClient {
     // the full route.
     pub fn from_config_with_basic_routing(config: &Config)
                                           -> DeliveryResult<APIClient> {
-        let host = try!(config.api_host_and_port());
-        let proto_str = try!(config.api_protocol());
-        let proto = try!(HProto::from_str(&proto_str));
-        Ok(APIClient::new(proto, &host))
+        try!(config.require_network("contacting the Automate server"));
+        let server_url = try!(config.server_url_parts());
+        let proto = try!(HProto::from_str(&server_url.protocol));
+        let mut tls = try!(tls::Tls::from_config(config));
+        if let HProto::HTTPS = proto {
+            tls = tls.with_pins(config.server_pins());
+        }
+        let mut api_client = APIClient::new(proto, &server_url.host_and_port());
+        api_client.set_path_prefix(&server_url.path_prefix);
+        if let Some(signer) = try!(RequestSigner::from_config(config)) {
+            api_client.set_signer(signer);
+        }
+        let url = format!("{}://{}/", server_url.protocol, server_url.host_and_port());
+        if let Some(proxy) = proxy::proxy_for(config, &url, &server_url.host) {
+            api_client.set_proxy(proxy);
+        }
+        api_client.set_tls(tls);
+        Ok(api_client)
     }
 
     /// Create a new `APIClient` using HTTP attached to the enterprise
@@ -206,20 +248,41 @@ impl APIClient {
                 Err(DeliveryError::throw(ForbiddenRequest, Some(msg)))
             },
             Unauthorized => {
+                let skew = diagnostics::clock_skew(&response.headers);
+                let findings = diagnostics::explain_auth_failure(skew, diagnostics::token_age());
                 let detail = try!(APIClient::extract_pretty_json(&mut response));
                 if TokenResponse::parse_token_expired(&detail) {
-                    return Err(DeliveryError::throw(TokenExpired, None))
+                    let msg = if findings.is_empty() { None } else { Some(findings.join("\n")) };
+                    return Err(DeliveryError::throw(TokenExpired, msg))
+                }
+                let mut msg = format!("Request lacks valid authentication credentials.\n\
+                                      Detail:\n{}", detail);
+                for finding in findings {
+                    msg.push_str(&format!("\n{}", finding));
                 }
-                let msg = format!("Request lacks valid authentication credentials.\n\
-                                  Detail:\n{}", detail);
                 Err(DeliveryError::throw(AuthenticationFailed, Some(msg)))
             },
-            error_code @ _ => {
-                let msg = format!("Request returned: '{}'", error_code);
-                let mut detail = String::new();
-                let e = response.read_to_string(&mut detail).and(Ok(detail));
-                Err(DeliveryError::throw(ApiError(error_code, e), Some(msg)))
+            _ => Err(APIClient::api_error(&mut response)),
+        }
+    }
+
+    /// Turn a failed response into an `ApiError` whose detail is the
+    /// message decoded from the error payload of the server. The raw
+    /// body is kept in the error so `--debug` can show it.
+    pub fn api_error(response: &mut HyperResponse) -> DeliveryError {
+        let status = response.status;
+        let mut body = String::new();
+        match response.read_to_string(&mut body) {
+            Ok(_) => {
+                debug!("Status: {:?} Body: {:?}", status, body);
+                let error = ServerError::decode(status, &body);
+                let msg = error.friendly_message();
+                DeliveryError::throw(ApiError(status, Ok(error.body)), Some(msg))
             },
+            Err(e) => {
+                let msg = ServerError::decode(status, "").friendly_message();
+                DeliveryError::throw(ApiError(status, Err(e)), Some(msg))
+            }
         }
     }
 
@@ -228,11 +291,37 @@ impl APIClient {
             api_version: None,
             proto: proto,
             host: String::from(host),
+            path_prefix: String::new(),
             enterprise: None,
-            auth: None
+            auth: None,
+            signer: None,
+            proxy: None,
+            tls: tls::Tls::default(),
+            renew_with: None
         }
     }
 
+    /// Sign every request with `signer`
+    pub fn set_signer(&mut self, signer: RequestSigner) {
+        self.signer = Some(signer);
+    }
+
+    /// Renew the token of the user of `config` when the server rejects
+    /// it, and send the rejected request again with the new one
+    pub fn set_token_renewal(&mut self, config: &Config) {
+        self.renew_with = Some(config.clone());
+    }
+
+    /// Send every request through the HTTP `proxy`
+    pub fn set_proxy(&mut self, proxy: pac::Proxy) {
+        self.proxy = Some(proxy);
+    }
+
+    /// Trust the CA bundle and present the client certificate of `tls`
+    pub fn set_tls(&mut self, tls: tls::Tls) {
+        self.tls = tls;
+    }
+
     pub fn set_auth(&mut self, auth: APIAuth) {
         self.auth = Some(auth);
     }
@@ -245,6 +334,13 @@ impl APIClient {
         self.api_version = Some(String::from(api_version))
     }
 
+    /// Set a path under which the server is mounted, for servers that
+    /// live behind a reverse proxy (i.e. `/delivery`). Every request
+    /// route is prefixed with it.
+    pub fn set_path_prefix(&mut self, path_prefix: &str) {
+        self.path_prefix = path_prefix.trim_right_matches('/').to_string()
+    }
+
     pub fn get_auth_from_home(&mut self, server: &str, ent: &str,
                               user: &str) -> DeliveryResult<APIAuth> {
         match TokenStore::from_home() {
@@ -256,7 +352,12 @@ impl APIClient {
     }
 
     pub fn api_url(&self, path: &str) -> String {
-        let mut request_path = format!("{}://{}", self.proto, self.host);
+        format!("{}://{}{}", self.proto, self.host, self.api_path(path))
+    }
+
+    /// The path part of the url of `path`, which is what gets signed
+    pub fn api_path(&self, path: &str) -> String {
+        let mut request_path = self.path_prefix.clone();
 
         if let Some(ref version) = self.api_version {
             request_path += &format!("/api/{}", version);
@@ -293,90 +394,215 @@ impl APIClient {
     /// an empty string, no request body will be sent. This could be an
     /// `Options<String>` but (I think) keeping the simple `&str`
     /// avoids an allocation.
+    ///
+    /// A request the server rejects with a 401 is sent once more after
+    /// renewing the token, when the client was told how to renew it.
     fn req_with_body(&self,
                      http_method: HTTPMethod,
                      path: &str,
                      payload: &str) -> Result<HyperResponse, HttpError> {
+        let auth = self.auth.as_ref().map(|auth| self.current_auth(auth));
+        let response = try!(self.send(auth.as_ref(), &http_method, path, payload));
+        if response.status != StatusCode::Unauthorized {
+            return Ok(response)
+        }
+        match (auth, self.renew_with.as_ref()) {
+            (Some(auth), Some(config)) => match self.renew_auth(&auth, config) {
+                Some(renewed) => self.send(Some(&renewed), &http_method, path, payload),
+                None => Ok(response)
+            },
+            _ => Ok(response)
+        }
+    }
+
+    // The auth to send, with the token renewed earlier in the run if any
+    fn current_auth(&self, auth: &APIAuth) -> APIAuth {
+        let key = format!("{},{}", self.host, auth.user);
+        match renewed_tokens().get(&key) {
+            Some(token) => APIAuth { user: auth.user.clone(), token: token.clone() },
+            None => auth.clone()
+        }
+    }
+
+    // A new token for the rejected `auth`. The token is renewed once per
+    // run: a request rejected after that gets the renewed token if it
+    // was sent with the old one, and nothing otherwise.
+    fn renew_auth(&self, auth: &APIAuth, config: &Config) -> Option<APIAuth> {
+        let key = format!("{},{}", self.host, auth.user);
+        let mut renewed = renewed_tokens();
+        if let Some(token) = renewed.get(&key) {
+            return if *token == auth.token {
+                None
+            } else {
+                Some(APIAuth { user: auth.user.clone(), token: token.clone() })
+            }
+        }
+        sayln("yellow", "The API token was rejected, renewing it");
+        let token = match TokenStore::renew_token(config) {
+            Ok(token) => token,
+            Err(e) => {
+                sayln("red", &format!("Unable to renew the API token: {}",
+                                      e.detail().unwrap_or(e.to_string())));
+                auth.token.clone()
+            }
+        };
+        renewed.insert(key, token.clone());
+        if token == auth.token {
+            None
+        } else {
+            Some(APIAuth { user: auth.user.clone(), token: token })
+        }
+    }
+
+    fn send(&self,
+            auth: Option<&APIAuth>,
+            http_method: &HTTPMethod,
+            path: &str,
+            payload: &str) -> Result<HyperResponse, HttpError> {
         let url = self.api_url(path);
-        let client = hyper::Client::new();
-        let req = match http_method {
+        let method = format!("{:?}", http_method);
+        if let Some(response) = try!(fixtures::replay(&method, &self.api_path(path), &url)) {
+            return Ok(response)
+        }
+        let client = try!(proxy::client(self.proxy.as_ref(), &self.tls));
+        let req = match *http_method {
             HTTPMethod::GET    => client.get(&url),
             HTTPMethod::PUT    => client.put(&url),
             HTTPMethod::POST   => client.post(&url),
             HTTPMethod::DELETE => client.delete(&url)
         };
         let req = req.header(self.json_content());
-        let req = match self.auth {
-            Some(ref auth) => {
+        let req = match proxy::authorization(self.proxy.as_ref(), &self.tls, &url) {
+            Some(authorization) => req.header(authorization),
+            None => req
+        };
+        let req = match auth {
+            Some(auth) => {
                 let (deliv_user, deliv_token) = auth.auth_headers();
                 req.header(deliv_user).header(deliv_token)
             },
             None => req
         };
-        debug!("Request: {:?} Path: {:?} Payload: {:?}",
+        let req = match self.signer {
+            Some(ref signer) => {
+                let (key_id, timestamp, signature) = signer.sign_headers(&method,
+                                                                         &self.api_path(path),
+                                                                         payload);
+                req.header(key_id).header(timestamp).header(signature)
+            },
+            None => req
+        };
+        debug!("Request: {:?} Path: {:?} Payload: {}",
                 http_method, path, payload);
-        if payload.is_empty() {
+        let response = if payload.is_empty() {
             req.send()
         } else {
             req.body(payload).send()
+        };
+        match response {
+            Ok(r) => {
+                debug!("Response: {} {:?} {}", r.status, http_method, path);
+                budget::record(&r.headers);
+                fixtures::record(&method, &self.api_path(path), payload, &url, r)
+            },
+            Err(e) => {
+                debug!("Request failed: {:?} {:?} {}", http_method, path, e);
+                match pin::take_mismatch() {
+                    Some(actual) => {
+                        let msg = pin::mismatch_message(&self.host, &self.tls.pins, &actual);
+                        Err(HttpError::Io(io::Error::new(io::ErrorKind::Other, msg)))
+                    },
+                    None => Err(e)
+                }
+            }
         }
     }
 
     pub fn pipeline_exists(&self,
                           org: &str, proj: &str, pipe: &str) -> bool {
-        let path = format!("orgs/{}/projects/{}/pipelines/{}", org, proj, pipe);
-        match self.get(&path) {
-            Ok(res) => {
-                match res.status {
-                    StatusCode::Ok => {
-                        return true;
-                    },
-                    _ => {
-                        return false;
-                    }
-                }
-            },
-            Err(e) => {
-                sayln("red", &format!("pipeline_exists: HttpError: {:?}", e));
-                return false;
-            }
-        }
+        self.entity_exists(&pipeline_path(org, proj, pipe), "pipeline_exists")
     }
 
     pub fn project_exists(&self,
                           org: &str,
                           proj: &str) -> bool {
+        self.entity_exists(&project_path(org, proj), "project_exists")
+    }
 
-        let path = format!("orgs/{}/projects/{}", org, proj);
-        match self.get(&path) {
+    /// Ask whether a project and its pipeline exist at the same time,
+    /// so `project_exists` and `pipeline_exists` answer from the cache.
+    /// On a slow server that is one roundtrip to wait for instead of two.
+    pub fn prefetch_exists(&self, org: &str, proj: &str, pipe: &str) {
+        let client = self.clone();
+        let paths = vec![project_path(org, proj), pipeline_path(org, proj, pipe)];
+        let spinner = Spinner::start();
+        pool::parallel_map(paths, 2, move |path| client.entity_exists(&path, "prefetch_exists"));
+        spinner.stop();
+    }
+
+    // Whether the entity at `path` exists, the server is only asked the
+    // first time during a run. Only a found or not found is remembered.
+    fn entity_exists(&self, path: &str, check: &str) -> bool {
+        let url = self.api_url(path);
+        if let Some(exists) = exists_cache().get(&url) {
+            debug!("{}: cached answer for {}: {}", check, url, exists);
+            return *exists
+        }
+        match self.get(path) {
             Ok(res) => {
                 match res.status {
                     StatusCode::Ok => {
-                        return true;
+                        exists_cache().insert(url, true);
+                        true
                     },
-                    _ => {
-                        return false;
-                    }
+                    StatusCode::NotFound => {
+                        exists_cache().insert(url, false);
+                        false
+                    },
+                    _ => false
                 }
             },
             Err(e) => {
-                sayln("red", &format!("project_exists: HttpError: {:?}", e));
-                return false;
+                sayln("red", &format!("{}: HttpError: {:?}", check, e));
+                false
             }
         }
     }
 
-    pub fn create_delivery_project(&self, org: &str,
-                                   proj: &str) -> DeliveryResult<StatusCode> {
+    // Record that the entity at `path` exists, once we created it
+    fn remember_exists(&self, path: &str) {
+        exists_cache().insert(self.api_url(path), true);
+    }
+
+    // Whatever was cached under the entity at `path` is gone with it.
+    fn forget_exists(&self, path: &str) {
+        let url = self.api_url(path);
+        let children = format!("{}/", url);
+        exists_cache().retain(|cached, _| *cached != url && !cached.starts_with(&children));
+    }
+
+    pub fn create_delivery_project(&self, org: &OrgName,
+                                   proj: &ProjectName) -> DeliveryResult<StatusCode> {
         let path = format!("orgs/{}/projects", org);
         // FIXME: we'd like to use the native struct->json stuff, but
         // seeing link issues.
         let payload = format!("{{\"name\":\"{}\"}}", proj);
-        Self::parse_response(self.post(&path, &payload)?).map(|(code, _)| code)
+        let (code, _) = try!(Self::parse_response(self.post(&path, &payload)?));
+        self.remember_exists(&project_path(org, proj));
+        Ok(code)
+    }
+
+    /// Delete a project from the server, along with its pipelines and changes
+    pub fn delete_project(&self, org: &str, proj: &str) -> DeliveryResult<()> {
+        let path = project_path(org, proj);
+        debug!("delete project path: {}", path);
+        try!(APIClient::parse_response(try!(self.delete(&path))));
+        self.forget_exists(&path);
+        Ok(())
     }
 
-    pub fn create_github_project(&self, org: &str, proj: &str,
-                                repo_name: &str, git_org: &str, pipe: &str,
+    pub fn create_github_project(&self, org: &OrgName, proj: &ProjectName,
+                                repo_name: &str, git_org: &str, pipe: &PipelineName,
                                 ssl: bool) -> DeliveryResult<StatusCode> {
         let path = format!("orgs/{}/github-projects", org);
         let payload = format!("{{\
@@ -389,7 +615,9 @@ impl APIClient {
                                     \"verify_ssl\": {}\
                                 }}\
                               }}", proj, repo_name, git_org, pipe, ssl);
-        Self::parse_response(self.post(&path, &payload)?).map(|(code, _)| code)
+        let (code, _) = try!(Self::parse_response(self.post(&path, &payload)?));
+        self.remember_exists(&project_path(org, proj));
+        Ok(code)
     }
 
     fn get_scm_server_config(&self, scm: &str) -> DeliveryResult<Vec<SerdeJson>> {
@@ -427,9 +655,9 @@ impl APIClient {
         self.get_scm_server_config("Bitbucket")
     }
 
-    pub fn create_bitbucket_project(&self, org: &str, proj: &str,
+    pub fn create_bitbucket_project(&self, org: &OrgName, proj: &ProjectName,
                                     repo_name: &str, project_key: &str,
-                                    pipe: &str) -> DeliveryResult<StatusCode> {
+                                    pipe: &PipelineName) -> DeliveryResult<StatusCode> {
         let path = format!("orgs/{}/bitbucket-projects", org);
         let payload = format!("{{\
                                 \"name\":\"{}\",\
@@ -440,19 +668,23 @@ impl APIClient {
                                     \"pipeline_branch\":\"{}\"\
                                 }}\
                               }}", proj, repo_name, project_key, pipe);
-        Self::parse_response(self.post(&path, &payload)?).map(|(code, _)| code)
+        let (code, _) = try!(Self::parse_response(self.post(&path, &payload)?));
+        self.remember_exists(&project_path(org, proj));
+        Ok(code)
     }
 
     pub fn create_pipeline(&self,
-                           org: &str, proj: &str,
-                           pipe: &str, base: Option<&str>) -> DeliveryResult<StatusCode> {
+                           org: &OrgName, proj: &ProjectName,
+                           pipe: &PipelineName, base: Option<&str>) -> DeliveryResult<StatusCode> {
         let path = format!("orgs/{}/projects/{}/pipelines", org, proj);
 
         // We unwrap the provided base branch, if None we default to `master`
         let base_branch = base.unwrap_or("master");
         let payload = format!("{{\"name\":\"{}\",\"base\":\"{}\"}}", pipe, base_branch);
 
-        Self::parse_response(self.post(&path, &payload)?).map(|(code, _)| code)
+        let (code, _) = try!(Self::parse_response(self.post(&path, &payload)?));
+        self.remember_exists(&pipeline_path(org, proj, pipe));
+        Ok(code)
     }
 
     pub fn parse_json(result: Result<HyperResponse, HttpError>) -> DeliveryResult<SerdeJson> {
@@ -484,7 +716,10 @@ impl APIClient {
 
 }
 
-#[derive(Debug)]
+/// The environment variable that overrides the token store
+pub const TOKEN_ENV_VAR: &'static str = "DELIVERY_TOKEN";
+
+#[derive(Debug, Clone)]
 pub struct APIAuth {
     user: String,
     token: String
@@ -502,33 +737,45 @@ impl APIAuth {
     /// `enterprise`, and `user`.
     /// Reads API tokens from `$HOME/.delivery/api-tokens`.
     /// Lookup for the stored token, if it does not exist request it.
+    /// A token in `DELIVERY_TOKEN` is used as is, for CI systems that
+    /// have no token store.
     pub fn from_config(config: &Config) -> DeliveryResult<APIAuth> {
+        // Recorded exchanges carry no credentials to check ours against
+        if fixtures::replaying() {
+            return Ok(APIAuth{ user: try!(config.user()), token: String::new() })
+        }
+        if let Some(token) = env_variable(TOKEN_ENV_VAR) {
+            debug!("Using the token of {}", TOKEN_ENV_VAR);
+            return Ok(APIAuth{ user: try!(config.user()), token: token })
+        }
         if !try!(http::token::verify(&config)) {
             sayln("red", "Token expired");
             return APIAuth::from_token_request(config)
         }
-        let tstore = match config.token_file {
-            Some(ref f) => {
-                let file = PathBuf::from(f);
-                try!(TokenStore::from_file(&file))
-            },
-            None => try!(TokenStore::from_home())
-        };
-        let api_server = try!(config.api_host_and_port());
-        let ent = try!(config.enterprise());
-        let user = try!(config.user());
-        APIAuth::from_token_store(tstore, &api_server, &ent, &user).or_else(|e| {
+        APIAuth::from_stored_token(config).or_else(|e| {
             debug!("Ignoring {:?}\nRequesting token from config", e);
             APIAuth::from_token_request(&config)
         })
     }
 
+    /// Create an `APIAuth` from the token stored for the configured
+    /// `server`, `enterprise` and `user`, without ever prompting for
+    /// a password. Returns a `NoToken` error if there is none.
+    pub fn from_stored_token(config: &Config) -> DeliveryResult<APIAuth> {
+        let tstore = try!(TokenStore::for_config(config));
+        let api_server = try!(config.api_host_and_port());
+        let ent = try!(config.enterprise());
+        let user = try!(config.user());
+        APIAuth::from_token_store(tstore, &api_server, &ent, &user)
+    }
+
     pub fn from_token_store(tstore: TokenStore,
                             server: &str, ent: &str,
                             user: &str) -> DeliveryResult<APIAuth> {
         match tstore.lookup(server, ent, user) {
             Some(token) => {
                 debug!("Token found");
+                diagnostics::note_token_age(tstore.token_age(server, ent, user));
                 Ok(APIAuth{ user: String::from(user),
                             token: token.clone()})
             },
@@ -547,7 +794,7 @@ impl APIAuth {
         let interactive = !config.non_interactive.unwrap_or(false);
         if interactive {
             let token = try!(TokenStore::request_token(&config));
-            debug!("APIAuth from_token_request: {:?}@{:?}", user, token);
+            debug!("APIAuth from_token_request: {:?}", user);
             Ok(APIAuth{ user: user.clone(), token: token.clone()})
         } else {
             let msg = format!("Unable to request token due to --no-interactive \
@@ -571,6 +818,30 @@ impl APIAuth {
     }
 }
 
+// What the server said about the existence of projects and pipelines,
+// by url. Init checks for, then creates, the same entities; within a
+// single run one answer per entity is enough.
+// The tokens renewed during the run, by host and user
+static RENEWED_TOKENS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+fn renewed_tokens() -> MutexGuard<'static, BTreeMap<String, String>> {
+    RENEWED_TOKENS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+static EXISTS_CACHE: Mutex<BTreeMap<String, bool>> = Mutex::new(BTreeMap::new());
+
+fn exists_cache() -> MutexGuard<'static, BTreeMap<String, bool>> {
+    EXISTS_CACHE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn project_path(org: &str, proj: &str) -> String {
+    format!("orgs/{}/projects/{}", org, proj)
+}
+
+fn pipeline_path(org: &str, proj: &str, pipe: &str) -> String {
+    format!("orgs/{}/projects/{}/pipelines/{}", org, proj, pipe)
+}
+
 #[cfg(test)]
 mod tests {
     pub use super::*;
@@ -601,6 +872,17 @@ mod tests {
         assert_eq!("https://earth/api/v0/e/ncc-1701/foo", url)
     }
 
+    #[test]
+    fn from_config_offline_test() {
+        let mut config = Config::default()
+            .set_enterprise("ncc-1701")
+            .set_server("earth");
+        config.offline = Some(true);
+
+        let err = APIClient::from_config_no_auth(&config).unwrap_err();
+        assert!(assert_enum!(err.kind, Kind::OfflineMode));
+    }
+
     #[test]
     fn from_config_no_auth_override_api_version_test() {
         let config = Config::default()
@@ -613,6 +895,20 @@ mod tests {
         assert_eq!("https://earth/api/v1/e/ncc-1701/foo", url)
     }
 
+    #[test]
+    fn renewed_token_test() {
+        let client = APIClient::new_http("renew.example.com", "ncc-1701");
+        let auth = APIAuth { user: "kirk".to_string(), token: "old".to_string() };
+        assert_eq!("old", client.current_auth(&auth).token());
+        super::renewed_tokens().insert("renew.example.com,kirk".to_string(),
+                                       "new".to_string());
+        assert_eq!("new", client.current_auth(&auth).token());
+        let config = Config::default();
+        assert_eq!("new", client.renew_auth(&auth, &config).unwrap().token());
+        let renewed = client.current_auth(&auth);
+        assert!(client.renew_auth(&renewed, &config).is_none());
+    }
+
     #[test]
     fn from_config_with_basic_routing_test() {
         let config = Config::default()
@@ -622,6 +918,17 @@ mod tests {
         assert_eq!("https://earth/api/_status", url)
     }
 
+    #[test]
+    fn from_config_with_server_url_test() {
+        let config = Config::default()
+            .set_enterprise("ncc-1701")
+            .set_server_url("http://earth:8443/delivery/");
+
+        let client = APIClient::from_config_no_auth(&config).unwrap();
+        let url = client.api_url("foo");
+        assert_eq!("http://earth:8443/delivery/api/v0/e/ncc-1701/foo", url)
+    }
+
     #[test]
     fn from_config_needs_user() {
         let mut config = Config::default()
@@ -925,7 +1232,8 @@ mod tests {
                 let error = tuple.unwrap_err();
                 assert_eq!(
                     error.detail,
-                    Some("Request returned: \'501 Not Implemented\'".to_string())
+                    Some("The server does not support this request (501 Not Implemented)"
+                         .to_string())
                 );
                 match error.kind {
                     super::ApiError(code, _) => {
@@ -935,5 +1243,82 @@ mod tests {
                 };
             }
         }
+
+        mod exists {
+            use super::client;
+            use mockito::mock;
+            use types::{OrgName, ProjectName, PipelineName};
+
+            #[test]
+            fn answers_from_the_cache() {
+                mock("GET", "/api/v0/e/gamer/orgs/hyrule/projects/triforce")
+                    .with_status(200)
+                    .create_for(|| assert!(client().project_exists("hyrule", "triforce")));
+                // Without the mock the server answers 501
+                assert!(client().project_exists("hyrule", "triforce"));
+                assert!(!client().pipeline_exists("hyrule", "triforce", "master"));
+            }
+
+            #[test]
+            fn prefetches_the_project_and_the_pipeline() {
+                mock("GET", "/api/v0/e/gamer/orgs/hyrule/projects/majora")
+                    .with_status(200)
+                    .create_for(|| {
+                        mock("GET", "/api/v0/e/gamer/orgs/hyrule/projects/majora/pipelines/moon")
+                            .with_status(200)
+                            .create_for(|| client().prefetch_exists("hyrule", "majora", "moon"));
+                    });
+                assert!(client().project_exists("hyrule", "majora"));
+                assert!(client().pipeline_exists("hyrule", "majora", "moon"));
+            }
+
+            #[test]
+            fn remembers_created_entities() {
+                mock("POST", "/api/v0/e/gamer/orgs/hyrule/projects/ocarina/pipelines")
+                    .with_status(201)
+                    .create_for(|| {
+                        client().create_pipeline(&OrgName::new("hyrule").unwrap(),
+                                                 &ProjectName::new("ocarina").unwrap(),
+                                                 &PipelineName::new("master").unwrap(),
+                                                 None).unwrap();
+                    });
+                assert!(client().pipeline_exists("hyrule", "ocarina", "master"));
+            }
+
+            #[test]
+            fn forgets_deleted_projects() {
+                mock("GET", "/api/v0/e/gamer/orgs/hyrule/projects/skyward")
+                    .with_status(200)
+                    .create_for(|| {
+                        assert!(client().project_exists("hyrule", "skyward"));
+                        mock("DELETE", "/api/v0/e/gamer/orgs/hyrule/projects/skyward")
+                            .with_status(204)
+                            .create_for(|| {
+                                client().delete_project("hyrule", "skyward").unwrap();
+                            });
+                    });
+                // Without the mock the server answers 501
+                assert!(!client().project_exists("hyrule", "skyward"));
+            }
+
+            #[test]
+            fn follows_renamed_pipelines() {
+                let path = "/api/v0/e/gamer/orgs/hyrule/projects/wind/pipelines";
+                mock("GET", &format!("{}/waker", path))
+                    .with_status(200)
+                    .create_for(|| {
+                        assert!(client().pipeline_exists("hyrule", "wind", "waker"));
+                        mock("PUT", &format!("{}/waker", path))
+                            .with_status(204)
+                            .create_for(|| {
+                                let tide = PipelineName::new("tide").unwrap();
+                                client().rename_pipeline("hyrule", "wind", "waker", &tide)
+                                    .unwrap();
+                            });
+                    });
+                assert!(!client().pipeline_exists("hyrule", "wind", "waker"));
+                assert!(client().pipeline_exists("hyrule", "wind", "tide"));
+            }
+        }
     }
 }
@@ -0,0 +1,1133 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Proxy auto-config (PAC) files
+//!
+//! Some networks only publish their proxies through a PAC file, a
+//! JavaScript function `FindProxyForURL(url, host)` that returns the
+//! proxies to use for a URL, e.g. `"PROXY proxy.corp:8080; DIRECT"`.
+//! A PAC file is only used when `proxy_pac_url` in the cli.toml points
+//! at it, the system proxy settings are not looked at. It is fetched
+//! with the `ca_cert` and client certificate of the cli.toml. We
+//! evaluate the part of JavaScript these files are written in
+//! (variables, `if`/`else`, comparisons, string methods and the PAC
+//! helper functions) for the Delivery server, and the API client goes
+//! through the first HTTP proxy the function returns. A script using
+//! anything else fails to evaluate, and is reported and ignored.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::prelude::*;
+use std::net::{IpAddr, Ipv4Addr, ToSocketAddrs, UdpSocket};
+use std::sync::Mutex;
+use hyper::header::{Authorization, Basic, HeaderFormatter};
+use regex::Regex;
+use http::headers;
+use http::proxy;
+use http::tls::Tls;
+use config::Config;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+use utils::say::sayln;
+
+/// The evaluated proxy of each PAC url and host, so a run fetches and
+/// evaluates the PAC file once
+static PROXIES: Mutex<BTreeMap<String, Option<Proxy>>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proxy {
+    pub host: String,
+    pub port: u16,
+    pub credentials: Option<Basic>,
+}
+
+impl Proxy {
+    /// The `Proxy-Authorization` header of an authenticated proxy
+    pub fn authorization(&self) -> Option<headers::ProxyAuthorization> {
+        self.credentials.as_ref().map(|basic| {
+            let header = Authorization(basic.clone());
+            headers::ProxyAuthorization(HeaderFormatter(&header).to_string())
+        })
+    }
+}
+
+/// The proxy to reach `url` on `host` through, `None` to connect
+/// directly. A PAC file that cannot be fetched or evaluated is reported
+/// and ignored.
+pub fn proxy_for(config: &Config, url: &str, host: &str) -> Option<Proxy> {
+    let pac_url = match config.proxy_pac_url.clone() {
+        Some(pac_url) => pac_url,
+        None => return None
+    };
+    let key = format!("{} {}", pac_url, host);
+    let mut cache = PROXIES.lock().unwrap();
+    if let Some(proxy) = cache.get(&key) {
+        return proxy.clone()
+    }
+    let fetched = Tls::from_config(config).and_then(|tls| fetch(&pac_url, &tls));
+    let proxy = match fetched.and_then(|script| evaluate(&script, url, host)) {
+        Ok(result) => {
+            debug!("FindProxyForURL({}, {}) of {}: {}", url, host, pac_url, result);
+            parse_result(&result)
+        },
+        Err(e) => {
+            sayln("yellow", &format!("WARN: Ignoring the proxy auto-config file {}: {}",
+                                     pac_url, e.detail().unwrap_or(e.to_string())));
+            None
+        }
+    };
+    cache.insert(key, proxy.clone());
+    proxy
+}
+
+/// The content of the PAC file at `pac_url`, a URL or a local path. It
+/// is fetched directly, trusting what `tls` trusts.
+pub fn fetch(pac_url: &str, tls: &Tls) -> DeliveryResult<String> {
+    let mut script = String::new();
+    let path = pac_url.trim_left_matches("file://");
+    if !pac_url.starts_with("http://") && !pac_url.starts_with("https://") {
+        try!(try!(File::open(path)).read_to_string(&mut script));
+        return Ok(script)
+    }
+    let client = match proxy::client(None, tls) {
+        Ok(client) => client,
+        Err(e) => return Err(pac_error(&format!("Unable to fetch {}: {}", pac_url, e)))
+    };
+    let mut response = match client.get(pac_url).send() {
+        Ok(response) => response,
+        Err(e) => return Err(pac_error(&format!("Unable to fetch {}: {}", pac_url, e)))
+    };
+    if !response.status.is_success() {
+        return Err(pac_error(&format!("Unable to fetch {}: {}", pac_url, response.status)))
+    }
+    try!(response.read_to_string(&mut script));
+    Ok(script)
+}
+
+/// Run `FindProxyForURL(url, host)` of the PAC `script`
+pub fn evaluate(script: &str, url: &str, host: &str) -> DeliveryResult<String> {
+    let program = try!(Parser::new(try!(tokenize(script))).program());
+    let mut interpreter = Interpreter::new(Box::new(resolve));
+    for statement in program.iter() {
+        try!(interpreter.exec(statement));
+    }
+    let args = vec![Value::Str(url.to_string()), Value::Str(host.to_string())];
+    match try!(interpreter.call("FindProxyForURL", args)) {
+        Value::Str(result) => Ok(result),
+        other => Err(pac_error(&format!("FindProxyForURL returned {:?}", other)))
+    }
+}
+
+/// The first HTTP proxy of a `FindProxyForURL` result, `None` when the
+/// result says to connect directly first. SOCKS proxies are skipped.
+pub fn parse_result(result: &str) -> Option<Proxy> {
+    for entry in result.split(';') {
+        let mut words = entry.split_whitespace();
+        let kind = words.next().unwrap_or("").to_uppercase();
+        match kind.as_ref() {
+            "DIRECT" => return None,
+            "PROXY" | "HTTP" => {
+                if let Some(proxy) = words.next().and_then(parse_host_port) {
+                    return Some(proxy)
+                }
+            },
+            _ => continue
+        }
+    }
+    None
+}
+
+fn parse_host_port(address: &str) -> Option<Proxy> {
+    let mut parts = address.rsplitn(2, ':');
+    match (parts.next(), parts.next()) {
+        (Some(port), Some(host)) => port.parse().ok().map(|port| {
+            Proxy { host: host.to_string(), port: port, credentials: None }
+        }),
+        (Some(host), None) => Some(Proxy { host: host.to_string(), port: 80, credentials: None }),
+        _ => None
+    }
+}
+
+fn pac_error(msg: &str) -> DeliveryError {
+    DeliveryError::throw(Kind::ProxyAutoConfigFailed, Some(msg.to_string()))
+}
+
+fn resolve(host: &str) -> Option<IpAddr> {
+    (host, 0).to_socket_addrs().ok()
+        .and_then(|mut addrs| addrs.find(|a| a.is_ipv4()))
+        .map(|a| a.ip())
+}
+
+// The address of the interface that routes to the outside, no packet
+// is sent to find it.
+fn my_ip_address() -> IpAddr {
+    UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| socket.connect("198.51.100.1:80").map(|_| socket))
+        .and_then(|socket| socket.local_addr())
+        .map(|addr| addr.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Punct(&'static str),
+}
+
+const PUNCTUATION: [&'static str; 21] = ["===", "!==", "==", "!=", "<=", ">=", "&&", "||",
+                                         "(", ")", "{", "}", ";", ",", ".", "!", "=", "+",
+                                         "-", "<", ">"];
+
+fn tokenize(source: &str) -> DeliveryResult<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let rest: String = chars[i..].iter().take(3).collect();
+        if c.is_whitespace() {
+            i += 1;
+        } else if rest.starts_with("//") {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if rest.starts_with("/*") {
+            i += 2;
+            while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '"' || c == '\'' {
+            let mut s = String::new();
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                if chars[i] == '\\' && i + 1 < chars.len() {
+                    i += 1;
+                }
+                s.push(chars[i]);
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err(pac_error("Unterminated string"))
+            }
+            i += 1;
+            tokens.push(Token::Str(s));
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            match number.parse() {
+                Ok(n) => tokens.push(Token::Num(n)),
+                Err(_) => return Err(pac_error(&format!("Invalid number {}", number)))
+            }
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_'
+                                      || chars[i] == '$') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            match PUNCTUATION.iter().find(|p| rest.starts_with(*p)) {
+                Some(p) => {
+                    i += p.len();
+                    tokens.push(Token::Punct(p));
+                },
+                None => return Err(pac_error(&format!("Unsupported character '{}'", c)))
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Str(String),
+    Num(f64),
+    Var(String),
+    Call(String, Vec<Expr>),
+    Member(Box<Expr>, String, Option<Vec<Expr>>),
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    Binary(Box<Expr>, &'static str, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Stmt {
+    Function(String, Vec<String>, Vec<Stmt>),
+    If(Expr, Box<Stmt>, Option<Box<Stmt>>),
+    Return(Expr),
+    // `var`, `let` and `const`, in the scope of the function
+    Declare(Vec<(String, Expr)>),
+    Assign(String, Expr),
+    Block(Vec<Stmt>),
+    Expr(Expr),
+    Empty,
+}
+
+// How deep statements and expressions may nest, a script can not run
+// the parser, or the interpreter after it, out of stack
+const MAX_DEPTH: usize = 100;
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>) -> Parser {
+        Parser { tokens: tokens, pos: 0, depth: 0 }
+    }
+
+    fn enter(&mut self) -> DeliveryResult<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(pac_error("The script nests too deep"))
+        }
+        Ok(())
+    }
+
+    fn program(&mut self) -> DeliveryResult<Vec<Stmt>> {
+        let mut statements = vec![];
+        while self.pos < self.tokens.len() {
+            statements.push(try!(self.statement()));
+        }
+        Ok(statements)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> DeliveryResult<Token> {
+        match self.tokens.get(self.pos).cloned() {
+            Some(token) => {
+                self.pos += 1;
+                Ok(token)
+            },
+            None => Err(pac_error("Unexpected end of the script"))
+        }
+    }
+
+    fn is_punct(&self, punct: &str) -> bool {
+        self.peek() == Some(&Token::Punct(match PUNCTUATION.iter().find(|p| **p == punct) {
+            Some(p) => p,
+            None => return false
+        }))
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(&Token::Ident(ref name)) => name == keyword,
+            _ => false
+        }
+    }
+
+    fn expect(&mut self, punct: &str) -> DeliveryResult<()> {
+        if self.is_punct(punct) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(pac_error(&format!("Expected '{}', found {:?}", punct, self.peek())))
+        }
+    }
+
+    fn ident(&mut self) -> DeliveryResult<String> {
+        match try!(self.next()) {
+            Token::Ident(name) => Ok(name),
+            other => Err(pac_error(&format!("Expected a name, found {:?}", other)))
+        }
+    }
+
+    fn statement(&mut self) -> DeliveryResult<Stmt> {
+        try!(self.enter());
+        let statement = self.nested_statement();
+        self.depth -= 1;
+        statement
+    }
+
+    fn nested_statement(&mut self) -> DeliveryResult<Stmt> {
+        if self.is_punct(";") {
+            self.pos += 1;
+            return Ok(Stmt::Empty)
+        }
+        if self.is_punct("{") {
+            return self.block().map(Stmt::Block)
+        }
+        if self.is_keyword("function") {
+            self.pos += 1;
+            let name = try!(self.ident());
+            try!(self.expect("("));
+            let mut params = vec![];
+            while !self.is_punct(")") {
+                params.push(try!(self.ident()));
+                if !self.is_punct(")") {
+                    try!(self.expect(","));
+                }
+            }
+            try!(self.expect(")"));
+            let body = try!(self.block());
+            return Ok(Stmt::Function(name, params, body))
+        }
+        if self.is_keyword("if") {
+            self.pos += 1;
+            try!(self.expect("("));
+            let condition = try!(self.expression());
+            try!(self.expect(")"));
+            let then = try!(self.statement());
+            let otherwise = if self.is_keyword("else") {
+                self.pos += 1;
+                Some(Box::new(try!(self.statement())))
+            } else {
+                None
+            };
+            return Ok(Stmt::If(condition, Box::new(then), otherwise))
+        }
+        let statement = if self.is_keyword("return") {
+            self.pos += 1;
+            Stmt::Return(try!(self.expression()))
+        } else if self.is_keyword("var") || self.is_keyword("let") || self.is_keyword("const") {
+            self.pos += 1;
+            let mut declared = vec![];
+            loop {
+                let name = try!(self.ident());
+                let value = if self.is_punct("=") {
+                    self.pos += 1;
+                    try!(self.expression())
+                } else {
+                    Expr::Var("undefined".to_string())
+                };
+                declared.push((name, value));
+                if !self.is_punct(",") {
+                    break
+                }
+                self.pos += 1;
+            }
+            Stmt::Declare(declared)
+        } else {
+            match (self.peek().cloned(), self.tokens.get(self.pos + 1)) {
+                (Some(Token::Ident(name)), Some(&Token::Punct("="))) => {
+                    self.pos += 2;
+                    Stmt::Assign(name, try!(self.expression()))
+                },
+                _ => Stmt::Expr(try!(self.expression()))
+            }
+        };
+        if self.is_punct(";") {
+            self.pos += 1;
+        }
+        Ok(statement)
+    }
+
+    fn block(&mut self) -> DeliveryResult<Vec<Stmt>> {
+        try!(self.expect("{"));
+        let mut statements = vec![];
+        while !self.is_punct("}") {
+            statements.push(try!(self.statement()));
+        }
+        try!(self.expect("}"));
+        Ok(statements)
+    }
+
+    fn expression(&mut self) -> DeliveryResult<Expr> {
+        self.binary(0)
+    }
+
+    // Binary operators from the loosest to the tightest binding
+    fn binary(&mut self, level: usize) -> DeliveryResult<Expr> {
+        const LEVELS: [&'static [&'static str]; 4] = [&["||"], &["&&"],
+                                                       &["===", "!==", "==", "!=", "<=",
+                                                         ">=", "<", ">"],
+                                                       &["+", "-"]];
+        if level == LEVELS.len() {
+            return self.unary()
+        }
+        let mut left = try!(self.binary(level + 1));
+        loop {
+            let op = match LEVELS[level].iter().find(|op| self.is_punct(op)) {
+                Some(op) => *op,
+                None => return Ok(left)
+            };
+            self.pos += 1;
+            let right = try!(self.binary(level + 1));
+            left = Expr::Binary(Box::new(left), op, Box::new(right));
+        }
+    }
+
+    fn unary(&mut self) -> DeliveryResult<Expr> {
+        try!(self.enter());
+        let expr = self.nested_unary();
+        self.depth -= 1;
+        expr
+    }
+
+    fn nested_unary(&mut self) -> DeliveryResult<Expr> {
+        if self.is_punct("!") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(try!(self.unary()))))
+        }
+        if self.is_punct("-") {
+            self.pos += 1;
+            return Ok(Expr::Negate(Box::new(try!(self.unary()))))
+        }
+        let mut expr = try!(self.primary());
+        while self.is_punct(".") {
+            self.pos += 1;
+            let name = try!(self.ident());
+            let args = if self.is_punct("(") { Some(try!(self.arguments())) } else { None };
+            expr = Expr::Member(Box::new(expr), name, args);
+        }
+        Ok(expr)
+    }
+
+    fn arguments(&mut self) -> DeliveryResult<Vec<Expr>> {
+        try!(self.expect("("));
+        let mut args = vec![];
+        while !self.is_punct(")") {
+            args.push(try!(self.expression()));
+            if !self.is_punct(")") {
+                try!(self.expect(","));
+            }
+        }
+        try!(self.expect(")"));
+        Ok(args)
+    }
+
+    fn primary(&mut self) -> DeliveryResult<Expr> {
+        match try!(self.next()) {
+            Token::Str(s) => Ok(Expr::Str(s)),
+            Token::Num(n) => Ok(Expr::Num(n)),
+            Token::Punct("(") => {
+                let expr = try!(self.expression());
+                try!(self.expect(")"));
+                Ok(expr)
+            },
+            Token::Ident(name) => {
+                if self.is_punct("(") {
+                    Ok(Expr::Call(name, try!(self.arguments())))
+                } else {
+                    Ok(Expr::Var(name))
+                }
+            },
+            other => Err(pac_error(&format!("Unexpected {:?}", other)))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Str(String),
+    Num(f64),
+    Bool(bool),
+    Undefined,
+}
+
+impl Value {
+    fn truthy(&self) -> bool {
+        match *self {
+            Value::Str(ref s) => !s.is_empty(),
+            Value::Num(n) => n != 0.0,
+            Value::Bool(b) => b,
+            Value::Undefined => false,
+        }
+    }
+
+    fn string(&self) -> String {
+        match *self {
+            Value::Str(ref s) => s.clone(),
+            Value::Num(n) => format!("{}", n),
+            Value::Bool(b) => format!("{}", b),
+            Value::Undefined => "undefined".to_string(),
+        }
+    }
+
+    fn number(&self) -> f64 {
+        match *self {
+            Value::Str(ref s) => s.trim().parse().unwrap_or(::std::f64::NAN),
+            Value::Num(n) => n,
+            Value::Bool(b) => if b { 1.0 } else { 0.0 },
+            Value::Undefined => ::std::f64::NAN,
+        }
+    }
+
+    // `==`, converting to numbers unless both sides are strings
+    fn loose_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (&Value::Str(ref a), &Value::Str(ref b)) => a == b,
+            (&Value::Undefined, &Value::Undefined) => true,
+            (&Value::Undefined, _) | (_, &Value::Undefined) => false,
+            _ => self.number() == other.number()
+        }
+    }
+}
+
+type Resolver = Box<Fn(&str) -> Option<IpAddr>>;
+
+struct Interpreter {
+    functions: HashMap<String, (Vec<String>, Vec<Stmt>)>,
+    scopes: Vec<HashMap<String, Value>>,
+    resolve: Resolver,
+}
+
+impl Interpreter {
+    fn new(resolve: Resolver) -> Interpreter {
+        Interpreter { functions: HashMap::new(), scopes: vec![HashMap::new()], resolve: resolve }
+    }
+
+    // Runs `statement`, the value of a `return` ends the function
+    fn exec(&mut self, statement: &Stmt) -> DeliveryResult<Option<Value>> {
+        match *statement {
+            Stmt::Function(ref name, ref params, ref body) => {
+                self.functions.insert(name.clone(), (params.clone(), body.clone()));
+            },
+            Stmt::If(ref condition, ref then, ref otherwise) => {
+                if try!(self.eval(condition)).truthy() {
+                    return self.exec(then)
+                } else if let Some(ref otherwise) = *otherwise {
+                    return self.exec(otherwise)
+                }
+            },
+            Stmt::Return(ref expr) => return self.eval(expr).map(Some),
+            Stmt::Declare(ref declared) => {
+                for &(ref name, ref expr) in declared.iter() {
+                    let value = try!(self.eval(expr));
+                    let scope = self.scopes.len() - 1;
+                    self.scopes[scope].insert(name.clone(), value);
+                }
+            },
+            Stmt::Assign(ref name, ref expr) => {
+                let value = try!(self.eval(expr));
+                let global = !self.scopes.last().map(|s| s.contains_key(name)).unwrap_or(false)
+                    && self.scopes[0].contains_key(name);
+                let scope = if global { 0 } else { self.scopes.len() - 1 };
+                self.scopes[scope].insert(name.clone(), value);
+            },
+            Stmt::Block(ref statements) => {
+                for statement in statements.iter() {
+                    if let Some(value) = try!(self.exec(statement)) {
+                        return Ok(Some(value))
+                    }
+                }
+            },
+            Stmt::Expr(ref expr) => {
+                try!(self.eval(expr));
+            },
+            Stmt::Empty => {}
+        }
+        Ok(None)
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Value>) -> DeliveryResult<Value> {
+        let (params, body) = match self.functions.get(name) {
+            Some(function) => function.clone(),
+            None => return self.builtin(name, args)
+        };
+        if self.scopes.len() > 100 {
+            return Err(pac_error("Too much recursion"))
+        }
+        let mut scope = HashMap::new();
+        for (i, param) in params.iter().enumerate() {
+            scope.insert(param.clone(), args.get(i).cloned().unwrap_or(Value::Undefined));
+        }
+        self.scopes.push(scope);
+        let result = self.exec(&Stmt::Block(body));
+        self.scopes.pop();
+        result.map(|value| value.unwrap_or(Value::Undefined))
+    }
+
+    fn eval(&mut self, expr: &Expr) -> DeliveryResult<Value> {
+        match *expr {
+            Expr::Str(ref s) => Ok(Value::Str(s.clone())),
+            Expr::Num(n) => Ok(Value::Num(n)),
+            Expr::Var(ref name) => {
+                match name.as_ref() {
+                    "true" => return Ok(Value::Bool(true)),
+                    "false" => return Ok(Value::Bool(false)),
+                    "null" | "undefined" => return Ok(Value::Undefined),
+                    _ => {}
+                }
+                let local = self.scopes.last().and_then(|s| s.get(name)).cloned();
+                match local.or_else(|| self.scopes[0].get(name).cloned()) {
+                    Some(value) => Ok(value),
+                    None => Err(pac_error(&format!("{} is not defined", name)))
+                }
+            },
+            Expr::Call(ref name, ref args) => {
+                let mut values = vec![];
+                for arg in args.iter() {
+                    values.push(try!(self.eval(arg)));
+                }
+                self.call(name, values)
+            },
+            Expr::Member(ref object, ref name, ref args) => {
+                let object = try!(self.eval(object)).string();
+                let mut values = vec![];
+                for arg in args.iter().flat_map(|a| a.iter()) {
+                    values.push(try!(self.eval(arg)));
+                }
+                string_member(&object, name, args.is_some(), &values)
+            },
+            Expr::Not(ref expr) => Ok(Value::Bool(!try!(self.eval(expr)).truthy())),
+            Expr::Negate(ref expr) => Ok(Value::Num(-try!(self.eval(expr)).number())),
+            Expr::Binary(ref left, op, ref right) => {
+                let left = try!(self.eval(left));
+                // Short-circuit like JavaScript does, the right side may
+                // well do a DNS lookup
+                match op {
+                    "||" if left.truthy() => return Ok(left),
+                    "&&" if !left.truthy() => return Ok(left),
+                    "||" | "&&" => return self.eval(right),
+                    _ => {}
+                }
+                let right = try!(self.eval(right));
+                Ok(match op {
+                    "==" => Value::Bool(left.loose_eq(&right)),
+                    "!=" => Value::Bool(!left.loose_eq(&right)),
+                    "===" => Value::Bool(left == right),
+                    "!==" => Value::Bool(left != right),
+                    "<" => Value::Bool(left.number() < right.number()),
+                    ">" => Value::Bool(left.number() > right.number()),
+                    "<=" => Value::Bool(left.number() <= right.number()),
+                    ">=" => Value::Bool(left.number() >= right.number()),
+                    "-" => Value::Num(left.number() - right.number()),
+                    _ => match (&left, &right) {
+                        (&Value::Num(l), &Value::Num(r)) => Value::Num(l + r),
+                        _ => Value::Str(left.string() + &right.string()),
+                    },
+                })
+            },
+        }
+    }
+
+    // The functions every PAC file can call
+    fn builtin(&mut self, name: &str, args: Vec<Value>) -> DeliveryResult<Value> {
+        let arg = |i: usize| args.get(i).map(|a| a.string()).unwrap_or_default();
+        Ok(match name {
+            "isPlainHostName" => Value::Bool(!arg(0).contains('.')),
+            "dnsDomainIs" => Value::Bool(arg(0).to_lowercase().ends_with(&arg(1).to_lowercase())),
+            "localHostOrDomainIs" => {
+                let (host, hostdom) = (arg(0).to_lowercase(), arg(1).to_lowercase());
+                Value::Bool(host == hostdom ||
+                            (!host.contains('.') && hostdom.starts_with(&format!("{}.", host))))
+            },
+            "dnsDomainLevels" => Value::Num(arg(0).matches('.').count() as f64),
+            "shExpMatch" => Value::Bool(sh_exp_match(&arg(0), &arg(1))),
+            "isResolvable" => Value::Bool(self.lookup(&arg(0)).is_some()),
+            "dnsResolve" => match self.lookup(&arg(0)) {
+                Some(ip) => Value::Str(ip.to_string()),
+                None => Value::Undefined
+            },
+            "isInNet" => match (self.lookup(&arg(0)), arg(1).parse(), arg(2).parse()) {
+                (Some(IpAddr::V4(ip)), Ok(pattern), Ok(mask)) => {
+                    Value::Bool(in_net(ip, pattern, mask))
+                },
+                _ => Value::Bool(false)
+            },
+            "myIpAddress" => Value::Str(my_ip_address().to_string()),
+            "alert" => Value::Undefined,
+            _ => return Err(pac_error(&format!("Unsupported function {}", name)))
+        })
+    }
+
+    fn lookup(&self, host: &str) -> Option<IpAddr> {
+        host.parse().ok().or_else(|| (self.resolve)(host))
+    }
+}
+
+fn string_member(object: &str, name: &str, call: bool,
+                 args: &[Value]) -> DeliveryResult<Value> {
+    let arg = |i: usize| args.get(i).map(|a| a.string()).unwrap_or_default();
+    Ok(match (name, call) {
+        ("length", false) => Value::Num(object.chars().count() as f64),
+        ("toLowerCase", true) => Value::Str(object.to_lowercase()),
+        ("toUpperCase", true) => Value::Str(object.to_uppercase()),
+        ("indexOf", true) => Value::Num(object.find(&arg(0))
+                                        .map(|i| object[..i].chars().count() as f64)
+                                        .unwrap_or(-1.0)),
+        ("substring", true) => {
+            let chars: Vec<char> = object.chars().collect();
+            let index = |i: usize, default: usize| args.get(i)
+                .map(|a| a.number())
+                .map(|n| if n.is_nan() || n < 0.0 { 0 } else { n as usize })
+                .unwrap_or(default)
+                .min(chars.len());
+            let (a, b) = (index(0, 0), index(1, chars.len()));
+            Value::Str(chars[a.min(b)..a.max(b)].iter().collect())
+        },
+        _ => return Err(pac_error(&format!("Unsupported string member {}", name)))
+    })
+}
+
+// Shell expression matching of PAC files: `*` and `?` wildcards
+fn sh_exp_match(s: &str, pattern: &str) -> bool {
+    let mut re = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            _ => re.push_str(&::regex::quote(&c.to_string())),
+        }
+    }
+    re.push('$');
+    Regex::new(&re).map(|re| re.is_match(s)).unwrap_or(false)
+}
+
+fn in_net(ip: Ipv4Addr, pattern: Ipv4Addr, mask: Ipv4Addr) -> bool {
+    let (ip, pattern, mask) = (u32::from(ip), u32::from(pattern), u32::from(mask));
+    ip & mask == pattern & mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::{Interpreter, Parser, tokenize, sh_exp_match, in_net};
+    use std::cell::Cell;
+    use std::fs::File;
+    use std::io::Write;
+    use std::net::IpAddr;
+    use std::rc::Rc;
+    use mockito::{SERVER_URL, mock};
+    use tempdir::TempDir;
+    use config::Config;
+    use http::tls::Tls;
+
+    const PAC: &'static str = r#"
+        // Internal hosts go direct
+        var corp = "corp.example.com";
+        function isInternal(host) {
+            return isPlainHostName(host) || dnsDomainIs(host, "." + corp);
+        }
+        function FindProxyForURL(url, host) {
+            host = host.toLowerCase();
+            if (isInternal(host) || isInNet(host, "10.0.0.0", "255.0.0.0"))
+                return "DIRECT";
+            else if (shExpMatch(url, "https://*.example.org/*")) {
+                return 'PROXY secure.example.com:3128; DIRECT';
+            }
+            /* everything else */
+            return "PROXY proxy.example.com:8080; PROXY backup.example.com:8080";
+        }
+    "#;
+
+    // `FindProxyForURL` of `script`, with a resolver that only knows
+    // automate.example.net and counts its lookups in `lookups`
+    fn run(script: &str, url: &str, host: &str,
+           lookups: Rc<Cell<usize>>) -> DeliveryResult<super::Value> {
+        let program = try!(Parser::new(try!(tokenize(script))).program());
+        let resolve = move |host: &str| -> Option<IpAddr> {
+            lookups.set(lookups.get() + 1);
+            if host == "automate.example.net" { "10.1.2.3".parse().ok() } else { None }
+        };
+        let mut interpreter = Interpreter::new(Box::new(resolve));
+        for statement in program.iter() {
+            try!(interpreter.exec(statement));
+        }
+        interpreter.call("FindProxyForURL", vec![super::Value::Str(url.to_string()),
+                                                 super::Value::Str(host.to_string())])
+    }
+
+    fn eval_with(script: &str, url: &str, host: &str) -> String {
+        match run(script, url, host, Rc::new(Cell::new(0))) {
+            Ok(super::Value::Str(result)) => result,
+            other => panic!("Unexpected result {:?}", other)
+        }
+    }
+
+    // The value of the JavaScript expression `expr`, as a string
+    fn expr(expr: &str) -> String {
+        eval_with(&format!("function FindProxyForURL(url, host) {{ return \"\" + ({}); }}",
+                           expr), "http://a/", "a")
+    }
+
+    #[test]
+    fn test_evaluate_pac() {
+        assert_eq!("DIRECT", eval_with(PAC, "https://delivery/", "delivery"));
+        assert_eq!("DIRECT", eval_with(PAC, "https://a.corp.example.com/", "A.Corp.Example.com"));
+        assert_eq!("DIRECT", eval_with(PAC, "https://automate.example.net/", "automate.example.net"));
+        assert_eq!("PROXY secure.example.com:3128; DIRECT",
+                   eval_with(PAC, "https://automate.example.org/e/x", "automate.example.org"));
+        assert_eq!("PROXY proxy.example.com:8080; PROXY backup.example.com:8080",
+                   eval_with(PAC, "https://automate.example.com/", "automate.example.com"));
+    }
+
+    #[test]
+    fn test_evaluate_errors() {
+        assert!(evaluate("function FindProxyForURL(url, host) { return timeRange(8, 18); }",
+                         "https://a/", "a").is_err());
+        assert!(evaluate("function FindProxyForURL(url, host) { return \"DIRECT\"",
+                         "https://a/", "a").is_err());
+        assert!(evaluate("var x = 1;", "https://a/", "a").is_err());
+    }
+
+    #[test]
+    fn test_parse_result() {
+        assert_eq!(None, parse_result("DIRECT"));
+        assert_eq!(None, parse_result("DIRECT; PROXY proxy:8080"));
+        assert_eq!(Some(Proxy { host: "proxy".to_string(), port: 8080, credentials: None }),
+                   parse_result("SOCKS socks:1080; PROXY proxy:8080; DIRECT"));
+        assert_eq!(Some(Proxy { host: "proxy".to_string(), port: 80, credentials: None }),
+                   parse_result(" PROXY proxy "));
+        assert_eq!(None, parse_result(""));
+    }
+
+    #[test]
+    fn test_sh_exp_match() {
+        assert!(sh_exp_match("http://home.example.com/x", "*.example.com/*"));
+        assert!(sh_exp_match("a1", "a?"));
+        assert!(!sh_exp_match("a.b", "a?c"));
+    }
+
+    #[test]
+    fn test_operators() {
+        assert_eq!("true", expr("true || false && false"));
+        assert_eq!("false", expr("(true || false) && false"));
+        assert_eq!("true", expr("1 + 2 == 3"));
+        assert_eq!("12", expr("\"1\" + 2"));
+        assert_eq!("-1", expr("2 - 3"));
+        assert_eq!("-5", expr("-(2 + 3)"));
+        assert_eq!("true", expr("!!\"x\""));
+        assert_eq!("false", expr("!\"x\""));
+        assert_eq!("true", expr("\"10\" > 9"));
+        assert_eq!("true", expr("1 <= 1 && 1 >= 1 && !(1 < 1) && !(1 > 1)"));
+        assert_eq!("b", expr("\"\" || \"b\""));
+        assert_eq!("0", expr("0 && \"b\""));
+        assert_eq!("undefined", expr("null"));
+    }
+
+    #[test]
+    fn test_equality() {
+        assert_eq!("true", expr("\"1\" == 1"));
+        assert_eq!("false", expr("\"1\" === 1"));
+        assert_eq!("true", expr("\"1\" !== 1"));
+        assert_eq!("false", expr("\"1\" != 1"));
+        assert_eq!("true", expr("true == 1"));
+        assert_eq!("true", expr("null == undefined"));
+        assert_eq!("false", expr("null == 0"));
+        assert_eq!("false", expr("\"a\" == \"A\""));
+    }
+
+    #[test]
+    fn test_statements() {
+        let pac = r#"
+            var level = 0, name;
+            function describe(host) {
+                var level = dnsDomainLevels(host);
+                if (level == 0) return "plain";
+                else if (level == 1) { return "short"; }
+                else {
+                    if (level > 3) return "deep";
+                }
+                name = host;
+                return "long";
+            }
+            function FindProxyForURL(url, host) {
+                var kind = describe(host);;
+                return kind + " " + level + " " + name;
+            }
+        "#;
+        assert_eq!("plain 0 undefined", eval_with(pac, "", "a"));
+        assert_eq!("short 0 undefined", eval_with(pac, "", "a.b"));
+        assert_eq!("long 0 a.b.c", eval_with(pac, "", "a.b.c"));
+        assert_eq!("deep 0 undefined", eval_with(pac, "", "a.b.c.d.e"));
+    }
+
+    #[test]
+    fn test_tokens() {
+        let pac = "/* a PAC file\n * with comments */\n\
+                   function FindProxyForURL(url, host) { // the entry point\n\
+                   \treturn 'it\\'s' + \"\\\"\" + \"// not a comment\"; }\n\
+                   // the end";
+        assert_eq!("it's\"// not a comment", eval_with(pac, "", ""));
+        assert_eq!("0.5", expr("0.5"));
+        assert!(tokenize("var a = 'open").is_err());
+        assert!(tokenize("var a = 1.2.3").is_err());
+        assert!(tokenize("var a = b # c").is_err());
+    }
+
+    #[test]
+    fn test_pac_functions() {
+        let call = |f: &str| eval_with(&format!("function FindProxyForURL(url, host) {{ \
+                                                 return \"\" + {}; }}", f), "", "");
+        assert_eq!("true", call("isPlainHostName(\"delivery\")"));
+        assert_eq!("false", call("isPlainHostName(\"delivery.corp\")"));
+        assert_eq!("true", call("dnsDomainIs(\"www.Corp.com\", \".corp.COM\")"));
+        assert_eq!("false", call("dnsDomainIs(\"www.corp.com\", \".corp.org\")"));
+        assert_eq!("true", call("localHostOrDomainIs(\"www\", \"www.corp.com\")"));
+        assert_eq!("true", call("localHostOrDomainIs(\"www.corp.com\", \"www.corp.com\")"));
+        assert_eq!("false", call("localHostOrDomainIs(\"www.corp.org\", \"www.corp.com\")"));
+        assert_eq!("2", call("dnsDomainLevels(\"www.corp.com\")"));
+        assert_eq!("10.1.2.3", call("dnsResolve(\"automate.example.net\")"));
+        assert_eq!("undefined", call("dnsResolve(\"unknown.example.net\")"));
+        assert_eq!("true", call("isResolvable(\"automate.example.net\")"));
+        assert_eq!("false", call("isResolvable(\"unknown.example.net\")"));
+        assert_eq!("true", call("isInNet(\"automate.example.net\", \"10.1.0.0\", \
+                                 \"255.255.0.0\")"));
+        assert_eq!("false", call("isInNet(\"automate.example.net\", \"10.2.0.0\", \
+                                  \"255.255.0.0\")"));
+        assert_eq!("true", call("isInNet(\"192.168.1.20\", \"192.168.1.0\", \
+                                 \"255.255.255.0\")"));
+        assert_eq!("false", call("isInNet(\"unknown.example.net\", \"0.0.0.0\", \
+                                  \"0.0.0.0\")"));
+        assert_eq!("false", call("isInNet(\"10.1.2.3\", \"10.0.0.0\", \"not a mask\")"));
+        assert!(call("myIpAddress()").parse::<IpAddr>().is_ok());
+        assert_eq!("undefined", call("alert(\"hello\")"));
+    }
+
+    #[test]
+    fn test_in_net() {
+        let ip = |s: &str| s.parse().unwrap();
+        assert!(in_net(ip("10.1.2.3"), ip("10.0.0.0"), ip("255.0.0.0")));
+        assert!(!in_net(ip("11.1.2.3"), ip("10.0.0.0"), ip("255.0.0.0")));
+        assert!(in_net(ip("11.1.2.3"), ip("0.0.0.0"), ip("0.0.0.0")));
+    }
+
+    #[test]
+    fn test_lookups_short_circuit() {
+        let pac = r#"function FindProxyForURL(url, host) {
+            if (isPlainHostName(host) || isResolvable(host)) return "DIRECT";
+            return "PROXY proxy:8080";
+        }"#;
+        let lookups = Rc::new(Cell::new(0));
+        assert_eq!(super::Value::Str("DIRECT".to_string()),
+                   run(pac, "", "delivery", lookups.clone()).unwrap());
+        assert_eq!(0, lookups.get());
+        assert_eq!(super::Value::Str("PROXY proxy:8080".to_string()),
+                   run(pac, "", "unknown.example.net", lookups.clone()).unwrap());
+        assert_eq!(1, lookups.get());
+        // Addresses are not looked up
+        run("function FindProxyForURL(url, host) { return dnsResolve(\"10.0.0.1\"); }",
+            "", "", lookups.clone()).unwrap();
+        assert_eq!(1, lookups.get());
+    }
+
+    #[test]
+    fn test_unsupported_scripts_fail() {
+        let unsupported = [
+            // Syntax beyond what PAC files are evaluated with
+            "function FindProxyForURL(url, host) { return host ? \"DIRECT\" : \"\"; }",
+            "function FindProxyForURL(url, host) { return /corp/.test(host); }",
+            "function FindProxyForURL(url, host) { var a = [1]; return \"DIRECT\"; }",
+            "function FindProxyForURL(url, host) { switch (host) {} }",
+            "function FindProxyForURL(url, host) { return 2 * 3; }",
+            // Functions and members that are not there
+            "function FindProxyForURL(url, host) { return weekdayRange(\"MON\", \"FRI\"); }",
+            "function FindProxyForURL(url, host) { return host.split(\".\"); }",
+            "function FindProxyForURL(url, host) { return host.length(); }",
+            "function FindProxyForURL(url, host) { return unknown; }",
+            // Broken scripts
+            "function FindProxyForURL(url, host) { return \"DIRECT\";",
+            "function FindProxyForURL(url, host { return \"DIRECT\"; }",
+            "function (url, host) { return \"DIRECT\"; }",
+            "var = 1;",
+        ];
+        for script in unsupported.iter() {
+            assert!(run(script, "", "a", Rc::new(Cell::new(0))).is_err(), "{}", script);
+        }
+    }
+
+    #[test]
+    fn test_runaway_scripts_fail() {
+        let recursion = "function FindProxyForURL(url, host) { \
+                         return FindProxyForURL(url, host); }";
+        assert!(evaluate(recursion, "http://a/", "a").is_err());
+        let nested = format!("function FindProxyForURL(url, host) {{ return {}1{}; }}",
+                             "(".repeat(10000), ")".repeat(10000));
+        assert!(evaluate(&nested, "http://a/", "a").is_err());
+        let negated = format!("function FindProxyForURL(url, host) {{ return {}1; }}",
+                              "!".repeat(10000));
+        assert!(evaluate(&negated, "http://a/", "a").is_err());
+        let blocks = format!("function FindProxyForURL(url, host) {{ {}{} }}",
+                             "{".repeat(10000), "}".repeat(10000));
+        assert!(evaluate(&blocks, "http://a/", "a").is_err());
+    }
+
+    #[test]
+    fn test_truncated_scripts_never_panic() {
+        for (i, _) in PAC.char_indices() {
+            let _ = evaluate(&PAC[..i], "https://automate.example.com/", "automate.example.com");
+        }
+    }
+
+    #[test]
+    fn test_evaluate_result() {
+        assert!(evaluate("function FindProxyForURL(url, host) { return 1; }",
+                         "http://a/", "a").is_err());
+        assert!(evaluate("function FindProxyForURL(url, host) { }", "http://a/", "a").is_err());
+        assert!(evaluate("function findProxyForURL(url, host) { return \"DIRECT\"; }",
+                         "http://a/", "a").is_err());
+    }
+
+    #[test]
+    fn test_fetch() {
+        mock("GET", "/pac/wpad.dat")
+            .with_status(200)
+            .with_body("function FindProxyForURL(url, host) { return \"DIRECT\"; }")
+            .create();
+        mock("GET", "/pac/missing.dat")
+            .with_status(404)
+            .create();
+        let tls = Tls::default();
+        assert!(fetch(&format!("{}/pac/wpad.dat", SERVER_URL), &tls).unwrap()
+                .contains("FindProxyForURL"));
+        assert!(fetch(&format!("{}/pac/missing.dat", SERVER_URL), &tls).is_err());
+
+        let tempdir = TempDir::new("pac").unwrap();
+        let path = tempdir.path().join("proxy.pac");
+        File::create(&path).unwrap().write_all(PAC.as_bytes()).unwrap();
+        assert_eq!(PAC, fetch(&path.to_string_lossy(), &tls).unwrap());
+        assert_eq!(PAC, fetch(&format!("file://{}", path.display()), &tls).unwrap());
+        assert!(fetch(&tempdir.path().join("missing.pac").to_string_lossy(), &tls).is_err());
+    }
+
+    #[test]
+    fn test_proxy_for() {
+        // Without proxy_pac_url nothing is looked up
+        assert_eq!(None, proxy_for(&Config::default(), "https://a.example.com/",
+                                   "a.example.com"));
+
+        let tempdir = TempDir::new("pac").unwrap();
+        let path = tempdir.path().join("proxy.pac");
+        File::create(&path).unwrap().write_all(PAC.as_bytes()).unwrap();
+        let config = Config::default().set_proxy_pac_url(&path.to_string_lossy());
+        assert_eq!(Some(Proxy { host: "proxy.example.com".to_string(), port: 8080,
+                                credentials: None }),
+                   proxy_for(&config, "https://automate.example.com/", "automate.example.com"));
+        assert_eq!(None, proxy_for(&config, "https://delivery/", "delivery"));
+
+        // A script that does not evaluate is ignored
+        let broken = tempdir.path().join("broken.pac");
+        File::create(&broken).unwrap().write_all(b"function {").unwrap();
+        let config = Config::default().set_proxy_pac_url(&broken.to_string_lossy());
+        assert_eq!(None, proxy_for(&config, "https://automate.example.com/",
+                                   "automate.example.com"));
+    }
+
+    #[test]
+    fn test_string_members() {
+        let pac = r#"function FindProxyForURL(url, host) {
+            if (host.indexOf("build") == 0 && host.length > 5) return host.substring(0, 5);
+            return -1 + "";
+        }"#;
+        assert_eq!("build", eval_with(pac, "", "builder.corp"));
+        assert_eq!("-1", eval_with(pac, "", "web"));
+    }
+}
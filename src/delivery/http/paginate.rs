@@ -0,0 +1,131 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+//! Paginated endpoints
+//!
+//! The endpoints listing things honor `limit` and `offset`. A GET of a
+//! path with a `limit` is asked again at the next offset for as long as
+//! the pages come back full, and the pages are merged into one list. A
+//! server ignoring `offset` answers the same page again, which stops it.
+
+use hyper::status::StatusCode;
+use serde_json::{self, Value};
+use http::APIClient;
+use types::DeliveryResult;
+
+/// Pages fetched at most, whatever the server says
+pub const MAX_PAGES: usize = 1000;
+
+/// The `limit` of the query of `path`, if any
+pub fn limit(path: &str) -> Option<usize> {
+    param(path, "limit").and_then(|l| l.parse().ok())
+        .and_then(|l| if l > 0 { Some(l) } else { None })
+}
+
+/// `path` asking for the page at `offset`
+pub fn at_offset(path: &str, offset: usize) -> String {
+    let (base, query) = match path.find('?') {
+        Some(i) => (&path[..i], &path[i + 1..]),
+        None => (path, "")
+    };
+    let mut params: Vec<String> = query.split('&')
+        .filter(|p| !p.is_empty() && !p.starts_with("offset="))
+        .map(|p| p.to_string())
+        .collect();
+    params.push(format!("offset={}", offset));
+    format!("{}?{}", base, params.join("&"))
+}
+
+/// GET `path` and, when it has a `limit` and answers with a list, the
+/// following pages too, merged into one pretty printed list
+pub fn get_all(client: &APIClient,
+               path: &str) -> DeliveryResult<(StatusCode, Option<String>)> {
+    let (status, body) = try!(APIClient::parse_response(try!(client.get(path))));
+    let limit = match (limit(path), body.as_ref()) {
+        (Some(limit), Some(_)) => limit,
+        _ => return Ok((status, body))
+    };
+    let mut page = match serde_json::from_str::<Value>(body.as_ref().unwrap()) {
+        Ok(Value::Array(page)) => page,
+        _ => return Ok((status, body))
+    };
+    let mut offset = param(path, "offset").and_then(|o| o.parse().ok()).unwrap_or(0);
+    let mut items = page.clone();
+    let mut pages = 1;
+    while page.len() >= limit && pages < MAX_PAGES {
+        offset += page.len();
+        let next = match try!(APIClient::parse_response(try!(client.get(&at_offset(path, offset))))) {
+            (_, Some(body)) => match try!(serde_json::from_str::<Value>(&body)) {
+                Value::Array(next) => next,
+                _ => break
+            },
+            (_, None) => break
+        };
+        if next.is_empty() || next == page {
+            break
+        }
+        debug!("Page {} of {}: {} items", pages + 1, path, next.len());
+        items.extend(next.iter().cloned());
+        page = next;
+        pages += 1;
+    }
+    Ok((status, Some(try!(serde_json::to_string_pretty(&Value::Array(items))))))
+}
+
+fn param<'p>(path: &'p str, name: &str) -> Option<&'p str> {
+    let query = match path.find('?') {
+        Some(i) => &path[i + 1..],
+        None => return None
+    };
+    query.split('&')
+        .find(|p| p.starts_with(name) && p[name.len()..].starts_with('='))
+        .map(|p| &p[name.len() + 1..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, SERVER_ADDRESS};
+    use serde_json::{self, Value};
+    use http::APIClient;
+
+    #[test]
+    fn test_limit_and_offset() {
+        assert_eq!(Some(10), limit("orgs/a/projects/b/changes?state=open&limit=10"));
+        assert_eq!(None, limit("orgs/a/projects/b/changes?limit=0"));
+        assert_eq!(None, limit("orgs/a/projects/b/changes"));
+        assert_eq!("changes?limit=2&offset=4", at_offset("changes?offset=2&limit=2", 4));
+        assert_eq!("changes?offset=0", at_offset("changes", 0));
+    }
+
+    #[test]
+    fn test_get_all() {
+        let base = "/api/v0/e/pager/orgs/acme/projects/rocket/changes";
+        mock("GET", &format!("{}?limit=2", base))
+            .with_status(200).with_body("[{\"id\": \"a\"}, {\"id\": \"b\"}]").create();
+        mock("GET", &format!("{}?limit=2&offset=2", base))
+            .with_status(200).with_body("[{\"id\": \"c\"}, {\"id\": \"d\"}]").create();
+        mock("GET", &format!("{}?limit=2&offset=4", base))
+            .with_status(200).with_body("[{\"id\": \"e\"}]").create();
+        let client = APIClient::new_http(SERVER_ADDRESS, "pager");
+        let (_, body) = get_all(&client, "orgs/acme/projects/rocket/changes?limit=2").unwrap();
+        let ids: Vec<Value> = match serde_json::from_str(&body.unwrap()).unwrap() {
+            Value::Array(items) => items.into_iter().map(|i| i["id"].clone()).collect(),
+            other => panic!("not a list: {:?}", other)
+        };
+        assert_eq!(vec![json!("a"), json!("b"), json!("c"), json!("d"), json!("e")], ids);
+    }
+}
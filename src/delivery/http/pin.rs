@@ -0,0 +1,201 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+// Certificate pinning
+//
+// A pin is the base64 encoded SHA-256 digest of the server certificate's
+// SubjectPublicKeyInfo (the same value HPKP uses). Pins are configured in
+// the cli.toml as a list so that a new key can be added before the old
+// one is rotated out:
+//
+// ```toml
+// server_pins = ["sha256/YLh1dUR9y6Kja30RrAn7JKnbQG/uEtLMkBgFF2Fuihg="]
+// ```
+//
+// The pins are checked during the handshake of the connections the API
+// client makes, by the verify callback of its TLS context (see
+// `http::tls`), so a request never reaches a server whose key is not
+// pinned. `delivery pin fetch` uses the `openssl` binary to read the pin
+// of the server to record.
+
+use std::io::prelude::*;
+use std::process::Stdio;
+use std::sync::Mutex;
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+use utils;
+
+// The pin of the last certificate refused during a handshake. The
+// verify callback cannot return an error of its own, so the request
+// that failed picks it up here to explain why.
+static MISMATCH: Mutex<Option<String>> = Mutex::new(None);
+
+const PIN_PREFIX: &'static str = "sha256/";
+
+/// Strip the optional `sha256/` prefix and surrounding whitespace
+/// so pins can be compared regardless of how they were written.
+pub fn normalize_pin(pin: &str) -> String {
+    let pin = pin.trim();
+    if pin.starts_with(PIN_PREFIX) {
+        pin[PIN_PREFIX.len()..].to_string()
+    } else {
+        pin.to_string()
+    }
+}
+
+/// Format a raw digest the way we store it in the cli.toml
+pub fn format_pin(digest: &str) -> String {
+    format!("{}{}", PIN_PREFIX, normalize_pin(digest))
+}
+
+/// Returns true if `actual` matches any of the `expected` pins
+pub fn pin_matches(expected: &[String], actual: &str) -> bool {
+    let actual = normalize_pin(actual);
+    expected.iter().any(|p| normalize_pin(p) == actual)
+}
+
+/// The pin of a DER encoded SubjectPublicKeyInfo
+pub fn spki_pin(spki: &[u8]) -> String {
+    let mut digest = [0; 32];
+    let mut sha = Sha256::new();
+    sha.input(spki);
+    sha.result(&mut digest);
+    format_pin(&base64(&digest))
+}
+
+/// Remember that the server presented a certificate pinned as `actual`
+pub fn record_mismatch(actual: &str) {
+    *MISMATCH.lock().unwrap() = Some(actual.to_string());
+}
+
+/// The pin of the certificate refused since the last call, if any
+pub fn take_mismatch() -> Option<String> {
+    MISMATCH.lock().unwrap().take()
+}
+
+/// What to tell the user when `server` presented a certificate pinned
+/// as `actual`, which is none of `pins`
+pub fn mismatch_message(server: &str, pins: &[String], actual: &str) -> String {
+    format!("The certificate presented by {} does not match any \
+            of the pins in your cli.toml.\n\nExpected one of:\n  {}\n\
+            Got:\n  {}\n\nIf the server certificate was rotated on \
+            purpose, run `delivery pin fetch` to record the new pin.",
+            server, pins.join("\n  "), actual)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &'static [u8] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Connect to `host:port` and return the pin of the certificate it presents
+pub fn fetch_pin(host: &str, port: &str) -> DeliveryResult<String> {
+    let connect = format!("{}:{}", host, port);
+    let chain = try!(openssl(&["s_client", "-connect", &connect,
+                               "-servername", host], b""));
+    let pubkey = try!(openssl(&["x509", "-pubkey", "-noout"], &chain));
+    let der = try!(openssl(&["pkey", "-pubin", "-outform", "der"], &pubkey));
+    let digest = try!(openssl(&["dgst", "-sha256", "-binary"], &der));
+    let encoded = try!(openssl(&["base64", "-A"], &digest));
+    Ok(format_pin(&String::from_utf8_lossy(&encoded)))
+}
+
+// Run an `openssl` subcommand, feeding it `input` on stdin and
+// returning whatever it writes to stdout.
+fn openssl(args: &[&str], input: &[u8]) -> DeliveryResult<Vec<u8>> {
+    let mut child = try!(utils::make_command("openssl")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            let msg = format!("Unable to run openssl: {}", e);
+            DeliveryError::throw(Kind::FailedToExecute, Some(msg))
+        }));
+    if let Some(ref mut stdin) = child.stdin {
+        try!(stdin.write_all(input));
+    }
+    let output = try!(child.wait_with_output());
+    if output.status.success() && !output.stdout.is_empty() {
+        Ok(output.stdout)
+    } else {
+        let msg = format!("`openssl {}` failed: {}", args.join(" "),
+                          String::from_utf8_lossy(&output.stderr));
+        Err(DeliveryError::throw(Kind::CertificatePinFetchFailed, Some(msg)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_pin() {
+        assert_eq!("abc=", normalize_pin("sha256/abc="));
+        assert_eq!("abc=", normalize_pin("  abc=\n"));
+    }
+
+    #[test]
+    fn test_format_pin() {
+        assert_eq!("sha256/abc=", format_pin("abc=\n"));
+        assert_eq!("sha256/abc=", format_pin("sha256/abc="));
+    }
+
+    #[test]
+    fn test_pin_matches() {
+        let pins = vec!["sha256/old=".to_string(), "new=".to_string()];
+        assert!(pin_matches(&pins, "sha256/new="));
+        assert!(pin_matches(&pins, "old="));
+        assert!(!pin_matches(&pins, "sha256/other="));
+        assert!(!pin_matches(&[], "sha256/other="));
+    }
+
+    #[test]
+    fn test_spki_pin() {
+        // The SHA-256 digest of nothing
+        assert_eq!("sha256/47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=", spki_pin(b""));
+        assert_eq!("Zm9vYg==", base64(b"foob"));
+        assert_eq!("Zm9vYmE=", base64(b"fooba"));
+        assert_eq!("Zm9vYmFy", base64(b"foobar"));
+    }
+
+    #[test]
+    fn test_mismatch() {
+        record_mismatch("sha256/other=");
+        assert_eq!(Some("sha256/other=".to_string()), take_mismatch());
+        assert_eq!(None, take_mismatch());
+        let msg = mismatch_message("automate:443", &["sha256/old=".to_string()],
+                                   "sha256/other=");
+        assert!(msg.contains("Expected one of:\n  sha256/old=\nGot:\n  sha256/other="));
+    }
+}
@@ -0,0 +1,174 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use http::{APIClient, pipeline_path};
+use delivery_config::pipelines::{Notifications, PipelineState, ServerState};
+use hyper::status::StatusCode;
+use serde_json;
+use types::{DeliveryResult, PipelineName};
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Dependencies {
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PipelineList {
+    #[serde(default)]
+    pipelines: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct PipelineDetails {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    base: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SlackWebhook {
+    url: String,
+    #[serde(default)]
+    enabled: bool,
+}
+
+impl APIClient {
+    /// The projects a pipeline depends on, as declared in the
+    /// `dependencies` of its `.delivery/config.json`.
+    pub fn pipeline_dependencies(&self, org: &str, proj: &str,
+                                 pipe: &str) -> DeliveryResult<Vec<String>> {
+        let path = format!("orgs/{}/projects/{}/pipelines/{}/dependencies",
+                           org, proj, pipe);
+        debug!("pipeline dependencies path: {}", path);
+        let response = try!(self.get(&path));
+        if let StatusCode::NotFound = response.status {
+            return Ok(Vec::new())
+        }
+        match try!(APIClient::parse_response(response)) {
+            (_, Some(body)) => {
+                let deps = try!(serde_json::from_str::<Dependencies>(&body));
+                Ok(deps.dependencies)
+            },
+            (_, None) => Ok(Vec::new())
+        }
+    }
+
+    /// The names of the pipelines of a project
+    pub fn list_pipelines(&self, org: &str, proj: &str) -> DeliveryResult<Vec<String>> {
+        let path = format!("orgs/{}/projects/{}/pipelines", org, proj);
+        debug!("list pipelines path: {}", path);
+        match try!(APIClient::parse_response(try!(self.get(&path)))) {
+            (_, Some(body)) => Ok(try!(serde_json::from_str::<PipelineList>(&body)).pipelines),
+            (_, None) => Ok(Vec::new())
+        }
+    }
+
+    /// The base branch of a pipeline, if the server reports it
+    pub fn pipeline_base(&self, org: &str, proj: &str,
+                         pipe: &str) -> DeliveryResult<Option<String>> {
+        let path = format!("orgs/{}/projects/{}/pipelines/{}", org, proj, pipe);
+        debug!("pipeline path: {}", path);
+        match try!(APIClient::parse_response(try!(self.get(&path)))) {
+            (_, Some(body)) => Ok(try!(serde_json::from_str::<PipelineDetails>(&body)).base),
+            (_, None) => Ok(None)
+        }
+    }
+
+    pub fn update_pipeline_base(&self, org: &str, proj: &str,
+                                pipe: &str, base: &str) -> DeliveryResult<()> {
+        let path = format!("orgs/{}/projects/{}/pipelines/{}", org, proj, pipe);
+        let payload = try!(serde_json::to_string(&PipelineDetails {
+            name: pipe.to_string(), base: Some(base.to_string())
+        }));
+        try!(APIClient::parse_response(try!(self.put(&path, &payload))));
+        Ok(())
+    }
+
+    pub fn set_pipeline_dependencies(&self, org: &str, proj: &str, pipe: &str,
+                                     dependencies: &[String]) -> DeliveryResult<()> {
+        let path = format!("orgs/{}/projects/{}/pipelines/{}/dependencies",
+                           org, proj, pipe);
+        let payload = try!(serde_json::to_string(&Dependencies {
+            dependencies: dependencies.to_vec()
+        }));
+        try!(APIClient::parse_response(try!(self.put(&path, &payload))));
+        Ok(())
+    }
+
+    pub fn delete_pipeline(&self, org: &str, proj: &str, pipe: &str) -> DeliveryResult<()> {
+        let path = format!("orgs/{}/projects/{}/pipelines/{}", org, proj, pipe);
+        debug!("delete pipeline path: {}", path);
+        try!(APIClient::parse_response(try!(self.delete(&path))));
+        self.forget_exists(&path);
+        Ok(())
+    }
+
+    /// Rename a pipeline; its changes and its branch on the server follow
+    pub fn rename_pipeline(&self, org: &str, proj: &str, pipe: &str,
+                           new_name: &PipelineName) -> DeliveryResult<()> {
+        let path = pipeline_path(org, proj, pipe);
+        debug!("rename pipeline path: {}", path);
+        let payload = try!(serde_json::to_string(&PipelineDetails {
+            name: new_name.to_string(), base: None
+        }));
+        try!(APIClient::parse_response(try!(self.put(&path, &payload))));
+        self.forget_exists(&path);
+        self.remember_exists(&pipeline_path(org, proj, new_name));
+        Ok(())
+    }
+
+    /// The Slack notification settings of a project, if any
+    pub fn project_notifications(&self, org: &str,
+                                 proj: &str) -> DeliveryResult<Option<Notifications>> {
+        let path = format!("orgs/{}/projects/{}/notifications/slack-webhook", org, proj);
+        let response = try!(self.get(&path));
+        if let StatusCode::NotFound = response.status {
+            return Ok(None)
+        }
+        match try!(APIClient::parse_response(response)) {
+            (_, Some(body)) => {
+                let webhook = try!(serde_json::from_str::<SlackWebhook>(&body));
+                Ok(Some(Notifications { slack_url: webhook.url, enabled: webhook.enabled }))
+            },
+            (_, None) => Ok(None)
+        }
+    }
+
+    pub fn set_project_notifications(&self, org: &str, proj: &str,
+                                     notifications: &Notifications) -> DeliveryResult<()> {
+        let path = format!("orgs/{}/projects/{}/notifications/slack-webhook", org, proj);
+        let payload = try!(serde_json::to_string(&SlackWebhook {
+            url: notifications.slack_url.clone(), enabled: notifications.enabled
+        }));
+        try!(APIClient::parse_response(try!(self.put(&path, &payload))));
+        Ok(())
+    }
+
+    /// Everything `delivery reconcile` manages for a project
+    pub fn project_state(&self, org: &str, proj: &str) -> DeliveryResult<ServerState> {
+        let mut state = ServerState::default();
+        for pipe in try!(self.list_pipelines(org, proj)) {
+            let pipeline = PipelineState {
+                base: try!(self.pipeline_base(org, proj, &pipe)),
+                dependencies: try!(self.pipeline_dependencies(org, proj, &pipe)),
+            };
+            state.pipelines.insert(pipe, pipeline);
+        }
+        state.notifications = try!(self.project_notifications(org, proj));
+        Ok(state)
+    }
+}
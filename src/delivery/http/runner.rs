@@ -0,0 +1,165 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// Job runners are the hosts that run the phase jobs of a pipeline. The
+// server connects to them over ssh as the `job_runner` user, with the
+// key it hands back when the runner is registered.
+
+use http::APIClient;
+use hyper::status::StatusCode;
+use regex::Regex;
+use serde_json;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+
+/// The user the server logs in as on a runner host
+pub const JOB_RUNNER_USER: &'static str = "job_runner";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Runner {
+    pub hostname: String,
+    #[serde(default)]
+    pub os: String,
+    #[serde(default)]
+    pub platform: String,
+    #[serde(default)]
+    pub platform_family: String,
+    #[serde(default)]
+    pub platform_version: String,
+    #[serde(default, skip_serializing)]
+    pub openssh_public_key: Option<String>,
+}
+
+impl Runner {
+    pub fn new(hostname: &str) -> Runner {
+        Runner { hostname: hostname.to_string(), ..Default::default() }
+    }
+
+    pub fn to_json(&self) -> DeliveryResult<String> {
+        Ok(try!(serde_json::to_string(&self)))
+    }
+
+    pub fn parse_json(response: &str) -> DeliveryResult<Runner> {
+        Ok(try!(serde_json::from_str::<Runner>(response)))
+    }
+
+    pub fn parse_json_list(response: &str) -> DeliveryResult<Vec<Runner>> {
+        Ok(try!(serde_json::from_str::<Vec<Runner>>(response)))
+    }
+
+    /// The command to run on the runner host so that the server is able
+    /// to log into it. Only available right after registering it, since
+    /// that is the only time the server returns the public key.
+    pub fn install_command(&self) -> Option<String> {
+        self.openssh_public_key.as_ref().map(|key| {
+            format!("sudo useradd --create-home {user} 2>/dev/null; \\\n\
+                    sudo mkdir -p ~{user}/.ssh && \\\n\
+                    echo '{key}' | sudo tee -a ~{user}/.ssh/authorized_keys > /dev/null && \\\n\
+                    sudo chown -R {user} ~{user}/.ssh && \\\n\
+                    sudo chmod 700 ~{user}/.ssh && sudo chmod 600 ~{user}/.ssh/authorized_keys",
+                    user = JOB_RUNNER_USER, key = key.trim())
+        })
+    }
+}
+
+/// Verify that the hostname is safe to use as part of the URL
+pub fn validate_hostname(hostname: &str) -> DeliveryResult<()> {
+    let r = Regex::new(r"^[A-Za-z0-9]([A-Za-z0-9.-]*[A-Za-z0-9])?$").unwrap();
+    if r.is_match(hostname) {
+        Ok(())
+    } else {
+        let msg = format!("Invalid runner hostname '{}'", hostname);
+        Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+    }
+}
+
+impl APIClient {
+    /// List the runners registered in the enterprise
+    pub fn list_runners(&self) -> DeliveryResult<Vec<Runner>> {
+        let response = try!(self.get("runners"));
+        match try!(APIClient::parse_response(response)) {
+            (_, Some(body)) => Runner::parse_json_list(&body),
+            (_, None) => Ok(Vec::new())
+        }
+    }
+
+    /// Register a new runner, returns it with the public key the
+    /// server will use to log into it.
+    pub fn register_runner(&self, runner: &Runner) -> DeliveryResult<Runner> {
+        try!(validate_hostname(&runner.hostname));
+        let payload = try!(runner.to_json());
+        let response = try!(self.post("runners", &payload));
+        if let StatusCode::Conflict = response.status {
+            let msg = format!("A runner named {} is already registered", runner.hostname);
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        match try!(APIClient::parse_response(response)) {
+            (_, Some(body)) => Runner::parse_json(&body),
+            (_, None) => Ok(runner.clone())
+        }
+    }
+
+    /// Remove a runner from the enterprise
+    pub fn delete_runner(&self, hostname: &str) -> DeliveryResult<()> {
+        try!(validate_hostname(hostname));
+        let response = try!(self.delete(&format!("runners/{}", hostname)));
+        try!(APIClient::parse_response(response));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runner_to_json_skips_public_key() {
+        let mut runner = Runner::new("runner-1.example.com");
+        runner.os = "linux".to_string();
+        runner.openssh_public_key = Some("ssh-rsa AAAA".to_string());
+        let json = runner.to_json().unwrap();
+        assert!(json.contains("\"hostname\":\"runner-1.example.com\""));
+        assert!(json.contains("\"os\":\"linux\""));
+        assert!(!json.contains("ssh-rsa"));
+    }
+
+    #[test]
+    fn test_runner_parse_json_list() {
+        let response = "[{\"hostname\":\"a.example.com\",\"os\":\"linux\"},\
+                        {\"hostname\":\"b.example.com\"}]";
+        let runners = Runner::parse_json_list(response).unwrap();
+        assert_eq!(2, runners.len());
+        assert_eq!("linux", runners[0].os);
+        assert_eq!("", runners[1].platform);
+        assert_eq!(None, runners[1].openssh_public_key);
+    }
+
+    #[test]
+    fn test_install_command() {
+        let mut runner = Runner::new("a.example.com");
+        assert_eq!(None, runner.install_command());
+        runner.openssh_public_key = Some("ssh-rsa AAAA delivery\n".to_string());
+        let cmd = runner.install_command().unwrap();
+        assert!(cmd.contains("echo 'ssh-rsa AAAA delivery' | sudo tee -a ~job_runner/.ssh/authorized_keys"));
+    }
+
+    #[test]
+    fn test_validate_hostname() {
+        assert!(validate_hostname("runner-1.example.com").is_ok());
+        assert!(validate_hostname("../orgs").is_err());
+        assert!(validate_hostname("").is_err());
+    }
+}
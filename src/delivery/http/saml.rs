@@ -15,7 +15,7 @@
 // limitations under the License.
 //
 
-use errors::{DeliveryError, Kind};
+use errors::DeliveryError;
 use http::*;
 use hyper::status::StatusCode;
 use serde_json;
@@ -48,17 +48,7 @@ pub fn is_enabled(config: &Config) -> Result<bool, DeliveryError> {
             debug!("endpoint 'saml/enabled' not found");
             Ok(false)
         },
-        error_code @ _ => {
-            let msg = format!("lookup of SAML authentication returned {}",
-                              error_code);
-            let mut detail = String::new();
-            let e = match result.read_to_string(&mut detail) {
-                Ok(_) => Ok(detail),
-                Err(e) => Err(e)
-            };
-            Err(DeliveryError{ kind: Kind::ApiError(error_code, e),
-                               detail: Some(msg)})
-        }
+        _ => Err(APIClient::api_error(&mut result))
     }
 }
 
@@ -0,0 +1,487 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Branch protection on the source code provider of a project
+//!
+//! The pipeline branches of a GitHub or Bitbucket backed project should
+//! only move when Delivery merges into them: a merge needs the Delivery
+//! status check and nobody may force push. `delivery protect sync` sets
+//! that up through the API of the provider, which Delivery does not do,
+//! with a token of the provider from `GITHUB_TOKEN` or `BITBUCKET_TOKEN`.
+//!
+//! A branch may be protected already, by other checks or review rules.
+//! The current protection is read first and only what Delivery needs is
+//! added to it, the rest is kept as it is.
+
+use std::env;
+use std::io::prelude::*;
+use hyper::header::{Accept, Authorization, Bearer, ContentType, UserAgent, qitem};
+use hyper::mime::{Mime, TopLevel, SubLevel};
+use hyper::status::StatusCode;
+use serde_json::{self, Value};
+use config::Config;
+use errors::{DeliveryError, Kind};
+use http::{proxy, pac, tls, APIClient};
+use types::DeliveryResult;
+
+pub const GITHUB_API_URL: &'static str = "https://api.github.com";
+
+/// The check a protected branch requires, unless `--status-check` says
+pub const DEFAULT_STATUS_CHECK: &'static str = "delivery/verify";
+
+/// A client of the API of GitHub or Bitbucket Server
+#[derive(Debug, Clone)]
+pub struct ScpClient {
+    provider: &'static str,
+    api_url: String,
+    token: String,
+    proxy: Option<pac::Proxy>,
+    tls: tls::Tls,
+}
+
+/// The environment variable with the token of `provider`
+pub fn token_var(provider: &str) -> &'static str {
+    match provider {
+        "bitbucket" => "BITBUCKET_TOKEN",
+        _ => "GITHUB_TOKEN",
+    }
+}
+
+impl ScpClient {
+    pub fn new(provider: &'static str, api_url: &str, token: &str) -> ScpClient {
+        ScpClient {
+            provider: provider,
+            api_url: api_url.trim_right_matches('/').to_string(),
+            token: token.to_string(),
+            proxy: None,
+            tls: tls::Tls::default(),
+        }
+    }
+
+    /// A client of `provider` at `api_url`, going through the proxy and
+    /// with the certificates of the `config`
+    pub fn from_config(config: &Config, provider: &'static str,
+                       api_url: &str) -> DeliveryResult<ScpClient> {
+        try!(config.require_network("contacting the source code provider"));
+        let var = token_var(provider);
+        let token = match env::var(var) {
+            Ok(ref token) if !token.is_empty() => token.clone(),
+            _ => {
+                let msg = format!("Set {} to a token of {} allowed to administer the \
+                                   repository", var, provider);
+                return Err(DeliveryError::throw(Kind::ConfigValidation, Some(msg)))
+            }
+        };
+        let mut client = ScpClient::new(provider, api_url, &token);
+        let host = api_url.split("://").last().unwrap_or("")
+            .split(|c| c == '/' || c == ':').next().unwrap_or("").to_string();
+        client.proxy = proxy::proxy_for(config, api_url, &host);
+        client.tls = try!(tls::Tls::from_config(config));
+        Ok(client)
+    }
+
+    /// Require `check` to merge into `branch` of the repository and
+    /// forbid force pushes to it, keeping the rest of its protection.
+    /// Returns what changed, nothing when the branch was protected so
+    /// already. With `dry_run` nothing is changed, only read.
+    pub fn protect_branch(&self, org: &str, repo: &str, branch: &str, check: &str,
+                          dry_run: bool) -> DeliveryResult<Vec<String>> {
+        match self.provider {
+            "bitbucket" => self.protect_bitbucket_branch(org, repo, branch, check, dry_run),
+            _ => self.protect_github_branch(org, repo, branch, check, dry_run),
+        }
+    }
+
+    // GitHub replaces the whole protection on a PUT, so the current one
+    // is read and sent back with the check added.
+    fn protect_github_branch(&self, org: &str, repo: &str, branch: &str, check: &str,
+                             dry_run: bool) -> DeliveryResult<Vec<String>> {
+        let path = format!("repos/{}/{}/branches/{}/protection", org, repo, branch);
+        let current = match try!(self.get_optional(&path)) {
+            Some(body) => Some(try!(serde_json::from_str::<Value>(&body))),
+            None => None
+        };
+        let changes = github_changes(current.as_ref(), check);
+        if !changes.is_empty() && !dry_run {
+            let protection = match current {
+                Some(ref current) => github_protection_update(current, check),
+                None => github_protection(check)
+            };
+            try!(self.send("PUT", &path, &protection.to_string()));
+        }
+        Ok(changes)
+    }
+
+    // Bitbucket Server keeps the two apart: a fast-forward-only branch
+    // permission, and a required build condition. Neither is
+    // deduplicated, so each is only added when the branch has none.
+    fn protect_bitbucket_branch(&self, project_key: &str, repo: &str, branch: &str,
+                                check: &str, dry_run: bool) -> DeliveryResult<Vec<String>> {
+        let mut changes = Vec::new();
+        let restrictions = format!("rest/branch-permissions/2.0/projects/{}/repos/{}/restrictions",
+                                   project_key, repo);
+        let existing = try!(self.send("GET", &restrictions, ""));
+        if !has_restriction(&existing, branch) {
+            changes.push("forbid force pushes".to_string());
+            if !dry_run {
+                try!(self.send("POST", &restrictions, &bitbucket_restriction(branch).to_string()));
+            }
+        }
+
+        let conditions = format!("rest/required-builds/latest/projects/{}/repos/{}/conditions",
+                                 project_key, repo);
+        let existing = try!(self.send("GET", &conditions, ""));
+        if !has_required_build(&existing, branch) {
+            changes.push(format!("require {}", check));
+            if !dry_run {
+                let condition = format!("rest/required-builds/latest/projects/{}/repos/{}/condition",
+                                        project_key, repo);
+                try!(self.send("POST", &condition,
+                               &bitbucket_required_build(branch, check).to_string()));
+            }
+        }
+        Ok(changes)
+    }
+
+    // The body of a GET, `None` when there is nothing at `path`
+    fn get_optional(&self, path: &str) -> DeliveryResult<Option<String>> {
+        match self.send("GET", path, "") {
+            Ok(body) => Ok(Some(body)),
+            Err(DeliveryError{ kind: Kind::ApiError(StatusCode::NotFound, _), .. }) => Ok(None),
+            Err(e) => Err(e)
+        }
+    }
+
+    fn send(&self, method: &str, path: &str, payload: &str) -> DeliveryResult<String> {
+        let url = format!("{}/{}", self.api_url, path);
+        let client = try!(proxy::client(self.proxy.as_ref(), &self.tls));
+        let req = match method {
+            "GET" => client.get(&url),
+            "PUT" => client.put(&url),
+            _ => client.post(&url),
+        };
+        let req = req.header(Authorization(Bearer { token: self.token.clone() }))
+            .header(UserAgent("delivery-cli".to_string()))
+            .header(Accept(vec![qitem(Mime(TopLevel::Application, SubLevel::Json, vec![]))]))
+            .header(ContentType::json());
+        let req = match proxy::authorization(self.proxy.as_ref(), &self.tls, &url) {
+            Some(authorization) => req.header(authorization),
+            None => req
+        };
+        debug!("{} {} {}", method, url, payload);
+        let mut response = if payload.is_empty() {
+            try!(req.send())
+        } else {
+            try!(req.body(payload).send())
+        };
+        match response.status {
+            StatusCode::Ok | StatusCode::Created | StatusCode::NoContent => {
+                let mut body = String::new();
+                try!(response.read_to_string(&mut body));
+                Ok(body)
+            },
+            _ => Err(APIClient::api_error(&mut response))
+        }
+    }
+}
+
+/// The branch protection of GitHub: the check is required on an up to
+/// date branch and force pushes are refused
+pub fn github_protection(check: &str) -> Value {
+    json!({
+        "required_status_checks": { "strict": true, "contexts": [check] },
+        "enforce_admins": true,
+        "required_pull_request_reviews": null,
+        "restrictions": null,
+        "allow_force_pushes": false,
+        "allow_deletions": false
+    })
+}
+
+/// What protecting a branch whose GitHub protection is `current`
+/// changes, one line per change
+pub fn github_changes(current: Option<&Value>, check: &str) -> Vec<String> {
+    let current = match current {
+        Some(current) => current,
+        None => return vec![format!("require {}", check),
+                            "forbid force pushes and deleting the branch".to_string()]
+    };
+    let mut changes = Vec::new();
+    let checks = &current["required_status_checks"];
+    if !github_required_checks(checks).iter().any(|c| c == check) {
+        changes.push(format!("require {}", check));
+    }
+    if checks.is_object() && checks["strict"] != Value::Bool(true) {
+        changes.push("require the branch to be up to date".to_string());
+    }
+    if current["allow_force_pushes"]["enabled"] == Value::Bool(true) {
+        changes.push("forbid force pushes".to_string());
+    }
+    if current["allow_deletions"]["enabled"] == Value::Bool(true) {
+        changes.push("forbid deleting the branch".to_string());
+    }
+    changes
+}
+
+// The contexts a GitHub protection requires, as `contexts` or `checks`
+fn github_required_checks(checks: &Value) -> Vec<String> {
+    let contexts = checks["contexts"].as_array().cloned().unwrap_or_default();
+    let from_checks = checks["checks"].as_array().cloned().unwrap_or_default();
+    contexts.iter().filter_map(|c| c.as_str())
+        .chain(from_checks.iter().filter_map(|c| c["context"].as_str()))
+        .map(|c| c.to_string())
+        .collect()
+}
+
+/// The protection to PUT for a branch GitHub answered `current` for:
+/// the same settings, in the form the update takes, with `check`
+/// required and force pushes and deletions forbidden
+pub fn github_protection_update(current: &Value, check: &str) -> Value {
+    let logins = |users: &Value, key: &str| -> Value {
+        Value::Array(users.as_array().cloned().unwrap_or_default().iter()
+                     .filter_map(|u| u[key].as_str().map(|s| Value::String(s.to_string())))
+                     .collect())
+    };
+    let enabled = |setting: &str| current[setting]["enabled"].as_bool().unwrap_or(false);
+
+    let current_checks = &current["required_status_checks"];
+    let mut status_checks = json!({ "strict": true });
+    if current_checks["checks"].is_array() {
+        let mut checks = current_checks["checks"].as_array().cloned().unwrap_or_default();
+        if !checks.iter().any(|c| c["context"] == Value::String(check.to_string())) {
+            checks.push(json!({ "context": check }));
+        }
+        status_checks["checks"] = Value::Array(checks);
+    } else {
+        let mut contexts = github_required_checks(current_checks);
+        if !contexts.iter().any(|c| c == check) {
+            contexts.push(check.to_string());
+        }
+        status_checks["contexts"] = json!(contexts);
+    }
+
+    let reviews = &current["required_pull_request_reviews"];
+    let reviews = if reviews.is_object() {
+        let mut update = json!({
+            "dismiss_stale_reviews": reviews["dismiss_stale_reviews"].as_bool().unwrap_or(false),
+            "require_code_owner_reviews": reviews["require_code_owner_reviews"].as_bool()
+                .unwrap_or(false),
+        });
+        if let Some(count) = reviews["required_approving_review_count"].as_u64() {
+            update["required_approving_review_count"] = json!(count);
+        }
+        let dismissal = &reviews["dismissal_restrictions"];
+        if dismissal.is_object() {
+            update["dismissal_restrictions"] = json!({
+                "users": logins(&dismissal["users"], "login"),
+                "teams": logins(&dismissal["teams"], "slug"),
+            });
+        }
+        update
+    } else {
+        Value::Null
+    };
+
+    let restrictions = &current["restrictions"];
+    let restrictions = if restrictions.is_object() {
+        json!({
+            "users": logins(&restrictions["users"], "login"),
+            "teams": logins(&restrictions["teams"], "slug"),
+            "apps": logins(&restrictions["apps"], "slug"),
+        })
+    } else {
+        Value::Null
+    };
+
+    json!({
+        "required_status_checks": status_checks,
+        "enforce_admins": enabled("enforce_admins"),
+        "required_pull_request_reviews": reviews,
+        "restrictions": restrictions,
+        "required_linear_history": enabled("required_linear_history"),
+        "required_conversation_resolution": enabled("required_conversation_resolution"),
+        "block_creations": enabled("block_creations"),
+        "lock_branch": enabled("lock_branch"),
+        "allow_fork_syncing": enabled("allow_fork_syncing"),
+        "allow_force_pushes": false,
+        "allow_deletions": false
+    })
+}
+
+fn branch_matcher(branch: &str) -> Value {
+    json!({
+        "id": format!("refs/heads/{}", branch),
+        "displayId": branch,
+        "type": { "id": "BRANCH", "name": "Branch" },
+        "active": true
+    })
+}
+
+/// The Bitbucket branch permission that refuses rewriting history
+pub fn bitbucket_restriction(branch: &str) -> Value {
+    json!({ "type": "fast-forward-only", "matcher": branch_matcher(branch) })
+}
+
+/// The Bitbucket required build condition of `check`
+pub fn bitbucket_required_build(branch: &str, check: &str) -> Value {
+    json!({ "buildParentKeys": [check], "refMatcher": branch_matcher(branch) })
+}
+
+// Whether the restrictions listed by Bitbucket forbid rewriting the
+// history of `branch` already
+fn has_restriction(restrictions: &str, branch: &str) -> bool {
+    let id = format!("refs/heads/{}", branch);
+    serde_json::from_str::<Value>(restrictions).ok()
+        .and_then(|v| v.get("values").and_then(|v| v.as_array()).cloned())
+        .map(|values| values.iter().any(|r| {
+            r["type"] == Value::String("fast-forward-only".to_string())
+                && r["matcher"]["id"] == Value::String(id.clone())
+        }))
+        .unwrap_or(false)
+}
+
+// Whether the conditions listed by Bitbucket cover `branch` already
+fn has_required_build(conditions: &str, branch: &str) -> bool {
+    let id = format!("refs/heads/{}", branch);
+    serde_json::from_str::<Value>(conditions).ok()
+        .and_then(|v| v.get("values").and_then(|v| v.as_array()).cloned())
+        .map(|values| values.iter().any(|c| c["refMatcher"]["id"] == Value::String(id.clone())))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, SERVER_URL};
+
+    #[test]
+    fn test_github_protection() {
+        let protection = github_protection("delivery/verify");
+        assert_eq!(protection["required_status_checks"]["contexts"][0], "delivery/verify");
+        assert_eq!(protection["allow_force_pushes"].as_bool(), Some(false));
+    }
+
+    // What GitHub answers for a branch protected by another CI
+    const PROTECTED: &'static str = r#"{
+        "required_status_checks": {"strict": false, "contexts": ["ci/travis"]},
+        "enforce_admins": {"enabled": false},
+        "required_pull_request_reviews": {
+            "dismiss_stale_reviews": true, "required_approving_review_count": 2,
+            "dismissal_restrictions": {"users": [{"login": "alice"}], "teams": []}
+        },
+        "restrictions": {"users": [], "teams": [{"slug": "release"}], "apps": []},
+        "required_linear_history": {"enabled": true},
+        "allow_force_pushes": {"enabled": true},
+        "allow_deletions": {"enabled": false}
+    }"#;
+
+    #[test]
+    fn test_github_protection_update_keeps_settings() {
+        let current: Value = serde_json::from_str(PROTECTED).unwrap();
+        let update = github_protection_update(&current, "delivery/verify");
+        assert_eq!(json!(["ci/travis", "delivery/verify"]),
+                   update["required_status_checks"]["contexts"]);
+        assert_eq!(Some(false), update["enforce_admins"].as_bool());
+        assert_eq!(Some(2), update["required_pull_request_reviews"]
+                   ["required_approving_review_count"].as_u64());
+        assert_eq!(json!(["alice"]),
+                   update["required_pull_request_reviews"]["dismissal_restrictions"]["users"]);
+        assert_eq!(json!(["release"]), update["restrictions"]["teams"]);
+        assert_eq!(Some(true), update["required_linear_history"].as_bool());
+        assert_eq!(Some(false), update["allow_force_pushes"].as_bool());
+    }
+
+    #[test]
+    fn test_github_changes() {
+        let current: Value = serde_json::from_str(PROTECTED).unwrap();
+        assert_eq!(vec!["require delivery/verify", "require the branch to be up to date",
+                        "forbid force pushes"],
+                   github_changes(Some(&current), "delivery/verify"));
+        let update = github_protection_update(&current, "delivery/verify");
+        let protected = json!({
+            "required_status_checks": update["required_status_checks"].clone(),
+            "allow_force_pushes": {"enabled": false},
+            "allow_deletions": {"enabled": false}
+        });
+        assert!(github_changes(Some(&protected), "delivery/verify").is_empty());
+        assert_eq!(2, github_changes(None, "delivery/verify").len());
+    }
+
+    #[test]
+    fn test_has_restriction() {
+        let restrictions = r#"{"values":[{"id":1,"type":"fast-forward-only",
+                               "matcher":{"id":"refs/heads/master"}}]}"#;
+        assert!(has_restriction(restrictions, "master"));
+        assert!(!has_restriction(restrictions, "release"));
+        assert!(!has_restriction("", "master"));
+    }
+
+    #[test]
+    fn test_has_required_build() {
+        let conditions = r#"{"values":[{"id":1,"refMatcher":{"id":"refs/heads/master"}}]}"#;
+        assert!(has_required_build(conditions, "master"));
+        assert!(!has_required_build(conditions, "release"));
+        assert!(!has_required_build("", "master"));
+    }
+
+    #[test]
+    fn test_protect_github_branch() {
+        let _g = mock("GET", "/repos/chef/frijol/branches/protected/protection")
+            .with_status(404)
+            .with_body(r#"{"message":"Branch not protected"}"#)
+            .create();
+        let _m = mock("PUT", "/repos/chef/frijol/branches/protected/protection")
+            .match_header("authorization", "Bearer s3cret")
+            .with_status(200)
+            .with_body("{}")
+            .create();
+        let client = ScpClient::new("github", SERVER_URL, "s3cret");
+        let changes = client.protect_branch("chef", "frijol", "protected", "delivery/verify",
+                                            false).unwrap();
+        assert_eq!("require delivery/verify", changes[0]);
+    }
+
+    #[test]
+    fn test_protect_github_branch_already_protected() {
+        // Nothing to change, so no PUT; one would fail without a mock
+        let _g = mock("GET", "/repos/chef/frijol/branches/done/protection")
+            .with_status(200)
+            .with_body(r#"{"required_status_checks":{"strict":true,
+                           "contexts":["delivery/verify"]},
+                           "allow_force_pushes":{"enabled":false}}"#)
+            .create();
+        let client = ScpClient::new("github", SERVER_URL, "s3cret");
+        assert!(client.protect_branch("chef", "frijol", "done", "delivery/verify", false)
+                .unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_protect_github_branch_refused() {
+        let _g = mock("GET", "/repos/chef/frijol/branches/refused/protection")
+            .with_status(404)
+            .with_body("{}")
+            .create();
+        let _m = mock("PUT", "/repos/chef/frijol/branches/refused/protection")
+            .with_status(403)
+            .with_body(r#"{"message":"Must have admin rights to Repository."}"#)
+            .create();
+        let client = ScpClient::new("github", SERVER_URL, "s3cret");
+        let e = client.protect_branch("chef", "frijol", "refused", "delivery/verify", false)
+            .unwrap_err();
+        assert!(e.detail.unwrap().contains("admin rights"));
+    }
+}
@@ -0,0 +1,153 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Server error payloads
+//!
+//! When a request fails, the Delivery server usually says why in a JSON
+//! body, e.g. `{"error": "pipeline_exists", "message": "..."}`. This
+//! module turns those bodies into a message a user can act on: known
+//! error codes get a message of our own, otherwise we use the message
+//! the server sent, and only fall back to the HTTP status when the body
+//! says nothing useful. The raw body is kept so it can be shown with
+//! `--debug`.
+
+use hyper::status::StatusCode;
+use serde_json;
+use serde_json::Value as SerdeJson;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerError {
+    pub status: StatusCode,
+    pub code: Option<String>,
+    pub message: Option<String>,
+    pub body: String,
+}
+
+impl ServerError {
+    /// Decode the body of a failed response. Bodies that are not JSON,
+    /// or JSON without any of the fields we know about, decode to an
+    /// error with neither code nor message.
+    pub fn decode(status: StatusCode, body: &str) -> ServerError {
+        let json: SerdeJson = serde_json::from_str(body).unwrap_or(SerdeJson::Null);
+        let code = json.get("error").and_then(|e| e.as_str())
+            .or_else(|| json.get("code").and_then(|c| c.as_str()))
+            .map(|c| c.to_string());
+        let message = json.get("message").and_then(|m| m.as_str())
+            .or_else(|| json.get("error_description").and_then(|m| m.as_str()))
+            .map(|m| m.to_string())
+            .or_else(|| json.get("errors").and_then(|e| e.as_array()).map(|errors| {
+                errors.iter()
+                    .filter_map(|e| e.as_str().or_else(|| e.get("message").and_then(|m| m.as_str())))
+                    .collect::<Vec<&str>>()
+                    .join("; ")
+            }))
+            .filter(|m| !m.is_empty());
+        ServerError {
+            status: status,
+            code: code,
+            message: message,
+            body: body.trim().to_string(),
+        }
+    }
+
+    /// The message to show the user, always ending with the HTTP status.
+    /// The server's own message is the most specific, then the one of
+    /// its error code, then the one of the status.
+    pub fn friendly_message(&self) -> String {
+        let text = self.message.clone()
+            .or(self.code.as_ref().and_then(|c| code_message(c).map(|m| m.to_string())))
+            .unwrap_or(status_message(self.status).to_string());
+        format!("{} ({})", text, self.status)
+    }
+}
+
+// Messages for the error codes the server is known to send. Keeping
+// them in one table gives translations a single place to hook into.
+fn code_message(code: &str) -> Option<&'static str> {
+    match code {
+        "not_found" => Some("The requested resource does not exist on the server"),
+        "conflict" | "already_exists" => Some("The resource already exists on the server"),
+        "pipeline_exists" => Some("A pipeline with that name already exists"),
+        "invalid_json" | "bad_request" => Some("The server could not understand the request"),
+        "missing_field" => Some("The request is missing a required field"),
+        "invalid_name" => Some("The name is not valid; use letters, digits, '-' and '_'"),
+        "merge_conflict" => Some("The change does not merge cleanly into its pipeline"),
+        "change_not_approvable" => Some("The change cannot be approved in its current state"),
+        "change_not_deliverable" => Some("The change cannot be delivered in its current state"),
+        "superseded" => Some("The change was superseded by a newer patchset"),
+        _ => None
+    }
+}
+
+fn status_message(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BadRequest => "The server rejected the request as invalid",
+        StatusCode::NotFound => "The requested resource does not exist on the server",
+        StatusCode::MethodNotAllowed => "The server does not allow this request",
+        StatusCode::Conflict => "The request conflicts with the current state on the server",
+        StatusCode::PreconditionFailed => "The resource changed on the server since it was read",
+        StatusCode::UnprocessableEntity => "The server could not process the request",
+        StatusCode::TooManyRequests => "The server is rate limiting requests; try again later",
+        StatusCode::NotImplemented => "The server does not support this request",
+        StatusCode::BadGateway | StatusCode::ServiceUnavailable | StatusCode::GatewayTimeout =>
+            "The server is unavailable; try again later",
+        s if s.is_server_error() =>
+            "The server failed to handle the request; check the logs on the Automate server",
+        _ => "The server could not complete the request",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::status::StatusCode;
+
+    #[test]
+    fn test_known_code() {
+        let error = ServerError::decode(StatusCode::Conflict,
+            r#"{"error": "pipeline_exists", "message": "pipeline 'master' exists"}"#);
+        assert_eq!(Some("pipeline_exists".to_string()), error.code);
+        assert_eq!("pipeline 'master' exists (409 Conflict)", error.friendly_message());
+        let without_message = ServerError::decode(StatusCode::Conflict,
+            r#"{"error": "pipeline_exists"}"#);
+        assert_eq!("A pipeline with that name already exists (409 Conflict)",
+                   without_message.friendly_message());
+    }
+
+    #[test]
+    fn test_server_message() {
+        let error = ServerError::decode(StatusCode::BadRequest,
+            r#"{"error": "weird_failure", "message": "patchset is too large"}"#);
+        assert_eq!("patchset is too large (400 Bad Request)", error.friendly_message());
+        let errors = ServerError::decode(StatusCode::UnprocessableEntity,
+            r#"{"errors": ["name is required", {"message": "base is required"}]}"#);
+        assert_eq!(Some("name is required; base is required".to_string()), errors.message);
+    }
+
+    #[test]
+    fn test_body_without_details() {
+        let error = ServerError::decode(StatusCode::BadGateway, "<html>Bad Gateway</html>");
+        assert_eq!(None, error.code);
+        assert_eq!(None, error.message);
+        assert_eq!("<html>Bad Gateway</html>", error.body);
+        assert_eq!("The server is unavailable; try again later (502 Bad Gateway)",
+                   error.friendly_message());
+        assert_eq!("The server is unavailable; try again later (502 Bad Gateway)",
+                   ServerError::decode(StatusCode::BadGateway,
+                                       r#"{"message": ""}"#).friendly_message());
+    }
+}
@@ -0,0 +1,147 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+// Request signing
+//
+// Audited environments want proof of which automation did what, which
+// the shared user token does not give. When `signing_key_id` and
+// `signing_key_file` are set in the cli.toml, every request carries an
+// HMAC-SHA256 of its method, timestamp, path and body, along with the key id,
+// for the server or a proxy in front of it to verify:
+//
+//   x-delivery-key-id:    automation-1
+//   x-delivery-timestamp: 1500000000
+//   x-delivery-signature: hex(hmac(key, "<method>\n<timestamp>\n<path>\n<body>"))
+//
+// The method is signed so that a captured GET cannot be replayed as a
+// DELETE of the same path.
+
+use std::fmt;
+use std::path::PathBuf;
+use crypto::hmac::Hmac;
+use crypto::mac::Mac;
+use crypto::sha2::Sha256;
+use time;
+use config::Config;
+use errors::{DeliveryError, Kind};
+use http::headers::{DeliveryKeyId, DeliverySignature, DeliveryTimestamp};
+use types::DeliveryResult;
+use utils::read_file;
+
+#[derive(Clone)]
+pub struct RequestSigner {
+    key_id: String,
+    key: Vec<u8>,
+}
+
+// Keep the key out of debug logs
+impl fmt::Debug for RequestSigner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RequestSigner {{ key_id: {:?} }}", self.key_id)
+    }
+}
+
+impl RequestSigner {
+    pub fn new(key_id: &str, key: &[u8]) -> RequestSigner {
+        RequestSigner { key_id: key_id.to_string(), key: key.to_vec() }
+    }
+
+    /// The signer configured in the cli.toml, if signing is enabled
+    pub fn from_config(config: &Config) -> DeliveryResult<Option<RequestSigner>> {
+        let key_id = match config.signing_key_id() {
+            Ok(key_id) => key_id,
+            Err(_) => return Ok(None)
+        };
+        let key_file = try!(config.signing_key_file());
+        let key = try!(read_file(&PathBuf::from(&key_file)));
+        let key = key.trim();
+        if key.is_empty() {
+            let msg = format!("The signing key file {} is empty", key_file);
+            return Err(DeliveryError::throw(Kind::MissingConfig, Some(msg)))
+        }
+        Ok(Some(RequestSigner::new(&key_id, key.as_bytes())))
+    }
+
+    pub fn string_to_sign(method: &str, timestamp: i64, path: &str, body: &str) -> String {
+        format!("{}\n{}\n{}\n{}", method, timestamp, path, body)
+    }
+
+    /// The hex encoded signature of a request
+    pub fn sign(&self, method: &str, timestamp: i64, path: &str, body: &str) -> String {
+        let mut hmac = Hmac::new(Sha256::new(), &self.key);
+        hmac.input(RequestSigner::string_to_sign(method, timestamp, path, body).as_bytes());
+        hmac.result().code().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// The headers that sign a request sent now
+    pub fn sign_headers(&self, method: &str, path: &str,
+                        body: &str) -> (DeliveryKeyId, DeliveryTimestamp, DeliverySignature) {
+        let timestamp = time::get_time().sec;
+        (DeliveryKeyId(self.key_id.clone()),
+         DeliveryTimestamp(timestamp.to_string()),
+         DeliverySignature(self.sign(method, timestamp, path, body)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::Config;
+    use std::fs::File;
+    use std::io::Write;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_sign() {
+        let signer = RequestSigner::new("automation-1", b"s3cr3t");
+        assert_eq!("POST\n1500000000\n/api/v0/e/chef/orgs\n{}",
+                   RequestSigner::string_to_sign("POST", 1500000000, "/api/v0/e/chef/orgs",
+                                                 "{}"));
+        let signature = signer.sign("POST", 1500000000, "/api/v0/e/chef/orgs", "{}");
+        assert_eq!(64, signature.len());
+        assert_eq!(signature, signer.sign("POST", 1500000000, "/api/v0/e/chef/orgs", "{}"));
+        assert!(signature != signer.sign("POST", 1500000001, "/api/v0/e/chef/orgs", "{}"));
+        assert!(signature != RequestSigner::new("automation-1", b"other")
+                                .sign("POST", 1500000000, "/api/v0/e/chef/orgs", "{}"));
+        assert!(!format!("{:?}", signer).contains("s3cr3t"));
+    }
+
+    #[test]
+    fn test_sign_method() {
+        let signer = RequestSigner::new("automation-1", b"s3cr3t");
+        let path = "/api/v0/e/chef/orgs/o/projects/p";
+        assert!(signer.sign("GET", 1500000000, path, "") !=
+                signer.sign("DELETE", 1500000000, path, ""));
+    }
+
+    #[test]
+    fn test_from_config() {
+        assert!(RequestSigner::from_config(&Config::default()).unwrap().is_none());
+
+        let tmp = TempDir::new("signing").unwrap();
+        let key_path = tmp.path().join("signing.key");
+        File::create(&key_path).unwrap().write_all(b"s3cr3t\n").unwrap();
+        let config = Config::default().set_signing_key_id("automation-1")
+            .set_signing_key_file(key_path.to_str().unwrap());
+        let signer = RequestSigner::from_config(&config).unwrap().unwrap();
+        assert_eq!(RequestSigner::new("automation-1", b"s3cr3t").sign("GET", 1, "/", ""),
+                   signer.sign("GET", 1, "/", ""));
+
+        let no_file = Config::default().set_signing_key_id("automation-1");
+        assert!(RequestSigner::from_config(&no_file).is_err());
+    }
+}
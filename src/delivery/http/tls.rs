@@ -0,0 +1,423 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+//! Custom CA bundle and client certificate
+//!
+//! `ca_cert` in the cli.toml, or `--ca-cert`, is a PEM bundle of the
+//! certificate authorities to trust instead of the system ones, i.e. for
+//! an Automate server with a certificate signed by an internal CA.
+//! `client_cert` and `client_key` present a client certificate to the
+//! servers asking for one; without `client_key` the key is read from the
+//! `client_cert` file. The API client uses them, and so do the git clones
+//! of build cookbook generators over https, through `git -c`.
+//!
+//! The API client also checks the `server_pins` of the Automate server
+//! here, on the connections it makes (see `http::pin`), and that the
+//! certificate is issued for the host it connects to.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use hyper;
+use config::Config;
+use errors::{DeliveryError, Kind};
+use http::pac::Proxy;
+use types::DeliveryResult;
+
+static CA_CERT: Mutex<Option<String>> = Mutex::new(None);
+
+/// Trust the CA bundle at `path`, over the `ca_cert` of the cli.toml
+pub fn set_ca_cert(path: &str) {
+    *CA_CERT.lock().unwrap() = Some(path.to_string());
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tls {
+    pub ca_cert: Option<PathBuf>,
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    // The pins the server certificate must match, if any
+    pub pins: Vec<String>,
+}
+
+impl Tls {
+    pub fn from_config(config: &Config) -> DeliveryResult<Tls> {
+        let ca_cert = CA_CERT.lock().unwrap().clone().or(config.ca_cert.clone());
+        Tls::new(ca_cert, config.client_cert.clone(), config.client_key.clone())
+    }
+
+    /// The settings, once checked that every file they name exists
+    pub fn new(ca_cert: Option<String>, client_cert: Option<String>,
+               client_key: Option<String>) -> DeliveryResult<Tls> {
+        if client_key.is_some() && client_cert.is_none() {
+            let msg = "client_key is set without client_cert; set both in your cli.toml";
+            return Err(DeliveryError::throw(Kind::TlsConfig, Some(msg.to_string())))
+        }
+        let tls = Tls {
+            ca_cert: ca_cert.map(PathBuf::from),
+            client_key: client_key.or(client_cert.clone()).map(PathBuf::from),
+            client_cert: client_cert.map(PathBuf::from),
+            pins: Vec::new(),
+        };
+        for path in tls.paths() {
+            if !path.is_file() {
+                let msg = format!("{} does not exist or is not a file", path.display());
+                return Err(DeliveryError::throw(Kind::TlsConfig, Some(msg)))
+            }
+        }
+        Ok(tls)
+    }
+
+    /// The same settings, only trusting a server certificate that
+    /// matches one of `pins`
+    pub fn with_pins(self, pins: Vec<String>) -> Tls {
+        Tls { pins: pins, ..self }
+    }
+
+    /// Whether nothing differs from the system trust store
+    pub fn is_default(&self) -> bool {
+        self.paths().is_empty() && self.pins.is_empty()
+    }
+
+    /// The same settings as `key=value` pairs for `git -c`
+    pub fn git_config(&self) -> Vec<String> {
+        let mut settings = vec![];
+        if let Some(ref ca_cert) = self.ca_cert {
+            settings.push(format!("http.sslCAInfo={}", ca_cert.display()));
+        }
+        if let Some(ref client_cert) = self.client_cert {
+            settings.push(format!("http.sslCert={}", client_cert.display()));
+        }
+        if let Some(ref client_key) = self.client_key {
+            settings.push(format!("http.sslKey={}", client_key.display()));
+        }
+        settings
+    }
+
+    fn paths(&self) -> Vec<&Path> {
+        vec![&self.ca_cert, &self.client_cert, &self.client_key].into_iter()
+            .filter_map(|path| path.as_ref().map(|p| p.as_path()))
+            .collect()
+    }
+}
+
+/// A client trusting the CA bundle and presenting the client certificate
+/// of `tls`. Through a `proxy`, every connection is a CONNECT tunnel.
+#[cfg(feature = "tls")]
+pub fn client(proxy: Option<&Proxy>, tls: &Tls) -> hyper::Result<hyper::Client> {
+    use hyper::net::HttpsConnector;
+
+    let ssl = try!(connector::context(tls));
+    let client = match proxy {
+        Some(proxy) => hyper::Client::with_connector(connector::Tunnel {
+            proxy: proxy.clone(),
+            ssl: ssl,
+        }),
+        None => hyper::Client::with_connector(HttpsConnector::new(ssl))
+    };
+    Ok(client)
+}
+
+#[cfg(not(feature = "tls"))]
+pub fn client(_proxy: Option<&Proxy>, _tls: &Tls) -> hyper::Result<hyper::Client> {
+    let msg = "This delivery is built without TLS support and can not use \
+               ca_cert, client_cert or server_pins";
+    Err(hyper::Error::Io(io::Error::new(io::ErrorKind::Other, msg)))
+}
+
+#[cfg(feature = "tls")]
+mod connector {
+    use std::io::prelude::*;
+    use std::sync::Arc;
+    use hyper;
+    use hyper::net::{HttpConnector, HttpStream, HttpsStream, NetworkConnector, SslClient};
+    use openssl::nid::Nid;
+    use openssl::ssl::{Ssl, SslContext, SslMethod, SslStream, SSL_VERIFY_PEER};
+    use openssl::x509::{X509, X509FileType, X509StoreContext};
+    use http::pac::Proxy;
+    use http::pin;
+    use super::{read_connect_response, certificate_matches, Tls};
+
+    /// Wraps connections in TLS, checking the certificate of the server
+    /// against the host of each connection
+    #[derive(Clone)]
+    pub struct Verified {
+        context: Arc<SslContext>,
+        tls: Tls,
+    }
+
+    impl SslClient for Verified {
+        type Stream = SslStream<HttpStream>;
+
+        fn wrap_client(&self, stream: HttpStream, host: &str) -> hyper::Result<Self::Stream> {
+            let mut ssl = try!(Ssl::new(&self.context));
+            try!(ssl.set_hostname(host));
+            let (host, tls) = (host.to_string(), self.tls.clone());
+            ssl.set_verify_callback(SSL_VERIFY_PEER, move |preverify_ok, context| {
+                verify(preverify_ok, context, &host, &tls)
+            });
+            SslStream::connect(ssl, stream).map_err(From::from)
+        }
+    }
+
+    pub fn context(tls: &Tls) -> hyper::Result<Verified> {
+        let mut context = try!(SslContext::new(SslMethod::Sslv23));
+        try!(context.set_cipher_list("DEFAULT"));
+        match tls.ca_cert {
+            Some(ref ca_cert) => try!(context.set_CA_file(ca_cert)),
+            None => try!(context.set_default_verify_paths())
+        }
+        if let (Some(cert), Some(key)) = (tls.client_cert.as_ref(), tls.client_key.as_ref()) {
+            try!(context.set_certificate_file(cert, X509FileType::PEM));
+            try!(context.set_private_key_file(key, X509FileType::PEM));
+            try!(context.check_private_key());
+        }
+        context.set_verify(SSL_VERIFY_PEER, None);
+        Ok(Verified { context: Arc::new(context), tls: tls.clone() })
+    }
+
+    // On top of the usual verification, the certificate of the server
+    // itself (depth 0 of the chain) has to be issued for `host` and match
+    // one of the pins, if any.
+    fn verify(preverify_ok: bool, context: &X509StoreContext, host: &str, tls: &Tls) -> bool {
+        if !preverify_ok || context.error_depth() > 0 {
+            return preverify_ok
+        }
+        match context.get_current_cert() {
+            Some(cert) => check_host(&cert, host) && check_pin(&cert, tls),
+            None => false
+        }
+    }
+
+    fn check_host(cert: &X509, host: &str) -> bool {
+        let (mut dns_names, mut addresses) = (vec![], vec![]);
+        if let Some(names) = cert.subject_alt_names() {
+            for name in &names {
+                if let Some(dns_name) = name.dnsname() {
+                    dns_names.push(dns_name.to_string());
+                }
+                if let Some(address) = name.ipaddress() {
+                    addresses.push(address.to_vec());
+                }
+            }
+        }
+        let common_name = cert.subject_name().text_by_nid(Nid::CN).map(|cn| cn.to_string());
+        if certificate_matches(host, &dns_names, &addresses, common_name.as_ref()) {
+            true
+        } else {
+            debug!("The certificate of the server is not issued for {}: {:?} {:?} {:?}",
+                   host, dns_names, addresses, common_name);
+            false
+        }
+    }
+
+    // openssl 0.7 only serializes RSA keys, so other keys never match
+    fn check_pin(cert: &X509, tls: &Tls) -> bool {
+        if tls.pins.is_empty() {
+            return true
+        }
+        let actual = pin::spki_pin(&cert.public_key().save_pub());
+        debug!("Server pin is {}", actual);
+        if pin::pin_matches(&tls.pins, &actual) {
+            true
+        } else {
+            pin::record_mismatch(&actual);
+            false
+        }
+    }
+
+    /// Connects through a CONNECT tunnel of `proxy`, the TLS handshake
+    /// happening inside it
+    pub struct Tunnel {
+        pub proxy: Proxy,
+        pub ssl: Verified,
+    }
+
+    impl NetworkConnector for Tunnel {
+        type Stream = HttpsStream<<Verified as SslClient>::Stream>;
+
+        fn connect(&self, host: &str, port: u16, scheme: &str) -> hyper::Result<Self::Stream> {
+            let mut stream: HttpStream = try!(HttpConnector.connect(&self.proxy.host,
+                                                                    self.proxy.port, "http"));
+            try!(write!(stream, "CONNECT {0}:{1} HTTP/1.1\r\nHost: {0}:{1}\r\n", host, port));
+            if let Some(authorization) = self.proxy.authorization() {
+                try!(write!(stream, "Proxy-Authorization: {}\r\n", authorization.0));
+            }
+            try!(write!(stream, "\r\n"));
+            try!(stream.flush());
+            try!(read_connect_response(&mut stream));
+            if scheme == "https" {
+                self.ssl.wrap_client(stream, host).map(HttpsStream::Https)
+            } else {
+                Ok(HttpsStream::Http(stream))
+            }
+        }
+    }
+}
+
+/// Whether a certificate with the subject alternative names `dns_names`
+/// and `addresses` (raw octets), and the subject `common_name`, is
+/// issued for `host`. The common name only counts without DNS names, and
+/// a wildcard only stands for the whole leftmost label (RFC 6125).
+#[cfg(any(feature = "tls", test))]
+fn certificate_matches(host: &str, dns_names: &[String], addresses: &[Vec<u8>],
+                       common_name: Option<&String>) -> bool {
+    let host = host.trim_right_matches('.').to_lowercase();
+    let host = host.trim_left_matches('[').trim_right_matches(']');
+    if let Ok(ip) = host.parse() {
+        let octets = match ip {
+            ::std::net::IpAddr::V4(ip) => ip.octets().to_vec(),
+            ::std::net::IpAddr::V6(ip) => ip.octets().to_vec(),
+        };
+        return addresses.iter().any(|a| *a == octets)
+    }
+    if dns_names.is_empty() {
+        return common_name.map_or(false, |cn| name_matches(host, cn))
+    }
+    dns_names.iter().any(|name| name_matches(host, name))
+}
+
+#[cfg(any(feature = "tls", test))]
+fn name_matches(host: &str, name: &str) -> bool {
+    let name = name.trim_right_matches('.').to_lowercase();
+    if !name.starts_with("*.") {
+        return host == name
+    }
+    // `*.example.com` matches `a.example.com`, not `example.com` nor
+    // `a.b.example.com`, and never a bare top level domain
+    let suffix = &name[1..];
+    if suffix.matches('.').count() < 2 {
+        return false
+    }
+    match host.find('.') {
+        Some(i) => i > 0 && &host[i..] == suffix,
+        None => false
+    }
+}
+
+/// Read the answer of a proxy to a CONNECT up to the end of its headers,
+/// one byte at a time not to eat into what comes through the tunnel
+#[cfg(any(feature = "tls", test))]
+fn read_connect_response<R: io::Read>(stream: &mut R) -> io::Result<()> {
+    let mut head = Vec::new();
+    let mut byte = [0; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if try!(stream.read(&mut byte)) == 0 || head.len() > 16384 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      "The proxy did not answer the CONNECT request"))
+        }
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let status = head.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some("200") => Ok(()),
+        _ => Err(io::Error::new(io::ErrorKind::Other,
+                                format!("The proxy refused the tunnel: {}", status)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::{Cursor, Read};
+    use tempdir::TempDir;
+    use errors::Kind;
+
+    #[test]
+    fn new_checks_the_files() {
+        let tempdir = TempDir::new("tls").unwrap();
+        let ca_cert = tempdir.path().join("ca.pem");
+        File::create(&ca_cert).unwrap();
+        let ca = Some(ca_cert.to_string_lossy().to_string());
+
+        let tls = Tls::new(ca.clone(), None, None).unwrap();
+        assert_eq!(Some(ca_cert.clone()), tls.ca_cert);
+        assert!(!tls.is_default());
+        assert!(Tls::new(None, None, None).unwrap().is_default());
+        let pinned = Tls::default().with_pins(vec!["sha256/abc=".to_string()]);
+        assert!(!pinned.is_default());
+        assert!(pinned.git_config().is_empty());
+
+        let missing = Some(tempdir.path().join("nope.pem").to_string_lossy().to_string());
+        let e = Tls::new(ca.clone(), missing.clone(), None).unwrap_err();
+        assert!(assert_enum!(e.kind, Kind::TlsConfig));
+        let e = Tls::new(None, None, ca.clone()).unwrap_err();
+        assert!(assert_enum!(e.kind, Kind::TlsConfig));
+    }
+
+    #[test]
+    fn client_key_defaults_to_client_cert() {
+        let tempdir = TempDir::new("tls").unwrap();
+        let client_cert = tempdir.path().join("client.pem");
+        File::create(&client_cert).unwrap();
+        let cert = Some(client_cert.to_string_lossy().to_string());
+
+        let tls = Tls::new(None, cert, None).unwrap();
+        assert_eq!(Some(client_cert.clone()), tls.client_key);
+        assert_eq!(vec![format!("http.sslCert={}", client_cert.display()),
+                        format!("http.sslKey={}", client_cert.display())],
+                   tls.git_config());
+    }
+
+    #[test]
+    fn certificate_matches_the_host() {
+        let names = |list: &[&str]| list.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        let dns = names(&["automate.example.com", "*.build.example.com"]);
+        assert!(certificate_matches("automate.example.com", &dns, &[], None));
+        assert!(certificate_matches("Automate.Example.com.", &dns, &[], None));
+        assert!(certificate_matches("node1.build.example.com", &dns, &[], None));
+        assert!(!certificate_matches("build.example.com", &dns, &[], None));
+        assert!(!certificate_matches("a.node1.build.example.com", &dns, &[], None));
+        assert!(!certificate_matches("evil.com", &dns, &[], None));
+        assert!(!certificate_matches("automate.example.com.evil.com", &dns, &[], None));
+
+        // The common name only counts without DNS names
+        let cn = "automate.example.com".to_string();
+        assert!(certificate_matches("automate.example.com", &[], &[], Some(&cn)));
+        assert!(!certificate_matches("automate.example.com", &names(&["other.example.com"]),
+                                     &[], Some(&cn)));
+        assert!(!certificate_matches("automate.example.com", &[], &[], None));
+
+        // Wildcards cover one whole label below a registered domain
+        assert!(!certificate_matches("example.com", &names(&["*.com"]), &[], None));
+        assert!(!certificate_matches("a.b", &names(&["*.b"]), &[], None));
+
+        // Addresses only match address entries
+        let addresses = vec![vec![10, 1, 2, 3], vec![0, 0, 0, 0, 0, 0, 0, 0,
+                                                     0, 0, 0, 0, 0, 0, 0, 1]];
+        assert!(certificate_matches("10.1.2.3", &[], &addresses, None));
+        assert!(certificate_matches("[::1]", &[], &addresses, None));
+        assert!(!certificate_matches("10.1.2.4", &[], &addresses, None));
+        assert!(!certificate_matches("10.1.2.3", &names(&["10.1.2.3"]), &[],
+                                     Some(&"10.1.2.3".to_string())));
+    }
+
+    #[test]
+    fn connect_response() {
+        let mut answer = Cursor::new(b"HTTP/1.1 200 Connection established\r\n\r\nTLS".to_vec());
+        assert!(read_connect_response(&mut answer).is_ok());
+        let mut rest = String::new();
+        answer.read_to_string(&mut rest).unwrap();
+        assert_eq!("TLS", rest);
+
+        let mut refused = Cursor::new(b"HTTP/1.1 403 Forbidden\r\n\r\n".to_vec());
+        let e = read_connect_response(&mut refused).unwrap_err();
+        assert!(e.to_string().contains("403 Forbidden"));
+        assert!(read_connect_response(&mut Cursor::new(b"HTTP/1.1 200".to_vec())).is_err());
+    }
+}
@@ -55,6 +55,33 @@ impl TokenResponse {
     }
 }
 
+/// The payload to issue a token for another user, `ttl` in seconds
+#[derive(Serialize, Debug, Clone)]
+pub struct IssueRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl: Option<u64>
+}
+
+impl IssueRequest {
+    pub fn payload(ttl: Option<u64>) -> Result<String, DeliveryError> {
+        Ok(serde_json::to_string(&IssueRequest { ttl: ttl })?)
+    }
+}
+
+/// Mint a token for `user` through the admin API. Only admins of the
+/// enterprise may do so, anyone else gets a `ForbiddenRequest`.
+pub fn issue(client: &APIClient, user: &str, ttl: Option<u64>) -> Result<String, DeliveryError> {
+    let payload = try!(IssueRequest::payload(ttl));
+    let path = format!("users/{}/tokens", user);
+    match try!(APIClient::parse_response(try!(client.post(&path, &payload)))) {
+        (_, Some(content)) => TokenResponse::parse_token(&content),
+        (code, None) => {
+            let msg = format!("The server issued no token for {} ({})", user, code);
+            Err(DeliveryError::throw(Kind::NoToken, Some(msg)))
+        }
+    }
+}
+
 // Verify an API token for a user against a Delivery Server
 //
 // This method verifies that a user has an existing Token on disk,
@@ -105,18 +132,13 @@ pub fn request(config: &Config, pass: &str) -> Result<String, DeliveryError> {
         },
         StatusCode::Unauthorized => {
             let ent = try!(config.enterprise());
-            let server = try!(config.server());
+            let server = try!(config.server_host());
             let msg = format!("Details: server={}, enterprise={}, user={}",
                               &server, &ent, &user);
             Err(DeliveryError{ kind: Kind::AuthenticationFailed,
                                detail: Some(msg)})
         },
-        error_code @ _ => {
-            let msg = format!("token request returned {}",
-                              error_code);
-            Err(DeliveryError{ kind: Kind::AuthenticationFailed,
-                               detail: Some(msg)})
-        }
+        _ => Err(APIClient::api_error(&mut result))
     }
 }
 
@@ -131,6 +153,12 @@ mod tests {
         assert_eq!(expect, payload.unwrap());
     }
 
+    #[test]
+    fn issue_request_payload_test() {
+        assert_eq!("{\"ttl\":3600}", IssueRequest::payload(Some(3600)).unwrap());
+        assert_eq!("{}", IssueRequest::payload(None).unwrap());
+    }
+
     #[test]
     fn token_response_parse_token_test() {
         let response = "{\"token\":\"abc123\"}";
@@ -0,0 +1,236 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+// The environment contract for phases
+//
+// Every phase we execute, either through `delivery job` (chef-client
+// running the build_cookbook) or through `delivery local` (the commands
+// in the project.toml), gets the same set of `DELIVERY_*` environment
+// variables. Variables that do not apply to a run are still exported,
+// with an empty value, so scripts can rely on them being defined.
+//
+// | Variable                         | Description                          |
+// |----------------------------------|--------------------------------------|
+// | DELIVERY_RUN_MODE                | `job` or `local`                     |
+// | DELIVERY_ENTERPRISE              | Enterprise name                      |
+// | DELIVERY_ORGANIZATION            | Organization name                    |
+// | DELIVERY_PROJECT                 | Project name                         |
+// | DELIVERY_PIPELINE                | Target pipeline                      |
+// | DELIVERY_STAGE                   | Stage being run                      |
+// | DELIVERY_PHASE                   | Phase(s) being run                   |
+// | DELIVERY_CHANGE_ID               | Change ID                            |
+// | DELIVERY_PATCHSET_NUMBER         | Patchset number                      |
+// | DELIVERY_PATCHSET_BRANCH         | Branch the patchset lives on         |
+// | DELIVERY_SHA                     | Git SHA being built                  |
+// | DELIVERY_WORKSPACE_ROOT          | Root of the job workspace            |
+// | DELIVERY_WORKSPACE_REPO          | Path of the project repository       |
+// | DELIVERY_WORKSPACE_CHEF          | Path of the chef directory           |
+// | DELIVERY_WORKSPACE_CACHE         | Path of the cache directory          |
+// | DELIVERY_PRIOR_STAGE             | Stage that runs before this one      |
+// | DELIVERY_PRIOR_STAGE_METADATA    | Change JSON fetched from the server  |
+
+use std::process::Command;
+use std::path::Path;
+use hyper::status::StatusCode;
+use http::{APIAuth, APIClient};
+use job::change::Change;
+use job::workspace::Workspace;
+use config::Config;
+use types::DeliveryResult;
+use utils::path_to_string;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhaseEnv {
+    pub run_mode: String,
+    pub enterprise: String,
+    pub organization: String,
+    pub project: String,
+    pub pipeline: String,
+    pub stage: String,
+    pub phase: String,
+    pub change_id: String,
+    pub patchset_number: String,
+    pub patchset_branch: String,
+    pub sha: String,
+    pub workspace_root: String,
+    pub workspace_repo: String,
+    pub workspace_chef: String,
+    pub workspace_cache: String,
+    pub prior_stage: String,
+    pub prior_stage_metadata: String,
+}
+
+impl PhaseEnv {
+    /// The environment for a phase run by `delivery job`
+    pub fn for_job(change: &Change, ws: &Workspace) -> DeliveryResult<PhaseEnv> {
+        Ok(PhaseEnv {
+            run_mode: "job".to_string(),
+            enterprise: change.enterprise.clone(),
+            organization: change.organization.clone(),
+            project: change.project.clone(),
+            pipeline: change.pipeline.clone(),
+            stage: change.stage.clone(),
+            phase: change.phase.clone(),
+            change_id: change.change_id.clone(),
+            patchset_number: change.patchset_number.clone(),
+            patchset_branch: change.patchset_branch.clone(),
+            sha: change.sha.clone(),
+            workspace_root: path_to_string(&ws.root)?,
+            workspace_repo: path_to_string(&ws.repo)?,
+            workspace_chef: path_to_string(&ws.chef)?,
+            workspace_cache: path_to_string(&ws.cache)?,
+            prior_stage: prior_stage(&change.stage).unwrap_or("").to_string(),
+            prior_stage_metadata: String::new(),
+        })
+    }
+
+    /// The environment for a phase run by `delivery local`. There is
+    /// no workspace in this case, the repository is the project itself.
+    pub fn for_local(project: &str, stage: &str, phase: &str,
+                     sha: &str, repo: &Path) -> DeliveryResult<PhaseEnv> {
+        Ok(PhaseEnv {
+            run_mode: "local".to_string(),
+            project: project.to_string(),
+            stage: stage.to_string(),
+            phase: phase.to_string(),
+            sha: sha.to_string(),
+            workspace_root: path_to_string(repo)?,
+            workspace_repo: path_to_string(repo)?,
+            prior_stage: prior_stage(stage).unwrap_or("").to_string(),
+            ..Default::default()
+        })
+    }
+
+    pub fn set_prior_stage_metadata(&mut self, metadata: &str) {
+        self.prior_stage_metadata = metadata.to_string();
+    }
+
+    /// Returns the list of variables in the order they are documented
+    pub fn vars(&self) -> Vec<(&'static str, &str)> {
+        vec![
+            ("DELIVERY_RUN_MODE", &self.run_mode),
+            ("DELIVERY_ENTERPRISE", &self.enterprise),
+            ("DELIVERY_ORGANIZATION", &self.organization),
+            ("DELIVERY_PROJECT", &self.project),
+            ("DELIVERY_PIPELINE", &self.pipeline),
+            ("DELIVERY_STAGE", &self.stage),
+            ("DELIVERY_PHASE", &self.phase),
+            ("DELIVERY_CHANGE_ID", &self.change_id),
+            ("DELIVERY_PATCHSET_NUMBER", &self.patchset_number),
+            ("DELIVERY_PATCHSET_BRANCH", &self.patchset_branch),
+            ("DELIVERY_SHA", &self.sha),
+            ("DELIVERY_WORKSPACE_ROOT", &self.workspace_root),
+            ("DELIVERY_WORKSPACE_REPO", &self.workspace_repo),
+            ("DELIVERY_WORKSPACE_CHEF", &self.workspace_chef),
+            ("DELIVERY_WORKSPACE_CACHE", &self.workspace_cache),
+            ("DELIVERY_PRIOR_STAGE", &self.prior_stage),
+            ("DELIVERY_PRIOR_STAGE_METADATA", &self.prior_stage_metadata),
+        ]
+    }
+
+    /// Export every variable into the provided command
+    pub fn apply(&self, command: &mut Command) {
+        for (key, value) in self.vars() {
+            command.env(key, value);
+        }
+    }
+}
+
+/// Returns the stage that runs right before `stage` in a pipeline
+pub fn prior_stage(stage: &str) -> Option<&'static str> {
+    match stage.to_lowercase().as_ref() {
+        "build" => Some("verify"),
+        "acceptance" => Some("build"),
+        "union" => Some("acceptance"),
+        "rehearsal" => Some("union"),
+        "delivered" => Some("rehearsal"),
+        _ => None
+    }
+}
+
+/// Fetch the change from the server so that phases can inspect the
+/// results of the stages that ran before them. Jobs run unattended, so
+/// this only uses the stored token and never prompts for one.
+pub fn fetch_prior_stage_metadata(config: &Config,
+                                  change_id: &str) -> DeliveryResult<String> {
+    let org = try!(config.organization());
+    let proj = try!(config.project());
+    let mut client = try!(APIClient::from_config_no_auth(config));
+    client.set_auth(try!(APIAuth::from_stored_token(config)));
+    let path = format!("orgs/{}/projects/{}/changes/{}", org, proj, change_id);
+    debug!("prior stage metadata path: {}", path);
+    let result = try!(client.get(&path));
+    match try!(APIClient::parse_response(result)) {
+        (StatusCode::Ok, Some(content)) => Ok(content),
+        _ => Ok(String::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn change() -> Change {
+        Change {
+            enterprise: "ent".to_string(),
+            organization: "org".to_string(),
+            project: "proj".to_string(),
+            pipeline: "master".to_string(),
+            stage: "acceptance".to_string(),
+            phase: "provision".to_string(),
+            git_url: "ssh://git".to_string(),
+            sha: "".to_string(),
+            patchset_branch: "_reviews/master/foo/1".to_string(),
+            change_id: "a-change-id".to_string(),
+            patchset_number: "1".to_string()
+        }
+    }
+
+    #[test]
+    fn test_prior_stage() {
+        assert_eq!(None, prior_stage("verify"));
+        assert_eq!(Some("verify"), prior_stage("build"));
+        assert_eq!(Some("union"), prior_stage("Rehearsal"));
+    }
+
+    #[test]
+    fn test_for_job() {
+        let ws = Workspace::new(&PathBuf::from("/ws"));
+        let env = PhaseEnv::for_job(&change(), &ws).unwrap();
+        let vars = env.vars();
+        assert!(vars.contains(&("DELIVERY_RUN_MODE", "job")));
+        assert!(vars.contains(&("DELIVERY_CHANGE_ID", "a-change-id")));
+        assert!(vars.contains(&("DELIVERY_PIPELINE", "master")));
+        assert!(vars.contains(&("DELIVERY_WORKSPACE_REPO", "/ws/repo")));
+        assert!(vars.contains(&("DELIVERY_PRIOR_STAGE", "build")));
+    }
+
+    #[test]
+    fn test_for_local_exports_every_variable() {
+        let env = PhaseEnv::for_local("proj", "verify", "unit", "abc123",
+                                      &PathBuf::from("/proj")).unwrap();
+        let ws = Workspace::new(&PathBuf::from("/ws"));
+        let job_env = PhaseEnv::for_job(&change(), &ws).unwrap();
+        let local_keys: Vec<&str> = env.vars().iter().map(|&(k, _)| k).collect();
+        let job_keys: Vec<&str> = job_env.vars().iter().map(|&(k, _)| k).collect();
+        assert_eq!(job_keys, local_keys);
+        assert!(env.vars().contains(&("DELIVERY_RUN_MODE", "local")));
+        assert!(env.vars().contains(&("DELIVERY_CHANGE_ID", "")));
+        assert!(env.vars().contains(&("DELIVERY_WORKSPACE_REPO", "/proj")));
+    }
+}
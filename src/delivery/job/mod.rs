@@ -18,3 +18,4 @@
 pub mod workspace;
 pub mod change;
 pub mod dna;
+pub mod env;
@@ -22,16 +22,19 @@ use serde_json;
 use delivery_config::{DeliveryConfig, BuildCookbookLocation};
 use job::dna::{Top, DNA, WorkspaceCompat};
 use job::change::{Change, BuilderCompat};
+use job::env::PhaseEnv;
 use std::process::{Command, Stdio};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs::File;
 use std::io::prelude::*;
 use utils;
+use utils::limits::{self, Limits};
 use utils::path_to_string;
 use utils::path_join_many::PathJoinMany;
 use utils::path_ext::{is_file, is_dir};
 use std::error;
 use config::Config;
+use project;
 
 pub struct Workspace {
     pub root: PathBuf,
@@ -41,6 +44,10 @@ pub struct Workspace {
     pub ssh_wrapper: PathBuf
 }
 
+/// The file `build` leaves at the root of a workspace, so that commands
+/// run from inside one can tell
+pub const WORKSPACE_MARKER: &'static str = ".delivery-workspace";
+
 #[derive(Debug)]
 pub enum Privilege {
     Drop,
@@ -77,6 +84,17 @@ impl Workspace {
         }
     }
 
+    /// The workspace of a single run, under `runs/<run_id>` of the
+    /// job root, so runs never see what another left behind
+    pub fn for_run(job_root: &Path, run_id: &str) -> Workspace {
+        Workspace::new(&job_root.join("runs").join(run_id))
+    }
+
+    /// Remove the whole workspace tree
+    pub fn remove(&self) -> Result<(), DeliveryError> {
+        utils::remove_recursive(&self.root)
+    }
+
     // Build the workspace tree on the build-node
     pub fn build(&self) -> Result<(), DeliveryError> {
         try!(self.clean_chef_nodes());
@@ -85,6 +103,7 @@ impl Workspace {
         try!(utils::mkdir_recursive(&self.chef.join("cookbooks")));
         try!(utils::mkdir_recursive(&self.cache));
         try!(utils::mkdir_recursive(&self.repo));
+        try!(File::create(&self.root.join(WORKSPACE_MARKER)));
         Ok(())
     }
 
@@ -114,7 +133,7 @@ impl Workspace {
         let branch = config.build_cookbook_get("branch").unwrap_or("master".to_owned());
         let build_cookbook_path = &self.chef.join("build_cookbook");
         git::git_command(&["clone", &git_url,
-                         &path_to_string(build_cookbook_path)],
+                         &path_to_string(build_cookbook_path)?],
                          &self.chef)?;
         git::git_command(&["checkout", &branch], build_cookbook_path)?;
         Ok(())
@@ -126,7 +145,7 @@ impl Workspace {
                         config: &DeliveryConfig) -> DeliveryResult<()> {
         let name = config.build_cookbook_name()?;
         let site = config.build_cookbook_get("site")
-                        .unwrap_or("https://supermarket.chef.io".to_owned());
+                        .unwrap_or(project::DEFAULT_SUPERMARKET.to_owned());
         let result = utils::make_command("knife")
              .arg("supermarket")
              .arg("download")
@@ -134,19 +153,19 @@ impl Workspace {
              .arg("-m")
              .arg(&site)
              .arg("-f")
-             .arg(&path_to_string(&self.chef.join("build_cookbook.tgz")))
+             .arg(&path_to_string(&self.chef.join("build_cookbook.tgz"))?)
              .current_dir(&self.root)
              .output()?;
         utils::cmd_success_or_err(&result, Kind::SupermarketFailed)?;
         let tar_result = utils::make_command("tar")
              .arg("zxf")
-             .arg(&path_to_string(&self.chef.join("build_cookbook.tgz")))
+             .arg(&path_to_string(&self.chef.join("build_cookbook.tgz"))?)
              .current_dir(&self.chef)
              .output()?;
         utils::cmd_success_or_err(&tar_result, Kind::TarFailed)?;
         let mv_result = utils::make_command("mv")
-             .arg(&path_to_string(&self.chef.join(name)))
-             .arg(&path_to_string(&self.chef.join("build_cookbook")))
+             .arg(&path_to_string(&self.chef.join(name))?)
+             .arg(&path_to_string(&self.chef.join("build_cookbook"))?)
              .current_dir(&self.chef)
              .output()?;
         utils::cmd_success_or_err(&mv_result, Kind::MoveFailed)?;
@@ -159,14 +178,14 @@ impl Workspace {
                         .arg("download")
                         .arg(&format!("/cookbooks/{}", &name))
                         .arg("--chef-repo-path")
-                        .arg(&path_to_string(&self.chef.join("tmp_cookbook")))
+                        .arg(&path_to_string(&self.chef.join("tmp_cookbook"))?)
                         .current_dir(&self.root)
                         .output()?;
         utils::cmd_success_or_err(&result, Kind::ChefServerFailed)?;
         let mv_result = utils::make_command("mv")
                             .arg(&path_to_string(&self.chef.join_many(&["tmp_cookbook",
-                                                                      "cookbooks", &name])))
-                            .arg(&path_to_string(&self.chef.join("build_cookbook")))
+                                                                      "cookbooks", &name]))?)
+                            .arg(&path_to_string(&self.chef.join("build_cookbook"))?)
                             .current_dir(&self.chef)
                             .output()?;
         utils::cmd_success_or_err(&mv_result, Kind::MoveFailed)?;
@@ -225,8 +244,8 @@ impl Workspace {
                 Ok(o) => o,
                 Err(e) => {
                     let d = format!("failed to execute 'berks vendor {}' from '{}': {}",
-                                    &path_to_string(&self.chef.join("cookbooks")),
-                                    &path_to_string(&self.chef.join("build_cookbook")),
+                                    &path_to_string(&self.chef.join("cookbooks"))?,
+                                    &path_to_string(&self.chef.join("build_cookbook"))?,
                                     error::Error::description(&e));
                     return Err(DeliveryError{ kind: Kind::FailedToExecute,
                                                       detail: Some(d)}) },
@@ -240,8 +259,8 @@ impl Workspace {
             debug!("No Berksfile found; simply moving the cookbook");
             try!(utils::mkdir_recursive(&self.chef.join("cookbooks")));
             let mv_result = Command::new("mv")
-                                .arg(&path_to_string(&self.chef.join("build_cookbook")))
-                                .arg(&path_to_string(&self.chef.join_many(&["cookbooks", bc_name])))
+                                .arg(&path_to_string(&self.chef.join("build_cookbook"))?)
+                                .arg(&path_to_string(&self.chef.join_many(&["cookbooks", bc_name]))?)
                                 .current_dir(&self.chef)
                                 .output()?;
             utils::cmd_success_or_err(&mv_result, Kind::MoveFailed)?;
@@ -260,7 +279,9 @@ impl Workspace {
 
     pub fn run_job(&self, phase_arg: &str,
                     drop_privilege: &Privilege,
-                    local_change: &bool) -> DeliveryResult<()> {
+                    local_change: &bool,
+                    phase_env: &PhaseEnv,
+                    limits: &Limits) -> DeliveryResult<()> {
         let config = DeliveryConfig::load_config(&self.repo)?;
         let bc_name = config.build_cookbook_name()?;
         let run_list = {
@@ -272,12 +293,12 @@ impl Workspace {
         command.arg("-z").arg("--force-formatter");
         try!(self.handle_privilege_drop(drop_privilege, &mut command));
         if ! local_change {
-          command.env("HOME", &path_to_string(&self.cache));
+          command.env("HOME", &path_to_string(&self.cache)?);
         }
         command.arg("-j")
-            .arg(&path_to_string(&self.chef.join("dna.json")))
+            .arg(&path_to_string(&self.chef.join("dna.json"))?)
             .arg("-c")
-            .arg(&path_to_string(&self.chef.join("config.rb")))
+            .arg(&path_to_string(&self.chef.join("config.rb"))?)
             .arg("-r")
             .arg(run_list)
             .stdout(Stdio::inherit())
@@ -287,19 +308,9 @@ impl Workspace {
             "default" => command.env("DELIVERY_BUILD_SETUP", "TRUE"),
             _ => command.env("DELIVERY_BUILD_SETUP", "FALSE")
         };
+        phase_env.apply(&mut command);
         debug!("Job Command: {:?}", command);
-        let output = match command.output() {
-            Ok(o) => o,
-            Err(e) => {
-                return Err(DeliveryError{
-                    kind: Kind::FailedToExecute,
-                    detail: Some(
-                        format!("failed to execute chef-client: {}",
-                        error::Error::description(&e))
-                    )
-                })
-            },
-        };
+        let output = limits::run(&mut command, phase_arg, limits)?;
         utils::cmd_success_or_err(&output, Kind::ChefFailed)?;
         Ok(())
     }
@@ -341,14 +352,14 @@ impl Workspace {
         let build_cb_name = config.build_cookbook_name()?;
         self.berks_vendor(&build_cb_name)?;
         let workspace_data = WorkspaceCompat{
-            root: path_to_string(&self.root),
-            chef: path_to_string(&self.chef),
-            cache: path_to_string(&self.cache),
-            repo: path_to_string(&self.repo),
-            ssh_wrapper: path_to_string(&self.ssh_wrapper),
+            root: path_to_string(&self.root)?,
+            chef: path_to_string(&self.chef)?,
+            cache: path_to_string(&self.cache)?,
+            repo: path_to_string(&self.repo)?,
+            ssh_wrapper: path_to_string(&self.ssh_wrapper)?,
         };
         let top = Top{
-            workspace_path: path_to_string(ws_path),
+            workspace_path: path_to_string(ws_path)?,
             workspace: workspace_data,
             change: change,
             // TODO: When we have a reserved field to pass attributes
@@ -358,9 +369,9 @@ impl Workspace {
             config: DeliveryConfig::load_raw_config(&self.repo)?,
         };
         let compat = BuilderCompat{
-            workspace: path_to_string(&self.root),
-            repo: path_to_string(&self.repo),
-            cache: path_to_string(&self.cache),
+            workspace: path_to_string(&self.root)?,
+            repo: path_to_string(&self.repo)?,
+            cache: path_to_string(&self.cache)?,
             build_id: "deprecated".to_string(),
             build_user: "dbuild".to_string()
         };
@@ -383,6 +394,11 @@ impl Workspace {
             try!(git::git_command(&["clone", git_url, "."], &self.repo));
         }
         try!(git::git_command(&["fetch", "origin"], &self.repo));
+        // The change may build on commits a shallow clone does not have.
+        if git::is_shallow_repository(&self.repo) {
+            debug!("Fetching the full history of the shallow clone in {:?}", self.repo);
+            try!(git::unshallow(&self.repo));
+        }
         try!(self.reset_repo("HEAD"));
         try!(git::git_command(&["checkout", pipeline], &self.repo));
         try!(self.reset_repo(&format!("remotes/origin/{}", pipeline)));
@@ -397,6 +413,23 @@ impl Workspace {
 
 }
 
+/// Returns the root of the workspace or cache managed by the CLI that
+/// `dir` is in, if any: a job workspace, or anything under `~/.delivery`,
+/// where the CLI keeps its caches, logs and unprivileged workspaces.
+pub fn managed_root(dir: &Path) -> Option<PathBuf> {
+    if let Some(marker) = utils::walk_tree_for_path(dir, WORKSPACE_MARKER) {
+        return marker.parent().map(Path::to_path_buf)
+    }
+    match utils::home_dir(&[".delivery"]) {
+        Ok(dot_delivery) => if dir.starts_with(&dot_delivery) {
+            Some(dot_delivery)
+        } else {
+            None
+        },
+        Err(_) => None
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -415,6 +448,18 @@ mod test {
         assert_eq!(w.repo, root.join("repo"));
     }
 
+    #[test]
+    fn test_workspace_for_run() {
+        let root = PathBuf::from("/tmp/cli-workspace-run");
+        let w = Workspace::for_run(&root, "1500000000-42");
+        assert_eq!(w.repo, root.join("runs").join("1500000000-42").join("repo"));
+        assert!(w.build().is_ok(), "The workspace build process failed");
+        assert!(is_dir(&w.cache));
+        w.remove().unwrap();
+        assert!(!is_dir(&w.root));
+        utils::remove_recursive(&root).unwrap();
+    }
+
     #[test]
     fn test_workspace_build() {
         let root = PathBuf::from("/tmp/cli-workspace-build");
@@ -425,6 +470,8 @@ mod test {
         assert!(is_dir(&w.chef.join("nodes")));
         assert!(is_dir(&w.cache));
         assert!(is_dir(&w.repo));
+        assert_eq!(Some(root.clone()), managed_root(&w.repo.join("cookbooks")));
+        assert_eq!(None, managed_root(&PathBuf::from("/tmp")));
         // Remove temp cli workspace
         utils::remove_recursive(&root).unwrap();
     }
@@ -18,6 +18,7 @@
 // #![feature(plugin, path_ext, convert)]
 extern crate regex;
 #[macro_use] extern crate log;
+extern crate env_logger;
 extern crate term;
 extern crate toml;
 extern crate time;
@@ -34,6 +35,7 @@ extern crate uuid;
 extern crate mime;
 extern crate clap;
 extern crate crypto;
+#[cfg(feature = "tls")] extern crate openssl;
 #[cfg(test)] extern crate mockito;
 
 #[macro_export]
@@ -74,3 +76,15 @@ pub mod command;
 pub mod fips;
 pub mod json;
 pub mod user;
+pub mod logs;
+pub mod report;
+pub mod prompt;
+pub mod changelog;
+pub mod features;
+pub mod secretscan;
+pub mod telemetry;
+pub mod tickets;
+pub mod events;
+pub mod annotations;
+pub mod library;
+pub mod schedule;
@@ -0,0 +1,133 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+//! Running delivery commands from another program
+//!
+//! The `delivery` binary parses its arguments with clap and ends the
+//! process with `process::exit`, neither of which suits a tool that
+//! embeds this crate. The functions of this module run the same
+//! commands from options built in code and hand the outcome back as a
+//! `DeliveryResult`: they never exit the process, and a panic in a
+//! command comes back as a `CommandPanicked` error instead of unwinding
+//! into the caller.
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use delivery::cli::diff::DiffClapOptions;
+//! use delivery::library;
+//!
+//! let options = DiffClapOptions { change: "my-feature", ..Default::default() };
+//! let config = library::load_config(Path::new("."), &options).unwrap();
+//! match library::diff(&options, &config) {
+//!     Ok(exit_code) => println!("diff exited with {}", exit_code),
+//!     Err(e) => println!("diff failed: {}", e),
+//! }
+//! ```
+
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::process;
+use cli::Options;
+use cli::init::InitClapOptions;
+use cli::review::ReviewClapOptions;
+use cli::diff::DiffClapOptions;
+use command::Command;
+use command::init::InitCommand;
+use command::review::ReviewCommand;
+use command::diff::DiffCommand;
+use config::Config;
+use errors::{DeliveryError, Kind};
+use types::{DeliveryResult, ExitCode};
+use utils::strict;
+
+/// Load the cli.toml that applies to `dir` and merge `options` into it,
+/// the way the binary does before running a command.
+pub fn load_config<O: Options>(dir: &Path, options: &O) -> DeliveryResult<Config> {
+    let config = try!(Config::load_config(&dir.to_path_buf()));
+    let config = try!(options.merge_options_and_config(config));
+    if config.strict.unwrap_or(false) {
+        strict::turn_on()
+    }
+    Ok(config)
+}
+
+/// Run `delivery init` in the current directory.
+pub fn init(options: &InitClapOptions, config: &Config) -> DeliveryResult<ExitCode> {
+    run_command(InitCommand{options: options, config: config, scp: None})
+}
+
+/// Run `delivery review` in the current directory.
+pub fn review(options: &ReviewClapOptions, config: &Config) -> DeliveryResult<ExitCode> {
+    run_command(ReviewCommand{options: options, config: config})
+}
+
+/// Run `delivery diff` in the current directory.
+pub fn diff(options: &DiffClapOptions, config: &Config) -> DeliveryResult<ExitCode> {
+    run_command(DiffCommand{options: options, config: config})
+}
+
+/// Set up, run and tear down `command`. The child processes started
+/// during the setup are stopped whatever the outcome of the run.
+pub fn run_command<C: Command>(command: C) -> DeliveryResult<ExitCode> {
+    let mut child_processes: Vec<process::Child> = Vec::new();
+    try!(command.setup(&mut child_processes));
+    let result = panic::catch_unwind(AssertUnwindSafe(|| command.run()));
+    try!(command.teardown(child_processes));
+    match result {
+        Ok(command_result) => command_result,
+        Err(cause) => {
+            let detail = cause.downcast_ref::<&str>().map(|s| s.to_string())
+                .or_else(|| cause.downcast_ref::<String>().cloned());
+            Err(DeliveryError::throw(Kind::CommandPanicked, detail))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run_command;
+    use command::Command;
+    use errors::Kind;
+    use types::{DeliveryResult, ExitCode};
+
+    struct Panicking;
+
+    impl Command for Panicking {
+        fn run(&self) -> DeliveryResult<ExitCode> {
+            panic!("boom")
+        }
+    }
+
+    struct Exiting(ExitCode);
+
+    impl Command for Exiting {
+        fn run(&self) -> DeliveryResult<ExitCode> {
+            Ok(self.0)
+        }
+    }
+
+    #[test]
+    fn test_run_command_returns_the_exit_code() {
+        assert_eq!(3, run_command(Exiting(3)).unwrap());
+    }
+
+    #[test]
+    fn test_run_command_turns_panics_into_errors() {
+        let err = run_command(Panicking).unwrap_err();
+        assert!(assert_enum!(err.kind, Kind::CommandPanicked));
+        assert_eq!(Some("boom".to_string()), err.detail);
+    }
+}
@@ -0,0 +1,120 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! The logger behind the `log` macros
+//!
+//! What gets printed to stderr is picked by the global `-v` flags (info,
+//! then debug, then trace) and refined with a `RUST_LOG` filter, e.g.
+//! `RUST_LOG=delivery::http=trace`. With `--log-commands`, or
+//! `log_commands = true` in the cli.toml, every git command and HTTP
+//! request the CLI makes is also appended to the CLI log file, whatever
+//! the verbosity, so a support case can start from the log. Credentials
+//! are redacted from both.
+
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use env_logger::{LogBuilder, Logger};
+use log::{self, Log, LogLevel, LogLevelFilter, LogMetadata, LogRecord};
+
+static LOG_COMMANDS: AtomicBool = AtomicBool::new(false);
+
+/// The modules whose debug output are the git commands and HTTP requests
+pub const COMMAND_TARGETS: [&'static str; 2] = ["delivery::git", "delivery::http"];
+
+struct CliLogger {
+    stderr: Logger,
+}
+
+impl Log for CliLogger {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        self.stderr.enabled(metadata) || logs_command(metadata)
+    }
+
+    fn log(&self, record: &LogRecord) {
+        self.stderr.log(record);
+        if logs_command(record.metadata()) {
+            super::record(&format!("{} {}: {}", record.level(), record.target(), record.args()));
+        }
+    }
+}
+
+/// Set up logging for a run, at the level of `verbosity` `-v` flags
+pub fn init(verbosity: u64) {
+    let mut builder = LogBuilder::new();
+    builder.format(|record: &LogRecord| {
+        super::redact(&format!("{}:{}: {}", record.level(),
+                               record.location().module_path(), record.args()))
+    });
+    builder.filter(None, level_for(verbosity));
+    if let Ok(filter) = env::var("RUST_LOG") {
+        builder.parse(&filter);
+    }
+    let logger = CliLogger { stderr: builder.build() };
+    let result = log::set_logger(|max_level| {
+        // The command log is turned on after the config is read, so
+        // leave the filtering to `enabled`
+        max_level.set(LogLevelFilter::Trace);
+        Box::new(logger)
+    });
+    if result.is_err() {
+        debug!("A logger is already set");
+    }
+}
+
+/// Append the git commands and HTTP requests to the log file from now on
+pub fn turn_on_command_log() {
+    LOG_COMMANDS.store(true, Ordering::Relaxed);
+}
+
+pub fn level_for(verbosity: u64) -> LogLevelFilter {
+    match verbosity {
+        0 => LogLevelFilter::Error,
+        1 => LogLevelFilter::Info,
+        2 => LogLevelFilter::Debug,
+        _ => LogLevelFilter::Trace,
+    }
+}
+
+fn logs_command(metadata: &LogMetadata) -> bool {
+    LOG_COMMANDS.load(Ordering::Relaxed) && is_command(metadata.level(), metadata.target())
+}
+
+fn is_command(level: LogLevel, target: &str) -> bool {
+    level <= LogLevel::Debug && COMMAND_TARGETS.iter().any(|t| target.starts_with(t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{level_for, is_command};
+    use log::{LogLevel, LogLevelFilter};
+
+    #[test]
+    fn test_level_for() {
+        assert_eq!(LogLevelFilter::Error, level_for(0));
+        assert_eq!(LogLevelFilter::Info, level_for(1));
+        assert_eq!(LogLevelFilter::Debug, level_for(2));
+        assert_eq!(LogLevelFilter::Trace, level_for(5));
+    }
+
+    #[test]
+    fn test_is_command() {
+        assert!(is_command(LogLevel::Debug, "delivery::git"));
+        assert!(is_command(LogLevel::Info, "delivery::http::change"));
+        assert!(!is_command(LogLevel::Trace, "delivery::git"));
+        assert!(!is_command(LogLevel::Debug, "delivery::project"));
+    }
+}
@@ -0,0 +1,215 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! CLI log files
+//!
+//! Every invocation of the cli appends a few lines to
+//! `~/.delivery/logs/delivery.log` (the command that was run, how it
+//! exited and the error it failed with, if any). The file is rotated
+//! once it grows past `MAX_LOG_SIZE`, keeping at most `MAX_ROTATED_LOGS`
+//! older files, and rotated files older than `MAX_LOG_AGE` are removed.
+//! This keeps what we ask users to send to support small and recent.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use regex::Regex;
+use time;
+use types::DeliveryResult;
+use utils;
+
+pub mod logger;
+pub mod output;
+
+pub const LOG_FILE_NAME: &'static str = "delivery.log";
+pub const MAX_LOG_SIZE: u64 = 1024 * 1024;
+pub const MAX_ROTATED_LOGS: usize = 5;
+pub const MAX_LOG_AGE: u64 = 14 * 24 * 60 * 60;
+
+/// The directory holding the cli log files: `~/.delivery/logs`
+pub fn logs_dir() -> DeliveryResult<PathBuf> {
+    utils::home_dir(&[".delivery", "logs"])
+}
+
+/// Append a timestamped line to the log file, rotating it first if
+/// needed. Logging is best effort: a failure to write the log must
+/// never make a command fail, so errors are only reported at debug.
+pub fn record(line: &str) {
+    let result = logs_dir().and_then(|dir| {
+        try!(rotate(&dir, MAX_LOG_SIZE, Duration::from_secs(MAX_LOG_AGE)));
+        append(&dir, line)
+    });
+    if let Err(e) = result {
+        debug!("Unable to write to the log file: {:?}", e);
+    }
+}
+
+/// Append a timestamped line to the log file in `dir`
+pub fn append(dir: &Path, line: &str) -> DeliveryResult<()> {
+    try!(utils::mkdir_recursive(dir));
+    let mut file = try!(OpenOptions::new().create(true).append(true)
+                                          .open(dir.join(LOG_FILE_NAME)));
+    try!(writeln!(file, "[{}] {}", time::now().rfc3339(), redact(line)));
+    Ok(())
+}
+
+/// Rotate the log file in `dir` once it reaches `max_size` bytes and
+/// remove the rotated files last modified longer than `max_age` ago.
+pub fn rotate(dir: &Path, max_size: u64, max_age: Duration) -> DeliveryResult<()> {
+    let current = dir.join(LOG_FILE_NAME);
+    if let Ok(meta) = fs::metadata(&current) {
+        if meta.len() >= max_size {
+            for i in (1..MAX_ROTATED_LOGS).rev() {
+                let older = rotated_log(dir, i);
+                if older.exists() {
+                    try!(fs::rename(&older, rotated_log(dir, i + 1)));
+                }
+            }
+            try!(fs::rename(&current, rotated_log(dir, 1)));
+        }
+    }
+    let now = SystemTime::now();
+    for i in 1..(MAX_ROTATED_LOGS + 1) {
+        let path = rotated_log(dir, i);
+        let expired = fs::metadata(&path).and_then(|m| m.modified()).ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .map(|age| age > max_age)
+            .unwrap_or(false);
+        if expired {
+            try!(fs::remove_file(&path));
+        }
+    }
+    Ok(())
+}
+
+/// Every log file in `dir`, oldest first
+pub fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = (1..(MAX_ROTATED_LOGS + 1)).rev()
+        .map(|i| rotated_log(dir, i))
+        .collect();
+    files.push(dir.join(LOG_FILE_NAME));
+    files.into_iter().filter(|f| f.exists()).collect()
+}
+
+/// The last `count` lines logged in `dir`, across rotated files
+pub fn tail(dir: &Path, count: usize) -> DeliveryResult<Vec<String>> {
+    let mut lines = Vec::new();
+    for file in log_files(dir) {
+        let mut content = String::new();
+        try!(try!(File::open(&file)).read_to_string(&mut content));
+        lines.extend(content.lines().map(|l| l.to_string()));
+    }
+    let skip = lines.len().saturating_sub(count);
+    Ok(lines.split_off(skip))
+}
+
+/// Print whatever gets appended to the log file in `dir`, until the
+/// process is interrupted. Starts over when the file gets rotated.
+pub fn follow<F>(dir: &Path, mut print: F) -> DeliveryResult<()> where F: FnMut(&str) {
+    let path = dir.join(LOG_FILE_NAME);
+    let mut position = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    loop {
+        thread::sleep(Duration::from_millis(500));
+        let len = match fs::metadata(&path) {
+            Ok(meta) => meta.len(),
+            Err(_) => continue
+        };
+        if len < position {
+            position = 0;
+        }
+        if len == position {
+            continue
+        }
+        let mut file = try!(File::open(&path));
+        try!(file.seek(SeekFrom::Start(position)));
+        let mut content = String::new();
+        try!(file.read_to_string(&mut content));
+        position += content.len() as u64;
+        for line in content.lines() {
+            print(line);
+        }
+    }
+}
+
+/// Mask the value of anything that looks like a credential, so logs
+/// and config files can be shared without leaking tokens.
+pub fn redact(text: &str) -> String {
+    let re = Regex::new(r#"(?i)(token|password|passwd|secret)(["']?\s*[:=]\s*["']?)[^"'\s,}]+"#)
+        .unwrap();
+    re.replace_all(text, "$1$2[REDACTED]")
+}
+
+fn rotated_log(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("{}.{}", LOG_FILE_NAME, index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::time::Duration;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_redact() {
+        assert_eq!("token = \"[REDACTED]\"", redact("token = \"abc123\""));
+        assert_eq!("{\"password\":\"[REDACTED]\"}", redact("{\"password\":\"hunter2\"}"));
+        assert_eq!("server = \"automate.example.com\"",
+                   redact("server = \"automate.example.com\""));
+    }
+
+    #[test]
+    fn test_tail_across_rotated_logs() {
+        let tmp = TempDir::new("logs").unwrap();
+        let dir = tmp.path();
+        File::create(dir.join("delivery.log.1")).unwrap().write_all(b"one\ntwo\n").unwrap();
+        File::create(dir.join(LOG_FILE_NAME)).unwrap().write_all(b"three\nfour\n").unwrap();
+        assert_eq!(vec!["two", "three", "four"], tail(dir, 3).unwrap());
+        assert_eq!(4, tail(dir, 100).unwrap().len());
+    }
+
+    #[test]
+    fn test_rotate_by_size() {
+        let tmp = TempDir::new("logs").unwrap();
+        let dir = tmp.path();
+        append(dir, "first").unwrap();
+        rotate(dir, 1, Duration::from_secs(MAX_LOG_AGE)).unwrap();
+        append(dir, "second").unwrap();
+        rotate(dir, 1, Duration::from_secs(MAX_LOG_AGE)).unwrap();
+        assert!(!dir.join(LOG_FILE_NAME).exists());
+        assert_eq!(vec![dir.join("delivery.log.2"), dir.join("delivery.log.1")],
+                   log_files(dir));
+        let lines = tail(dir, 10).unwrap();
+        assert!(lines[0].ends_with("first"));
+        assert!(lines[1].ends_with("second"));
+    }
+
+    #[test]
+    fn test_rotate_removes_old_logs() {
+        let tmp = TempDir::new("logs").unwrap();
+        let dir = tmp.path();
+        File::create(dir.join("delivery.log.1")).unwrap();
+        rotate(dir, MAX_LOG_SIZE, Duration::from_secs(MAX_LOG_AGE)).unwrap();
+        assert!(dir.join("delivery.log.1").exists());
+        ::std::thread::sleep(Duration::from_millis(10));
+        rotate(dir, MAX_LOG_SIZE, Duration::from_millis(1)).unwrap();
+        assert!(fs::metadata(dir.join("delivery.log.1")).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Output of the tools the cli runs
+//!
+//! Tools like `chef generate` print plenty, and only matters when they
+//! fail. Their output goes to `~/.delivery/logs/<name>.log`, replaced on
+//! each run, and is shown as it comes with `--verbose`. When the tool
+//! fails, the error carries the last `TAIL_LINES` lines and the path of
+//! the full log.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+use utils;
+use utils::say::sayln;
+
+pub const TAIL_LINES: usize = 20;
+
+/// Run `command`, logging its output to the `name` log of the cli log
+/// directory, and fail with an error of `kind` when it fails
+pub fn run(command: &mut Command, name: &str, kind: Kind) -> DeliveryResult<()> {
+    let dir = try!(super::logs_dir());
+    run_in(&dir, command, name, kind, log_enabled!(::log::LogLevel::Info)).map(|_| ())
+}
+
+/// Run `command` with its output logged to `<dir>/<name>.log`, and
+/// printed with `echo`. The path of the log on success.
+pub fn run_in(dir: &Path, command: &mut Command, name: &str, kind: Kind,
+              echo: bool) -> DeliveryResult<PathBuf> {
+    try!(utils::mkdir_recursive(dir));
+    let log_path = dir.join(format!("{}.log", name));
+    let mut log = try!(File::create(&log_path));
+    let mut child = try!(command.stdin(Stdio::null())
+                         .stdout(Stdio::piped())
+                         .stderr(Stdio::piped())
+                         .spawn());
+
+    // Both streams are read at once, or a tool filling the pipe of one
+    // while we wait on the other would never finish
+    let (sender, receiver) = mpsc::channel();
+    let mut readers = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        readers.push(forward(stdout, sender.clone()));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        readers.push(forward(stderr, sender.clone()));
+    }
+    drop(sender);
+
+    let mut tail = VecDeque::with_capacity(TAIL_LINES);
+    for line in receiver.iter() {
+        try!(writeln!(log, "{}", line));
+        if echo {
+            sayln("white", &format!("  {}", line));
+        }
+        if tail.len() == TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    }
+    for reader in readers {
+        let _ = reader.join();
+    }
+    let status = try!(child.wait());
+    if status.success() {
+        return Ok(log_path)
+    }
+    let lines: Vec<String> = tail.into_iter().collect();
+    let msg = format!("{} failed ({}). Its last lines:\n{}\nThe full output is in {}",
+                      name, status, lines.join("\n"), log_path.display());
+    Err(DeliveryError::throw(kind, Some(msg)))
+}
+
+fn forward<R: Read + Send + 'static>(stream: R,
+                                     sender: mpsc::Sender<String>) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            match line {
+                Ok(line) => if sender.send(line).is_err() { break },
+                Err(_) => break
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::process::Command;
+    use tempdir::TempDir;
+    use errors::Kind;
+
+    fn script(body: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(body);
+        command
+    }
+
+    #[test]
+    fn test_run_in_logs_the_output() {
+        let tmp = TempDir::new("output").unwrap();
+        let path = run_in(tmp.path(), &mut script("echo generated; echo warned >&2"),
+                          "chef-generate", Kind::ChefdkGenerateFailed, false).unwrap();
+        assert_eq!(tmp.path().join("chef-generate.log"), path);
+        let mut log = String::new();
+        File::open(&path).unwrap().read_to_string(&mut log).unwrap();
+        assert!(log.contains("generated\n"));
+        assert!(log.contains("warned\n"));
+    }
+
+    #[test]
+    fn test_run_in_shows_the_tail_on_failure() {
+        let tmp = TempDir::new("output").unwrap();
+        let body = "i=1; while [ $i -le 30 ]; do echo line $i; i=$((i+1)); done; exit 3";
+        let err = run_in(tmp.path(), &mut script(body), "chef-generate",
+                         Kind::ChefdkGenerateFailed, false).unwrap_err();
+        assert!(assert_enum!(err.kind, Kind::ChefdkGenerateFailed));
+        let detail = err.detail.unwrap();
+        assert!(detail.contains("line 11\n"));
+        assert!(detail.contains("line 30\n"));
+        assert!(!detail.contains("line 10\n"));
+        assert!(detail.contains(&tmp.path().join("chef-generate.log").display().to_string()));
+    }
+}
@@ -0,0 +1,196 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Selecting changes for bulk operations
+//!
+//! `delivery change abandon --state open --older-than 90d` picks the
+//! changes of a pipeline by state, age and feature branch, so stale
+//! changes can be cleaned up by the hundred rather than one at a time.
+
+use time;
+use errors::{DeliveryError, Kind};
+use http::change::ChangeSummary;
+use types::DeliveryResult;
+
+/// Which changes of a pipeline an operation applies to. The state is
+/// picked by the server, the rest here.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    pub state: String,
+    pub pipeline: String,
+    pub older_than: Option<Age>,
+    // A glob on the feature branch of the change
+    pub topic: Option<String>,
+}
+
+impl Selector {
+    /// Whether `change` is selected `now`, seconds since the epoch. A
+    /// change whose age the server does not report is never old enough.
+    pub fn matches(&self, change: &ChangeSummary, now: i64) -> bool {
+        if let Some(ref pattern) = self.topic {
+            if !glob_match(pattern, &change.topic) {
+                return false
+            }
+        }
+        match self.older_than {
+            Some(ref age) => match parse_submitted_at(&change.submitted_at) {
+                Some(submitted) => now - submitted > age.seconds,
+                None => false
+            },
+            None => true
+        }
+    }
+
+    /// i.e. `open changes of legacy older than 90d matching feature/*`
+    pub fn describe(&self) -> String {
+        let mut description = format!("{} changes of {}", self.state, self.pipeline);
+        if let Some(ref age) = self.older_than {
+            description.push_str(&format!(" older than {}", age.given));
+        }
+        if let Some(ref topic) = self.topic {
+            description.push_str(&format!(" matching {}", topic));
+        }
+        description
+    }
+}
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// An age as the user gave it, i.e. `90d`, and in seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Age {
+    pub given: String,
+    pub seconds: i64,
+}
+
+/// Parse an age like `90d`, `12w` or `36h`
+pub fn parse_age(age: &str) -> DeliveryResult<Age> {
+    let age = age.trim();
+    let (number, unit) = match age.char_indices().last() {
+        Some((i, _)) => age.split_at(i),
+        None => ("", "")
+    };
+    let unit = match unit {
+        "h" => 60 * 60,
+        "d" => DAY,
+        "w" => 7 * DAY,
+        _ => 0
+    };
+    let seconds = number.parse::<i64>().ok()
+        .filter(|n| *n > 0 && unit > 0)
+        .and_then(|n| n.checked_mul(unit));
+    match seconds {
+        Some(seconds) => Ok(Age { given: age.to_string(), seconds: seconds }),
+        None => {
+            let msg = format!("'{}' is not an age, use a number of hours, days or weeks, \
+                               i.e. 36h, 90d or 12w", age);
+            Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+    }
+}
+
+/// When the change was submitted, as the server writes it
+/// (`2016-03-14 20:12:59` or `2016-03-14T20:12:59Z`), in seconds since
+/// the epoch
+pub fn parse_submitted_at(submitted_at: &str) -> Option<i64> {
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%SZ"].iter()
+        .filter_map(|format| time::strptime(submitted_at.trim(), format).ok())
+        .map(|tm| tm.to_timespec().sec)
+        .next()
+}
+
+/// Match `text` against a glob where `*` is any run of characters and
+/// `?` any one character
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and how much of the text it took
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::change::ChangeSummary;
+
+    fn change(topic: &str, submitted_at: &str) -> ChangeSummary {
+        ChangeSummary {
+            id: "c1".to_string(), topic: topic.to_string(), target: "legacy".to_string(),
+            title: String::new(), sha: String::new(), stage: String::new(),
+            stage_status: String::new(), patchset_number: 1, approved_by: String::new(),
+            approved_at: String::new(), submitted_at: submitted_at.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(90 * 24 * 60 * 60, parse_age("90d").unwrap().seconds);
+        assert_eq!(2 * 7 * 24 * 60 * 60, parse_age("2w").unwrap().seconds);
+        assert_eq!(36 * 60 * 60, parse_age("36h").unwrap().seconds);
+        assert_eq!("36h", parse_age(" 36h").unwrap().given);
+        assert!(parse_age("90").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("").is_err());
+        assert!(parse_age("-1d").is_err());
+        assert!(parse_age("9é").is_err());
+        assert!(parse_age("99999999999999999w").is_err());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("feature/*", "feature/login"));
+        assert!(glob_match("*-wip", "rob/login-wip"));
+        assert!(glob_match("rob/?ogin*", "rob/login-wip"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("feature/*", "fix/login"));
+        assert!(!glob_match("rob/?", "rob/login"));
+    }
+
+    #[test]
+    fn test_selector() {
+        let now = parse_submitted_at("2024-06-01 00:00:00").unwrap();
+        let selector = Selector {
+            state: "open".to_string(), pipeline: "legacy".to_string(),
+            older_than: Some(parse_age("90d").unwrap()), topic: Some("rob/*".to_string()),
+        };
+        assert_eq!("open changes of legacy older than 90d matching rob/*", selector.describe());
+        assert!(selector.matches(&change("rob/login", "2024-01-01 10:00:00"), now));
+        assert!(selector.matches(&change("rob/login", "2024-01-01T10:00:00Z"), now));
+        assert!(!selector.matches(&change("rob/login", "2024-05-01 10:00:00"), now));
+        assert!(!selector.matches(&change("ann/login", "2024-01-01 10:00:00"), now));
+        assert!(!selector.matches(&change("rob/login", ""), now));
+        let any = Selector { older_than: None, topic: None, ..selector };
+        assert!(any.matches(&change("ann/login", ""), now));
+    }
+}
@@ -0,0 +1,226 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// Delivery ordering
+//
+// Pipelines declare the projects they depend on (the `dependencies` of
+// their `.delivery/config.json`). When changes on several of those
+// projects are approved together they have to be delivered upstream
+// first, otherwise the downstream change is blocked waiting for a
+// dependency that is itself waiting on the union stage.
+
+use std::collections::{HashMap, HashSet};
+use errors::{DeliveryError, Kind};
+use http::change::ChangeSummary;
+use types::DeliveryResult;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeRef {
+    pub project: String,
+    pub id: String,
+}
+
+impl ChangeRef {
+    /// Parse a `project:change-id` reference; a bare change id belongs
+    /// to `default_project`.
+    pub fn parse(reference: &str, default_project: &str) -> DeliveryResult<ChangeRef> {
+        let reference = reference.trim();
+        let (project, id) = match reference.find(':') {
+            Some(i) => (&reference[..i], &reference[i + 1..]),
+            None => (default_project, reference)
+        };
+        if project.is_empty() || id.is_empty() {
+            let msg = format!("Invalid change '{}', expected <change-id> or \
+                              <project>:<change-id>", reference);
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        Ok(ChangeRef { project: project.to_string(), id: id.to_string() })
+    }
+}
+
+/// Parse a comma separated list of change references
+pub fn parse_changes(list: &str, default_project: &str) -> DeliveryResult<Vec<ChangeRef>> {
+    list.split(',')
+        .filter(|c| !c.trim().is_empty())
+        .map(|c| ChangeRef::parse(c, default_project))
+        .collect()
+}
+
+/// Dependencies may be written as `org/project`, we only compare names
+pub fn dependency_name(dep: &str) -> &str {
+    dep.rsplit('/').next().unwrap_or(dep)
+}
+
+/// The organization and name of a dependency; a bare project name is in
+/// the organization of the project that depends on it.
+pub fn dependency_project<'a>(dep: &'a str, org: &'a str) -> (&'a str, &'a str) {
+    match dep.rfind('/') {
+        Some(i) => (&dep[..i], &dep[i + 1..]),
+        None => (org, dep)
+    }
+}
+
+// Every project `project` depends on, directly or not
+fn all_dependencies<'a>(project: &'a str,
+                        deps: &'a HashMap<String, Vec<String>>) -> HashSet<&'a str> {
+    let mut seen = HashSet::new();
+    let mut pending = vec![project];
+    while let Some(p) = pending.pop() {
+        if let Some(ds) = deps.get(p) {
+            for d in ds.iter().map(|d| dependency_name(d)) {
+                if seen.insert(d) {
+                    pending.push(d);
+                }
+            }
+        }
+    }
+    seen
+}
+
+/// Sort `changes` so that every change comes after the changes of the
+/// projects it depends on. `deps` maps a project to its dependencies.
+/// Changes that do not depend on each other keep the order they were
+/// given in.
+pub fn order(changes: &[ChangeRef],
+             deps: &HashMap<String, Vec<String>>) -> DeliveryResult<Vec<ChangeRef>> {
+    let upstream: Vec<HashSet<&str>> = changes.iter()
+        .map(|c| all_dependencies(&c.project, deps))
+        .collect();
+    let mut remaining: Vec<usize> = (0..changes.len()).collect();
+    let mut ordered = Vec::new();
+
+    while !remaining.is_empty() {
+        // The first change none of the remaining changes has to precede
+        let ready = remaining.iter().position(|&i| {
+            !remaining.iter().any(|&j| {
+                j != i && upstream[i].contains(changes[j].project.as_str())
+                    && changes[j].project != changes[i].project
+            })
+        });
+        match ready {
+            Some(pos) => ordered.push(changes[remaining.remove(pos)].clone()),
+            None => {
+                let cycle: Vec<String> = remaining.iter()
+                    .map(|&i| format!("{}:{}", changes[i].project, changes[i].id))
+                    .collect();
+                let msg = format!("These changes depend on each other: {}", cycle.join(", "));
+                return Err(DeliveryError::throw(Kind::DependencyCycle, Some(msg)))
+            }
+        }
+    }
+    Ok(ordered)
+}
+
+/// An approved change waiting to be merged in a pipeline
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueuedChange {
+    pub project: String,
+    pub change: ChangeSummary,
+}
+
+/// The estimated merge order of approved changes: first approved, first
+/// merged, except that a change waits for the changes of the projects
+/// it depends on.
+pub fn merge_queue(mut queued: Vec<QueuedChange>,
+                   deps: &HashMap<String, Vec<String>>) -> DeliveryResult<Vec<QueuedChange>> {
+    queued.sort_by(|a, b| a.change.approved_at.cmp(&b.change.approved_at));
+    let refs: Vec<ChangeRef> = queued.iter()
+        .map(|q| ChangeRef { project: q.project.clone(), id: q.change.id.clone() })
+        .collect();
+    let ordered = try!(order(&refs, deps));
+    Ok(ordered.iter().filter_map(|r| {
+        queued.iter().find(|q| q.project == r.project && q.change.id == r.id).cloned()
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn change(project: &str, id: &str) -> ChangeRef {
+        ChangeRef { project: project.to_string(), id: id.to_string() }
+    }
+
+    fn deps(list: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        list.iter()
+            .map(|&(p, ds)| (p.to_string(), ds.iter().map(|d| d.to_string()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_changes() {
+        let changes = parse_changes("abc, api:def,", "web").unwrap();
+        assert_eq!(vec![change("web", "abc"), change("api", "def")], changes);
+        assert!(parse_changes("api:", "web").is_err());
+    }
+
+    #[test]
+    fn test_dependency_project() {
+        assert_eq!(("other", "db"), dependency_project("other/db", "acme"));
+        assert_eq!(("acme", "db"), dependency_project("db", "acme"));
+    }
+
+    #[test]
+    fn test_order_upstream_first() {
+        let changes = vec![change("web", "1"), change("api", "2"), change("db", "3")];
+        let d = deps(&[("web", &["api"]), ("api", &["org/db"])]);
+        assert_eq!(vec![change("db", "3"), change("api", "2"), change("web", "1")],
+                   order(&changes, &d).unwrap());
+    }
+
+    #[test]
+    fn test_order_keeps_independent_changes_in_place() {
+        let changes = vec![change("a", "1"), change("b", "2"), change("a", "3")];
+        assert_eq!(changes, order(&changes, &HashMap::new()).unwrap());
+    }
+
+    #[test]
+    fn test_order_through_missing_project() {
+        // web depends on api through a project with no change to deliver
+        let changes = vec![change("web", "1"), change("db", "2")];
+        let d = deps(&[("web", &["api"]), ("api", &["db"])]);
+        assert_eq!(vec![change("db", "2"), change("web", "1")],
+                   order(&changes, &d).unwrap());
+    }
+
+    fn queued(project: &str, id: &str, approved_at: &str) -> QueuedChange {
+        let json = format!("[{{\"id\":\"{}\",\"topic\":\"t\",\"target\":\"master\",\
+                            \"approved_at\":\"{}\"}}]", id, approved_at);
+        QueuedChange {
+            project: project.to_string(),
+            change: ChangeSummary::parse_json_list(&json).unwrap().remove(0),
+        }
+    }
+
+    #[test]
+    fn test_merge_queue_by_approval_then_dependencies() {
+        let q = vec![queued("web", "1", "2017-05-02T10:00:00Z"),
+                     queued("api", "2", "2017-05-02T11:00:00Z"),
+                     queued("docs", "3", "2017-05-01T09:00:00Z")];
+        let d = deps(&[("web", &["api"])]);
+        let ids: Vec<String> = merge_queue(q, &d).unwrap()
+            .into_iter().map(|q| q.change.id).collect();
+        assert_eq!(vec!["3", "2", "1"], ids);
+    }
+
+    #[test]
+    fn test_order_detects_cycles() {
+        let changes = vec![change("a", "1"), change("b", "2")];
+        let d = deps(&[("a", &["b"]), ("b", &["a"])]);
+        assert!(order(&changes, &d).is_err());
+    }
+}
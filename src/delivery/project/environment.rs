@@ -0,0 +1,155 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+//! The Chef environments of the stages of a pipeline
+//!
+//! delivery-truck deploys acceptance to an environment of its own per
+//! project and pipeline, named `acceptance-<ent>-<org>-<project>-<pipeline>`,
+//! while union, rehearsal and delivered are shared by every project and
+//! named after the stage. Comparing what those environments hold is how
+//! you find out why a change that passed acceptance breaks in union.
+
+use std::collections::BTreeMap;
+use serde_json;
+use serde_json::Value;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+use utils;
+
+/// The parts of an environment that change what gets deployed
+pub const DEPLOYMENT_KEYS: &'static [&'static str] = &["cookbook_versions",
+                                                       "default_attributes",
+                                                       "override_attributes"];
+
+/// Returns the name of the Chef environment of a stage
+pub fn environment_name(stage: &str, ent: &str, org: &str,
+                        proj: &str, pipeline: &str) -> String {
+    match stage {
+        "acceptance" => format!("acceptance-{}-{}-{}-{}", ent, org, proj, pipeline),
+        _ => stage.to_string()
+    }
+}
+
+/// Fetch an environment from the Chef Server with knife
+pub fn fetch(name: &str) -> DeliveryResult<Value> {
+    let result = try!(utils::make_command("knife")
+                      .args(&["environment", "show", name, "-F", "json"])
+                      .output());
+    try!(utils::cmd_success_or_err(&result, Kind::EnvironmentFetchFailed));
+    match serde_json::from_slice(&result.stdout) {
+        Ok(env) => Ok(env),
+        Err(e) => {
+            let msg = format!("knife returned invalid JSON for the environment \
+                               '{}': {}", name, e);
+            Err(DeliveryError::throw(Kind::EnvironmentFetchFailed, Some(msg)))
+        }
+    }
+}
+
+/// Flattens the deployment keys of an environment into a map from
+/// dotted paths, i.e. `override_attributes.app.version`, to the value
+/// found there. Arrays are compared as a whole and empty hashes,
+/// which set nothing, are left out.
+pub fn deployment_attributes(env: &Value) -> BTreeMap<String, Value> {
+    let mut attributes = BTreeMap::new();
+    for key in DEPLOYMENT_KEYS {
+        if let Some(value) = env.get(key) {
+            flatten(key, value, &mut attributes);
+        }
+    }
+    attributes
+}
+
+fn flatten(path: &str, value: &Value, into: &mut BTreeMap<String, Value>) {
+    match *value {
+        Value::Object(ref map) => {
+            for (key, child) in map.iter() {
+                flatten(&format!("{}.{}", path, key), child, into);
+            }
+        },
+        _ => { into.insert(path.to_string(), value.clone()); }
+    }
+}
+
+/// A path whose value differs between two environments. `None` means
+/// the path is not set in that environment.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Difference {
+    pub path: String,
+    pub from: Option<Value>,
+    pub to: Option<Value>,
+}
+
+/// Returns the differences between two environments, sorted by path
+pub fn diff(from: &Value, to: &Value) -> Vec<Difference> {
+    let from = deployment_attributes(from);
+    let mut to = deployment_attributes(to);
+    let mut differences = Vec::new();
+    for (path, value) in from.into_iter() {
+        match to.remove(&path) {
+            Some(ref other) if *other == value => {},
+            other => differences.push(Difference { path: path, from: Some(value), to: other }),
+        }
+    }
+    for (path, value) in to.into_iter() {
+        differences.push(Difference { path: path, from: None, to: Some(value) });
+    }
+    differences.sort_by(|a, b| a.path.cmp(&b.path));
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_environment_name() {
+        assert_eq!("acceptance-ent-org-proj-master",
+                   environment_name("acceptance", "ent", "org", "proj", "master"));
+        assert_eq!("union", environment_name("union", "ent", "org", "proj", "master"));
+    }
+
+    #[test]
+    fn test_diff() {
+        let acceptance = json!({
+            "name": "acceptance-ent-org-proj-master",
+            "cookbook_versions": { "proj": "= 1.2.0" },
+            "override_attributes": {
+                "app": { "version": "1.2.0", "port": 8080, "flags": ["a"] }
+            },
+            "default_attributes": {}
+        });
+        let union = json!({
+            "name": "union",
+            "cookbook_versions": { "proj": "= 1.1.0" },
+            "override_attributes": {
+                "app": { "version": "1.2.0", "flags": ["a", "b"] },
+                "db": { "host": "db.union" }
+            }
+        });
+        let differences = diff(&acceptance, &union);
+        let paths: Vec<&str> = differences.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(vec!["cookbook_versions.proj",
+                        "override_attributes.app.flags",
+                        "override_attributes.app.port",
+                        "override_attributes.db.host"], paths);
+        assert_eq!(Some(json!("= 1.2.0")), differences[0].from);
+        assert_eq!(Some(json!("= 1.1.0")), differences[0].to);
+        assert_eq!(None, differences[2].to);
+        assert_eq!(None, differences[3].from);
+        assert!(diff(&acceptance, &acceptance).is_empty());
+    }
+}
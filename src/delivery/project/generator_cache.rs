@@ -0,0 +1,186 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! The cache of custom build cookbook generators
+//!
+//! Every generator `delivery init` fetches is kept in
+//! `~/.delivery/cache/generator-cookbooks`, with a `<entry>.source.json`
+//! next to it recording where it came from and, for git generators, the
+//! commit that was cloned. That is what tells a stale entry from a good
+//! one when init is asked to `--generator-refresh` and when
+//! `delivery cache clean` runs.
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use serde_json;
+use git;
+use utils;
+use utils::path_ext::is_dir;
+use types::DeliveryResult;
+use super::SupermarketGenerator;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CacheEntry {
+    pub generator: String,
+    pub sha: Option<String>,
+}
+
+pub fn source_file(generator_path: &Path) -> PathBuf {
+    let mut name = generator_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".source.json");
+    generator_path.with_file_name(name)
+}
+
+/// Write down where the entry at `generator_path` was fetched from
+pub fn record(generator_path: &Path, generator: &str) -> DeliveryResult<()> {
+    let sha = if is_dir(&generator_path.join(".git")) {
+        git::git_command(&["rev-parse", "HEAD"], generator_path).ok()
+            .map(|result| result.stdout.trim().to_string())
+    } else {
+        None
+    };
+    let entry = CacheEntry { generator: generator.to_string(), sha: sha };
+    let json = try!(serde_json::to_string_pretty(&entry));
+    try!(try!(File::create(source_file(generator_path))).write_all(json.as_bytes()));
+    Ok(())
+}
+
+pub fn read(generator_path: &Path) -> Option<CacheEntry> {
+    let mut content = String::new();
+    File::open(source_file(generator_path)).ok()
+        .and_then(|mut f| f.read_to_string(&mut content).ok())
+        .and_then(|_| serde_json::from_str(&content).ok())
+}
+
+/// The commit HEAD of a git generator points to upstream, `None` when
+/// the remote cannot be reached.
+pub fn upstream_sha(generator: &str) -> Option<String> {
+    git::git_command(&["ls-remote", generator, "HEAD"], &utils::cwd()).ok()
+        .and_then(|result| result.stdout.split_whitespace().next().map(String::from))
+}
+
+/// Whether the entry at `generator_path` should be fetched again: its
+/// origin is unknown, its source is gone from disk, its git upstream has
+/// moved, or it is an unpinned Supermarket cookbook that may have a
+/// newer release. An unreachable git remote never makes an entry stale.
+pub fn is_stale(generator_path: &Path) -> bool {
+    let entry = match read(generator_path) {
+        Some(entry) => entry,
+        None => return true
+    };
+    match SupermarketGenerator::parse(&entry.generator) {
+        Ok(Some(supermarket)) => supermarket.version.is_none(),
+        Ok(None) if Path::new(&entry.generator).has_root() => {
+            !Path::new(&entry.generator).exists()
+        },
+        Ok(None) => sha_moved(&entry.sha, &upstream_sha(&entry.generator)),
+        Err(_) => true
+    }
+}
+
+fn sha_moved(cached: &Option<String>, upstream: &Option<String>) -> bool {
+    match (cached, upstream) {
+        (&Some(ref cached), &Some(ref upstream)) => cached != upstream,
+        _ => false
+    }
+}
+
+/// The generators in the cache
+pub fn entries(cache_path: &Path) -> DeliveryResult<Vec<PathBuf>> {
+    if !is_dir(cache_path) {
+        return Ok(vec![])
+    }
+    let mut entries = vec![];
+    for entry in try!(fs::read_dir(cache_path)) {
+        let path = try!(entry).path();
+        if is_dir(&path) {
+            entries.push(path);
+        }
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+/// Remove an entry and its source record from the cache
+pub fn remove(generator_path: &Path) -> DeliveryResult<()> {
+    try!(utils::remove_recursive(generator_path));
+    let source = source_file(generator_path);
+    if source.exists() {
+        try!(fs::remove_file(&source));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use tempdir::TempDir;
+    use super::*;
+
+    #[test]
+    fn test_source_file() {
+        assert_eq!(Path::new("/cache/gen.source.json"),
+                   source_file(Path::new("/cache/gen")).as_path());
+    }
+
+    #[test]
+    fn test_record_and_read() {
+        let tmp = TempDir::new("generator-cache").unwrap();
+        let generator_path = tmp.path().join("gen");
+        fs::create_dir(&generator_path).unwrap();
+        assert_eq!(None, read(&generator_path));
+        record(&generator_path, "/src/gen").unwrap();
+        assert_eq!(Some(CacheEntry { generator: "/src/gen".to_string(), sha: None }),
+                   read(&generator_path));
+        assert_eq!(vec![generator_path.clone()], entries(tmp.path()).unwrap());
+        remove(&generator_path).unwrap();
+        assert!(!source_file(&generator_path).exists());
+        assert!(entries(tmp.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_is_stale() {
+        let tmp = TempDir::new("generator-cache").unwrap();
+        let unknown = tmp.path().join("unknown");
+        fs::create_dir(&unknown).unwrap();
+        assert!(is_stale(&unknown));
+
+        let disk = tmp.path().join("disk");
+        record(&disk, &tmp.path().join("unknown").to_string_lossy()).unwrap();
+        assert!(!is_stale(&disk));
+        record(&disk, "/no/such/generator").unwrap();
+        assert!(is_stale(&disk));
+
+        let supermarket = tmp.path().join("supermarket");
+        record(&supermarket, "supermarket:gen@1.2.3").unwrap();
+        assert!(!is_stale(&supermarket));
+        record(&supermarket, "supermarket:gen").unwrap();
+        assert!(is_stale(&supermarket));
+    }
+
+    #[test]
+    fn test_sha_moved() {
+        let a = Some("a".to_string());
+        let b = Some("b".to_string());
+        assert!(sha_moved(&a, &b));
+        assert!(!sha_moved(&a, &a));
+        assert!(!sha_moved(&a, &None));
+        assert!(!sha_moved(&None, &b));
+    }
+}
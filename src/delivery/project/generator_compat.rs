@@ -0,0 +1,314 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Which ChefDK or Chef Workstation a build cookbook generator works with
+//!
+//! A generator can ship a `delivery_generator.toml` at its root declaring
+//! the versions of each flavour of the DK it supports:
+//!
+//! ```toml
+//! chefdk = ">= 2.0, < 4.0"
+//! workstation = "~> 0.4"
+//! ```
+//!
+//! Before running `chef generate`, `delivery init` compares the installed
+//! DK against the range for its flavour and refuses to generate a cookbook
+//! that would come out broken. A generator without the file, a flavour
+//! without a range, or a DK whose version can not be told are all let
+//! through.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+use regex::Regex;
+use toml;
+use utils;
+use utils::path_ext::is_file;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+
+pub const METADATA_FILE: &'static str = "delivery_generator.toml";
+
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Compatibility {
+    pub chefdk: Option<String>,
+    pub workstation: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flavor {
+    ChefDK,
+    Workstation,
+}
+
+impl fmt::Display for Flavor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Flavor::ChefDK => write!(f, "ChefDK"),
+            Flavor::Workstation => write!(f, "Chef Workstation")
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Installed {
+    pub flavor: Flavor,
+    pub version: Version,
+}
+
+/// A dotted version; missing trailing parts compare as zero
+#[derive(Debug, Clone)]
+pub struct Version(Vec<u64>);
+
+impl Version {
+    pub fn parse(version: &str) -> Option<Version> {
+        let parts: Result<Vec<u64>, _> = version.trim().split('.')
+            .map(|p| p.parse::<u64>())
+            .collect();
+        match parts {
+            Ok(ref p) if !p.is_empty() => Some(Version(p.clone())),
+            _ => None
+        }
+    }
+
+    fn part(&self, i: usize) -> u64 {
+        self.0.get(i).cloned().unwrap_or(0)
+    }
+
+    // The upper bound of `~> self`: bump the next to last part
+    fn pessimistic_bound(&self) -> Version {
+        if self.0.len() < 2 {
+            return Version(vec![self.part(0) + 1])
+        }
+        let mut bound = self.0[..self.0.len() - 1].to_vec();
+        let last = bound.len() - 1;
+        bound[last] += 1;
+        Version(bound)
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Version) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Version) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Version) -> Ordering {
+        let len = self.0.len().max(other.0.len());
+        (0..len).map(|i| self.part(i).cmp(&other.part(i)))
+            .find(|o| *o != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(|p| p.to_string()).collect();
+        write!(f, "{}", parts.join("."))
+    }
+}
+
+/// Whether `version` satisfies every comma separated constraint of
+/// `requirement`, e.g. `">= 2.0, < 4.0"` or `"~> 0.4"`
+pub fn satisfies(requirement: &str, version: &Version) -> DeliveryResult<bool> {
+    let re = Regex::new(r"^\s*(>=|<=|~>|>|<|=)?\s*([0-9][0-9.]*)\s*$").unwrap();
+    for constraint in requirement.split(',') {
+        let caps = match re.captures(constraint) {
+            Some(caps) => caps,
+            None => return Err(invalid_requirement(requirement))
+        };
+        let wanted = match Version::parse(caps.at(2).unwrap_or("")) {
+            Some(v) => v,
+            None => return Err(invalid_requirement(requirement))
+        };
+        let ok = match caps.at(1).unwrap_or("=") {
+            ">=" => *version >= wanted,
+            "<=" => *version <= wanted,
+            ">" => *version > wanted,
+            "<" => *version < wanted,
+            "~>" => *version >= wanted && *version < wanted.pessimistic_bound(),
+            _ => *version == wanted
+        };
+        if !ok {
+            return Ok(false)
+        }
+    }
+    Ok(true)
+}
+
+fn invalid_requirement(requirement: &str) -> DeliveryError {
+    DeliveryError::throw(Kind::ConfigValidation, Some(format!(
+        "The version requirement '{}' in the generator's {} can not be \
+         parsed; use constraints like '>= 2.0, < 4.0' or '~> 0.4'.",
+        requirement, METADATA_FILE
+    )))
+}
+
+impl Compatibility {
+    /// The compatibility the generator at `generator` declares, if any
+    pub fn load(generator: &Path) -> DeliveryResult<Option<Compatibility>> {
+        let path = generator.join(METADATA_FILE);
+        if !is_file(&path) {
+            return Ok(None)
+        }
+        let mut content = String::new();
+        let mut file = try!(File::open(&path));
+        try!(file.read_to_string(&mut content));
+        Ok(Some(try!(toml::from_str::<Compatibility>(&content))))
+    }
+
+    pub fn requirement(&self, flavor: Flavor) -> Option<&String> {
+        match flavor {
+            Flavor::ChefDK => self.chefdk.as_ref(),
+            Flavor::Workstation => self.workstation.as_ref()
+        }
+    }
+
+    /// Fails with `IncompatibleGenerator` when `installed` is outside
+    /// the range declared for its flavour
+    pub fn check(&self, installed: &Installed) -> DeliveryResult<()> {
+        let requirement = match self.requirement(installed.flavor) {
+            Some(r) => r,
+            None => return Ok(())
+        };
+        if try!(satisfies(requirement, &installed.version)) {
+            return Ok(())
+        }
+        Err(DeliveryError::throw(Kind::IncompatibleGenerator, Some(format!(
+            "The build cookbook generator needs {flavor} {req}, but {flavor} \
+             {ver} is installed.\nUpgrade {flavor}, or point --generator at a \
+             release of the generator that supports {flavor} {ver}.",
+            flavor = installed.flavor, req = requirement, ver = installed.version
+        ))))
+    }
+}
+
+/// Tell the flavour and version of the DK from the output of `chef --version`
+pub fn parse_chef_version(output: &str) -> Option<Installed> {
+    let checks = [
+        (r"(?i)Chef Workstation version:\s*([0-9][0-9.]*)", Flavor::Workstation),
+        (r"(?i)Chef Development Kit Version:\s*([0-9][0-9.]*)", Flavor::ChefDK),
+    ];
+    for &(pattern, flavor) in checks.iter() {
+        let re = Regex::new(pattern).unwrap();
+        if let Some(version) = re.captures(output)
+            .and_then(|caps| caps.at(1))
+            .and_then(Version::parse) {
+            return Some(Installed { flavor: flavor, version: version })
+        }
+    }
+    None
+}
+
+/// The installed DK, or `None` when `chef --version` does not say
+pub fn installed() -> Option<Installed> {
+    let output = match utils::make_command("chef").arg("--version").output() {
+        Ok(o) => o,
+        Err(_) => return None
+    };
+    if !output.status.success() {
+        return None
+    }
+    parse_chef_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Check the generator at `generator` against the installed DK
+pub fn check(generator: &Path) -> DeliveryResult<()> {
+    let compat = match try!(Compatibility::load(generator)) {
+        Some(c) => c,
+        None => return Ok(())
+    };
+    match installed() {
+        Some(installed) => compat.check(&installed),
+        None => {
+            debug!("Unable to detect the ChefDK or Chef Workstation version; \
+                    not checking {}", METADATA_FILE);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use errors::Kind;
+    use std::fs::File;
+    use tempdir::TempDir;
+
+    fn v(s: &str) -> Version {
+        Version::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_versions_compare_by_part() {
+        assert!(v("2.10") > v("2.9"));
+        assert_eq!(v("2.0"), v("2"));
+        assert!(Version::parse("2.x").is_none());
+    }
+
+    #[test]
+    fn test_requirements_are_anded() {
+        assert!(satisfies(">= 2.0, < 4.0", &v("3.12.10")).unwrap());
+        assert!(!satisfies(">= 2.0, < 4.0", &v("4.0.0")).unwrap());
+        assert!(satisfies("~> 0.4", &v("0.18.3")).unwrap());
+        assert!(!satisfies("~> 0.4", &v("1.0")).unwrap());
+        assert!(satisfies("~> 2.5.1", &v("2.5.9")).unwrap());
+        assert!(!satisfies("~> 2.5.1", &v("2.6.0")).unwrap());
+        assert!(satisfies("3.1", &v("3.1.0")).unwrap());
+        let e = satisfies("newer than 2", &v("3.0")).unwrap_err();
+        assert!(assert_enum!(e.kind, Kind::ConfigValidation));
+    }
+
+    #[test]
+    fn test_chef_version_tells_the_flavor() {
+        let dk = "Chef Development Kit Version: 3.8.14\nchef-client version: 14.10.9\n";
+        assert_eq!(parse_chef_version(dk),
+                   Some(Installed { flavor: Flavor::ChefDK, version: v("3.8.14") }));
+        let ws = "Chef Workstation version: 0.4.2\nchef-client version: 14.11.21\n";
+        assert_eq!(parse_chef_version(ws),
+                   Some(Installed { flavor: Flavor::Workstation, version: v("0.4.2") }));
+        assert_eq!(parse_chef_version("chef: command not found"), None);
+    }
+
+    #[test]
+    fn test_check_only_applies_the_installed_flavor() {
+        let tmp = TempDir::new("generator-compat").unwrap();
+        assert_eq!(Compatibility::load(tmp.path()).unwrap(), None);
+        File::create(tmp.path().join(METADATA_FILE)).unwrap()
+            .write_all(b"chefdk = \">= 2.0, < 4.0\"\n").unwrap();
+        let compat = Compatibility::load(tmp.path()).unwrap().unwrap();
+
+        let old_dk = Installed { flavor: Flavor::ChefDK, version: v("1.6.11") };
+        let e = compat.check(&old_dk).unwrap_err();
+        assert!(assert_enum!(e.kind, Kind::IncompatibleGenerator));
+        assert!(e.detail.unwrap().contains("ChefDK >= 2.0, < 4.0"));
+
+        let ws = Installed { flavor: Flavor::Workstation, version: v("0.1.0") };
+        assert!(compat.check(&ws).is_ok());
+    }
+}
@@ -15,19 +15,34 @@
 // limitations under the License.
 //
 
-use utils::{self, walk_tree_for_path, mkdir_recursive, cmd_success_or_err};
-use utils::path_ext::is_dir;
+use utils::{self, walk_tree_for_path, mkdir_recursive, cmd_success_or_err, path_to_string};
+use utils::path_ext::{is_dir, is_file};
+use utils::managed_region;
+use utils::say::{sayln, outln};
+use utils::preflight;
+use logs::output;
 use errors::{DeliveryError, Kind};
-use types::DeliveryResult;
+use types::{DeliveryResult, OrgName, ProjectName, PipelineName};
 use std::path::{Path, PathBuf};
-use http::APIClient;
-use git::{self, ReviewResult};
+use hyper;
+use http::{scp, APIClient};
+use http::tls::Tls;
+use git::{self, CommitIdentity, GitRemote, ReviewResult};
 use std::process::Command;
 use std::fs;
 use std::fs::File;
 use std::io::prelude::*;
 use config::Config;
 
+pub mod bulk;
+pub mod deliver_order;
+pub mod environment;
+pub mod generator_cache;
+pub mod generator_compat;
+pub mod pipeline_template;
+pub mod rollback;
+pub mod wizard;
+
 // README with a brief description of delivery and how to use it. This is added
 // to a new project by `delivery init` so we have something to submit as the
 // first change.
@@ -99,30 +114,54 @@ impl SourceCodeProvider {
         }
     }
 
-    // Verify if the SCP is configured on the Delivery Server
-    pub fn verify_server_config(&self, client: &APIClient) -> DeliveryResult<()> {
-        match self.kind {
+    // Verify if the SCP is configured on the Delivery Server, and return
+    // the API url the server uses for it
+    pub fn verify_server_config(&self, client: &APIClient) -> DeliveryResult<Option<String>> {
+        let scp_config = match self.kind {
             Type::Github => {
                 let scp_config = try!(client.get_github_server_config());
                 if scp_config.is_empty() {
                     return Err(DeliveryError{ kind: Kind::NoGithubSCPConfig, detail: None })
                 }
+                scp_config
             },
             Type::Bitbucket => {
                 let scp_config = try!(client.get_bitbucket_server_config());
                 if scp_config.is_empty() {
                     return Err(DeliveryError{ kind: Kind::NoBitbucketSCPConfig, detail: None })
                 }
+                scp_config
             }
+        };
+        Ok(scp_config.iter()
+           .filter_map(|c| c.get("root_api_url").and_then(|u| u.as_str()))
+           .next()
+           .map(String::from))
+    }
+
+    /// The git remote of the repository on the source code provider,
+    /// from the API url the server uses for it. Without that url only
+    /// the one of github.com is known.
+    pub fn remote_url(&self, root_api_url: Option<&str>) -> Option<String> {
+        match self.kind {
+            Type::Github => {
+                let api = root_api_url.unwrap_or(scp::GITHUB_API_URL);
+                let url = hyper::Url::parse(api).ok()?;
+                let host = url.host_str()?.trim_start_matches("api.");
+                Some(format!("git@{}:{}/{}.git", host, self.organization, self.repo_name))
+            },
+            Type::Bitbucket => root_api_url.map(|api| {
+                format!("{}/scm/{}/{}.git", api.trim_end_matches('/'),
+                        self.organization.to_lowercase(), self.repo_name)
+            })
         }
-        Ok(())
     }
 }
 
 // Create a Delivery Pipeline.
 // Returns true if created, returns false if already exists.
-pub fn create_delivery_pipeline(client: &APIClient, org: &str,
-                                proj: &str, pipe: &str) -> DeliveryResult<bool> {
+pub fn create_delivery_pipeline(client: &APIClient, org: &OrgName,
+                                proj: &ProjectName, pipe: &PipelineName) -> DeliveryResult<bool> {
     if client.pipeline_exists(org, proj, pipe) {
         return Ok(false)
     } else {
@@ -134,8 +173,8 @@ pub fn create_delivery_pipeline(client: &APIClient, org: &str,
 // Create a Delivery Project with Delivery as SCP (default).
 // If the project is created, return true.
 // If the project already exists, return false
-pub fn create_delivery_project(client: &APIClient, org: &str,
-                               proj: &str) -> DeliveryResult<bool> {
+pub fn create_delivery_project(client: &APIClient, org: &OrgName,
+                               proj: &ProjectName) -> DeliveryResult<bool> {
     if client.project_exists(org, proj) {
         return Ok(false)
     } else {
@@ -154,25 +193,232 @@ pub fn git_remote_up_to_date(config: &Config) -> DeliveryResult<bool> {
     Ok(remote == current)
 }
 
+/// What differs between the `current` delivery remote and the one the
+/// config `expected`, one `part: current -> expected` line per part
+pub fn git_remote_differences(expected: &str, current: &str) -> Vec<String> {
+    match (git::parse_delivery_remote(expected), git::parse_delivery_remote(current)) {
+        (Some(e), Some(c)) => {
+            let parts = [("user", &c.user, &e.user),
+                         ("server", &c.server, &e.server),
+                         ("enterprise", &c.enterprise, &e.enterprise),
+                         ("organization", &c.organization, &e.organization),
+                         ("project", &c.project, &e.project)];
+            parts.iter()
+                .filter(|&&(_, c, e)| c != e)
+                .map(|&(part, c, e)| format!("{}: {} -> {}", part, c, e))
+                .collect()
+        },
+        _ => vec![format!("url: {} -> {}", current, expected)]
+    }
+}
+
 
 // Push local content to the Delivery Server if no upstream commits.
 // Returns true if commits pushed, returns false if upstream commits found.
-pub fn push_project_content_to_delivery(pipeline: &str) -> DeliveryResult<bool> {
+//
+// Unless `force`, refuse to push a local branch that is behind or has
+// diverged from its copy on another remote: the pipeline would start
+// from a history the rest of the team does not have.
+pub fn push_project_content_to_delivery(pipeline: &str, force: bool) -> DeliveryResult<bool> {
     if try!(git::server_content(pipeline)) {
-        Ok(false)
-    } else {
-        try!(git::git_push(pipeline));
-        Ok(true)
+        return Ok(false)
     }
+    if !force {
+        let diverged = try!(diverged_remote_branches(pipeline, &project_path()?));
+        if !diverged.is_empty() {
+            let lines: Vec<String> = diverged.iter().map(|d| {
+                format!("  {}: {} commits behind, {} ahead", d.remote_ref, d.behind, d.ahead)
+            }).collect();
+            let msg = format!("The local {} branch is missing commits of other remotes:\n{}\n\
+                               Pull them into {} first, or run the command again with \
+                               --force-push to push the local branch as it is.",
+                              pipeline, lines.join("\n"), pipeline);
+            return Err(DeliveryError::throw(Kind::BranchDiverged, Some(msg)))
+        }
+    }
+    try!(git::git_push(pipeline));
+    Ok(true)
 }
 
-// Check to see if the origin remote is set up.
-pub fn missing_github_remote() -> DeliveryResult<bool> {
-    let git_remote_result = git::git_command(&["remote"], &try!(project_path()));
-    match git_remote_result {
-        Ok(git_result) => Ok(!git_result.stdout.contains("origin")),
-        Err(e) => return Err(e)
+/// What init does when the project on the server has a history that
+/// shares nothing with the local one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Takeover {
+    Refuse,
+    // Merge the history of the server into the local branch
+    Adopt,
+    // Force push the local branch over the history of the server
+    ReplaceHistory,
+}
+
+/// Whether `a` and `b` come from the same repository, i.e. share a root
+/// commit
+pub fn share_history(a: &str, b: &str, path: &Path) -> DeliveryResult<bool> {
+    let roots = try!(git::root_commits(a, path));
+    let other_roots = try!(git::root_commits(b, path));
+    Ok(roots.iter().any(|r| other_roots.contains(r)))
+}
+
+/// Make sure the history of `pipeline` on the server comes from the
+/// local repository before wiring the two together. A project of the
+/// same name with an unrelated history belongs to another repository;
+/// it is only taken over with an explicit `takeover`.
+pub fn check_server_history(pipeline: &str, takeover: Takeover, identity: &CommitIdentity,
+                            path: &Path) -> DeliveryResult<()> {
+    if !try!(git::server_content(pipeline)) {
+        return Ok(())
+    }
+    let server_ref = try!(git::fetch_pipeline(pipeline, path));
+    if try!(share_history(pipeline, &server_ref, path)) {
+        return Ok(())
+    }
+    match takeover {
+        Takeover::Refuse => {
+            let msg = format!("The {} pipeline on the server has a history unrelated to the \
+                               local {} branch, the project probably belongs to another \
+                               repository.\nRun the command again with --adopt to merge its \
+                               history into {}, or --replace-history to overwrite it with the \
+                               local one.", pipeline, pipeline, pipeline);
+            Err(DeliveryError::throw(Kind::UnrelatedHistory, Some(msg)))
+        },
+        Takeover::Adopt => {
+            sayln("yellow", &format!("  Merging the unrelated history of the {} pipeline \
+                                      into the local branch", pipeline));
+            adopt_history(pipeline, &server_ref, identity, path)
+        },
+        Takeover::ReplaceHistory => {
+            sayln("yellow", &format!("  Replacing the history of the {} pipeline with the \
+                                      local branch", pipeline));
+            try!(git::git_command(&["push", "--force", "delivery", pipeline], path));
+            Ok(())
+        }
+    }
+}
+
+// Merge `server_ref` into the `pipeline` branch, the one that gets pushed,
+// whatever is checked out now, and check the starting point out again
+fn adopt_history(pipeline: &str, server_ref: &str, identity: &CommitIdentity,
+                 path: &Path) -> DeliveryResult<()> {
+    let start = try!(git::git_command(&["symbolic-ref", "--quiet", "--short", "HEAD"], path)
+        .or_else(|_| git::git_command(&["rev-parse", "HEAD"], path)))
+        .stdout.trim().to_string();
+    if start != pipeline {
+        try!(git::git_command(&["checkout", "--quiet", pipeline], path));
+    }
+    let merged = git::git_merge_unrelated_in(server_ref, identity, path);
+    if start != pipeline {
+        try!(git::git_command(&["checkout", "--quiet", &start], path));
+    }
+    merged
+}
+
+/// A copy of a branch on another remote with commits the local branch has not
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub remote_ref: String,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+/// The copies of `branch` on the remotes other than delivery, as of their
+/// last fetch, that the local branch is behind or has diverged from
+pub fn diverged_remote_branches(branch: &str, path: &Path) -> DeliveryResult<Vec<Divergence>> {
+    let names: Vec<String> = try!(git::git_remotes(path)).into_iter()
+        .map(|r| r.name)
+        .filter(|n| n != "delivery")
+        .collect();
+    let mut diverged = Vec::new();
+    for name in names {
+        let remote_ref = format!("{}/{}", name, branch);
+        let full_ref = format!("refs/remotes/{}", remote_ref);
+        if git::git_command(&["rev-parse", "--verify", "--quiet", &full_ref], path).is_err() {
+            continue
+        }
+        let (ahead, behind) = try!(git::ahead_behind(branch, &full_ref, path));
+        if behind > 0 {
+            diverged.push(Divergence { remote_ref: remote_ref, ahead: ahead, behind: behind });
+        }
+    }
+    Ok(diverged)
+}
+
+// Find the git remote of the repository the project is linked to on the
+// source code provider.
+//
+// The remote named by `upstream_remote` in the config always wins. Then
+// we look for a remote whose URL points at the SCP organization and
+// repository, since forks and mirrors often call it `github` or
+// `upstream` rather than `origin`. Without an SCP we settle for `origin`.
+pub fn find_upstream_remote(remotes: &[GitRemote], configured: Option<&str>,
+                            scp: Option<&SourceCodeProvider>) -> Option<GitRemote> {
+    if let Some(name) = configured {
+        return remotes.iter().find(|r| r.name == name).cloned()
     }
+    if let Some(scp) = scp {
+        return remotes.iter().find(|r| {
+            match git::parse_remote_url(&r.url) {
+                Some(l) => l.owner.to_lowercase() == scp.organization.to_lowercase()
+                    && l.repo.to_lowercase() == scp.repo_name.to_lowercase(),
+                None => false
+            }
+        }).cloned()
+    }
+    remotes.iter().find(|r| r.name == "origin").cloned()
+}
+
+pub fn upstream_remote(config: &Config,
+                       scp: Option<&SourceCodeProvider>) -> DeliveryResult<Option<GitRemote>> {
+    let remotes = try!(git::git_remotes(&try!(project_path())));
+    let configured = config.upstream_remote().ok();
+    Ok(find_upstream_remote(&remotes, configured.as_ref().map(|c| c.as_str()), scp))
+}
+
+/// The source code provider a git remote URL points at
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteScp {
+    /// `github`, `bitbucket` or `gitlab`, the names `SourceCodeProvider::new`
+    /// takes; it does not take `gitlab` though.
+    pub provider: &'static str,
+    pub organization: String,
+    pub repo_name: String,
+}
+
+// Tell the source code provider from the ssh and https URLs GitHub,
+// Bitbucket and GitLab hand out. Hosts go by any name on premises, so
+// besides the host name Bitbucket Server is known by its `/scm/` https
+// path and its 7999 ssh port.
+pub fn scp_from_remote_url(url: &str) -> Option<RemoteScp> {
+    let location = match git::parse_remote_url(url) {
+        Some(l) => l,
+        None => return None
+    };
+    let host = location.host.to_lowercase();
+    let provider = if host.contains("github") {
+        "github"
+    } else if host.contains("bitbucket") || url.contains("/scm/") || url.contains(":7999/") {
+        "bitbucket"
+    } else if host.contains("gitlab") {
+        "gitlab"
+    } else {
+        return None
+    };
+    Some(RemoteScp {
+        provider: provider,
+        organization: location.owner,
+        repo_name: location.repo,
+    })
+}
+
+// The source code provider of the upstream remote, if it is on one
+pub fn detect_scp(config: &Config) -> DeliveryResult<Option<RemoteScp>> {
+    let remote = try!(upstream_remote(config, None));
+    Ok(remote.and_then(|r| scp_from_remote_url(&r.url)))
+}
+
+// Check to see if the upstream remote is set up.
+pub fn missing_github_remote(config: &Config,
+                             scp: Option<&SourceCodeProvider>) -> DeliveryResult<bool> {
+    upstream_remote(config, scp).map(|r| r.is_none())
 }
 
 /// Search for the project root directory
@@ -206,9 +452,11 @@ pub fn missing_github_remote() -> DeliveryResult<bool> {
 pub fn root_dir(dir: &Path) -> DeliveryResult<PathBuf> {
     match walk_tree_for_path(&PathBuf::from(&dir), ".git/config") {
         Some(p) => {
-           let git_d = p.parent().unwrap();
-           let root_d = git_d.parent().unwrap();
-           Ok(PathBuf::from(root_d))
+           match p.parent().and_then(|git_d| git_d.parent()) {
+               Some(root_d) => Ok(PathBuf::from(root_d)),
+               None => Err(DeliveryError{kind: Kind::NoGitConfig,
+                                         detail: None})
+           }
         },
         None => Err(DeliveryError{kind: Kind::NoGitConfig,
                                   detail: None})
@@ -222,7 +470,20 @@ pub fn project_path() -> DeliveryResult<PathBuf> {
 // Return the project name from the current path
 pub fn project_from_cwd() -> DeliveryResult<String> {
     let cwd = try!(self::root_dir(&utils::cwd()));
-    Ok(cwd.file_name().unwrap().to_str().unwrap().to_string())
+    match cwd.file_name() {
+        Some(name) => path_to_string(name),
+        None => Err(DeliveryError::throw(Kind::NoGitConfig,
+                                         Some(format!("{} has no project name",
+                                                      cwd.display()))))
+    }
+}
+
+// Return the project name from the path of a bare repository,
+// i.e. `/srv/git/frijol.git` is the project `frijol`
+pub fn project_from_bare_repo(path: &Path) -> Option<String> {
+    path.file_name().and_then(|n| n.to_str())
+        .map(|n| n.trim_right_matches(".git").to_string())
+        .and_then(|n| if n.is_empty() { None } else { Some(n) })
 }
 
 // Return the project name or try to extract it from the current path
@@ -234,6 +495,49 @@ pub fn project_or_from_cwd(proj: &str) -> DeliveryResult<String> {
     }
 }
 
+// A project given on the command line, possibly qualified with its
+// organization, `org/project`, or its enterprise too, `ent/org/project`,
+// along with the enterprise and organization it lives in once the
+// qualifiers and `--ent` and `--org` are combined.
+#[derive(Debug, PartialEq)]
+pub struct ProjectRef {
+    pub ent: String,
+    pub org: String,
+    pub name: String,
+}
+
+impl ProjectRef {
+    // Resolve `proj`, or the project of the current directory, against
+    // the `ent` and `org` options. Empty options take the qualifiers of
+    // `proj`; an option differing from its qualifier is an error.
+    pub fn resolve(proj: &str, ent: &str, org: &str) -> DeliveryResult<ProjectRef> {
+        let proj = try!(project_or_from_cwd(proj));
+        let parts: Vec<&str> = proj.split('/').collect();
+        if parts.len() > 3 || parts.iter().any(|p| p.is_empty()) {
+            let msg = format!("Invalid project '{}', use PROJECT, ORG/PROJECT or \
+                               ENT/ORG/PROJECT", proj);
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        let mut parts = parts.into_iter().rev();
+        let name = parts.next().unwrap_or_default();
+        let org = try!(qualifier("organization", parts.next(), org));
+        let ent = try!(qualifier("enterprise", parts.next(), ent));
+        Ok(ProjectRef { ent: ent, org: org, name: name.to_string() })
+    }
+}
+
+fn qualifier(what: &str, qualified: Option<&str>, option: &str) -> DeliveryResult<String> {
+    match qualified {
+        Some(q) if !option.is_empty() && q != option => {
+            let msg = format!("The project is qualified with the {} {} but {} was given",
+                              what, q, option);
+            Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        },
+        Some(q) => Ok(q.to_string()),
+        None => Ok(option.to_string())
+    }
+}
+
 // Create the feature branch `add-delivery-config`
 //
 // This branch is created to start modifying the project repository
@@ -264,7 +568,8 @@ pub fn create_feature_branch_if_missing(project_path: &PathBuf, branch_name: &st
 }
 
 // Add and commit the generated build_cookbook
-pub fn add_commit_build_cookbook(custom_config_passed: &bool) -> DeliveryResult<bool> {
+pub fn add_commit_build_cookbook(custom_config_passed: &bool,
+                                 identity: &CommitIdentity) -> DeliveryResult<bool> {
     // .delivery is probably not yet under version control, so we have to add
     // the whole folder instead of .delivery/build_cookbook.
     try!(git::git_command(&["add", ".delivery"], &try!(project_path())));
@@ -276,7 +581,7 @@ pub fn add_commit_build_cookbook(custom_config_passed: &bool) -> DeliveryResult<
 
     // Commit the changes made in .delivery but detect if nothing has changed,
     // if that is the case, we are Ok() to continue
-    match git::git_commit(&commit_msg) {
+    match git::git_commit(&commit_msg, identity) {
       Ok(_) => Ok(true),
       Err(DeliveryError{ kind: Kind::EmptyGitCommit, .. }) => Ok(false),
       Err(e) => Err(e)
@@ -296,18 +601,81 @@ pub fn create_delivery_readme() -> DeliveryResult<bool> {
     }
 }
 
-pub fn commit_delivery_readme() -> DeliveryResult<()> {
+pub fn commit_delivery_readme(identity: &CommitIdentity) -> DeliveryResult<()> {
     try!(git::git_command(&["add", "DELIVERY.md"], &try!(project_path())));
     let commit_msg = "New pipeline verification commit".to_string();
-    try!(git::git_command(&["commit", "-m", &commit_msg], &try!(project_path())));
+    try!(git::git_commit(&commit_msg, identity));
     Ok(())
 }
 
-pub fn create_dot_delivery() -> &'static Path {
+// The managed region of the README holding the pipeline status badge
+const README_BADGE_REGION: &'static str = "badge";
+
+// Find the README of the project, if it has one
+pub fn readme_path(project_path: &Path) -> Option<PathBuf> {
+    ["README.md", "README.markdown", "README"].iter()
+        .map(|f| project_path.join(f))
+        .find(|p| is_file(p))
+}
+
+// The markdown of the pipeline status badge, linking to the project
+pub fn readme_badge(config: &Config) -> DeliveryResult<String> {
+    let pipe = try!(config.pipeline());
+    Ok(format!("[![Delivery pipeline {}]({})]({})", pipe,
+               try!(config.pipeline_badge_url()), try!(config.project_url())))
+}
+
+// Add or refresh the pipeline status badge in the README of the project.
+//
+// Returns the README path if it was modified, `None` if it was already
+// up-to-date or if the project has no README.
+pub fn inject_readme_badge(config: &Config,
+                           project_path: &Path) -> DeliveryResult<Option<PathBuf>> {
+    match try!(readme_with_badge(config, project_path)) {
+        Some((readme, updated)) => {
+            let mut f = try!(File::create(&readme));
+            try!(f.write_all(updated.as_bytes()));
+            Ok(Some(readme))
+        },
+        None => Ok(None)
+    }
+}
+
+// The README that is missing the badge, if any
+pub fn readme_missing_badge(config: &Config,
+                            project_path: &Path) -> DeliveryResult<Option<PathBuf>> {
+    Ok(try!(readme_with_badge(config, project_path)).map(|(readme, _)| readme))
+}
+
+fn readme_with_badge(config: &Config,
+                     project_path: &Path) -> DeliveryResult<Option<(PathBuf, String)>> {
+    let readme = match readme_path(project_path) {
+        Some(r) => r,
+        None => return Ok(None)
+    };
+    let badge = try!(readme_badge(config));
+    let content = try!(utils::read_file(&readme));
+    let updated = managed_region::upsert(&content, README_BADGE_REGION, &badge);
+    if updated == content {
+        return Ok(None)
+    }
+    Ok(Some((readme, updated)))
+}
+
+pub fn commit_readme_badge(readme: &Path, identity: &CommitIdentity) -> DeliveryResult<bool> {
+    try!(git::git_command(&["add", &readme.to_string_lossy()], &try!(project_path())));
+    match git::git_commit("Add Delivery pipeline status badge to README", identity) {
+      Ok(_) => Ok(true),
+      Err(DeliveryError{ kind: Kind::EmptyGitCommit, .. }) => Ok(false),
+      Err(e) => Err(e)
+    }
+}
+
+pub fn create_dot_delivery() -> DeliveryResult<&'static Path> {
     // TODO: should we be doing some relative pathing here?
     let dot_delivery = Path::new(".delivery");
-    fs::create_dir_all(dot_delivery).unwrap();
-    dot_delivery
+    try!(fs::create_dir_all(dot_delivery));
+    Ok(dot_delivery)
 }
 
 pub fn create_build_cookbook<P>(pipeline: &str, path: P) -> DeliveryResult<Command>
@@ -319,8 +687,7 @@ pub fn create_build_cookbook<P>(pipeline: &str, path: P) -> DeliveryResult<Comma
         .arg("--pipeline")
         .arg(pipeline)
         .current_dir(&try!(project_path()));
-    let output = command.output()?;
-    cmd_success_or_err(&output, Kind::ChefdkGenerateFailed)?;
+    try!(output::run(&mut command, "chef-generate", Kind::ChefdkGenerateFailed));
     Ok(command)
 }
 
@@ -328,39 +695,151 @@ pub fn create_build_cookbook<P>(pipeline: &str, path: P) -> DeliveryResult<Comma
 pub enum CustomCookbookSource {
     Cached,
     Disk,
-    Git
+    Git,
+    Supermarket
+}
+
+pub const DEFAULT_SUPERMARKET: &'static str = "https://supermarket.chef.io";
+
+const SUPERMARKET_PREFIX: &'static str = "supermarket:";
+
+/// A build cookbook generator published on a Supermarket, given as
+/// `supermarket:<name>` or `supermarket:<name>@<version>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SupermarketGenerator {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl SupermarketGenerator {
+    /// `None` when the generator does not come from a Supermarket
+    pub fn parse(generator: &str) -> DeliveryResult<Option<SupermarketGenerator>> {
+        if !generator.starts_with(SUPERMARKET_PREFIX) {
+            return Ok(None)
+        }
+        let mut parts = generator[SUPERMARKET_PREFIX.len()..].splitn(2, '@');
+        let name = parts.next().unwrap_or("");
+        let version = parts.next();
+        if name.is_empty() || version == Some("") {
+            let msg = format!("Invalid generator '{}', use supermarket:NAME or \
+                               supermarket:NAME@VERSION", generator);
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        Ok(Some(SupermarketGenerator {
+            name: name.to_string(),
+            version: version.map(String::from),
+        }))
+    }
+
+    /// The directory of the generator in the cache; every version gets
+    /// its own so a pinned version never changes under our feet.
+    pub fn cache_dir_name(&self) -> String {
+        match self.version {
+            Some(ref version) => format!("supermarket-{}-{}", self.name, version),
+            None => format!("supermarket-{}", self.name)
+        }
+    }
+}
+
+/// Where a custom generator is kept in the generator cache
+pub fn generator_cache_dir(generator: &str, cache_path: &Path) -> DeliveryResult<PathBuf> {
+    match try!(SupermarketGenerator::parse(generator)) {
+        Some(supermarket) => Ok(cache_path.join(supermarket.cache_dir_name())),
+        None => Ok(cache_path.join(Path::new(generator).file_stem().unwrap_or_default()))
+    }
 }
 
 // Custom build_cookbook generation
 //
 // This method handles a custom generator which could be:
 // 1) A local path
-// 2) Or a git repo URL
-// TODO) From Supermarket
+// 2) A git repo URL
+// 3) Or a cookbook on a Supermarket, `supermarket:NAME[@VERSION]`
+//
+// With `refresh`, a cached generator is fetched again when it is stale.
+// `offline`, only the cache and local paths are used. Git repos are
+// cloned with the CA bundle and client certificate of `tls`.
 pub fn download_or_mv_custom_build_cookbook_generator(
         generator: &Path,
-        cache_path: &Path) -> DeliveryResult<CustomCookbookSource> {
+        cache_path: &Path,
+        supermarket_site: &str,
+        refresh: bool,
+        offline: bool,
+        tls: &Tls) -> DeliveryResult<CustomCookbookSource> {
+    try!(preflight::check(cache_path, preflight::GENERATOR_SPACE,
+                          "the build cookbook generator"));
     try!(mkdir_recursive(cache_path));
-    if generator.has_root() {
+    let generator_str = generator.to_string_lossy();
+    let cache_generator_path = try!(generator_cache_dir(&generator_str, cache_path));
+    if refresh && !offline && is_dir(&cache_generator_path)
+        && generator_cache::is_stale(&cache_generator_path) {
+        try!(generator_cache::remove(&cache_generator_path));
+    }
+    let source = if let Some(supermarket) = try!(SupermarketGenerator::parse(&generator_str)) {
+        if is_dir(&cache_generator_path) {
+            return Ok(CustomCookbookSource::Cached)
+        }
+        try!(fetchable_offline(&generator_str, offline));
+        try!(download_supermarket_generator(&supermarket, supermarket_site, cache_path));
+        CustomCookbookSource::Supermarket
+    } else if generator.has_root() {
         try!(utils::copy_recursive(&generator, &cache_path));
-        return Ok(CustomCookbookSource::Disk)
+        CustomCookbookSource::Disk
     } else {
-        let mut cache_generator_path: PathBuf = cache_path.to_path_buf();
-        cache_generator_path.push(generator.file_name().unwrap());
         if is_dir(&cache_generator_path) {
             return Ok(CustomCookbookSource::Cached)
-        } else {
-            let cache_path_str = &cache_generator_path.to_string_lossy();
-            let generator_str  = &generator.to_string_lossy();
-            try!(git::clone(&cache_path_str, &generator_str));
-            return Ok(CustomCookbookSource::Git)
         }
+        try!(fetchable_offline(&generator_str, offline));
+        try!(git::clone_with_config(&cache_generator_path.to_string_lossy(), &generator_str,
+                                    &tls.git_config()));
+        CustomCookbookSource::Git
+    };
+    try!(generator_cache::record(&cache_generator_path, &generator_str));
+    Ok(source)
+}
+
+fn fetchable_offline(generator: &str, offline: bool) -> DeliveryResult<()> {
+    if offline {
+        let msg = format!("Not fetching {} in offline mode, and it is not cached", generator);
+        return Err(DeliveryError::throw(Kind::OfflineMode, Some(msg)))
     }
+    Ok(())
+}
+
+// Download the generator tarball with knife, the same way the build
+// cookbooks of jobs are, and unpack it into the cache.
+fn download_supermarket_generator(generator: &SupermarketGenerator, site: &str,
+                                  cache_path: &Path) -> DeliveryResult<()> {
+    let dir_name = generator.cache_dir_name();
+    let tarball = cache_path.join(format!("{}.tgz", dir_name));
+    let unpack_path = cache_path.join(format!("{}.tmp", dir_name));
+    try!(mkdir_recursive(&unpack_path));
+
+    let mut knife = utils::make_command("knife");
+    knife.arg("supermarket").arg("download").arg(&generator.name);
+    if let Some(ref version) = generator.version {
+        knife.arg(version);
+    }
+    knife.arg("-m").arg(site)
+        .arg("-f").arg(&try!(path_to_string(&tarball)))
+        .current_dir(cache_path);
+    try!(output::run(&mut knife, "knife-supermarket-download", Kind::SupermarketFailed));
+    let tar_result = try!(utils::make_command("tar")
+                          .arg("zxf")
+                          .arg(&try!(path_to_string(&tarball)))
+                          .current_dir(&unpack_path)
+                          .output());
+    try!(cmd_success_or_err(&tar_result, Kind::TarFailed));
+    try!(fs::rename(unpack_path.join(&generator.name), cache_path.join(&dir_name)));
+    try!(utils::remove_recursive(&unpack_path));
+    try!(fs::remove_file(&tarball));
+    Ok(())
 }
 
 // Generate the build_cookbook using ChefDK generate
 pub fn chef_generate_build_cookbook_from_generator(
       generator: &Path, project_path: &Path) -> DeliveryResult<Command> {
+    try!(generator_compat::check(generator));
     let mut command = utils::make_command("chef");
     command.arg("generate")
         .arg("build-cookbook")
@@ -369,8 +848,7 @@ pub fn chef_generate_build_cookbook_from_generator(
         .arg(generator)
         .current_dir(&project_path);
 
-    let output = command.output()?;
-    cmd_success_or_err(&output, Kind::ChefdkGenerateFailed)?;
+    try!(output::run(&mut command, "chef-generate", Kind::ChefdkGenerateFailed));
     Ok(command)
 }
 
@@ -380,19 +858,40 @@ pub fn generator_cache_path() -> DeliveryResult<PathBuf> {
 }
 
 pub fn review(target: &str, head: &str) -> DeliveryResult<ReviewResult> {
+    review_as(target, head, head)
+}
+
+/// Submit `head` for review as the feature branch `topic`
+pub fn review_as(target: &str, head: &str, topic: &str) -> DeliveryResult<ReviewResult> {
     if target == head {
         Err(DeliveryError{ kind: Kind::CannotReviewSameBranch, detail: None })
     } else {
-        Ok(try!(git::git_push_review(head, target)))
+        Ok(try!(git::git_push_review_to_topic(head, target, topic)))
     }
 }
 
-pub fn handle_review_result(review: &ReviewResult,
-                            no_open: &bool) -> DeliveryResult<Option<String>> {
+/// Show the url of a submitted review and, unless `no_open` or the
+/// environment says nobody would see it, open it in the browser of the
+/// `config`. Not being able to open it is not an error, the url is there
+/// to copy.
+pub fn handle_review_result(review: &ReviewResult, no_open: &bool,
+                            config: &Config) -> DeliveryResult<Option<String>> {
     match review.url {
         Some(ref url) => {
-            if !no_open {
-                try!(utils::open::item(&url));
+            outln("magenta", url);
+            let skip = if *no_open { None } else { utils::open::skip_reason(config.no_open) };
+            if let Some(ref why) = skip {
+                sayln("white", &format!("Not opening the review in a browser: {}.", why));
+            }
+            if !no_open && skip.is_none() {
+                let browser = config.browser.as_ref().map(|b| b.as_ref());
+                if let Err(e) = utils::open::browse(&url, browser) {
+                    debug!("Unable to open {}: {:?}", url, e);
+                    sayln("yellow", "We could not open the review in the browser for you.");
+                    sayln("yellow", "Set `browser` in your cli.toml to the program to open it \
+                                     with, or pass --no-open to bypass attempting to open \
+                                     this review in a browser.");
+                }
             }
             Ok(Some(url.clone()))
         },
@@ -403,7 +902,37 @@ pub fn handle_review_result(review: &ReviewResult,
 #[cfg(test)]
 mod tests {
     use std::path::Path;
-    use super::root_dir;
+    use std::fs::File;
+    use std::io::prelude::*;
+    use tempdir::TempDir;
+    use config::Config;
+    use utils::read_file;
+    use super::{root_dir, inject_readme_badge, find_upstream_remote, SourceCodeProvider,
+                project_from_bare_repo, SupermarketGenerator, generator_cache_dir,
+                git_remote_differences, diverged_remote_branches, Divergence, share_history,
+                ProjectRef, RemoteScp, scp_from_remote_url, adopt_history};
+    use git;
+    use git::{CommitIdentity, GitRemote};
+
+    #[test]
+    fn resolve_qualified_project_refs() {
+        let project_ref = |ent: &str, org: &str, name: &str| ProjectRef {
+            ent: ent.to_string(), org: org.to_string(), name: name.to_string()
+        };
+        assert_eq!(project_ref("", "", "frijol"), ProjectRef::resolve("frijol", "", "").unwrap());
+        assert_eq!(project_ref("ent", "org", "frijol"),
+                   ProjectRef::resolve("frijol", "ent", "org").unwrap());
+        assert_eq!(project_ref("ent", "beans", "frijol"),
+                   ProjectRef::resolve("beans/frijol", "ent", "").unwrap());
+        assert_eq!(project_ref("other", "beans", "frijol"),
+                   ProjectRef::resolve("other/beans/frijol", "", "").unwrap());
+        assert_eq!(project_ref("other", "beans", "frijol"),
+                   ProjectRef::resolve("other/beans/frijol", "other", "beans").unwrap());
+        assert!(ProjectRef::resolve("other/beans/frijol", "ent", "").is_err());
+        assert!(ProjectRef::resolve("beans/frijol", "", "org").is_err());
+        assert!(ProjectRef::resolve("a/b/c/d", "", "").is_err());
+        assert!(ProjectRef::resolve("beans//frijol", "", "").is_err());
+    }
 
     #[test]
     fn detect_error_if_root_project_is_not_a_git_repo() {
@@ -415,4 +944,215 @@ mod tests {
             Err(_) => assert!(true)
         }
     }
+
+    #[test]
+    fn inject_readme_badge_only_once() {
+        let tmp = TempDir::new("readme-badge").unwrap();
+        let mut config = Config::default();
+        config.server = Some("server".to_string());
+        config.enterprise = Some("ent".to_string());
+        config.organization = Some("org".to_string());
+        config.project = Some("proj".to_string());
+        assert_eq!(None, inject_readme_badge(&config, tmp.path()).unwrap());
+
+        let readme = tmp.path().join("README.md");
+        File::create(&readme).unwrap().write_all(b"# proj\n\nHello\n").unwrap();
+        assert_eq!(Some(readme.clone()), inject_readme_badge(&config, tmp.path()).unwrap());
+        let content = read_file(&readme).unwrap();
+        assert!(content.contains("[![Delivery pipeline master](https://server/api/v0/e/ent/\
+                                  orgs/org/projects/proj/pipelines/master/badge)]"));
+        assert!(content.ends_with("Hello\n"));
+        assert_eq!(None, inject_readme_badge(&config, tmp.path()).unwrap());
+    }
+
+    // Also used by the tests of the submodules
+    pub fn remote(name: &str, url: &str) -> GitRemote {
+        GitRemote { name: name.to_string(), url: url.to_string() }
+    }
+
+    #[test]
+    fn find_upstream_remote_by_scp_url() {
+        let remotes = vec![remote("origin", "git@github.com:me/fork.git"),
+                           remote("github", "https://github.com/Chef/delivery-cli.git")];
+        let scp = SourceCodeProvider::new("github", "delivery-cli", "chef",
+                                          "master", false).unwrap();
+        assert_eq!(Some(remotes[1].clone()), find_upstream_remote(&remotes, None, Some(&scp)));
+        assert_eq!(Some(remotes[0].clone()), find_upstream_remote(&remotes, None, None));
+        assert_eq!(Some(remotes[0].clone()),
+                   find_upstream_remote(&remotes, Some("origin"), Some(&scp)));
+        assert_eq!(None, find_upstream_remote(&remotes, Some("upstream"), None));
+    }
+
+    #[test]
+    fn test_scp_remote_url() {
+        let github = SourceCodeProvider::new("github", "cli", "chef", "master", false).unwrap();
+        assert_eq!(Some("git@github.com:chef/cli.git".to_string()), github.remote_url(None));
+        assert_eq!(Some("git@ghe.example.com:chef/cli.git".to_string()),
+                   github.remote_url(Some("https://ghe.example.com/api/v3")));
+        let bitbucket = SourceCodeProvider::new("bitbucket", "cli", "PROJ", "master",
+                                                true).unwrap();
+        assert_eq!(None, bitbucket.remote_url(None));
+        assert_eq!(Some("https://git.example.com/scm/proj/cli.git".to_string()),
+                   bitbucket.remote_url(Some("https://git.example.com/")));
+    }
+
+    #[test]
+    fn test_scp_from_remote_url() {
+        let scp = |provider: &'static str, org: &str, repo: &str| Some(RemoteScp {
+            provider: provider, organization: org.to_string(), repo_name: repo.to_string()
+        });
+        assert_eq!(scp("github", "chef", "delivery-cli"),
+                   scp_from_remote_url("git@github.com:chef/delivery-cli.git"));
+        assert_eq!(scp("github", "chef", "delivery-cli"),
+                   scp_from_remote_url("https://github.example.com/chef/delivery-cli"));
+        assert_eq!(scp("bitbucket", "PROJ", "repo"),
+                   scp_from_remote_url("https://git.example.com/scm/PROJ/repo.git"));
+        assert_eq!(scp("bitbucket", "PROJ", "repo"),
+                   scp_from_remote_url("ssh://git@git.example.com:7999/PROJ/repo.git"));
+        assert_eq!(scp("bitbucket", "team", "repo"),
+                   scp_from_remote_url("git@bitbucket.org:team/repo.git"));
+        assert_eq!(scp("gitlab", "group", "repo"),
+                   scp_from_remote_url("https://gitlab.com/group/repo.git"));
+        assert_eq!(None, scp_from_remote_url("ssh://git@git.example.com/team/repo.git"));
+        assert_eq!(None, scp_from_remote_url("/some/local/path"));
+    }
+
+    #[test]
+    fn test_project_from_bare_repo() {
+        assert_eq!(Some("frijol".to_string()),
+                   project_from_bare_repo(Path::new("/srv/git/frijol.git")));
+        assert_eq!(Some("frijol".to_string()), project_from_bare_repo(Path::new("/srv/frijol")));
+        assert_eq!(None, project_from_bare_repo(Path::new("/srv/.git")));
+    }
+
+    #[test]
+    fn test_supermarket_generator_parse() {
+        assert_eq!(None, SupermarketGenerator::parse("https://github.com/chef/gen.git").unwrap());
+        assert_eq!(Some(SupermarketGenerator { name: "my-generator".to_string(), version: None }),
+                   SupermarketGenerator::parse("supermarket:my-generator").unwrap());
+        assert_eq!(Some(SupermarketGenerator {
+                       name: "my-generator".to_string(),
+                       version: Some("1.2.3".to_string())
+                   }),
+                   SupermarketGenerator::parse("supermarket:my-generator@1.2.3").unwrap());
+        assert!(SupermarketGenerator::parse("supermarket:").is_err());
+        assert!(SupermarketGenerator::parse("supermarket:my-generator@").is_err());
+    }
+
+    #[test]
+    fn test_diverged_remote_branches() {
+        let tmp = TempDir::new("diverged").unwrap();
+        let path = tmp.path();
+        let commit = |message: &str| {
+            git::git_command(&["-c", "user.name=test", "-c", "user.email=test@example.com",
+                               "commit", "--allow-empty", "-m", message], path).unwrap();
+        };
+        git::git_command(&["init"], path).unwrap();
+        git::git_command(&["checkout", "-b", "master"], path).unwrap();
+        commit("one");
+        git::git_command(&["remote", "add", "origin", "https://example.com/repo.git"], path)
+            .unwrap();
+        git::git_command(&["remote", "add", "delivery", "ssh://a@b@c/b/d/e"], path).unwrap();
+        git::git_command(&["update-ref", "refs/remotes/origin/master", "HEAD"], path).unwrap();
+        // Ahead of origin is fine
+        commit("two");
+        assert!(diverged_remote_branches("master", path).unwrap().is_empty());
+
+        // Diverged from origin is not
+        git::git_command(&["checkout", "-b", "other", "master~1"], path).unwrap();
+        commit("three");
+        git::git_command(&["update-ref", "refs/remotes/origin/master", "HEAD"], path).unwrap();
+        git::git_command(&["update-ref", "refs/remotes/delivery/master", "HEAD"], path).unwrap();
+        assert_eq!(vec![Divergence { remote_ref: "origin/master".to_string(),
+                                     ahead: 1, behind: 1 }],
+                   diverged_remote_branches("master", path).unwrap());
+    }
+
+    #[test]
+    fn test_share_history() {
+        let tmp = TempDir::new("share-history").unwrap();
+        let path = tmp.path();
+        let commit = |message: &str| {
+            git::git_command(&["-c", "user.name=test", "-c", "user.email=test@example.com",
+                               "commit", "--allow-empty", "-m", message], path).unwrap();
+        };
+        git::git_command(&["init"], path).unwrap();
+        git::git_command(&["checkout", "-b", "master"], path).unwrap();
+        commit("one");
+        git::git_command(&["checkout", "-b", "related"], path).unwrap();
+        commit("two");
+        git::git_command(&["checkout", "--orphan", "unrelated"], path).unwrap();
+        commit("elsewhere");
+        assert!(share_history("master", "related", path).unwrap());
+        assert!(!share_history("master", "unrelated", path).unwrap());
+
+        // Adopting the unrelated history makes it part of the branch
+        git::git_command(&["checkout", "master"], path).unwrap();
+        let identity = CommitIdentity { name: Some("test".to_string()),
+                                        email: Some("test@example.com".to_string()) };
+        git::git_merge_unrelated_in("unrelated", &identity, path).unwrap();
+        assert!(share_history("master", "unrelated", path).unwrap());
+    }
+
+    #[test]
+    fn test_adopt_history() {
+        let tmp = TempDir::new("adopt-history").unwrap();
+        let path = tmp.path();
+        let git = |args: &[&str]| git::git_command(args, path).unwrap().stdout;
+        let commit = |file: &str, content: &str| {
+            let mut f = File::create(path.join(file)).unwrap();
+            f.write_all(content.as_bytes()).unwrap();
+            git(&["add", file]);
+            git(&["-c", "user.name=test", "-c", "user.email=test@example.com",
+                  "commit", "-m", content]);
+        };
+        let identity = CommitIdentity { name: Some("test".to_string()),
+                                        email: Some("test@example.com".to_string()) };
+        git(&["init"]);
+        git(&["checkout", "-b", "master"]);
+        commit("README", "local");
+        git(&["branch", "feature"]);
+        git(&["checkout", "--orphan", "server"]);
+        commit("README", "server");
+
+        // The history goes into the pipeline branch, not the one checked out
+        git(&["checkout", "--orphan", "clean-server"]);
+        git(&["rm", "--quiet", "-rf", "."]);
+        commit("SERVER", "clean server");
+        git(&["checkout", "feature"]);
+        adopt_history("master", "clean-server", &identity, path).unwrap();
+        assert!(share_history("master", "clean-server", path).unwrap());
+        assert!(!share_history("feature", "clean-server", path).unwrap());
+        assert_eq!("feature", git(&["symbolic-ref", "--short", "HEAD"]).trim());
+
+        // A conflicting merge is aborted
+        let before = git(&["rev-parse", "master"]);
+        assert!(adopt_history("master", "server", &identity, path).is_err());
+        assert_eq!(before, git(&["rev-parse", "master"]));
+        assert_eq!("feature", git(&["symbolic-ref", "--short", "HEAD"]).trim());
+        assert!(!path.join(".git/MERGE_HEAD").exists());
+    }
+
+    #[test]
+    fn test_git_remote_differences() {
+        let current = "ssh://link@hyrule@castle.com:8989/hyrule/heroes/sword";
+        assert!(git_remote_differences(current, current).is_empty());
+        assert_eq!(vec!["server: castle.com:8989 -> localhost:36534".to_string(),
+                        "project: sword -> master-sword".to_string()],
+                   git_remote_differences(
+                       "ssh://link@hyrule@localhost:36534/hyrule/heroes/master-sword", current));
+        assert_eq!(vec!["url: git@github.com:zelda/sword.git -> ssh://a@b@c/b/d/e".to_string()],
+                   git_remote_differences("ssh://a@b@c/b/d/e", "git@github.com:zelda/sword.git"));
+    }
+
+    #[test]
+    fn test_generator_cache_dir() {
+        let cache = Path::new("/cache");
+        assert_eq!(cache.join("gen"),
+                   generator_cache_dir("https://github.com/chef/gen.git", cache).unwrap());
+        assert_eq!(cache.join("supermarket-gen"),
+                   generator_cache_dir("supermarket:gen", cache).unwrap());
+        assert_eq!(cache.join("supermarket-gen-1.2.3"),
+                   generator_cache_dir("supermarket:gen@1.2.3", cache).unwrap());
+    }
 }
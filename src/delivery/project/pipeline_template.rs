@@ -0,0 +1,156 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+//! Pipeline templates
+//!
+//! `delivery init --pipeline-template` sets a project up from a bundle
+//! a team shares across its projects: a local directory or a git
+//! repository laid out like `.delivery/` itself, i.e. a `config.json`,
+//! a `build_cookbook/` skeleton and a `project.toml` with the phase
+//! settings. Bundles are fetched into their own cache the same way
+//! custom generators are, then every text file is rendered with the
+//! variables of the project before being synced into `.delivery/`.
+//!
+//! The variables are `{{.enterprise}}`, `{{.organization}}`,
+//! `{{.project}}`, `{{.pipeline}}` and `{{.server}}`. Anything else in
+//! braces is left alone, so ERB or mustache templates of the build
+//! cookbook go through untouched.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use regex::{Captures, Regex};
+use config::Config;
+use delivery_config::sync;
+use types::DeliveryResult;
+use utils::{self, mkdir_recursive};
+
+pub type Variables = BTreeMap<String, String>;
+
+/// The variables of the project a template is rendered with; the ones
+/// that are not configured are not rendered.
+pub fn variables(config: &Config) -> Variables {
+    let mut vars = Variables::new();
+    let values = vec![("enterprise", config.enterprise()),
+                      ("organization", config.organization()),
+                      ("project", config.project()),
+                      ("pipeline", config.pipeline()),
+                      ("server", config.server_host())];
+    for (name, value) in values {
+        if let Ok(value) = value {
+            vars.insert(name.to_string(), value);
+        }
+    }
+    vars
+}
+
+pub fn render(text: &str, vars: &Variables) -> String {
+    let re = Regex::new(r"\{\{\s*\.(\w+)\s*\}\}").unwrap();
+    re.replace_all(text, |caps: &Captures| {
+        match vars.get(caps.at(1).unwrap_or("")) {
+            Some(value) => value.clone(),
+            None => caps.at(0).unwrap_or("").to_string()
+        }
+    })
+}
+
+/// Render every file of the template at `template_dir` into `dest_dir`
+/// and return them, relative to it. Files that are not UTF-8 are copied
+/// as they are, and the `.git` of a cloned template is left out.
+pub fn render_dir(template_dir: &Path, dest_dir: &Path,
+                  vars: &Variables) -> DeliveryResult<Vec<PathBuf>> {
+    let mut rendered = Vec::new();
+    for file in try!(sync::files_under(template_dir)) {
+        if file.starts_with(".git") {
+            continue
+        }
+        let mut content = Vec::new();
+        try!(try!(File::open(template_dir.join(&file))).read_to_end(&mut content));
+        let content = match String::from_utf8(content) {
+            Ok(text) => render(&text, vars).into_bytes(),
+            Err(e) => e.into_bytes()
+        };
+        let dest = dest_dir.join(&file);
+        if let Some(parent) = dest.parent() {
+            try!(mkdir_recursive(parent));
+        }
+        try!(try!(File::create(&dest)).write_all(&content));
+        rendered.push(file);
+    }
+    Ok(rendered)
+}
+
+/// Where pipeline templates are cached, next to the generators
+pub fn cache_path() -> DeliveryResult<PathBuf> {
+    utils::home_dir(&[".delivery/cache/pipeline-templates"])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::prelude::*;
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+    use utils::{mkdir_recursive, read_file};
+    use config::Config;
+    use super::{render, render_dir, variables, Variables};
+
+    fn vars() -> Variables {
+        let mut vars = Variables::new();
+        vars.insert("project".to_string(), "frijol".to_string());
+        vars.insert("organization".to_string(), "food".to_string());
+        vars
+    }
+
+    #[test]
+    fn test_render_known_variables_only() {
+        assert_eq!("frijol in food", render("{{.project}} in {{ .organization }}", &vars()));
+        assert_eq!("{{.server}} {{name}} <%= @project %>",
+                   render("{{.server}} {{name}} <%= @project %>", &vars()));
+    }
+
+    #[test]
+    fn test_variables_server() {
+        let config = Config::default().set_server_url("https://automate.example.com:8443/a2");
+        assert_eq!(Some(&"automate.example.com".to_string()), variables(&config).get("server"));
+        let config = Config::default().set_server("delivery.example.com");
+        assert_eq!(Some(&"delivery.example.com".to_string()), variables(&config).get("server"));
+    }
+
+    #[test]
+    fn test_render_dir() {
+        let template = TempDir::new("template").unwrap();
+        let dest = TempDir::new("rendered").unwrap();
+        mkdir_recursive(&template.path().join("build_cookbook/recipes")).unwrap();
+        mkdir_recursive(&template.path().join(".git")).unwrap();
+        File::create(template.path().join("config.json")).unwrap()
+            .write_all(b"{\"build_cookbook\": {\"name\": \"build_{{.project}}\"}}").unwrap();
+        File::create(template.path().join("build_cookbook/recipes/unit.rb")).unwrap()
+            .write_all(b"# Unit tests of {{.project}}").unwrap();
+        File::create(template.path().join(".git/HEAD")).unwrap()
+            .write_all(b"ref: refs/heads/master").unwrap();
+
+        let files = render_dir(template.path(), dest.path(), &vars()).unwrap();
+        assert_eq!(vec![PathBuf::from("build_cookbook/recipes/unit.rb"),
+                        PathBuf::from("config.json")], files);
+        assert_eq!("{\"build_cookbook\": {\"name\": \"build_frijol\"}}",
+                   read_file(dest.path().join("config.json")).unwrap());
+        assert_eq!("# Unit tests of frijol",
+                   read_file(dest.path().join("build_cookbook/recipes/unit.rb")).unwrap());
+        assert!(!dest.path().join(".git").exists());
+    }
+}
@@ -0,0 +1,251 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Undoing what a failed `delivery init` did to the repository
+//!
+//! Before init changes something in the repository it records how that
+//! thing was: the branch it started from, the branches it moves, the
+//! files and directories it writes and the git remotes it sets. When init
+//! fails, `rollback` puts them back. What init did on the server, or
+//! pushed to it, stays.
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use git;
+use types::DeliveryResult;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Mutation {
+    /// A branch and its commit before, `None` when init creates it
+    Branch(String, Option<String>),
+    /// A file and its content before, `None` when init creates it
+    File(PathBuf, Option<Vec<u8>>),
+    /// A directory init creates
+    Dir(PathBuf),
+    /// A git remote and its url before, `None` when init adds it
+    Remote(String, Option<String>),
+}
+
+#[derive(Debug)]
+pub struct Journal {
+    project_path: PathBuf,
+    start: Option<String>,
+    mutations: Vec<Mutation>,
+}
+
+impl Journal {
+    /// Start recording, from the branch (or commit) checked out now
+    pub fn begin(project_path: &Path) -> Journal {
+        let start = git::git_command(&["symbolic-ref", "--quiet", "--short", "HEAD"], project_path)
+            .or_else(|_| git::git_command(&["rev-parse", "HEAD"], project_path))
+            .ok()
+            .map(|r| r.stdout.trim().to_string());
+        Journal {
+            project_path: project_path.to_path_buf(),
+            start: start,
+            mutations: Vec::new(),
+        }
+    }
+
+    fn remembers(&self, m: &Mutation) -> bool {
+        self.mutations.iter().any(|known| match (known, m) {
+            (&Mutation::Branch(ref a, _), &Mutation::Branch(ref b, _)) => a == b,
+            (&Mutation::File(ref a, _), &Mutation::File(ref b, _)) => a == b,
+            (&Mutation::Dir(ref a), &Mutation::Dir(ref b)) => a == b,
+            (&Mutation::Remote(ref a, _), &Mutation::Remote(ref b, _)) => a == b,
+            _ => false
+        })
+    }
+
+    // Only the first state of everything matters
+    fn record(&mut self, m: Mutation) {
+        if !self.remembers(&m) {
+            debug!("Init journal: {:?}", m);
+            self.mutations.push(m);
+        }
+    }
+
+    /// Before creating, or committing to, `branch`
+    pub fn remember_branch(&mut self, branch: &str) {
+        let sha = git::git_command(&["rev-parse", "--verify", "--quiet",
+                                     &format!("refs/heads/{}", branch)], &self.project_path)
+            .ok()
+            .map(|r| r.stdout.trim().to_string());
+        self.record(Mutation::Branch(branch.to_string(), sha));
+    }
+
+    /// Before writing `path`
+    pub fn remember_file(&mut self, path: &Path) {
+        let path = self.project_path.join(path);
+        let mut content = None;
+        if let Ok(mut f) = File::open(&path) {
+            let mut buf = Vec::new();
+            if f.read_to_end(&mut buf).is_ok() {
+                content = Some(buf);
+            }
+        }
+        self.record(Mutation::File(path, content));
+    }
+
+    /// Before writing into `path`, which is only removed again when it
+    /// did not exist yet
+    pub fn remember_dir(&mut self, path: &Path) {
+        let path = self.project_path.join(path);
+        if !path.exists() {
+            self.record(Mutation::Dir(path));
+        }
+    }
+
+    /// Before adding or changing the git remote `name`
+    pub fn remember_remote(&mut self, name: &str) {
+        let url = git::git_command(&["config", "--get", &format!("remote.{}.url", name)],
+                                   &self.project_path)
+            .ok()
+            .map(|r| r.stdout.trim().to_string());
+        self.record(Mutation::Remote(name.to_string(), url));
+    }
+
+    /// Put back everything recorded, returns what was done and what failed
+    pub fn rollback(&self) -> Vec<DeliveryResult<String>> {
+        let path = &self.project_path;
+        let git = |args: &[&str]| git::git_command(args, path).map(|_| ());
+        let mut done = Vec::new();
+
+        if let Some(ref start) = self.start {
+            done.push(git(&["checkout", "--quiet", start])
+                      .map(|_| format!("Checked out '{}' again", start)));
+        }
+        for m in self.mutations.iter().rev() {
+            let result = match *m {
+                Mutation::Branch(ref name, None) => {
+                    git(&["branch", "-D", name]).map(|_| format!("Deleted branch '{}'", name))
+                },
+                Mutation::Branch(ref name, Some(ref sha)) => {
+                    let reset = if self.start.as_ref() == Some(name) {
+                        git(&["reset", "--quiet", "--keep", sha])
+                    } else {
+                        git(&["branch", "--force", name, sha])
+                    };
+                    reset.map(|_| format!("Reset branch '{}' to {}", name, sha))
+                },
+                Mutation::File(ref file, ref content) => {
+                    // Whatever init staged of it goes too
+                    let _ = git(&["reset", "--quiet", "--", &file.to_string_lossy()]);
+                    restore_file(file, content)
+                },
+                Mutation::Dir(ref dir) => {
+                    let _ = git(&["rm", "-r", "--cached", "--quiet", "--ignore-unmatch",
+                                  &dir.to_string_lossy()]);
+                    if dir.exists() {
+                        fs::remove_dir_all(dir)
+                            .map(|_| format!("Removed {}", dir.display()))
+                            .map_err(From::from)
+                    } else {
+                        continue
+                    }
+                },
+                Mutation::Remote(ref name, None) => {
+                    git(&["remote", "remove", name]).map(|_| format!("Removed git remote '{}'", name))
+                },
+                Mutation::Remote(ref name, Some(ref url)) => {
+                    git(&["remote", "set-url", name, url])
+                        .map(|_| format!("Set git remote '{}' back to {}", name, url))
+                },
+            };
+            done.push(result);
+        }
+        done
+    }
+}
+
+fn restore_file(file: &Path, content: &Option<Vec<u8>>) -> DeliveryResult<String> {
+    match *content {
+        Some(ref bytes) => {
+            try!(try!(File::create(file)).write_all(bytes));
+            Ok(format!("Restored {}", file.display()))
+        },
+        None => {
+            if file.exists() {
+                try!(fs::remove_file(file));
+            }
+            Ok(format!("Removed {}", file.display()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::path::Path;
+    use tempdir::TempDir;
+    use git;
+
+    fn write(path: &Path, content: &str) {
+        File::create(path).unwrap().write_all(content.as_bytes()).unwrap();
+    }
+
+    fn read(path: &Path) -> String {
+        let mut content = String::new();
+        File::open(path).unwrap().read_to_string(&mut content).unwrap();
+        content
+    }
+
+    #[test]
+    fn test_rollback() {
+        let tempdir = TempDir::new("rollback").unwrap();
+        let path = tempdir.path();
+        let git = |args: &[&str]| git::git_command(args, path).unwrap().stdout;
+        git(&["init"]);
+        git(&["checkout", "-b", "master"]);
+        write(&path.join("README.md"), "# frijol\n");
+        git(&["add", "README.md"]);
+        git(&["-c", "user.name=test", "-c", "user.email=test@example.com",
+              "commit", "-m", "README"]);
+        git(&["remote", "add", "upstream", "git@example.com:frijol"]);
+
+        let mut journal = Journal::begin(path);
+        journal.remember_branch("add-delivery-config");
+        git(&["checkout", "-b", "add-delivery-config"]);
+        journal.remember_dir(Path::new(".delivery"));
+        fs::create_dir_all(path.join(".delivery")).unwrap();
+        write(&path.join(".delivery/config.json"), "{}");
+        git(&["add", ".delivery"]);
+        journal.remember_file(Path::new("README.md"));
+        write(&path.join("README.md"), "# frijol\n[badge]\n");
+        journal.remember_file(Path::new("DELIVERY.md"));
+        write(&path.join("DELIVERY.md"), "# Delivery\n");
+        git(&["add", "DELIVERY.md"]);
+        journal.remember_remote("delivery");
+        git(&["remote", "add", "delivery", "ssh://delivery/frijol"]);
+        journal.remember_remote("upstream");
+        git(&["remote", "set-url", "upstream", "git@example.com:refrito"]);
+
+        let done = journal.rollback();
+        assert!(done.iter().all(|r| r.is_ok()), "{:?}", done);
+        assert_eq!("master", git(&["symbolic-ref", "--short", "HEAD"]).trim());
+        assert_eq!("", git(&["branch", "--list", "add-delivery-config"]).trim());
+        assert!(!path.join(".delivery").exists());
+        assert!(!path.join("DELIVERY.md").exists());
+        assert_eq!("# frijol\n", read(&path.join("README.md")));
+        assert_eq!("upstream", git(&["remote"]).trim());
+        assert_eq!("git@example.com:frijol",
+                   git(&["config", "--get", "remote.upstream.url"]).trim());
+        assert_eq!("", git(&["status", "--porcelain"]).trim());
+    }
+}
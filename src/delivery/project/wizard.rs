@@ -0,0 +1,305 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! The questions `delivery init --interactive` asks
+//!
+//! Init needs a server, an enterprise, an organization, a project, a
+//! pipeline and, optionally, the source code provider the project lives
+//! on. The wizard asks for each of them, offering what the config
+//! already says or, failing that, what the git remotes of the repository
+//! tell, and writes the answers to `.delivery/cli.toml` so the next
+//! command does not have to ask again.
+
+use std::path::Path;
+use config::Config;
+use errors::Kind;
+use git::{self, GitRemote};
+use project::{self, SourceCodeProvider};
+use types::DeliveryResult;
+use utils;
+use utils::prompt::Prompt;
+use utils::say::{say, sayln};
+
+// How many times the source code provider is asked for before giving up
+const MAX_SCP_ATTEMPTS: usize = 3;
+
+const SCP_CHOICES: [&'static str; 3] = ["delivery", "github", "bitbucket"];
+
+/// What the git remotes of the repository tell about the project
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Guess {
+    pub user: Option<String>,
+    pub server: Option<String>,
+    pub enterprise: Option<String>,
+    pub organization: Option<String>,
+    pub project: Option<String>,
+    /// `github` or `bitbucket`, when the upstream remote is on either
+    pub scp: Option<&'static str>,
+    pub scp_organization: Option<String>,
+    pub repo_name: Option<String>,
+}
+
+impl Guess {
+    /// Guess from the `delivery` remote, pointing at the server, and the
+    /// upstream remote, pointing at the source code provider.
+    pub fn from_remotes(remotes: &[GitRemote]) -> Guess {
+        let mut guess = Guess::default();
+        let delivery = remotes.iter().find(|r| r.name == "delivery")
+                              .and_then(|r| git::parse_delivery_remote(&r.url));
+        if let Some(d) = delivery {
+            let server = d.server.split(':').next().unwrap_or("").to_string();
+            guess.user = Some(d.user);
+            guess.server = Some(server);
+            guess.enterprise = Some(d.enterprise);
+            guess.organization = Some(d.organization);
+            guess.project = Some(d.project);
+        }
+        let upstream = project::find_upstream_remote(remotes, None, None);
+        if let Some(l) = upstream.as_ref().and_then(|r| git::parse_remote_url(&r.url)) {
+            if guess.project.is_none() {
+                guess.project = Some(l.repo.clone());
+            }
+            guess.repo_name = Some(l.repo);
+        }
+        let scp = upstream.as_ref().and_then(|r| project::scp_from_remote_url(&r.url));
+        if let Some(scp) = scp {
+            if scp.provider != "gitlab" {
+                guess.scp = Some(scp.provider);
+                guess.scp_organization = Some(scp.organization);
+            }
+        }
+        guess
+    }
+}
+
+/// Ask for the settings init needs and write them to the
+/// `.delivery/cli.toml` of the project. `ask_scp` is false when the
+/// source code provider came with --github or --bitbucket.
+pub fn run(config: Config, ask_scp: bool,
+           no_verify_ssl: bool) -> DeliveryResult<(Config, Option<SourceCodeProvider>)> {
+    let dir = project::project_path().unwrap_or(utils::cwd());
+    let remotes = git::git_remotes(&dir).unwrap_or(Vec::new());
+    let guess = Guess::from_remotes(&remotes);
+    let (config, scp) = try!(ask(config, &guess, ask_scp, no_verify_ssl));
+    try!(write(&config, &dir));
+    say("white", "Configuration written to ");
+    sayln("yellow", &format!("{}", dir.join(".delivery").join("cli.toml").display()));
+    Ok((config, scp))
+}
+
+/// Ask each question, offering the configured value or else the guess
+pub fn ask(config: Config, guess: &Guess, ask_scp: bool,
+           no_verify_ssl: bool) -> DeliveryResult<(Config, Option<SourceCodeProvider>)> {
+    let interactive = !config.non_interactive.unwrap_or(false);
+    let question = |q: &str, current: DeliveryResult<String>, guessed: &Option<String>| {
+        let default = current.ok().or(guessed.clone());
+        let prompt = Prompt::new(q).interactive(interactive);
+        match default {
+            Some(ref d) => prompt.default(d).input(),
+            None => prompt.input()
+        }
+    };
+    let mut config = config;
+    // A --server-url already says where the server is
+    if config.server_url.is_none() {
+        let server = try!(question("Delivery server", config.server_host(), &guess.server));
+        config = config.set_server(&server);
+    }
+    let ent = try!(question("Enterprise", config.enterprise(), &guess.enterprise));
+    let org = try!(question("Organization", config.organization(), &guess.organization));
+    let user = try!(question("User", config.user(), &guess.user));
+    let proj = try!(question("Project", config.project(), &guess.project));
+    let pipeline = try!(question("Pipeline", config.pipeline(), &Some("master".to_string())));
+    config = config.set_enterprise(&ent)
+                   .set_organization(&org)
+                   .set_user(&user)
+                   .set_project(&proj)
+                   .set_pipeline(&pipeline);
+    if !ask_scp {
+        return Ok((config, None))
+    }
+    let scp = try!(ask_scp_until_valid(guess, &proj, &pipeline, interactive, no_verify_ssl));
+    Ok((config, scp))
+}
+
+// Ask for the source code provider until `SourceCodeProvider::new` takes
+// the answers
+fn ask_scp_until_valid(guess: &Guess, proj: &str, pipeline: &str, interactive: bool,
+                       no_verify_ssl: bool) -> DeliveryResult<Option<SourceCodeProvider>> {
+    let mut last_error = None;
+    for _ in 0..MAX_SCP_ATTEMPTS {
+        sayln("white", "Where does the project source live?");
+        let choice = try!(Prompt::new("Source code provider")
+                          .default(guess.scp.unwrap_or("delivery"))
+                          .interactive(interactive)
+                          .select(&SCP_CHOICES));
+        let kind = SCP_CHOICES[choice];
+        if kind == "delivery" {
+            return Ok(None)
+        }
+        let owner_question = if kind == "github" { "GitHub organization" }
+                             else { "Bitbucket project key" };
+        let owner_default = match guess.scp {
+            Some(k) if k == kind => guess.scp_organization.clone().unwrap_or(String::new()),
+            _ => String::new()
+        };
+        let owner = try!(Prompt::new(owner_question).default(&owner_default)
+                                                    .interactive(interactive).input());
+        let repo_default = guess.repo_name.clone().unwrap_or(proj.to_string());
+        let repo = try!(Prompt::new("Repository name").default(&repo_default)
+                                                      .interactive(interactive).input());
+        // Only GitHub projects take the SSL setting, like init does
+        let no_ssl = kind != "github" || no_verify_ssl;
+        match SourceCodeProvider::new(kind, &repo, &owner, pipeline, no_ssl) {
+            Ok(scp) => return Ok(Some(scp)),
+            Err(e) => {
+                if let Some(ref d) = e.detail {
+                    sayln("red", d);
+                }
+                match e.kind {
+                    Kind::OptionConstraint if interactive => last_error = Some(e),
+                    _ => return Err(e)
+                }
+            }
+        }
+    }
+    Err(last_error.unwrap())
+}
+
+// Only the answers go to the cli.toml, not whatever other options init
+// runs with.
+fn write(config: &Config, dir: &Path) -> DeliveryResult<()> {
+    let on_disk = try!(Config::load_project_config(&dir.to_path_buf()));
+    let mut answers = on_disk.set_enterprise(&try!(config.enterprise()))
+                             .set_organization(&try!(config.organization()))
+                             .set_user(&try!(config.user()))
+                             .set_project(&try!(config.project()))
+                             .set_pipeline(&try!(config.pipeline()));
+    // A --server-url is kept as given, with its port and path prefix
+    match config.server_url {
+        Some(ref url) => answers = answers.set_server_url(url),
+        None => if let Ok(server) = config.server_host() {
+            answers = answers.set_server(&server);
+        }
+    }
+    try!(answers.write_file(dir));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::Config;
+    use errors::Kind;
+    use project::tests::remote;
+    use utils::prompt::with_answers;
+    use utils::say::capture;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_guess_from_remotes() {
+        let remotes = vec![
+            remote("origin", "git@github.com:chef/frijol.git"),
+            remote("delivery", "ssh://concha@mexicana@cocina.central.com:8989/mexicana/oaxaca/frijoles"),
+        ];
+        let guess = Guess::from_remotes(&remotes);
+        assert_eq!(Some("concha".to_string()), guess.user);
+        assert_eq!(Some("cocina.central.com".to_string()), guess.server);
+        assert_eq!(Some("mexicana".to_string()), guess.enterprise);
+        assert_eq!(Some("oaxaca".to_string()), guess.organization);
+        assert_eq!(Some("frijoles".to_string()), guess.project);
+        assert_eq!(Some("github"), guess.scp);
+        assert_eq!(Some("chef".to_string()), guess.scp_organization);
+        assert_eq!(Some("frijol".to_string()), guess.repo_name);
+
+        let remotes = vec![remote("origin", "https://git.example.com/scm/PROJ/tortilla.git")];
+        let guess = Guess::from_remotes(&remotes);
+        assert_eq!(Some("bitbucket"), guess.scp);
+        assert_eq!(Some("PROJ".to_string()), guess.scp_organization);
+        assert_eq!(Some("tortilla".to_string()), guess.project);
+        assert_eq!(None, guess.server);
+
+        let remotes = vec![remote("origin", "https://gitlab.com/group/tamal.git")];
+        let guess = Guess::from_remotes(&remotes);
+        assert_eq!(None, guess.scp);
+        assert_eq!(Some("tamal".to_string()), guess.repo_name);
+    }
+
+    #[test]
+    fn test_ask() {
+        let guess = Guess {
+            server: Some("cocina.central.com".to_string()),
+            scp: Some("github"),
+            scp_organization: Some("chef".to_string()),
+            repo_name: Some("frijol".to_string()),
+            ..Guess::default()
+        };
+        let config = Config::default().set_enterprise("mexicana").set_project("frijol");
+        let answers = ["", "", "oaxaca", "concha", "", "release", "", "", ""];
+        let (result, _) = capture(|| {
+            with_answers(&answers, || ask(config.clone(), &guess, true, false))
+        });
+        let (config, scp) = result.unwrap();
+        assert_eq!("cocina.central.com", config.server().unwrap());
+        assert_eq!("mexicana", config.enterprise().unwrap());
+        assert_eq!("oaxaca", config.organization().unwrap());
+        assert_eq!("concha", config.user().unwrap());
+        assert_eq!("frijol", config.project().unwrap());
+        assert_eq!("release", config.pipeline().unwrap());
+        let scp = scp.unwrap();
+        assert_eq!("chef", scp.organization);
+        assert_eq!("frijol", scp.repo_name);
+        assert_eq!("release", scp.branch);
+    }
+
+    #[test]
+    fn test_ask_scp_is_validated() {
+        let config = Config::default().set_server("s").set_enterprise("e")
+                                      .set_organization("o").set_user("u")
+                                      .set_project("p");
+        // An empty project key is asked for again
+        let answers = ["", "", "", "", "", "", "bitbucket", "", "repo",
+                       "bitbucket", "PROJ", "repo"];
+        let (result, _) = capture(|| {
+            with_answers(&answers, || ask(config.clone(), &Guess::default(), true, false))
+        });
+        let scp = result.unwrap().1.unwrap();
+        assert_eq!("PROJ", scp.organization);
+        assert!(!scp.verify_ssl);
+
+        let mut config = config;
+        config.non_interactive = Some(true);
+        let (result, _) = capture(|| ask(config.clone(), &Guess::default(), true, false));
+        assert!(result.unwrap().1.is_none());
+        config.user = None;
+        let (result, _) = capture(|| ask(config.clone(), &Guess::default(), false, false));
+        assert!(assert_enum!(result.unwrap_err().kind, Kind::PromptUnanswered));
+    }
+
+    #[test]
+    fn test_write_keeps_the_server_url() {
+        let dir = TempDir::new("wizard").unwrap();
+        let config = Config::default().set_server_url("https://automate.example.com:8443/a2")
+                                      .set_enterprise("e").set_organization("o")
+                                      .set_user("u").set_project("p").set_pipeline("master");
+        write(&config, dir.path()).unwrap();
+        let written = Config::load_project_config(&dir.path().to_path_buf()).unwrap();
+        assert_eq!(Some("https://automate.example.com:8443/a2".to_string()), written.server_url);
+        assert_eq!(None, written.server);
+        assert_eq!("automate.example.com", written.server_host().unwrap());
+    }
+}
@@ -0,0 +1,307 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Shell prompt summary
+//!
+//! `delivery prompt` runs every time a shell draws its prompt, so it
+//! must never wait on the network. The last known state of the change
+//! of each branch, of every pipeline, is kept in
+//! `~/.delivery/state/changes.json`; the prompt prints what is cached
+//! and, when that is older than `max_age`, starts a detached
+//! `delivery prompt --refresh` that updates the cache for the next
+//! prompt. The commands that fetch the open changes anyway, like the
+//! merge check of `delivery status`, update it too, and `delivery
+//! review` starts a refresh once the change is submitted.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use serde_json;
+use time;
+use http::change::ChangeSummary;
+use types::DeliveryResult;
+use utils::{self, read_file};
+
+pub const CACHE_FILE_NAME: &'static str = "changes.json";
+pub const DEFAULT_MAX_AGE: i64 = 60;
+
+/// The state of the change of a branch
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChangeState {
+    #[serde(default)]
+    pub id: String,
+    pub stage: String,
+    pub stage_status: String,
+    pub patchset: u64,
+}
+
+impl ChangeState {
+    pub fn from_summary(change: &ChangeSummary) -> ChangeState {
+        ChangeState {
+            id: change.id.clone(),
+            stage: change.stage.clone(),
+            stage_status: change.stage_status.clone(),
+            patchset: change.patchset_number,
+        }
+    }
+
+    /// The terse form printed in the prompt, i.e. `verify✔ ●2`
+    pub fn summary(&self) -> String {
+        let symbol = match self.stage_status.as_ref() {
+            "passed" => "✔",
+            "failed" => "✘",
+            "running" | "" => "…",
+            _ => "?"
+        };
+        let stage = if self.stage.is_empty() { "review" } else { &self.stage };
+        let mut summary = format!("{}{}", stage, symbol);
+        if self.patchset > 1 {
+            summary.push_str(&format!(" ●{}", self.patchset));
+        }
+        summary
+    }
+}
+
+/// What was last fetched for a branch; `change` is `None` when the
+/// branch had no open change.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CachedBranch {
+    pub change: Option<ChangeState>,
+    pub updated_at: i64,
+}
+
+/// The branches of every pipeline, by pipeline key then branch name;
+/// both may contain a `/` so they are never joined into one key.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct PromptCache {
+    #[serde(default)]
+    pub pipelines: BTreeMap<String, BTreeMap<String, CachedBranch>>,
+}
+
+impl PromptCache {
+    /// The cache file shared by every repository of the user
+    pub fn path() -> DeliveryResult<PathBuf> {
+        utils::home_dir(&[".delivery", "state", CACHE_FILE_NAME])
+    }
+
+    /// What the branches of a pipeline are cached under
+    pub fn pipeline_key(ent: &str, org: &str, proj: &str, pipe: &str) -> String {
+        format!("{}/{}/{}/{}", ent, org, proj, pipe)
+    }
+
+    /// Load the cache, a missing or unreadable cache is an empty one
+    pub fn load(path: &Path) -> PromptCache {
+        read_file(&path.to_path_buf()).ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or(PromptCache::default())
+    }
+
+    // Several processes update the cache, so a reader never sees it
+    // half written: the content goes to a file of its own first.
+    pub fn save(&self, path: &Path) -> DeliveryResult<()> {
+        if let Some(dir) = path.parent() {
+            try!(fs::create_dir_all(dir));
+        }
+        let content = try!(serde_json::to_string(self));
+        let tmp = path.with_extension(format!("json.{}", ::std::process::id()));
+        {
+            let mut file = try!(File::create(&tmp));
+            try!(file.write_all(content.as_bytes()));
+        }
+        try!(fs::rename(&tmp, path));
+        Ok(())
+    }
+
+    /// What was last fetched for `branch` of the pipeline
+    pub fn get(&self, pipeline_key: &str, branch: &str) -> Option<&CachedBranch> {
+        self.pipelines.get(pipeline_key).and_then(|branches| branches.get(branch))
+    }
+
+    pub fn update(&mut self, pipeline_key: &str, branch: &str,
+                  change: Option<ChangeState>, now: i64) {
+        self.pipelines.entry(pipeline_key.to_string())
+            .or_insert_with(BTreeMap::new)
+            .insert(branch.to_string(), CachedBranch { change: change, updated_at: now });
+    }
+
+    /// Record the open `changes` of the pipeline under `pipeline_key`;
+    /// the branches cached with a change that is no longer open have
+    /// none now.
+    pub fn update_pipeline(&mut self, pipeline_key: &str, changes: &[ChangeSummary], now: i64) {
+        let closed: Vec<String> = match self.pipelines.get(pipeline_key) {
+            Some(branches) => branches.keys()
+                .filter(|b| !changes.iter().any(|c| c.topic == **b))
+                .cloned()
+                .collect(),
+            None => Vec::new()
+        };
+        for branch in closed {
+            self.update(pipeline_key, &branch, None, now);
+        }
+        for change in changes.iter() {
+            self.update(pipeline_key, &change.topic, Some(ChangeState::from_summary(change)), now);
+        }
+    }
+
+    /// The cached change of `branch`, if fetched in the last `max_age`
+    /// seconds; `Some(None)` when the branch had no open change.
+    pub fn fresh(&self, pipeline_key: &str, branch: &str,
+                 max_age: i64, now: i64) -> Option<Option<ChangeState>> {
+        if self.is_stale(pipeline_key, branch, max_age, now) {
+            return None
+        }
+        self.get(pipeline_key, branch).map(|b| b.change.clone())
+    }
+
+    /// Whether `branch` was not fetched in the last `max_age` seconds
+    pub fn is_stale(&self, pipeline_key: &str, branch: &str, max_age: i64, now: i64) -> bool {
+        self.get(pipeline_key, branch).map(|b| now - b.updated_at > max_age).unwrap_or(true)
+    }
+
+    /// The prompt summary of `branch`, empty when there is no change
+    pub fn summary(&self, pipeline_key: &str, branch: &str) -> String {
+        self.get(pipeline_key, branch)
+            .and_then(|b| b.change.as_ref())
+            .map(|c| c.summary())
+            .unwrap_or(String::new())
+    }
+}
+
+/// Seconds since the epoch
+pub fn now() -> i64 {
+    time::get_time().sec
+}
+
+/// The branch checked out in the repository at `project_path`, read
+/// straight from `.git/HEAD` to avoid spawning git on every prompt.
+pub fn current_branch(project_path: &Path) -> Option<String> {
+    let head = read_file(&project_path.join(".git").join("HEAD")).ok();
+    head.and_then(|h| parse_head(&h))
+}
+
+/// Update the cache in a detached `delivery prompt --refresh` run from
+/// `path`, so the caller returns at once. Nothing happens when the
+/// library runs in another program.
+pub fn spawn_refresh(path: &Path) {
+    let is_delivery = env::current_exe().ok()
+        .and_then(|exe| exe.file_stem().map(|s| s == "delivery"))
+        .unwrap_or(false);
+    if !is_delivery {
+        debug!("Not refreshing the prompt cache from another program");
+        return
+    }
+    let result = env::current_exe().and_then(|exe| {
+        Command::new(exe)
+            .args(&["prompt", "--refresh"])
+            .current_dir(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+    });
+    if let Err(e) = result {
+        debug!("Unable to refresh the prompt cache: {:?}", e);
+    }
+}
+
+fn parse_head(head: &str) -> Option<String> {
+    let head = head.trim();
+    if head.starts_with("ref: refs/heads/") {
+        Some(head["ref: refs/heads/".len()..].to_string())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::change::ChangeSummary;
+    use tempdir::TempDir;
+
+    fn state(stage: &str, status: &str, patchset: u64) -> ChangeState {
+        ChangeState { id: String::new(), stage: stage.to_string(),
+                      stage_status: status.to_string(), patchset: patchset }
+    }
+
+    #[test]
+    fn test_change_state_summary() {
+        assert_eq!("verify✔", state("verify", "passed", 1).summary());
+        assert_eq!("acceptance✘ ●3", state("acceptance", "failed", 3).summary());
+        assert_eq!("review… ●2", state("", "", 2).summary());
+    }
+
+    #[test]
+    fn test_cache_staleness_and_summary() {
+        let mut cache = PromptCache::default();
+        assert!(cache.is_stale("p", "foo", 60, 1000));
+        cache.update("p", "foo", Some(state("verify", "running", 1)), 1000);
+        cache.update("p", "bar", None, 1000);
+        assert!(!cache.is_stale("p", "foo", 60, 1030));
+        assert!(cache.is_stale("p", "foo", 60, 1061));
+        assert_eq!("verify…", cache.summary("p", "foo"));
+        assert_eq!("", cache.summary("p", "bar"));
+        assert_eq!("", cache.summary("p", "baz"));
+        assert_eq!("", cache.summary("q", "foo"));
+    }
+
+    #[test]
+    fn test_update_pipeline() {
+        let change = |id: &str, topic: &str| ChangeSummary {
+            id: id.to_string(), topic: topic.to_string(), target: "master".to_string(),
+            title: String::new(), sha: String::new(), stage: "verify".to_string(),
+            stage_status: "passed".to_string(), patchset_number: 1,
+            approved_by: String::new(), approved_at: String::new(),
+            submitted_at: String::new(),
+        };
+        let key = PromptCache::pipeline_key("ent", "org", "proj", "release");
+        let other = PromptCache::pipeline_key("ent", "org", "other", "release");
+        // A pipeline whose name extends the one refreshed
+        let longer = PromptCache::pipeline_key("ent", "org", "proj", "release/1.x");
+        let mut cache = PromptCache::default();
+        cache.update(&other, "foo", Some(state("build", "running", 1)), 900);
+        cache.update(&longer, "foo", Some(state("build", "running", 1)), 900);
+        cache.update_pipeline(&key, &[change("c1", "foo"), change("c2", "feature/bar")], 1000);
+        assert_eq!("c2", cache.fresh(&key, "feature/bar", 60, 1010).unwrap().unwrap().id);
+        assert_eq!(None, cache.fresh(&key, "foo", 60, 1100));
+
+        cache.update_pipeline(&key, &[change("c2", "feature/bar")], 1200);
+        assert_eq!(Some(None), cache.fresh(&key, "foo", 60, 1210));
+        assert_eq!("build…", cache.summary(&other, "foo"));
+        assert_eq!("build…", cache.summary(&longer, "foo"));
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let tmp = TempDir::new("prompt").unwrap();
+        let path = tmp.path().join("state").join(CACHE_FILE_NAME);
+        assert_eq!(PromptCache::default(), PromptCache::load(&path));
+        let mut cache = PromptCache::default();
+        cache.update("p", "foo", Some(state("verify", "passed", 2)), 42);
+        cache.save(&path).unwrap();
+        assert_eq!(cache, PromptCache::load(&path));
+    }
+
+    #[test]
+    fn test_parse_head() {
+        assert_eq!(Some("feature/foo".to_string()), parse_head("ref: refs/heads/feature/foo\n"));
+        assert_eq!(None, parse_head("3e1f0a4b0c1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f\n"));
+    }
+}
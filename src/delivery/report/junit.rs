@@ -0,0 +1,198 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+// JUnit XML is the lowest common denominator of test runners: rspec,
+// pytest, cargo2junit, jest and friends can all write it. We only read
+// the testcase elements and what they contain, so a regex based parser
+// is enough and saves us an XML dependency.
+
+use regex::Regex;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+
+/// How many failing tests we list in a summary before truncating
+pub const MAX_LISTED_FAILURES: usize = 25;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedTest {
+    pub name: String,
+    pub classname: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct JUnitReport {
+    pub tests: usize,
+    pub failures: usize,
+    pub errors: usize,
+    pub skipped: usize,
+    pub failed: Vec<FailedTest>,
+}
+
+impl JUnitReport {
+    /// Parse the content of a JUnit XML file
+    pub fn parse(xml: &str) -> DeliveryResult<JUnitReport> {
+        let testcase = Regex::new(r"(?s)<testcase\b([^>]*?)(?:/>|>(.*?)</testcase>)").unwrap();
+        let outcome = Regex::new(r"(?s)<(failure|error|skipped)\b([^>]*?)(?:/>|>(.*?)</(?:failure|error|skipped)>)")
+            .unwrap();
+        if !xml.contains("<testsuite") {
+            let msg = "No <testsuite> element found, is this a JUnit XML report?".to_string();
+            return Err(DeliveryError::throw(Kind::InvalidReport, Some(msg)))
+        }
+
+        let mut report = JUnitReport::default();
+        for case in testcase.captures_iter(xml) {
+            report.tests += 1;
+            let attrs = case.at(1).unwrap_or("");
+            let body = case.at(2).unwrap_or("");
+            if let Some(o) = outcome.captures(body) {
+                match o.at(1) {
+                    Some("skipped") => report.skipped += 1,
+                    Some(kind) => {
+                        if kind == "failure" {
+                            report.failures += 1;
+                        } else {
+                            report.errors += 1;
+                        }
+                        let message = attribute(o.at(2).unwrap_or(""), "message")
+                            .unwrap_or_else(|| unescape(o.at(3).unwrap_or("").trim()));
+                        report.failed.push(FailedTest {
+                            name: attribute(attrs, "name").unwrap_or_default(),
+                            classname: attribute(attrs, "classname").unwrap_or_default(),
+                            message: message.lines().next().unwrap_or("").to_string(),
+                        });
+                    },
+                    None => {}
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    pub fn passed(&self) -> usize {
+        self.tests - self.failures - self.errors - self.skipped
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.failures == 0 && self.errors == 0
+    }
+
+    /// A markdown summary of the report, to post as a comment
+    pub fn to_markdown(&self, phase: &str) -> String {
+        let status = if self.is_success() { "passed" } else { "failed" };
+        let mut summary = format!("**{}** tests {}: {} tests, {} passed, {} failed, {} skipped",
+                                  phase, status, self.tests, self.passed(),
+                                  self.failures + self.errors, self.skipped);
+        if !self.failed.is_empty() {
+            summary.push_str("\n\nFailing tests:\n");
+            for test in self.failed.iter().take(MAX_LISTED_FAILURES) {
+                summary.push_str(&format!("\n* `{}`", test.full_name()));
+                if !test.message.is_empty() {
+                    summary.push_str(&format!(": {}", test.message));
+                }
+            }
+            if self.failed.len() > MAX_LISTED_FAILURES {
+                summary.push_str(&format!("\n* ...and {} more",
+                                          self.failed.len() - MAX_LISTED_FAILURES));
+            }
+        }
+        summary
+    }
+}
+
+impl FailedTest {
+    pub fn full_name(&self) -> String {
+        if self.classname.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}.{}", self.classname, self.name)
+        }
+    }
+}
+
+// The unescaped value of an XML attribute
+fn attribute(attrs: &str, name: &str) -> Option<String> {
+    let re = Regex::new(&format!(r#"\b{}\s*=\s*(?:"([^"]*)"|'([^']*)')"#, name)).unwrap();
+    re.captures(attrs).and_then(|c| c.at(1).or(c.at(2))).map(unescape)
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &'static str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="rspec" tests="5" failures="1" errors="1" skipped="1">
+    <testcase classname="default_spec" name="installs the package" time="0.1"/>
+    <testcase classname="default_spec" name="starts the service" time="0.2">
+      <failure message="expected &quot;running&quot; got &quot;stopped&quot;" type="RSpec::Expectations">
+        backtrace...
+      </failure>
+    </testcase>
+    <testcase classname="default_spec" name="renders the template">
+      <error>undefined method `[]&apos; for nil
+      more backtrace</error>
+    </testcase>
+    <testcase classname="default_spec" name="is pending"><skipped/></testcase>
+    <testcase name="no class"></testcase>
+  </testsuite>
+</testsuites>"#;
+
+    #[test]
+    fn test_parse_counts() {
+        let report = JUnitReport::parse(REPORT).unwrap();
+        assert_eq!(5, report.tests);
+        assert_eq!(1, report.failures);
+        assert_eq!(1, report.errors);
+        assert_eq!(1, report.skipped);
+        assert_eq!(2, report.passed());
+        assert!(!report.is_success());
+    }
+
+    #[test]
+    fn test_parse_failed_tests() {
+        let report = JUnitReport::parse(REPORT).unwrap();
+        assert_eq!(FailedTest { name: "starts the service".to_string(),
+                                classname: "default_spec".to_string(),
+                                message: "expected \"running\" got \"stopped\"".to_string() },
+                   report.failed[0]);
+        assert_eq!("undefined method `[]' for nil", report.failed[1].message);
+    }
+
+    #[test]
+    fn test_parse_rejects_other_xml() {
+        assert!(JUnitReport::parse("<html></html>").is_err());
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let report = JUnitReport::parse(REPORT).unwrap();
+        let expect = "**unit** tests failed: 5 tests, 2 passed, 2 failed, 1 skipped\n\n\
+                      Failing tests:\n\n\
+                      * `default_spec.starts the service`: expected \"running\" got \"stopped\"\n\
+                      * `default_spec.renders the template`: undefined method `[]' for nil";
+        assert_eq!(expect, report.to_markdown("unit"));
+    }
+}
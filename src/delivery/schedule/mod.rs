@@ -0,0 +1,482 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Scheduled deliveries
+//!
+//! Change freezes and deploy windows mean someone staying up until the
+//! window opens to press the button. `delivery deliver --at` and
+//! `--window` schedule the delivery instead: servers that advertise
+//! `scheduled_delivery` keep the schedule themselves, for the others it
+//! is kept in `~/.delivery/schedule.json` and delivered by
+//! `delivery schedule run`.
+//!
+//! Times are UTC, windows included. A delivery that fails is tried
+//! again later and later, and given up on after `MAX_ATTEMPTS` or as
+//! soon as the server refuses it for good.
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use serde_json;
+use time;
+use config::Config;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+use utils::{self, read_file};
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// How many times a delivery is tried before it is given up on
+pub const MAX_ATTEMPTS: u32 = 5;
+
+// The wait after the first failed attempt, doubled after every other
+const RETRY_DELAY: i64 = 60;
+const MAX_RETRY_DELAY: i64 = 60 * 60;
+const DAYS: [&'static str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// The windows known without any configuration. `[delivery_windows]`
+/// in the cli.toml adds others and can redefine these.
+pub const BUILTIN_WINDOWS: [(&'static str, &'static str); 2] = [
+    ("weeknights", "mon-thu 22:00-06:00"),
+    ("weekends", "sat,sun 00:00-24:00"),
+];
+
+/// A weekly delivery window, i.e. `mon-thu 22:00-06:00`: it opens at
+/// 22:00 on the listed days and closes at 06:00 the day after.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    // Indexed by day of the week, Sunday first
+    days: [bool; 7],
+    // Minutes since midnight
+    start: i64,
+    end: i64,
+}
+
+impl Window {
+    /// Parse `<days> <HH:MM>-<HH:MM>`, where days are `daily` or a comma
+    /// separated list of days and ranges of days, i.e. `mon-fri,sun`
+    pub fn parse(spec: &str) -> DeliveryResult<Window> {
+        let parts: Vec<&str> = spec.split_whitespace().collect();
+        if parts.len() != 2 {
+            return Err(invalid(&format!("'{}' is not a window, write it as \
+                                         `<days> <HH:MM>-<HH:MM>`, i.e. \
+                                         `mon-thu 22:00-06:00`", spec)))
+        }
+        let days = try!(parse_days(parts[0]));
+        let hours: Vec<&str> = parts[1].split('-').collect();
+        if hours.len() != 2 {
+            return Err(invalid(&format!("'{}' is not a range of hours, i.e. 22:00-06:00",
+                                        parts[1])))
+        }
+        let start = try!(parse_hour(hours[0]));
+        let end = try!(parse_hour(hours[1]));
+        if start == end || start == 24 * 60 {
+            return Err(invalid(&format!("the window {} is empty", parts[1])))
+        }
+        Ok(Window { days: days, start: start, end: end })
+    }
+
+    /// The window `name`, from the cli.toml or built in
+    pub fn named(name: &str, config: &Config) -> DeliveryResult<Window> {
+        Window::parse(&try!(window_spec(name, config)))
+    }
+
+    /// The first time the window is open at or after `from`, and when it
+    /// closes again, as seconds since the epoch
+    pub fn next(&self, from: i64) -> (i64, i64) {
+        let midnight = from - from.rem_euclid(DAY);
+        // The window opened the day before may still be open
+        for d in -1..8 {
+            let day = midnight + d * DAY;
+            if !self.days[weekday(day)] {
+                continue
+            }
+            let open = day + self.start * 60;
+            let close = if self.end > self.start {
+                day + self.end * 60
+            } else {
+                day + DAY + self.end * 60
+            };
+            if close > from {
+                return (open.max(from), close)
+            }
+        }
+        unreachable!("a window opens at least once a week")
+    }
+}
+
+/// The spec of the window `name`, from the cli.toml or built in
+pub fn window_spec(name: &str, config: &Config) -> DeliveryResult<String> {
+    let spec = config.delivery_window(name).or_else(|| {
+        BUILTIN_WINDOWS.iter().find(|w| w.0 == name).map(|w| w.1.to_string())
+    });
+    spec.ok_or_else(|| {
+        invalid(&format!("Unknown delivery window {}; use one of {} or add it to the \
+                          [delivery_windows] of your cli.toml", name, builtin_names()))
+    })
+}
+
+/// Parse the `--at` of a delivery: `2024-06-01T22:00Z`, with or without
+/// seconds, or `2024-06-01 22:00`, all UTC
+pub fn parse_at(at: &str) -> DeliveryResult<i64> {
+    let formats = ["%Y-%m-%dT%H:%MZ", "%Y-%m-%dT%H:%M:%SZ", "%Y-%m-%d %H:%M"];
+    for format in formats.iter() {
+        if let Ok(tm) = time::strptime(at.trim(), format) {
+            return Ok(tm.to_timespec().sec)
+        }
+    }
+    Err(invalid(&format!("'{}' is not a time, write it in UTC as 2024-06-01T22:00Z", at)))
+}
+
+/// i.e. `2024-06-01T22:00Z`
+pub fn format_time(secs: i64) -> String {
+    let tm = time::at_utc(time::Timespec::new(secs, 0));
+    time::strftime("%Y-%m-%dT%H:%MZ", &tm).unwrap_or_else(|_| secs.to_string())
+}
+
+/// When to deliver: the first time `window` is open at or after
+/// `earliest`, and when the window closes again
+pub fn plan(earliest: i64, window: Option<&Window>) -> (i64, Option<i64>) {
+    match window {
+        Some(window) => {
+            let (at, until) = window.next(earliest);
+            (at, Some(until))
+        },
+        None => (earliest, None)
+    }
+}
+
+/// A delivery kept in the local schedule, with what it takes to talk to
+/// the server from wherever `delivery schedule run` is started
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub change: String,
+    pub server_url: String,
+    pub user: String,
+    pub ent: String,
+    pub org: String,
+    pub project: String,
+    pub at: i64,
+    // The spec of the window, to find the next opening when the
+    // delivery could not happen before it closed
+    #[serde(default)]
+    pub window: Option<String>,
+    #[serde(default)]
+    pub until: Option<i64>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub attempts: u32,
+    // Not before then, after a failed attempt
+    #[serde(default)]
+    pub retry_at: Option<i64>,
+}
+
+impl Entry {
+    /// `2024-06-01T22:00Z`, or `2024-06-01T22:00Z-2024-06-02T06:00Z`
+    /// when it has to happen within a window
+    pub fn when(&self) -> String {
+        match self.until {
+            Some(until) => format!("{}-{}", format_time(self.at), format_time(until)),
+            None => format_time(self.at)
+        }
+    }
+
+    /// What to do about the entry `now`
+    pub fn due(&self, now: i64) -> Due {
+        if self.gave_up() {
+            return Due::GaveUp
+        }
+        if now < self.at || self.retry_at.map(|r| now < r).unwrap_or(false) {
+            return Due::NotYet
+        }
+        match (self.until, self.window.as_ref()) {
+            (Some(until), Some(spec)) if now >= until => {
+                match Window::parse(spec) {
+                    Ok(window) => Due::Missed(window.next(now)),
+                    Err(_) => Due::Now
+                }
+            },
+            _ => Due::Now
+        }
+    }
+
+    pub fn gave_up(&self) -> bool {
+        self.attempts >= MAX_ATTEMPTS
+    }
+
+    /// Record an attempt that failed with `error` at `now`; a
+    /// `permanent` failure is not tried again
+    pub fn failed(&mut self, error: String, permanent: bool, now: i64) {
+        self.attempts = if permanent { MAX_ATTEMPTS } else { self.attempts + 1 };
+        let delay = RETRY_DELAY.checked_shl(self.attempts.saturating_sub(1))
+            .unwrap_or(MAX_RETRY_DELAY)
+            .min(MAX_RETRY_DELAY);
+        self.retry_at = Some(now + delay);
+        self.last_error = Some(error);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Due {
+    NotYet,
+    Now,
+    // The window closed before the delivery happened; the next opening
+    Missed((i64, i64)),
+    // Failed too many times, or for good
+    GaveUp,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Schedule {
+    #[serde(default)]
+    pub entries: Vec<Entry>,
+}
+
+impl Schedule {
+    /// `~/.delivery/schedule.json`
+    pub fn path() -> DeliveryResult<PathBuf> {
+        utils::home_dir(&[".delivery", "schedule.json"])
+    }
+
+    pub fn load(path: &Path) -> DeliveryResult<Schedule> {
+        if !path.exists() {
+            return Ok(Schedule::default())
+        }
+        Ok(try!(serde_json::from_str(&try!(read_file(path)))))
+    }
+
+    // Written next to the schedule and renamed over it, so a
+    // `schedule run` reading it meanwhile never sees half of it
+    pub fn save(&self, path: &Path) -> DeliveryResult<()> {
+        if let Some(dir) = path.parent() {
+            try!(utils::mkdir_recursive(dir));
+        }
+        let tmp = path.with_extension("json.tmp");
+        {
+            let mut f = try!(File::create(&tmp));
+            try!(f.write_all(try!(serde_json::to_string_pretty(&self)).as_bytes()));
+        }
+        try!(fs::rename(&tmp, path));
+        Ok(())
+    }
+
+    /// Add `entry`, replacing the schedule of the same change
+    pub fn add(&mut self, entry: Entry) {
+        self.entries.retain(|e| e.change != entry.change);
+        self.entries.push(entry);
+        self.entries.sort_by_key(|e| e.at);
+    }
+
+    pub fn remove(&mut self, change: &str) -> Option<Entry> {
+        let index = self.entries.iter().position(|e| e.change == change);
+        index.map(|i| self.entries.remove(i))
+    }
+
+    pub fn get_mut(&mut self, change: &str) -> Option<&mut Entry> {
+        self.entries.iter_mut().find(|e| e.change == change)
+    }
+}
+
+fn parse_days(spec: &str) -> DeliveryResult<[bool; 7]> {
+    let mut days = [false; 7];
+    if spec == "daily" {
+        return Ok([true; 7])
+    }
+    for item in spec.split(',') {
+        let range: Vec<&str> = item.split('-').collect();
+        let (first, last) = match range.len() {
+            1 => (try!(parse_day(range[0])), try!(parse_day(range[0]))),
+            2 => (try!(parse_day(range[0])), try!(parse_day(range[1]))),
+            _ => return Err(invalid(&format!("'{}' is not a range of days, i.e. mon-fri", item)))
+        };
+        // fri-mon wraps around the weekend
+        let mut day = first;
+        loop {
+            days[day] = true;
+            if day == last {
+                break
+            }
+            day = (day + 1) % 7;
+        }
+    }
+    Ok(days)
+}
+
+fn parse_day(day: &str) -> DeliveryResult<usize> {
+    let lower = day.to_lowercase();
+    DAYS.iter().position(|d| lower.starts_with(d)).ok_or_else(|| {
+        invalid(&format!("'{}' is not a day, use {} or daily", day, DAYS.join(", ")))
+    })
+}
+
+fn parse_hour(hour: &str) -> DeliveryResult<i64> {
+    let parts: Vec<&str> = hour.split(':').collect();
+    let parsed = match parts.len() {
+        2 => parts[0].parse::<i64>().ok().and_then(|h| {
+            parts[1].parse::<i64>().ok().map(|m| (h, m))
+        }),
+        _ => None
+    };
+    match parsed {
+        Some((h, m)) if h >= 0 && m >= 0 && m < 60 && (h < 24 || (h == 24 && m == 0)) => {
+            Ok(h * 60 + m)
+        },
+        _ => Err(invalid(&format!("'{}' is not an hour, i.e. 22:00", hour)))
+    }
+}
+
+// The epoch was a Thursday
+fn weekday(secs: i64) -> usize {
+    ((secs.div_euclid(DAY) + 4).rem_euclid(7)) as usize
+}
+
+fn builtin_names() -> String {
+    BUILTIN_WINDOWS.iter().map(|w| w.0).collect::<Vec<_>>().join(", ")
+}
+
+fn invalid(msg: &str) -> DeliveryError {
+    DeliveryError::throw(Kind::InvalidSchedule, Some(msg.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+    use config::Config;
+    use errors::Kind;
+
+    #[test]
+    fn test_parse_at() {
+        // A Saturday
+        let at = parse_at("2024-06-01T22:00Z").unwrap();
+        assert_eq!(1717279200, at);
+        assert_eq!(at, parse_at("2024-06-01T22:00:00Z").unwrap());
+        assert_eq!(at, parse_at("2024-06-01 22:00").unwrap());
+        assert_eq!("2024-06-01T22:00Z", format_time(at));
+        let e = parse_at("tonight").unwrap_err();
+        assert!(assert_enum!(e.kind, Kind::InvalidSchedule));
+    }
+
+    #[test]
+    fn test_window_parse() {
+        let w = Window::parse("mon-thu 22:00-06:00").unwrap();
+        assert_eq!([false, true, true, true, true, false, false], w.days);
+        assert_eq!((22 * 60, 6 * 60), (w.start, w.end));
+        assert_eq!([true, true, false, false, false, true, true],
+                   Window::parse("fri-mon 00:00-24:00").unwrap().days);
+        assert_eq!([true; 7], Window::parse("daily 01:30-02:00").unwrap().days);
+        assert!(Window::parse("mon-thu").is_err());
+        assert!(Window::parse("someday 22:00-06:00").is_err());
+        assert!(Window::parse("mon 25:00-06:00").is_err());
+        assert!(Window::parse("mon 22:00-22:00").is_err());
+    }
+
+    #[test]
+    fn test_window_next() {
+        let weeknights = Window::parse("mon-thu 22:00-06:00").unwrap();
+        let saturday = parse_at("2024-06-01T22:00Z").unwrap();
+        assert_eq!((parse_at("2024-06-03T22:00Z").unwrap(), parse_at("2024-06-04T06:00Z").unwrap()),
+                   weeknights.next(saturday));
+        // Inside the window opened the evening before
+        let tuesday_early = parse_at("2024-06-04T03:00Z").unwrap();
+        assert_eq!((tuesday_early, parse_at("2024-06-04T06:00Z").unwrap()),
+                   weeknights.next(tuesday_early));
+        // Thursday night is the last one of the week
+        let friday_noon = parse_at("2024-06-07T12:00Z").unwrap();
+        assert_eq!(parse_at("2024-06-10T22:00Z").unwrap(), weeknights.next(friday_noon).0);
+    }
+
+    #[test]
+    fn test_named_windows() {
+        let config = Config::default();
+        assert!(Window::named("weeknights", &config).is_ok());
+        let e = Window::named("holidays", &config).unwrap_err();
+        assert!(e.detail.unwrap().contains("use one of weeknights, weekends"));
+
+        let mut config = Config::default();
+        let mut windows = ::std::collections::BTreeMap::new();
+        windows.insert("weeknights".to_string(), "mon-fri 20:00-23:00".to_string());
+        config.delivery_windows = Some(windows);
+        assert_eq!(Window::parse("mon-fri 20:00-23:00").unwrap(),
+                   Window::named("weeknights", &config).unwrap());
+    }
+
+    #[test]
+    fn test_entry_due() {
+        let window = Window::parse("mon-thu 22:00-06:00").unwrap();
+        let (at, until) = plan(parse_at("2024-06-01T22:00Z").unwrap(), Some(&window));
+        let entry = Entry {
+            change: "c1".to_string(), server_url: "https://automate.example.com".to_string(),
+            user: "rob".to_string(), ent: "ent".to_string(), org: "org".to_string(),
+            project: "rocket".to_string(), at: at, until: until,
+            window: Some("mon-thu 22:00-06:00".to_string()), last_error: None,
+            attempts: 0, retry_at: None,
+        };
+        assert_eq!("2024-06-03T22:00Z-2024-06-04T06:00Z", entry.when());
+        assert_eq!(Due::NotYet, entry.due(at - 1));
+        assert_eq!(Due::Now, entry.due(at));
+        assert_eq!(Due::Missed(window.next(until.unwrap())), entry.due(until.unwrap()));
+    }
+
+    #[test]
+    fn test_entry_failed_attempts() {
+        let mut entry = Entry {
+            change: "c1".to_string(), server_url: "https://automate.example.com".to_string(),
+            user: "rob".to_string(), ent: "ent".to_string(), org: "org".to_string(),
+            project: "rocket".to_string(), at: 1000, until: None, window: None,
+            last_error: None, attempts: 0, retry_at: None,
+        };
+        entry.failed("503".to_string(), false, 1000);
+        assert_eq!(Due::NotYet, entry.due(1059));
+        assert_eq!(Due::Now, entry.due(1060));
+        entry.failed("503".to_string(), false, 1060);
+        assert_eq!(Some(1060 + 120), entry.retry_at);
+        for _ in 2..MAX_ATTEMPTS {
+            entry.failed("503".to_string(), false, 2000);
+        }
+        assert_eq!(Due::GaveUp, entry.due(100_000));
+
+        let mut refused = Entry { attempts: 0, retry_at: None, ..entry };
+        refused.failed("412".to_string(), true, 1000);
+        assert!(refused.gave_up());
+    }
+
+    #[test]
+    fn test_schedule_store() {
+        let tmp = TempDir::new("schedule").unwrap();
+        let path = tmp.path().join(".delivery").join("schedule.json");
+        let mut schedule = Schedule::load(&path).unwrap();
+        assert!(schedule.entries.is_empty());
+        let entry = Entry {
+            change: "c1".to_string(), server_url: "https://automate.example.com".to_string(),
+            user: "rob".to_string(), ent: "ent".to_string(), org: "org".to_string(),
+            project: "rocket".to_string(), at: 2000, until: None, window: None,
+            last_error: None, attempts: 0, retry_at: None,
+        };
+        schedule.add(Entry { at: 1000, ..entry.clone() });
+        schedule.add(entry.clone());
+        schedule.add(Entry { change: "c0".to_string(), ..entry.clone() });
+        schedule.save(&path).unwrap();
+
+        let mut loaded = Schedule::load(&path).unwrap();
+        assert_eq!(schedule, loaded);
+        assert_eq!(2, loaded.entries.len());
+        assert_eq!(Some(entry), loaded.remove("c1"));
+        assert_eq!(None, loaded.remove("c1"));
+    }
+}
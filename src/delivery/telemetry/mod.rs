@@ -0,0 +1,329 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+//! Anonymous usage analytics
+//!
+//! Nothing is recorded until `delivery telemetry enable` drops an
+//! `enabled` file in `~/.delivery/telemetry`. From then on every command
+//! appends an event to the `spool.jsonl` next to it: the name of the
+//! command, how long it took, the kind of error it failed with, if any,
+//! and the version of the cli. Nothing that identifies the user, the
+//! server or the project is part of it.
+//!
+//! Once `BATCH_SIZE` events are spooled, a detached `delivery telemetry
+//! flush` posts them to the `telemetry_endpoint` of the cli.toml, so no
+//! command ever waits on it. Without an endpoint the events stay in the
+//! spool, which never keeps more than `MAX_SPOOLED` of them. With
+//! `air_gapped = true` in the cli.toml nothing is recorded nor sent,
+//! whatever the opt-in says.
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use hyper;
+use hyper::header::ContentType;
+use serde_json;
+use config::Config;
+use errors::{DeliveryError, Kind};
+use http::proxy;
+use http::tls::Tls;
+use types::{DeliveryResult, ExitCode};
+use utils;
+
+pub const BATCH_SIZE: usize = 20;
+pub const MAX_SPOOLED: usize = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Event {
+    pub command: String,
+    pub duration_ms: u64,
+    pub result: String,
+    pub version: String,
+}
+
+impl Event {
+    /// The `result` of the event is `ok` for a success, `exit N` for a
+    /// command that exited with N and the kind of the error otherwise.
+    pub fn new(command: &str, duration: Duration, result: &DeliveryResult<ExitCode>,
+               version: &str) -> Event {
+        let result = match *result {
+            Ok(0) => "ok".to_string(),
+            Ok(code) => format!("exit {}", code),
+            Err(ref e) => {
+                let kind = format!("{:?}", e.kind);
+                kind.split('(').next().unwrap_or_default().to_string()
+            }
+        };
+        Event {
+            command: command.to_string(),
+            duration_ms: duration.as_secs() * 1000 + (duration.subsec_nanos() / 1_000_000) as u64,
+            result: result,
+            version: version.to_string(),
+        }
+    }
+}
+
+/// The directory of the opt-in and the spool: `~/.delivery/telemetry`
+pub fn telemetry_dir() -> DeliveryResult<PathBuf> {
+    utils::home_dir(&[".delivery", "telemetry"])
+}
+
+pub fn is_enabled(dir: &Path) -> bool {
+    dir.join("enabled").exists()
+}
+
+pub fn enable(dir: &Path) -> DeliveryResult<()> {
+    try!(utils::mkdir_recursive(dir));
+    try!(File::create(dir.join("enabled")));
+    Ok(())
+}
+
+/// Opt out, dropping whatever was not sent yet
+pub fn disable(dir: &Path) -> DeliveryResult<()> {
+    for name in ["enabled", "spool.jsonl"].iter() {
+        let path = dir.join(name);
+        if path.exists() {
+            try!(fs::remove_file(&path));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the cli.toml forbids any network traffic besides the server
+pub fn air_gapped(config: &Config) -> bool {
+    config.air_gapped.unwrap_or(false)
+}
+
+/// Spool the event of a command that just ran, when the user opted in,
+/// and start sending the batch once it is full. Like the log file, this
+/// is best effort and never makes a command fail.
+pub fn record(event: &Event) {
+    let dir = match telemetry_dir() {
+        Ok(dir) => dir,
+        Err(_) => return
+    };
+    if !is_enabled(&dir) {
+        return
+    }
+    let config = Config::load_config(&utils::cwd()).unwrap_or_default();
+    if air_gapped(&config) {
+        return
+    }
+    let result = spool(&dir, event).and_then(|spooled| {
+        if spooled >= BATCH_SIZE && config.telemetry_endpoint.is_some()
+            && !config.offline.unwrap_or(false) {
+            try!(flush_in_background());
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        debug!("Unable to record the usage analytics: {:?}", e);
+    }
+}
+
+/// Append `event` to the spool in `dir`, keeping the latest
+/// `MAX_SPOOLED` events, and return how many are spooled
+pub fn spool(dir: &Path, event: &Event) -> DeliveryResult<usize> {
+    try!(utils::mkdir_recursive(dir));
+    let mut events = try!(spooled(dir));
+    events.push(event.clone());
+    if events.len() > MAX_SPOOLED {
+        let extra = events.len() - MAX_SPOOLED;
+        events.drain(..extra);
+        try!(write_spool(dir, &events));
+    } else {
+        let mut file = try!(OpenOptions::new().create(true).append(true)
+                                              .open(spool_path(dir)));
+        try!(writeln!(file, "{}", try!(serde_json::to_string(event))));
+    }
+    Ok(events.len())
+}
+
+/// The events waiting in the spool of `dir`, oldest first
+pub fn spooled(dir: &Path) -> DeliveryResult<Vec<Event>> {
+    let path = spool_path(dir);
+    if !path.exists() {
+        return Ok(Vec::new())
+    }
+    let mut content = String::new();
+    try!(try!(File::open(&path)).read_to_string(&mut content));
+    // A line cut short by a crash is not worth failing over.
+    Ok(content.lines().filter_map(|l| serde_json::from_str(l).ok()).collect())
+}
+
+/// Send every spooled event to `endpoint` and empty the spool. The
+/// events are taken out of the spool first so two flushes never send
+/// them twice, and put back when the endpoint can not be reached.
+pub fn flush(dir: &Path, config: &Config, endpoint: &str) -> DeliveryResult<usize> {
+    if air_gapped(config) {
+        let msg = "air_gapped is set in the cli.toml, usage analytics are not sent";
+        return Err(DeliveryError::throw(Kind::TelemetryFailed, Some(msg.to_string())))
+    }
+    try!(config.require_network("sending usage analytics"));
+    let sending = dir.join("sending.jsonl");
+    if !spool_path(dir).exists() {
+        return Ok(0)
+    }
+    try!(fs::rename(spool_path(dir), &sending));
+    let mut content = String::new();
+    try!(try!(File::open(&sending)).read_to_string(&mut content));
+    let events: Vec<Event> = content.lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect();
+    if let Err(e) = post(config, endpoint, &events) {
+        let mut file = try!(OpenOptions::new().create(true).append(true)
+                                              .open(spool_path(dir)));
+        try!(file.write_all(content.as_bytes()));
+        try!(fs::remove_file(&sending));
+        return Err(e)
+    }
+    try!(fs::remove_file(&sending));
+    Ok(events.len())
+}
+
+fn post(config: &Config, endpoint: &str, events: &[Event]) -> DeliveryResult<()> {
+    let url = try!(hyper::Url::parse(endpoint).map_err(|e| {
+        let msg = format!("Invalid telemetry_endpoint {}: {}", endpoint, e);
+        DeliveryError::throw(Kind::TelemetryFailed, Some(msg))
+    }));
+    let proxy = proxy::proxy_for(config, endpoint, url.host_str().unwrap_or_default());
+    let tls = try!(Tls::from_config(config));
+    let client = try!(proxy::client(proxy.as_ref(), &tls));
+    let body = try!(serde_json::to_string(&events));
+    let response = client.post(endpoint)
+        .header(ContentType::json());
+    let response = match proxy::authorization(proxy.as_ref(), &tls, endpoint) {
+        Some(authorization) => response.header(authorization),
+        None => response
+    };
+    let response = response.body(&body).send();
+    match response {
+        Ok(ref response) if response.status.is_success() => Ok(()),
+        Ok(response) => {
+            let msg = format!("{} answered {}", endpoint, response.status);
+            Err(DeliveryError::throw(Kind::TelemetryFailed, Some(msg)))
+        },
+        Err(e) => {
+            let msg = format!("Unable to reach {}: {}", endpoint, e);
+            Err(DeliveryError::throw(Kind::TelemetryFailed, Some(msg)))
+        }
+    }
+}
+
+// Run `delivery telemetry flush` without waiting for it, or keeping
+// its output around.
+fn flush_in_background() -> DeliveryResult<()> {
+    let delivery = try!(env::current_exe());
+    try!(Command::new(delivery)
+         .arg("telemetry").arg("flush")
+         .stdin(Stdio::null())
+         .stdout(Stdio::null())
+         .stderr(Stdio::null())
+         .spawn());
+    Ok(())
+}
+
+fn spool_path(dir: &Path) -> PathBuf {
+    dir.join("spool.jsonl")
+}
+
+fn write_spool(dir: &Path, events: &[Event]) -> DeliveryResult<()> {
+    let mut file = try!(File::create(spool_path(dir)));
+    for event in events.iter() {
+        try!(writeln!(file, "{}", try!(serde_json::to_string(event))));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use tempdir::TempDir;
+    use mockito::{mock, SERVER_URL};
+    use config::Config;
+    use errors::{DeliveryError, Kind};
+    use super::*;
+
+    fn event(command: &str) -> Event {
+        Event::new(command, Duration::from_millis(1500), &Ok(0), "0.0.1")
+    }
+
+    #[test]
+    fn test_event_result_is_the_error_kind() {
+        assert_eq!("ok", event("review").result);
+        assert_eq!(1500, event("review").duration_ms);
+        assert_eq!("exit 2", Event::new("job", Duration::from_secs(1), &Ok(2), "0.0.1").result);
+        let e = Err(DeliveryError::throw(Kind::PhaseFailed(2), None));
+        assert_eq!("PhaseFailed", Event::new("local", Duration::from_secs(1), &e, "0.0.1").result);
+    }
+
+    #[test]
+    fn test_enable_spool_and_disable() {
+        let tmp = TempDir::new("telemetry").unwrap();
+        let dir = tmp.path().join("telemetry");
+        assert!(!is_enabled(&dir));
+        enable(&dir).unwrap();
+        assert!(is_enabled(&dir));
+
+        assert_eq!(1, spool(&dir, &event("review")).unwrap());
+        assert_eq!(2, spool(&dir, &event("diff")).unwrap());
+        assert_eq!(vec![event("review"), event("diff")], spooled(&dir).unwrap());
+
+        disable(&dir).unwrap();
+        assert!(!is_enabled(&dir));
+        assert!(spooled(&dir).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_spool_keeps_the_latest_events() {
+        let tmp = TempDir::new("telemetry").unwrap();
+        for _ in 0..MAX_SPOOLED {
+            spool(tmp.path(), &event("review")).unwrap();
+        }
+        assert_eq!(MAX_SPOOLED, spool(tmp.path(), &event("diff")).unwrap());
+        let events = spooled(tmp.path()).unwrap();
+        assert_eq!(MAX_SPOOLED, events.len());
+        assert_eq!(event("diff"), events[MAX_SPOOLED - 1]);
+    }
+
+    #[test]
+    fn test_flush_posts_and_empties_the_spool() {
+        let tmp = TempDir::new("telemetry").unwrap();
+        spool(tmp.path(), &event("review")).unwrap();
+        let endpoint = format!("{}/telemetry", SERVER_URL);
+        let _m = mock("POST", "/telemetry").with_status(204).create();
+        assert_eq!(1, flush(tmp.path(), &Config::default(), &endpoint).unwrap());
+        assert!(spooled(tmp.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_flush_keeps_the_spool_on_failure() {
+        let tmp = TempDir::new("telemetry").unwrap();
+        spool(tmp.path(), &event("review")).unwrap();
+        let endpoint = format!("{}/telemetry-down", SERVER_URL);
+        let _m = mock("POST", "/telemetry-down").with_status(503).create();
+        assert!(flush(tmp.path(), &Config::default(), &endpoint).is_err());
+        assert_eq!(vec![event("review")], spooled(tmp.path()).unwrap());
+
+        let mut config = Config::default();
+        config.air_gapped = Some(true);
+        assert!(flush(tmp.path(), &config, &endpoint).is_err());
+        assert_eq!(1, spooled(tmp.path()).unwrap().len());
+    }
+}
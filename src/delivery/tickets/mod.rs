@@ -0,0 +1,159 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Issue tracker tickets of a change
+//!
+//! With a `ticket_pattern` regex in the cli.toml (i.e. `[A-Z]+-[0-9]+`
+//! for JIRA), `delivery review` looks for ticket ids in the name of the
+//! feature branch and in the messages of its commits, and lists them
+//! on a `Tickets:` line of the change description. With a
+//! `ticket_link_url` as well, the change is posted there so the
+//! tracker, or a webhook in front of it, can link back to the change.
+
+use hyper;
+use hyper::header::ContentType;
+use regex::Regex;
+use serde_json;
+use config::Config;
+use errors::{DeliveryError, Kind};
+use http::proxy;
+use http::tls::Tls;
+use types::DeliveryResult;
+
+/// What starts the line of the change description listing its tickets
+pub const TICKETS_LABEL: &'static str = "Tickets:";
+
+/// What is posted to the `ticket_link_url`
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TicketLink {
+    pub change_id: String,
+    pub change_url: String,
+    pub tickets: Vec<String>,
+}
+
+/// The ticket ids matching `pattern` in `texts`, in the order they
+/// first show up
+pub fn find(pattern: &str, texts: &[&str]) -> DeliveryResult<Vec<String>> {
+    let re = try!(Regex::new(pattern).map_err(|e| {
+        let msg = format!("Invalid ticket_pattern {}: {}", pattern, e);
+        DeliveryError::throw(Kind::ConfigValidation, Some(msg))
+    }));
+    let mut tickets: Vec<String> = Vec::new();
+    for text in texts.iter() {
+        for (start, end) in re.find_iter(text) {
+            let ticket = &text[start..end];
+            if !tickets.iter().any(|t| t == ticket) {
+                tickets.push(ticket.to_string());
+            }
+        }
+    }
+    Ok(tickets)
+}
+
+/// `description` with a `Tickets:` line listing `tickets`, in place of
+/// the one it had, if any
+pub fn describe(description: &str, tickets: &[String]) -> String {
+    let kept: Vec<&str> = description.lines()
+        .filter(|l| !l.trim_left().starts_with(TICKETS_LABEL))
+        .collect();
+    let kept = kept.join("\n");
+    let kept = kept.trim_right();
+    if tickets.is_empty() {
+        return kept.to_string()
+    }
+    let line = format!("{} {}", TICKETS_LABEL, tickets.join(", "));
+    if kept.is_empty() {
+        line
+    } else {
+        format!("{}\n\n{}", kept, line)
+    }
+}
+
+/// Post the change and its tickets to `url`
+pub fn link(config: &Config, url: &str, ticket_link: &TicketLink) -> DeliveryResult<()> {
+    let parsed = try!(hyper::Url::parse(url).map_err(|e| {
+        let msg = format!("Invalid ticket_link_url {}: {}", url, e);
+        DeliveryError::throw(Kind::TicketLinkFailed, Some(msg))
+    }));
+    let proxy = proxy::proxy_for(config, url, parsed.host_str().unwrap_or_default());
+    let tls = try!(Tls::from_config(config));
+    let client = try!(proxy::client(proxy.as_ref(), &tls));
+    let body = try!(serde_json::to_string(ticket_link));
+    let response = client.post(url)
+        .header(ContentType::json());
+    let response = match proxy::authorization(proxy.as_ref(), &tls, url) {
+        Some(authorization) => response.header(authorization),
+        None => response
+    };
+    let response = response.body(&body).send();
+    match response {
+        Ok(ref response) if response.status.is_success() => Ok(()),
+        Ok(response) => {
+            let msg = format!("{} answered {}", url, response.status);
+            Err(DeliveryError::throw(Kind::TicketLinkFailed, Some(msg)))
+        },
+        Err(e) => {
+            let msg = format!("Unable to reach {}: {}", url, e);
+            Err(DeliveryError::throw(Kind::TicketLinkFailed, Some(msg)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::{mock, SERVER_URL};
+    use config::Config;
+    use errors::Kind;
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        let texts = ["PROJ-12-fix-login", "Fix login\n\nSee PROJ-12 and OPS-7.", "Tidy up"];
+        assert_eq!(vec!["PROJ-12".to_string(), "OPS-7".to_string()],
+                   find("[A-Z]+-[0-9]+", &texts).unwrap());
+        assert!(find("[A-Z]+-[0-9]+", &["no tickets here"]).unwrap().is_empty());
+        assert!(assert_enum!(find("[A-Z", &texts).unwrap_err().kind, Kind::ConfigValidation));
+    }
+
+    #[test]
+    fn test_describe() {
+        let tickets = vec!["PROJ-12".to_string(), "OPS-7".to_string()];
+        assert_eq!("Tickets: PROJ-12, OPS-7", describe("", &tickets));
+        assert_eq!("Fixes the login.\n\nTickets: PROJ-12, OPS-7",
+                   describe("Fixes the login.\n", &tickets));
+        assert_eq!("Fixes the login.\n\nTickets: PROJ-12, OPS-7",
+                   describe("Fixes the login.\n\nTickets: PROJ-12", &tickets));
+        assert_eq!("Fixes the login.", describe("Fixes the login.\n\nTickets: PROJ-12", &[]));
+    }
+
+    #[test]
+    fn test_link() {
+        let ticket_link = TicketLink {
+            change_id: "abc".to_string(),
+            change_url: "https://automate.example.com/e/ent/#/changes/abc".to_string(),
+            tickets: vec!["PROJ-12".to_string()],
+        };
+        let url = format!("{}/tickets/link", SERVER_URL);
+        let _m = mock("POST", "/tickets/link").with_status(201).create();
+        assert!(link(&Config::default(), &url, &ticket_link).is_ok());
+
+        let url = format!("{}/tickets/down", SERVER_URL);
+        let _m = mock("POST", "/tickets/down").with_status(502).create();
+        let e = link(&Config::default(), &url, &ticket_link).unwrap_err();
+        assert!(assert_enum!(e.kind, Kind::TicketLinkFailed));
+    }
+}
@@ -24,25 +24,39 @@
 //! and will immediately rewrite the backing file. Find an existing
 //! token using `lookup`.
 //!
-use std::io;
+//! When each token was written is kept next to the store, in
+//! `api-tokens.issued`, so older clis keep reading the store itself.
+//!
 use std::io::prelude::*;
 use std::io::BufReader;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs::{File, OpenOptions};
 use std::collections::BTreeMap;
-use errors::DeliveryError;
+use errors::{DeliveryError, Kind};
 use types::DeliveryResult;
 use utils;
 use utils::path_join_many::PathJoinMany;
 use config::Config;
 use http;
 use utils::say::{sayln,say};
+use utils::prompt::Prompt;
 use utils::{home_dir, env_variable};
 use rpassword;
+use time;
+
+/// A token of the store and what it authenticates
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredToken {
+    pub server: String,
+    pub ent: String,
+    pub user: String,
+    pub token: String,
+}
 
 #[derive(Debug)]
 pub struct TokenStore {
     tokens: BTreeMap<String, String>,
+    issued: BTreeMap<String, i64>,
     path: PathBuf
 }
 
@@ -54,9 +68,19 @@ impl TokenStore {
         TokenStore::from_file(&token_path)
     }
 
+    /// The store of the configured `token_file`, the one in the home
+    /// directory when there is none.
+    pub fn for_config(config: &Config) -> DeliveryResult<TokenStore> {
+        match config.token_file {
+            Some(ref f) => TokenStore::from_file(&PathBuf::from(f)),
+            None => TokenStore::from_home()
+        }
+    }
+
     pub fn from_file(path: &PathBuf) -> Result<TokenStore, DeliveryError> {
         let tokens = try!(TokenStore::read_config(&path));
-        let tstore = TokenStore {path: path.clone(), tokens: tokens};
+        let issued = TokenStore::read_issued(&TokenStore::issued_path(&path));
+        let tstore = TokenStore {path: path.clone(), tokens: tokens, issued: issued};
         Ok(tstore)
     }
 
@@ -70,6 +94,30 @@ impl TokenStore {
         self.tokens.get(&key)
     }
 
+    /// How many seconds ago the token was written to the store, `None`
+    /// when it was written by a cli that did not record it.
+    pub fn token_age(&self, server: &str, ent: &str, user: &str) -> Option<i64> {
+        let key = TokenStore::key(server, ent, user);
+        self.issued.get(&key).map(|at| time::get_time().sec - at)
+    }
+
+    /// Every token of the store, in key order
+    pub fn stored_tokens(&self) -> Vec<StoredToken> {
+        self.tokens.iter().filter_map(|(key, token)| {
+            let parts: Vec<&str> = key.splitn(3, ',').collect();
+            if parts.len() == 3 {
+                Some(StoredToken {
+                    server: parts[0].to_string(),
+                    ent: parts[1].to_string(),
+                    user: parts[2].to_string(),
+                    token: token.clone(),
+                })
+            } else {
+                None
+            }
+        }).collect()
+    }
+
     pub fn write_token(&mut self,
                        server: &str,
                        ent: &str,
@@ -77,10 +125,10 @@ impl TokenStore {
                        token: &str) -> Result<Option<String>, DeliveryError> {
 
         let result = self.set_token(server, ent, user, token);
-        match self.write_config() {
-            Ok(_) => Ok(result),
-            Err(e) => Err(e)
-        }
+        self.issued.insert(TokenStore::key(server, ent, user), time::get_time().sec);
+        try!(self.write_config());
+        try!(self.write_issued());
+        Ok(result)
     }
 
     pub fn verify_token(config: &Config) -> DeliveryResult<String>  {
@@ -123,15 +171,22 @@ impl TokenStore {
             None => try!(http::saml::is_enabled(&config)),
         };
         let token = if saml {
-            let mut enter = String::new();
-            say("red", "Press Enter to open a browser window to retrieve a new token.");
-            try!(io::stdin().read_line(&mut enter));
-            sayln("white", "Launching browser..");
-            try!(TokenStore::initate_saml_auth(&config));
-            let mut token = String::new();
-            say("white", "Enter token: ");
-            try!(io::stdin().read_line(&mut token));
-            token.trim().to_string()
+            match utils::open::skip_reason(config.no_open) {
+                Some(why) => {
+                    sayln("white", &format!("Not launching a browser: {}.", why));
+                    sayln("white", &format!("Open {} to retrieve a new token.",
+                                            try!(TokenStore::web_token_url(&config))));
+                },
+                None => {
+                    try!(Prompt::new("Press Enter to open a browser window to retrieve \
+                                      a new token")
+                         .default("")
+                         .input());
+                    sayln("white", "Launching browser..");
+                    try!(TokenStore::initate_saml_auth(&config));
+                }
+            }
+            try!(Prompt::new("Enter token").secret().input())
         } else {
             // For automation purposes if the end-user provides the environment
             // variable `AUTOMATE_PASSWORD` we will consume it instead of prompting
@@ -152,22 +207,69 @@ impl TokenStore {
         Ok(token)
     }
 
+    /// Replace the stored token of the configured user with a new one.
+    /// With basic authentication the password comes from
+    /// `AUTOMATE_PASSWORD` when it is set, so the token renews without
+    /// a prompt; otherwise we ask for it, unless we may not interact.
+    pub fn renew_token(config: &Config) -> DeliveryResult<String> {
+        let saml = match config.saml {
+            Some(b) => b,
+            None => try!(http::saml::is_enabled(&config)),
+        };
+        let interactive = !config.non_interactive.unwrap_or(false);
+        match env_variable("AUTOMATE_PASSWORD") {
+            Some(ref pass) if !saml => {
+                let token = try!(http::token::request(&config, pass));
+                let mut tstore = try!(TokenStore::for_config(&config));
+                try!(tstore.write_token(&try!(config.api_host_and_port()),
+                                        &try!(config.enterprise()),
+                                        &try!(config.user()), &token));
+                sayln("green", &format!("saved API token to: {}", tstore.path().display()));
+                Ok(token)
+            },
+            _ if interactive => TokenStore::request_token(&config),
+            _ => {
+                let msg = "Unable to renew the API token due to --no-interactive flag. \
+                           Set AUTOMATE_PASSWORD or run `delivery token --renew`".to_string();
+                Err(DeliveryError::throw(Kind::NoToken, Some(msg)))
+            }
+        }
+    }
+
+    /// Write a token issued for `user` to `<dir>/<user>/api-tokens`, a
+    /// token store a build node can point its `token_file` to.
+    pub fn write_issued_token(dir: &Path, server: &str, ent: &str,
+                              user: &str, token: &str) -> DeliveryResult<PathBuf> {
+        if user.is_empty() || user == "." || user == ".." || user.contains('/')
+            || user.contains('\\') {
+            let msg = format!("'{}' is not a user name a token can be written for", user);
+            return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+        let user_dir = dir.join(user);
+        try!(utils::mkdir_recursive(&user_dir));
+        let path = user_dir.join("api-tokens");
+        // Private before the token goes in, also when the store existed
+        try!(utils::create_private_file(&path));
+        try!(utils::chmod(&path, "0600"));
+        let mut tstore = try!(TokenStore::from_file(&path));
+        try!(tstore.write_token(server, ent, user, token));
+        Ok(path)
+    }
+
     fn web_token_url(config: &Config) -> Result<String, DeliveryError> {
-        let host = try!(config.api_host_and_port());
+        let base_url = try!(config.server_base_url());
         let ent = try!(config.enterprise());
-        let proto = try!(config.api_protocol());
         let path = "#/dashboard?token";
-        Ok(TokenStore::format_web_token_url(&host, &ent, &proto, &path))
+        Ok(TokenStore::format_web_token_url(&base_url, &ent, &path))
     }
 
-    fn format_web_token_url(host: &str, ent: &str, proto: &str, path: &str) -> String {
-        format!("{}://{}/e/{}/{}",
-            proto, host, ent, path)
+    fn format_web_token_url(base_url: &str, ent: &str, path: &str) -> String {
+        format!("{}/e/{}/{}", base_url, ent, path)
     }
 
     fn initate_saml_auth(config: &Config) -> Result<(), DeliveryError> {
         let url = try!(TokenStore::web_token_url(&config));
-        utils::open::item(&url)
+        utils::open::browse(&url, config.browser.as_ref().map(|b| b.as_ref()))
     }
 
     fn key(server: &str, ent: &str, user: &str) -> String {
@@ -190,6 +292,34 @@ impl TokenStore {
         Ok(())
     }
 
+    fn issued_path(path: &PathBuf) -> PathBuf {
+        let mut name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+        name.push(".issued");
+        path.with_file_name(name)
+    }
+
+    fn write_issued(&self) -> Result<(), DeliveryError> {
+        let mut file = try!(File::create(TokenStore::issued_path(&self.path)));
+        for (k, v) in self.issued.iter() {
+            try!(file.write_all(format!("{}|{}\n", k, v).as_bytes()));
+        }
+        Ok(())
+    }
+
+    // Issue times are only a diagnostic aid, an unreadable file is
+    // the same as no file.
+    fn read_issued(path: &PathBuf) -> BTreeMap<String, i64> {
+        let content = utils::read_file(path).unwrap_or_default();
+        content.lines().filter_map(|line| {
+            let mut items = line.trim().rsplitn(2, '|');
+            let at = items.next().and_then(|a| a.parse::<i64>().ok());
+            match (items.next(), at) {
+                (Some(key), Some(at)) => Some((key.to_string(), at)),
+                _ => None
+            }
+        }).collect()
+    }
+
     fn read_config(path: &PathBuf) -> Result<BTreeMap<String, String>, DeliveryError> {
         let mut opener = OpenOptions::new();
         opener.create(true);
@@ -217,6 +347,35 @@ impl TokenStore {
 
 }
 
+/// Parse a token lifetime such as `3600`, `90m`, `12h` or `30d` into
+/// seconds. An empty value leaves the lifetime to the server.
+pub fn parse_ttl(value: &str) -> DeliveryResult<Option<u64>> {
+    if value.is_empty() {
+        return Ok(None)
+    }
+    let (number, unit) = match value.char_indices().last() {
+        Some((i, c)) if c.is_alphabetic() => (&value[..i], c),
+        _ => (value, 's')
+    };
+    let multiplier = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => 0
+    };
+    match number.parse::<u64>() {
+        Ok(n) if n > 0 && multiplier > 0 && n.checked_mul(multiplier).is_some() => {
+            Ok(Some(n * multiplier))
+        },
+        _ => {
+            let msg = format!("--ttl must be a duration like 3600, 90m, 12h or 30d, \
+                              got '{}'", value);
+            Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -245,6 +404,61 @@ mod tests {
         let mut content = String::new();
         assert_eq!(true, f.read_to_string(&mut content).is_ok());
         assert_eq!("127.0.0.1,acme,bob|beefbeef\n", content);
+        assert_eq!(vec![StoredToken { server: "127.0.0.1".to_string(),
+                                      ent: "acme".to_string(),
+                                      user: "bob".to_string(),
+                                      token: "beefbeef".to_string() }],
+                   tstore.stored_tokens());
+        let age = tstore.token_age("127.0.0.1", "acme", "bob").unwrap();
+        assert!(age >= 0 && age < 60);
+        assert_eq!(None, tstore.token_age("127.0.0.1", "acme", "alice"));
+        let reloaded = TokenStore::from_file(&tfile).unwrap();
+        assert!(reloaded.token_age("127.0.0.1", "acme", "bob").is_some());
+    }
+
+    #[test]
+    fn parse_ttl_test() {
+        assert_eq!(None, parse_ttl("").unwrap());
+        assert_eq!(Some(3600), parse_ttl("3600").unwrap());
+        assert_eq!(Some(5400), parse_ttl("90m").unwrap());
+        assert_eq!(Some(30 * 24 * 60 * 60), parse_ttl("30d").unwrap());
+        assert!(parse_ttl("0h").is_err());
+        assert!(parse_ttl("2w").is_err());
+        assert!(parse_ttl("soon").is_err());
+        assert!(parse_ttl("5é").is_err());
+        assert!(parse_ttl("18446744073709551615d").is_err());
+    }
+
+    #[test]
+    fn write_issued_token_test() {
+        let tempdir = TempDir::new("issued").unwrap();
+        let path = TokenStore::write_issued_token(tempdir.path(), "automate.example.com",
+                                                  "acme", "builder", "cafe").unwrap();
+        assert_eq!(tempdir.path().join_many(&["builder", "api-tokens"]), path);
+        let tstore = TokenStore::from_file(&path).unwrap();
+        assert_eq!(Some(&"cafe".to_string()),
+                   tstore.lookup("automate.example.com", "acme", "builder"));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn write_issued_token_is_private_test() {
+        use std::os::unix::fs::PermissionsExt;
+        let tempdir = TempDir::new("issued").unwrap();
+        let path = TokenStore::write_issued_token(tempdir.path(), "automate.example.com",
+                                                  "acme", "builder", "cafe").unwrap();
+        let mode = path.metadata().unwrap().permissions().mode();
+        assert_eq!(0o600, mode & 0o777);
+    }
+
+    #[test]
+    fn write_issued_token_rejects_paths_test() {
+        let tempdir = TempDir::new("issued").unwrap();
+        for user in &["..", "../other", "a/b", ""] {
+            assert!(TokenStore::write_issued_token(tempdir.path(), "automate.example.com",
+                                                   "acme", user, "cafe").is_err());
+        }
+        assert!(!tempdir.path().join("api-tokens").exists());
     }
 
     #[test]
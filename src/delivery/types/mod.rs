@@ -20,3 +20,7 @@ use std::result;
 
 pub type DeliveryResult<T> = result::Result<T, DeliveryError>;
 pub type ExitCode = i32;
+
+pub mod names;
+
+pub use self::names::{OrgName, ProjectName, PipelineName, ChangeId};
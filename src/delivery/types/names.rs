@@ -0,0 +1,160 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! The names of the things a Delivery server keeps
+//!
+//! Organizations, projects, pipelines and changes are all plain strings
+//! on the wire, which makes `create_pipeline(org, proj, pipe)` easy to
+//! call with its arguments swapped. Each gets a type of its own here,
+//! checked when it is built so a bad name is reported before anything
+//! is sent to the server. They deref to `str` for everything else.
+
+use std::fmt;
+use std::ops::Deref;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+
+const MAX_LENGTH: usize = 255;
+
+macro_rules! name_type {
+    ($name:ident, $what:expr, $check:ident) => (
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn new(name: &str) -> DeliveryResult<$name> {
+                match $check(name) {
+                    None => Ok($name(name.to_string())),
+                    Some(why) => Err(DeliveryError::throw(Kind::InvalidName, Some(format!(
+                        "'{}' is not a valid {} name: {}", name, $what, why
+                    ))))
+                }
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+    )
+}
+
+name_type!(OrgName, "organization", check_name);
+name_type!(ProjectName, "project", check_name);
+name_type!(PipelineName, "pipeline", check_pipeline);
+name_type!(ChangeId, "change id", check_change_id);
+
+// Organizations and projects end up in urls and paths of the server
+fn check_name(name: &str) -> Option<&'static str> {
+    if let Some(why) = check_length(name) {
+        return Some(why)
+    }
+    if name == "." || name == ".." {
+        return Some("it is a relative path")
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.') {
+        return Some("use letters, digits, '_', '-' and '.' only")
+    }
+    None
+}
+
+// Pipelines are git branches, so they follow the rules of git for those
+fn check_pipeline(name: &str) -> Option<&'static str> {
+    if let Some(why) = check_length(name) {
+        return Some(why)
+    }
+    if name.chars().any(|c| c.is_whitespace() || c.is_control()
+                        || "~^:?*[\\".contains(c)) {
+        return Some("git branch names have no spaces nor any of ~^:?*[\\")
+    }
+    if name.contains("..") || name.contains("@{") || name.contains("//") {
+        return Some("git branch names have no '..', '@{' nor '//'")
+    }
+    if name.starts_with('-') || name.starts_with('/') || name.ends_with('/')
+        || name.ends_with('.') || name.ends_with(".lock") {
+        return Some("git branch names do not start with '-' or '/', nor end with \
+                     '/', '.' or '.lock'")
+    }
+    None
+}
+
+fn check_change_id(id: &str) -> Option<&'static str> {
+    if let Some(why) = check_length(id) {
+        return Some(why)
+    }
+    if !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Some("use letters, digits and '-' only")
+    }
+    None
+}
+
+fn check_length(name: &str) -> Option<&'static str> {
+    if name.is_empty() {
+        Some("it is empty")
+    } else if name.len() > MAX_LENGTH {
+        Some("it is longer than 255 characters")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use errors::Kind;
+
+    #[test]
+    fn test_names() {
+        assert_eq!("delivery-cli", &*ProjectName::new("delivery-cli").unwrap());
+        assert!(OrgName::new("Chef_Software.1").is_ok());
+        let e = OrgName::new("my org").unwrap_err();
+        assert!(assert_enum!(e.kind, Kind::InvalidName));
+        assert!(e.detail.unwrap().contains("'my org' is not a valid organization name"));
+        assert!(ProjectName::new("").is_err());
+        assert!(ProjectName::new("..").is_err());
+        assert!(ProjectName::new(&"a".repeat(256)).is_err());
+    }
+
+    #[test]
+    fn test_pipeline_names() {
+        assert!(PipelineName::new("master").is_ok());
+        assert!(PipelineName::new("release/1.x").is_ok());
+        assert!(PipelineName::new("feature..x").is_err());
+        assert!(PipelineName::new("-master").is_err());
+        assert!(PipelineName::new("master.lock").is_err());
+        assert!(PipelineName::new("a b").is_err());
+    }
+
+    #[test]
+    fn test_change_ids() {
+        assert!(ChangeId::new("4f7bc2a0-38b5-4a0e-9bd7-a9c4ba1c6b90").is_ok());
+        assert!(ChangeId::new("../admin").is_err());
+    }
+}
@@ -0,0 +1,147 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// JSON filters
+//
+// `delivery api --filter` picks what it prints out of the response,
+// with the common subset of jq and JMESPath paths: `.changes[0].id`,
+// `changes[0].id`, and `[]` or `[*]` to apply the rest of the path to
+// every item of a list (or every value of an object), i.e.
+// `.[].title` or `[*].title`. Items missing the rest of the path are
+// left out of the list, as JMESPath does.
+
+use serde_json::Value;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    Each,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    segments: Vec<Segment>,
+}
+
+impl Filter {
+    pub fn parse(expression: &str) -> DeliveryResult<Filter> {
+        let expression = expression.trim();
+        let mut segments = Vec::new();
+        let mut rest = if expression.starts_with('.') { &expression[1..] } else { expression };
+        while !rest.is_empty() {
+            if rest.starts_with('[') {
+                let end = match rest.find(']') {
+                    Some(end) => end,
+                    None => return Err(invalid(expression, "a '[' is never closed"))
+                };
+                segments.push(match rest[1..end].trim() {
+                    "" | "*" => Segment::Each,
+                    index => match index.parse::<usize>() {
+                        Ok(i) => Segment::Index(i),
+                        Err(_) => return Err(invalid(expression, &format!("'{}' is not an index",
+                                                                          index)))
+                    }
+                });
+                rest = &rest[end + 1..];
+            } else {
+                let end = rest.find(|c| c == '.' || c == '[').unwrap_or(rest.len());
+                if end == 0 {
+                    return Err(invalid(expression, "a field name is missing"))
+                }
+                segments.push(Segment::Key(rest[..end].to_string()));
+                rest = &rest[end..];
+            }
+            if rest.starts_with('.') {
+                rest = &rest[1..];
+                if rest.is_empty() {
+                    return Err(invalid(expression, "a field name is missing"))
+                }
+            }
+        }
+        Ok(Filter { segments: segments })
+    }
+
+    /// What the filter picks out of `value`, `null` when nothing matches
+    pub fn apply(&self, value: &Value) -> Value {
+        apply(value, &self.segments)
+    }
+}
+
+fn apply(value: &Value, segments: &[Segment]) -> Value {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => return value.clone()
+    };
+    match (segment, value) {
+        (&Segment::Key(ref key), &Value::Object(ref map)) => {
+            map.get(key).map(|v| apply(v, rest)).unwrap_or(Value::Null)
+        },
+        (&Segment::Index(i), &Value::Array(ref items)) => {
+            items.get(i).map(|v| apply(v, rest)).unwrap_or(Value::Null)
+        },
+        (&Segment::Each, &Value::Array(ref items)) => each(items.iter(), rest),
+        (&Segment::Each, &Value::Object(ref map)) => each(map.values(), rest),
+        _ => Value::Null
+    }
+}
+
+fn each<'v, I: Iterator<Item = &'v Value>>(items: I, rest: &[Segment]) -> Value {
+    Value::Array(items.map(|v| apply(v, rest)).filter(|v| *v != Value::Null).collect())
+}
+
+fn invalid(expression: &str, why: &str) -> DeliveryError {
+    DeliveryError::throw(Kind::OptionConstraint,
+                         Some(format!("Invalid filter '{}': {}", expression, why)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let response = json!({
+            "changes": [
+                {"id": "8f1a", "title": "Add beans", "stage": {"name": "verify"}},
+                {"id": "91c0", "stage": {"name": "build"}}
+            ],
+            "count": 2
+        });
+        let filter = |e: &str| Filter::parse(e).unwrap().apply(&response);
+        assert_eq!(json!(2), filter(".count"));
+        assert_eq!(json!("8f1a"), filter(".changes[0].id"));
+        assert_eq!(json!("build"), filter("changes[1].stage.name"));
+        assert_eq!(json!(["Add beans"]), filter(".changes[].title"));
+        assert_eq!(json!(["verify", "build"]), filter("changes[*].stage.name"));
+        assert_eq!(Value::Null, filter(".changes[5].id"));
+        assert_eq!(Value::Null, filter(".count.nope"));
+        assert_eq!(response, filter("."));
+        assert_eq!(json!([[{"id": "8f1a", "title": "Add beans", "stage": {"name": "verify"}},
+                           {"id": "91c0", "stage": {"name": "build"}}], 2]),
+                   filter(".[]"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Filter::parse(".changes[0").is_err());
+        assert!(Filter::parse(".changes[x]").is_err());
+        assert!(Filter::parse(".changes..id").is_err());
+        assert!(Filter::parse(".changes.").is_err());
+    }
+}
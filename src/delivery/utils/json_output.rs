@@ -0,0 +1,174 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+// Machine readable output
+//
+// With the global `--json` flag, what commands `say` is held back and a
+// single JSON document describing the run is printed when the command
+// exits instead. Commands add what they know in a structured way (the
+// resources they created, the steps they skipped, their results) and
+// everything they would have printed is kept, without colors, as
+// `messages`, so even commands that add nothing get usable output.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde::Serialize;
+use serde_json::{self, Value};
+use errors::DeliveryError;
+use types::ExitCode;
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+static DOCUMENT: Mutex<Option<Document>> = Mutex::new(None);
+
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct Resource {
+    pub kind: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct Skipped {
+    pub step: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct ErrorDetails {
+    pub message: String,
+    pub detail: Option<String>,
+}
+
+/// Everything `--json` prints about a run
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct Document {
+    pub command: String,
+    pub exit_code: ExitCode,
+    pub created: Vec<Resource>,
+    pub skipped: Vec<Skipped>,
+    pub result: BTreeMap<String, Value>,
+    pub messages: Vec<String>,
+    pub error: Option<ErrorDetails>,
+    #[serde(skip_serializing)]
+    pending: String,
+}
+
+impl Document {
+    /// Keep printed text as messages, one per line
+    pub fn add_text(&mut self, text: &str) {
+        self.pending.push_str(text);
+        while let Some(end) = self.pending.find('\n') {
+            let line: String = self.pending.drain(..end + 1).collect();
+            let line = line.trim_right();
+            if !line.is_empty() {
+                self.messages.push(line.to_string());
+            }
+        }
+    }
+
+    pub fn to_json(&mut self) -> String {
+        let rest = self.pending.trim_right().to_string();
+        self.pending.clear();
+        if !rest.is_empty() {
+            self.messages.push(rest);
+        }
+        serde_json::to_string_pretty(&self).unwrap_or_default()
+    }
+}
+
+pub fn turn_on() {
+    JSON_OUTPUT.store(true, Ordering::Relaxed);
+}
+
+/// Whether `--json` was given
+pub fn enabled() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+fn update<F>(f: F) where F: FnOnce(&mut Document) {
+    if enabled() {
+        let mut doc = DOCUMENT.lock().unwrap_or_else(|e| e.into_inner());
+        f(doc.get_or_insert_with(Document::default));
+    }
+}
+
+/// Record text that would have been printed
+pub fn add_text(text: &str) {
+    update(|doc| doc.add_text(text));
+}
+
+/// Record a resource the command created, i.e. `created("pipeline", "master")`
+pub fn created(kind: &str, name: &str) {
+    update(|doc| doc.created.push(Resource { kind: kind.to_string(), name: name.to_string() }));
+}
+
+/// Record a step the command did not need to run, and why
+pub fn skipped(step: &str, reason: &str) {
+    update(|doc| doc.skipped.push(Skipped { step: step.to_string(),
+                                            reason: reason.to_string() }));
+}
+
+/// Record a result of the command under `key`
+pub fn set<T: Serialize>(key: &str, value: &T) {
+    let value = serde_json::to_value(value).unwrap_or(Value::Null);
+    update(|doc| { doc.result.insert(key.to_string(), value); });
+}
+
+/// Print the document of the run, if `--json` was given
+pub fn finish(command: &str, exit_code: ExitCode, error: Option<&DeliveryError>) {
+    if !enabled() {
+        return
+    }
+    let mut doc = DOCUMENT.lock().unwrap_or_else(|e| e.into_inner())
+        .take().unwrap_or_default();
+    doc.command = command.to_string();
+    doc.exit_code = exit_code;
+    doc.error = error.map(|e| ErrorDetails { message: format!("{}", e), detail: e.detail() });
+    println!("{}", doc.to_json());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_messages() {
+        let mut doc = Document::default();
+        doc.add_text("Chef Delivery\n");
+        doc.add_text("Creating ");
+        doc.add_text("pipeline...\n\n");
+        doc.add_text("done");
+        doc.to_json();
+        assert_eq!(vec!["Chef Delivery", "Creating pipeline...", "done"], doc.messages);
+    }
+
+    #[test]
+    fn test_document_to_json() {
+        let mut doc = Document { command: "init".to_string(), ..Document::default() };
+        doc.created.push(Resource { kind: "project".to_string(), name: "frijol".to_string() });
+        doc.skipped.push(Skipped { step: "pipeline".to_string(),
+                                   reason: "already exists".to_string() });
+        let json: Value = serde_json::from_str(&doc.to_json()).unwrap();
+        assert_eq!(json["command"], "init");
+        assert_eq!(json["exit_code"].as_i64(), Some(0));
+        assert_eq!(json["created"][0]["name"], "frijol");
+        assert_eq!(json["skipped"][0]["reason"], "already exists");
+        assert_eq!(json["error"], Value::Null);
+        assert!(json.get("pending").is_none());
+    }
+}
@@ -0,0 +1,296 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Resource limits of a phase run
+//!
+//! The runners give each phase the limits its project.toml declares:
+//! a timeout, enforced here by killing the phase once it runs out, and
+//! on unix a memory and a CPU time limit, set as rlimits of the phase
+//! process before it starts.
+
+use std::fmt;
+use std::process::{Command, ExitStatus, Output};
+use std::thread;
+use std::time::{Duration, Instant};
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+
+// Seconds a phase gets to wrap up between SIGXCPU and SIGKILL
+const CPU_GRACE: u64 = 5;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Limits {
+    /// Wall clock seconds
+    pub timeout: Option<u64>,
+    /// Bytes of address space
+    pub memory: Option<u64>,
+    /// Seconds of CPU time
+    pub cpu: Option<u64>,
+}
+
+impl Limits {
+    pub fn parse(timeout: Option<&str>, memory: Option<&str>,
+                 cpu: Option<&str>) -> DeliveryResult<Limits> {
+        Ok(Limits {
+            timeout: match timeout { Some(t) => Some(try!(parse_duration(t))), None => None },
+            memory: match memory { Some(m) => Some(try!(parse_size(m))), None => None },
+            cpu: match cpu { Some(c) => Some(try!(parse_duration(c))), None => None },
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Limits::default()
+    }
+
+    /// The limits of phases run one after the other by the same
+    /// process: their times add up, and the memory is the largest one.
+    /// A phase without a limit lifts it for all of them.
+    pub fn then(&self, next: &Limits) -> Limits {
+        Limits {
+            timeout: self.timeout.and_then(|a| next.timeout.map(|b| a + b)),
+            memory: self.memory.and_then(|a| next.memory.map(|b| a.max(b))),
+            cpu: self.cpu.and_then(|a| next.cpu.map(|b| a + b)),
+        }
+    }
+}
+
+impl fmt::Display for Limits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut limits = vec![];
+        if let Some(timeout) = self.timeout {
+            limits.push(format!("timeout {}s", timeout));
+        }
+        if let Some(memory) = self.memory {
+            limits.push(format!("memory {} bytes", memory));
+        }
+        if let Some(cpu) = self.cpu {
+            limits.push(format!("cpu {}s", cpu));
+        }
+        write!(f, "{}", limits.join(", "))
+    }
+}
+
+/// Parse a duration such as `90`, `90s`, `30m` or `2h` into seconds
+pub fn parse_duration(value: &str) -> DeliveryResult<u64> {
+    let (number, unit) = split_unit(value.trim());
+    let multiplier = match unit {
+        None | Some('s') => 1,
+        Some('m') => 60,
+        Some('h') => 60 * 60,
+        _ => return Err(invalid_limit(&format!("'{}' is not a duration like 90s, 30m or 2h",
+                                               value)))
+    };
+    match number.parse::<u64>().ok().and_then(|n| n.checked_mul(multiplier)) {
+        Some(n) if n > 0 => Ok(n),
+        _ => Err(invalid_limit(&format!("'{}' is not a duration like 90s, 30m or 2h", value)))
+    }
+}
+
+/// Parse a size such as `1048576`, `512K`, `512M` or `2G` into bytes
+pub fn parse_size(value: &str) -> DeliveryResult<u64> {
+    let value_upper = value.trim().to_uppercase();
+    let (number, unit) = split_unit(value_upper.trim_right_matches('B'));
+    let multiplier = match unit {
+        Some('K') => 1 << 10,
+        Some('M') => 1 << 20,
+        Some('G') => 1 << 30,
+        _ => 1
+    };
+    match number.parse::<u64>().ok().and_then(|n| n.checked_mul(multiplier)) {
+        Some(n) if n > 0 => Ok(n),
+        _ => Err(invalid_limit(&format!("'{}' is not a size like 512M or 2G", value)))
+    }
+}
+
+// The number and the unit letter of `value`, without a unit letter when
+// it ends with a digit.
+fn split_unit(value: &str) -> (&str, Option<char>) {
+    match value.char_indices().last() {
+        Some((i, c)) if !c.is_ascii_digit() => (&value[..i], Some(c)),
+        _ => (value, None)
+    }
+}
+
+/// Run the `command` of `phase` within `limits` and wait for it. The
+/// output of the command must be inherited, it is not captured. A
+/// phase stopped by one of its limits is an error naming the limit.
+pub fn run(command: &mut Command, phase: &str, limits: &Limits) -> DeliveryResult<Output> {
+    set_rlimits(command, limits);
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            let msg = format!("failed to execute the {} phase: {}", phase, e);
+            return Err(DeliveryError::throw(Kind::FailedToExecute, Some(msg)))
+        }
+    };
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = try!(child.try_wait()) {
+            break status
+        }
+        if let Some(timeout) = limits.timeout {
+            if started.elapsed() >= Duration::from_secs(timeout) {
+                try!(child.kill());
+                try!(child.wait());
+                return Err(limit_error(&format!("The {} phase was killed after running \
+                                                for {}s, its timeout", phase, timeout)))
+            }
+        }
+        thread::sleep(Duration::from_millis(200));
+    };
+    if let Some(msg) = exceeded_limit(&status, phase, limits) {
+        return Err(limit_error(&msg))
+    }
+    Ok(Output { status: status, stdout: vec![], stderr: vec![] })
+}
+
+fn invalid_limit(msg: &str) -> DeliveryError {
+    DeliveryError::throw(Kind::OptionConstraint, Some(msg.to_string()))
+}
+
+fn limit_error(msg: &str) -> DeliveryError {
+    DeliveryError::throw(Kind::PhaseLimitExceeded, Some(msg.to_string()))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn set_rlimits(command: &mut Command, limits: &Limits) {
+    use std::io;
+    use std::os::unix::process::CommandExt;
+    use libc;
+
+    fn rlimit(soft: u64, hard: u64) -> libc::rlimit {
+        libc::rlimit { rlim_cur: soft as libc::rlim_t, rlim_max: hard as libc::rlim_t }
+    }
+
+    fn checked(result: libc::c_int) -> io::Result<()> {
+        match result {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error())
+        }
+    }
+
+    let (memory, cpu) = (limits.memory, limits.cpu);
+    if memory.is_none() && cpu.is_none() {
+        return
+    }
+    // Only async-signal-safe calls between fork and exec
+    unsafe {
+        command.pre_exec(move || {
+            if let Some(bytes) = memory {
+                try!(checked(libc::setrlimit(libc::RLIMIT_AS, &rlimit(bytes, bytes))));
+            }
+            if let Some(seconds) = cpu {
+                let limit = rlimit(seconds, seconds + CPU_GRACE);
+                try!(checked(libc::setrlimit(libc::RLIMIT_CPU, &limit)));
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn set_rlimits(_command: &mut Command, limits: &Limits) {
+    if limits.memory.is_some() || limits.cpu.is_some() {
+        ::utils::say::sayln("yellow", "WARN: memory and cpu phase limits are not supported \
+                                       on Windows, only the timeout applies");
+    }
+}
+
+// Which limit stopped the phase, told from the signal that killed it.
+// Running out of memory makes allocations fail, which most programs
+// die of but nothing tells for sure.
+#[cfg(not(target_os = "windows"))]
+fn exceeded_limit(status: &ExitStatus, phase: &str, limits: &Limits) -> Option<String> {
+    use std::os::unix::process::ExitStatusExt;
+    use libc;
+
+    let signal = match status.signal() {
+        Some(signal) => signal,
+        None => return None
+    };
+    match (limits.cpu, limits.memory) {
+        (Some(cpu), _) if signal == libc::SIGXCPU || signal == libc::SIGKILL => {
+            Some(format!("The {} phase was killed after using {}s of CPU time, its cpu \
+                         limit", phase, cpu))
+        },
+        (_, Some(memory)) if signal == libc::SIGSEGV || signal == libc::SIGABRT
+                             || signal == libc::SIGKILL => {
+            Some(format!("The {} phase was killed by signal {}, likely for exceeding its \
+                         memory limit of {} bytes", phase, signal, memory))
+        },
+        _ => None
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn exceeded_limit(_status: &ExitStatus, _phase: &str, _limits: &Limits) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use errors::Kind;
+    use utils;
+
+    #[test]
+    fn test_parse_limits() {
+        assert_eq!(90, parse_duration("90").unwrap());
+        assert_eq!(1800, parse_duration("30m").unwrap());
+        assert_eq!(7200, parse_duration(" 2h ").unwrap());
+        assert!(parse_duration("0").is_err());
+        assert!(parse_duration("2d").is_err());
+        assert_eq!(512 << 20, parse_size("512M").unwrap());
+        assert_eq!(2 << 30, parse_size("2gb").unwrap());
+        assert_eq!(4096, parse_size("4096").unwrap());
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("É").is_err());
+        assert!(parse_duration("5é").is_err());
+        assert!(parse_duration("99999999999999999h").is_err());
+        assert!(parse_size("99999999999999G").is_err());
+        match parse_size("0") {
+            Err(DeliveryError { kind: Kind::OptionConstraint, .. }) => {},
+            other => panic!("expected an option constraint, got {:?}", other)
+        }
+        let limits = Limits::parse(Some("1h"), None, Some("10m")).unwrap();
+        assert_eq!(Limits { timeout: Some(3600), memory: None, cpu: Some(600) }, limits);
+        assert!(Limits::default().is_empty());
+    }
+
+    #[test]
+    fn test_sequential_limits() {
+        let unit = Limits { timeout: Some(60), memory: Some(100), cpu: None };
+        let lint = Limits { timeout: Some(30), memory: Some(200), cpu: Some(10) };
+        assert_eq!(Limits { timeout: Some(90), memory: Some(200), cpu: None },
+                   unit.then(&lint));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_run_within_limits() {
+        let limits = Limits { timeout: Some(1), memory: None, cpu: None };
+        let output = run(utils::make_command("true").arg("unit"), "unit", &limits).unwrap();
+        assert!(output.status.success());
+        match run(utils::make_command("sleep").arg("5"), "unit", &limits) {
+            Err(e) => match e.kind {
+                Kind::PhaseLimitExceeded => assert!(e.detail().unwrap().contains("timeout")),
+                _ => panic!("Unexpected error {:?}", e)
+            },
+            Ok(_) => panic!("sleep outlived its timeout")
+        }
+    }
+}
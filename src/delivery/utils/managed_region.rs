@@ -0,0 +1,111 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// Managed regions
+//
+// A managed region is a block of a file the cli owns, delimited by HTML
+// comment markers so it is invisible once the file is rendered:
+//
+// ```text
+// <!-- delivery:badge:begin -->
+// ...content generated by the cli...
+// <!-- delivery:badge:end -->
+// ```
+//
+// Everything outside the markers belongs to the user and is left alone,
+// which makes it safe to regenerate the region as many times as needed.
+
+/// The marker opening the region `name`
+pub fn begin_marker(name: &str) -> String {
+    format!("<!-- delivery:{}:begin -->", name)
+}
+
+/// The marker closing the region `name`
+pub fn end_marker(name: &str) -> String {
+    format!("<!-- delivery:{}:end -->", name)
+}
+
+/// Returns the content of the region `name` if the file has one
+pub fn find<'a>(content: &'a str, name: &str) -> Option<&'a str> {
+    let begin = begin_marker(name);
+    let end = end_marker(name);
+    content.find(&begin).and_then(|b| {
+        let start = b + begin.len();
+        content[start..].find(&end).map(|e| content[start..start + e].trim_matches('\n'))
+    })
+}
+
+/// Replace the content of the region `name` with `body`. When the file
+/// has no such region yet it is added right below the title of the
+/// document, or at the very top if there is no title.
+pub fn upsert(content: &str, name: &str, body: &str) -> String {
+    let begin = begin_marker(name);
+    let end = end_marker(name);
+    let region = format!("{}\n{}\n{}", begin, body.trim_matches('\n'), end);
+
+    if let Some(b) = content.find(&begin) {
+        if let Some(e) = content[b..].find(&end) {
+            let after = b + e + end.len();
+            return format!("{}{}{}", &content[..b], region, &content[after..])
+        }
+    }
+
+    match content.lines().next() {
+        Some(title) if title.starts_with("# ") => {
+            let rest = &content[title.len()..].trim_left_matches('\n');
+            format!("{}\n\n{}\n\n{}", title, region, rest)
+        },
+        _ => format!("{}\n\n{}", region, content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upsert_below_title() {
+        let content = "# My Project\n\nSome text\n";
+        let expect = "# My Project\n\n\
+                      <!-- delivery:badge:begin -->\nBADGE\n<!-- delivery:badge:end -->\n\n\
+                      Some text\n";
+        assert_eq!(expect, upsert(content, "badge", "BADGE"));
+    }
+
+    #[test]
+    fn test_upsert_without_title() {
+        let content = "Some text\n";
+        let expect = "<!-- delivery:badge:begin -->\nBADGE\n<!-- delivery:badge:end -->\n\n\
+                      Some text\n";
+        assert_eq!(expect, upsert(content, "badge", "BADGE"));
+    }
+
+    #[test]
+    fn test_upsert_is_idempotent() {
+        let content = "# My Project\n\nSome text\n";
+        let once = upsert(content, "badge", "BADGE");
+        assert_eq!(once, upsert(&once, "badge", "BADGE"));
+    }
+
+    #[test]
+    fn test_upsert_replaces_existing_region() {
+        let content = "Intro\n<!-- delivery:badge:begin -->\nOLD\n<!-- delivery:badge:end -->\nOutro\n";
+        let expect = "Intro\n<!-- delivery:badge:begin -->\nNEW\n<!-- delivery:badge:end -->\nOutro\n";
+        assert_eq!(expect, upsert(content, "badge", "NEW"));
+        assert_eq!(Some("NEW"), find(expect, "badge"));
+        assert_eq!(None, find(expect, "other"));
+    }
+}
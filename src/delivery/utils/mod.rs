@@ -23,7 +23,6 @@ use std::env;
 use std::process;
 use std::fs::File;
 use std::process::Output as CmdOutput;
-use std::io;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
 use utils::path_join_many::PathJoinMany;
@@ -32,6 +31,17 @@ pub mod say;
 pub mod path_join_many;
 pub mod path_ext;
 pub mod open;
+pub mod managed_region;
+pub mod pool;
+pub mod privilege;
+pub mod poll;
+pub mod json_output;
+pub mod template;
+pub mod filter;
+pub mod preflight;
+pub mod strict;
+pub mod limits;
+pub mod prompt;
 #[cfg(test)]
 pub mod test_paths;
 
@@ -83,17 +93,18 @@ pub fn home_dir(to_append: &[&str]) -> Result<PathBuf, DeliveryError>
 // assert_eq!(coolness, 10);
 // ```
 pub fn read_from_terminal() -> DeliveryResult<String> {
-    let mut buff = String::new();
-    try!(io::stdin().read_line(&mut buff));
-    Ok(buff.trim().to_string())
+    prompt::read_answer(None).map(|answer| answer.unwrap_or_default())
 }
 
-/// Walk up a file hierarchy searching for `dir/target`.
+/// Walk up a file hierarchy searching for `dir/target`. `target` is
+/// `/` separated, i.e. `.git/config`, on every platform.
 pub fn walk_tree_for_path<P>(dir: P, target: &str) -> Option<PathBuf>
         where P: AsRef<Path> {
-    let mut current = dir.as_ref();
+    let start = plain_path(dir.as_ref());
+    let target: Vec<&str> = target.split('/').collect();
+    let mut current = start.as_path();
     loop {
-        let candidate = current.join(target);
+        let candidate = current.join_many(&target);
         if fs::metadata(&candidate).is_ok() {
             let ans = PathBuf::from(candidate);
             return Some(ans)
@@ -105,16 +116,16 @@ pub fn walk_tree_for_path<P>(dir: P, target: &str) -> Option<PathBuf>
     }
 }
 
-// Convert a path into a String. Panic if the path contains
-// non-unicode sequences.
-pub fn path_to_string<P: AsRef<Path>>(p: P) -> String {
+// Convert a path into a String. Fail with `NonUnicodePath` if the path
+// contains non-unicode sequences.
+pub fn path_to_string<P: AsRef<Path>>(p: P) -> DeliveryResult<String> {
     let path = p.as_ref();
     match path.to_str() {
-        Some(s) => s.to_string(),
+        Some(s) => Ok(s.to_string()),
         None => {
-            let s = format!("invalid path (non-unicode): {}",
-                            path.to_string_lossy());
-            panic!(s)
+            let msg = format!("invalid path (non-unicode): {}",
+                              path.to_string_lossy());
+            Err(DeliveryError::throw(Kind::NonUnicodePath, Some(msg)))
         }
     }
 }
@@ -158,17 +169,19 @@ pub fn file_needs_updated<A, B>(source_f: A, dest_f: B) -> DeliveryResult<bool>
         where A: AsRef<Path>,
               B: AsRef<Path> {
     if dest_f.as_ref().exists() {
-        let mut md5_source = Md5::new();            
+        // Compare bytes rather than strings, data files are not
+        // necessarily UTF-8.
+        let mut md5_source = Md5::new();
         let mut source_f = try!(File::open(&source_f));
-        let mut source_str = String::new();
-        try!(source_f.read_to_string(&mut source_str));
-        md5_source.input_str(&source_str);
+        let mut source_bytes = Vec::new();
+        try!(source_f.read_to_end(&mut source_bytes));
+        md5_source.input(&source_bytes);
 
         let mut md5_dest = Md5::new();
         let mut dest_f = try!(File::open(&dest_f));
-        let mut dest_str = String::new();
-        try!(dest_f.read_to_string(&mut dest_str));
-        md5_dest.input_str(&dest_str);
+        let mut dest_bytes = Vec::new();
+        try!(dest_f.read_to_end(&mut dest_bytes));
+        md5_dest.input(&dest_bytes);
 
         // If the md5 sun matches, return None to signify that
         // the file was not copied because they match exactly.
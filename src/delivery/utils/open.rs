@@ -39,7 +39,10 @@
 /// * OS X: open
 /// * Windows: start
 ///
+/// Use `browse` for web pages, it honors the browser the user picked.
+///
 use std::env;
+use std::path::Path;
 use std::process::{Command, Output};
 use errors::{DeliveryError, Kind};
 use tempdir::TempDir;
@@ -73,21 +76,85 @@ use utils::path_join_many::PathJoinMany;
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR
 // OTHER DEALINGS IN THE SOFTWARE.
 
+/// Open `url` in a browser: the `browser` program when one is given,
+/// else the one of the `BROWSER` environment variable, else the
+/// program of the system.
+pub fn browse(url: &str, browser: Option<&str>) -> Result<(), DeliveryError> {
+    let browser = browser.map(|b| b.to_string()).or_else(|| env::var("BROWSER").ok());
+    match browser {
+        Some(ref b) if !b.trim().is_empty() => {
+            let argv = browser_command(b, url);
+            process_response(&argv[0], try!(Command::new(&argv[0]).args(&argv[1..]).output()))
+        },
+        _ => item(url)
+    }
+}
+
+/// Why not to open a browser, `None` to go ahead. `no_open` from the
+/// cli.toml decides when set; unset, SSH sessions, containers and CI
+/// builds, where nobody would see the browser, skip it.
+pub fn skip_reason(no_open: Option<bool>) -> Option<String> {
+    match no_open {
+        Some(true) => Some("no_open is set in your cli.toml".to_string()),
+        Some(false) => None,
+        None => headless(&|key| env::var(key).ok(), Path::new("/.dockerenv").exists())
+            .map(|env| format!("this looks like {} (set no_open = false in your cli.toml \
+                                to open it anyway)", env))
+    }
+}
+
+// What the environment, read through `var`, says the cli runs in when
+// there is no one in front of a browser
+fn headless(var: &Fn(&str) -> Option<String>, dockerenv: bool) -> Option<&'static str> {
+    let set = |key: &str| var(key).map(|v| !v.is_empty() && v != "false").unwrap_or(false);
+    if ["CI", "CONTINUOUS_INTEGRATION", "BUILD_NUMBER", "JENKINS_URL", "GITHUB_ACTIONS"]
+        .iter().any(|key| set(key)) {
+        Some("a CI build")
+    } else if ["SSH_CONNECTION", "SSH_CLIENT", "SSH_TTY"].iter().any(|key| set(key)) {
+        Some("an SSH session")
+    } else if dockerenv || set("container") || set("KUBERNETES_SERVICE_HOST") {
+        Some("a container")
+    } else {
+        None
+    }
+}
+
+// The command line of `browser` for `url`, which replaces a `%s` or
+// comes last. Like `EDITOR`, the browser may come with arguments.
+fn browser_command(browser: &str, url: &str) -> Vec<String> {
+    let mut argv: Vec<String> = browser.split_whitespace().map(|a| a.to_string()).collect();
+    if argv.iter().any(|a| a.contains("%s")) {
+        argv = argv.iter().map(|a| a.replace("%s", url)).collect();
+    } else {
+        argv.push(url.to_string());
+    }
+    argv
+}
+
 #[cfg(target_os = "macos")]
 pub fn item(path: &str) -> Result<(), DeliveryError> {
     item_for_cmds(path, &["open"])
 }
 
+// Without a display xdg-open falls back to a text browser, which would
+// take the terminal over in the middle of a command.
 #[cfg(not(any(target_os = "windows", target_os = "macos")))]
 pub fn item(path: &str) -> Result<(), DeliveryError> {
+    if env::var_os("DISPLAY").is_none() && env::var_os("WAYLAND_DISPLAY").is_none() {
+        return Err(DeliveryError { kind: Kind::OpenFailed,
+                                   detail: Some("No display to open it on".to_string()) })
+    }
     item_for_cmds(path, &["xdg-open", "gnome-open", "kde-open"])
 }
 
+// The empty argument is the window title, `start` would take a quoted
+// path for one.
 #[cfg(target_os = "windows")]
 pub fn item(path: &str) -> Result<(), DeliveryError> {
     process_response("start", try!(Command::new("cmd.exe")
                                     .arg("/c")
                                     .arg("start")
+                                    .arg("")
                                     .arg(path)
                                     .output()))
 }
@@ -177,3 +244,43 @@ pub fn edit_str(name: &str,
     try!(f.read_to_string(&mut content));
     Ok(content)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use super::{browser_command, headless, skip_reason};
+
+    #[test]
+    fn test_browser_command() {
+        assert_eq!(vec!["firefox", "https://a.example.com/"],
+                   browser_command("firefox", "https://a.example.com/"));
+        assert_eq!(vec!["chromium", "--new-window", "https://a.example.com/"],
+                   browser_command("chromium --new-window", "https://a.example.com/"));
+        assert_eq!(vec!["open", "-a", "Safari", "https://a.example.com/", "--args"],
+                   browser_command("open -a Safari %s --args", "https://a.example.com/"));
+    }
+
+    #[test]
+    fn test_headless() {
+        let env = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+        let check = |vars: HashMap<String, String>, dockerenv: bool| {
+            headless(&|key| vars.get(key).cloned(), dockerenv)
+        };
+        assert_eq!(None, check(env(&[("HOME", "/home/me")]), false));
+        assert_eq!(None, check(env(&[("CI", "false")]), false));
+        assert_eq!(Some("a CI build"), check(env(&[("CI", "true"), ("SSH_TTY", "/dev/pts/0")]),
+                                             false));
+        assert_eq!(Some("an SSH session"),
+                   check(env(&[("SSH_CONNECTION", "10.0.0.1 5122 10.0.0.2 22")]), false));
+        assert_eq!(Some("a container"), check(env(&[]), true));
+        assert_eq!(Some("a container"), check(env(&[("container", "podman")]), false));
+    }
+
+    #[test]
+    fn test_skip_reason_config_wins() {
+        assert_eq!(None, skip_reason(Some(false)));
+        assert!(skip_reason(Some(true)).is_some());
+    }
+}
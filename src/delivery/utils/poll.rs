@@ -0,0 +1,213 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+// Polling with exponential backoff
+//
+// Watch and status loops ask the server the same question until the
+// answer changes. With hundreds of watchers open a fixed short interval
+// adds up, so the wait doubles every time nothing changed, up to a
+// maximum, and is reset as soon as something does. A random jitter
+// keeps watchers started at the same time from polling in lockstep.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use types::DeliveryResult;
+
+pub const DEFAULT_POLL_INTERVAL: u64 = 5;
+pub const DEFAULT_MAX_POLL_INTERVAL: u64 = 120;
+
+// Fraction of the delay that is randomized, in both directions
+const JITTER: f64 = 0.2;
+
+// How often a sleeping poller checks whether it was cancelled
+const CANCEL_CHECK: u64 = 100;
+
+/// The outcome of one check of a poll loop
+#[derive(Debug, PartialEq)]
+pub enum Poll<T> {
+    /// Done polling, with the final result
+    Ready(T),
+    /// Something changed; poll again soon
+    Changed,
+    /// Nothing changed; back off before polling again
+    Unchanged,
+}
+
+/// Delays between polls: `initial`, doubling up to `max`
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    current: Duration,
+    seed: u64,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Backoff {
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos() as u64 ^ d.as_secs()).unwrap_or(0);
+        Backoff {
+            initial: initial,
+            max: if max < initial { initial } else { max },
+            current: initial,
+            seed: seed | 1,
+        }
+    }
+
+    /// The delay to wait before the next poll, jitter included
+    pub fn next_delay(&mut self) -> Duration {
+        let base = self.current;
+        self.current = (self.current * 2).min(self.max);
+        let spread = (self.random() * 2.0 - 1.0) * JITTER;
+        let millis = to_millis(base) as f64 * (1.0 + spread);
+        Duration::from_millis(millis.max(0.0) as u64)
+    }
+
+    /// Back to the initial delay, when something changed
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+    }
+
+    // A number in [0, 1); xorshift is plenty to spread pollers apart.
+    fn random(&mut self) -> f64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        (self.seed % 10_000) as f64 / 10_000.0
+    }
+}
+
+/// Lets another thread (i.e. a Ctrl-C handler) stop a poll loop
+#[derive(Debug, Clone, Default)]
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+    pub fn new() -> Cancel {
+        Cancel::default()
+    }
+
+    /// Cancelled by itself once `timeout` passed
+    pub fn after(timeout: Duration) -> Cancel {
+        let cancel = Cancel::new();
+        let timer = cancel.clone();
+        thread::spawn(move || {
+            thread::sleep(timeout);
+            timer.cancel();
+        });
+        cancel
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Run `check` until it is `Ready`, waiting between checks as
+/// `backoff` says. Returns `None` when `cancel` was triggered first.
+pub fn poll<T, F>(backoff: &mut Backoff, cancel: &Cancel,
+                  mut check: F) -> DeliveryResult<Option<T>>
+        where F: FnMut() -> DeliveryResult<Poll<T>> {
+    loop {
+        if cancel.is_cancelled() {
+            return Ok(None)
+        }
+        match try!(check()) {
+            Poll::Ready(result) => return Ok(Some(result)),
+            Poll::Changed => backoff.reset(),
+            Poll::Unchanged => ()
+        }
+        if !sleep(backoff.next_delay(), cancel) {
+            return Ok(None)
+        }
+    }
+}
+
+// Sleep for `delay` in small steps; false if cancelled meanwhile.
+fn sleep(delay: Duration, cancel: &Cancel) -> bool {
+    let mut left = to_millis(delay);
+    while left > 0 {
+        if cancel.is_cancelled() {
+            return false
+        }
+        let step = left.min(CANCEL_CHECK);
+        thread::sleep(Duration::from_millis(step));
+        left -= step;
+    }
+    !cancel.is_cancelled()
+}
+
+fn to_millis(d: Duration) -> u64 {
+    d.as_secs() * 1_000 + (d.subsec_nanos() / 1_000_000) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn within_jitter(expected: u64, actual: Duration) -> bool {
+        let millis = actual.as_secs() * 1_000 + (actual.subsec_nanos() / 1_000_000) as u64;
+        millis >= expected * 8 / 10 && millis <= expected * 12 / 10
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+        for expected in &[1_000, 2_000, 4_000, 5_000, 5_000] {
+            assert!(within_jitter(*expected, backoff.next_delay()));
+        }
+        backoff.reset();
+        assert!(within_jitter(1_000, backoff.next_delay()));
+    }
+
+    #[test]
+    fn test_poll_until_ready() {
+        let mut backoff = Backoff::new(Duration::from_millis(1), Duration::from_millis(4));
+        let mut checks = 0;
+        let result = poll(&mut backoff, &Cancel::new(), || {
+            checks += 1;
+            Ok(if checks == 3 { Poll::Ready(checks) } else { Poll::Unchanged })
+        });
+        assert_eq!(Some(3), result.unwrap());
+    }
+
+    #[test]
+    fn test_poll_cancelled() {
+        let mut backoff = Backoff::new(Duration::from_millis(1), Duration::from_millis(4));
+        let cancel = Cancel::new();
+        let result: Option<()> = poll(&mut backoff, &cancel.clone(), || {
+            cancel.cancel();
+            Ok(Poll::Changed)
+        }).unwrap();
+        assert_eq!(None, result);
+    }
+
+    #[test]
+    fn test_poll_cancelled_after_timeout() {
+        let mut backoff = Backoff::new(Duration::from_millis(1), Duration::from_millis(4));
+        let cancel = Cancel::after(Duration::from_millis(20));
+        let result: Option<()> = poll(&mut backoff, &cancel, || Ok(Poll::Unchanged)).unwrap();
+        assert_eq!(None, result);
+        assert!(cancel.is_cancelled());
+    }
+}
@@ -0,0 +1,120 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+// Bounded parallelism
+//
+// Bulk commands talk to the server once per item; running a few of those
+// requests at the same time speeds them up, but running all of them at
+// once is a good way to trip the server rate limits. `parallel_map`
+// runs at most `concurrency` items at a time.
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Parse the value of a `--concurrency` option, empty means the default
+pub fn parse_concurrency(value: &str) -> DeliveryResult<usize> {
+    if value.is_empty() {
+        return Ok(DEFAULT_CONCURRENCY)
+    }
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => {
+            let msg = format!("--concurrency must be a number greater than 0, got '{}'",
+                              value);
+            Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+        }
+    }
+}
+
+/// Apply `f` to every item using at most `concurrency` threads.
+/// The results are returned in the same order as the items.
+pub fn parallel_map<T, R, F>(items: Vec<T>, concurrency: usize, f: F) -> Vec<R>
+        where T: Send + 'static, R: Send + 'static, F: Fn(T) -> R + Send + Sync + 'static {
+    let count = items.len();
+    let queue = Arc::new(Mutex::new(items.into_iter().enumerate()));
+    let results = Arc::new(Mutex::new(Vec::with_capacity(count)));
+    let f = Arc::new(f);
+    let workers: Vec<_> = (0..concurrency.max(1).min(count)).map(|_| {
+        let queue = queue.clone();
+        let results = results.clone();
+        let f = f.clone();
+        thread::spawn(move || {
+            loop {
+                let next = queue.lock().unwrap().next();
+                match next {
+                    Some((i, item)) => {
+                        let r = f(item);
+                        results.lock().unwrap().push((i, r));
+                    },
+                    None => break
+                }
+            }
+        })
+    }).collect();
+    for worker in workers {
+        worker.join().expect("parallel_map worker panicked");
+    }
+    let mut results = Arc::try_unwrap(results).ok()
+        .expect("parallel_map workers are done").into_inner().unwrap();
+    results.sort_by_key(|&(i, _)| i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parallel_map_keeps_order() {
+        let items: Vec<u64> = (0..20).collect();
+        let results = parallel_map(items, 3, |i| {
+            thread::sleep(Duration::from_millis(20 - i));
+            i * 2
+        });
+        assert_eq!((0..20).map(|i| i * 2).collect::<Vec<u64>>(), results);
+    }
+
+    #[test]
+    fn test_parallel_map_is_bounded() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (r, p) = (running.clone(), peak.clone());
+        parallel_map((0..12).collect::<Vec<u32>>(), 2, move |_| {
+            let now = r.fetch_add(1, Ordering::SeqCst) + 1;
+            p.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(5));
+            r.fetch_sub(1, Ordering::SeqCst);
+        });
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn test_parse_concurrency() {
+        assert_eq!(DEFAULT_CONCURRENCY, parse_concurrency("").unwrap());
+        assert_eq!(8, parse_concurrency("8").unwrap());
+        assert!(parse_concurrency("0").is_err());
+        assert!(parse_concurrency("many").is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// Pre-flight checks
+//
+// Creating a job workspace or cloning a generator writes a good amount
+// of data. When the disk is full, or the directory belongs to someone
+// else, git dies half way through with an error that says little about
+// the cause. Checking the target up front lets us fail before starting,
+// and say why.
+
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+use utils::free_disk_space;
+
+/// Free space a job workspace needs: the repository, the build cookbook
+/// and its dependencies, and the cache of the chef-client run
+pub const WORKSPACE_SPACE: u64 = 1024 * 1024 * 1024;
+
+/// Free space the clone of a build cookbook generator needs
+pub const GENERATOR_SPACE: u64 = 50 * 1024 * 1024;
+
+/// i.e. `1.5 GB`
+pub fn format_bytes(bytes: u64) -> String {
+    let units = ["bytes", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{:.1} {}", value, units[unit])
+    }
+}
+
+/// The closest directory to `path` that exists, `path` itself
+/// included; that is where the files would end up.
+pub fn existing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors().find(|p| p.is_dir()).map(Path::to_path_buf)
+}
+
+/// Fail when `path` cannot be written to, or when the filesystem it
+/// lives on has less than `needed` bytes free. `what` names the
+/// operation in the error, i.e. "the job workspace".
+pub fn check(path: &Path, needed: u64, what: &str) -> DeliveryResult<()> {
+    let dir = match existing_ancestor(path) {
+        Some(dir) => dir,
+        None => return Ok(())
+    };
+    try!(check_writable(&dir, what));
+    if let Some(free) = free_disk_space(&dir) {
+        debug!("{} has {} free", dir.display(), format_bytes(free));
+        if free < needed {
+            let msg = format!("{} has {} free, {} needs at least {}. Free some space \
+                               and try again.", dir.display(), format_bytes(free), what,
+                              format_bytes(needed));
+            return Err(DeliveryError::throw(Kind::InsufficientDiskSpace, Some(msg)))
+        }
+    }
+    Ok(())
+}
+
+// Permissions alone do not tell, think of read-only mounts; write a
+// file and remove it.
+fn check_writable(dir: &Path, what: &str) -> DeliveryResult<()> {
+    let probe = dir.join(format!(".delivery-preflight-{}", process::id()));
+    match OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            Ok(())
+        },
+        Err(e) => {
+            let msg = format!("{} cannot be written to ({}), {} is created there. Check \
+                               the owner and permissions of the directory.",
+                              dir.display(), e, what);
+            Err(DeliveryError::throw(Kind::PathNotWritable, Some(msg)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!("512 bytes", format_bytes(512));
+        assert_eq!("1.5 KB", format_bytes(1536));
+        assert_eq!("1.0 GB", format_bytes(WORKSPACE_SPACE));
+    }
+
+    #[test]
+    fn test_check() {
+        let tmp = TempDir::new("preflight").unwrap();
+        let target = tmp.path().join("not").join("there");
+        assert_eq!(Some(tmp.path().to_path_buf()), existing_ancestor(&target));
+        assert!(check(&target, 1, "the test").is_ok());
+        assert!(!tmp.path().read_dir().unwrap().any(|_| true));
+        match check(&target, u64::max_value(), "the test") {
+            Err(DeliveryError { kind: Kind::InsufficientDiskSpace, .. }) => {},
+            other => panic!("expected InsufficientDiskSpace, got {:?}", other)
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+// Privilege escalation for local phases
+//
+// Some phases need elevated rights, functional tests binding low ports
+// being the usual suspect. A phase only runs elevated when the
+// project.toml lists it in `privileged_phases`. On Unix we go through
+// the configured sudo command and never let it prompt half way through
+// a phase: credentials are checked before the phase starts, and in
+// non-interactive mode we refuse rather than wait for a password nobody
+// will type. Windows has no sudo, there the cli itself has to be started
+// from an elevated prompt.
+
+use std::process::{Command, Stdio};
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+use utils::elevated_process;
+
+pub const DEFAULT_SUDO_COMMAND: &'static str = "sudo";
+
+#[derive(Debug, PartialEq)]
+pub enum Escalation {
+    /// The cli already runs elevated, the phase runs as is
+    NotNeeded,
+    /// sudo works without a password (cached credentials or NOPASSWD)
+    Sudo,
+    /// sudo needs the password, asked once before the phase starts
+    AskPassword,
+    /// sudo needs a password but we are not allowed to ask for it
+    Refuse,
+}
+
+/// What it takes to elevate, given the state of the process and of sudo
+pub fn plan(elevated: bool, passwordless: bool, interactive: bool) -> Escalation {
+    if elevated {
+        Escalation::NotNeeded
+    } else if passwordless {
+        Escalation::Sudo
+    } else if interactive {
+        Escalation::AskPassword
+    } else {
+        Escalation::Refuse
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Escalator {
+    sudo: Vec<String>,
+    interactive: bool,
+}
+
+impl Escalator {
+    /// `sudo_command` is the `sudo_command` of the cli.toml, it may carry
+    /// its own arguments (e.g. `sudo -u tester`).
+    pub fn new(sudo_command: Option<String>, interactive: bool) -> Escalator {
+        let command = sudo_command.unwrap_or(DEFAULT_SUDO_COMMAND.to_string());
+        let mut sudo: Vec<String> = command.split_whitespace().map(|s| s.to_string()).collect();
+        if sudo.is_empty() {
+            sudo.push(DEFAULT_SUDO_COMMAND.to_string());
+        }
+        Escalator { sudo: sudo, interactive: interactive }
+    }
+
+    /// The command line running `program` with elevated privileges for
+    /// `phase`. The returned vector starts with the program to execute.
+    pub fn escalate(&self, phase: &str, program: &str, args: &[&str],
+                    env: &[(&str, &str)]) -> DeliveryResult<Vec<String>> {
+        if cfg!(target_os = "windows") {
+            if elevated_process() {
+                return Ok(plain_argv(program, args))
+            }
+            let msg = format!("The {} phase needs elevated privileges; run delivery \
+                              from an elevated prompt (Run as administrator).", phase);
+            return Err(DeliveryError::throw(Kind::PrivilegeEscalationRefused, Some(msg)))
+        }
+        let elevated = elevated_process();
+        let passwordless = !elevated && self.passwordless();
+        match plan(elevated, passwordless, self.interactive) {
+            Escalation::NotNeeded => Ok(plain_argv(program, args)),
+            Escalation::Sudo => Ok(self.sudo_argv(program, args, env)),
+            Escalation::AskPassword => {
+                try!(self.validate(phase));
+                Ok(self.sudo_argv(program, args, env))
+            },
+            Escalation::Refuse => {
+                let msg = format!("The {} phase needs elevated privileges and `{}` \
+                                  asks for a password, which is not possible in \
+                                  non-interactive mode. Allow it to run without a \
+                                  password or run delivery as root.",
+                                  phase, self.sudo.join(" "));
+                Err(DeliveryError::throw(Kind::PrivilegeEscalationRefused, Some(msg)))
+            }
+        }
+    }
+
+    /// The sudo command line for `program`. `-n` keeps sudo from ever
+    /// prompting, and the phase environment goes through `env` since
+    /// sudo resets the environment of the commands it runs.
+    pub fn sudo_argv(&self, program: &str, args: &[&str], env: &[(&str, &str)]) -> Vec<String> {
+        let mut argv = self.sudo.clone();
+        argv.push("-n".to_string());
+        argv.push("env".to_string());
+        for &(key, value) in env {
+            argv.push(format!("{}={}", key, value));
+        }
+        argv.extend(plain_argv(program, args));
+        argv
+    }
+
+    // Whether sudo runs without asking for a password right now
+    fn passwordless(&self) -> bool {
+        Command::new(&self.sudo[0])
+            .args(&self.sudo[1..])
+            .args(&["-n", "true"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    }
+
+    // Let sudo ask for the password once, so the phase itself runs
+    // with cached credentials.
+    fn validate(&self, phase: &str) -> DeliveryResult<()> {
+        let status = Command::new(&self.sudo[0])
+            .args(&self.sudo[1..])
+            .arg("-v")
+            .status();
+        match status {
+            Ok(ref s) if s.success() => Ok(()),
+            _ => {
+                let msg = format!("Unable to get elevated privileges for the {} phase \
+                                  with `{}`", phase, self.sudo.join(" "));
+                Err(DeliveryError::throw(Kind::PrivilegeEscalationRefused, Some(msg)))
+            }
+        }
+    }
+}
+
+fn plain_argv(program: &str, args: &[&str]) -> Vec<String> {
+    let mut argv = vec![program.to_string()];
+    argv.extend(args.iter().map(|a| a.to_string()));
+    argv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan() {
+        assert_eq!(Escalation::NotNeeded, plan(true, false, false));
+        assert_eq!(Escalation::Sudo, plan(false, true, false));
+        assert_eq!(Escalation::AskPassword, plan(false, false, true));
+        assert_eq!(Escalation::Refuse, plan(false, false, false));
+    }
+
+    #[test]
+    fn test_sudo_argv() {
+        let escalator = Escalator::new(Some("sudo -u tester".to_string()), true);
+        assert_eq!(vec!["sudo", "-u", "tester", "-n", "env", "DELIVERY_PHASE=functional",
+                        "rspec", "spec/functional"],
+                   escalator.sudo_argv("rspec", &["spec/functional"],
+                                       &[("DELIVERY_PHASE", "functional")]));
+    }
+
+    #[test]
+    fn test_default_sudo_command() {
+        let escalator = Escalator::new(None, false);
+        assert_eq!(vec!["sudo", "-n", "env", "make"], escalator.sudo_argv("make", &[], &[]));
+        let blank = Escalator::new(Some("  ".to_string()), false);
+        assert_eq!(escalator.sudo, blank.sudo);
+    }
+}
@@ -0,0 +1,305 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// Questions to the user
+//
+// Every question goes through a `Prompt` so they all behave the same:
+// the default is shown and an empty answer takes it, a question left
+// unanswered past its timeout takes it too, and a non-interactive run
+// takes it without asking. A question without a default fails with
+// `PromptUnanswered` in those last two cases rather than hang.
+//
+// Answers to secret questions, tokens and the like, are read without
+// echo and never printed or logged.
+//
+// Tests answer the questions asked on their thread with `with_answers`.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use errors::{DeliveryError, Kind};
+use rpassword;
+use types::DeliveryResult;
+use utils::say::{say, sayln};
+
+thread_local!(static ANSWERS: RefCell<Option<VecDeque<String>>> = RefCell::new(None));
+
+// How many invalid answers a question takes before it gives up
+const MAX_ATTEMPTS: usize = 3;
+
+pub struct Prompt<'a> {
+    question: &'a str,
+    default: Option<String>,
+    timeout: Option<Duration>,
+    interactive: bool,
+    secret: bool,
+}
+
+impl<'a> Prompt<'a> {
+    pub fn new(question: &'a str) -> Prompt<'a> {
+        Prompt {
+            question: question,
+            default: None,
+            timeout: None,
+            interactive: true,
+            secret: false,
+        }
+    }
+
+    /// The answer taken on an empty answer, a timeout or when not
+    /// interactive
+    pub fn default(mut self, answer: &str) -> Prompt<'a> {
+        self.default = Some(answer.to_string());
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Prompt<'a> {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn interactive(mut self, interactive: bool) -> Prompt<'a> {
+        self.interactive = interactive;
+        self
+    }
+
+    /// Read the answer without echo and keep it out of the output and
+    /// the logs
+    pub fn secret(mut self) -> Prompt<'a> {
+        self.secret = true;
+        self
+    }
+
+    /// Ask for a free form answer
+    pub fn input(&self) -> DeliveryResult<String> {
+        let hint = match self.default {
+            Some(ref d) if !d.is_empty() => format!(" [{}]", d),
+            _ => String::new()
+        };
+        self.ask(&hint, |answer| Some(answer.to_string()))
+    }
+
+    /// Ask a yes or no question
+    pub fn confirm(&self) -> DeliveryResult<bool> {
+        let hint = match self.default.as_ref().map(|d| parse_yes_no(d)) {
+            Some(Some(true)) => " [Y/n]",
+            Some(Some(false)) => " [y/N]",
+            _ => " [y/n]"
+        };
+        self.ask(hint, parse_yes_no)
+    }
+
+    /// Ask to pick one of `choices`, by number or by name. The default,
+    /// if any, is one of the choices.
+    pub fn select(&self, choices: &[&str]) -> DeliveryResult<usize> {
+        for (i, choice) in choices.iter().enumerate() {
+            sayln("white", &format!("  {}) {}", i + 1, choice));
+        }
+        let hint = match self.default {
+            Some(ref d) => format!(" [{}]", d),
+            None => format!(" [1-{}]", choices.len())
+        };
+        self.ask(&hint, |answer| {
+            match answer.parse::<usize>() {
+                Ok(n) if n >= 1 && n <= choices.len() => Some(n - 1),
+                _ => choices.iter().position(|c| *c == answer)
+            }
+        })
+    }
+
+    fn ask<T, F>(&self, hint: &str, parse: F) -> DeliveryResult<T>
+            where F: Fn(&str) -> Option<T> {
+        if !self.interactive {
+            return self.take_default(&parse, "delivery runs non-interactively")
+        }
+        for _ in 0..MAX_ATTEMPTS {
+            say("yellow", &format!("{}{}: ", self.question, hint));
+            let read = if self.secret { read_secret_line } else { read_line };
+            let answer = match try!(read_answer_with(self.timeout, read)) {
+                Some(answer) => answer,
+                None => {
+                    sayln("white", "");
+                    return self.take_default(&parse, "no answer came in time")
+                }
+            };
+            if !self.secret {
+                debug!("You answered '{}'", answer);
+            }
+            if answer.is_empty() && self.default.is_some() {
+                return self.take_default(&parse, "")
+            }
+            match parse(&answer) {
+                Some(value) => return Ok(value),
+                None if self.secret => sayln("red", "That is not a valid answer"),
+                None => sayln("red", &format!("'{}' is not a valid answer", answer))
+            }
+        }
+        Err(DeliveryError::throw(Kind::PromptUnanswered,
+                                 Some(format!("No valid answer to '{}'", self.question))))
+    }
+
+    fn take_default<T, F>(&self, parse: &F, why: &str) -> DeliveryResult<T>
+            where F: Fn(&str) -> Option<T> {
+        match self.default.as_ref().and_then(|d| parse(d)) {
+            Some(value) => Ok(value),
+            None => Err(DeliveryError::throw(
+                Kind::PromptUnanswered,
+                Some(format!("'{}' needs an answer but {}", self.question, why))
+            ))
+        }
+    }
+}
+
+fn parse_yes_no(answer: &str) -> Option<bool> {
+    match answer.to_lowercase().as_ref() {
+        "y" | "yes" => Some(true),
+        "n" | "no" => Some(false),
+        _ => None
+    }
+}
+
+/// Read a line the user typed, trimmed; `None` when `timeout` passed
+/// first.
+pub fn read_answer(timeout: Option<Duration>) -> DeliveryResult<Option<String>> {
+    read_answer_with(timeout, read_line)
+}
+
+fn read_answer_with(timeout: Option<Duration>,
+                    read: fn() -> DeliveryResult<String>) -> DeliveryResult<Option<String>> {
+    let scripted = ANSWERS.with(|a| {
+        a.borrow_mut().as_mut().map(|answers| answers.pop_front())
+    });
+    if let Some(answer) = scripted {
+        return Ok(answer)
+    }
+    match timeout {
+        None => read().map(Some),
+        Some(timeout) => {
+            // The reader stays blocked on stdin after a timeout, the
+            // process is about to move on without it anyway.
+            let (tx, rx) = mpsc::channel();
+            thread::spawn(move || { let _ = tx.send(read()); });
+            match rx.recv_timeout(timeout) {
+                Ok(line) => line.map(Some),
+                Err(_) => Ok(None)
+            }
+        }
+    }
+}
+
+fn read_line() -> DeliveryResult<String> {
+    let mut buff = String::new();
+    try!(io::stdin().read_line(&mut buff));
+    Ok(buff.trim().to_string())
+}
+
+// Without echo on a terminal; stdin that is not one is read as it is
+fn read_secret_line() -> DeliveryResult<String> {
+    match rpassword::read_password() {
+        Ok(answer) => {
+            sayln("white", "");
+            Ok(answer.trim().to_string())
+        },
+        Err(_) => read_line()
+    }
+}
+
+/// Run `f` with the questions it asks on this thread answered with
+/// `answers`, in order. Once they run out the questions time out.
+pub fn with_answers<F, T>(answers: &[&str], f: F) -> T where F: FnOnce() -> T {
+    let scripted = answers.iter().map(|a| a.to_string()).collect();
+    let outer = ANSWERS.with(|a| a.borrow_mut().replace(scripted));
+    let result = f();
+    ANSWERS.with(|a| *a.borrow_mut() = outer);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Prompt, with_answers};
+    use errors::Kind;
+    use utils::say::capture;
+
+    #[test]
+    fn test_confirm() {
+        let confirm = |answers: &[&str], default: Option<&str>| {
+            capture(|| {
+                with_answers(answers, || {
+                    let prompt = Prompt::new("Sure?");
+                    match default {
+                        Some(d) => prompt.default(d).confirm(),
+                        None => prompt.confirm()
+                    }
+                })
+            }).0.unwrap()
+        };
+        assert!(confirm(&["y"], None));
+        assert!(!confirm(&["No"], None));
+        assert!(confirm(&["", "maybe"], Some("y")));
+        assert!(confirm(&["maybe", "yes"], None));
+    }
+
+    #[test]
+    fn test_non_interactive() {
+        assert!(!Prompt::new("Sure?").default("n").interactive(false).confirm().unwrap());
+        let err = Prompt::new("Sure?").interactive(false).confirm().unwrap_err();
+        assert!(assert_enum!(err.kind, Kind::PromptUnanswered));
+    }
+
+    #[test]
+    fn test_no_answer() {
+        let (result, _) = capture(|| {
+            with_answers(&[], || Prompt::new("Name").default("proj").input())
+        });
+        assert_eq!("proj", result.unwrap());
+        let (result, _) = capture(|| with_answers(&[], || Prompt::new("Name").input()));
+        assert!(result.is_err());
+        let (result, _) = capture(|| {
+            with_answers(&["x", "maybe", "z"], || Prompt::new("Sure?").confirm())
+        });
+        assert!(assert_enum!(result.unwrap_err().kind, Kind::PromptUnanswered));
+    }
+
+    #[test]
+    fn test_secret_answers_are_not_printed() {
+        let (result, output) = capture(|| {
+            with_answers(&["s3cr3t", "yes"], || Prompt::new("Sure?").secret().confirm())
+        });
+        assert!(result.unwrap());
+        assert!(!output.text().contains("s3cr3t"));
+    }
+
+    #[test]
+    fn test_select() {
+        let choices = ["master", "release/1.x"];
+        let (result, _) = capture(|| {
+            with_answers(&["2"], || Prompt::new("Pipeline").select(&choices))
+        });
+        assert_eq!(1, result.unwrap());
+        let (result, _) = capture(|| {
+            with_answers(&["3", "master"], || Prompt::new("Pipeline").select(&choices))
+        });
+        assert_eq!(0, result.unwrap());
+        let (result, _) = capture(|| {
+            with_answers(&[""], || Prompt::new("Pipeline").default("release/1.x")
+                                                            .select(&choices))
+        });
+        assert_eq!(1, result.unwrap());
+    }
+}
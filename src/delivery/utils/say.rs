@@ -15,18 +15,51 @@
 // limitations under the License.
 //
 
+// Terminal output
+//
+// Everything the cli prints goes through `say` or `out`. What a command
+// produces for its caller (urls, ids, JSON, the rows of a listing) is
+// `out` and goes to stdout; progress, status and errors are `say` and go
+// to stderr, so `delivery review | pbcopy` gets the url and nothing else.
+// Each call is written while holding the lock of its stream, so output
+// from different threads never gets interleaved in the middle of a
+// message. Work that runs concurrently and
+// prints several lines (parallel phases, watchers, ...) should `capture`
+// its output into a `Buffer` and flush it as a whole, either directly or
+// through an `OrderedOutput` to keep the tasks in their original order.
+
 use term;
+use utils::json_output;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{Sender, Receiver};
 use std::sync::mpsc::channel;
 use std::thread::{self, JoinHandle};
 use std::io::prelude::*;
 use std::io;
 use std::time::Duration;
+use libc;
 
 /// Because sometimes, you just want a global variable.
-static mut SHOW_SPINNER: bool = true;
-static mut SHOW_OUTPUT:  bool = true;
-static mut COLORIZE:     bool = true;
+static SHOW_SPINNER: AtomicBool = AtomicBool::new(true);
+static SHOW_OUTPUT:  AtomicBool = AtomicBool::new(true);
+static COLORIZE:     AtomicBool = AtomicBool::new(true);
+static SHOW_DEBUG:   AtomicBool = AtomicBool::new(false);
+
+// The buffer of the thread's current `capture`, if any
+thread_local!(static CAPTURE: RefCell<Option<Vec<Segment>>> = RefCell::new(None));
+
+// Which stream a piece of output goes to
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stream {
+    Data,
+    Chatter,
+}
+
+// A piece of output, its stream and the color to print it with
+type Segment = (Stream, String, String);
 
 pub struct Spinner {
     tx: Sender<isize>,
@@ -48,27 +81,21 @@ impl Spinner {
     fn spin(rx: Receiver<isize>) {
         let spinner_chars = vec!["|", "/", "-", "\\"];
         for spin in spinner_chars.iter().cycle() {
-            unsafe {
-                if SHOW_SPINNER {
-                    say("yellow", *spin);
-                }
+            if SHOW_SPINNER.load(Ordering::Relaxed) {
+                say("yellow", *spin);
             }
             let r = rx.try_recv();
             match r {
                 Ok(_) => {
-                    unsafe {
-                        if SHOW_SPINNER {
-                            say("white", "\x08 \x08");
-                        }
+                    if SHOW_SPINNER.load(Ordering::Relaxed) {
+                        say("white", "\x08 \x08");
                     }
                     break;
                 },
                 Err(_) => {
                     thread::sleep(Duration::from_millis(100));
-                    unsafe {
-                        if SHOW_SPINNER {
-                            say("white", "\x08");
-                        }
+                    if SHOW_SPINNER.load(Ordering::Relaxed) {
+                        say("white", "\x08");
                     }
                     continue;
                 }
@@ -78,31 +105,32 @@ impl Spinner {
 }
 
 pub fn turn_off_output() {
-    unsafe {
-        SHOW_OUTPUT = false;
-    }
+    SHOW_OUTPUT.store(false, Ordering::Relaxed);
 }
 
 pub fn turn_on_output() {
-    unsafe {
-        SHOW_OUTPUT = true;
-    }
+    SHOW_OUTPUT.store(true, Ordering::Relaxed);
 }
 
 pub fn turn_off_color() {
-    unsafe {
-        COLORIZE = false;
-    }
+    COLORIZE.store(false, Ordering::Relaxed);
 }
 
 pub fn turn_off_spinner() {
-    unsafe {
-        SHOW_SPINNER = false;
-    }
+    SHOW_SPINNER.store(false, Ordering::Relaxed);
+}
+
+pub fn turn_on_debug() {
+    SHOW_DEBUG.store(true, Ordering::Relaxed);
+}
+
+/// Whether `--debug` asked for the details behind errors
+pub fn debug_enabled() -> bool {
+    SHOW_DEBUG.load(Ordering::Relaxed)
 }
 
-fn say_term(mut t: Box<term::StdoutTerminal>, color: &str, to_say: &str) {
-    let color_const = match color {
+fn color_for(color: &str) -> term::color::Color {
+    match color {
         "success" => term::color::BRIGHT_GREEN,
         "error" => term::color::BRIGHT_RED,
         "green" => term::color::BRIGHT_GREEN,
@@ -112,38 +140,107 @@ fn say_term(mut t: Box<term::StdoutTerminal>, color: &str, to_say: &str) {
         "white" => term::color::WHITE,
         "cyan" => term::color::BRIGHT_CYAN,
         _ => term::color::WHITE
-    };
-    t.fg(color_const).unwrap();
-    t.write_all(to_say.as_bytes()).unwrap();
-    t.reset().unwrap();
-    io::stdout().flush().ok().expect("Could not flush stdout");
+    }
 }
 
-pub fn say(color: &str, to_say: &str) {
-    match term::stdout() {
+// Write the segments to their streams as a single block. The locks are
+// reentrant, so holding them here keeps other threads out while the
+// terminal writes (which lock the streams themselves) go through.
+fn write_segments(segments: &[Segment]) {
+    if json_output::enabled() {
+        for &(_, _, ref text) in segments {
+            json_output::add_text(text)
+        }
+        return
+    }
+    let show_chatter = SHOW_OUTPUT.load(Ordering::Relaxed);
+    let colorize = COLORIZE.load(Ordering::Relaxed);
+    let (stdout, stderr) = (io::stdout(), io::stderr());
+    let (mut out, mut err) = (stdout.lock(), stderr.lock());
+    let (mut out_term, mut err_term) = (None, None);
+    for &(stream, ref color, ref text) in segments {
+        match stream {
+            Stream::Data => {
+                // Colors are for people, not for what reads a pipe
+                let t = out_term.get_or_insert_with(term::stdout);
+                write_colored(t.as_mut().map(|t| &mut **t), &mut out, color, text,
+                              colorize && stdout_is_tty());
+            },
+            Stream::Chatter if show_chatter => {
+                let t = err_term.get_or_insert_with(term::stderr);
+                write_colored(t.as_mut().map(|t| &mut **t), &mut err, color, text, colorize);
+            },
+            Stream::Chatter => debug!("{}", text)
+        }
+    }
+    out.flush().ok().expect("Could not flush stdout");
+    err.flush().ok();
+}
+
+fn write_colored<T, W>(terminal: Option<&mut T>, plain: &mut W,
+                       color: &str, text: &str, colorize: bool)
+        where T: term::Terminal + ?Sized, W: Write {
+    match terminal {
         Some(t) => {
-            unsafe {
-                if SHOW_OUTPUT {
-                    if COLORIZE {
-                        say_term(t, color, to_say)
-                    } else {
-                        print!("{}", to_say)
-                    }
-                } else {
-                    debug!("{}", to_say)
-                }
+            if colorize {
+                t.fg(color_for(color)).unwrap();
+                t.write_all(text.as_bytes()).unwrap();
+                t.reset().unwrap();
+            } else {
+                t.write_all(text.as_bytes()).unwrap();
             }
         },
         None => {
-            print!("{}", to_say);
-            io::stdout().flush().ok().expect("Could not flush stdout");
+            plain.write_all(text.as_bytes()).ok();
         }
     }
 }
 
+#[cfg(unix)]
+fn stdout_is_tty() -> bool {
+    unsafe { libc::isatty(libc::STDOUT_FILENO) == 1 }
+}
+
+#[cfg(not(unix))]
+fn stdout_is_tty() -> bool {
+    true
+}
+
+// Send the segments to the current capture of this thread, or to the
+// terminal if nothing is capturing.
+fn emit(segments: Vec<Segment>) {
+    let rest = CAPTURE.with(|c| {
+        match *c.borrow_mut() {
+            Some(ref mut buffer) => {
+                buffer.extend(segments);
+                None
+            },
+            None => Some(segments)
+        }
+    });
+    if let Some(segments) = rest {
+        write_segments(&segments);
+    }
+}
+
+/// Tell the user about progress or status, on stderr
+pub fn say(color: &str, to_say: &str) {
+    emit(vec![(Stream::Chatter, color.to_string(), to_say.to_string())]);
+}
+
 pub fn sayln(color: &str, to_say: &str) {
-    say(color, to_say);
-    say(color, "\n");
+    emit(vec![(Stream::Chatter, color.to_string(), to_say.to_string()),
+              (Stream::Chatter, color.to_string(), "\n".to_string())]);
+}
+
+/// Print what the command produces, on stdout
+pub fn out(color: &str, data: &str) {
+    emit(vec![(Stream::Data, color.to_string(), data.to_string())]);
+}
+
+pub fn outln(color: &str, data: &str) {
+    emit(vec![(Stream::Data, color.to_string(), data.to_string()),
+              (Stream::Data, color.to_string(), "\n".to_string())]);
 }
 
 pub fn print_error(primary_error_str: &str, secondary_error_str: &str) -> () {
@@ -151,3 +248,140 @@ pub fn print_error(primary_error_str: &str, secondary_error_str: &str) -> () {
     sayln("error", &final_error_str_primary);
     sayln("white", &secondary_error_str);
 }
+
+/// Output captured by `capture`, waiting to be flushed
+#[derive(Debug, Default)]
+pub struct Buffer {
+    segments: Vec<Segment>,
+}
+
+impl Buffer {
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// The captured text, without colors
+    pub fn text(&self) -> String {
+        self.segments.iter().map(|&(_, _, ref text)| text.as_str()).collect()
+    }
+
+    /// The captured text that went to stdout
+    pub fn data(&self) -> String {
+        self.segments.iter()
+            .filter(|&&(stream, _, _)| stream == Stream::Data)
+            .map(|&(_, _, ref text)| text.as_str())
+            .collect()
+    }
+
+    /// Print the whole buffer at once
+    pub fn flush(self) {
+        if !self.segments.is_empty() {
+            emit(self.segments);
+        }
+    }
+}
+
+/// Run `f` collecting everything it says on this thread into a `Buffer`
+/// instead of printing it.
+pub fn capture<F, T>(f: F) -> (T, Buffer) where F: FnOnce() -> T {
+    let outer = CAPTURE.with(|c| c.borrow_mut().replace(Vec::new()));
+    let result = f();
+    let segments = CAPTURE.with(|c| {
+        let mut c = c.borrow_mut();
+        let segments = c.take().unwrap_or_default();
+        *c = outer;
+        segments
+    });
+    (result, Buffer { segments: segments })
+}
+
+/// Flushes the buffers of a set of tasks in the order the tasks were
+/// started, no matter the order in which they finish. Clones share the
+/// same state so each task can get its own handle.
+#[derive(Clone, Default)]
+pub struct OrderedOutput {
+    state: Arc<Mutex<(usize, BTreeMap<usize, Buffer>)>>,
+}
+
+impl OrderedOutput {
+    pub fn new() -> OrderedOutput {
+        OrderedOutput::default()
+    }
+
+    /// Hand over the buffer of task number `index` (starting at 0). It
+    /// is printed as soon as the buffers of all previous tasks are.
+    pub fn submit(&self, index: usize, buffer: Buffer) {
+        let mut state = self.state.lock().unwrap();
+        state.1.insert(index, buffer);
+        loop {
+            let next = state.0;
+            match state.1.remove(&next) {
+                Some(b) => {
+                    b.flush();
+                    state.0 += 1;
+                },
+                None => break
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_capture() {
+        let (result, buffer) = capture(|| {
+            say("white", "one ");
+            sayln("red", "two");
+            42
+        });
+        assert_eq!(42, result);
+        assert_eq!("one two\n", buffer.text());
+    }
+
+    #[test]
+    fn test_capture_keeps_data_apart() {
+        let (_, buffer) = capture(|| {
+            say("white", "Review for change ");
+            outln("magenta", "https://delivery/e/ent/#/organizations/o/projects/p/changes/1");
+            sayln("white", "Done");
+        });
+        assert_eq!("https://delivery/e/ent/#/organizations/o/projects/p/changes/1\n",
+                   buffer.data());
+        assert!(buffer.text().starts_with("Review for change https://"));
+    }
+
+    #[test]
+    fn test_nested_capture_flushes_into_outer() {
+        let (_, outer) = capture(|| {
+            say("white", "a");
+            let (_, inner) = capture(|| say("white", "b"));
+            say("white", "c");
+            inner.flush();
+        });
+        assert_eq!("acb", outer.text());
+    }
+
+    #[test]
+    fn test_ordered_output() {
+        let (_, out) = capture(|| {
+            let bus = OrderedOutput::new();
+            let handles: Vec<_> = (0..4).rev().map(|i| {
+                let bus = bus.clone();
+                thread::spawn(move || {
+                    let (_, b) = capture(|| sayln("white", &format!("task {}", i)));
+                    (i, b, bus)
+                })
+            }).collect();
+            // Tasks finish in reverse, they still come out in order
+            for h in handles {
+                let (i, b, bus) = h.join().unwrap();
+                bus.submit(i, b);
+            }
+        });
+        assert_eq!("task 0\ntask 1\ntask 2\ntask 3\n", out.text());
+    }
+}
@@ -0,0 +1,106 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+// Strict mode
+//
+// With the global `--strict` flag, or `strict = true` in the cli.toml,
+// the warnings that are advisory by default fail the command instead.
+// Each warning exits with a code of its own so automation can tell which
+// policy a run broke.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use errors::{DeliveryError, Kind};
+use types::{DeliveryResult, ExitCode};
+use utils::say::sayln;
+
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Warning {
+    ShallowClone,
+    UnpinnedBuildDeps,
+    ConfigDrift,
+    UnbumpedVersion,
+    MissingUpstreamRemote,
+    DeliveryRemoteMismatch,
+    LineEndings,
+}
+
+impl Warning {
+    /// The exit code of a command that failed on this warning
+    pub fn exit_code(&self) -> ExitCode {
+        match *self {
+            Warning::ShallowClone => 20,
+            Warning::UnpinnedBuildDeps => 21,
+            Warning::ConfigDrift => 22,
+            Warning::UnbumpedVersion => 23,
+            Warning::MissingUpstreamRemote => 24,
+            Warning::DeliveryRemoteMismatch => 25,
+            Warning::LineEndings => 26,
+        }
+    }
+}
+
+pub fn turn_on() {
+    STRICT.store(true, Ordering::Relaxed);
+}
+
+/// Whether `--strict` was given or set in the config
+pub fn enabled() -> bool {
+    STRICT.load(Ordering::Relaxed)
+}
+
+/// Print `msg` as a warning, or fail with it in strict mode
+pub fn warn(warning: Warning, msg: &str) -> DeliveryResult<()> {
+    try!(check(warning, msg));
+    sayln("yellow", &format!("WARN: {}", msg));
+    Ok(())
+}
+
+/// Fail with `msg` in strict mode, for warnings that were already
+/// printed in a form of their own
+pub fn check(warning: Warning, msg: &str) -> DeliveryResult<()> {
+    if enabled() {
+        return Err(DeliveryError::throw(Kind::StrictWarning(warning), Some(msg.to_string())))
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let warnings = [Warning::ShallowClone, Warning::UnpinnedBuildDeps,
+                        Warning::ConfigDrift, Warning::UnbumpedVersion,
+                        Warning::MissingUpstreamRemote, Warning::DeliveryRemoteMismatch,
+                        Warning::LineEndings];
+        let mut codes: Vec<ExitCode> = warnings.iter().map(|w| w.exit_code()).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(warnings.len(), codes.len());
+        assert!(!codes.contains(&1));
+    }
+
+    #[test]
+    fn test_strict_warning_exit_code() {
+        let e = DeliveryError::throw(Kind::StrictWarning(Warning::ConfigDrift), None);
+        assert_eq!(22, e.exit_code());
+        assert_eq!(1, DeliveryError::throw(Kind::MissingConfig, None).exit_code());
+    }
+}
@@ -0,0 +1,173 @@
+//
+// Copyright:: Copyright (c) 2017 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+// Output templates
+//
+// `--format template='{{.change.id}} {{.change.stage}}'` prints one line
+// per item through a template instead of the usual output, so scripts
+// can pick the fields they need without going through JSON and jq.
+// Fields are paths into the JSON form of the item, `{{.}}` is the whole
+// item; missing fields render empty. `\n` and `\t` in the template are
+// turned into newlines and tabs, since shells rarely do it for us.
+
+use std::io::{self, Write};
+use serde::Serialize;
+use serde_json::{self, Value};
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+
+const TEMPLATE_PREFIX: &'static str = "template=";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Field(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> DeliveryResult<Template> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(Part::Text(unescape(&rest[..start])));
+            }
+            let end = match rest[start..].find("}}") {
+                Some(end) => start + end,
+                None => return Err(invalid(template, "a '{{' is never closed"))
+            };
+            let field = rest[start + 2..end].trim();
+            if !field.starts_with('.') {
+                let msg = format!("fields start with a '.', i.e. {{{{.{}}}}}", field);
+                return Err(invalid(template, &msg))
+            }
+            parts.push(Part::Field(field.split('.')
+                                   .filter(|s| !s.is_empty())
+                                   .map(|s| s.to_string())
+                                   .collect()));
+            rest = &rest[end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(unescape(rest)));
+        }
+        Ok(Template { parts: parts })
+    }
+
+    pub fn render(&self, item: &Value) -> String {
+        let mut out = String::new();
+        for part in self.parts.iter() {
+            match *part {
+                Part::Text(ref text) => out.push_str(text),
+                Part::Field(ref path) => out.push_str(&field_to_string(lookup(item, path))),
+            }
+        }
+        out
+    }
+
+    /// Print one line per item
+    pub fn print_each<T: Serialize>(&self, items: &[T]) -> DeliveryResult<()> {
+        let stdout = io::stdout();
+        let mut lock = stdout.lock();
+        for item in items.iter() {
+            let value = try!(serde_json::to_value(item));
+            try!(writeln!(lock, "{}", self.render(&value)));
+        }
+        Ok(())
+    }
+}
+
+/// The template of a `--format` option; no template without one
+pub fn from_format_option(format: &str) -> DeliveryResult<Option<Template>> {
+    if format.is_empty() {
+        return Ok(None)
+    }
+    if !format.starts_with(TEMPLATE_PREFIX) {
+        let msg = format!("Unknown format '{}', use --format template='{{{{.field}}}}'", format);
+        return Err(DeliveryError::throw(Kind::OptionConstraint, Some(msg)))
+    }
+    Template::parse(&format[TEMPLATE_PREFIX.len()..]).map(Some)
+}
+
+fn invalid(template: &str, why: &str) -> DeliveryError {
+    DeliveryError::throw(Kind::OptionConstraint,
+                         Some(format!("Invalid template '{}': {}", template, why)))
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("\\n", "\n").replace("\\t", "\t")
+}
+
+fn lookup<'v>(item: &'v Value, path: &[String]) -> Option<&'v Value> {
+    let mut current = item;
+    for key in path.iter() {
+        let next = match *current {
+            Value::Object(ref map) => map.get(key),
+            Value::Array(ref items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None
+        };
+        match next {
+            Some(v) => current = v,
+            None => return None
+        }
+    }
+    Some(current)
+}
+
+fn field_to_string(value: Option<&Value>) -> String {
+    match value {
+        None | Some(&Value::Null) => String::new(),
+        Some(&Value::String(ref s)) => s.clone(),
+        Some(v @ &Value::Array(_)) | Some(v @ &Value::Object(_)) => {
+            serde_json::to_string(v).unwrap_or_default()
+        },
+        Some(v) => v.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let item = json!({
+            "change": {"id": "8f1a", "stage": "verify", "approved": true},
+            "depends_on": ["frijol", "tortilla"],
+            "position": 2
+        });
+        let t = Template::parse("{{.position}}. {{ .change.id }} {{.change.stage}}").unwrap();
+        assert_eq!("2. 8f1a verify", t.render(&item));
+        let t = Template::parse("{{.depends_on.1}}\\t{{.change.approved}}{{.nope.nada}}").unwrap();
+        assert_eq!("tortilla\ttrue", t.render(&item));
+        let t = Template::parse("{{.depends_on}}").unwrap();
+        assert_eq!("[\"frijol\",\"tortilla\"]", t.render(&item));
+    }
+
+    #[test]
+    fn test_from_format_option() {
+        assert_eq!(None, from_format_option("").unwrap());
+        assert!(from_format_option("template={{.id}}").unwrap().is_some());
+        assert!(from_format_option("yaml").is_err());
+        assert!(from_format_option("template={{.id").is_err());
+        assert!(from_format_option("template={{id}}").is_err());
+    }
+}
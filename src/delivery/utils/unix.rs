@@ -23,6 +23,10 @@ use utils::path_to_string;
 use std::path::{Path, PathBuf};
 use std::convert::AsRef;
 use std::error;
+use std::ffi::CString;
+use std::mem;
+use std::fs::OpenOptions;
+use std::os::unix::fs::OpenOptionsExt;
 
 pub fn copy_recursive<A, B>(f: &A, t: &B) -> Result<(), DeliveryError>
         where A: AsRef<Path> + ?Sized,
@@ -58,12 +62,19 @@ pub fn chmod<P: ?Sized>(path: &P, setting: &str) -> Result<(), DeliveryError>
     super::cmd_success_or_err(&result, Kind::ChmodFailed)
 }
 
+/// Create `path` readable and writable by its owner only, so no one
+/// else gets to read it between its creation and a later chmod
+pub fn create_private_file(path: &Path) -> Result<(), DeliveryError> {
+    try!(OpenOptions::new().write(true).create(true).mode(0o600).open(path));
+    Ok(())
+}
+
 pub fn chown_all<P: AsRef<Path>>(who: &str,
                                  paths: &[P]) -> Result<(), DeliveryError> {
     let mut command = Command::new("chown");
     command.arg("-R").arg(who);
     for p in paths {
-        command.arg(&path_to_string(p));
+        command.arg(&try!(path_to_string(p)));
     }
     let output = match command.output() {
         Ok(o) => o,
@@ -83,6 +94,26 @@ pub fn privileged_process() -> bool {
     }
 }
 
+// Whether the process runs with root privileges, the effective user is
+// what matters for binding low ports and the like.
+pub fn elevated_process() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+// Bytes available to unprivileged users on the filesystem of `path`,
+// `None` when it cannot be told.
+pub fn free_disk_space(path: &Path) -> Option<u64> {
+    let c_path = match path_to_string(path).ok().and_then(|p| CString::new(p).ok()) {
+        Some(p) => p,
+        None => return None
+    };
+    let mut stat: libc::statvfs = unsafe { mem::zeroed() };
+    match unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } {
+        0 => Some(stat.f_bavail as u64 * stat.f_frsize as u64),
+        _ => None
+    }
+}
+
 // Abstraction for command creation. Needed because of how we're
 // wrapping commands in Windows. See this function in the
 // corresponding windows module.
@@ -114,6 +145,20 @@ pub fn find_command(command: &str) -> Option<PathBuf> {
     None
 }
 
+/// Settings every git command runs with, none needed here
+pub const GIT_SETTINGS: &'static [&'static str] = &[];
+
+/// Paths need no rewriting for git on unix
+pub fn plain_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Editors on unix write unix line endings, there is nothing to advise
+#[allow(unused_variables)]
+pub fn autocrlf_advice(value: &str, has_gitattributes: bool) -> Option<String> {
+    None
+}
+
 pub fn ca_path() -> String {
     String::from("/opt/chefdk/embedded/ssl/certs/cacert.pem")
 }
@@ -124,10 +124,70 @@ fn find_command_with_pathext(candidate: &PathBuf) -> Option<PathBuf> {
     None
 }
 
+/// Settings every git command runs with. Checkouts of projects with
+/// deep trees, think `node_modules`, go over `MAX_PATH` without them.
+pub const GIT_SETTINGS: &'static [&'static str] = &["core.longpaths=true"];
+
+/// `path` the way git for Windows takes it: `canonicalize` gives
+/// verbatim paths, `\\?\C:\src` or `\\?\UNC\server\share`, which git
+/// cannot use as a work tree, and paths built from config files may mix
+/// in forward slashes.
+pub fn plain_path(path: &Path) -> PathBuf {
+    let s = path.to_string_lossy().replace('/', "\\");
+    let plain = if s.starts_with("\\\\?\\UNC\\") {
+        format!("\\\\{}", &s[8..])
+    } else if s.starts_with("\\\\?\\") {
+        s[4..].to_string()
+    } else {
+        s
+    };
+    PathBuf::from(plain)
+}
+
+/// What to tell the user when `core.autocrlf` is set to `value` in a
+/// repository without a `.gitattributes`. Git for Windows then commits
+/// the CRLF line endings of the editor, and the build cookbook scripts
+/// that reach the Linux builders fail with `bad interpreter`.
+pub fn autocrlf_advice(value: &str, has_gitattributes: bool) -> Option<String> {
+    match value.trim() {
+        "true" | "input" => None,
+        _ if has_gitattributes => None,
+        value => Some(format!("core.autocrlf is '{}', files are committed with the \
+                               Windows line endings they have in the work tree, which \
+                               breaks scripts run on the Linux builders.\n  Run \
+                               `git config core.autocrlf true`, or commit a \
+                               .gitattributes with `* text=auto`.",
+                              if value.is_empty() { "unset" } else { value }))
+    }
+}
+
 pub fn ca_path() -> String {
     String::from("C:\\opscode\\chefdk\\embedded\\ssl\\certs\\cacert.pem")
 }
 
+// Whether the process runs elevated, i.e. as a member of the
+// Administrators group with UAC elevation granted.
+pub fn elevated_process() -> bool {
+    let check = "([Security.Principal.WindowsPrincipal]\
+                 [Security.Principal.WindowsIdentity]::GetCurrent()).IsInRole(\
+                 [Security.Principal.WindowsBuiltInRole]::Administrator)";
+    match make_command(check).output() {
+        Ok(out) => String::from_utf8_lossy(&out.stdout).trim() == "True",
+        Err(_) => false
+    }
+}
+
+// Bytes available to the user on the drive of `path`, `None` when it
+// cannot be told.
+pub fn free_disk_space(path: &Path) -> Option<u64> {
+    let check = format!("(New-Object System.IO.DriveInfo('{}')).AvailableFreeSpace",
+                        path.display());
+    match make_command(&check).output() {
+        Ok(out) => String::from_utf8_lossy(&out.stdout).trim().parse().ok(),
+        Err(_) => None
+    }
+}
+
 // ---------------
 // dummy functions
 // ---------------
@@ -143,6 +203,11 @@ pub fn chmod<P: ?Sized>(path: &P, setting: &str) -> Result<(), DeliveryError>
     Ok(())
 }
 
+pub fn create_private_file(path: &Path) -> Result<(), DeliveryError> {
+    try!(fs::OpenOptions::new().write(true).create(true).open(path));
+    Ok(())
+}
+
 #[allow(unused_variables)]
 pub fn chown_all<P: AsRef<Path>>(who: &str,
                             paths: &[P]) ->  Result<(), DeliveryError>
@@ -156,3 +221,38 @@ pub fn privileged_process() -> bool {
 // -------------------
 // end dummy functions
 // -------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+    use utils::walk_tree_for_path;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_plain_path() {
+        assert_eq!(PathBuf::from("C:\\src\\app"), plain_path(Path::new("\\\\?\\C:\\src\\app")));
+        assert_eq!(PathBuf::from("\\\\server\\share\\app"),
+                   plain_path(Path::new("\\\\?\\UNC\\server\\share\\app")));
+        assert_eq!(PathBuf::from("C:\\src\\app\\.git"), plain_path(Path::new("C:/src/app/.git")));
+    }
+
+    #[test]
+    fn test_autocrlf_advice() {
+        assert!(autocrlf_advice("true\n", false).is_none());
+        assert!(autocrlf_advice("input", false).is_none());
+        assert!(autocrlf_advice("false", true).is_none());
+        assert!(autocrlf_advice("", false).unwrap().contains("core.autocrlf is 'unset'"));
+    }
+
+    #[test]
+    fn test_walk_tree_for_path_from_verbatim_path() {
+        let tmp = TempDir::new("walk-tree").unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::File::create(root.join(".git").join("config")).unwrap();
+        fs::create_dir_all(root.join("src").join("lib")).unwrap();
+        let found = walk_tree_for_path(root.join("src").join("lib"), ".git/config").unwrap();
+        assert_eq!(plain_path(&root).join(".git").join("config"), found);
+    }
+}
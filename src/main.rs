@@ -18,7 +18,6 @@
 extern crate regex;
 extern crate clap;
 extern crate log;
-extern crate env_logger;
 extern crate term;
 extern crate hyper;
 extern crate delivery;
@@ -27,6 +26,5 @@ extern crate time;
 use delivery::cli;
 
 fn main() {
-    env_logger::init().unwrap();
     cli::run();
 }
@@ -298,3 +298,19 @@ test!(job_verify_dna_json_with_extra_attributes {
         None => panic!("No delivery/config/unlimited, {}", dna_data)
     };
 });
+
+test!(fixture_repo_create {
+    let workspace = TempDir::new("fixture-workspace").unwrap();
+    let mut command = delivery_cmd();
+    command.arg("fixture").arg("repo").arg("create").arg("scratch")
+           .arg("--branch").arg("main");
+    assert_command_successful(&mut command, &workspace.path());
+    let clone = workspace.path().join("scratch");
+    assert!(workspace.path().join("scratch.git").is_dir());
+    let status = panic_on_error!(git_command(&["status", "-sb"], &clone));
+    assert!(status.stdout.starts_with("## main...origin/main"));
+
+    let mut again = delivery_cmd();
+    again.arg("fixture").arg("repo").arg("create").arg("scratch");
+    assert_command_failed(&mut again, &workspace.path());
+});